@@ -0,0 +1,102 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use psd::Psd;
+
+/// The width and height of the PSD that we generate for benchmarking RLE decoding
+const SIZE: u32 = 1024;
+
+/// Decoding the RLE compressed composite image of a large PSD
+fn rle_composite(c: &mut Criterion) {
+    let bytes = rle_compressed_psd(SIZE, SIZE);
+
+    c.bench_function("parse 1024x1024 rle psd", |b| {
+        b.iter(|| Psd::from_bytes(black_box(&bytes)).unwrap())
+    });
+
+    let psd = Psd::from_bytes(&bytes).unwrap();
+    c.bench_function("rgba 1024x1024 rle composite", |b| {
+        b.iter(|| black_box(&psd).rgba())
+    });
+}
+
+/// Decoding and flattening the RLE compressed layers of one of our fixtures
+fn rle_layers(c: &mut Criterion) {
+    let psd = include_bytes!("../tests/fixtures/rle-3-layer-8x8.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    c.bench_function("rgba rle layers", |b| {
+        b.iter(|| {
+            for layer in black_box(&psd).layers() {
+                black_box(layer.rgba());
+            }
+        })
+    });
+
+    c.bench_function("flatten rle layers", |b| {
+        b.iter(|| black_box(&psd).flatten_layers_rgba(&|_| true).unwrap())
+    });
+}
+
+/// Create an RGB PSD without any layers whose composite image is RLE compressed.
+///
+/// Every row alternates between runs of a repeated byte and runs of literal bytes so that both
+/// kinds of PackBits packets are exercised.
+fn rle_compressed_psd(width: u32, height: u32) -> Vec<u8> {
+    let channel_count = 3;
+
+    let mut rows = vec![];
+    for row in 0..height {
+        let mut packed = vec![];
+
+        for (packet, start) in (0..width).step_by(128).enumerate() {
+            let len = (width - start).min(128) as usize;
+
+            if packet % 2 == 0 {
+                packed.push((1 - len as i16) as u8);
+                packed.push(row as u8);
+            } else {
+                packed.push(len as u8 - 1);
+                packed.extend((0..len).map(|idx| (idx as u32 + row) as u8));
+            }
+        }
+
+        rows.push(packed);
+    }
+
+    let mut psd = vec![];
+
+    // File header section
+    psd.extend_from_slice(b"8BPS");
+    psd.extend_from_slice(&1u16.to_be_bytes());
+    psd.extend_from_slice(&[0; 6]);
+    psd.extend_from_slice(&(channel_count as u16).to_be_bytes());
+    psd.extend_from_slice(&height.to_be_bytes());
+    psd.extend_from_slice(&width.to_be_bytes());
+    psd.extend_from_slice(&8u16.to_be_bytes());
+    psd.extend_from_slice(&3u16.to_be_bytes());
+
+    // Color mode data section and image resources section
+    psd.extend_from_slice(&0u32.to_be_bytes());
+    psd.extend_from_slice(&0u32.to_be_bytes());
+
+    // Layer and mask information section with empty layer info and global layer mask info
+    psd.extend_from_slice(&8u32.to_be_bytes());
+    psd.extend_from_slice(&[0; 8]);
+
+    // Image data section
+    psd.extend_from_slice(&1u16.to_be_bytes());
+    for _ in 0..channel_count {
+        for row in rows.iter() {
+            psd.extend_from_slice(&(row.len() as u16).to_be_bytes());
+        }
+    }
+    for _ in 0..channel_count {
+        for row in rows.iter() {
+            psd.extend_from_slice(row);
+        }
+    }
+
+    psd
+}
+
+criterion_group!(benches, rle_composite, rle_layers);
+criterion_main!(benches);
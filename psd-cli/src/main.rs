@@ -0,0 +1,100 @@
+//! A command line tool for inspecting PSD files and exporting their layers, built on the public
+//! API of the psd crate.
+
+use std::error::Error;
+use std::process;
+
+use psd::Psd;
+
+const USAGE: &str = "Usage:
+    psd info <file.psd>
+    psd layers <file.psd>
+    psd export <file.psd> --layer <name> <out.png>
+    psd flatten <file.psd> <out.png>";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Err(err) = run(&args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+    match args.as_slice() {
+        ["info", file] => info(&read_psd(file)?),
+        ["layers", file] => layers(&read_psd(file)?),
+        ["export", file, "--layer", name, out] | ["export", file, out, "--layer", name] => {
+            export(&read_psd(file)?, name, out)
+        }
+        ["flatten", file, out] => flatten(&read_psd(file)?, out),
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn read_psd(file: &str) -> Result<Psd, Box<dyn Error>> {
+    let bytes = std::fs::read(file).map_err(|err| format!("Could not read {}: {}", file, err))?;
+
+    Ok(Psd::from_vec(bytes)?)
+}
+
+/// Print general information about the PSD
+fn info(psd: &Psd) -> Result<(), Box<dyn Error>> {
+    println!("Width: {}", psd.width());
+    println!("Height: {}", psd.height());
+    println!("Depth: {:?}", psd.depth());
+    println!("Color mode: {:?}", psd.color_mode());
+    println!("Layers: {}", psd.layers().len());
+    println!("Groups: {}", psd.groups().len());
+
+    Ok(())
+}
+
+/// Print one line per layer, from the top of the layers panel to the bottom
+fn layers(psd: &Psd) -> Result<(), Box<dyn Error>> {
+    for layer in psd.layers() {
+        let group = match layer.parent_id().and_then(|id| psd.groups().get(&id)) {
+            Some(group) => group.name(),
+            None => "",
+        };
+
+        println!(
+            "{}\t{}x{}\t({}, {})\t{}\t{}\t{}",
+            layer.name(),
+            layer.width(),
+            layer.height(),
+            layer.layer_left(),
+            layer.layer_top(),
+            if layer.visible() { "visible" } else { "hidden" },
+            layer.opacity(),
+            group,
+        );
+    }
+
+    Ok(())
+}
+
+/// Export a single layer as a PNG
+fn export(psd: &Psd, name: &str, out: &str) -> Result<(), Box<dyn Error>> {
+    let layer = psd
+        .layer_by_name(name)
+        .ok_or_else(|| format!("No layer named {}", name))?;
+
+    std::fs::write(out, psd::export::layer_to_png(layer)?)?;
+
+    Ok(())
+}
+
+/// Flatten the visible layers into a PNG
+fn flatten(psd: &Psd, out: &str) -> Result<(), Box<dyn Error>> {
+    std::fs::write(out, psd::export::flattened_to_png(psd)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// cargo test -p psd-cli --test cli info -- --exact
+#[test]
+fn info() {
+    let output = psd(&["info", &fixture("green-1x1.psd")]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Width: 1"));
+    assert!(stdout.contains("Layers: 1"));
+}
+
+/// cargo test -p psd-cli --test cli layers -- --exact
+#[test]
+fn layers() {
+    let output = psd(&["layers", &fixture("two-layers-red-green-1x1.psd")]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let names: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split('\t').next().unwrap())
+        .collect();
+    assert_eq!(names, ["Red", "Green"]);
+}
+
+/// cargo test -p psd-cli --test cli export_and_flatten -- --exact
+#[test]
+fn export_and_flatten() {
+    let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+
+    let layer_png = out_dir.join("psd-cli-green-layer.png");
+    let output = psd(&[
+        "export",
+        &fixture("two-layers-red-green-1x1.psd"),
+        "--layer",
+        "Green",
+        layer_png.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&layer_png).unwrap()[..8], PNG_SIGNATURE);
+
+    let flattened_png = out_dir.join("psd-cli-flattened.png");
+    let output = psd(&[
+        "flatten",
+        &fixture("two-layers-red-green-1x1.psd"),
+        flattened_png.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&flattened_png).unwrap()[..8], PNG_SIGNATURE);
+}
+
+/// cargo test -p psd-cli --test cli unknown_layer -- --exact
+#[test]
+fn unknown_layer() {
+    let output = psd(&[
+        "export",
+        &fixture("green-1x1.psd"),
+        "--layer",
+        "Missing",
+        "out.png",
+    ]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("No layer named Missing"));
+}
+
+fn psd(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_psd"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn fixture(name: &str) -> String {
+    format!("{}/../tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
@@ -0,0 +1,179 @@
+//! Read PSDs from asynchronous readers, such as a PSD that is being downloaded from object
+//! storage.
+//!
+//! Like [`Psd::from_vec`], the PSD is held in memory once it has been read, so this doesn't
+//! use less memory than reading the whole file into a `Vec` first. It only saves blocking a
+//! thread while the PSD is read.
+//!
+//! ```ignore
+//! let file = async_std::fs::File::open("my-psd-file.psd").await?;
+//! let psd = Psd::from_async_reader(file).await?;
+//! ```
+
+use core::future::poll_fn;
+use core::pin::Pin;
+use std::io::{self, ErrorKind};
+
+use futures_io::{AsyncRead, AsyncSeek, SeekFrom};
+
+use crate::prelude::*;
+use crate::sections::file_header_section::FileHeaderSection;
+use crate::sections::{MajorSections, SectionLayout, FILE_HEADER_SECTION_LEN};
+use crate::shared_bytes::SharedBytes;
+use crate::{Psd, PsdError, PsdOptions};
+
+/// Sections are read this many bytes at a time, so that a PSD that claims to have a huge
+/// section only allocates as much memory as the reader actually has bytes.
+const CHUNK_LEN: usize = 64 * 1024;
+
+impl Psd {
+    /// Create a Psd by reading it from an asynchronous reader.
+    ///
+    /// The major sections of the PSD are read into memory one after the other, and the color
+    /// mode data section, which isn't used, is skipped over instead of being read. The image
+    /// data section is read in full along with the others, so the memory that is used is about
+    /// the size of the PSD.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let file = async_std::fs::File::open("my-psd-file.psd").await?;
+    ///
+    /// let psd = Psd::from_async_reader(file).await?;
+    /// ```
+    pub async fn from_async_reader<R>(reader: R) -> Result<Psd, PsdError>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        Psd::from_async_reader_with_options(reader, &PsdOptions::default()).await
+    }
+
+    /// Create a Psd by reading it from an asynchronous reader, rejecting PSDs that are larger
+    /// than the limits in the given options.
+    ///
+    /// The size of the canvas is checked as soon as the file header has been read, so nothing
+    /// else is read from PSDs that are too large.
+    pub async fn from_async_reader_with_options<R>(
+        mut reader: R,
+        options: &PsdOptions,
+    ) -> Result<Psd, PsdError>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let mut file_header = vec![0; FILE_HEADER_SECTION_LEN];
+        read_exact(&mut reader, &mut file_header).await?;
+
+        let header = FileHeaderSection::from_bytes(&file_header).map_err(PsdError::HeaderError)?;
+        options
+            .check_canvas(header.width.0, header.height.0)
+            .map_err(PsdError::LimitError)?;
+
+        let color_mode_data_len = read_u32(&mut reader).await?;
+        seek(&mut reader, SeekFrom::Current(color_mode_data_len as i64)).await?;
+
+        let image_resources = read_section(&mut reader).await?;
+        let layer_and_mask = read_section(&mut reader).await?;
+        let image_data = read_to_end(&mut reader).await?;
+
+        let layout = SectionLayout::from_section_lengths([
+            FILE_HEADER_SECTION_LEN,
+            4 + color_mode_data_len as usize,
+            image_resources.len(),
+            layer_and_mask.len(),
+            image_data.len(),
+        ]);
+
+        let major_sections = MajorSections {
+            file_header: &file_header,
+            // An empty color mode data section, since we skipped over it
+            color_mode_data: &[0; 4],
+            image_resources: &image_resources,
+            layer_and_mask: SharedBytes::new(layer_and_mask),
+            image_data: SharedBytes::new(image_data),
+            layout,
+        };
+
+        Psd::from_major_sections(&major_sections, options)
+    }
+}
+
+/// Read a length delimited section, including its length.
+async fn read_section<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, PsdError> {
+    let len = read_u32(reader).await?;
+
+    let mut section = len.to_be_bytes().to_vec();
+    let mut remaining = len as usize;
+    while remaining > 0 {
+        let start = section.len();
+        let chunk_len = remaining.min(CHUNK_LEN);
+
+        section.resize(start + chunk_len, 0);
+        read_exact(reader, &mut section[start..]).await?;
+
+        remaining -= chunk_len;
+    }
+
+    Ok(section)
+}
+
+async fn read_to_end<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, PsdError> {
+    let mut bytes = vec![];
+
+    loop {
+        let start = bytes.len();
+        bytes.resize(start + CHUNK_LEN, 0);
+
+        let read = read(reader, &mut bytes[start..]).await?;
+        bytes.truncate(start + read);
+
+        if read == 0 {
+            return Ok(bytes);
+        }
+    }
+}
+
+async fn read_u32<R: AsyncRead + Unpin>(reader: &mut R) -> Result<u32, PsdError> {
+    let mut bytes = [0; 4];
+    read_exact(reader, &mut bytes).await?;
+
+    Ok(u32::from_be_bytes(bytes))
+}
+
+async fn read_exact<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    mut buf: &mut [u8],
+) -> Result<(), PsdError> {
+    while !buf.is_empty() {
+        let read = read(reader, buf).await?;
+        if read == 0 {
+            return Err(read_error(&io::Error::from(ErrorKind::UnexpectedEof)));
+        }
+
+        buf = &mut buf[read..];
+    }
+
+    Ok(())
+}
+
+async fn read<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Result<usize, PsdError> {
+    loop {
+        match poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, buf)).await {
+            Ok(read) => return Ok(read),
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(read_error(&err)),
+        }
+    }
+}
+
+async fn seek<R: AsyncSeek + Unpin>(reader: &mut R, pos: SeekFrom) -> Result<u64, PsdError> {
+    poll_fn(|cx| Pin::new(&mut *reader).poll_seek(cx, pos))
+        .await
+        .map_err(|err| read_error(&err))
+}
+
+fn read_error(err: &io::Error) -> PsdError {
+    PsdError::ReadError {
+        kind: err.kind(),
+        message: err.to_string(),
+    }
+}
@@ -0,0 +1,188 @@
+//! Convert many PSDs one after another while reusing the buffers that they're flattened into,
+//! such as on a server that converts thousands of PSDs an hour and would otherwise allocate and
+//! free a canvas sized buffer for every layer of every PSD.
+//!
+//! ```ignore
+//! let mut batch = PsdBatch::new();
+//!
+//! for upload in uploads {
+//!     let image = batch.flatten(upload)?;
+//!     encode_png(image.rgba(), image.width(), image.height())?;
+//! }
+//! ```
+//!
+//! The PSDs are parsed like [`Psd::from_vec`], so their channels point into the bytes that were
+//! uploaded instead of being copied out of them.
+//!
+//! A [`PsdBatch`] is used by one thread at a time and doesn't start any threads. With the
+//! `parallel` feature, `flatten_all` spreads PSDs across one thread per core, each with its own
+//! `PsdBatch`.
+
+use crate::blend::blend_rgba;
+use crate::prelude::*;
+use crate::{LayerRgbaCache, Psd, PsdError, PsdLayer, PsdOptions};
+
+/// Parses and flattens PSDs into buffers that are kept between PSDs.
+///
+/// The buffers grow to fit the largest PSD that was converted, see
+/// [`PsdBatch::release_buffers`].
+#[derive(Debug, Default)]
+pub struct PsdBatch {
+    options: PsdOptions,
+    rgba: Vec<u8>,
+    layer_rgba: LayerRgbaCache,
+}
+
+/// The pixels of a PSD that a [`PsdBatch`] converted, which are overwritten by the next PSD
+#[derive(Debug)]
+pub struct BatchImage<'a> {
+    width: u32,
+    height: u32,
+    rgba: &'a [u8],
+}
+
+impl PsdBatch {
+    /// Create a batch that parses PSDs with the default [`PsdOptions`]
+    pub fn new() -> PsdBatch {
+        PsdBatch::default()
+    }
+
+    /// Create a batch that parses PSDs with the options
+    pub fn with_options(options: PsdOptions) -> PsdBatch {
+        PsdBatch {
+            options,
+            ..PsdBatch::default()
+        }
+    }
+
+    /// Parse the PSD and flatten its visible layers, like [`Psd::flatten_layers_rgba`]. The
+    /// bytes are parsed like [`Psd::from_vec`] and freed once the PSD is flattened.
+    pub fn flatten(&mut self, bytes: Vec<u8>) -> Result<BatchImage<'_>, PsdError> {
+        let psd = Psd::from_vec_with_options(bytes, &self.options)?;
+
+        Ok(self.flatten_psd(&psd, &|_| true))
+    }
+
+    /// Flatten the layers of a PSD that was already parsed that pass the filter, like
+    /// [`Psd::flatten_layers_rgba`]
+    pub fn flatten_psd(
+        &mut self,
+        psd: &Psd,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+    ) -> BatchImage<'_> {
+        self.resize_rgba(psd);
+        psd.flatten_into_cached(&mut self.rgba, filter, &blend_rgba, &mut self.layer_rgba);
+        psd.orient_in_place(&mut self.rgba);
+
+        let (width, height) = psd.oriented_size();
+        BatchImage {
+            width,
+            height,
+            rgba: &self.rgba,
+        }
+    }
+
+    /// Parse the PSD and get its composite image, like [`Psd::rgba`]. The bytes are parsed
+    /// like [`Psd::from_vec`].
+    pub fn composite(&mut self, bytes: Vec<u8>) -> Result<BatchImage<'_>, PsdError> {
+        let psd = Psd::from_vec_with_options(bytes, &self.options)?;
+
+        self.resize_rgba(&psd);
+        psd.rgba_into(&mut self.rgba)?;
+
+        let (width, height) = psd.oriented_size();
+        Ok(BatchImage {
+            width,
+            height,
+            rgba: &self.rgba,
+        })
+    }
+
+    /// Free the buffers, such as after converting an unusually large PSD. They are allocated
+    /// again by the next PSD.
+    pub fn release_buffers(&mut self) {
+        self.rgba = vec![];
+        self.layer_rgba = LayerRgbaCache::default();
+    }
+
+    fn resize_rgba(&mut self, psd: &Psd) {
+        self.rgba
+            .resize(psd.width() as usize * psd.height() as usize * 4, 0);
+    }
+}
+
+impl<'a> BatchImage<'a> {
+    /// The width of the PSD, or its height when its EXIF orientation turned it sideways (see
+    /// [`PsdOptions::apply_exif_orientation`])
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the PSD, or its width when its EXIF orientation turned it sideways
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA pixels, `[R, G, B, A, R, G, B, A, ...]`
+    pub fn rgba(&self) -> &'a [u8] {
+        self.rgba
+    }
+}
+
+/// Flatten every PSD (see [`PsdBatch::flatten`]) on one thread per core, and return what
+/// `convert` returns for each of them, in the same order as the PSDs.
+///
+/// The threads are started by each call and stop once every PSD has been converted. Each one
+/// converts PSDs with its own [`PsdBatch`] until none are left, so the buffers are reused
+/// between the PSDs that a thread converts, but neither threads nor buffers are kept between
+/// calls. Pass every PSD to one call instead of calling this for each PSD. `convert` is given
+/// the index of the PSD and its pixels, and should copy or encode the pixels since they are
+/// overwritten by the thread's next PSD.
+///
+/// ```ignore
+/// let pngs = psd::batch::flatten_all(uploads, &PsdOptions::default(), |_, image| {
+///     image.map(|image| encode_png(image.rgba(), image.width(), image.height()))
+/// });
+/// ```
+#[cfg(feature = "parallel")]
+pub fn flatten_all<T, F>(psds: Vec<Vec<u8>>, options: &PsdOptions, convert: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize, Result<BatchImage<'_>, PsdError>) -> T + Sync,
+{
+    use std::sync::Mutex;
+
+    let threads = std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+        .min(psds.len());
+
+    let next = Mutex::new(psds.into_iter().enumerate());
+    let mut converted: Vec<(usize, T)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut batch = PsdBatch::with_options(options.clone());
+                    let mut converted = vec![];
+
+                    loop {
+                        let (idx, bytes) = match next.lock().unwrap().next() {
+                            Some(psd) => psd,
+                            None => return converted,
+                        };
+
+                        converted.push((idx, convert(idx, batch.flatten(bytes))));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    converted.sort_by_key(|(idx, _)| *idx);
+    converted.into_iter().map(|(_, output)| output).collect()
+}
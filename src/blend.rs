@@ -1,3 +1,5 @@
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
 use crate::sections::layer_and_mask_information_section::layer::BlendMode;
 
 // Multiplies the pixel's current alpha by the passed in `opacity`
@@ -6,6 +8,75 @@ pub(crate) fn apply_opacity(pixel: &mut [u8; 4], opacity: u8) {
     pixel[3] = (pixel[3] as f32 * alpha) as u8;
 }
 
+/// Set the color of every fully transparent pixel of the RGBA to black, so that the color that
+/// the PSD stored under it doesn't show up in the output
+pub(crate) fn clear_transparent_pixels(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        if pixel[3] == 0 {
+            pixel[..3].fill(0);
+        }
+    }
+}
+
+/// Blend the `top` pixel over the `bottom` pixel (`[R, G, B, A]`) using the blend mode.
+///
+/// This is the blending that [`Psd::flatten_layers_rgba`](crate::Psd::flatten_layers_rgba)
+/// uses, so custom blenders passed to
+/// [`Psd::flatten_layers_rgba_with_blender`](crate::Psd::flatten_layers_rgba_with_blender) can
+/// fall back to it for the modes that they don't handle.
+pub fn blend_rgba(top: [u8; 4], bottom: [u8; 4], blend_mode: BlendMode) -> [u8; 4] {
+    let mut out = [0; 4];
+    blend_pixels(top, bottom, blend_mode, &mut out);
+    out
+}
+
+/// Like [`blend_rgba`], but blends the colors in linear light instead of in sRGB, like
+/// Photoshop's "Blend RGB Colors Using Gamma: 1.00" color setting.
+///
+/// Blending in sRGB darkens the places where colors overlap, such as the dark fringe of a
+/// gradient that fades into transparency. Converting the colors to linear light before blending
+/// them and back to sRGB afterwards avoids this.
+pub fn blend_rgba_linear(top: [u8; 4], bottom: [u8; 4], blend_mode: BlendMode) -> [u8; 4] {
+    let alpha_s = top[3] as f32 / 255.;
+    let alpha_b = bottom[3] as f32 / 255.;
+    let alpha_output = alpha_s + alpha_b * (1. - alpha_s);
+
+    if alpha_output == 0. {
+        return [0; 4];
+    }
+
+    let blend_f = map_blend_mode(blend_mode);
+    let mut out = [0; 4];
+    for channel in 0..3 {
+        let color_s = srgb_to_linear(top[channel] as f32 / 255.);
+        let color_b = srgb_to_linear(bottom[channel] as f32 / 255.);
+        let color = composite(color_s, alpha_s, color_b, alpha_b, blend_f) / alpha_output;
+
+        out[channel] = (linear_to_srgb(color.clamp(0., 1.)) * 255.).round() as u8;
+    }
+    out[3] = (255. * alpha_output).round() as u8;
+
+    out
+}
+
+/// https://en.wikipedia.org/wiki/SRGB#From_sRGB_to_CIE_XYZ
+pub(crate) fn srgb_to_linear(color: f32) -> f32 {
+    if color <= 0.04045 {
+        color / 12.92
+    } else {
+        ((color + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// https://en.wikipedia.org/wiki/SRGB#From_CIE_XYZ_to_sRGB
+pub(crate) fn linear_to_srgb(color: f32) -> f32 {
+    if color <= 0.0031308 {
+        color * 12.92
+    } else {
+        1.055 * color.powf(1. / 2.4) - 0.055
+    }
+}
+
 ///
 /// https://www.w3.org/TR/compositing-1/#simplealphacompositing
 /// `Cs = (1 - αb) x Cs + αb x B(Cb, Cs)`
@@ -0,0 +1,278 @@
+//! Summarize what the crate couldn't fully interpret in a PSD, so that an application can warn
+//! its users that a preview might not match Photoshop.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//!
+//! let report = psd.capability_report();
+//! for gap in report.gaps() {
+//!     println!("The preview might differ from Photoshop: {}", gap);
+//! }
+//! ```
+
+use alloc::collections::{BTreeMap, BTreeSet};
+
+use thiserror::Error;
+
+use crate::prelude::*;
+use crate::psd_channel::PsdChannelCompression;
+use crate::sections::file_header_section::ColorMode;
+use crate::{BlendMode, Psd, PsdWarning};
+
+/// The keys of the additional layer information blocks that hold adjustment layers
+const ADJUSTMENT_LAYER_KEYS: [&str; 17] = [
+    "brit", "levl", "curv", "expA", "vibA", "hue ", "hue2", "blnc", "blwh", "phfl", "mixr", "clrL",
+    "nvrt", "post", "thrs", "grdm", "selc",
+];
+
+/// The blend modes that [`crate::blend_rgba`] can't blend with
+const UNSUPPORTED_BLEND_MODES: [BlendMode; 12] = [
+    BlendMode::Dissolve,
+    BlendMode::LinearBurn,
+    BlendMode::DarkerColor,
+    BlendMode::LighterColor,
+    BlendMode::VividLight,
+    BlendMode::LinearLight,
+    BlendMode::PinLight,
+    BlendMode::HardMix,
+    BlendMode::Hue,
+    BlendMode::Saturation,
+    BlendMode::Color,
+    BlendMode::Luminosity,
+];
+
+/// What couldn't be fully interpreted in a PSD. See [`Psd::capability_report`].
+#[derive(Debug, PartialEq, Default)]
+pub struct CapabilityReport {
+    pub(crate) gaps: Vec<UnsupportedFeature>,
+}
+
+/// Something in a PSD that is left out of, or approximated in, the pixels that we render.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum UnsupportedFeature {
+    /// The document's color mode isn't converted to RGB, so its channels are shown as if they
+    /// were red, green and blue (or gray)
+    #[error("The {color_mode:?} color mode isn't converted to RGB.")]
+    ColorMode {
+        /// The color mode of the document
+        color_mode: ColorMode,
+    },
+    /// Adjustment layers are ignored, so the layers below them aren't adjusted
+    #[error("{count} adjustment layers ignored.")]
+    AdjustmentLayers {
+        /// The number of adjustment layers
+        count: usize,
+    },
+    /// Visible layers use blend modes that we can't blend with, so the layers can't be flattened
+    #[error("{layers} layers use blend modes that aren't supported: {blend_modes:?}.")]
+    BlendModes {
+        /// The number of visible layers with blend modes that aren't supported
+        layers: usize,
+        /// The blend modes, in the order of [`BlendMode`]'s variants
+        blend_modes: Vec<BlendMode>,
+    },
+    /// Layer effects that aren't rendered, even by
+    /// [`Compositor::with_effects`](crate::compositor::Compositor::with_effects)
+    #[error("{layers} layers have effects that aren't rendered: {effects:?}.")]
+    LayerEffects {
+        /// The number of layers with effects that aren't rendered
+        layers: usize,
+        /// The keys of the effects, such as `OrGl` for an outer glow
+        effects: Vec<String>,
+    },
+    /// Type layers show the text as Photoshop last rendered it, since we don't lay out text
+    #[error("{count} text layers not rendered.")]
+    TextLayers {
+        /// The number of type layers
+        count: usize,
+    },
+    /// Layer channels that use a compression that we can't decompress, so they were left empty
+    #[error("{compression:?} compression in {channels} channels.")]
+    LayerCompression {
+        /// How the channels are compressed
+        compression: PsdChannelCompression,
+        /// The number of channels
+        channels: usize,
+    },
+    /// The composite image uses a compression that we can't decompress, so the layers are
+    /// flattened instead
+    #[error("The composite image has unsupported compression {compression:?}.")]
+    CompositeCompression {
+        /// How the composite image is compressed
+        compression: PsdChannelCompression,
+    },
+    /// The composite image was cut short, so the layers are flattened instead
+    #[error("The composite image's compressed data was cut short.")]
+    TruncatedComposite,
+    /// Malformed layers were skipped while recovering
+    #[error("{count} malformed layers skipped.")]
+    SkippedLayers {
+        /// The number of layers
+        count: usize,
+    },
+    /// Malformed image resources were skipped while recovering
+    #[error("Malformed image resources skipped.")]
+    SkippedResources,
+    /// Image resources that we don't parse yet
+    #[error("{} image resources ignored: {resource_ids:?}.", .resource_ids.len())]
+    IgnoredResources {
+        /// The IDs of the resources, in ascending order
+        resource_ids: Vec<i16>,
+    },
+    /// Blocks of additional layer information that we don't parse yet, other than adjustment
+    /// layers
+    #[error("{} kinds of additional layer information ignored: {keys:?}.", .keys.len())]
+    IgnoredLayerInfo {
+        /// The four character keys of the blocks, in ascending order
+        keys: Vec<String>,
+    },
+}
+
+impl CapabilityReport {
+    pub(crate) fn new(psd: &Psd) -> CapabilityReport {
+        let mut gaps = vec![];
+
+        let color_mode = psd.color_mode();
+        if matches!(
+            color_mode,
+            ColorMode::Indexed | ColorMode::Lab | ColorMode::Duotone | ColorMode::Multichannel
+        ) {
+            gaps.push(UnsupportedFeature::ColorMode { color_mode });
+        }
+
+        let mut adjustment_layers = 0;
+        let mut compressed_channels: BTreeMap<u8, (PsdChannelCompression, usize)> = BTreeMap::new();
+        let mut skipped_layers = 0;
+        let mut skipped_resources = false;
+        let mut resource_ids = BTreeSet::new();
+        let mut keys = BTreeSet::new();
+        let mut composite_gaps = vec![];
+        for warning in psd.warnings() {
+            match warning {
+                PsdWarning::IgnoredLayerInfo { key, .. }
+                    if ADJUSTMENT_LAYER_KEYS.contains(&key.as_str()) =>
+                {
+                    adjustment_layers += 1
+                }
+                PsdWarning::IgnoredLayerInfo { key, .. }
+                | PsdWarning::SkippedLayerInfo { key, .. } => {
+                    keys.insert(key.clone());
+                }
+                PsdWarning::IgnoredResource { resource_id } => {
+                    resource_ids.insert(*resource_id);
+                }
+                PsdWarning::UnsupportedLayerCompression { compression, .. } => {
+                    compressed_channels
+                        .entry(*compression as u8)
+                        .or_insert((*compression, 0))
+                        .1 += 1;
+                }
+                PsdWarning::UnsupportedCompositeCompression { compression } => {
+                    composite_gaps.push(UnsupportedFeature::CompositeCompression {
+                        compression: *compression,
+                    })
+                }
+                PsdWarning::TruncatedComposite => {
+                    composite_gaps.push(UnsupportedFeature::TruncatedComposite)
+                }
+                PsdWarning::SkippedLayer { .. } => skipped_layers += 1,
+                PsdWarning::SkippedResource { .. }
+                | PsdWarning::SkippedRemainingResources { .. } => skipped_resources = true,
+            }
+        }
+
+        if adjustment_layers > 0 {
+            gaps.push(UnsupportedFeature::AdjustmentLayers {
+                count: adjustment_layers,
+            });
+        }
+
+        let mut blend_mode_layers = 0;
+        let mut blend_modes = BTreeMap::new();
+        for layer in psd.layers().iter().filter(|layer| layer.visible()) {
+            let blend_mode = layer.blend_mode();
+            if UNSUPPORTED_BLEND_MODES.contains(&blend_mode) {
+                blend_mode_layers += 1;
+                blend_modes.insert(blend_mode as u8, blend_mode);
+            }
+        }
+        if blend_mode_layers > 0 {
+            gaps.push(UnsupportedFeature::BlendModes {
+                layers: blend_mode_layers,
+                blend_modes: blend_modes.into_values().collect(),
+            });
+        }
+
+        let mut effect_layers = 0;
+        let mut effects = BTreeSet::new();
+        for layer in psd.layers().iter() {
+            let ignored_effects = layer
+                .effects()
+                .map_or(&[][..], |effects| effects.ignored_effects());
+            if !ignored_effects.is_empty() {
+                effect_layers += 1;
+                effects.extend(ignored_effects.iter().cloned());
+            }
+        }
+        if effect_layers > 0 {
+            gaps.push(UnsupportedFeature::LayerEffects {
+                layers: effect_layers,
+                effects: effects.into_iter().collect(),
+            });
+        }
+
+        let text_layers = psd
+            .layers()
+            .iter()
+            .filter(|layer| layer.text().is_some())
+            .count();
+        if text_layers > 0 {
+            gaps.push(UnsupportedFeature::TextLayers { count: text_layers });
+        }
+
+        gaps.extend(
+            compressed_channels
+                .into_values()
+                .map(
+                    |(compression, channels)| UnsupportedFeature::LayerCompression {
+                        compression,
+                        channels,
+                    },
+                ),
+        );
+        gaps.extend(composite_gaps);
+
+        if skipped_layers > 0 {
+            gaps.push(UnsupportedFeature::SkippedLayers {
+                count: skipped_layers,
+            });
+        }
+        if skipped_resources {
+            gaps.push(UnsupportedFeature::SkippedResources);
+        }
+        if !resource_ids.is_empty() {
+            gaps.push(UnsupportedFeature::IgnoredResources {
+                resource_ids: resource_ids.into_iter().collect(),
+            });
+        }
+        if !keys.is_empty() {
+            gaps.push(UnsupportedFeature::IgnoredLayerInfo {
+                keys: keys.into_iter().collect(),
+            });
+        }
+
+        CapabilityReport { gaps }
+    }
+
+    /// What couldn't be fully interpreted, from the gaps that affect the whole image to the
+    /// data that was ignored
+    pub fn gaps(&self) -> &[UnsupportedFeature] {
+        &self.gaps
+    }
+
+    /// Whether everything in the PSD was interpreted
+    pub fn is_fully_supported(&self) -> bool {
+        self.gaps.is_empty()
+    }
+}
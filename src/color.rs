@@ -0,0 +1,338 @@
+//! The color conversions that the crate uses to turn a PSD's channels into RGBA, for code that
+//! reads raw channels and wants the same colors that [`Psd::rgba`] would give.
+//!
+//! Colors are in the form that PSDs store them: CMYK values are inverted so that 255 means no
+//! ink, and Lab values are bytes where 128 is a neutral `a` or `b`.
+//!
+//! ```ignore
+//! let cmyk = [255, 0, 255, 255];
+//!
+//! assert_eq!(psd::color::cmyk_to_rgb(cmyk), [255, 0, 255]);
+//! ```
+//!
+//! [`Psd::rgba`]: crate::Psd::rgba
+
+use core::cmp::Reverse;
+
+use crate::blend::{linear_to_srgb, srgb_to_linear};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::prelude::*;
+
+/// The D50 white point that Photoshop's Lab colors are relative to
+const D50_WHITE: [f32; 3] = [0.964_22, 1., 0.825_21];
+
+/// Converts linear sRGB to XYZ relative to D50, using the Bradford adaptation
+const SRGB_TO_XYZ_D50: [[f32; 3]; 3] = [
+    [0.436_074_7, 0.385_064_9, 0.143_080_4],
+    [0.222_504_5, 0.716_878_6, 0.060_616_9],
+    [0.013_932_2, 0.097_104_5, 0.714_173_3],
+];
+
+/// Converts XYZ relative to D50 to linear sRGB, the inverse of [`SRGB_TO_XYZ_D50`]
+const XYZ_D50_TO_SRGB: [[f32; 3]; 3] = [
+    [3.133_856, -1.616_866_7, -0.490_614_6],
+    [-0.978_768_4, 1.916_141_5, 0.033_454],
+    [0.071_945_3, -0.228_991_4, 1.405_242_7],
+];
+
+/// `(6/29)^3`, below which Lab is linear instead of a cube root
+const LAB_EPSILON: f32 = 216. / 24389.;
+/// `(29/3)^3`, the slope of the linear part of Lab
+const LAB_KAPPA: f32 = 24389. / 27.;
+
+/// How many of the high bits of each channel pick a pixel's bucket in [`dominant_colors`]
+const DOMINANT_COLOR_BITS: u32 = 5;
+
+/// One of the most common colors of an image, see [`dominant_colors`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DominantColor {
+    /// The average color of the pixels that are close to this color, as `[R, G, B]`
+    pub rgb: [u8; 3],
+    /// How many pixels are close to this color
+    pub pixel_count: u32,
+}
+
+/// Convert a CMYK color, stored inverted as PSDs store it, to RGB.
+///
+/// Each of cyan, magenta and yellow becomes red, green and blue, darkened by the black.
+pub fn cmyk_to_rgb(cmyk: [u8; 4]) -> [u8; 3] {
+    let [c, m, y, k] = cmyk;
+
+    [apply_black(c, k), apply_black(m, k), apply_black(y, k)]
+}
+
+/// Convert an RGB color to CMYK, stored inverted as PSDs store it.
+///
+/// Uses as much black as possible, so [`cmyk_to_rgb`] gives back the same color.
+pub fn rgb_to_cmyk(rgb: [u8; 3]) -> [u8; 4] {
+    let k = rgb.iter().copied().max().unwrap_or(0);
+    if k == 0 {
+        return [255, 255, 255, 0];
+    }
+
+    // Rounded up so that darkening by the black doesn't fall short of the channel
+    let channel = |value: u8| (value as u32 * 255).div_ceil(k as u32) as u8;
+
+    [channel(rgb[0]), channel(rgb[1]), channel(rgb[2]), k]
+}
+
+/// Darken an inverted cyan, magenta or yellow value by an inverted black value
+pub(crate) fn apply_black(value: u8, black: u8) -> u8 {
+    (value as u32 * black as u32 / 255) as u8
+}
+
+/// Convert a Lab color, stored as PSDs store it, to sRGB.
+///
+/// The lightness is from 0 to 255 for 0 to 100, and `a` and `b` are from 0 to 255 for -128 to
+/// 127. Colors that sRGB can't show are clipped.
+pub fn lab_to_rgb(lab: [u8; 3]) -> [u8; 3] {
+    let lightness = lab[0] as f32 * 100. / 255.;
+    let a = lab[1] as f32 - 128.;
+    let b = lab[2] as f32 - 128.;
+
+    let fy = (lightness + 16.) / 116.;
+    let fx = fy + a / 500.;
+    let fz = fy - b / 200.;
+
+    let inverse = |f: f32| {
+        let cubed = f * f * f;
+        if cubed > LAB_EPSILON {
+            cubed
+        } else {
+            (116. * f - 16.) / LAB_KAPPA
+        }
+    };
+    let xyz = [
+        inverse(fx) * D50_WHITE[0],
+        inverse(fy) * D50_WHITE[1],
+        inverse(fz) * D50_WHITE[2],
+    ];
+
+    let linear = multiply(&XYZ_D50_TO_SRGB, xyz);
+
+    let mut rgb = [0; 3];
+    for (channel, linear) in rgb.iter_mut().zip(linear.iter()) {
+        *channel = (linear_to_srgb(linear.clamp(0., 1.)) * 255.).round() as u8;
+    }
+    rgb
+}
+
+/// Convert an sRGB color to Lab, stored as PSDs store it. See [`lab_to_rgb`].
+pub fn rgb_to_lab(rgb: [u8; 3]) -> [u8; 3] {
+    let linear = [
+        srgb_to_linear(rgb[0] as f32 / 255.),
+        srgb_to_linear(rgb[1] as f32 / 255.),
+        srgb_to_linear(rgb[2] as f32 / 255.),
+    ];
+    let xyz = multiply(&SRGB_TO_XYZ_D50, linear);
+
+    let forward = |t: f32| {
+        if t > LAB_EPSILON {
+            t.powf(1. / 3.)
+        } else {
+            (LAB_KAPPA * t + 16.) / 116.
+        }
+    };
+    let fx = forward(xyz[0] / D50_WHITE[0]);
+    let fy = forward(xyz[1] / D50_WHITE[1]);
+    let fz = forward(xyz[2] / D50_WHITE[2]);
+
+    let lightness = 116. * fy - 16.;
+    let a = 500. * (fx - fy);
+    let b = 200. * (fy - fz);
+
+    [
+        (lightness * 255. / 100.).round().clamp(0., 255.) as u8,
+        (a + 128.).round().clamp(0., 255.) as u8,
+        (b + 128.).round().clamp(0., 255.) as u8,
+    ]
+}
+
+/// Expand a grayscale value to RGB
+pub fn gray_to_rgb(gray: u8) -> [u8; 3] {
+    [gray, gray, gray]
+}
+
+/// Convert an HSB color to RGB, where the hue is in degrees and the saturation and brightness
+/// are from 0.0 to 1.0
+pub fn hsb_to_rgb(hue: f32, saturation: f32, brightness: f32) -> [u8; 3] {
+    let hue = (hue % 360. + 360.) % 360. / 60.;
+    let chroma = brightness * saturation;
+    let x = chroma * (1. - (hue % 2. - 1.).abs());
+    let m = brightness - chroma;
+
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    let to_u8 = |value: f32| ((value + m) * 255.).round().clamp(0., 255.) as u8;
+    [to_u8(r), to_u8(g), to_u8(b)]
+}
+
+/// Scale a 16 bit channel value down to 8 bits by keeping its high byte
+pub fn sixteen_to_eight(value: u16) -> u8 {
+    (value / 256) as u8
+}
+
+/// Scale an 8 bit channel value up to 16 bits, so that 255 becomes 65535.
+///
+/// [`sixteen_to_eight`] gives back the same value.
+pub fn eight_to_sixteen(value: u8) -> u16 {
+    value as u16 * 257
+}
+
+/// The `n` most common colors of the RGBA pixels (`[R, G, B, A, R, G, B, A, ...]`) of an image,
+/// most common first.
+///
+/// Colors are counted in a histogram whose buckets are the top 5 bits of each channel, so
+/// colors that are almost the same are counted together. Fully transparent pixels are skipped.
+pub fn dominant_colors(rgba: &[u8], n: usize) -> Vec<DominantColor> {
+    let shift = 8 - DOMINANT_COLOR_BITS;
+    let bucket_count = 1 << (DOMINANT_COLOR_BITS * 3);
+
+    // The sum of the red, green and blue and the number of pixels in each bucket
+    let mut buckets = vec![[0u64; 4]; bucket_count];
+    for pixel in rgba.chunks_exact(4) {
+        if pixel[3] == 0 {
+            continue;
+        }
+
+        let bucket = (pixel[0] as usize >> shift) << (DOMINANT_COLOR_BITS * 2)
+            | (pixel[1] as usize >> shift) << DOMINANT_COLOR_BITS
+            | pixel[2] as usize >> shift;
+        let bucket = &mut buckets[bucket];
+        for channel in 0..3 {
+            bucket[channel] += pixel[channel] as u64;
+        }
+        bucket[3] += 1;
+    }
+
+    let mut colors: Vec<DominantColor> = buckets
+        .iter()
+        .filter(|bucket| bucket[3] > 0)
+        .map(|bucket| {
+            let count = bucket[3];
+            let average = |channel: usize| ((bucket[channel] + count / 2) / count) as u8;
+
+            DominantColor {
+                rgb: [average(0), average(1), average(2)],
+                pixel_count: count as u32,
+            }
+        })
+        .collect();
+
+    // Stable, so buckets with as many pixels stay in the order of their colors
+    colors.sort_by_key(|color| Reverse(color.pixel_count));
+    colors.truncate(n);
+
+    colors
+}
+
+fn multiply(matrix: &[[f32; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.; 3];
+    for (out, row) in out.iter_mut().zip(matrix.iter()) {
+        *out = row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CMYK stored inverted, where 255 means no ink.
+    #[test]
+    fn cmyk() {
+        assert_eq!(cmyk_to_rgb([255, 255, 255, 255]), [255, 255, 255]);
+        assert_eq!(cmyk_to_rgb([0, 255, 255, 255]), [0, 255, 255]);
+        assert_eq!(cmyk_to_rgb([255, 255, 255, 0]), [0, 0, 0]);
+        assert_eq!(cmyk_to_rgb([200, 100, 255, 128]), [100, 50, 128]);
+
+        for rgb in [
+            [0, 0, 0],
+            [255, 255, 255],
+            [10, 200, 73],
+            [1, 2, 3],
+            [254, 0, 255],
+        ] {
+            assert_eq!(cmyk_to_rgb(rgb_to_cmyk(rgb)), rgb, "{:?}", rgb);
+        }
+    }
+
+    /// Hues wrap around, so -120 degrees is blue.
+    #[test]
+    fn hsb() {
+        assert_eq!(hsb_to_rgb(0., 1., 1.), [255, 0, 0]);
+        assert_eq!(hsb_to_rgb(120., 1., 0.5), [0, 128, 0]);
+        assert_eq!(hsb_to_rgb(-120., 1., 1.), [0, 0, 255]);
+        assert_eq!(hsb_to_rgb(300., 0., 0.2), [51, 51, 51]);
+    }
+
+    /// Lab relative to D50, which Photoshop uses.
+    #[test]
+    fn lab() {
+        assert_eq!(lab_to_rgb([255, 128, 128]), [255, 255, 255]);
+        assert_eq!(lab_to_rgb([0, 128, 128]), [0, 0, 0]);
+        // Photoshop shows sRGB red as 54, 81, 70
+        assert_eq!(rgb_to_lab([255, 0, 0]), [138, 209, 198]);
+
+        for rgb in [[255, 0, 0], [0, 128, 0], [30, 60, 200], [128, 128, 128]] {
+            let back = lab_to_rgb(rgb_to_lab(rgb));
+            for (back, rgb) in back.iter().zip(rgb.iter()) {
+                assert!(
+                    (*back as i32 - *rgb as i32).abs() <= 3,
+                    "{:?} {:?}",
+                    back,
+                    rgb
+                );
+            }
+        }
+    }
+
+    /// The most common colors come first, and transparent pixels aren't counted.
+    #[test]
+    fn most_common_colors() {
+        let mut rgba = vec![];
+        for _ in 0..3 {
+            rgba.extend_from_slice(&[200, 10, 10, 255]);
+        }
+        rgba.extend_from_slice(&[202, 12, 12, 255]);
+        rgba.extend_from_slice(&[0, 0, 255, 255]);
+        rgba.extend_from_slice(&[0, 255, 0, 0]);
+        rgba.extend_from_slice(&[0, 255, 0, 0]);
+
+        assert_eq!(
+            dominant_colors(&rgba, 3),
+            vec![
+                DominantColor {
+                    rgb: [201, 11, 11],
+                    pixel_count: 4,
+                },
+                DominantColor {
+                    rgb: [0, 0, 255],
+                    pixel_count: 1,
+                },
+            ]
+        );
+        assert_eq!(dominant_colors(&rgba, 1).len(), 1);
+    }
+
+    /// 16 bit values keep their high byte.
+    #[test]
+    fn sixteen_bit() {
+        assert_eq!(sixteen_to_eight(0), 0);
+        assert_eq!(sixteen_to_eight(255), 0);
+        assert_eq!(sixteen_to_eight(256), 1);
+        assert_eq!(sixteen_to_eight(65535), 255);
+
+        for value in 0..=255 {
+            assert_eq!(sixteen_to_eight(eight_to_sixteen(value)), value);
+        }
+    }
+}
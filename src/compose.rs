@@ -0,0 +1,112 @@
+//! Build layered PSDs out of images, such as bundling generated sprites back into a PSD that
+//! artists can open.
+//!
+//! ```ignore
+//! let sprites = vec![
+//!     ("Head", (16, 16, head_rgba), 0, 0),
+//!     ("Body", (16, 32, body_rgba), 0, 16),
+//! ];
+//!
+//! std::fs::write("character.psd", psd::compose::images_to_bytes(sprites)?)?;
+//! ```
+
+use crate::prelude::*;
+use crate::writer::{PsdWriter, PsdWriterError, WriterLayer};
+use crate::{Psd, PsdOptions};
+
+/// An image of RGBA pixels (`[R, G, B, A, R, G, B, A, ...]`).
+pub trait RgbaImage {
+    /// The width of the image, in pixels
+    fn width(&self) -> u32;
+
+    /// The height of the image, in pixels
+    fn height(&self) -> u32;
+
+    /// The pixels of the image, row by row
+    fn rgba(&self) -> &[u8];
+}
+
+/// `(width, height, rgba)`
+impl RgbaImage for (u32, u32, Vec<u8>) {
+    fn width(&self) -> u32 {
+        self.0
+    }
+
+    fn height(&self) -> u32 {
+        self.1
+    }
+
+    fn rgba(&self) -> &[u8] {
+        &self.2
+    }
+}
+
+/// `(width, height, rgba)`
+impl RgbaImage for (u32, u32, &[u8]) {
+    fn width(&self) -> u32 {
+        self.0
+    }
+
+    fn height(&self) -> u32 {
+        self.1
+    }
+
+    fn rgba(&self) -> &[u8] {
+        self.2
+    }
+}
+
+/// Create a PSD with a layer for each `(name, image, x, y)`, where `x` and `y` are the position
+/// of the image's top left corner.
+///
+/// The first image is the top layer, matching the order of [`Psd::layers`]. The canvas is
+/// just large enough to fit every image, and the layers are flattened into the composite image.
+pub fn from_images<I, N, R>(images: I) -> Result<Psd, PsdWriterError>
+where
+    I: IntoIterator<Item = (N, R, i32, i32)>,
+    N: Into<String>,
+    R: RgbaImage,
+{
+    let bytes = images_to_bytes(images)?;
+
+    read_written(bytes)
+}
+
+/// Write a PSD with a layer for each `(name, image, x, y)`. See [`from_images`].
+pub fn images_to_bytes<I, N, R>(images: I) -> Result<Vec<u8>, PsdWriterError>
+where
+    I: IntoIterator<Item = (N, R, i32, i32)>,
+    N: Into<String>,
+    R: RgbaImage,
+{
+    let mut layers = vec![];
+    let (mut width, mut height) = (0, 0);
+
+    for (name, image, x, y) in images {
+        let right = x as i64 + image.width() as i64;
+        let bottom = y as i64 + image.height() as i64;
+        width = width.max(right.clamp(0, u32::MAX as i64) as u32);
+        height = height.max(bottom.clamp(0, u32::MAX as i64) as u32);
+
+        let rgba = image.rgba().to_vec();
+        layers.push(WriterLayer::new(name, image.width(), image.height(), rgba).position(x, y));
+    }
+
+    let mut writer = PsdWriter::new(width, height);
+    for layer in layers {
+        writer = writer.layer(layer);
+    }
+
+    // Without a composite image the layers are flattened when the PSD is read, which gives us
+    // the composite image to write.
+    let without_composite = writer.to_bytes()?;
+    let composite = read_written(without_composite)?.rgba();
+
+    writer.composite_rgba(composite).to_bytes()
+}
+
+/// Read a PSD that we wrote. The caller chose how many images to write, so the limits that
+/// guard against untrusted files don't apply.
+fn read_written(bytes: Vec<u8>) -> Result<Psd, PsdWriterError> {
+    Psd::from_vec_with_options(bytes, &PsdOptions::unlimited()).map_err(PsdWriterError::ReadError)
+}
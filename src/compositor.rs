@@ -0,0 +1,159 @@
+//! Keep a flattened image of a PSD up to date as its layers are shown and hidden, such as for
+//! previewing a PSD while toggling its layers on and off.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//! let mut compositor = Compositor::new(&psd);
+//!
+//! // Only the pixels that the layer covers are flattened again
+//! compositor.set_layer_visible(2, false);
+//! draw(compositor.rgba());
+//! ```
+//!
+//! Use [`Compositor::with_effects`] to also render the layers' drop shadows, strokes and color
+//! overlays, so that styled layers look the way that they do in Photoshop.
+
+use core::ops::Range;
+
+use crate::blend::blend_rgba;
+use crate::prelude::*;
+use crate::{LayerRgbaCache, Psd, PsdLayer};
+
+/// A flattened image of a PSD that can be updated when a layer is shown or hidden, without
+/// flattening the entire PSD again.
+///
+/// The RGBA of each layer is cached the first time that it is needed, so toggling layers
+/// doesn't decode them again.
+#[derive(Debug)]
+pub struct Compositor<'a> {
+    psd: &'a Psd,
+    visible: Vec<bool>,
+    rgba: Vec<u8>,
+    cached_layer_rgba: LayerRgbaCache,
+    /// Whether the layers' effects are rendered
+    effects: bool,
+}
+
+impl<'a> Compositor<'a> {
+    /// Flatten the PSD's visible layers.
+    pub fn new(psd: &'a Psd) -> Compositor<'a> {
+        Compositor::flatten(psd, false)
+    }
+
+    /// Flatten the PSD's visible layers along with their drop shadows, strokes and color
+    /// overlays, see [`PsdLayer::rgba_with_effects`].
+    ///
+    /// Rendering effects is slower, and a layer with effects is flattened again across the
+    /// entire PSD when it is shown or hidden.
+    pub fn with_effects(psd: &'a Psd) -> Compositor<'a> {
+        Compositor::flatten(psd, true)
+    }
+
+    fn flatten(psd: &'a Psd, effects: bool) -> Compositor<'a> {
+        let mut compositor = Compositor {
+            psd,
+            visible: psd.layers().iter().map(|layer| layer.visible()).collect(),
+            rgba: vec![0; psd.width() as usize * psd.height() as usize * 4],
+            cached_layer_rgba: LayerRgbaCache::default(),
+            effects,
+        };
+
+        if psd.layers().is_empty() {
+            compositor.rgba = psd.rgba_unoriented();
+        } else {
+            compositor.recomposite(0..psd.width() as usize, 0..psd.height() as usize);
+        }
+
+        compositor
+    }
+
+    /// The flattened pixels.
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Take the flattened pixels
+    pub fn into_rgba(self) -> Vec<u8> {
+        self.rgba
+    }
+
+    /// Whether the layer at the index in [`Psd::layers`] is currently shown
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn is_layer_visible(&self, idx: usize) -> bool {
+        self.visible[idx]
+    }
+
+    /// Show or hide the layer at the index in [`Psd::layers`], flattening only the pixels that
+    /// the layer covers again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds.
+    pub fn set_layer_visible(&mut self, idx: usize, visible: bool) {
+        let len = self.visible.len();
+        assert!(
+            idx < len,
+            "layer index {} is out of bounds for {} layers",
+            idx,
+            len
+        );
+
+        if self.visible[idx] == visible {
+            return;
+        }
+        self.visible[idx] = visible;
+
+        if let Some((columns, rows)) = self.dirty_region(&self.psd.layers()[idx]) {
+            self.recomposite(columns, rows);
+        }
+    }
+
+    /// The columns and rows of the PSD that the layer covers, if any
+    fn dirty_region(&self, layer: &PsdLayer) -> Option<(Range<usize>, Range<usize>)> {
+        let canvas = self.psd.canvas_rect();
+
+        // Fill layers cover the entire PSD, while empty layers don't cover anything. Effects
+        // can reach anywhere on the PSD.
+        let region = match layer.effects() {
+            Some(_) if self.effects => canvas,
+            _ => layer.placement()?.visible_canvas_rect()?,
+        };
+
+        Some((
+            region.left as usize..region.right as usize + 1,
+            region.top as usize..region.bottom as usize + 1,
+        ))
+    }
+
+    fn recomposite(&mut self, columns: Range<usize>, rows: Range<usize>) {
+        let layers_to_flatten_top_down: Vec<(usize, &PsdLayer)> = self
+            .psd
+            .layers()
+            .iter()
+            .enumerate()
+            .filter(|(idx, layer)| layer.opacity() > 0 && self.visible[*idx])
+            .collect();
+
+        if layers_to_flatten_top_down.is_empty() {
+            let width = self.psd.width() as usize;
+            for top in rows {
+                self.rgba[(top * width + columns.start) * 4..(top * width + columns.end) * 4]
+                    .fill(0);
+            }
+            return;
+        }
+
+        self.psd.flatten_region(
+            &mut self.rgba,
+            (columns, rows),
+            &layers_to_flatten_top_down,
+            &mut self.cached_layer_rgba,
+            &blend_rgba,
+            self.effects,
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Compare two PSDs, such as two revisions of the same file, and report how their layers
+//! changed.
+//!
+//! ```ignore
+//! let before = Psd::from_bytes(before)?;
+//! let after = Psd::from_bytes(after)?;
+//!
+//! let diff = psd::diff::diff(&before, &after, &DiffOptions { pixels: true });
+//! for change in diff.changes() {
+//!     println!("{:?}", change);
+//! }
+//! ```
+//!
+//! Layers are matched up by their layer ID, so a layer that was renamed or moved is still
+//! recognized as the same layer. Layers without an ID (from PSDs saved by older versions of
+//! Photoshop) are matched up by their name instead.
+
+use crate::prelude::*;
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::{LayerBounds, Psd, PsdLayer};
+
+/// Configures what [`diff`] compares
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// If true, compare the pixels of every layer that is in both PSDs and report the
+    /// percentage of pixels that changed.
+    ///
+    /// This requires decoding every layer in both PSDs, so it is off by default.
+    pub pixels: bool,
+}
+
+/// The differences between two PSDs
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsdDiff {
+    changes: Vec<LayerChange>,
+}
+
+impl PsdDiff {
+    /// Every change that was found.
+    ///
+    /// Removed layers come first, in the order that they were in the old PSD, followed by the
+    /// changes to the layers of the new PSD in the order that the layers are in.
+    pub fn changes(&self) -> &[LayerChange] {
+        &self.changes
+    }
+
+    /// If true, no differences were found between the layers of the two PSDs
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Identifies a layer within a PSD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerRef {
+    /// The layer's ID, if the PSD stores one
+    pub id: Option<u32>,
+    /// The layer's name
+    pub name: String,
+}
+
+/// The properties that affect how a layer is blended with the layers below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendParameters {
+    /// The layer's blend mode
+    pub blend_mode: BlendMode,
+    /// The layer's opacity
+    pub opacity: u8,
+    /// Whether or not the layer is visible
+    pub visible: bool,
+    /// Whether or not the layer is a clipping base (see `PsdLayer::is_clipping_mask`)
+    pub clipping_mask: bool,
+}
+
+/// A single difference between a layer in the old PSD and the new PSD
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerChange {
+    /// The layer is only in the new PSD
+    Added(LayerRef),
+    /// The layer is only in the old PSD
+    Removed(LayerRef),
+    /// The layer's name changed
+    Renamed {
+        /// The layer in the new PSD
+        layer: LayerRef,
+        /// The layer's name in the old PSD
+        old_name: String,
+    },
+    /// The layer's position or size changed.
+    ///
+    /// The bounds are `None` for empty layers.
+    BoundsChanged {
+        /// The layer in the new PSD
+        layer: LayerRef,
+        /// The layer's bounds in the old PSD
+        old: Option<LayerBounds>,
+        /// The layer's bounds in the new PSD
+        new: Option<LayerBounds>,
+    },
+    /// The layer's blend mode, opacity, visibility or clipping changed
+    BlendChanged {
+        /// The layer in the new PSD
+        layer: LayerRef,
+        /// The layer's blend parameters in the old PSD
+        old: BlendParameters,
+        /// The layer's blend parameters in the new PSD
+        new: BlendParameters,
+    },
+    /// Some of the layer's pixels changed. Only reported when [`DiffOptions::pixels`] is set.
+    PixelsChanged {
+        /// The layer in the new PSD
+        layer: LayerRef,
+        /// The percentage (0 to 100) of the pixels covered by the layer in either PSD that
+        /// are different.
+        percent: f32,
+    },
+}
+
+/// Compare the layers of two PSDs
+pub fn diff(old: &Psd, new: &Psd, options: &DiffOptions) -> PsdDiff {
+    let mut changes = vec![];
+
+    let old_layers: HashMap<LayerKey, &PsdLayer> = old
+        .layers()
+        .iter()
+        .map(|layer| (LayerKey::new(layer), layer))
+        .collect();
+    let new_layers: HashMap<LayerKey, &PsdLayer> = new
+        .layers()
+        .iter()
+        .map(|layer| (LayerKey::new(layer), layer))
+        .collect();
+
+    for layer in old.layers() {
+        if !new_layers.contains_key(&LayerKey::new(layer)) {
+            changes.push(LayerChange::Removed(LayerRef::new(layer)));
+        }
+    }
+
+    for new_layer in new.layers() {
+        let old_layer = match old_layers.get(&LayerKey::new(new_layer)) {
+            Some(old_layer) => *old_layer,
+            None => {
+                changes.push(LayerChange::Added(LayerRef::new(new_layer)));
+                continue;
+            }
+        };
+
+        if old_layer.name() != new_layer.name() {
+            changes.push(LayerChange::Renamed {
+                layer: LayerRef::new(new_layer),
+                old_name: old_layer.name().to_string(),
+            });
+        }
+
+        let (old_bounds, new_bounds) = (old_layer.bounds(), new_layer.bounds());
+        if old_bounds != new_bounds {
+            changes.push(LayerChange::BoundsChanged {
+                layer: LayerRef::new(new_layer),
+                old: old_bounds,
+                new: new_bounds,
+            });
+        }
+
+        let (old_blend, new_blend) = (
+            BlendParameters::new(old_layer),
+            BlendParameters::new(new_layer),
+        );
+        if old_blend != new_blend {
+            changes.push(LayerChange::BlendChanged {
+                layer: LayerRef::new(new_layer),
+                old: old_blend,
+                new: new_blend,
+            });
+        }
+
+        if options.pixels {
+            let percent = pixel_difference_percent(old_layer, new_layer);
+            if percent > 0. {
+                changes.push(LayerChange::PixelsChanged {
+                    layer: LayerRef::new(new_layer),
+                    percent,
+                });
+            }
+        }
+    }
+
+    PsdDiff { changes }
+}
+
+/// How a layer is matched up with the same layer in the other PSD
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum LayerKey {
+    Id(u32),
+    Name(String),
+}
+
+impl LayerKey {
+    fn new(layer: &PsdLayer) -> LayerKey {
+        match layer.layer_id() {
+            Some(id) => LayerKey::Id(id),
+            None => LayerKey::Name(layer.name().to_string()),
+        }
+    }
+}
+
+impl LayerRef {
+    fn new(layer: &PsdLayer) -> LayerRef {
+        LayerRef {
+            id: layer.layer_id(),
+            name: layer.name().to_string(),
+        }
+    }
+}
+
+impl BlendParameters {
+    fn new(layer: &PsdLayer) -> BlendParameters {
+        BlendParameters {
+            blend_mode: layer.blend_mode(),
+            opacity: layer.opacity(),
+            visible: layer.visible(),
+            clipping_mask: layer.is_clipping_mask(),
+        }
+    }
+}
+
+/// The percentage of the pixels that are covered by either layer that differ between them.
+///
+/// Pixels outside of a layer's bounds are treated as transparent, as are pixels outside of the
+/// PSD when the PSDs are different sizes.
+fn pixel_difference_percent(old: &PsdLayer, new: &PsdLayer) -> f32 {
+    let (old_rgba, new_rgba) = (old.rgba(), new.rgba());
+
+    let width = old.psd_width.max(new.psd_width);
+    let height = old.psd_height.max(new.psd_height);
+
+    let mut covered = 0;
+    let mut different = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let old_pixel = layer_pixel(old, &old_rgba, x, y);
+            let new_pixel = layer_pixel(new, &new_rgba, x, y);
+
+            if old_pixel[3] == 0 && new_pixel[3] == 0 {
+                continue;
+            }
+
+            covered += 1;
+            if old_pixel != new_pixel {
+                different += 1;
+            }
+        }
+    }
+
+    if covered == 0 {
+        return 0.;
+    }
+
+    different as f32 / covered as f32 * 100.
+}
+
+/// Get a pixel from the layer's RGBA
+fn layer_pixel<'a>(layer: &PsdLayer, rgba: &'a [u8], x: u32, y: u32) -> &'a [u8] {
+    if x >= layer.psd_width || y >= layer.psd_height || !layer.contains_canvas_point(x, y) {
+        return &[0; 4];
+    }
+
+    let idx = (y * layer.psd_width + x) as usize * 4;
+    &rgba[idx..idx + 4]
+}
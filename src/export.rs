@@ -0,0 +1,368 @@
+//! Export layers and flattened images as PNGs, and the composite image as a PNG or TIFF with
+//! the document's ICC profile and resolution embedded.
+//!
+//! Requires the `export` feature.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//!
+//! for layer in psd.layers() {
+//!     std::fs::write(format!("{}.png", layer.name()), psd::export::layer_to_png(layer)?)?;
+//! }
+//!
+//! // Keep working while the composite image is encoded and written
+//! let saving = psd::export::save_composite_in_background(Arc::new(psd), "print.tiff", ImageFormat::Tiff);
+//! saving.join().unwrap()?;
+//! ```
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::{Psd, PsdError, PsdLayer};
+
+/// The image formats that the composite image can be exported as
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ImageFormat {
+    /// An 8 bit RGBA PNG
+    Png,
+    /// An uncompressed 8 bit RGBA TIFF
+    Tiff,
+}
+
+/// Encode a layer as a PNG.
+///
+/// The PNG is the size of the entire PSD, with the layer's pixels at the layer's position.
+pub fn layer_to_png(layer: &PsdLayer) -> Result<Vec<u8>, PsdError> {
+    rgba_to_png(&layer.rgba(), layer.psd_width, layer.psd_height)
+}
+
+/// Flatten all of the visible layers in the PSD and encode the result as a PNG.
+pub fn flattened_to_png(psd: &Psd) -> Result<Vec<u8>, PsdError> {
+    let rgba = psd.flatten_layers_rgba(&|_| true)?;
+    let (width, height) = psd.oriented_size();
+
+    rgba_to_png(&rgba, width, height)
+}
+
+/// Encode RGBA pixels (`vec![R, G, B, A, R, G, B, A, ...]`) as a PNG.
+///
+/// Fails if `rgba` isn't `width * height * 4` bytes long, or if the PNG can't be encoded, such
+/// as when the width or height is 0.
+pub fn rgba_to_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, PsdError> {
+    check_rgba_len(rgba, width, height)?;
+
+    let mut png = vec![];
+
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    writer.write_image_data(rgba).map_err(encode_error)?;
+    writer.finish().map_err(encode_error)?;
+
+    Ok(png)
+}
+
+/// Encode the composite image (see [`Psd::rgba`]) as a PNG, embedding the PSD's ICC profile and
+/// resolution when it has them.
+pub fn composite_to_png(psd: &Psd) -> Result<Vec<u8>, PsdError> {
+    let rgba = psd.rgba();
+    let (width, height) = psd.oriented_size();
+    check_rgba_len(&rgba, width, height)?;
+
+    let mut png = vec![];
+
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    if let Some(resolution) = psd.resolution() {
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: pixels_per_meter(resolution.horizontal_dpi()),
+            yppu: pixels_per_meter(resolution.vertical_dpi()),
+            unit: png::Unit::Meter,
+        }));
+    }
+
+    let mut writer = encoder.write_header().map_err(encode_error)?;
+    if let Some(icc_profile) = psd.icc_profile() {
+        // The profile's name, a null separator, the compression method (0 is zlib) and the
+        // compressed profile
+        let mut iccp = b"ICC Profile\0\0".to_vec();
+        iccp.extend(zlib_stored(icc_profile));
+        writer
+            .write_chunk(png::chunk::ChunkType(*b"iCCP"), &iccp)
+            .map_err(encode_error)?;
+    }
+    writer.write_image_data(&rgba).map_err(encode_error)?;
+    writer.finish().map_err(encode_error)?;
+
+    Ok(png)
+}
+
+/// Encode the composite image (see [`Psd::rgba`]) as an uncompressed TIFF, embedding the PSD's
+/// ICC profile and resolution when it has them.
+pub fn composite_to_tiff(psd: &Psd) -> Vec<u8> {
+    let rgba = psd.rgba();
+    let (width, height) = psd.oriented_size();
+
+    // Entries have to be sorted by their tag
+    let mut entries = vec![
+        TiffEntry::long(TAG_IMAGE_WIDTH, width),
+        TiffEntry::long(TAG_IMAGE_LENGTH, height),
+        TiffEntry::shorts(TAG_BITS_PER_SAMPLE, &[8, 8, 8, 8]),
+        // Uncompressed
+        TiffEntry::short(TAG_COMPRESSION, 1),
+        // RGB
+        TiffEntry::short(TAG_PHOTOMETRIC_INTERPRETATION, 2),
+        TiffEntry {
+            tag: TAG_STRIP_OFFSETS,
+            kind: TIFF_LONG,
+            count: 1,
+            value: TiffValue::ImageData,
+        },
+        TiffEntry::short(TAG_SAMPLES_PER_PIXEL, 4),
+        TiffEntry::long(TAG_ROWS_PER_STRIP, height),
+        TiffEntry::long(TAG_STRIP_BYTE_COUNTS, rgba.len() as u32),
+    ];
+    if let Some(resolution) = psd.resolution() {
+        entries.push(TiffEntry::rational(
+            TAG_X_RESOLUTION,
+            resolution.horizontal_dpi(),
+        ));
+        entries.push(TiffEntry::rational(
+            TAG_Y_RESOLUTION,
+            resolution.vertical_dpi(),
+        ));
+    }
+    // The channels of each pixel are next to each other
+    entries.push(TiffEntry::short(TAG_PLANAR_CONFIGURATION, 1));
+    if psd.resolution().is_some() {
+        // Inches
+        entries.push(TiffEntry::short(TAG_RESOLUTION_UNIT, 2));
+    }
+    // Unassociated alpha
+    entries.push(TiffEntry::short(TAG_EXTRA_SAMPLES, 2));
+    if let Some(icc_profile) = psd.icc_profile() {
+        entries.push(TiffEntry {
+            tag: TAG_ICC_PROFILE,
+            kind: TIFF_UNDEFINED,
+            count: icc_profile.len() as u32,
+            value: TiffValue::Offset(icc_profile.to_vec()),
+        });
+    }
+
+    // The header and the image file directory are followed by the values that didn't fit in
+    // their entries, and then the pixels.
+    let values_start = 8 + 2 + entries.len() * 12 + 4;
+    let values_len: usize = entries
+        .iter()
+        .map(|entry| match &entry.value {
+            // Values start on a word boundary
+            TiffValue::Offset(bytes) => bytes.len() + bytes.len() % 2,
+            _ => 0,
+        })
+        .sum();
+    let image_data_start = (values_start + values_len) as u32;
+
+    // Little endian, with the image file directory right after the header
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+
+    let mut values = vec![];
+    tiff.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for entry in entries {
+        tiff.extend_from_slice(&entry.tag.to_le_bytes());
+        tiff.extend_from_slice(&entry.kind.to_le_bytes());
+        tiff.extend_from_slice(&entry.count.to_le_bytes());
+
+        let value = match entry.value {
+            TiffValue::Inline(value) => value,
+            TiffValue::ImageData => image_data_start.to_le_bytes(),
+            TiffValue::Offset(bytes) => {
+                let offset = (values_start + values.len()) as u32;
+                values.extend_from_slice(&bytes);
+                if bytes.len() % 2 == 1 {
+                    values.push(0);
+                }
+                offset.to_le_bytes()
+            }
+        };
+        tiff.extend_from_slice(&value);
+    }
+    // There is only one image file directory
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+
+    tiff.extend(values);
+    tiff.extend(rgba);
+
+    tiff
+}
+
+/// Encode the composite image in the given format.
+pub fn composite_to_image(psd: &Psd, format: ImageFormat) -> Result<Vec<u8>, PsdError> {
+    match format {
+        ImageFormat::Png => composite_to_png(psd),
+        ImageFormat::Tiff => Ok(composite_to_tiff(psd)),
+    }
+}
+
+/// Encode the composite image and write it to a file on another thread, so that the PSD can
+/// keep being worked with while large images are saved.
+///
+/// Join the returned handle to find out whether writing the file succeeded.
+pub fn save_composite_in_background(
+    psd: Arc<Psd>,
+    path: impl AsRef<Path>,
+    format: ImageFormat,
+) -> JoinHandle<io::Result<()>> {
+    let path = path.as_ref().to_path_buf();
+
+    std::thread::spawn(move || {
+        let image = composite_to_image(&psd, format)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        std::fs::write(path, image)
+    })
+}
+
+/// Make sure that there are four bytes for every pixel of the image
+fn check_rgba_len(rgba: &[u8], width: u32, height: u32) -> Result<(), PsdError> {
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4));
+    if expected != Some(rgba.len()) {
+        return Err(PsdError::InvalidBufferLength {
+            expected: expected.unwrap_or(usize::MAX),
+            actual: rgba.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn encode_error(error: png::EncodingError) -> PsdError {
+    PsdError::EncodeError(error.to_string())
+}
+
+const TIFF_SHORT: u16 = 3;
+const TIFF_LONG: u16 = 4;
+const TIFF_RATIONAL: u16 = 5;
+const TIFF_UNDEFINED: u16 = 7;
+
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_BITS_PER_SAMPLE: u16 = 258;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC_INTERPRETATION: u16 = 262;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_SAMPLES_PER_PIXEL: u16 = 277;
+const TAG_ROWS_PER_STRIP: u16 = 278;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_X_RESOLUTION: u16 = 282;
+const TAG_Y_RESOLUTION: u16 = 283;
+const TAG_PLANAR_CONFIGURATION: u16 = 284;
+const TAG_RESOLUTION_UNIT: u16 = 296;
+const TAG_EXTRA_SAMPLES: u16 = 338;
+const TAG_ICC_PROFILE: u16 = 34675;
+
+/// An entry of a TIFF image file directory
+struct TiffEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value: TiffValue,
+}
+
+/// The value of a TIFF directory entry
+enum TiffValue {
+    /// A value that fits in the entry's four bytes, padded with zeros
+    Inline([u8; 4]),
+    /// Bytes that are stored after the directory, with the entry pointing to them
+    Offset(Vec<u8>),
+    /// The position of the pixels
+    ImageData,
+}
+
+impl TiffEntry {
+    fn short(tag: u16, value: u16) -> TiffEntry {
+        TiffEntry::shorts(tag, &[value])
+    }
+
+    fn shorts(tag: u16, values: &[u16]) -> TiffEntry {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+
+        TiffEntry::with_bytes(tag, TIFF_SHORT, values.len() as u32, bytes)
+    }
+
+    fn long(tag: u16, value: u32) -> TiffEntry {
+        TiffEntry::with_bytes(tag, TIFF_LONG, 1, value.to_le_bytes().to_vec())
+    }
+
+    /// Photoshop stores resolutions as fixed 16.16 numbers, so a denominator of 65536 doesn't
+    /// lose any precision.
+    fn rational(tag: u16, value: f64) -> TiffEntry {
+        let mut bytes = ((value * 65536.).round() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&65536u32.to_le_bytes());
+
+        TiffEntry::with_bytes(tag, TIFF_RATIONAL, 1, bytes)
+    }
+
+    fn with_bytes(tag: u16, kind: u16, count: u32, bytes: Vec<u8>) -> TiffEntry {
+        let value = if bytes.len() <= 4 {
+            let mut inline = [0; 4];
+            inline[..bytes.len()].copy_from_slice(&bytes);
+            TiffValue::Inline(inline)
+        } else {
+            TiffValue::Offset(bytes)
+        };
+
+        TiffEntry {
+            tag,
+            kind,
+            count,
+            value,
+        }
+    }
+}
+
+fn pixels_per_meter(dpi: f64) -> u32 {
+    (dpi / 0.0254).round() as u32
+}
+
+/// Wrap bytes in a zlib stream without compressing them, using stored deflate blocks.
+fn zlib_stored(bytes: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+
+    let mut blocks = bytes.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+
+    zlib.extend_from_slice(&adler32(bytes).to_be_bytes());
+    zlib
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
@@ -0,0 +1,29 @@
+use crate::prelude::*;
+use crate::psd_channel::PsdChannelKind;
+
+/// An alpha or spot channel of the composite image, such as a saved selection, along with the
+/// name that it has in Photoshop's channels panel. See [`crate::Psd::extra_channels`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtraChannel {
+    pub(crate) index: usize,
+    pub(crate) name: Option<String>,
+}
+
+impl ExtraChannel {
+    /// The position of the channel among all of the composite image's channels, counting the
+    /// color and transparency channels
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The channel's name, such as "Glow Mask", or `None` if the PSD doesn't name it
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The kind of channel to pass to [`crate::Psd::composite_channel`] to get the channel's
+    /// pixels
+    pub fn kind(&self) -> PsdChannelKind {
+        PsdChannelKind::Spot(self.index as u16)
+    }
+}
@@ -0,0 +1,301 @@
+//! Read the export directives that artists put in layer and group names for Adobe Generator's
+//! "Image Assets", such as `icon.png, 200% icon@2x.png`.
+//!
+//! Generator reads these from the names themselves, so any layer or group can be tagged, and
+//! layers whose names aren't directives have no assets.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//!
+//! for layer in psd.layers() {
+//!     for asset in layer.generator_assets() {
+//!         println!("{} is exported as {}", layer.name(), asset.file_name);
+//!     }
+//! }
+//! ```
+
+use crate::prelude::*;
+
+/// One of the files that a layer or group's name asks for, such as `200% icon@2x.png`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetSpec {
+    /// The path of the file, including any folders and the extension, such as `icons/home.png`.
+    /// A quality suffix, such as the `80%` of `photo.jpg80%`, isn't included.
+    pub file_name: String,
+    /// The format of the file, from its extension
+    pub format: AssetFormat,
+    /// How big the file is compared to the layer
+    pub scale: AssetScale,
+}
+
+/// The format of an asset, along with the quality that was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetFormat {
+    /// `.png`, with the number of bits per pixel that was asked for (8, 24 or 32) if any
+    Png {
+        /// The bits per pixel, such as the 8 of `icon.png8`
+        bits: Option<u8>,
+    },
+    /// `.jpg` or `.jpeg`, with the quality that was asked for if any
+    Jpg {
+        /// The quality in percent. Both `photo.jpg80%` and `photo.jpg8` are 80.
+        quality: Option<u8>,
+    },
+    /// `.webp`, with the quality that was asked for if any
+    Webp {
+        /// The quality in percent, like [`AssetFormat::Jpg`]
+        quality: Option<u8>,
+    },
+    /// `.gif`
+    Gif,
+    /// `.svg`
+    Svg,
+}
+
+/// How big an asset is compared to its layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssetScale {
+    /// The same size as the layer
+    Original,
+    /// Scaled by a factor, such as 2 for `200% icon@2x.png`
+    Factor(f64),
+    /// Resized to a width and height, such as `64x64 icon.png`. A dimension is `None` when it is
+    /// `?`, such as `64x? icon.png`, which keeps the layer's aspect ratio.
+    Size {
+        /// The width of the asset
+        width: Option<AssetLength>,
+        /// The height of the asset
+        height: Option<AssetLength>,
+    },
+}
+
+/// A width or height of an asset, such as `64`, `2in` or `10mm`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssetLength {
+    /// The length in the unit
+    pub value: f64,
+    /// The unit of the length
+    pub unit: AssetUnit,
+}
+
+/// The unit of an [`AssetLength`]. Lengths without a unit are in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum AssetUnit {
+    Pixels,
+    Inches,
+    Centimeters,
+    Millimeters,
+}
+
+/// Read the assets that a layer or group's name asks for.
+///
+/// Directives are separated by commas or plus signs. Directives that Generator wouldn't export,
+/// such as a name without a known extension, are skipped.
+pub fn parse_asset_specs(name: &str) -> Vec<AssetSpec> {
+    name.split([',', '+'])
+        .filter_map(|directive| parse_asset_spec(directive.trim()))
+        .collect()
+}
+
+/// Read a single directive, such as `200% icon@2x.png`
+fn parse_asset_spec(directive: &str) -> Option<AssetSpec> {
+    // Without a scale, the whole directive is the file, which can have spaces in it
+    let (scale, file) = match directive
+        .split_once(' ')
+        .and_then(|(scale, file)| Some((parse_scale(scale)?, file.trim())))
+    {
+        Some((scale, file)) => (scale, file),
+        None => (AssetScale::Original, directive),
+    };
+
+    let (stem, extension) = file.rsplit_once('.')?;
+    if stem.is_empty() || stem.ends_with('/') {
+        return None;
+    }
+
+    let (format, suffix_len) = parse_extension(extension)?;
+    let file_name = file[..file.len() - suffix_len].to_string();
+
+    Some(AssetSpec {
+        file_name,
+        format,
+        scale,
+    })
+}
+
+/// The format of an extension along with the length of its quality suffix, such as `jpg80%`
+fn parse_extension(extension: &str) -> Option<(AssetFormat, usize)> {
+    let lowercase = extension.to_ascii_lowercase();
+    let format_len = lowercase
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(lowercase.len());
+    let (kind, suffix) = lowercase.split_at(format_len);
+
+    let format = match kind {
+        "png" => AssetFormat::Png {
+            bits: match suffix {
+                "" => None,
+                "8" | "24" | "32" => suffix.parse().ok(),
+                _ => return None,
+            },
+        },
+        "jpg" | "jpeg" => AssetFormat::Jpg {
+            quality: parse_quality(suffix)?,
+        },
+        "webp" => AssetFormat::Webp {
+            quality: parse_quality(suffix)?,
+        },
+        "gif" if suffix.is_empty() => AssetFormat::Gif,
+        "svg" if suffix.is_empty() => AssetFormat::Svg,
+        _ => return None,
+    };
+
+    Some((format, suffix.len()))
+}
+
+/// A quality suffix is either a percentage from 1% to 100% or a number from 1 to 10. Returns
+/// `None` if the suffix isn't a quality, or `Some(None)` if there is no suffix.
+fn parse_quality(suffix: &str) -> Option<Option<u8>> {
+    if suffix.is_empty() {
+        return Some(None);
+    }
+
+    let quality = match suffix.strip_suffix('%') {
+        Some(percent) => percent.parse::<u8>().ok()?,
+        None => suffix.parse::<u8>().ok().filter(|quality| *quality <= 10)? * 10,
+    };
+
+    (1..=100).contains(&quality).then_some(Some(quality))
+}
+
+/// A scale is either a percentage, such as `200%`, or a size, such as `64x64` or `2inx?`
+fn parse_scale(scale: &str) -> Option<AssetScale> {
+    if let Some(percent) = scale.strip_suffix('%') {
+        let percent = percent
+            .parse::<f64>()
+            .ok()
+            .filter(|percent| *percent > 0.)?;
+        return Some(AssetScale::Factor(percent / 100.));
+    }
+
+    let (width, height) = scale.split_once('x')?;
+    let (width, height) = (parse_length(width)?, parse_length(height)?);
+    if width.is_none() && height.is_none() {
+        return None;
+    }
+
+    Some(AssetScale::Size { width, height })
+}
+
+/// A length with an optional unit, or `?`. Returns `Some(None)` for `?`.
+fn parse_length(length: &str) -> Option<Option<AssetLength>> {
+    if length == "?" {
+        return Some(None);
+    }
+
+    let units = [
+        ("px", AssetUnit::Pixels),
+        ("in", AssetUnit::Inches),
+        ("cm", AssetUnit::Centimeters),
+        ("mm", AssetUnit::Millimeters),
+    ];
+    let (value, unit) = units
+        .iter()
+        .find_map(|(suffix, unit)| Some((length.strip_suffix(suffix)?, *unit)))
+        .unwrap_or((length, AssetUnit::Pixels));
+    let value = value.parse::<f64>().ok().filter(|value| *value > 0.)?;
+
+    Some(Some(AssetLength { value, unit }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(file_name: &str, scale: AssetScale) -> AssetSpec {
+        AssetSpec {
+            file_name: file_name.to_string(),
+            format: AssetFormat::Png { bits: None },
+            scale,
+        }
+    }
+
+    /// A name can ask for the same layer at several scales.
+    #[test]
+    fn scaled_assets() {
+        assert_eq!(
+            parse_asset_specs("icon.png, 200% icon@2x.png"),
+            vec![
+                png("icon.png", AssetScale::Original),
+                png("icon@2x.png", AssetScale::Factor(2.)),
+            ]
+        );
+
+        assert_eq!(
+            parse_asset_specs("64x? icons/home page.png + 2inx10mm print.png"),
+            vec![
+                png(
+                    "icons/home page.png",
+                    AssetScale::Size {
+                        width: Some(AssetLength {
+                            value: 64.,
+                            unit: AssetUnit::Pixels
+                        }),
+                        height: None,
+                    }
+                ),
+                png(
+                    "print.png",
+                    AssetScale::Size {
+                        width: Some(AssetLength {
+                            value: 2.,
+                            unit: AssetUnit::Inches
+                        }),
+                        height: Some(AssetLength {
+                            value: 10.,
+                            unit: AssetUnit::Millimeters
+                        }),
+                    }
+                ),
+            ]
+        );
+    }
+
+    /// Quality suffixes are read and left out of the file name.
+    #[test]
+    fn quality_suffixes() {
+        let formats: Vec<(String, AssetFormat)> =
+            parse_asset_specs("a.jpg80%, b.jpeg8, c.png8, d.webp, e.GIF, f.svg")
+                .into_iter()
+                .map(|asset| (asset.file_name, asset.format))
+                .collect();
+
+        assert_eq!(
+            formats,
+            vec![
+                ("a.jpg".to_string(), AssetFormat::Jpg { quality: Some(80) }),
+                ("b.jpeg".to_string(), AssetFormat::Jpg { quality: Some(80) }),
+                ("c.png".to_string(), AssetFormat::Png { bits: Some(8) }),
+                ("d.webp".to_string(), AssetFormat::Webp { quality: None }),
+                ("e.GIF".to_string(), AssetFormat::Gif),
+                ("f.svg".to_string(), AssetFormat::Svg),
+            ]
+        );
+    }
+
+    /// Names that aren't directives don't ask for any assets.
+    #[test]
+    fn not_directives() {
+        for name in [
+            "Background",
+            "Layer 1 copy",
+            "v1.2",
+            "a.png16",
+            "a.jpg11",
+            ".png",
+        ] {
+            assert_eq!(parse_asset_specs(name), vec![], "{}", name);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Points and rectangles in the PSD's pixel coordinates, where (0, 0) is the top left pixel of
+//! the canvas.
+//!
+//! Layers and masks can extend past the canvas, so coordinates can be negative or larger than
+//! the PSD's width and height.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//! let layer = psd.layer_by_name("Logo").unwrap();
+//!
+//! // The part of the layer that is on the canvas
+//! let visible = layer.placement().and_then(|placement| placement.visible_canvas_rect());
+//! ```
+
+/// A position in pixels
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Point {
+    /// The column, increasing to the right
+    pub x: i32,
+    /// The row, increasing downwards
+    pub y: i32,
+}
+
+impl Point {
+    /// Create a point at the given column and row
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    /// Convert a point that is relative to `origin`, such as a position within a layer whose
+    /// top left pixel is at `origin`, into the PSD's coordinates.
+    pub fn to_canvas_space(self, origin: Point) -> Point {
+        Point {
+            x: origin.x + self.x,
+            y: origin.y + self.y,
+        }
+    }
+}
+
+/// A rectangle of pixels.
+///
+/// `right` and `bottom` are inclusive, so a rectangle whose `left` and `right` are both 0 is one
+/// pixel wide.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Rect {
+    /// The position of the top row of pixels
+    pub top: i32,
+    /// The position of the left column of pixels
+    pub left: i32,
+    /// The position of the bottom row of pixels
+    pub bottom: i32,
+    /// The position of the right column of pixels
+    pub right: i32,
+}
+
+impl Rect {
+    /// The rectangle of `width` by `height` pixels whose top left pixel is at `origin`.
+    ///
+    /// Returns `None` if the width or height is 0.
+    pub fn from_origin_and_size(origin: Point, width: u32, height: u32) -> Option<Rect> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        Some(Rect {
+            top: origin.y,
+            left: origin.x,
+            bottom: origin.y + height as i32 - 1,
+            right: origin.x + width as i32 - 1,
+        })
+    }
+
+    /// The number of columns of pixels within the rectangle
+    pub fn width(&self) -> u32 {
+        (self.right - self.left + 1) as u32
+    }
+
+    /// The number of rows of pixels within the rectangle
+    pub fn height(&self) -> u32 {
+        (self.bottom - self.top + 1) as u32
+    }
+
+    /// The top left pixel of the rectangle
+    pub fn origin(&self) -> Point {
+        Point::new(self.left, self.top)
+    }
+
+    /// Whether the pixel is within the rectangle
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.left
+            && point.x <= self.right
+            && point.y >= self.top
+            && point.y <= self.bottom
+    }
+
+    /// The pixels that are within both rectangles, or `None` if they don't overlap
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let intersection = Rect {
+            top: self.top.max(other.top),
+            left: self.left.max(other.left),
+            bottom: self.bottom.min(other.bottom),
+            right: self.right.min(other.right),
+        };
+
+        if intersection.left > intersection.right || intersection.top > intersection.bottom {
+            return None;
+        }
+
+        Some(intersection)
+    }
+
+    /// The smallest rectangle that covers both rectangles
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            top: self.top.min(other.top),
+            left: self.left.min(other.left),
+            bottom: self.bottom.max(other.bottom),
+            right: self.right.max(other.right),
+        }
+    }
+
+    /// Convert a rectangle that is relative to `origin` into the PSD's coordinates, see
+    /// [`Point::to_canvas_space`].
+    pub fn to_canvas_space(&self, origin: Point) -> Rect {
+        Rect {
+            top: origin.y + self.top,
+            left: origin.x + self.left,
+            bottom: origin.y + self.bottom,
+            right: origin.x + self.right,
+        }
+    }
+}
+
+/// Copy the pixels within the rectangle out of RGBA pixels that are `width` pixels wide, into
+/// a buffer of `rect.width() * rect.height() * 4` bytes. The rectangle must be within the pixels.
+pub(crate) fn crop_rgba(rgba: &[u8], width: u32, rect: Rect, cropped: &mut [u8]) {
+    let row_len = width as usize * 4;
+    let columns = rect.left as usize * 4..(rect.right as usize + 1) * 4;
+    let rows = rect.top as usize..rect.bottom as usize + 1;
+
+    for (top, cropped_row) in rows.zip(cropped.chunks_exact_mut(columns.len())) {
+        let row = &rgba[top * row_len..(top + 1) * row_len];
+        cropped_row.copy_from_slice(&row[columns.clone()]);
+    }
+}
+
+/// Where a layer's pixels are, both on the PSD's canvas and within the layer itself, along with
+/// the part of them that is on the canvas. See [`crate::PsdLayer::placement`].
+///
+/// Like [`Rect`], the `right` and `bottom` of every rectangle are inclusive.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct LayerPlacement {
+    pub(crate) canvas_rect: Rect,
+    pub(crate) layer_rect: Rect,
+    pub(crate) visible_canvas_rect: Option<Rect>,
+    pub(crate) visible_layer_rect: Option<Rect>,
+}
+
+impl LayerPlacement {
+    /// Place a layer whose pixels cover the rectangle of the canvas, on a canvas of the size.
+    pub(crate) fn new(canvas_rect: Rect, canvas_width: u32, canvas_height: u32) -> LayerPlacement {
+        let origin = canvas_rect.origin();
+        let to_layer_space = |rect: Rect| Rect {
+            top: rect.top - origin.y,
+            left: rect.left - origin.x,
+            bottom: rect.bottom - origin.y,
+            right: rect.right - origin.x,
+        };
+
+        let visible_canvas_rect =
+            Rect::from_origin_and_size(Point::new(0, 0), canvas_width, canvas_height)
+                .and_then(|canvas| canvas_rect.intersect(&canvas));
+
+        LayerPlacement {
+            canvas_rect,
+            layer_rect: to_layer_space(canvas_rect),
+            visible_canvas_rect,
+            visible_layer_rect: visible_canvas_rect.map(to_layer_space),
+        }
+    }
+
+    /// The layer's pixels in the PSD's coordinates. Can extend past the canvas.
+    pub fn canvas_rect(&self) -> Rect {
+        self.canvas_rect
+    }
+
+    /// The layer's pixels relative to its top left pixel, so `top` and `left` are 0
+    pub fn layer_rect(&self) -> Rect {
+        self.layer_rect
+    }
+
+    /// The part of the layer that is on the canvas, in the PSD's coordinates, or `None` if the
+    /// layer is entirely off of the canvas
+    pub fn visible_canvas_rect(&self) -> Option<Rect> {
+        self.visible_canvas_rect
+    }
+
+    /// The part of the layer that is on the canvas, relative to the layer's top left pixel, or
+    /// `None` if the layer is entirely off of the canvas
+    pub fn visible_layer_rect(&self) -> Option<Rect> {
+        self.visible_layer_rect
+    }
+
+    /// The position within the layer of a point in the PSD's coordinates, or `None` if the
+    /// layer doesn't cover it. The point can be off of the canvas.
+    pub fn canvas_to_layer(&self, point: Point) -> Option<Point> {
+        if !self.canvas_rect.contains(point) {
+            return None;
+        }
+
+        Some(Point::new(
+            point.x - self.canvas_rect.left,
+            point.y - self.canvas_rect.top,
+        ))
+    }
+}
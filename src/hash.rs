@@ -0,0 +1,182 @@
+//! [xxHash64](https://github.com/Cyan4973/xxHash), which we use to hash pixels since, unlike
+//! the standard library's hasher, its hashes are the same on every platform and every version
+//! of the crate. That lets them be stored and compared across runs.
+
+use core::hash::Hasher;
+
+const PRIME_1: u64 = 11400714785074694791;
+const PRIME_2: u64 = 14029467366897019727;
+const PRIME_3: u64 = 1609587929392839161;
+const PRIME_4: u64 = 9650029242287828579;
+const PRIME_5: u64 = 2870177450012600261;
+
+/// Hashes bytes in stripes of 32 bytes as they're written, so the bytes never need to be joined
+/// together.
+pub(crate) struct XxHash64 {
+    seed: u64,
+    accumulators: [u64; 4],
+    /// The bytes that didn't fill a stripe yet
+    buffer: [u8; 32],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl XxHash64 {
+    pub(crate) fn with_seed(seed: u64) -> XxHash64 {
+        XxHash64 {
+            seed,
+            accumulators: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            buffer: [0; 32],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn process_stripe(&mut self, stripe: &[u8]) {
+        for (accumulator, lane) in self.accumulators.iter_mut().zip(stripe.chunks_exact(8)) {
+            *accumulator = round(*accumulator, read_u64(lane));
+        }
+    }
+}
+
+impl Hasher for XxHash64 {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+
+        // Fill up the stripe that was started by an earlier write
+        if self.buffer_len > 0 {
+            let len = bytes.len().min(32 - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + len].copy_from_slice(&bytes[..len]);
+            self.buffer_len += len;
+            bytes = &bytes[len..];
+
+            if self.buffer_len < 32 {
+                return;
+            }
+            let stripe = self.buffer;
+            self.process_stripe(&stripe);
+            self.buffer_len = 0;
+        }
+
+        let mut stripes = bytes.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.process_stripe(stripe);
+        }
+
+        let remainder = stripes.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffer_len = remainder.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = if self.total_len >= 32 {
+            let [v1, v2, v3, v4] = self.accumulators;
+            let mut hash = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for accumulator in self.accumulators.iter() {
+                hash = merge_round(hash, *accumulator);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut remainder = &self.buffer[..self.buffer_len];
+        while remainder.len() >= 8 {
+            hash ^= round(0, read_u64(remainder));
+            hash = hash
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            remainder = &remainder[8..];
+        }
+        if remainder.len() >= 4 {
+            let lane = u32::from_le_bytes([remainder[0], remainder[1], remainder[2], remainder[3]]);
+            hash ^= (lane as u64).wrapping_mul(PRIME_1);
+            hash = hash
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            remainder = &remainder[4..];
+        }
+        for byte in remainder {
+            hash ^= (*byte as u64).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        // Avalanche
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^= hash >> 32;
+
+        hash
+    }
+}
+
+fn round(accumulator: u64, lane: u64) -> u64 {
+    accumulator
+        .wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn merge_round(hash: u64, accumulator: u64) -> u64 {
+    (hash ^ round(0, accumulator))
+        .wrapping_mul(PRIME_1)
+        .wrapping_add(PRIME_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut lane = [0; 8];
+    lane.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(lane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    fn xxh64(bytes: &[u8]) -> u64 {
+        let mut hasher = XxHash64::with_seed(0);
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    // The reference hashes from the xxHash implementation
+    #[test]
+    fn reference_hashes() {
+        assert_eq!(xxh64(b""), 0xef46db3751d8e999);
+        assert_eq!(xxh64(b"a"), 0xd24ec4f1a98c6e5b);
+        assert_eq!(xxh64(b"abc"), 0x44bc2cf5ad770999);
+        assert_eq!(
+            xxh64(b"Nobody inspects the spammish repetition"),
+            0xfbcea83c8a378bf1
+        );
+    }
+
+    // Splitting the bytes across writes doesn't change the hash
+    #[test]
+    fn hash_across_writes() {
+        let bytes: Vec<u8> = (0..100).collect();
+        assert_eq!(xxh64(&bytes), 0x6ac1e58032166597);
+
+        for split in [0, 1, 7, 31, 32, 33, 64, 99, 100] {
+            let mut hasher = XxHash64::with_seed(0);
+            hasher.write(&bytes[..split]);
+            hasher.write(&bytes[split..]);
+            assert_eq!(hasher.finish(), 0x6ac1e58032166597);
+        }
+    }
+}
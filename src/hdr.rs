@@ -0,0 +1,64 @@
+//! Convert the floats of 32 bit (HDR) PSDs into 8 bit RGBA.
+//!
+//! 32 bit PSDs store linear light, where 1.0 is white but highlights can be much brighter.
+//! Tone mapping decides how those values are squeezed into 0 to 255.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//!
+//! let options = ToneMapOptions {
+//!     exposure: 0.5,
+//!     tone_mapping: ToneMapping::Reinhard,
+//! };
+//! let rgba = psd.rgba_tone_mapped(&options);
+//! ```
+
+use crate::blend::linear_to_srgb;
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+/// How colors brighter than white are brought into range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Colors brighter than white become white
+    Clamp,
+    /// `color / (1 + color)`, which keeps the detail in highlights at the cost of darkening
+    /// the rest of the image
+    Reinhard,
+}
+
+/// Configures how 32 bit PSDs are converted into 8 bit RGBA
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapOptions {
+    /// Every color is multiplied by this before tone mapping. Defaults to 1.0.
+    pub exposure: f32,
+    /// Defaults to [`ToneMapping::Clamp`].
+    pub tone_mapping: ToneMapping,
+}
+
+impl Default for ToneMapOptions {
+    fn default() -> Self {
+        ToneMapOptions {
+            exposure: 1.,
+            tone_mapping: ToneMapping::Clamp,
+        }
+    }
+}
+
+/// Tone map linear RGBA floats into sRGB bytes. The transparency is clamped, but otherwise
+/// left as is.
+pub(crate) fn tone_map_into(rgba: &mut [u8], rgba32f: &[f32], options: &ToneMapOptions) {
+    for (pixel, floats) in rgba.chunks_exact_mut(4).zip(rgba32f.chunks_exact(4)) {
+        for channel in 0..3 {
+            let color = floats[channel] * options.exposure;
+            let color = match options.tone_mapping {
+                ToneMapping::Clamp => color,
+                ToneMapping::Reinhard => color / (1. + color.max(0.)),
+            };
+
+            pixel[channel] = (linear_to_srgb(color.clamp(0., 1.)) * 255.).round() as u8;
+        }
+
+        pixel[3] = (floats[3].clamp(0., 1.) * 255.).round() as u8;
+    }
+}
@@ -4,12 +4,27 @@
 //! This will help you better understand the current approach and discover ways to improve it.
 //!
 //! psd spec: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
+//!
+//! ## no_std
+//!
+//! The crate only needs `alloc`. Disable the default `std` feature and enable the `libm`
+//! feature (used for the floating point math in blending and fill layers) to use it in `no_std`
+//! environments. The `parallel` and `export` features require `std`.
+//!
+//! Without `std`, [`Psd::groups`] returns a `BTreeMap` instead of a `HashMap`, and [`Psd`] is
+//! not `Sync`.
 
 #![deny(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(all(not(feature = "std"), not(feature = "libm")))]
+compile_error!("Building without the `std` feature requires the `libm` feature.");
 
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::ops::Deref;
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use core::hash::Hasher;
+use core::ops::{Deref, Range};
 
 use thiserror::Error;
 
@@ -18,24 +33,111 @@ use sections::image_data_section::ImageDataSectionError;
 use sections::image_resources_section::ImageResourcesSectionError;
 use sections::layer_and_mask_information_section::layer::PsdLayerError;
 
+pub use crate::blend::{blend_rgba, blend_rgba_linear};
+use crate::capability::CapabilityReport;
+use crate::color::DominantColor;
+pub use crate::extra_channel::ExtraChannel;
+use crate::geometry::{crop_rgba, Rect};
+use crate::hash::XxHash64;
+use crate::hdr::{tone_map_into, ToneMapOptions};
+use crate::options::channel_byte_count;
+pub use crate::options::{PsdLimitError, PsdOptions};
+pub use crate::pixel_format::PixelFormat;
+use crate::prelude::*;
+use crate::psd_channel::rle_decompress;
 use crate::psd_channel::IntoRgba;
 pub use crate::psd_channel::{PsdChannelCompression, PsdChannelKind};
 pub use crate::sections::file_header_section::{ColorMode, PsdDepth};
 use crate::sections::image_data_section::ChannelBytes;
 use crate::sections::image_data_section::ImageDataSection;
+pub use crate::sections::image_resources_section::ExifOrientation;
 pub use crate::sections::image_resources_section::ImageResource;
 use crate::sections::image_resources_section::ImageResourcesSection;
+pub use crate::sections::image_resources_section::UrlListEntry;
+pub use crate::sections::image_resources_section::{BezierKnot, PathPoint, PsdPath, Subpath};
+pub use crate::sections::image_resources_section::{ColorSampler, ColorSpace};
 pub use crate::sections::image_resources_section::{DescriptorField, UnitFloatStructure};
+pub use crate::sections::image_resources_section::{ResolutionInfo, ResolutionUnit};
+pub use crate::sections::layer_and_mask_information_section::animation::{
+    Keyframe, KeyframeInterpolation, KeyframeValue, LayerFrameState, TimelineTrack,
+    TimelineTrackKind,
+};
+pub use crate::sections::layer_and_mask_information_section::annotation::{
+    Annotation, AnnotationKind,
+};
+use crate::sections::layer_and_mask_information_section::effects::DEFAULT_GLOBAL_LIGHT_ANGLE;
+pub use crate::sections::layer_and_mask_information_section::effects::{
+    ColorOverlay, DropShadow, LayerEffects, StrokeEffect,
+};
+pub use crate::sections::layer_and_mask_information_section::fill::{
+    FillLayer, GradientColorStop, GradientFill, GradientKind, GradientTransparencyStop, PatternFill,
+};
+pub use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+pub use crate::sections::layer_and_mask_information_section::layer::Knockout;
+pub use crate::sections::layer_and_mask_information_section::layer::LayerBounds;
+pub use crate::sections::layer_and_mask_information_section::layer::ParseBlendModeError;
 pub use crate::sections::layer_and_mask_information_section::layer::PsdGroup;
 pub use crate::sections::layer_and_mask_information_section::layer::PsdLayer;
+pub use crate::sections::layer_and_mask_information_section::linked_file::{
+    LinkedFile, LinkedFileKind,
+};
+pub use crate::sections::layer_and_mask_information_section::mask::LayerMask;
+pub use crate::sections::layer_and_mask_information_section::metadata::LayerMetadata;
+pub use crate::sections::layer_and_mask_information_section::pattern::Pattern;
+pub use crate::sections::layer_and_mask_information_section::text::{
+    TextLayer, TextWarp, WarpStyle,
+};
+pub use crate::sections::layer_and_mask_information_section::vector_mask::VectorMask;
+pub use crate::sections::layer_and_mask_information_section::vector_stroke::{
+    LineCap, LineJoin, StrokeAlignment, VectorStroke,
+};
 use crate::sections::layer_and_mask_information_section::LayerAndMaskInformationSection;
 use crate::sections::MajorSections;
+pub use crate::sections::SectionLayout;
+use crate::shared_bytes::SharedBytes;
+use crate::validate::ValidationReport;
+pub use crate::warning::PsdWarning;
 
 use self::sections::file_header_section::FileHeaderSection;
 
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "async")]
+mod async_reader;
+pub mod batch;
 mod blend;
+pub mod capability;
+pub mod color;
+pub mod compose;
+pub mod compositor;
+pub mod diff;
+#[cfg(feature = "export")]
+pub mod export;
+mod extra_channel;
+pub mod generator;
+pub mod geometry;
+mod hash;
+pub mod hdr;
+#[cfg(not(feature = "std"))]
+mod math;
+mod options;
+pub mod patch;
+mod pixel_format;
+mod prelude;
 mod psd_channel;
+#[cfg(feature = "quantize")]
+pub mod quantize;
 mod sections;
+mod shared_bytes;
+pub mod spritesheet;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+pub mod validate;
+mod warning;
+#[cfg(feature = "parallel")]
+mod worker_pool;
+pub mod writer;
 
 /// An list of errors returned when processing PSD file.
 ///
@@ -55,6 +157,32 @@ pub enum PsdError {
     /// Failed to parse PSD resource section
     #[error("Failed to parse PSD resource section: '{0}'.")]
     ResourceError(ImageResourcesSectionError),
+    /// The PSD is larger than the limits in its [`PsdOptions`]
+    #[error("PSD exceeds a limit: '{0}'.")]
+    LimitError(PsdLimitError),
+    /// A buffer for the PSD's RGBA pixels isn't `width * height * 4` bytes long
+    #[error(
+        "Expected a buffer of {expected} bytes for the RGBA pixels, but it was {actual} bytes."
+    )]
+    InvalidBufferLength {
+        /// Four bytes for every pixel of the PSD
+        expected: usize,
+        /// The length of the buffer that was given
+        actual: usize,
+    },
+    /// Failed to encode an image
+    #[cfg(feature = "export")]
+    #[error("Failed to encode image: '{0}'.")]
+    EncodeError(String),
+    /// Failed to read the PSD from an asynchronous reader
+    #[cfg(feature = "async")]
+    #[error("Failed to read PSD: '{message}'.")]
+    ReadError {
+        /// The kind of the reader's error
+        kind: std::io::ErrorKind,
+        /// The reader's error, which is kept as a message since `io::Error` can't be compared
+        message: String,
+    },
 }
 
 /// Represents the contents of a PSD file
@@ -63,12 +191,30 @@ pub enum PsdError {
 ///
 /// We do not currently support PSB since the original authors didn't need it, but adding
 /// support should be trivial. If you'd like to support PSB please open an issue.
+///
+/// ## Threads
+///
+/// A `Psd` is `Send` and `Sync`, and none of its methods that take `&self` keep any state
+/// between calls other than caches, so a shared `&Psd` (or an `Arc<Psd>`) can be flattened from
+/// multiple threads at once. Without the `std` feature it is only `Send`.
 #[derive(Debug)]
 pub struct Psd {
     file_header_section: FileHeaderSection,
     image_resources_section: ImageResourcesSection,
     layer_and_mask_information_section: LayerAndMaskInformationSection,
     image_data_section: ImageDataSection,
+    warnings: Vec<PsdWarning>,
+    /// Where the major sections were in the PSD file
+    section_layout: SectionLayout,
+    /// Whether fully transparent pixels are returned as `[0, 0, 0, 0]`, see
+    /// [`PsdOptions::clear_transparent_pixels`]
+    clear_transparent_pixels: bool,
+    /// Whether the composite image and the flattened layers are rotated or flipped by the EXIF
+    /// orientation, see [`PsdOptions::apply_exif_orientation`]
+    apply_exif_orientation: bool,
+    /// The most bytes that flattening the layers may decode them into, see
+    /// [`PsdOptions::max_decoded_bytes`]
+    max_decoded_bytes: u64,
 }
 
 impl Psd {
@@ -84,40 +230,263 @@ impl Psd {
     /// let psd = Psd::from_bytes(psd_bytes);
     /// ```
     pub fn from_bytes(bytes: &[u8]) -> Result<Psd, PsdError> {
+        Psd::from_bytes_with_options(bytes, &PsdOptions::default())
+    }
+
+    /// Create a Psd from a byte slice, rejecting PSDs that are larger than the limits in the
+    /// given options.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let options = PsdOptions {
+    ///     max_decoded_bytes: 256 * 1024 * 1024,
+    ///     ..PsdOptions::default()
+    /// };
+    ///
+    /// let psd = Psd::from_bytes_with_options(psd_bytes, &options);
+    /// ```
+    pub fn from_bytes_with_options(bytes: &[u8], options: &PsdOptions) -> Result<Psd, PsdError> {
         let major_sections = MajorSections::from_bytes(bytes).map_err(PsdError::HeaderError)?;
 
+        Psd::from_major_sections(&major_sections, options)
+    }
+
+    /// Create a Psd from the bytes of a PSD file that it takes ownership of.
+    ///
+    /// Unlike [`Psd::from_bytes`], the channels of the layers and of the composite image point
+    /// into `bytes` instead of being copied out of them, so reading a large PSD doesn't need
+    /// twice its size in memory. The bytes are freed along with the Psd.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let psd_bytes = std::fs::read("./my-psd-file.psd")?;
+    ///
+    /// let psd = Psd::from_vec(psd_bytes);
+    /// ```
+    pub fn from_vec(bytes: Vec<u8>) -> Result<Psd, PsdError> {
+        Psd::from_vec_with_options(bytes, &PsdOptions::default())
+    }
+
+    /// Like [`Psd::from_vec`], rejecting PSDs that are larger than the limits in the given
+    /// options.
+    pub fn from_vec_with_options(bytes: Vec<u8>, options: &PsdOptions) -> Result<Psd, PsdError> {
+        let bytes = SharedBytes::new(bytes);
+        let major_sections = MajorSections::from_shared(&bytes).map_err(PsdError::HeaderError)?;
+
+        Psd::from_major_sections(&major_sections, options)
+    }
+
+    /// Like [`Psd::from_bytes_with_options`], but also returns what was left out of the PSD
+    /// while parsing it.
+    ///
+    /// Set [`PsdOptions::recover`] to skip malformed layers and resources instead of failing.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let options = PsdOptions {
+    ///     recover: true,
+    ///     ..PsdOptions::default()
+    /// };
+    ///
+    /// let (psd, warnings) = Psd::from_bytes_with_warnings(psd_bytes, &options)?;
+    /// for warning in warnings {
+    ///     println!("{}", warning);
+    /// }
+    /// ```
+    pub fn from_bytes_with_warnings(
+        bytes: &[u8],
+        options: &PsdOptions,
+    ) -> Result<(Psd, Vec<PsdWarning>), PsdError> {
+        let psd = Psd::from_bytes_with_options(bytes, options)?;
+        let warnings = psd.warnings.clone();
+
+        Ok((psd, warnings))
+    }
+
+    /// Check that the bytes of a PSD are consistent with the file format's specification, such
+    /// as section lengths that match their sections and channel data that is as long as the
+    /// layer's bounds need. See [`validate`] for the problems that are found.
+    ///
+    /// Unlike parsing, every problem is reported instead of stopping at the first one, and
+    /// problems that parsing works around are reported too.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let report = Psd::validate(psd_bytes);
+    /// if !report.is_valid() {
+    ///     println!("{:?}", report.issues());
+    /// }
+    /// ```
+    pub fn validate(bytes: &[u8]) -> ValidationReport {
+        validate::validate(bytes)
+    }
+
+    /// What was left out of the PSD while parsing it, such as image resources and additional
+    /// layer information that we don't parse yet, or channels with unsupported compression.
+    ///
+    /// These are the same warnings that [`Psd::from_bytes_with_warnings`] returns.
+    pub fn warnings(&self) -> &[PsdWarning] {
+        &self.warnings
+    }
+
+    /// What couldn't be fully interpreted in this PSD, such as adjustment layers, effects that
+    /// aren't rendered and channels with unsupported compression, so that applications can warn
+    /// that a preview might differ from Photoshop.
+    ///
+    /// ```ignore
+    /// let report = psd.capability_report();
+    /// if !report.is_fully_supported() {
+    ///     for gap in report.gaps() {
+    ///         println!("{}", gap);
+    ///     }
+    /// }
+    /// ```
+    pub fn capability_report(&self) -> CapabilityReport {
+        CapabilityReport::new(self)
+    }
+
+    /// Where the major sections of the PSD, its resource blocks and the records and channels of
+    /// each of its layers are within the file that the PSD was read from.
+    ///
+    /// The layers are in the same order as [`Psd::layers`], so they follow layers that have been
+    /// moved.
+    pub fn section_layout(&self) -> SectionLayout {
+        let resources_start = self.section_layout.image_resources.start;
+        let layer_and_mask_start = self.section_layout.layer_and_mask.start;
+
+        SectionLayout {
+            resources: self
+                .image_resources_section
+                .block_ranges
+                .iter()
+                .map(|(id, range)| {
+                    (
+                        *id,
+                        resources_start + range.start..resources_start + range.end,
+                    )
+                })
+                .collect(),
+            layer_records: self
+                .layers()
+                .iter()
+                .map(|layer| layer.record_range(layer_and_mask_start))
+                .collect(),
+            layer_channels: self
+                .layers()
+                .iter()
+                .map(|layer| layer.channel_data_ranges(layer_and_mask_start))
+                .collect(),
+            ..self.section_layout.clone()
+        }
+    }
+
+    /// Parse the major sections of a PSD, which could come from a byte slice or be read from
+    /// a reader one section after the other.
+    fn from_major_sections(
+        major_sections: &MajorSections,
+        options: &PsdOptions,
+    ) -> Result<Psd, PsdError> {
+        let mut warnings = vec![];
+
         let file_header_section = FileHeaderSection::from_bytes(major_sections.file_header)
             .map_err(PsdError::HeaderError)?;
 
         let psd_width = file_header_section.width.0;
         let psd_height = file_header_section.height.0;
         let channel_count = file_header_section.channel_count.count();
+        let depth = file_header_section.depth;
+
+        span!(DEBUG, "psd", width = psd_width, height = psd_height);
+
+        options
+            .check_canvas(psd_width, psd_height)
+            .map_err(PsdError::LimitError)?;
+        options
+            .check_layer_count(LayerAndMaskInformationSection::layer_count(
+                &major_sections.layer_and_mask,
+            ))
+            .map_err(PsdError::LimitError)?;
 
-        let layer_and_mask_information_section = LayerAndMaskInformationSection::from_bytes(
-            major_sections.layer_and_mask,
+        let mut layer_and_mask_information_section = LayerAndMaskInformationSection::from_bytes(
+            &major_sections.layer_and_mask,
             psd_width,
             psd_height,
+            file_header_section.version,
+            options.recover,
+            options.max_decoded_bytes,
+            &mut warnings,
         )
-        .map_err(PsdError::LayerError)?;
+        .map_err(|error| match error {
+            PsdLayerError::LimitError(error) => PsdError::LimitError(error),
+            error => PsdError::LayerError(error),
+        })?;
+        layer_and_mask_information_section.set_layer_rgba_caching(options.cache_layer_rgba);
+        layer_and_mask_information_section
+            .set_clear_transparent_pixels(options.clear_transparent_pixels);
+        layer_and_mask_information_section
+            .set_vector_mask_rasterization(options.rasterize_vector_masks);
+
+        // Make sure that the composite image and the layers won't decompress to more than the
+        // limit before we decode any of them. The patterns were already decoded. The layers are
+        // interleaved into RGBA lazily, so those buffers are checked when they are flattened.
+        let mut decoded_bytes = channel_byte_count(psd_width as u64, psd_height as u64, depth)
+            .and_then(|bytes| bytes.checked_mul(channel_count as u64));
+        for layer in layer_and_mask_information_section.layers.iter() {
+            decoded_bytes = decoded_bytes
+                .zip(layer.decoded_byte_count(depth))
+                .and_then(|(total, bytes)| total.checked_add(bytes));
+        }
+        for pattern in layer_and_mask_information_section.patterns.iter() {
+            decoded_bytes =
+                decoded_bytes.and_then(|total| total.checked_add(pattern.rgba.len() as u64));
+        }
+        let decoded_bytes = decoded_bytes
+            .ok_or(PsdLimitError::SizeOverflow)
+            .map_err(PsdError::LimitError)?;
+        options
+            .check_decoded_bytes(decoded_bytes)
+            .map_err(PsdError::LimitError)?;
 
         let image_data_section = ImageDataSection::from_bytes(
-            major_sections.image_data,
+            &major_sections.image_data,
             file_header_section.depth,
+            psd_width,
             psd_height,
             channel_count,
+            file_header_section.version,
+            &mut warnings,
         )
         .map_err(PsdError::ImageError)?;
 
-        let image_resources_section =
-            ImageResourcesSection::from_bytes(major_sections.image_resources)
-                .map_err(PsdError::ResourceError)?;
+        let image_resources_section = ImageResourcesSection::from_bytes(
+            major_sections.image_resources,
+            options.recover,
+            &mut warnings,
+        )
+        .map_err(PsdError::ResourceError)?;
+        layer_and_mask_information_section.set_global_light_angle(
+            image_resources_section
+                .global_angle
+                .map_or(DEFAULT_GLOBAL_LIGHT_ANGLE, f64::from),
+        );
 
-        Ok(Psd {
+        let psd = Psd {
             file_header_section,
             image_resources_section,
             layer_and_mask_information_section,
             image_data_section,
-        })
+            warnings,
+            section_layout: major_sections.layout.clone(),
+            clear_transparent_pixels: options.clear_transparent_pixels,
+            apply_exif_orientation: options.apply_exif_orientation,
+            max_decoded_bytes: options.max_decoded_bytes,
+        };
+
+        Ok(psd)
     }
 }
 
@@ -133,6 +502,16 @@ impl Psd {
         self.file_header_section.height.0
     }
 
+    /// The rectangle of every pixel in the PSD, with its top left pixel at (0, 0)
+    pub fn canvas_rect(&self) -> Rect {
+        Rect {
+            top: 0,
+            left: 0,
+            bottom: self.height() as i32 - 1,
+            right: self.width() as i32 - 1,
+        }
+    }
+
     /// The number of bits per channel
     pub fn depth(&self) -> PsdDepth {
         self.file_header_section.depth
@@ -146,7 +525,18 @@ impl Psd {
 
 // Methods for working with layers
 impl Psd {
-    /// Get all of the layers in the PSD
+    /// Get all of the layers in the PSD, from the top of the layers view to the bottom.
+    ///
+    /// Groups are not layers. The records that Photoshop stores to open and close each group,
+    /// including the hidden `</Layer group>` divider, are never in this list, so every entry is
+    /// a layer that can be drawn. The groups are in [`Psd::groups`] instead, and each layer's
+    /// `parent_id` is the group that it's in. The layers of a group, including the layers of its
+    /// nested groups, are always next to each other in this list, see
+    /// [`Psd::get_group_sub_layers`].
+    ///
+    /// Every layer index in the crate, such as [`Psd::layer_by_idx`], [`Psd::move_layer`], the
+    /// index that is passed to the filters of [`Psd::flatten_layers_rgba`] and
+    /// [`SectionLayout::layer_record`], is an index into this list.
     pub fn layers(&self) -> &Vec<PsdLayer> {
         &self.layer_and_mask_information_section.layers
     }
@@ -158,9 +548,44 @@ impl Psd {
             .item_by_name(name)
     }
 
+    /// Get every layer that the predicate returns true for, from the top of the layers view to
+    /// the bottom.
+    pub fn find_layers<F>(&self, predicate: F) -> Vec<&PsdLayer>
+    where
+        F: Fn(&PsdLayer) -> bool,
+    {
+        self.layers()
+            .iter()
+            .filter(|layer| predicate(layer))
+            .collect()
+    }
+
+    /// Like [`Psd::find_layers`], but only searches the layers inside of the group, including the
+    /// layers inside of its nested groups.
+    ///
+    /// Returns an empty vector if there isn't a group with the ID.
+    pub fn find_layers_in_group<F>(&self, group_id: u32, predicate: F) -> Vec<&PsdLayer>
+    where
+        F: Fn(&PsdLayer) -> bool,
+    {
+        match self.get_group_sub_layers(&group_id) {
+            Some(layers) => layers.iter().filter(|layer| predicate(layer)).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Get every layer whose name matches the glob pattern, such as `icon_*`, where `*` matches
+    /// any number of characters and `?` matches exactly one character.
+    ///
+    /// Combine [`Psd::find_layers_in_group`] with the layers' `name_matches` to match names
+    /// within a group.
+    pub fn layers_matching(&self, pattern: &str) -> Vec<&PsdLayer> {
+        self.find_layers(|layer| layer.name_matches(pattern))
+    }
+
     /// Get a layer by index.
     ///
-    /// index 0 is the bottom layer, index 1 is the layer above that, etc
+    /// index 0 is the top layer, index 1 is the layer below that, etc. See [`Psd::layers`].
     pub fn layer_by_idx(&self, idx: usize) -> &PsdLayer {
         self.layer_and_mask_information_section
             .layers
@@ -168,13 +593,37 @@ impl Psd {
             .unwrap()
     }
 
-    /// Get all of the groups in the PSD, in the order that they appear in the PSD file.
+    /// Get all of the groups in the PSD, keyed by their ID.
+    ///
+    /// Iterating over the map visits the groups in an arbitrary order. Use
+    /// [`Psd::groups_in_order`] to visit them in the order that they appear in the PSD.
     pub fn groups(&self) -> &HashMap<u32, PsdGroup> {
         &self.layer_and_mask_information_section.groups
     }
 
-    /// Get the group ID's in the order that they appear in Photoshop.
-    /// (i.e. from the bottom of layers view to the top of the layers view).
+    /// Get all of the groups in the PSD, in the same order as [`Psd::group_ids_in_order`].
+    pub fn groups_in_order(&self) -> Vec<&PsdGroup> {
+        self.layer_and_mask_information_section
+            .groups
+            .in_order()
+            .collect()
+    }
+
+    /// Get a group by name.
+    ///
+    /// If more than one group has the name, the one that is highest up in the layers view is
+    /// returned.
+    pub fn group_by_name(&self, name: &str) -> Option<&PsdGroup> {
+        self.layer_and_mask_information_section
+            .groups
+            .in_order()
+            .rev()
+            .find(|group| group.name() == name)
+    }
+
+    /// Get the group ID's in the order that Photoshop closes the groups, which is the order of
+    /// the bottom of each group from the top of the layers view to the bottom. Nested groups come
+    /// before the groups that they're in.
     pub fn group_ids_in_order(&self) -> &Vec<u32> {
         self.layer_and_mask_information_section
             .groups
@@ -192,6 +641,137 @@ impl Psd {
         }
     }
 
+    /// Move the layer at `idx` so that it ends up at `new_idx` in [`Psd::layers`], like
+    /// dragging it in Photoshop's layers panel.
+    ///
+    /// The layer joins the group of the layer that it ends up above, or of the layer that it
+    /// ends up below if it becomes the bottom layer. The ranges of layers that each group
+    /// contains and [`Psd::group_ids_in_order`] are kept up to date.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` or `new_idx` is out of bounds.
+    pub fn move_layer(&mut self, idx: usize, new_idx: usize) {
+        self.layer_and_mask_information_section
+            .move_layer(idx, new_idx);
+    }
+
+    /// Move the layer at `idx` to the top of the group with the given ID, or to the top of the
+    /// PSD when `group_id` is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds or there is no group with the ID.
+    pub fn set_parent(&mut self, idx: usize, group_id: Option<u32>) {
+        self.layer_and_mask_information_section
+            .set_parent(idx, group_id);
+    }
+
+    /// Get all of the patterns that are stored in the PSD
+    pub fn patterns(&self) -> &Vec<Pattern> {
+        &self.layer_and_mask_information_section.patterns
+    }
+
+    /// Get a pattern by its unique ID
+    pub fn pattern_by_id(&self, id: &str) -> Option<&Pattern> {
+        self.patterns().iter().find(|pattern| pattern.id() == id)
+    }
+
+    /// Get the annotations that were attached to the image with Photoshop's note tool, such as
+    /// an artist's comments.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.layer_and_mask_information_section.annotations
+    }
+
+    /// Drop the RGBA pixels that the layers cached because the PSD was parsed with
+    /// [`PsdOptions::cache_layer_rgba`]. The layers keep caching their pixels the next time that
+    /// they're needed.
+    pub fn clear_pixel_caches(&mut self) {
+        self.layer_and_mask_information_section
+            .clear_layer_rgba_caches();
+    }
+
+    /// Get the files that the PSD's smart objects show, such as the images that were placed
+    /// into it. Embedded files hold their bytes.
+    pub fn linked_files(&self) -> &[LinkedFile] {
+        &self.layer_and_mask_information_section.linked_files
+    }
+
+    /// Get a linked file by its unique ID
+    pub fn linked_file_by_id(&self, unique_id: &str) -> Option<&LinkedFile> {
+        self.linked_files()
+            .iter()
+            .find(|linked_file| linked_file.unique_id() == unique_id)
+    }
+
+    /// Get the top most layer that has a non transparent pixel at the given coordinate,
+    /// taking the layer's mask and clipping into account.
+    ///
+    /// Hidden layers, layers inside of hidden groups and layers that are clipped to a hidden
+    /// layer are skipped unless `include_hidden` is true.
+    ///
+    /// Returns `None` if the coordinate is outside of the PSD or every layer is transparent
+    /// there.
+    pub fn layer_at_pixel(&self, x: u32, y: u32, include_hidden: bool) -> Option<&PsdLayer> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+
+        let layers = self.layers();
+
+        for (idx, layer) in layers.iter().enumerate() {
+            if !include_hidden && !self.is_layer_shown(layer) {
+                continue;
+            }
+
+            if layer.alpha_at(x, y) == 0 {
+                continue;
+            }
+
+            // A clipped layer is only visible where the base layer that it is clipped to is
+            // visible, so it is hidden along with its base.
+            if !layer.is_clipping_mask() {
+                let base = layers[idx + 1..]
+                    .iter()
+                    .find(|layer| layer.is_clipping_mask());
+
+                match base {
+                    Some(base)
+                        if (include_hidden || self.is_layer_shown(base))
+                            && base.alpha_at(x, y) > 0 => {}
+                    _ => continue,
+                }
+            }
+
+            return Some(layer);
+        }
+
+        None
+    }
+
+    /// Whether or not the layer and all of the groups that it is nested in are visible
+    fn is_layer_shown(&self, layer: &PsdLayer) -> bool {
+        if !layer.visible() {
+            return false;
+        }
+
+        let mut parent_id = layer.parent_id();
+        while let Some(id) = parent_id {
+            let group = match self.groups().get(&id) {
+                Some(group) => group,
+                None => break,
+            };
+
+            if !group.visible() {
+                return false;
+            }
+
+            parent_id = group.parent_id();
+        }
+
+        true
+    }
+
     /// Given a filter, combine all layers in the PSD that pass the filter into a vector
     /// of RGBA pixels.
     ///
@@ -206,6 +786,113 @@ impl Psd {
         &self,
         filter: &dyn Fn((usize, &PsdLayer)) -> bool,
     ) -> Result<Vec<u8>, PsdError> {
+        self.flatten_layers_rgba_with_blender(filter, &blend_rgba)
+    }
+
+    /// Like [`Psd::flatten_layers_rgba`], but blends the layers in linear light instead of in
+    /// sRGB. See [`blend_rgba_linear`].
+    pub fn flatten_layers_rgba_linear(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+    ) -> Result<Vec<u8>, PsdError> {
+        self.flatten_layers_rgba_with_blender(filter, &blend_rgba_linear)
+    }
+
+    /// Like [`Psd::flatten_layers_rgba`], but blends the pixels with the passed in `blender`
+    /// instead of the crate's blend modes.
+    ///
+    /// The blender is given the pixel of the upper layer, the flattened pixel below it and the
+    /// upper layer's blend mode, and returns the blended pixel. It is only called when the upper
+    /// pixel has some transparency and there is a pixel below it.
+    ///
+    /// Use [`blend_rgba`] to fall back to the crate's blending, such as when only overriding
+    /// some of the blend modes.
+    ///
+    /// ```ignore
+    /// let rgba = psd.flatten_layers_rgba_with_blender(&|_| true, &|top, bottom, blend_mode| {
+    ///     match blend_mode {
+    ///         BlendMode::LinearLight => my_linear_light(top, bottom),
+    ///         _ => psd::blend_rgba(top, bottom, blend_mode),
+    ///     }
+    /// })?;
+    /// ```
+    pub fn flatten_layers_rgba_with_blender(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        blender: &dyn Fn([u8; 4], [u8; 4], BlendMode) -> [u8; 4],
+    ) -> Result<Vec<u8>, PsdError> {
+        // The flattened pixels and a buffer for each layer
+        self.check_flatten_bytes(filter, 1)?;
+
+        Ok(self.orient(self.flatten_unoriented(filter, blender)))
+    }
+
+    /// Like [`Psd::flatten_layers_rgba`], but writes the pixels into a buffer instead of
+    /// allocating a new one, so that the buffer can be reused.
+    ///
+    /// The buffer must be `width * height * 4` bytes long.
+    pub fn flatten_layers_rgba_into(
+        &self,
+        rgba: &mut [u8],
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+    ) -> Result<(), PsdError> {
+        self.check_rgba_buffer(rgba)?;
+        self.check_flatten_bytes(filter, 0)?;
+        self.flatten_into(rgba, filter, &blend_rgba);
+        self.orient_in_place(rgba);
+
+        Ok(())
+    }
+
+    /// Flatten the layers without applying the EXIF orientation, so that the pixels line up
+    /// with the layers and the rest of the document
+    pub(crate) fn flatten_unoriented(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        blender: &dyn Fn([u8; 4], [u8; 4], BlendMode) -> [u8; 4],
+    ) -> Vec<u8> {
+        let mut rgba = vec![0; self.rgba_len()];
+        self.flatten_into(&mut rgba, filter, blender);
+
+        rgba
+    }
+
+    /// Flatten the layers into a buffer of the right length
+    fn flatten_into(
+        &self,
+        rgba: &mut [u8],
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        blender: &dyn Fn([u8; 4], [u8; 4], BlendMode) -> [u8; 4],
+    ) {
+        // During the process of flattening the PSD we might need to look at the pixels on one of
+        // the layers below if an upper layer is transparent.
+        //
+        // Anytime we need to calculate the RGBA for a layer we cache it so that we don't need
+        // to perform that operation again. The cache belongs to this call rather than to the
+        // PSD, so a `&Psd` can be flattened from multiple threads at once.
+        let mut cached_layer_rgba = LayerRgbaCache::default();
+
+        self.flatten_into_cached(rgba, filter, blender, &mut cached_layer_rgba);
+    }
+
+    /// Like `flatten_into`, but decodes the layers into a cache that is cleared first, so that
+    /// its buffers can be reused across PSDs
+    pub(crate) fn flatten_into_cached(
+        &self,
+        rgba: &mut [u8],
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        blender: &dyn Fn([u8; 4], [u8; 4], BlendMode) -> [u8; 4],
+        cached_layer_rgba: &mut LayerRgbaCache,
+    ) {
+        cached_layer_rgba.clear();
+
+        span!(
+            DEBUG,
+            "flatten_layers_rgba",
+            width = self.width(),
+            height = self.height()
+        );
+
         // When you create a PSD but don't create any new layers the bottom layer might not
         // show up in the layer and mask information section, so we won't see any layers.
         //
@@ -213,162 +900,1053 @@ impl Psd {
         // a completely transparent image if it is filtered out. But this should be a rare
         // use case so we can just always return the final image for now.
         if self.layers().is_empty() {
-            return Ok(self.rgba());
+            rgba.fill(0);
+            self.write_rgba(rgba);
+            return;
         }
 
-        // Filter out layers based on the passed in filter.
-        let layers_to_flatten_top_to_bottom: Vec<(usize, &PsdLayer)> = self
-            .layers()
-            .iter()
-            .enumerate()
-            // here we filter transparent layers and invisible layers
-            .filter(|(_, layer)| (layer.opacity > 0 && layer.visible) || layer.clipping_mask)
-            .filter(|(idx, layer)| filter((*idx, layer)))
-            .collect();
-
-        let pixel_count = self.width() * self.height();
+        let layers_to_flatten_top_to_bottom = self.layers_to_flatten(filter);
 
         // If there aren't any layers left after filtering we return a complete transparent image.
         if layers_to_flatten_top_to_bottom.is_empty() {
-            return Ok(vec![0; pixel_count as usize * 4]);
+            rgba.fill(0);
+            return;
         }
 
-        // During the process of flattening the PSD we might need to look at the pixels on one of
-        // the layers below if an upper layer is transparent.
-        //
-        // Anytime we need to calculate the RGBA for a layer we cache it so that we don't need
-        // to perform that operation again.
-        let cached_layer_rgba = RefCell::new(HashMap::new());
-
-        let mut flattened_pixels = Vec::with_capacity((pixel_count * 4) as usize);
+        self.flatten_region(
+            rgba,
+            (0..self.width() as usize, 0..self.height() as usize),
+            &layers_to_flatten_top_to_bottom,
+            cached_layer_rgba,
+            blender,
+            false,
+        );
+    }
 
-        // Iterate over each pixel and, if it is transparent, blend it with the pixel below it
-        // recursively.
-        for pixel_idx in 0..pixel_count as usize {
-            let left = pixel_idx % self.width() as usize;
-            let top = pixel_idx / self.width() as usize;
-            let pixel_coord = (left, top);
+    /// Like [`Psd::flatten_layers_rgba`], but flattens one row of pixels at a time, from the top
+    /// row down, so that a large PSD can be streamed to an encoder or a socket without holding
+    /// all of its flattened pixels in memory.
+    ///
+    /// Each row is `width * 4` bytes. Like [`Psd::flatten_tiles`], the layers are decoded one
+    /// band of rows at a time, so only the rows of each layer's channels that the band covers
+    /// are decompressed and held in memory, and fills and vector masks are only rendered
+    /// within the band. A PSD without layers decodes its whole composite image.
+    ///
+    /// ```ignore
+    /// for row in psd.flatten_rows(&|_| true) {
+    ///     encoder.write_row(&row)?;
+    /// }
+    /// ```
+    pub fn flatten_rows(&self, filter: &dyn Fn((usize, &PsdLayer)) -> bool) -> FlattenRows<'_> {
+        let layers = self.layers_to_flatten(filter);
+        let group_ids = layers
+            .iter()
+            .map(|(_, layer)| self.ancestor_group_ids(layer))
+            .collect();
 
-            let blended_pixel = self.flattened_pixel(
-                0,
-                pixel_coord,
-                &layers_to_flatten_top_to_bottom,
-                &cached_layer_rgba,
-            );
+        // Like `flatten_into`, the composite image is used when the PSD doesn't have any layers
+        let composite = self.layers().is_empty().then(|| self.rgba_unoriented());
 
-            flattened_pixels.push(blended_pixel[0]);
-            flattened_pixels.push(blended_pixel[1]);
-            flattened_pixels.push(blended_pixel[2]);
-            flattened_pixels.push(blended_pixel[3]);
+        FlattenRows {
+            psd: self,
+            layers,
+            group_ids,
+            cached_layer_rgba: LayerRgbaCache::default(),
+            band_end: 0,
+            composite,
+            rows: 0..self.height() as usize,
         }
-
-        Ok(flattened_pixels)
     }
 
-    /// Get the pixel at a coordinate within this image.
+    /// Like [`Psd::flatten_layers_rgba`], but flattens one square tile of `tile_size` pixels at
+    /// a time, from left to right and then from the top down, and passes each tile's rectangle
+    /// and RGBA pixels to `tile`. The tiles along the right and bottom edges are cut short by the
+    /// edges of the PSD.
     ///
-    /// If that pixel has transparency, recursively blending it with the pixel
-    /// below it until we reach a pixel with no transparency or the bottom of the stack.
-    fn flattened_pixel(
+    /// The layers are decoded one row of tiles at a time, and only the rows of each layer's
+    /// channels that the row of tiles covers are decompressed, with fills and vector masks only
+    /// rendered within it. The memory that is used is bounded by a row of tiles for each layer
+    /// on top of the compressed channels, instead of a buffer the size of the PSD for each
+    /// layer, which lets a server render a huge poster with little memory. A PSD without
+    /// layers decodes its whole composite image.
+    ///
+    /// The tiles are in the PSD's own orientation, even with
+    /// [`PsdOptions::apply_exif_orientation`].
+    ///
+    /// ```ignore
+    /// psd.flatten_tiles(512, &|_| true, |rect, rgba| {
+    ///     encoder.write_tile(rect.left, rect.top, rect.width(), rect.height(), rgba);
+    /// });
+    /// ```
+    pub fn flatten_tiles<F>(
         &self,
-        // Top is 0, below that is 1, ... etc
-        flattened_layer_top_down_idx: usize,
-        // (left, top)
-        pixel_coord: (usize, usize),
-        layers_to_flatten_top_down: &[(usize, &PsdLayer)],
-        cached_layer_rgba: &RefCell<HashMap<usize, Vec<u8>>>,
-    ) -> [u8; 4] {
-        let layer = layers_to_flatten_top_down[flattened_layer_top_down_idx].1;
-
-        let (pixel_left, pixel_top) = pixel_coord;
+        tile_size: u32,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        mut tile: F,
+    ) where
+        F: FnMut(Rect, &[u8]),
+    {
+        let tile_size = tile_size.max(1) as usize;
+        let (width, height) = (self.width() as usize, self.height() as usize);
 
-        // If this pixel is out of bounds of this layer we return the pixel below it.
-        // If there is no pixel below it we return a transparent pixel
-        if pixel_left < layer.layer_properties.layer_left as usize
-            || pixel_left > layer.layer_properties.layer_right as usize
-            || pixel_top < layer.layer_properties.layer_top as usize
-            || pixel_top > layer.layer_properties.layer_bottom as usize
-        {
-            if flattened_layer_top_down_idx + 1 < layers_to_flatten_top_down.len() {
-                return self.flattened_pixel(
-                    flattened_layer_top_down_idx + 1,
-                    pixel_coord,
-                    layers_to_flatten_top_down,
-                    cached_layer_rgba,
-                );
-            } else {
-                return [0; 4];
-            }
-        }
+        let layers = self.layers_to_flatten(filter);
+        let group_ids: Vec<Vec<u32>> = layers
+            .iter()
+            .map(|(_, layer)| self.ancestor_group_ids(layer))
+            .collect();
+        let stack = FlattenStack {
+            layers: &layers,
+            group_ids: &group_ids,
+            blender: &blend_rgba,
+            effects: false,
+        };
 
-        // If we haven't already calculated the RGBA for this layer, calculate and cache it
-        if cached_layer_rgba
-            .borrow()
-            .get(&flattened_layer_top_down_idx)
-            .is_none()
-        {
-            let pixels = layers_to_flatten_top_down[flattened_layer_top_down_idx]
-                .1
-                .rgba();
-            cached_layer_rgba
-                .borrow_mut()
-                .insert(flattened_layer_top_down_idx, pixels);
-        }
+        // Like `flatten_into`, the composite image is used when the PSD doesn't have any layers
+        let composite = self.layers().is_empty().then(|| self.rgba_unoriented());
 
-        let pixel = {
-            let cache = cached_layer_rgba.borrow();
-            let layer_rgba = cache.get(&flattened_layer_top_down_idx).unwrap();
+        let mut cached_layer_rgba = LayerRgbaCache::default();
+        let mut rgba = vec![];
+        for top in (0..height).step_by(tile_size) {
+            let bottom = (top + tile_size).min(height) as i32 - 1;
 
-            let pixel_idx = ((self.width() as usize * pixel_top) + pixel_left) * 4;
+            // The layers are decoded within the band of rows that the tiles cover, once for the
+            // whole row of tiles
+            if composite.is_none() && !layers.is_empty() {
+                cached_layer_rgba.set_region(Some(Rect {
+                    top: top as i32,
+                    left: 0,
+                    bottom,
+                    right: width as i32 - 1,
+                }));
+            }
 
-            let (start, end) = (pixel_idx, pixel_idx + 4);
+            for left in (0..width).step_by(tile_size) {
+                let rect = Rect {
+                    top: top as i32,
+                    left: left as i32,
+                    bottom,
+                    right: (left + tile_size).min(width) as i32 - 1,
+                };
 
-            let pixel = &layer_rgba[start..end];
-            let mut copy = [0; 4];
-            copy.copy_from_slice(pixel);
+                // Every layer was filtered out, so the tile is transparent
+                rgba.clear();
+                rgba.resize(rect.width() as usize * rect.height() as usize * 4, 0);
 
-            blend::apply_opacity(&mut copy, layer.opacity);
-            copy
-        };
+                if let Some(composite) = &composite {
+                    crop_rgba(composite, self.width(), rect, &mut rgba);
+                } else if !layers.is_empty() {
+                    let pixels = (top..=rect.bottom as usize)
+                        .flat_map(|top| (left..=rect.right as usize).map(move |left| (left, top)));
+                    for (pixel, pixel_coord) in rgba.chunks_exact_mut(4).zip(pixels) {
+                        let blended_pixel = self.flattened_pixel(
+                            0,
+                            (layers.len(), None),
+                            pixel_coord,
+                            &stack,
+                            &mut cached_layer_rgba,
+                        );
+                        pixel.copy_from_slice(&blended_pixel);
+                    }
 
-        // This pixel is fully opaque, return it
-        let pixel = if pixel[3] == 255 && layer.opacity == 255 {
-            pixel
-        } else {
-            // If this pixel has some transparency, blend it with the layer below it
-            if flattened_layer_top_down_idx + 1 < layers_to_flatten_top_down.len() {
-                let mut final_pixel = [0; 4];
-                // This pixel has some transparency and there is a pixel below it, blend them
-                let pixel_below = self.flattened_pixel(
-                    flattened_layer_top_down_idx + 1,
-                    pixel_coord,
-                    layers_to_flatten_top_down,
-                    cached_layer_rgba,
-                );
+                    if self.clear_transparent_pixels {
+                        blend::clear_transparent_pixels(&mut rgba);
+                    }
+                }
 
-                blend::blend_pixels(pixel, pixel_below, layer.blend_mode, &mut final_pixel);
-                final_pixel
-            } else {
-                // There is no pixel below this layer, so use it even though it has transparency
-                pixel
+                tile(rect, &rgba);
             }
-        };
-        pixel
+        }
+    }
+
+    /// Flatten each top level group that the filter returns true for on its own, as if every
+    /// other layer were hidden, such as for exporting each group of a PSD as a separate asset.
+    ///
+    /// Returns the name of each group, the RGBA pixels within its bounds (see
+    /// [`PsdGroup::bounds`]) cropped to the canvas, and those bounds, in the same order as
+    /// [`Psd::groups_in_order`]. Groups without any pixels on the canvas are left out, and like
+    /// [`Psd::flatten_layers_rgba`] the hidden layers within a group aren't flattened.
+    ///
+    /// The groups are flattened into one buffer that is reused between them, and only the
+    /// pixels within each group's bounds are flattened.
+    ///
+    /// ```ignore
+    /// for (name, rgba, bounds) in psd.export_groups(|group| !group.name().starts_with('_')) {
+    ///     let png = psd::export::rgba_to_png(&rgba, bounds.width(), bounds.height())?;
+    ///     std::fs::write(format!("{}.png", name), png)?;
+    /// }
+    /// ```
+    pub fn export_groups<F>(&self, filter: F) -> Vec<(String, Vec<u8>, Rect)>
+    where
+        F: Fn(&PsdGroup) -> bool,
+    {
+        let mut rgba = vec![];
+        let mut cached_layer_rgba = LayerRgbaCache::default();
+
+        let mut exported = vec![];
+        for group in self.groups_in_order() {
+            if group.parent_id().is_some() || !filter(group) {
+                continue;
+            }
+
+            let bounds = match group
+                .bounds()
+                .and_then(|bounds| bounds.intersect(&self.canvas_rect()))
+            {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+
+            let columns = bounds.left as usize..bounds.right as usize + 1;
+            let rows = bounds.top as usize..bounds.bottom as usize + 1;
+
+            // Fully transparent unless one of the group's layers is flattened over it
+            let mut group_rgba = vec![0; bounds.width() as usize * bounds.height() as usize * 4];
+
+            let layers = self.layers_to_flatten(&|(idx, _)| group.contained_layers.contains(&idx));
+            if !layers.is_empty() {
+                rgba.resize(self.rgba_len(), 0);
+                // The groups don't share any layers, so the layers' buffers are only kept for
+                // the next group to decode into
+                cached_layer_rgba.clear();
+                self.flatten_region(
+                    &mut rgba,
+                    (columns, rows),
+                    &layers,
+                    &mut cached_layer_rgba,
+                    &blend_rgba,
+                    false,
+                );
+
+                crop_rgba(&rgba, self.width(), bounds, &mut group_rgba);
+            }
+
+            exported.push((group.name().to_string(), group_rgba, bounds));
+        }
+
+        exported
+    }
+
+    /// The layers that pass the filter, from the top down, leaving out the layers that are
+    /// hidden or fully transparent unless they are the base of a clipping mask
+    fn layers_to_flatten(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+    ) -> Vec<(usize, &PsdLayer)> {
+        self.layers()
+            .iter()
+            .enumerate()
+            // here we filter transparent layers and invisible layers
+            .filter(|(_, layer)| (layer.opacity > 0 && layer.visible) || layer.clipping_mask)
+            .filter(|(idx, layer)| filter((*idx, layer)))
+            .collect()
+    }
+
+    /// Flatten the pixels within the `(columns, rows)` of the PSD into a buffer the size of
+    /// the PSD, leaving the pixels outside of them untouched.
+    ///
+    /// There must be at least one layer to flatten. The cache of each layer's RGBA is keyed by
+    /// the layer's index, so it can be reused across calls. When `effects` is true the layers'
+    /// effects are rendered, see [`PsdLayer::rgba_with_effects`].
+    pub(crate) fn flatten_region(
+        &self,
+        rgba: &mut [u8],
+        (columns, rows): (Range<usize>, Range<usize>),
+        layers_to_flatten_top_down: &[(usize, &PsdLayer)],
+        cached_layer_rgba: &mut LayerRgbaCache,
+        blender: &dyn Fn([u8; 4], [u8; 4], BlendMode) -> [u8; 4],
+        effects: bool,
+    ) {
+        let row_len = self.width() as usize * 4;
+        let group_ids: Vec<Vec<u32>> = layers_to_flatten_top_down
+            .iter()
+            .map(|(_, layer)| self.ancestor_group_ids(layer))
+            .collect();
+        let stack = FlattenStack {
+            layers: layers_to_flatten_top_down,
+            group_ids: &group_ids,
+            blender,
+            effects,
+        };
+
+        for top in rows {
+            let row = &mut rgba[top * row_len..(top + 1) * row_len];
+            self.flatten_row_into(row, top, columns.clone(), &stack, cached_layer_rgba);
+        }
+    }
+
+    /// Flatten the pixels within the columns of a row into a buffer the width of the PSD
+    fn flatten_row_into(
+        &self,
+        row: &mut [u8],
+        top: usize,
+        columns: Range<usize>,
+        stack: &FlattenStack,
+        cached_layer_rgba: &mut LayerRgbaCache,
+    ) {
+        let bytes = columns.start * 4..columns.end * 4;
+
+        // Iterate over each pixel and, if it is transparent, blend it with the pixel below it
+        // recursively.
+        for left in columns {
+            let blended_pixel = self.flattened_pixel(
+                0,
+                (stack.layers.len(), None),
+                (left, top),
+                stack,
+                cached_layer_rgba,
+            );
+
+            row[left * 4..left * 4 + 4].copy_from_slice(&blended_pixel);
+        }
+
+        if self.clear_transparent_pixels {
+            blend::clear_transparent_pixels(&mut row[bytes]);
+        }
+    }
+
+    /// The IDs of the groups that the layer is in, from the outermost group inwards
+    fn ancestor_group_ids(&self, layer: &PsdLayer) -> Vec<u32> {
+        let mut group_ids = vec![];
+
+        let mut parent_id = layer.parent_id();
+        while let Some(group_id) = parent_id {
+            group_ids.push(group_id);
+            parent_id = self
+                .groups()
+                .get(&group_id)
+                .and_then(|group| group.parent_id());
+        }
+
+        group_ids.reverse();
+        group_ids
+    }
+
+    /// Get the pixel at a coordinate within this image.
+    ///
+    /// If that pixel has transparency, recursively blending it with the pixel
+    /// below it until we reach a pixel with no transparency or the bottom of the stack.
+    ///
+    /// Groups that aren't pass through are isolated, so their layers are flattened on their own
+    /// before the group is blended with the layers below it.
+    fn flattened_pixel(
+        &self,
+        // Top is 0, below that is 1, ... etc
+        flattened_layer_top_down_idx: usize,
+        // The layers down to (but not including) this index are flattened, along with the
+        // isolated group that they're in. Everything below them is transparent.
+        (end, isolated_group): (usize, Option<u32>),
+        // (left, top)
+        pixel_coord: (usize, usize),
+        stack: &FlattenStack,
+        cached_layer_rgba: &mut LayerRgbaCache,
+    ) -> [u8; 4] {
+        let idx = flattened_layer_top_down_idx;
+        if idx >= end {
+            return [0; 4];
+        }
+
+        if let Some(group) = self.isolated_group_starting_at(stack, idx, isolated_group) {
+            let group_end = stack.group_end(idx, group.id());
+
+            let mut pixel = self.flattened_pixel(
+                idx,
+                (group_end, Some(group.id())),
+                pixel_coord,
+                stack,
+                cached_layer_rgba,
+            );
+            blend::apply_opacity(&mut pixel, group.opacity());
+
+            if group_end >= end {
+                return pixel;
+            }
+            let pixel_below = self.flattened_pixel(
+                group_end,
+                (end, isolated_group),
+                pixel_coord,
+                stack,
+                cached_layer_rgba,
+            );
+
+            return (stack.blender)(pixel, pixel_below, group.blend_mode());
+        }
+
+        let layer = stack.layers[idx].1;
+        let with_effects = stack.effects && layer.effects().is_some();
+
+        let (pixel_left, pixel_top) = pixel_coord;
+
+        // If this pixel is out of bounds of this layer we return the pixel below it.
+        // If there is no pixel below it we return a transparent pixel.
+        //
+        // Fill layers cover the entire PSD so they are never out of bounds, while empty layers
+        // are always out of bounds. Effects can reach past the layer's bounds.
+        if !with_effects && !layer.contains_canvas_point(pixel_left as u32, pixel_top as u32) {
+            return self.flattened_pixel(
+                idx + 1,
+                (end, isolated_group),
+                pixel_coord,
+                stack,
+                cached_layer_rgba,
+            );
+        }
+
+        // If we haven't already calculated the RGBA for this layer, calculate and cache it
+        let layer_idx = stack.layers[idx].0;
+        let (pixel, coverage) = {
+            let pixel_idx = cached_layer_rgba.pixel_idx(pixel_coord, self.width());
+            let layer_rgba = cached_layer_rgba.get_or_decode(layer_idx, layer, with_effects);
+
+            let (start, end) = (pixel_idx, pixel_idx + 4);
+
+            let pixel = &layer_rgba[start..end];
+            let mut copy = [0; 4];
+            copy.copy_from_slice(pixel);
+
+            // How much the layer knocks out, which doesn't depend on the fill opacity
+            let coverage = (copy[3] as u16 * layer.opacity as u16 / 255) as u8;
+
+            // The fill opacity has already faded the layer's pixels, but not its effects
+            if with_effects {
+                blend::apply_opacity(&mut copy, layer.opacity);
+            } else {
+                layer.apply_opacity(&mut copy);
+            }
+            (copy, coverage)
+        };
+
+        // This pixel is fully opaque, return it
+        if pixel[3] == 255 && layer.opacity == 255 {
+            return pixel;
+        }
+
+        let knockout_idx = stack.knockout_stop(idx, end);
+
+        // There is no pixel below this layer, so use it even though it has transparency
+        if idx + 1 >= end && knockout_idx.is_none() {
+            return pixel;
+        }
+
+        // This pixel has some transparency, blend it with the pixel below it
+        let mut pixel_below = self.flattened_pixel(
+            idx + 1,
+            (end, isolated_group),
+            pixel_coord,
+            stack,
+            cached_layer_rgba,
+        );
+
+        // Where the layer has pixels, the layers down to the knockout's stopping point are
+        // replaced by what's below them
+        if let Some(knockout_idx) = knockout_idx {
+            let knocked_out = self.flattened_pixel(
+                knockout_idx,
+                (end, isolated_group),
+                pixel_coord,
+                stack,
+                cached_layer_rgba,
+            );
+            pixel_below = mix_pixels(pixel_below, knocked_out, coverage);
+        }
+
+        (stack.blender)(pixel, pixel_below, layer.blend_mode)
+    }
+
+    /// The outermost group that isn't pass through and that the layer at the index is at the
+    /// top of, not counting the isolated group that is already being flattened.
+    fn isolated_group_starting_at(
+        &self,
+        stack: &FlattenStack,
+        idx: usize,
+        isolated_group: Option<u32>,
+    ) -> Option<&PsdGroup> {
+        let group_ids = &stack.group_ids[idx];
+
+        // The groups inside of the one that is already being flattened
+        let inner = match isolated_group {
+            Some(isolated_group) => group_ids
+                .iter()
+                .position(|group_id| *group_id == isolated_group)
+                .map_or(0, |position| position + 1),
+            None => 0,
+        };
+
+        group_ids[inner..]
+            .iter()
+            .filter_map(|group_id| self.groups().get(group_id))
+            .find(|group| group.blend_mode() != BlendMode::PassThrough)
+    }
+}
+
+/// The rows of a PSD's flattened pixels, from the top row down. See [`Psd::flatten_rows`].
+#[derive(Debug)]
+pub struct FlattenRows<'a> {
+    psd: &'a Psd,
+    layers: Vec<(usize, &'a PsdLayer)>,
+    /// The IDs of the groups that each layer is in, from the outermost group inwards
+    group_ids: Vec<Vec<u32>>,
+    /// The layers' RGBA within the band of rows that is being flattened
+    cached_layer_rgba: LayerRgbaCache,
+    /// The row below the band that the layers are decoded within
+    band_end: usize,
+    /// The pixels of a PSD without layers, which are used instead of flattening
+    composite: Option<Vec<u8>>,
+    rows: Range<usize>,
+}
+
+/// The number of rows that [`FlattenRows`] decodes the layers within at a time
+const FLATTEN_ROWS_BAND_HEIGHT: usize = 64;
+
+impl<'a> Iterator for FlattenRows<'a> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let top = self.rows.next()?;
+        let width = self.psd.width() as usize;
+        let row_len = width * 4;
+
+        if let Some(composite) = &self.composite {
+            return Some(composite[top * row_len..(top + 1) * row_len].to_vec());
+        }
+
+        // Every layer was filtered out, so the row is transparent
+        let mut row = vec![0; row_len];
+        if self.layers.is_empty() {
+            return Some(row);
+        }
+
+        // Move on to the next band, forgetting the layers' rows within the last one
+        if top >= self.band_end {
+            self.band_end = (top + FLATTEN_ROWS_BAND_HEIGHT).min(self.rows.end);
+            self.cached_layer_rgba.set_region(Some(Rect {
+                top: top as i32,
+                left: 0,
+                bottom: self.band_end as i32 - 1,
+                right: width as i32 - 1,
+            }));
+        }
+
+        let stack = FlattenStack {
+            layers: &self.layers,
+            group_ids: &self.group_ids,
+            blender: &blend_rgba,
+            effects: false,
+        };
+        self.psd
+            .flatten_row_into(&mut row, top, 0..width, &stack, &mut self.cached_layer_rgba);
+
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for FlattenRows<'a> {}
+
+/// The RGBA of the layers that have been flattened so far, keyed by the layer's index
+#[derive(Debug, Default)]
+pub(crate) struct LayerRgbaCache {
+    layers: HashMap<usize, Vec<u8>>,
+    /// Buffers from layers that were cleared out of the cache, which layers are decoded into
+    /// instead of allocating new buffers
+    spare: Vec<Vec<u8>>,
+    /// The part of the PSD that the layers are decoded within, or `None` for the whole PSD
+    region: Option<Rect>,
+}
+
+impl LayerRgbaCache {
+    /// The RGBA of the layer at the index, decoding it the first time that it is needed
+    fn get_or_decode(&mut self, layer_idx: usize, layer: &PsdLayer, effects: bool) -> &[u8] {
+        let spare = &mut self.spare;
+        let region = self.region;
+
+        self.layers.entry(layer_idx).or_insert_with(|| {
+            if effects && region.is_none() {
+                return layer.rgba_with_effects();
+            }
+
+            let mut rgba = spare.pop().unwrap_or_default();
+            match (region, effects) {
+                (None, _) => layer.rgba_into_vec(&mut rgba),
+                // Effects can reach past the layer, so they are rendered across the whole PSD
+                (Some(region), true) => {
+                    rgba.clear();
+                    rgba.resize(region.width() as usize * region.height() as usize * 4, 0);
+                    crop_rgba(
+                        &layer.rgba_with_effects(),
+                        layer.psd_width,
+                        region,
+                        &mut rgba,
+                    );
+                }
+                (Some(region), false) => layer.rgba_region_into(region, &mut rgba),
+            }
+
+            rgba
+        })
+    }
+
+    /// The index of the pixel at (left, top) of the PSD within the layers' RGBA
+    fn pixel_idx(&self, (left, top): (usize, usize), psd_width: u32) -> usize {
+        match self.region {
+            Some(region) => {
+                let (left, top) = (left - region.left as usize, top - region.top as usize);
+                (top * region.width() as usize + left) * 4
+            }
+            None => (top * psd_width as usize + left) * 4,
+        }
+    }
+
+    /// Forget every layer and only decode them within the region from now on
+    fn set_region(&mut self, region: Option<Rect>) {
+        self.clear();
+        self.region = region;
+    }
+
+    /// Forget every layer, keeping their buffers to decode the next layers into
+    pub(crate) fn clear(&mut self) {
+        self.spare
+            .extend(core::mem::take(&mut self.layers).into_values());
+    }
+}
+
+/// The layers that are being flattened, from the top down, along with the groups that they're in
+struct FlattenStack<'a> {
+    layers: &'a [(usize, &'a PsdLayer)],
+    /// The IDs of the groups that each layer is in, from the outermost group inwards
+    group_ids: &'a [Vec<u32>],
+    blender: &'a dyn Fn([u8; 4], [u8; 4], BlendMode) -> [u8; 4],
+    /// Whether the layers' effects are rendered
+    effects: bool,
+}
+
+impl<'a> FlattenStack<'a> {
+    /// The index after the last layer of the group, given the index of one of its layers
+    fn group_end(&self, idx: usize, group_id: u32) -> usize {
+        (idx + 1..self.layers.len())
+            .find(|idx| !self.group_ids[*idx].contains(&group_id))
+            .unwrap_or(self.layers.len())
+    }
+
+    /// The index of the layer that is revealed where the layer at the index knocks out the
+    /// layers below it, or `None` if it doesn't. An index of `end` or more is transparent.
+    ///
+    /// A shallow knockout reveals the layers below the layer's group. A deep knockout, or a
+    /// shallow one outside of any group, reveals the background, which is the bottom layer when
+    /// it isn't in a group.
+    fn knockout_stop(&self, idx: usize, end: usize) -> Option<usize> {
+        let knockout = self.layers[idx].1.knockout();
+        if knockout == Knockout::None {
+            return None;
+        }
+
+        if let (Knockout::Shallow, Some(group_id)) = (knockout, self.group_ids[idx].last()) {
+            return Some(self.group_end(idx, *group_id).min(end));
+        }
+
+        let bottom = self.layers.len() - 1;
+        if idx < bottom && bottom < end && self.group_ids[bottom].is_empty() {
+            Some(bottom)
+        } else {
+            Some(end)
+        }
     }
 }
 
+/// Mix from one pixel to another, where an `amount` of 255 is entirely the second pixel
+fn mix_pixels(from: [u8; 4], to: [u8; 4], amount: u8) -> [u8; 4] {
+    let amount = amount as u16;
+
+    let mut mixed = [0; 4];
+    for channel in 0..4 {
+        mixed[channel] =
+            ((from[channel] as u16 * (255 - amount) + to[channel] as u16 * amount) / 255) as u8;
+    }
+
+    mixed
+}
+
 // Methods for working with the final flattened image data
 impl Psd {
     /// Get the RGBA pixels for the PSD
     /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// If the PSD doesn't have a composite image (see [`Psd::has_composite_image`]) its layers
+    /// are flattened instead. A PSD without a composite image or layers is fully transparent.
+    ///
+    /// With [`PsdOptions::apply_exif_orientation`] the pixels are rotated or flipped to show a
+    /// photo upright, see [`Psd::oriented_size`].
     pub fn rgba(&self) -> Vec<u8> {
-        self.generate_rgba()
+        self.orient(self.rgba_unoriented())
+    }
+
+    /// The pixels of [`Psd::rgba`] without applying the EXIF orientation, so that they line up
+    /// with the layers and the rest of the document
+    pub(crate) fn rgba_unoriented(&self) -> Vec<u8> {
+        let mut rgba = vec![0; self.rgba_len()];
+        self.write_rgba(&mut rgba);
+
+        rgba
+    }
+
+    /// The same pixels as [`Psd::rgba`], laid out in the format, such as BGRA for a GPU
+    /// texture.
+    ///
+    /// ```ignore
+    /// let bgra = psd.pixels(PixelFormat::Bgra8);
+    /// ```
+    pub fn pixels(&self, format: PixelFormat) -> Vec<u8> {
+        // Composite images that aren't converted or touched up after they are interleaved are
+        // written straight into the format
+        let decoded_as_is = self.has_composite_image()
+            && self.color_mode() != ColorMode::Cmyk
+            && self.depth() != PsdDepth::ThirtyTwo
+            && !self.clear_transparent_pixels
+            && self.applied_exif_orientation().is_none();
+        if !decoded_as_is {
+            return format.layout_rgba(self.rgba());
+        }
+
+        span!(DEBUG, "composite_pixels");
+
+        let pixel_count = self.width() as usize * self.height() as usize;
+        let mut pixels = vec![0; pixel_count * format.bytes_per_pixel()];
+        if format == PixelFormat::Rgba16 && self.depth() == PsdDepth::Sixteen {
+            self.write_sixteen_bit_pixels(&mut pixels);
+        } else {
+            self.generate_pixels_into(format, &mut pixels);
+        }
+
+        pixels
+    }
+
+    /// Write the 16 bit values of a 16 bit composite image into zeroed [`PixelFormat::Rgba16`]
+    /// pixels
+    fn write_sixteen_bit_pixels(&self, pixels: &mut [u8]) {
+        let section = &self.image_data_section;
+        let channel = |kind| {
+            self.composite_channel_idx(kind)
+                .and_then(|idx| section.sixteen_bit_channel(idx))
+        };
+
+        let red = channel(PsdChannelKind::Red).unwrap_or_default();
+        // Single channel images (such as grayscale) use the first channel for every color
+        let green = channel(PsdChannelKind::Green);
+        let blue = channel(PsdChannelKind::Blue);
+        let alpha = channel(PsdChannelKind::TransparencyMask);
+
+        let channels = [
+            Some(&red),
+            Some(green.as_ref().unwrap_or(&red)),
+            Some(blue.as_ref().unwrap_or(&red)),
+            alpha.as_ref(),
+        ];
+        for (pixel_idx, pixel) in pixels.chunks_exact_mut(8).enumerate() {
+            for (offset, channel) in channels.iter().enumerate() {
+                // If there is no transparency data then the image is opaque
+                let value = match channel {
+                    Some(channel) => channel.get(pixel_idx).copied().unwrap_or(0),
+                    None => u16::MAX,
+                };
+                pixel[offset * 2..offset * 2 + 2].copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+    }
+
+    /// Like [`Psd::rgba`], but writes the pixels into a buffer instead of allocating a new one,
+    /// so that the buffer can be reused.
+    ///
+    /// The buffer must be `width * height * 4` bytes long.
+    pub fn rgba_into(&self, rgba: &mut [u8]) -> Result<(), PsdError> {
+        self.check_rgba_buffer(rgba)?;
+
+        rgba.fill(0);
+        self.write_rgba(rgba);
+        self.orient_in_place(rgba);
+
+        Ok(())
+    }
+
+    /// The orientation from the EXIF data of a photo that was brought into Photoshop, if the
+    /// PSD has one. See [`PsdOptions::apply_exif_orientation`].
+    pub fn exif_orientation(&self) -> Option<ExifOrientation> {
+        self.image_resources_section.exif_orientation
+    }
+
+    /// The width and height of [`Psd::rgba`] and the flattened layers, which are swapped when
+    /// the EXIF orientation is applied and turns the image sideways.
+    pub fn oriented_size(&self) -> (u32, u32) {
+        match self.applied_exif_orientation() {
+            Some(orientation) => orientation.oriented_size(self.width(), self.height()),
+            None => (self.width(), self.height()),
+        }
+    }
+
+    /// The EXIF orientation that is applied to the composite image and the flattened layers
+    fn applied_exif_orientation(&self) -> Option<ExifOrientation> {
+        self.exif_orientation()
+            .filter(|_| self.apply_exif_orientation)
+            .filter(|orientation| *orientation != ExifOrientation::Normal)
+    }
+
+    /// Apply the EXIF orientation (if it is applied at all) to pixels the size of the PSD
+    fn orient(&self, rgba: Vec<u8>) -> Vec<u8> {
+        match self.applied_exif_orientation() {
+            Some(orientation) => orientation.apply(&rgba, self.width(), self.height()),
+            None => rgba,
+        }
+    }
+
+    /// Like `orient`, but for a buffer of the right length that is overwritten
+    pub(crate) fn orient_in_place(&self, rgba: &mut [u8]) {
+        if let Some(orientation) = self.applied_exif_orientation() {
+            let oriented = orientation.apply(rgba, self.width(), self.height());
+            rgba.copy_from_slice(&oriented);
+        }
+    }
+
+    /// A hash of the PSD's size and [`Psd::rgba`] pixels, so that revisions of a PSD that look
+    /// the same can be found without keeping or comparing the pixels of both.
+    ///
+    /// Like [`PsdLayer::content_hash`] this is [xxHash64](https://github.com/Cyan4973/xxHash),
+    /// so it is the same on every platform and can be stored.
+    pub fn composite_hash(&self) -> u64 {
+        let (width, height) = self.oriented_size();
+
+        let mut hasher = XxHash64::with_seed(0);
+        hasher.write(&width.to_le_bytes());
+        hasher.write(&height.to_le_bytes());
+        hasher.write(&self.rgba());
+
+        hasher.finish()
+    }
+
+    /// The `n` most common colors of [`Psd::rgba`], most common first, such as for theming a UI
+    /// to match a design. See [`color::dominant_colors`].
+    pub fn dominant_colors(&self, n: usize) -> Vec<DominantColor> {
+        color::dominant_colors(&self.rgba(), n)
+    }
+
+    /// Write the RGBA pixels of the PSD into a zeroed buffer of the right length
+    fn write_rgba(&self, rgba: &mut [u8]) {
+        self.write_composite_rgba(rgba);
+
+        if self.clear_transparent_pixels {
+            blend::clear_transparent_pixels(rgba);
+        }
+    }
+
+    /// Write the pixels of the composite image, or the flattened layers when there isn't one,
+    /// into a zeroed buffer of the right length
+    fn write_composite_rgba(&self, rgba: &mut [u8]) {
+        span!(DEBUG, "composite_rgba");
+
+        if !self.has_composite_image() {
+            if !self.layers().is_empty() {
+                self.flatten_into(rgba, &|_| true, &blend_rgba);
+                return;
+            }
+
+            if self.image_data_section.is_empty() {
+                return;
+            }
+        }
+
+        if let Some(rgba32f) = self.rgba32f() {
+            tone_map_into(rgba, &rgba32f, &ToneMapOptions::default());
+            return;
+        }
+
+        self.generate_rgba_into(rgba);
+
+        // The cyan, magenta and yellow channels were placed into red, green and blue, so we
+        // darken them by the black channel.
+        if self.color_mode() == ColorMode::Cmyk {
+            if let Some(black) = self.image_data_section.channel(3) {
+                let black = match black {
+                    ChannelBytes::RawData(black) => Cow::Borrowed(&black[..]),
+                    ChannelBytes::RleCompressed(black, _) => Cow::Owned(rle_decompress(black)),
+                };
+
+                apply_black_channel(rgba, &black);
+            }
+        }
+    }
+
+    /// The RGBA floats of a 32 bit PSD's composite image, in linear light.
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// Colors can be brighter than 1.0, see [`Psd::rgba_tone_mapped`] to bring them into
+    /// range. Returns `None` if the PSD isn't 32 bit or doesn't have a composite image.
+    pub fn rgba32f(&self) -> Option<Vec<f32>> {
+        if self.depth() != PsdDepth::ThirtyTwo || !self.has_composite_image() {
+            return None;
+        }
+
+        let section = &self.image_data_section;
+        let color_channel_count = self.color_mode().color_channel_count();
+
+        let red = section.float_channel(0)?;
+        // Single channel images (such as grayscale) use the first channel for every color
+        let (green, blue) = match color_channel_count {
+            1 => (red, red),
+            _ => (section.float_channel(1)?, section.float_channel(2)?),
+        };
+        let alpha = match self.color_mode() {
+            ColorMode::Multichannel => None,
+            _ => section.float_channel(color_channel_count),
+        };
+
+        let mut rgba32f = Vec::with_capacity(red.len() * 4);
+        for (idx, red) in red.iter().enumerate() {
+            // If there is no transparency data then the image is opaque
+            let alpha = alpha.map(|alpha| alpha[idx]).unwrap_or(1.);
+            rgba32f.extend_from_slice(&[*red, green[idx], blue[idx], alpha]);
+        }
+
+        Some(rgba32f)
+    }
+
+    /// Like [`Psd::rgba`], but with control over how the colors of a 32 bit PSD are brought
+    /// into the range of a byte.
+    ///
+    /// [`Psd::rgba`] uses [`ToneMapOptions::default`]. PSDs that aren't 32 bit are returned as is.
+    pub fn rgba_tone_mapped(&self, options: &ToneMapOptions) -> Vec<u8> {
+        match self.rgba32f() {
+            Some(rgba32f) => {
+                let mut rgba = vec![0; self.rgba_len()];
+                tone_map_into(&mut rgba, &rgba32f, options);
+                self.orient(rgba)
+            }
+            None => self.rgba(),
+        }
+    }
+
+    /// The number of bytes of the PSD's RGBA pixels
+    fn rgba_len(&self) -> usize {
+        self.width() as usize * self.height() as usize * 4
+    }
+
+    /// Make sure that the RGBA buffer the size of the PSD that each layer passing the filter is
+    /// decoded into, plus `extra_buffers` more of them, fit within
+    /// [`PsdOptions::max_decoded_bytes`]
+    fn check_flatten_bytes(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        extra_buffers: u64,
+    ) -> Result<(), PsdError> {
+        let buffer_count = self.layers_to_flatten(filter).len() as u64 + extra_buffers;
+        let bytes = (self.rgba_len() as u64)
+            .checked_mul(buffer_count)
+            .ok_or(PsdError::LimitError(PsdLimitError::SizeOverflow))?;
+        if bytes > self.max_decoded_bytes {
+            return Err(PsdError::LimitError(PsdLimitError::TooManyDecodedBytes {
+                bytes,
+                max: self.max_decoded_bytes,
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn check_rgba_buffer(&self, rgba: &[u8]) -> Result<(), PsdError> {
+        let expected = self.rgba_len();
+        if rgba.len() != expected {
+            return Err(PsdError::InvalidBufferLength {
+                expected,
+                actual: rgba.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Whether the PSD holds a real composite image of all of its layers.
+    ///
+    /// PSDs that were saved with "Maximize Compatibility" turned off have an empty or
+    /// placeholder composite image, so the layers need to be flattened to see the final image.
+    pub fn has_composite_image(&self) -> bool {
+        self.image_resources_section.has_real_merged_data != Some(false)
+            && !self.image_data_section.is_empty()
     }
 
     /// Get the compression level for the flattened image data
     pub fn compression(&self) -> &PsdChannelCompression {
         &self.image_data_section.compression
     }
+
+    /// The decompressed plane of one of the composite image's channels, one byte per pixel in
+    /// scan-line order, for working with planar data without converting it into RGBA.
+    ///
+    /// The color channels are stored in the order of the color mode, so [`PsdChannelKind::Red`]
+    /// is the gray of a grayscale image and the cyan of a CMYK image. Grayscale images don't
+    /// have green or blue channels. [`PsdChannelKind::Spot`] gets a channel by its position, such
+    /// as the black of a CMYK image (3) or the spot and alpha channels that follow the color and
+    /// transparency channels. The bytes are as Photoshop stored them, so CMYK channels are
+    /// inverted (0 is full ink).
+    ///
+    /// Returns `None` if the channel isn't in the composite image, or if there is no composite
+    /// image (see [`Psd::has_composite_image`]).
+    pub fn composite_channel(&self, channel: PsdChannelKind) -> Option<Cow<'_, [u8]>> {
+        if !self.has_composite_image() {
+            return None;
+        }
+
+        let channel_bytes = match channel {
+            PsdChannelKind::Red => Some(self.red()),
+            PsdChannelKind::Green => self.green(),
+            PsdChannelKind::Blue => self.blue(),
+            PsdChannelKind::TransparencyMask => self.alpha(),
+            PsdChannelKind::Spot(idx) => self.image_data_section.channel(idx as usize),
+            PsdChannelKind::UserSuppliedLayerMask | PsdChannelKind::RealUserSuppliedLayerMask => {
+                None
+            }
+        }?;
+
+        Some(match channel_bytes {
+            ChannelBytes::RawData(bytes) => Cow::Borrowed(&bytes[..]),
+            ChannelBytes::RleCompressed(bytes, _) => Cow::Owned(rle_decompress(bytes)),
+        })
+    }
+
+    /// The alpha and spot channels that follow the color and transparency channels, such as
+    /// saved selections, named after the PSD's alpha channel names. Use
+    /// [`ExtraChannel::kind`] with [`Psd::composite_channel`] to get their pixels.
+    ///
+    /// The names are given to the channels in order. When there is a name for every channel
+    /// after the color channels, the first of them is an alpha channel instead of the
+    /// transparency, as in a PSD without layers that has a saved selection.
+    pub fn extra_channels(&self) -> Vec<ExtraChannel> {
+        let names = self.alpha_channel_names();
+        let channel_count = self.file_header_section.channel_count.count() as usize;
+
+        // Every channel of a multichannel image is an ink with its own name
+        let first = match self.color_mode() {
+            ColorMode::Multichannel => 0,
+            color_mode => color_mode.color_channel_count(),
+        };
+        let first = match channel_count.saturating_sub(first) <= names.len() {
+            true => first,
+            false => first + 1,
+        };
+
+        (first..channel_count)
+            .enumerate()
+            .map(|(name_idx, index)| ExtraChannel {
+                index,
+                name: names.get(name_idx).cloned(),
+            })
+            .collect()
+    }
+
+    /// Get the extra channel with the name, see [`Psd::extra_channels`]
+    pub fn extra_channel_by_name(&self, name: &str) -> Option<ExtraChannel> {
+        self.extra_channels()
+            .into_iter()
+            .find(|channel| channel.name() == Some(name))
+    }
 }
 
 // Methods for working with the image resources section
@@ -377,6 +1955,125 @@ impl Psd {
     pub fn resources(&self) -> &Vec<ImageResource> {
         &self.image_resources_section.resources
     }
+
+    /// The paths that were saved in the PSD's paths panel, in the order that they were stored.
+    ///
+    /// Path points are relative to the size of the document, see [`PathPoint::to_pixels`].
+    pub fn paths(&self) -> &[PsdPath] {
+        &self.image_resources_section.paths
+    }
+
+    /// The work path, which holds a path that has been drawn but not yet saved
+    pub fn work_path(&self) -> Option<&PsdPath> {
+        self.image_resources_section.work_path.as_ref()
+    }
+
+    /// The saved path that is used as the document's clipping path, for example when placing
+    /// the image in a page layout for printing.
+    pub fn clipping_path(&self) -> Option<&PsdPath> {
+        let clipping_path = self.image_resources_section.clipping_path.as_ref()?;
+
+        self.paths()
+            .iter()
+            .find(|path| path.name() == clipping_path.name)
+    }
+
+    /// The flatness of the clipping path, in device pixels.
+    ///
+    /// A flatness of `0.0` means that the printer's default flatness is used.
+    pub fn clipping_path_flatness(&self) -> Option<f64> {
+        let clipping_path = self.image_resources_section.clipping_path.as_ref()?;
+
+        self.clipping_path().map(|_| clipping_path.flatness)
+    }
+
+    /// The names of the alpha and spot channels, in the order of the channels. See
+    /// [`Psd::extra_channels`] for the channels that they name.
+    pub fn alpha_channel_names(&self) -> &[String] {
+        &self.image_resources_section.alpha_names
+    }
+
+    /// The resolution of the image, if the PSD stores it
+    pub fn resolution(&self) -> Option<&ResolutionInfo> {
+        self.image_resources_section.resolution.as_ref()
+    }
+
+    /// The angle of the document's global light in degrees, which the drop shadows of layers
+    /// that use the global light are cast away from. 120 if the PSD doesn't store it.
+    pub fn global_light_angle(&self) -> f64 {
+        self.image_resources_section
+            .global_angle
+            .map_or(DEFAULT_GLOBAL_LIGHT_ANGLE, f64::from)
+    }
+
+    /// The ICC profile that the document's colors are in, if one was embedded when saving
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.image_resources_section.icc_profile.as_deref()
+    }
+
+    /// The URL that the whole image links to when it is saved for the web, if the PSD stores one
+    pub fn url(&self) -> Option<&str> {
+        self.image_resources_section.url.as_deref()
+    }
+
+    /// The URLs of the PSD's URL list, which links the slices of the image when it is saved for
+    /// the web. See [`Psd::resources`] for the slices themselves.
+    pub fn url_list(&self) -> &[UrlListEntry] {
+        &self.image_resources_section.url_list
+    }
+
+    /// The points that were placed with the color sampler tool
+    pub fn color_samplers(&self) -> &[ColorSampler] {
+        &self.image_resources_section.color_samplers
+    }
+
+    /// The RGBA color of the final image under each of the color samplers, in the same order as
+    /// [`Psd::color_samplers`].
+    ///
+    /// Samplers that are outside of the canvas have no color.
+    pub fn sampled_colors(&self) -> Vec<Option<[u8; 4]>> {
+        if self.color_samplers().is_empty() {
+            return vec![];
+        }
+
+        // The samplers are positioned within the document, before any EXIF orientation
+        let rgba = self.rgba_unoriented();
+
+        self.color_samplers()
+            .iter()
+            .map(|sampler| {
+                let (x, y) = sampler.pixel(self.width(), self.height())?;
+                let idx = (y as usize * self.width() as usize + x as usize) * 4;
+
+                let mut pixel = [0; 4];
+                pixel.copy_from_slice(&rgba[idx..idx + 4]);
+                Some(pixel)
+            })
+            .collect()
+    }
+}
+
+impl Psd {
+    /// The position of the composite image's channel that holds the red, green, blue or
+    /// transparency, or `None` if the color mode doesn't have the channel
+    fn composite_channel_idx(&self, channel: PsdChannelKind) -> Option<usize> {
+        let color_channel_count = self.color_mode().color_channel_count();
+
+        match channel {
+            PsdChannelKind::Red => Some(0),
+            // Single channel images (such as grayscale) use the first channel for every color
+            PsdChannelKind::Green if color_channel_count > 1 => Some(1),
+            PsdChannelKind::Blue if color_channel_count > 1 => Some(2),
+            PsdChannelKind::TransparencyMask => match self.color_mode() {
+                // Multichannel images don't have transparency, each channel is an ink
+                ColorMode::Multichannel => None,
+                // The transparency comes right after the color channels, so the second channel
+                // of a grayscale image and the fourth channel of an RGB image.
+                _ => Some(color_channel_count),
+            },
+            _ => None,
+        }
+    }
 }
 
 impl IntoRgba for Psd {
@@ -386,25 +2083,28 @@ impl IntoRgba for Psd {
         idx
     }
 
+    fn rgba_row_len(&self) -> usize {
+        self.width() as usize
+    }
+
     fn red(&self) -> &ChannelBytes {
-        &self.image_data_section.red
+        // The header guarantees that there is at least one channel
+        self.image_data_section.channel(0).unwrap()
     }
 
     fn green(&self) -> Option<&ChannelBytes> {
-        match self.color_mode() {
-            // For 16 bit grayscale images I'm sometimes seeing two channels.
-            // Really not sure what the second channel is so until we know what it is we're ignoring it..
-            ColorMode::Grayscale => None,
-            _ => self.image_data_section.green.as_ref(),
-        }
+        self.composite_channel_idx(PsdChannelKind::Green)
+            .and_then(|idx| self.image_data_section.channel(idx))
     }
 
     fn blue(&self) -> Option<&ChannelBytes> {
-        self.image_data_section.blue.as_ref()
+        self.composite_channel_idx(PsdChannelKind::Blue)
+            .and_then(|idx| self.image_data_section.channel(idx))
     }
 
     fn alpha(&self) -> Option<&ChannelBytes> {
-        self.image_data_section.alpha.as_ref()
+        self.composite_channel_idx(PsdChannelKind::TransparencyMask)
+            .and_then(|idx| self.image_data_section.channel(idx))
     }
 
     fn psd_width(&self) -> u32 {
@@ -416,6 +2116,18 @@ impl IntoRgba for Psd {
     }
 }
 
+/// Convert pixels whose red, green and blue hold the cyan, magenta and yellow of a CMYK image
+/// into RGB.
+///
+/// PSDs store CMYK values inverted, so 255 means no ink.
+fn apply_black_channel(rgba: &mut [u8], black: &[u8]) {
+    for (pixel, black) in rgba.chunks_exact_mut(4).zip(black) {
+        for channel in &mut pixel[..3] {
+            *channel = color::apply_black(*channel, *black);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::sections::file_header_section::FileHeaderSectionError;
@@ -0,0 +1,190 @@
+use thiserror::Error;
+
+use crate::sections::file_header_section::PsdDepth;
+
+/// Options that control how a PSD is parsed.
+///
+/// The limits guard against files that claim to be much larger than they are. A file that is
+/// only a few kilobytes can describe a huge canvas, or thousands of layers, that would take
+/// gigabytes of memory to decode. Servers that parse untrusted PSDs should lower these limits
+/// to fit their memory budget.
+///
+/// # Example
+///
+/// ```ignore
+/// let options = PsdOptions {
+///     max_width: 4096,
+///     max_height: 4096,
+///     ..PsdOptions::default()
+/// };
+///
+/// let psd = Psd::from_bytes_with_options(psd_bytes, &options)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsdOptions {
+    /// The maximum width of the PSD, in pixels. Defaults to 30,000, the largest width that
+    /// Photoshop allows for a PSD.
+    pub max_width: u32,
+    /// The maximum height of the PSD, in pixels. Defaults to 30,000, the largest height that
+    /// Photoshop allows for a PSD.
+    pub max_height: u32,
+    /// The maximum number of layers (including group dividers). Defaults to 8,000, the most
+    /// layers that Photoshop allows.
+    pub max_layer_count: usize,
+    /// The maximum number of bytes that the PSD decodes to. When parsing, this counts the
+    /// channels of the composite image and every layer and the patterns. When flattening with
+    /// [`crate::Psd::flatten_layers_rgba`], it counts the RGBA buffer the size of the canvas
+    /// that each flattened layer is decoded into. Defaults to 16 GiB, which fits the RGBA
+    /// channels of a 32 bit PSD as large as `max_width` and `max_height` allow.
+    ///
+    /// The default accepts every PSD that Photoshop can save, not what fits in memory. Callers
+    /// that parse untrusted PSDs should set a limit that fits their memory budget.
+    pub max_decoded_bytes: u64,
+    /// Skip layer records and image resources that are malformed instead of failing to parse
+    /// the PSD, such as the partly corrupt files that old versions of Photoshop sometimes saved.
+    /// Defaults to false.
+    ///
+    /// Use [`crate::Psd::from_bytes_with_warnings`] to find out what was skipped.
+    pub recover: bool,
+    /// Keep the RGBA pixels of each layer after [`crate::PsdLayer::rgba`] interleaves them, so
+    /// that calling it again only copies them. Defaults to false.
+    ///
+    /// This trades memory for speed, such as for a viewer that renders the same layers many
+    /// times. Use [`crate::Psd::clear_pixel_caches`] to free the memory.
+    pub cache_layer_rgba: bool,
+    /// Set the red, green and blue of fully transparent pixels to 0 in the pixels that are
+    /// returned by [`crate::Psd::rgba`], [`crate::PsdLayer::rgba`], the flattening methods and
+    /// [`crate::compositor::Compositor`]. Defaults to false.
+    ///
+    /// PSDs store whatever color was last painted under a transparent pixel, so this keeps
+    /// golden image tests from failing when only those hidden colors change.
+    pub clear_transparent_pixels: bool,
+    /// Multiply each layer's vector mask (see [`crate::PsdLayer::rasterize_vector_mask`]) into
+    /// the alpha of [`crate::PsdLayer::rgba`], so that shape layers and other vector masked
+    /// layers are flattened with their masks. Defaults to false.
+    pub rasterize_vector_masks: bool,
+    /// Rotate or flip the pixels that are returned by [`crate::Psd::rgba`] and the flattening
+    /// methods by the PSD's EXIF orientation (see [`crate::Psd::exif_orientation`]), so that a
+    /// photo brought into Photoshop is shown upright like in an image viewer. Defaults to false.
+    ///
+    /// When the photo is turned sideways the pixels are [`crate::Psd::oriented_size`] instead
+    /// of the PSD's width and height. The layers, [`crate::Psd::flatten_rows`] and
+    /// [`crate::compositor::Compositor`] keep the document's orientation.
+    pub apply_exif_orientation: bool,
+}
+
+impl Default for PsdOptions {
+    fn default() -> Self {
+        PsdOptions {
+            max_width: 30_000,
+            max_height: 30_000,
+            max_layer_count: 8_000,
+            max_decoded_bytes: 16 * 1024 * 1024 * 1024,
+            recover: false,
+            cache_layer_rgba: false,
+            clear_transparent_pixels: false,
+            rasterize_vector_masks: false,
+            apply_exif_orientation: false,
+        }
+    }
+}
+
+/// A PSD was larger than one of the limits in its [`PsdOptions`]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PsdLimitError {
+    /// The PSD's width or height is larger than `max_width` or `max_height`
+    #[error("The PSD is {width}x{height}, larger than the limit of {max_width}x{max_height}.")]
+    CanvasTooLarge {
+        /// The width of the PSD
+        width: u32,
+        /// The height of the PSD
+        height: u32,
+        /// The largest width that is allowed
+        max_width: u32,
+        /// The largest height that is allowed
+        max_height: u32,
+    },
+    /// The PSD has more than `max_layer_count` layers
+    #[error("The PSD has {count} layers, more than the limit of {max}.")]
+    TooManyLayers {
+        /// The number of layers in the PSD
+        count: usize,
+        /// The most layers that are allowed
+        max: usize,
+    },
+    /// The PSD decodes to more than `max_decoded_bytes`
+    #[error("The PSD decodes to {bytes} bytes, more than the limit of {max}.")]
+    TooManyDecodedBytes {
+        /// The number of bytes that the PSD decodes to
+        bytes: u64,
+        /// The most bytes that are allowed
+        max: u64,
+    },
+    /// Computing the size of the PSD's decoded data overflowed
+    #[error("The size of the PSD's decoded data does not fit in memory.")]
+    SizeOverflow,
+}
+
+impl PsdOptions {
+    /// Options without any limits, for reading PSDs that we wrote ourselves
+    pub(crate) fn unlimited() -> PsdOptions {
+        PsdOptions {
+            max_width: u32::MAX,
+            max_height: u32::MAX,
+            max_layer_count: usize::MAX,
+            max_decoded_bytes: u64::MAX,
+            ..PsdOptions::default()
+        }
+    }
+
+    /// Make sure that the PSD's canvas is within the limits, and that an RGBA buffer the size
+    /// of the canvas can be addressed.
+    pub(crate) fn check_canvas(&self, width: u32, height: u32) -> Result<(), PsdLimitError> {
+        if width > self.max_width || height > self.max_height {
+            return Err(PsdLimitError::CanvasTooLarge {
+                width,
+                height,
+                max_width: self.max_width,
+                max_height: self.max_height,
+            });
+        }
+
+        // We use u32's for the pixel indices of RGBA buffers
+        width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or(PsdLimitError::SizeOverflow)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn check_layer_count(&self, count: usize) -> Result<(), PsdLimitError> {
+        if count > self.max_layer_count {
+            return Err(PsdLimitError::TooManyLayers {
+                count,
+                max: self.max_layer_count,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn check_decoded_bytes(&self, bytes: u64) -> Result<(), PsdLimitError> {
+        if bytes > self.max_decoded_bytes {
+            return Err(PsdLimitError::TooManyDecodedBytes {
+                bytes,
+                max: self.max_decoded_bytes,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of bytes that a channel of the given size decompresses to. Every row starts on
+/// a new byte, so the rows of 1 bit channels are padded.
+pub(crate) fn channel_byte_count(width: u64, height: u64, depth: PsdDepth) -> Option<u64> {
+    let row_len = width.checked_mul(depth as u64)?.div_ceil(8);
+
+    row_len.checked_mul(height)
+}
@@ -0,0 +1,381 @@
+//! Change a few fields of a PSD directly in its bytes.
+//!
+//! Unlike writing the PSD again, patching leaves every byte that isn't being changed as it was,
+//! including everything that this crate doesn't parse. Only the changed fields and the lengths
+//! of the sections that they're in are rewritten.
+//!
+//! ```ignore
+//! let bytes = std::fs::read("my-psd-file.psd")?;
+//!
+//! let patched = PsdPatch::new()
+//!     .layer_name(0, "Renamed")
+//!     .layer_visible(1, false)
+//!     .apply(&bytes)?;
+//!
+//! std::fs::write("my-psd-file.psd", patched)?;
+//! ```
+
+use core::ops::Range;
+
+use thiserror::Error;
+
+use crate::prelude::*;
+use crate::sections::{PsdCursor, PsdCursorError};
+use crate::{Psd, PsdError};
+
+/// The key of the additional layer information that holds the layer's unicode name
+const KEY_UNICODE_LAYER_NAME: &[u8; 4] = b"luni";
+
+/// Changes to make to the fields of a PSD, without writing the rest of it again.
+///
+/// Layers are referred to by their index in [`Psd::layers`] of the unpatched PSD.
+#[derive(Debug, Clone, Default)]
+pub struct PsdPatch {
+    layer_names: Vec<(usize, String)>,
+    layer_visibility: Vec<(usize, bool)>,
+    resources: Vec<(i16, Vec<u8>)>,
+}
+
+/// A PSD could not be patched
+#[derive(Debug, PartialEq, Error)]
+pub enum PsdPatchError {
+    /// The bytes could not be parsed as a PSD
+    #[error("Failed to parse the PSD to patch: '{0}'.")]
+    InvalidPsd(PsdError),
+
+    /// There is no layer at the index that was patched
+    #[error("There is no layer at index {layer_idx} to patch.")]
+    LayerNotFound {
+        /// The index of the layer in [`Psd::layers`]
+        layer_idx: usize,
+    },
+
+    /// The record of the layer that was patched is cut short
+    #[error("The record of layer {layer_idx} is cut short.")]
+    TruncatedLayerRecord {
+        /// The index of the layer in [`Psd::layers`]
+        layer_idx: usize,
+    },
+
+    /// The name of the image resource that was patched is cut short
+    #[error("The name of image resource {resource_id} is cut short.")]
+    TruncatedResource {
+        /// The ID of the image resource
+        resource_id: i16,
+    },
+}
+
+/// Bytes that replace a range of the original PSD
+struct Splice {
+    range: Range<usize>,
+    bytes: Vec<u8>,
+}
+
+/// Where the fields that can be patched are within a layer record
+struct RecordFields {
+    flags: usize,
+    extra_data_len: usize,
+    name: Range<usize>,
+    /// The additional layer information that holds the unicode name, if there is one
+    unicode_name: Option<Range<usize>>,
+}
+
+impl PsdPatch {
+    /// A patch that doesn't change anything yet
+    pub fn new() -> PsdPatch {
+        PsdPatch::default()
+    }
+
+    /// Rename a layer.
+    ///
+    /// Both the layer's Pascal string name and its unicode name are written, so names that
+    /// aren't ASCII are kept.
+    pub fn layer_name(mut self, layer_idx: usize, name: impl Into<String>) -> PsdPatch {
+        self.layer_names.retain(|(idx, _)| *idx != layer_idx);
+        self.layer_names.push((layer_idx, name.into()));
+        self
+    }
+
+    /// Show or hide a layer.
+    pub fn layer_visible(mut self, layer_idx: usize, visible: bool) -> PsdPatch {
+        self.layer_visibility.retain(|(idx, _)| *idx != layer_idx);
+        self.layer_visibility.push((layer_idx, visible));
+        self
+    }
+
+    /// Replace the data of the image resource with the given ID, keeping its name. The resource
+    /// is added to the end of the image resources section if the PSD doesn't have it.
+    pub fn resource(mut self, resource_id: i16, data: Vec<u8>) -> PsdPatch {
+        self.resources.retain(|(id, _)| *id != resource_id);
+        self.resources.push((resource_id, data));
+        self
+    }
+
+    /// Patch the bytes of a PSD, returning the patched bytes.
+    pub fn apply(&self, bytes: &[u8]) -> Result<Vec<u8>, PsdPatchError> {
+        let psd = Psd::from_bytes(bytes).map_err(PsdPatchError::InvalidPsd)?;
+        let layout = psd.section_layout();
+
+        let mut splices = vec![];
+        let record_fields = |layer_idx: usize| {
+            let record = layout
+                .layer_record(layer_idx)
+                .ok_or(PsdPatchError::LayerNotFound { layer_idx })?;
+
+            RecordFields::find(bytes, record.start)
+                .map_err(|_| PsdPatchError::TruncatedLayerRecord { layer_idx })
+        };
+
+        for (layer_idx, visible) in self.layer_visibility.iter() {
+            let flags = record_fields(*layer_idx)?.flags;
+
+            // Photoshop sets the second bit when the layer is hidden
+            let flag = if *visible {
+                bytes[flags] & !(1 << 1)
+            } else {
+                bytes[flags] | (1 << 1)
+            };
+            splices.push(Splice {
+                range: flags..flags + 1,
+                bytes: vec![flag],
+            });
+        }
+
+        let mut layer_info_growth = 0;
+        for (layer_idx, name) in self.layer_names.iter() {
+            let fields = record_fields(*layer_idx)?;
+
+            let pascal_name = pascal_name(name);
+            let unicode_name = unicode_name(name);
+            // Added right after the Pascal string name if the layer doesn't have one yet
+            let unicode_name_range = fields
+                .unicode_name
+                .clone()
+                .unwrap_or(fields.name.end..fields.name.end);
+
+            let growth = pascal_name.len() as i64 - fields.name.len() as i64
+                + unicode_name.len() as i64
+                - unicode_name_range.len() as i64;
+            layer_info_growth += growth;
+
+            splices.push(grown_len(bytes, fields.extra_data_len, growth));
+            splices.push(Splice {
+                range: fields.name,
+                bytes: pascal_name,
+            });
+            splices.push(Splice {
+                range: unicode_name_range,
+                bytes: unicode_name,
+            });
+        }
+
+        let mut resources_growth = 0;
+        for (resource_id, data) in self.resources.iter() {
+            let existing = layout
+                .resources()
+                .iter()
+                .find(|(id, _)| id == resource_id)
+                .map(|(_, range)| range.clone());
+
+            let (range, block) = match existing {
+                Some(range) => {
+                    let header = resource_header(bytes, range.start).map_err(|_| {
+                        PsdPatchError::TruncatedResource {
+                            resource_id: *resource_id,
+                        }
+                    })?;
+                    (range, resource_block(header, data))
+                }
+                None => {
+                    let end = layout.image_resources().end;
+
+                    // With an empty name
+                    let mut header = b"8BIM".to_vec();
+                    header.extend_from_slice(&resource_id.to_be_bytes());
+                    header.extend_from_slice(&[0, 0]);
+
+                    (end..end, resource_block(&header, data))
+                }
+            };
+
+            resources_growth += block.len() as i64 - range.len() as i64;
+            splices.push(Splice {
+                range,
+                bytes: block,
+            });
+        }
+
+        if resources_growth != 0 {
+            let start = layout.image_resources().start;
+            splices.push(grown_len(bytes, start, resources_growth));
+        }
+        if layer_info_growth != 0 {
+            // The length of the layer and mask information section is followed by the length
+            // of the layer info within it
+            let start = layout.layer_and_mask().start;
+            splices.push(grown_len(bytes, start, layer_info_growth));
+            splices.push(grown_len(bytes, start + 4, layer_info_growth));
+        }
+
+        Ok(splice(bytes, splices))
+    }
+}
+
+impl RecordFields {
+    /// Find the fields of the layer record that starts at the given position.
+    ///
+    /// | Length   | Description                                                                         |
+    /// |----------|-------------------------------------------------------------------------------------|
+    /// | 4 * 4    | Rectangle containing the contents of the layer: top, left, bottom, right            |
+    /// | 2        | Number of channels in the layer                                                     |
+    /// | 6 * n    | Channel information: 2 bytes for the channel ID and 4 for the length of its data    |
+    /// | 4        | Blend mode signature: '8BIM'                                                        |
+    /// | 4        | Blend mode key                                                                      |
+    /// | 1        | Opacity                                                                             |
+    /// | 1        | Clipping                                                                            |
+    /// | 1        | Flags                                                                               |
+    /// | 1        | Filler (zero)                                                                       |
+    /// | 4        | Length of the extra data field                                                      |
+    /// | Variable | Layer mask data, layer blending ranges, layer name and additional layer information |
+    fn find(bytes: &[u8], record_start: usize) -> Result<RecordFields, PsdCursorError> {
+        let mut cursor = PsdCursor::new(bytes);
+        cursor.seek(record_start as u64 + 16);
+
+        let channel_count = cursor.read_u16()?;
+        cursor.read(6 * channel_count as u32)?;
+
+        // Blend mode signature and key, opacity and clipping
+        cursor.read(10)?;
+        let flags = cursor.position() as usize;
+        cursor.read(2)?;
+
+        let extra_data_len = cursor.position() as usize;
+        let extra_data_end = cursor.read_u32()? as u64 + cursor.position();
+
+        // Layer mask data and blending ranges
+        let mask_len = cursor.read_u32()?;
+        cursor.read(mask_len)?;
+        let blending_ranges_len = cursor.read_u32()?;
+        cursor.read(blending_ranges_len)?;
+
+        // Padded to a multiple of 4 bytes
+        let name_start = cursor.position() as usize;
+        let name_len = cursor.read_u8()? as usize;
+        let padding = (4 - (name_len + 1) % 4) % 4;
+        let name = name_start..name_start + name_len + 1 + padding;
+        cursor.seek(name.end as u64);
+
+        let mut unicode_name = None;
+        while cursor.position() + 12 <= extra_data_end {
+            let start = cursor.position() as usize;
+            let signature = cursor.read_4()?;
+            if signature != b"8BIM" && signature != b"8B64" {
+                break;
+            }
+
+            let key = cursor.read_4()? == KEY_UNICODE_LAYER_NAME;
+            let len = cursor.read_u32()?;
+            cursor.read(len)?;
+
+            if key {
+                unicode_name = Some(start..cursor.position() as usize);
+                break;
+            }
+        }
+
+        Ok(RecordFields {
+            flags,
+            extra_data_len,
+            name,
+            unicode_name,
+        })
+    }
+}
+
+/// The name as a Pascal string padded to a multiple of 4 bytes. Since Photoshop reads it as
+/// Mac Roman, anything but ASCII is replaced, and the full name is stored in the unicode name.
+fn pascal_name(name: &str) -> Vec<u8> {
+    let mut bytes = vec![0];
+    bytes.extend(
+        name.chars()
+            .take(255)
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' }),
+    );
+    bytes[0] = (bytes.len() - 1) as u8;
+
+    pad_to_multiple_of_4(&mut bytes);
+    bytes
+}
+
+/// The additional layer information that holds the unicode name, padded to a multiple of 4
+/// bytes like Photoshop does
+fn unicode_name(name: &str) -> Vec<u8> {
+    let units: Vec<u16> = name.encode_utf16().collect();
+
+    let mut data = (units.len() as u32).to_be_bytes().to_vec();
+    for unit in units {
+        data.extend_from_slice(&unit.to_be_bytes());
+    }
+    pad_to_multiple_of_4(&mut data);
+
+    let mut bytes = b"8BIM".to_vec();
+    bytes.extend_from_slice(KEY_UNICODE_LAYER_NAME);
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&data);
+
+    bytes
+}
+
+fn pad_to_multiple_of_4(bytes: &mut Vec<u8>) {
+    let padding = (4 - bytes.len() % 4) % 4;
+    bytes.resize(bytes.len() + padding, 0);
+}
+
+/// The signature, ID and name of the resource block that starts at the given position
+fn resource_header(bytes: &[u8], block_start: usize) -> Result<&[u8], PsdCursorError> {
+    let mut cursor = PsdCursor::new(bytes);
+    cursor.seek(block_start as u64 + 6);
+    cursor.read_pascal_string()?;
+
+    Ok(&bytes[block_start..cursor.position() as usize])
+}
+
+/// A resource block with the given header and data, padded to make the size even
+fn resource_block(header: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut block = header.to_vec();
+    block.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    block.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        block.push(0);
+    }
+
+    block
+}
+
+/// Replace the 4 byte length at the given position with one that has grown by the given number
+/// of bytes
+fn grown_len(bytes: &[u8], position: usize, growth: i64) -> Splice {
+    let len = &bytes[position..position + 4];
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as i64 + growth;
+
+    Splice {
+        range: position..position + 4,
+        bytes: (len as u32).to_be_bytes().to_vec(),
+    }
+}
+
+/// Copy the bytes, replacing each of the ranges of the splices
+fn splice(bytes: &[u8], mut splices: Vec<Splice>) -> Vec<u8> {
+    splices.sort_by_key(|splice| (splice.range.start, splice.range.end));
+
+    let mut patched = Vec::with_capacity(bytes.len());
+    let mut copied = 0;
+    for splice in splices {
+        patched.extend_from_slice(&bytes[copied..splice.range.start]);
+        patched.extend_from_slice(&splice.bytes);
+        copied = splice.range.end;
+    }
+    patched.extend_from_slice(&bytes[copied..]);
+
+    patched
+}
@@ -0,0 +1,178 @@
+use crate::color::eight_to_sixteen;
+use crate::prelude::*;
+use crate::psd_channel::{interleave_channel, PsdChannelKind};
+
+/// The layout of the pixels that [`crate::Psd::pixels`] and [`crate::PsdLayer::pixels`]
+/// return, such as the layout that a GPU texture or an image crate expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// `[R, G, B, A, R, G, B, A, ...]`, one byte per channel. The same as
+    /// [`crate::Psd::rgba`].
+    Rgba8,
+    /// `[R, G, B, A, R, G, B, A, ...]`, two bytes per channel in native endian order, so that
+    /// the bytes can be cast to `u16`s.
+    ///
+    /// The composite image of a 16 bit PSD keeps its 16 bit values. Everything else is read
+    /// with 8 bits per channel, so each value is scaled up from 8 bits (255 becomes 65535).
+    Rgba16,
+    /// `[B, G, R, A, B, G, R, A, ...]`, one byte per channel
+    Bgra8,
+    /// `[R, G, B, R, G, B, ...]`, one byte per channel, without the alpha
+    Rgb8,
+}
+
+impl PixelFormat {
+    /// The number of bytes that each pixel takes up
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => 4,
+            PixelFormat::Rgba16 => 8,
+            PixelFormat::Rgb8 => 3,
+        }
+    }
+
+    /// Where the first byte of a color or transparency channel goes within each pixel, or
+    /// `None` if the format leaves the channel out.
+    pub(crate) fn channel_offset(self, channel: PsdChannelKind) -> Option<usize> {
+        let rgba_offset = channel.rgba_offset().ok()?;
+
+        match self {
+            PixelFormat::Rgba8 => Some(rgba_offset),
+            PixelFormat::Rgba16 => Some(rgba_offset * 2),
+            PixelFormat::Bgra8 => Some([2, 1, 0, 3][rgba_offset]),
+            PixelFormat::Rgb8 => Some(rgba_offset).filter(|offset| *offset < 3),
+        }
+    }
+
+    /// Write every byte of a channel into its own pixel, starting at the channel's offset
+    /// within each pixel.
+    ///
+    /// `pixels` must be at least `channel_bytes.len() * self.bytes_per_pixel()` bytes long.
+    pub(crate) fn interleave(self, pixels: &mut [u8], offset: usize, channel_bytes: &[u8]) {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => {
+                interleave_channel(pixels, offset, channel_bytes)
+            }
+            PixelFormat::Rgba16 => {
+                for (pixel, byte) in pixels.chunks_exact_mut(8).zip(channel_bytes) {
+                    pixel[offset..offset + 2]
+                        .copy_from_slice(&eight_to_sixteen(*byte).to_ne_bytes());
+                }
+            }
+            PixelFormat::Rgb8 => {
+                for (pixel, byte) in pixels.chunks_exact_mut(3).zip(channel_bytes) {
+                    pixel[offset] = *byte;
+                }
+            }
+        }
+    }
+
+    /// Make every pixel fully opaque, for images without transparency data.
+    pub(crate) fn fill_opaque(self, pixels: &mut [u8]) {
+        let offset = match self.channel_offset(PsdChannelKind::TransparencyMask) {
+            Some(offset) => offset,
+            None => return,
+        };
+        // The alpha is the last channel of every format that has one
+        for pixel in pixels.chunks_exact_mut(self.bytes_per_pixel()) {
+            pixel[offset..].fill(255);
+        }
+    }
+
+    /// Lay out RGBA pixels in this format, reusing their buffer unless the format is wider.
+    pub(crate) fn layout_rgba(self, mut rgba: Vec<u8>) -> Vec<u8> {
+        match self {
+            PixelFormat::Rgba8 => rgba,
+            PixelFormat::Rgba16 => rgba
+                .iter()
+                .flat_map(|channel| eight_to_sixteen(*channel).to_ne_bytes())
+                .collect(),
+            PixelFormat::Bgra8 => {
+                for pixel in rgba.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                rgba
+            }
+            PixelFormat::Rgb8 => {
+                let pixel_count = rgba.len() / 4;
+                for idx in 0..pixel_count {
+                    rgba.copy_within(idx * 4..idx * 4 + 3, idx * 3);
+                }
+                rgba.truncate(pixel_count * 3);
+                rgba
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RGBA: [u8; 8] = [1, 2, 3, 4, 255, 0, 128, 255];
+
+    const FORMATS: [PixelFormat; 4] = [
+        PixelFormat::Rgba8,
+        PixelFormat::Rgba16,
+        PixelFormat::Bgra8,
+        PixelFormat::Rgb8,
+    ];
+
+    /// Each format lays out the same two pixels.
+    #[test]
+    fn layout_rgba() {
+        assert_eq!(PixelFormat::Rgba8.layout_rgba(RGBA.to_vec()), RGBA);
+        assert_eq!(
+            PixelFormat::Bgra8.layout_rgba(RGBA.to_vec()),
+            [3, 2, 1, 4, 128, 0, 255, 255]
+        );
+        assert_eq!(
+            PixelFormat::Rgb8.layout_rgba(RGBA.to_vec()),
+            [1, 2, 3, 255, 0, 128]
+        );
+
+        let rgba16 = PixelFormat::Rgba16.layout_rgba(RGBA.to_vec());
+        let rgba16: Vec<u16> = rgba16
+            .chunks_exact(2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(rgba16, [257, 514, 771, 1028, 65535, 0, 32896, 65535]);
+
+        for format in FORMATS {
+            assert_eq!(
+                format.layout_rgba(RGBA.to_vec()).len(),
+                2 * format.bytes_per_pixel()
+            );
+        }
+    }
+
+    /// Interleaving each channel into zeroed pixels lays them out like `layout_rgba`, and images
+    /// without alpha are opaque.
+    #[test]
+    fn interleave_matches_layout_rgba() {
+        let channels: Vec<Vec<u8>> = (0..4)
+            .map(|channel| RGBA.iter().skip(channel).step_by(4).copied().collect())
+            .collect();
+        let kinds = [
+            PsdChannelKind::Red,
+            PsdChannelKind::Green,
+            PsdChannelKind::Blue,
+            PsdChannelKind::TransparencyMask,
+        ];
+
+        for format in FORMATS {
+            let mut pixels = vec![0; 2 * format.bytes_per_pixel()];
+            for (kind, channel) in kinds.iter().zip(&channels) {
+                if let Some(offset) = format.channel_offset(*kind) {
+                    format.interleave(&mut pixels, offset, channel);
+                }
+            }
+            assert_eq!(pixels, format.layout_rgba(RGBA.to_vec()));
+
+            let mut opaque = vec![0; 2 * format.bytes_per_pixel()];
+            format.fill_opaque(&mut opaque);
+            let opaque_rgba = [0, 0, 0, 255, 0, 0, 0, 255];
+            assert_eq!(opaque, format.layout_rgba(opaque_rgba.to_vec()));
+        }
+    }
+}
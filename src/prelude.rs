@@ -0,0 +1,30 @@
+//! The allocating types that the standard library's prelude would give us, imported from
+//! `alloc` so that they are also available when building without `std`.
+
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec::Vec;
+pub(crate) use alloc::{format, vec};
+
+// Without `std` there is no `HashMap`, so we fall back to a `BTreeMap`
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::BTreeMap as HashMap;
+#[cfg(feature = "std")]
+pub(crate) use std::collections::HashMap;
+
+/// Lets us keep calling `HashMap::with_capacity` when `HashMap` is a `BTreeMap`, which doesn't
+/// preallocate.
+#[cfg(not(feature = "std"))]
+pub(crate) trait WithCapacity {
+    fn with_capacity(capacity: usize) -> Self;
+}
+
+#[cfg(not(feature = "std"))]
+impl<K: Ord, V> WithCapacity for HashMap<K, V> {
+    fn with_capacity(_capacity: usize) -> Self {
+        HashMap::new()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) use crate::math::Float;
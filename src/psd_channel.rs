@@ -1,5 +1,10 @@
-use crate::sections::image_data_section::ChannelBytes;
-use crate::sections::PsdCursor;
+use core::ops::Range;
+
+use crate::color::sixteen_to_eight;
+use crate::pixel_format::PixelFormat;
+use crate::prelude::*;
+use crate::sections::image_data_section::{ChannelBytes, ScanlineLens};
+use crate::shared_bytes::SharedBytes;
 use thiserror::Error;
 
 pub trait IntoRgba {
@@ -11,6 +16,12 @@ pub trait IntoRgba {
     /// otherwise it will get transformed.
     fn rgba_idx(&self, idx: usize) -> usize;
 
+    /// The number of pixels in each row of the current rectangle.
+    ///
+    /// The pixels within a row are next to each other in the generated RGBA image, so we
+    /// insert channel bytes one row at a time.
+    fn rgba_row_len(&self) -> usize;
+
     /// The first channel
     fn red(&self) -> &ChannelBytes;
 
@@ -32,6 +43,21 @@ pub trait IntoRgba {
     fn generate_rgba(&self) -> Vec<u8> {
         let rgba_len = (self.psd_width() * self.psd_height() * 4) as usize;
 
+        let mut rgba = vec![0; rgba_len];
+        self.generate_rgba_into(&mut rgba);
+
+        rgba
+    }
+
+    /// Like `generate_rgba`, but writes into a zeroed buffer of `psd_width * psd_height * 4`
+    /// bytes.
+    fn generate_rgba_into(&self, rgba: &mut [u8]) {
+        self.generate_pixels_into(PixelFormat::Rgba8, rgba);
+    }
+
+    /// Like `generate_rgba_into`, but writes the channels straight into pixels of the given
+    /// format, into a zeroed buffer of `psd_width * psd_height * format.bytes_per_pixel()` bytes.
+    fn generate_pixels_into(&self, format: PixelFormat, pixels: &mut [u8]) {
         let red = self.red();
         let green = self.green();
         let blue = self.blue();
@@ -45,38 +71,32 @@ pub trait IntoRgba {
         //            return self.generate_16_bit_grayscale_rgba();
         //        }
 
-        let mut rgba = vec![0; rgba_len];
-
         use crate::psd_channel::PsdChannelKind::*;
 
-        self.insert_channel_bytes(&mut rgba, Red, red);
+        self.insert_channel_bytes(pixels, format, Red, red);
 
         // If there is a green channel we use it, otherwise we use the red channel since this is
         // a single channel grey image (such as a heightmap).
         if let Some(green) = green {
-            self.insert_channel_bytes(&mut rgba, Green, green);
+            self.insert_channel_bytes(pixels, format, Green, green);
         } else {
-            self.insert_channel_bytes(&mut rgba, Green, red);
+            self.insert_channel_bytes(pixels, format, Green, red);
         }
 
         // If there is a blue channel we use it, otherwise we use the red channel since this is
         // a single channel grey image (such as a heightmap).
         if let Some(blue) = blue {
-            self.insert_channel_bytes(&mut rgba, Blue, blue);
+            self.insert_channel_bytes(pixels, format, Blue, blue);
         } else {
-            self.insert_channel_bytes(&mut rgba, Blue, red);
+            self.insert_channel_bytes(pixels, format, Blue, red);
         }
 
         if let Some(alpha_channel) = alpha {
-            self.insert_channel_bytes(&mut rgba, TransparencyMask, alpha_channel);
+            self.insert_channel_bytes(pixels, format, TransparencyMask, alpha_channel);
         } else {
             // If there is no transparency data then the image is opaque
-            for idx in 0..rgba_len / 4 {
-                rgba[idx * 4 + 3] = 255;
-            }
+            format.fill_opaque(pixels);
         }
-
-        rgba
     }
 
     /// Generate an RGBA Vec<u8> from a composite image or layer that uses 16 bits per
@@ -87,18 +107,18 @@ pub trait IntoRgba {
         match self.red() {
             ChannelBytes::RawData(red) => match self.green().unwrap() {
                 ChannelBytes::RawData(green) => sixteen_to_eight_rgba(red, green),
-                ChannelBytes::RleCompressed(green) => {
+                ChannelBytes::RleCompressed(green, _) => {
                     let green = &rle_decompress(green);
 
                     sixteen_to_eight_rgba(red, green)
                 }
             },
-            ChannelBytes::RleCompressed(red) => {
+            ChannelBytes::RleCompressed(red, _) => {
                 let red = &rle_decompress(red);
 
                 match self.green().unwrap() {
                     ChannelBytes::RawData(green) => sixteen_to_eight_rgba(red, green),
-                    ChannelBytes::RleCompressed(green) => {
+                    ChannelBytes::RleCompressed(green, _) => {
                         let green = &rle_decompress(green);
                         sixteen_to_eight_rgba(red, green)
                     }
@@ -113,96 +133,329 @@ pub trait IntoRgba {
     /// it.
     fn insert_channel_bytes(
         &self,
-        rgba: &mut Vec<u8>,
+        pixels: &mut [u8],
+        format: PixelFormat,
         channel_kind: PsdChannelKind,
         channel_bytes: &ChannelBytes,
     ) {
+        // Formats without alpha leave out the transparency
+        let offset = match format.channel_offset(channel_kind) {
+            Some(offset) => offset,
+            None => return,
+        };
+
         match channel_bytes {
             ChannelBytes::RawData(channel_bytes) => {
-                let offset = channel_kind.rgba_offset().unwrap();
-
-                for (idx, byte) in channel_bytes.iter().enumerate() {
-                    let rgba_idx = self.rgba_idx(idx);
-                    rgba[rgba_idx * 4 + offset] = *byte;
-                }
+                self.insert_channel_rows(pixels, format, offset, channel_bytes);
             }
             // https://en.wikipedia.org/wiki/PackBits
-            ChannelBytes::RleCompressed(channel_bytes) => {
-                self.insert_rle_channel(rgba, channel_kind, &channel_bytes);
+            ChannelBytes::RleCompressed(channel_bytes, scanline_lens) => {
+                let channel_bytes = rle_decompress_channel(channel_bytes, scanline_lens);
+
+                self.insert_channel_rows(pixels, format, offset, &channel_bytes);
             }
         }
     }
 
-    /// rle decompress a channel (R,G,B or A) and insert it into a vector of RGBA pixels.
+    /// Insert an uncompressed channel (R,G,B or A) into a vector of pixels.
     ///
-    /// We use the channels offset to know where to put it.. So red would go in 0, 4, 8..
-    /// blue would go in 1, 5, 9.. etc
-    ///
-    /// https://en.wikipedia.org/wiki/PackBits - algorithm used for decompression
-    fn insert_rle_channel(
+    /// We use the channels offset to know where to put it.. So in RGBA pixels red would go in
+    /// 0, 4, 8.. blue would go in 1, 5, 9.. etc
+    fn insert_channel_rows(
         &self,
-        rgba: &mut Vec<u8>,
-        channel_kind: PsdChannelKind,
+        pixels: &mut [u8],
+        format: PixelFormat,
+        offset: usize,
         channel_bytes: &[u8],
     ) {
-        let mut cursor = PsdCursor::new(&channel_bytes[..]);
-
-        let mut idx = 0;
-        let offset = channel_kind.rgba_offset().unwrap();
+        let row_len = self.rgba_row_len();
+        if row_len == 0 {
+            return;
+        }
 
-        while cursor.position() != cursor.get_ref().len() as u64 {
-            let header = cursor.read_i8() as i16;
+        let bytes_per_pixel = format.bytes_per_pixel();
+        for (row, row_bytes) in channel_bytes.chunks(row_len).enumerate() {
+            let start = self.rgba_idx(row * row_len) * bytes_per_pixel;
+            let end = (start + row_bytes.len() * bytes_per_pixel).min(pixels.len());
+            if start >= end {
+                break;
+            }
 
-            if header == -128 {
-                continue;
-            } else if header >= 0 {
-                let bytes_to_read = 1 + header;
-                for byte in cursor.read(bytes_to_read as u32) {
-                    let rgba_idx = self.rgba_idx(idx);
-                    rgba[rgba_idx * 4 + offset] = *byte;
+            let row_bytes = &row_bytes[..(end - start) / bytes_per_pixel];
+            format.interleave(&mut pixels[start..end], offset, row_bytes);
+        }
+    }
+}
 
-                    idx += 1;
-                }
-            } else {
-                let repeat = 1 - header;
-                let byte = cursor.read_1()[0];
-                for _ in 0..repeat as usize {
-                    let rgba_idx = self.rgba_idx(idx);
-                    rgba[rgba_idx * 4 + offset] = byte;
-
-                    idx += 1;
+/// Rle decompress a channel
+///
+/// https://en.wikipedia.org/wiki/PackBits - algorithm used for decompression
+pub(crate) fn rle_decompress(bytes: &[u8]) -> Vec<u8> {
+    // PackBits rarely compresses by more than a few times, so this avoids most reallocations
+    let mut decompressed = Vec::with_capacity(bytes.len() * 4);
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let header = bytes[idx] as i8;
+        idx += 1;
+
+        match header {
+            // No-op, used as padding
+            -128 => {}
+            // Copy the next `header + 1` bytes literally
+            0..=127 => {
+                let end = (idx + header as usize + 1).min(bytes.len());
+                decompressed.extend_from_slice(&bytes[idx..end]);
+                idx = end;
+            }
+            // Repeat the next byte `1 - header` times
+            _ => {
+                if let Some(&byte) = bytes.get(idx) {
+                    let repeat = (1 - header as isize) as usize;
+                    decompressed.resize(decompressed.len() + repeat, byte);
                 }
-            };
+                idx += 1;
+            }
         }
     }
+
+    decompressed
 }
 
-/// Rle decompress a channel
-fn rle_decompress(bytes: &[u8]) -> Vec<u8> {
-    let mut cursor = PsdCursor::new(&bytes[..]);
+/// Like [`rle_decompress`], but only keeps the decompressed bytes within the range, such as
+/// a few rows of a channel, without holding the rest of the channel in memory.
+///
+/// The packets before the range are still read, but only to count the bytes that they hold.
+pub(crate) fn rle_decompress_range(bytes: &[u8], range: Range<usize>) -> Vec<u8> {
+    let mut decompressed = Vec::with_capacity(range.len());
+
+    // The position of the next decompressed byte within the whole channel
+    let mut pos = 0;
+    let mut idx = 0;
+    while idx < bytes.len() && pos < range.end {
+        let header = bytes[idx] as i8;
+        idx += 1;
+
+        let (len, literal) = match header {
+            -128 => continue,
+            0..=127 => ((header as usize + 1).min(bytes.len() - idx), true),
+            _ => ((1 - header as isize) as usize, false),
+        };
+
+        // The part of the packet that is within the range
+        let start = range.start.max(pos);
+        let end = range.end.min(pos + len);
+        if start < end {
+            match literal {
+                true => decompressed.extend_from_slice(&bytes[idx + start - pos..idx + end - pos]),
+                false => {
+                    if let Some(&byte) = bytes.get(idx) {
+                        decompressed.resize(decompressed.len() + end - start, byte);
+                    }
+                }
+            }
+        }
 
-    let mut decompressed = vec![];
+        pos += len;
+        idx += if literal { len } else { 1 };
+    }
 
-    while cursor.position() != cursor.get_ref().len() as u64 {
-        let header = cursor.read_i8() as i16;
+    decompressed
+}
 
-        if header == -128 {
+/// Rle compress a scanline, the inverse of [`rle_decompress`].
+///
+/// Runs of three or more bytes are repeated and everything else is copied literally, in
+/// packets of at most 128 bytes.
+pub(crate) fn rle_compress(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::with_capacity(bytes.len() + bytes.len() / 128 + 1);
+
+    let run_len = |start: usize| {
+        bytes[start..]
+            .iter()
+            .take(128)
+            .take_while(|byte| **byte == bytes[start])
+            .count()
+    };
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let run = run_len(idx);
+        if run >= 3 {
+            compressed.push((1 - run as isize) as u8);
+            compressed.push(bytes[idx]);
+            idx += run;
             continue;
-        } else if header >= 0 {
-            let bytes_to_read = 1 + header;
-            for byte in cursor.read(bytes_to_read as u32) {
-                decompressed.push(*byte);
-            }
-        } else {
-            let repeat = 1 - header;
-            let byte = cursor.read_1()[0];
-            for _ in 0..repeat as usize {
-                decompressed.push(byte);
+        }
+
+        let start = idx;
+        while idx < bytes.len() && idx - start < 128 && run_len(idx) < 3 {
+            idx += 1;
+        }
+        compressed.push((idx - start - 1) as u8);
+        compressed.extend_from_slice(&bytes[start..idx]);
+    }
+
+    compressed
+}
+
+/// Read the byte count of each of the `scanlines` scanlines at the start of RLE compressed
+/// image data. Every count takes `count_len` bytes, see
+/// [`crate::sections::file_header_section::PsdVersion::rle_byte_count_len`].
+///
+/// Returns `None` if the data is too short to hold all of the counts.
+pub(crate) fn rle_byte_counts(
+    bytes: &[u8],
+    scanlines: usize,
+    count_len: usize,
+) -> Option<Vec<usize>> {
+    let counts = bytes.get(..scanlines.checked_mul(count_len)?)?;
+
+    let counts = counts
+        .chunks_exact(count_len)
+        .map(|count| {
+            count
+                .iter()
+                .fold(0, |total, byte| total << 8 | *byte as usize)
+        })
+        .collect();
+
+    Some(counts)
+}
+
+/// Rle decompress the rows of a channel that are `row_len` bytes long.
+///
+/// When the scanline lengths add up, only the rows' own packets are read. Otherwise the packets
+/// before the rows are read to count their bytes, see [`rle_decompress_range`].
+pub(crate) fn rle_decompress_rows(
+    bytes: &[u8],
+    scanline_lens: &ScanlineLens,
+    rows: Range<usize>,
+    row_len: usize,
+) -> Vec<u8> {
+    match scanline_lens.byte_range(rows.clone(), bytes.len()) {
+        Some(range) => rle_decompress(&bytes[range]),
+        None => rle_decompress_range(bytes, rows.start * row_len..rows.end * row_len),
+    }
+}
+
+/// Rle decompress a whole channel
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn rle_decompress_channel(
+    bytes: &SharedBytes,
+    _scanline_lens: &ScanlineLens,
+) -> Vec<u8> {
+    rle_decompress(bytes)
+}
+
+/// Compressed channels smaller than this are decompressed on the current thread, since
+/// handing them to other threads would cost more than it saves.
+#[cfg(feature = "parallel")]
+const PARALLEL_RLE_MIN_LEN: usize = 64 * 1024;
+
+/// Rle decompress a whole channel, splitting the work across the worker threads.
+///
+/// Every scanline is compressed separately, so no packet ever depends on the packets before it.
+/// The scanline lengths are used to split the channel into one run of scanlines per thread.
+/// Channels whose lengths don't add up are decompressed on the current thread.
+#[cfg(feature = "parallel")]
+pub(crate) fn rle_decompress_channel(bytes: &SharedBytes, scanline_lens: &ScanlineLens) -> Vec<u8> {
+    let workers = crate::worker_pool::workers();
+    if bytes.len() < PARALLEL_RLE_MIN_LEN || workers.threads() < 2 {
+        return rle_decompress(bytes);
+    }
+
+    match scanline_lens.offsets(bytes.len()) {
+        Some(offsets) => rle_decompress_parts(bytes, &offsets, workers.threads()),
+        None => rle_decompress(bytes),
+    }
+}
+
+/// Rle decompress a channel on the worker threads, in about `parts` runs of scanlines with
+/// the same number of compressed bytes each, given the offset of each scanline followed by the
+/// channel's length
+#[cfg(feature = "parallel")]
+fn rle_decompress_parts(bytes: &SharedBytes, offsets: &[usize], parts: usize) -> Vec<u8> {
+    let target_len = (bytes.len() / parts).max(1);
+
+    let mut split = Vec::with_capacity(parts);
+    let mut part_start = 0;
+    for &offset in offsets {
+        if offset - part_start >= target_len {
+            split.push(bytes.slice(part_start..offset));
+            part_start = offset;
+        }
+    }
+    if part_start < bytes.len() {
+        split.push(bytes.slice(part_start..bytes.len()));
+    }
+
+    let parts = crate::worker_pool::workers()
+        .map(split.into_iter().map(|part| move || rle_decompress(&part)));
+
+    parts.concat()
+}
+
+/// Write every byte of a channel into every 4th byte of the RGBA pixels, starting at the offset.
+///
+/// `rgba` must be at least `channel_bytes.len() * 4` bytes long.
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+pub(crate) fn interleave_channel(rgba: &mut [u8], offset: usize, channel_bytes: &[u8]) {
+    interleave_channel_scalar(rgba, offset, channel_bytes);
+}
+
+/// Write every byte of a channel into every 4th byte of the RGBA pixels, starting at the offset.
+///
+/// `rgba` must be at least `channel_bytes.len() * 4` bytes long.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub(crate) fn interleave_channel(rgba: &mut [u8], offset: usize, channel_bytes: &[u8]) {
+    let chunks = channel_bytes.len() / 16;
+    assert!(rgba.len() >= chunks * 64);
+
+    // SAFETY: SSE2 is always available on x86_64, and we asserted above that every load and
+    // store (16 channel bytes and 64 RGBA bytes per chunk) is in bounds.
+    unsafe {
+        use core::arch::x86_64::*;
+
+        let zero = _mm_setzero_si128();
+        let shift = _mm_cvtsi32_si128(offset as i32 * 8);
+        // Selects the byte at the offset within each 4 byte pixel
+        let mask = _mm_sll_epi32(_mm_set1_epi32(0xFF), shift);
+
+        for chunk in 0..chunks {
+            let bytes = _mm_loadu_si128(channel_bytes.as_ptr().add(chunk * 16) as *const __m128i);
+
+            // Widen each byte into its own 32 bit lane, one lane per pixel
+            let low = _mm_unpacklo_epi8(bytes, zero);
+            let high = _mm_unpackhi_epi8(bytes, zero);
+            let pixels = [
+                _mm_unpacklo_epi16(low, zero),
+                _mm_unpackhi_epi16(low, zero),
+                _mm_unpacklo_epi16(high, zero),
+                _mm_unpackhi_epi16(high, zero),
+            ];
+
+            for (idx, pixels) in pixels.iter().enumerate() {
+                let dst = rgba.as_mut_ptr().add(chunk * 64 + idx * 16) as *mut __m128i;
+
+                let existing = _mm_andnot_si128(mask, _mm_loadu_si128(dst));
+                let merged = _mm_or_si128(existing, _mm_sll_epi32(*pixels, shift));
+                _mm_storeu_si128(dst, merged);
             }
-        };
+        }
     }
 
-    decompressed
+    interleave_channel_scalar(
+        &mut rgba[chunks * 64..],
+        offset,
+        &channel_bytes[chunks * 16..],
+    );
+}
+
+fn interleave_channel_scalar(rgba: &mut [u8], offset: usize, channel_bytes: &[u8]) {
+    for (pixel, byte) in rgba.chunks_exact_mut(4).zip(channel_bytes) {
+        pixel[offset] = *byte;
+    }
 }
 
 /// Take two 8 bit channels that together represent a 16 bit channel and convert them down
@@ -220,7 +473,7 @@ fn sixteen_to_eight_rgba(channel1: &[u8], channel2: &[u8]) -> Vec<u8> {
         let sixteen_bit = [channel1[idx], channel1[idx + 1]];
         let sixteen_bit = u16::from_be_bytes(sixteen_bit);
 
-        let eight_bit = (sixteen_bit / 256) as u8;
+        let eight_bit = sixteen_to_eight(sixteen_bit);
 
         eight.push(eight_bit);
         eight.push(eight_bit);
@@ -236,7 +489,7 @@ fn sixteen_to_eight_rgba(channel1: &[u8], channel2: &[u8]) -> Vec<u8> {
         let sixteen_bit = [channel2[idx], channel2[idx + 1]];
         let sixteen_bit = u16::from_be_bytes(sixteen_bit);
 
-        let eight_bit = (sixteen_bit / 256) as u8;
+        let eight_bit = sixteen_to_eight(sixteen_bit);
 
         eight.push(eight_bit);
         eight.push(eight_bit);
@@ -248,7 +501,7 @@ fn sixteen_to_eight_rgba(channel1: &[u8], channel2: &[u8]) -> Vec<u8> {
 }
 
 /// Indicates how a channe'sl data is compressed
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 #[allow(missing_docs)]
 pub enum PsdChannelCompression {
     /// Not compressed
@@ -278,12 +531,19 @@ impl PsdChannelCompression {
 #[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 #[allow(missing_docs)]
 pub enum PsdChannelKind {
-    Red = 0,
-    Green = 1,
-    Blue = 2,
-    TransparencyMask = -1,
-    UserSuppliedLayerMask = -2,
-    RealUserSuppliedLayerMask = -3,
+    Red,
+    Green,
+    Blue,
+    TransparencyMask,
+    /// The layer mask, which has its own rectangle (see [`crate::LayerMask::bounds`])
+    UserSuppliedLayerMask,
+    /// The layer mask of a layer that also has a vector mask, in which case
+    /// `UserSuppliedLayerMask` holds the vector mask. It has its own rectangle too (see
+    /// [`crate::LayerMask::real_bounds`]).
+    RealUserSuppliedLayerMask,
+    /// A channel after the first three color channels, by its ID (3 or more). For example the
+    /// black of a CMYK image is `Spot(3)`, and spot channels come after the color channels.
+    Spot(u16),
 }
 
 /// Represents an invalid channel
@@ -303,10 +563,24 @@ impl PsdChannelKind {
             -1 => Some(PsdChannelKind::TransparencyMask),
             -2 => Some(PsdChannelKind::UserSuppliedLayerMask),
             -3 => Some(PsdChannelKind::RealUserSuppliedLayerMask),
+            3..=i16::MAX => Some(PsdChannelKind::Spot(channel_id as u16)),
             _ => None,
         }
     }
 
+    /// The ID that the PSD stores for the channel, the inverse of [`PsdChannelKind::new`]
+    pub fn id(self) -> i16 {
+        match self {
+            PsdChannelKind::Red => 0,
+            PsdChannelKind::Green => 1,
+            PsdChannelKind::Blue => 2,
+            PsdChannelKind::TransparencyMask => -1,
+            PsdChannelKind::UserSuppliedLayerMask => -2,
+            PsdChannelKind::RealUserSuppliedLayerMask => -3,
+            PsdChannelKind::Spot(id) => id as i16,
+        }
+    }
+
     /// R -> 0
     /// G -> 1
     /// B -> 2
@@ -321,3 +595,154 @@ impl PsdChannelKind {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from https://en.wikipedia.org/wiki/PackBits
+    #[test]
+    fn rle_decompress_packbits() {
+        let compressed = [
+            0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7,
+            0xAA,
+        ];
+        let expected = [
+            0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0xAA, 0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0x22,
+            0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA,
+        ];
+
+        assert_eq!(rle_decompress(&compressed), expected);
+    }
+
+    /// Decompressing a range gives the same bytes as decompressing everything and then
+    /// slicing, including ranges that start or end within a packet.
+    #[test]
+    fn rle_decompress_range_matches_slice() {
+        let compressed = [
+            0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7,
+            0xAA,
+        ];
+        let decompressed = rle_decompress(&compressed);
+
+        for start in 0..decompressed.len() {
+            for end in start..=decompressed.len() {
+                assert_eq!(
+                    rle_decompress_range(&compressed, start..end),
+                    &decompressed[start..end]
+                );
+            }
+        }
+    }
+
+    /// PSDs store each scanline's byte count in 2 bytes and PSBs in 4.
+    #[test]
+    fn rle_byte_counts_of_both_widths() {
+        let count_len = crate::sections::file_header_section::PsdVersion::One.rle_byte_count_len();
+        let bytes = [0x01, 0x02, 0x00, 0x03, 0xFF];
+        assert_eq!(rle_byte_counts(&bytes, 2, count_len), Some(vec![0x0102, 3]));
+        assert_eq!(rle_byte_counts(&bytes, 3, count_len), None);
+
+        let bytes = [0x00, 0x01, 0x86, 0xA0, 0x00, 0x00, 0x00, 0x07];
+        assert_eq!(rle_byte_counts(&bytes, 2, 4), Some(vec![100_000, 7]));
+        assert_eq!(rle_byte_counts(&bytes[..7], 2, 4), None);
+        assert_eq!(rle_byte_counts(&[], 0, 4), Some(vec![]));
+    }
+
+    #[test]
+    fn rle_compress_round_trip() {
+        let mut bytes = vec![0xAA; 300];
+        bytes.extend(0..=255);
+        bytes.extend_from_slice(&[1, 1, 2, 2, 2, 3]);
+
+        for len in [0, 1, 2, 3, 129, 300, bytes.len()] {
+            let compressed = rle_compress(&bytes[..len]);
+            assert_eq!(rle_decompress(&compressed), &bytes[..len]);
+        }
+
+        // 300 repeated bytes are three packets of two bytes
+        assert_eq!(rle_compress(&bytes[..300]).len(), 6);
+    }
+
+    // Large enough to go through both the chunked and the leftover bytes when SIMD is enabled
+    #[test]
+    fn interleave_channel_every_offset() {
+        let channel_bytes: Vec<u8> = (0..37).collect();
+
+        for offset in 0..4 {
+            let mut rgba = vec![255; channel_bytes.len() * 4];
+            interleave_channel(&mut rgba, offset, &channel_bytes);
+
+            for (idx, pixel) in rgba.chunks(4).enumerate() {
+                for (channel, byte) in pixel.iter().enumerate() {
+                    let expected = if channel == offset { idx as u8 } else { 255 };
+                    assert_eq!(*byte, expected);
+                }
+            }
+        }
+    }
+
+    // Large enough to be split across threads
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn rle_decompress_parallel_matches_sequential() {
+        // Scanlines of 3, 1 and 4 compressed bytes, with the PSD's 2 byte count for each
+        let (mut compressed, mut counts) = (vec![], vec![]);
+        for idx in 0..PARALLEL_RLE_MIN_LEN {
+            let scanline: &[u8] = match idx % 3 {
+                0 => &[0xFD, idx as u8],
+                1 => &[0x80],
+                _ => &[0x02, 1, 2, idx as u8],
+            };
+            compressed.extend_from_slice(scanline);
+            counts.extend_from_slice(&(scanline.len() as u16).to_be_bytes());
+        }
+        let compressed = SharedBytes::new(compressed);
+        let scanline_lens = ScanlineLens::new(counts.into(), 2);
+
+        let expected = rle_decompress(&compressed);
+
+        assert_eq!(
+            rle_decompress_channel(&compressed, &scanline_lens),
+            expected
+        );
+        let offsets = scanline_lens.offsets(compressed.len()).unwrap();
+        for parts in 2..=5 {
+            assert_eq!(rle_decompress_parts(&compressed, &offsets, parts), expected);
+        }
+
+        // Lengths that don't add up to the compressed bytes are ignored
+        let truncated = compressed.slice(0..compressed.len() - 1);
+        assert_eq!(
+            rle_decompress_channel(&truncated, &scanline_lens),
+            rle_decompress(&truncated)
+        );
+    }
+
+    /// The rows of a channel are the same whether they're found with the scanline lengths or
+    /// by reading the packets before them.
+    #[test]
+    fn rle_decompress_rows_with_and_without_lens() {
+        let rows: Vec<Vec<u8>> = (0..5u8).map(|row| vec![row; 6]).collect();
+        let (mut compressed, mut counts) = (vec![], vec![]);
+        for row in &rows {
+            let scanline = rle_compress(row);
+            counts.extend_from_slice(&(scanline.len() as u16).to_be_bytes());
+            compressed.extend(scanline);
+        }
+
+        let scanline_lens = ScanlineLens::new(counts.into(), 2);
+        let missing_lens = ScanlineLens::new(SharedBytes::new(vec![]), 2);
+        for start in 0..rows.len() {
+            for end in start..=rows.len() {
+                let expected = rows[start..end].concat();
+                for lens in [&scanline_lens, &missing_lens] {
+                    assert_eq!(
+                        rle_decompress_rows(&compressed, lens, start..end, 6),
+                        expected
+                    );
+                }
+            }
+        }
+    }
+}
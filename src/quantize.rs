@@ -0,0 +1,312 @@
+//! Reduce RGBA pixels to a small palette of colors, for exporting PSDs to indexed formats such
+//! as GIFs or the palettes of retro consoles.
+//!
+//! Requires the `quantize` feature.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//!
+//! let options = QuantizeOptions {
+//!     palette_size: 16,
+//!     ..QuantizeOptions::default()
+//! };
+//! let indexed = psd::quantize::quantize(&psd.rgba(), psd.width(), psd.height(), &options)?;
+//!
+//! for (idx, color) in indexed.palette().iter().enumerate() {
+//!     println!("{}: {:?}", idx, color);
+//! }
+//! ```
+
+use thiserror::Error;
+
+use crate::prelude::*;
+
+/// Configures how [`quantize`] picks and applies the palette
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantizeOptions {
+    /// The most colors that the palette can have, from 1 to 256. Defaults to 256.
+    pub palette_size: usize,
+    /// Spread the difference between each pixel and its palette color onto the pixels that
+    /// haven't been mapped yet, using Floyd–Steinberg dithering. Defaults to true.
+    ///
+    /// Dithering hides the banding of smooth gradients at the cost of a noisier image.
+    pub dither: bool,
+}
+
+impl Default for QuantizeOptions {
+    fn default() -> Self {
+        QuantizeOptions {
+            palette_size: 256,
+            dither: true,
+        }
+    }
+}
+
+/// An error while quantizing an image
+#[derive(Debug, PartialEq, Error)]
+pub enum QuantizeError {
+    /// The palette size is outside of 1 to 256
+    #[error("A palette can have 1 to 256 colors, not {palette_size}.")]
+    InvalidPaletteSize {
+        /// The palette size that was given
+        palette_size: usize,
+    },
+
+    /// The RGBA pixels don't cover the image
+    #[error("Expected {expected} bytes of RGBA pixels, but there were {actual}.")]
+    InvalidRgbaLength {
+        /// Four bytes for every pixel of the image
+        expected: usize,
+        /// The number of bytes that were given
+        actual: usize,
+    },
+}
+
+/// An image whose pixels are indices into a palette of RGBA colors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedImage {
+    width: u32,
+    height: u32,
+    palette: Vec<[u8; 4]>,
+    indices: Vec<u8>,
+}
+
+impl IndexedImage {
+    /// The width of the image, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The colors of the image, as `[R, G, B, A]`
+    pub fn palette(&self) -> &[[u8; 4]] {
+        &self.palette
+    }
+
+    /// The index into the palette of each pixel, row by row
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// Look up each pixel's color in the palette.
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.indices
+            .iter()
+            .flat_map(|idx| self.palette[*idx as usize])
+            .collect()
+    }
+}
+
+/// Reduce the RGBA pixels (`[R, G, B, A, R, G, B, A, ...]`) of an image to a palette.
+///
+/// Images that already have few enough colors keep their exact colors. Otherwise the palette is
+/// picked using median cut, which repeatedly splits the colors of the image in two along the
+/// channel where they differ the most.
+pub fn quantize(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    options: &QuantizeOptions,
+) -> Result<IndexedImage, QuantizeError> {
+    let palette_size = options.palette_size;
+    if palette_size == 0 || palette_size > 256 {
+        return Err(QuantizeError::InvalidPaletteSize { palette_size });
+    }
+
+    let expected = width as usize * height as usize * 4;
+    if rgba.len() != expected {
+        return Err(QuantizeError::InvalidRgbaLength {
+            expected,
+            actual: rgba.len(),
+        });
+    }
+
+    let mut color_counts: HashMap<[u8; 4], u32> = HashMap::new();
+    for pixel in rgba.chunks_exact(4) {
+        *color_counts
+            .entry([pixel[0], pixel[1], pixel[2], pixel[3]])
+            .or_insert(0) += 1;
+    }
+
+    let mut colors: Vec<([u8; 4], u32)> = color_counts.into_iter().collect();
+    // Sort the colors so that the palette doesn't depend on the order of the map
+    colors.sort_unstable();
+
+    let exact = colors.len() <= palette_size;
+    let palette = if exact {
+        colors.iter().map(|(color, _)| *color).collect()
+    } else {
+        median_cut(colors, palette_size)
+    };
+
+    let indices = if exact || !options.dither {
+        map_pixels(rgba, &palette)
+    } else {
+        dither_pixels(rgba, width as usize, &palette)
+    };
+
+    Ok(IndexedImage {
+        width,
+        height,
+        palette,
+        indices,
+    })
+}
+
+/// Split the colors into boxes until there are as many boxes as palette colors, and use the
+/// average color of each box.
+fn median_cut(colors: Vec<([u8; 4], u32)>, palette_size: usize) -> Vec<[u8; 4]> {
+    let mut boxes = vec![colors];
+
+    while boxes.len() < palette_size {
+        // Split the box whose colors are spread out the furthest along one channel
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(idx, colors)| (idx, widest_channel(colors)))
+            .max_by_key(|(_, (_, range))| *range);
+
+        let (idx, (channel, _)) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        let mut colors = boxes.swap_remove(idx);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+
+        // Split at the median pixel, leaving at least one color on each side
+        let total: u64 = colors.iter().map(|(_, count)| *count as u64).sum();
+        let mut seen = 0;
+        let mut split = 1;
+        for (idx, (_, count)) in colors.iter().enumerate().take(colors.len() - 1) {
+            seen += *count as u64;
+            split = idx + 1;
+            if seen * 2 >= total {
+                break;
+            }
+        }
+
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|colors| average_color(colors)).collect()
+}
+
+/// The channel with the largest range of values, along with that range
+fn widest_channel(colors: &[([u8; 4], u32)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let values = colors.iter().map(|(color, _)| color[channel]);
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+/// The average of the colors, weighted by how many pixels have each color
+fn average_color(colors: &[([u8; 4], u32)]) -> [u8; 4] {
+    let mut sums = [0u64; 4];
+    let mut total = 0u64;
+    for (color, count) in colors {
+        for channel in 0..4 {
+            sums[channel] += color[channel] as u64 * *count as u64;
+        }
+        total += *count as u64;
+    }
+
+    let mut average = [0; 4];
+    for channel in 0..4 {
+        average[channel] = ((sums[channel] + total / 2) / total) as u8;
+    }
+    average
+}
+
+fn map_pixels(rgba: &[u8], palette: &[[u8; 4]]) -> Vec<u8> {
+    let mut nearest: HashMap<[u8; 4], u8> = HashMap::new();
+
+    rgba.chunks_exact(4)
+        .map(|pixel| {
+            let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            *nearest.entry(color).or_insert_with(|| {
+                nearest_color(
+                    palette,
+                    [
+                        color[0] as f32,
+                        color[1] as f32,
+                        color[2] as f32,
+                        color[3] as f32,
+                    ],
+                )
+            })
+        })
+        .collect()
+}
+
+/// Map each pixel to its nearest palette color, spreading the difference onto the pixel to the
+/// right (7/16) and the pixels below left (3/16), below (5/16) and below right (1/16).
+fn dither_pixels(rgba: &[u8], width: usize, palette: &[[u8; 4]]) -> Vec<u8> {
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+
+    // The error that was spread onto the pixels of the current row and the row below it
+    let mut errors = vec![[0f32; 4]; width + 2];
+    let mut next_errors = vec![[0f32; 4]; width + 2];
+
+    for row in rgba.chunks_exact(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            let mut color = [0f32; 4];
+            for channel in 0..4 {
+                color[channel] = (pixel[channel] as f32 + errors[x + 1][channel]).clamp(0., 255.);
+            }
+
+            let idx = nearest_color(palette, color);
+            indices.push(idx);
+
+            let chosen = palette[idx as usize];
+            for channel in 0..4 {
+                let error = color[channel] - chosen[channel] as f32;
+                errors[x + 2][channel] += error * 7. / 16.;
+                next_errors[x][channel] += error * 3. / 16.;
+                next_errors[x + 1][channel] += error * 5. / 16.;
+                next_errors[x + 2][channel] += error / 16.;
+            }
+        }
+
+        core::mem::swap(&mut errors, &mut next_errors);
+        next_errors.iter_mut().for_each(|error| *error = [0.; 4]);
+    }
+
+    indices
+}
+
+fn nearest_color(palette: &[[u8; 4]], color: [f32; 4]) -> u8 {
+    let distance = |candidate: &[u8; 4]| -> f32 {
+        (0..4)
+            .map(|channel| {
+                let difference = candidate[channel] as f32 - color[channel];
+                difference * difference
+            })
+            .sum()
+    };
+
+    let mut nearest = 0;
+    let mut nearest_distance = f32::MAX;
+    for (idx, candidate) in palette.iter().enumerate() {
+        let distance = distance(candidate);
+        if distance < nearest_distance {
+            nearest = idx;
+            nearest_distance = distance;
+        }
+    }
+
+    nearest as u8
+}
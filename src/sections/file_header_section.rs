@@ -1,4 +1,4 @@
-use crate::sections::PsdCursor;
+use crate::sections::{PsdCursor, PsdCursorError};
 use thiserror::Error;
 
 /// Bytes representing the string "8BPS".
@@ -66,6 +66,8 @@ pub enum FileHeaderSectionError {
     InvalidDepth { depth: u8 },
     #[error("Invalid color mode {color_mode}. Must be 0, 1, 2, 3, 4, 7, 8 or 9")]
     InvalidColorMode { color_mode: u8 },
+    #[error("Unexpected end of data: {0}")]
+    UnexpectedEnd(#[from] PsdCursorError),
 }
 
 impl FileHeaderSection {
@@ -86,44 +88,44 @@ impl FileHeaderSection {
         }
 
         // First four bytes must be '8BPS'
-        let signature = cursor.read_4();
+        let signature = cursor.read_4()?;
         if signature != EXPECTED_PSD_SIGNATURE {
             return Err(FileHeaderSectionError::InvalidSignature {});
         }
 
         // The next 2 bytes represent the version
-        let version = cursor.read_2();
+        let version = cursor.read_2()?;
         if version != EXPECTED_VERSION {
             return Err(FileHeaderSectionError::InvalidVersion {});
         }
 
         // The next 6 bytes are reserved and should always be 0
-        let reserved = cursor.read_6();
+        let reserved = cursor.read_6()?;
         if reserved != EXPECTED_RESERVED {
             return Err(FileHeaderSectionError::InvalidReserved {});
         }
 
         // The next 2 bytes represent the channel count
-        let channel_count = cursor.read_u16() as u8;
+        let channel_count = cursor.read_u16()? as u8;
         let channel_count = ChannelCount::new(channel_count)
             .ok_or(FileHeaderSectionError::ChannelCountOutOfRange { channel_count })?;
 
         // 4 bytes for the height
-        let height = cursor.read_u32();
+        let height = cursor.read_u32()?;
         let height =
             PsdHeight::new(height).ok_or(FileHeaderSectionError::HeightOutOfRange { height })?;
 
         // 4 bytes for the width
-        let width = cursor.read_u32();
+        let width = cursor.read_u32()?;
         let width =
             PsdWidth::new(width).ok_or(FileHeaderSectionError::WidthOutOfRange { width })?;
 
         // 2 bytes for depth
-        let depth = cursor.read_2()[1];
+        let depth = cursor.read_2()?[1];
         let depth = PsdDepth::new(depth).ok_or(FileHeaderSectionError::InvalidDepth { depth })?;
 
         // 2 bytes for color mode
-        let color_mode = cursor.read_2()[1];
+        let color_mode = cursor.read_2()?[1];
         let color_mode = ColorMode::new(color_mode)
             .ok_or(FileHeaderSectionError::InvalidColorMode { color_mode })?;
 
@@ -145,12 +147,25 @@ impl FileHeaderSection {
 /// Version: always equal to 1. Do not try to read the file if the version does not match this value. (**PSB** version is 2.)
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PsdVersion {
     /// Regular PSD (Not a PSB)
     One,
 }
 
+impl PsdVersion {
+    /// The number of bytes used for each of the scanline byte counts at the start of RLE
+    /// compressed image data.
+    ///
+    /// PSBs (which can't be read yet) use 4 bytes, since a compressed scanline of a document
+    /// that is wider than 30,000 pixels might not fit in 2.
+    pub(crate) fn rle_byte_count_len(self) -> usize {
+        match self {
+            PsdVersion::One => 2,
+        }
+    }
+}
+
 /// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
 ///
 /// The number of channels in the image, including any alpha channels. Supported range is 1 to 56.
@@ -279,6 +294,23 @@ impl ColorMode {
             _ => None,
         }
     }
+
+    /// The number of channels that the composite image uses for color.
+    ///
+    /// They are followed by the composite image's transparency (if it has any) and then any
+    /// spot color or alpha channels.
+    pub(crate) fn color_channel_count(&self) -> usize {
+        match self {
+            ColorMode::Rgb | ColorMode::Lab => 3,
+            ColorMode::Cmyk => 4,
+            // Every channel of a multichannel image is its own ink, we show the first one
+            ColorMode::Bitmap
+            | ColorMode::Grayscale
+            | ColorMode::Indexed
+            | ColorMode::Multichannel
+            | ColorMode::Duotone => 1,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,19 +1,26 @@
-use crate::psd_channel::PsdChannelCompression;
-use crate::sections::PsdCursor;
-use crate::PsdDepth;
+use alloc::borrow::Cow;
+use core::ops::Range;
+
+use crate::color::sixteen_to_eight;
+use crate::prelude::*;
+use crate::psd_channel::{rle_byte_counts, rle_decompress, PsdChannelCompression};
+use crate::sections::file_header_section::PsdVersion;
+use crate::sections::{PsdCursor, PsdCursorError};
+use crate::shared_bytes::SharedBytes;
+use crate::{PsdDepth, PsdWarning};
 use thiserror::Error;
 
 /// Represents an malformed image data
 #[derive(Debug, PartialEq, Error)]
 pub enum ImageDataSectionError {
-    #[error(
-        r#"Only 8 and 16 bit depths are supported at the moment.
-    If you'd like to see 1 and 32 bit depths supported - please open an issue."#
-    )]
+    #[error("Only 1, 8, 16 and 32 bit depths are supported.")]
     UnsupportedDepth,
 
     #[error("{compression} is an invalid layer channel compression. Must be 0, 1, 2 or 3")]
     InvalidCompression { compression: u16 },
+
+    #[error("Unexpected end of data: {0}")]
+    UnexpectedEnd(#[from] PsdCursorError),
 }
 
 /// The ImageDataSection comes from the final section in the PSD that contains the pixel data
@@ -27,200 +34,349 @@ pub enum ImageDataSectionError {
 ///
 /// | Length   | Description                                                                                                                                                                                                                                                                                                                                                                                                                                                              |
 /// |----------|--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------|
-/// | 2        | Compression method: <br> 0 = Raw image data <br> 1 = RLE compressed the image data starts with the byte counts for all the scan lines (rows * channels), with each count stored as a two-byte value. The RLE compressed data follows, with each scan line compressed separately. The RLE compression is the same compression algorithm used by the Macintosh ROM routine PackBits , and the TIFF standard. <br> 2 = ZIP without prediction <br> 3 = ZIP with prediction. |
+/// | 2        | Compression method: <br> 0 = Raw image data <br> 1 = RLE compressed the image data starts with the byte counts for all the scan lines (rows * channels), with each count stored as a two-byte value (four bytes in a PSB). The RLE compressed data follows, with each scan line compressed separately. The RLE compression is the same compression algorithm used by the Macintosh ROM routine PackBits , and the TIFF standard. <br> 2 = ZIP without prediction <br> 3 = ZIP with prediction. |
 /// | Variable | The image data. Planar order = RRR GGG BBB, etc.                                                                                                                                                                                                                                                                                                                                                                                                                         |
 #[derive(Debug)]
 pub struct ImageDataSection {
     /// The compression method for the image.
     pub(in crate) compression: PsdChannelCompression,
-    /// The red channel of the final image
-    pub(in crate) red: ChannelBytes,
-    /// The green channel of the final image
-    pub(in crate) green: Option<ChannelBytes>,
-    /// the blue channel of the final image
-    pub(in crate) blue: Option<ChannelBytes>,
-    /// the alpha channel of the final image.
-    /// If there is no alpha channel then it is a fully opaque image.
-    pub(in crate) alpha: Option<ChannelBytes>,
+    /// The channels of the final image, in the order that they are stored.
+    ///
+    /// Which channel is which depends on the color mode. For example, an RGB image stores red,
+    /// green and blue followed by its transparency and then any spot or alpha channels, while
+    /// a CMYK image stores cyan, magenta, yellow and black before its transparency.
+    pub(in crate) channels: Vec<ChannelBytes>,
+    /// The floats of each channel of a 32 bit image, which can be brighter than white.
+    ///
+    /// `channels` holds the same values clamped into bytes. Empty for other depths.
+    pub(crate) float_channels: Vec<Vec<f32>>,
+    /// The channels of a 16 bit image as they are stored, with two big endian bytes per pixel.
+    ///
+    /// `channels` holds the same values mapped down into bytes. Empty for other depths.
+    pub(crate) sixteen_bit_channels: Vec<ChannelBytes>,
 }
 
 impl ImageDataSection {
     /// Create an ImageDataSection from the bytes in the corresponding section in a PSD file
     /// (including the length market)
+    ///
+    /// A composite image with unsupported compression is left out and added to the warnings.
     pub fn from_bytes(
-        bytes: &[u8],
+        bytes: &SharedBytes,
         depth: PsdDepth,
+        psd_width: u32,
         psd_height: u32,
         channel_count: u8,
+        version: PsdVersion,
+        warnings: &mut Vec<PsdWarning>,
     ) -> Result<ImageDataSection, ImageDataSectionError> {
+        span!(DEBUG, "image_data_section", len = bytes.len());
+
+        // PSDs that were saved without a composite image might not have any image data
+        if bytes.len() < 2 {
+            return Ok(ImageDataSection {
+                compression: PsdChannelCompression::RawData,
+                channels: vec![],
+                float_channels: vec![],
+                sixteen_bit_channels: vec![],
+            });
+        }
+
         let mut cursor = PsdCursor::new(bytes);
         let channel_count = channel_count as usize;
 
-        let compression = cursor.read_u16();
+        let compression = cursor.read_u16()?;
         let compression = PsdChannelCompression::new(compression)
             .ok_or(ImageDataSectionError::InvalidCompression { compression })?;
 
-        let (red, green, blue, alpha) = match compression {
+        let channels: Vec<ChannelBytes> = match compression {
             PsdChannelCompression::RawData => {
                 // First 2 bytes were compression bytes
-                let channel_bytes = &bytes[2..];
-                let channel_byte_count = channel_bytes.len();
-
-                let bytes_per_channel = channel_byte_count / channel_count;
-
-                // First bytes are red
-                let mut red = channel_bytes[..bytes_per_channel].into();
-
-                // Next bytes are green
-                let green = if channel_count >= 2 {
-                    Some(ChannelBytes::RawData(
-                        channel_bytes[bytes_per_channel..2 * bytes_per_channel].into(),
-                    ))
-                } else {
-                    None
-                };
-
-                // Then comes blue
-                let blue = if channel_count >= 3 {
-                    Some(ChannelBytes::RawData(
-                        channel_bytes[2 * bytes_per_channel..3 * bytes_per_channel].into(),
-                    ))
-                } else {
-                    None
-                };
-
-                // And optionally alpha bytes
-                let alpha = if channel_count == 4 {
-                    Some(ChannelBytes::RawData(
-                        channel_bytes[3 * bytes_per_channel..4 * bytes_per_channel].to_vec(),
-                    ))
-                } else {
-                    None
-                };
-
-                match depth {
-                    PsdDepth::Eight => (ChannelBytes::RawData(red), green, blue, alpha),
-                    // If this is a 16bit image there will be two bytes per pixel. We
-                    // currently only support one byte per pixel so we convert the 2 bytes
-                    // back down into 1 byte by mapping 0-65535 down to 0-255
-                    PsdDepth::Sixteen => {
-                        for idx in 0..red.len() / 2 {
-                            let bytes = [red[2 * idx], red[2 * idx + 1]];
-                            let bits16 = u16::from_be_bytes(bytes);
-                            red[idx] = (bits16 / 256) as u8;
-                        }
-                        red.truncate(red.len() / 2);
-
-                        (ChannelBytes::RawData(red), green, blue, alpha)
-                    }
-                    _ => return Err(ImageDataSectionError::UnsupportedDepth),
-                }
+                let channel_bytes = bytes.slice(2..bytes.len());
+                let bytes_per_channel = channel_bytes.len() / channel_count;
+
+                (0..channel_count)
+                    .map(|idx| {
+                        let start = idx * bytes_per_channel;
+                        channel_bytes.slice(start..start + bytes_per_channel)
+                    })
+                    .map(ChannelBytes::RawData)
+                    .collect()
             }
             // # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
             //
             // RLE compressed the image data starts with the byte counts for all the scan lines
-            // (rows * channels), with each count stored as a two-byte value. The RLE compressed
-            // data follows, with each scan line compressed separately. The RLE compression is
-            // the same compression algorithm used by the Macintosh ROM routine PackBits,
-            // and the TIFF standard.
+            // (rows * channels), with each count stored as a two-byte value (four bytes in a
+            // PSB). The RLE compressed data follows, with each scan line compressed separately.
+            // The RLE compression is the same compression algorithm used by the Macintosh ROM
+            // routine PackBits, and the TIFF standard.
             PsdChannelCompression::RleCompressed => {
-                let mut red_byte_count = 0;
-                let mut green_byte_count = if channel_count >= 2 { Some(0) } else { None };
-                let mut blue_byte_count = if channel_count >= 3 { Some(0) } else { None };
-                let mut alpha_byte_count = if channel_count == 4 { Some(0) } else { None };
+                let scanlines = channel_count * psd_height as usize;
 
-                for _ in 0..psd_height {
-                    red_byte_count += cursor.read_u16() as usize;
-                }
+                // The byte counts are used to find where each channel starts, and each channel
+                // keeps its own counts so that its scanlines can be found without decompressing
+                // the ones before them
+                let count_len = version.rle_byte_count_len();
+                let byte_counts = rle_byte_counts(&bytes[2..], scanlines, count_len);
+                let mut channel_start = 2 + scanlines * count_len;
+                let channel_counts_len = psd_height as usize * count_len;
 
-                if let Some(ref mut green_byte_count) = green_byte_count {
-                    for _ in 0..psd_height {
-                        *green_byte_count += cursor.read_u16() as usize;
-                    }
-                }
+                let channels: Option<Vec<ChannelBytes>> = byte_counts.and_then(|byte_counts| {
+                    byte_counts
+                        .chunks(psd_height.max(1) as usize)
+                        .enumerate()
+                        .map(|(idx, channel_counts)| {
+                            let channel_end = channel_start + channel_counts.iter().sum::<usize>();
+                            if channel_end > bytes.len() {
+                                return None;
+                            }
+                            let channel = bytes.slice(channel_start..channel_end);
+                            channel_start = channel_end;
 
-                if let Some(ref mut blue_byte_count) = blue_byte_count {
-                    for _ in 0..psd_height {
-                        *blue_byte_count += cursor.read_u16() as usize;
-                    }
-                }
+                            let counts_start = 2 + idx * channel_counts_len;
+                            let counts =
+                                bytes.slice(counts_start..counts_start + channel_counts_len);
 
-                if let Some(ref mut alpha_byte_count) = alpha_byte_count {
-                    for _ in 0..psd_height {
-                        *alpha_byte_count += cursor.read_u16() as usize;
-                    }
-                }
+                            Some(ChannelBytes::RleCompressed(
+                                channel,
+                                ScanlineLens::new(counts, count_len),
+                            ))
+                        })
+                        .collect()
+                });
 
-                // 2 bytes for compression level, then 2 bytes for each scanline of each channel
-                // We're skipping over the bytes that describe the length of each scanling since
-                // we don't currently use them. We might re-think this in the future when we
-                // implement serialization of a Psd back into bytes.. But not a concern at the
-                // moment.
-                let channel_data_start = 2 + (channel_count * psd_height as usize * 2);
-
-                let (red_start, red_end) =
-                    (channel_data_start, channel_data_start + red_byte_count);
-
-                let red = bytes[red_start as usize..red_end as usize].into();
-
-                let green = match green_byte_count {
-                    Some(green_byte_count) => {
-                        let green_start = red_end;
-                        let green_end = green_start + green_byte_count;
-                        Some(ChannelBytes::RleCompressed(
-                            bytes[green_start..green_end].into(),
-                        ))
-                    }
-                    None => None,
-                };
-
-                let blue = match blue_byte_count {
-                    Some(blue_byte_count) => {
-                        let blue_start = red_end + green_byte_count.unwrap() as usize;
-                        let blue_end = blue_start + blue_byte_count as usize;
-                        Some(ChannelBytes::RleCompressed(
-                            bytes[blue_start..blue_end].into(),
-                        ))
-                    }
-                    None => None,
-                };
-
-                let alpha = match alpha_byte_count {
-                    Some(alpha_byte_count) => {
-                        let alpha_start =
-                            red_end + green_byte_count.unwrap() + blue_byte_count.unwrap();
-                        let alpha_end = alpha_start + alpha_byte_count;
-                        Some(ChannelBytes::RleCompressed(
-                            bytes[alpha_start..alpha_end].into(),
-                        ))
-                    }
-                    None => None,
-                };
-
-                (ChannelBytes::RleCompressed(red), green, blue, alpha)
+                channels.unwrap_or_else(|| {
+                    warnings.push(PsdWarning::TruncatedComposite);
+                    vec![]
+                })
+            }
+            // We can't decompress zip yet, so the PSD is treated as if it didn't have a
+            // composite image
+            PsdChannelCompression::ZipWithoutPrediction
+            | PsdChannelCompression::ZipWithPrediction => {
+                warnings.push(PsdWarning::UnsupportedCompositeCompression { compression });
+                vec![]
             }
-            PsdChannelCompression::ZipWithoutPrediction => unimplemented!(
-                r#"Zip without prediction compression is currently unsupported.
-                Please open an issue"#
-            ),
-            PsdChannelCompression::ZipWithPrediction => unimplemented!(
-                r#"Zip with prediction compression is currently unsupported.
-                Please open an issue"#
-            ),
+        };
+
+        // 32 bit images store a big endian float for every pixel. We keep the floats for tone
+        // mapping, and clamp them into bytes for everything that works with 8 bits.
+        let mut float_channels = vec![];
+        // 16 bit images store two big endian bytes for every pixel. We keep them for
+        // `PixelFormat::Rgba16`, and map them down into bytes for everything else.
+        let mut sixteen_bit_channels = vec![];
+        let channels = match depth {
+            PsdDepth::Sixteen => channels
+                .into_iter()
+                .map(|channel| {
+                    let bytes: Vec<u8> = channel_samples(&channel)
+                        .into_iter()
+                        .map(sixteen_to_eight)
+                        .collect();
+                    sixteen_bit_channels.push(channel);
+
+                    ChannelBytes::RawData(bytes.into())
+                })
+                .collect(),
+            PsdDepth::ThirtyTwo => channels
+                .into_iter()
+                .map(|channel| {
+                    let floats = channel_floats(&channel);
+                    let bytes: Vec<u8> = floats
+                        .iter()
+                        .map(|float| (float.clamp(0., 1.) * 255.).round() as u8)
+                        .collect();
+                    float_channels.push(floats);
+
+                    ChannelBytes::RawData(bytes.into())
+                })
+                .collect(),
+            // Bitmap images pack 8 pixels into every byte, we give each pixel its own byte so
+            // that they can be treated like grayscale images
+            PsdDepth::One => channels
+                .into_iter()
+                .map(|channel| {
+                    let packed = match &channel {
+                        ChannelBytes::RawData(bytes) => Cow::Borrowed(&bytes[..]),
+                        ChannelBytes::RleCompressed(bytes, _) => Cow::Owned(rle_decompress(bytes)),
+                    };
+
+                    ChannelBytes::RawData(unpack_bits(&packed, psd_width, psd_height).into())
+                })
+                .collect(),
+            _ => channels,
         };
 
         Ok(ImageDataSection {
             compression,
-            red,
-            green,
-            blue,
-            alpha,
+            channels,
+            float_channels,
+            sixteen_bit_channels,
         })
     }
+
+    /// Get one of the channels of the final image by its position
+    pub(in crate) fn channel(&self, idx: usize) -> Option<&ChannelBytes> {
+        self.channels.get(idx)
+    }
+
+    /// Get the floats of one of the channels of a 32 bit image by its position
+    pub(crate) fn float_channel(&self, idx: usize) -> Option<&[f32]> {
+        self.float_channels.get(idx).map(|floats| floats.as_slice())
+    }
+
+    /// Get the 16 bit values of one of the channels of a 16 bit image by its position
+    pub(crate) fn sixteen_bit_channel(&self, idx: usize) -> Option<Vec<u16>> {
+        self.sixteen_bit_channels.get(idx).map(channel_samples)
+    }
+
+    /// Whether the section is missing some or all of the composite image's channels
+    pub(crate) fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+            || self.channels.iter().any(|channel| match channel {
+                ChannelBytes::RawData(bytes) | ChannelBytes::RleCompressed(bytes, _) => {
+                    bytes.is_empty()
+                }
+            })
+    }
 }
 
+/// Give every pixel of a 1 bit image its own byte. A set bit is black and a clear bit is white.
+///
+/// Every row starts on a new byte, and pixels that are missing from the end of the data are
+/// white.
+fn unpack_bits(packed: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let width = width as usize;
+    let row_len = width.div_ceil(8).max(1);
+
+    let mut gray = Vec::with_capacity(width * height as usize);
+    for row in 0..height as usize {
+        let row = packed.get(row * row_len..).unwrap_or(&[]);
+
+        for x in 0..width {
+            let byte = row.get(x / 8).copied().unwrap_or(0);
+            let bit = (byte >> (7 - x % 8)) & 1;
+            gray.push(if bit == 1 { 0 } else { 255 });
+        }
+    }
+
+    gray
+}
+
+/// Decode the big endian floats of a channel of a 32 bit image
+fn channel_floats(channel: &ChannelBytes) -> Vec<f32> {
+    let bytes = match channel {
+        ChannelBytes::RawData(bytes) => Cow::Borrowed(&bytes[..]),
+        ChannelBytes::RleCompressed(bytes, _) => Cow::Owned(rle_decompress(bytes)),
+    };
+
+    bytes
+        .chunks_exact(4)
+        .map(|float| f32::from_be_bytes([float[0], float[1], float[2], float[3]]))
+        .collect()
+}
+
+/// Decode the big endian values of a channel of a 16 bit image
+fn channel_samples(channel: &ChannelBytes) -> Vec<u16> {
+    let bytes = match channel {
+        ChannelBytes::RawData(bytes) => Cow::Borrowed(&bytes[..]),
+        ChannelBytes::RleCompressed(bytes, _) => Cow::Owned(rle_decompress(bytes)),
+    };
+
+    bytes
+        .chunks_exact(2)
+        .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+        .collect()
+}
+
+/// The bytes of one channel, which share the buffer that the PSD was read from when they
+/// didn't have to be converted
 #[derive(Debug, Clone)]
 pub enum ChannelBytes {
-    RawData(Vec<u8>),
-    RleCompressed(Vec<u8>),
+    RawData(SharedBytes),
+    /// The compressed scanlines, along with their lengths
+    RleCompressed(SharedBytes, ScanlineLens),
+}
+
+/// The compressed length of each scanline of an RLE compressed channel, which the PSD stores
+/// before the scanlines. They let a channel be split into scanlines without reading every
+/// packet before them.
+#[derive(Debug, Clone)]
+pub struct ScanlineLens {
+    /// Big endian lengths of `count_len` bytes each
+    counts: SharedBytes,
+    count_len: usize,
+}
+
+impl ScanlineLens {
+    /// The lengths in the PSD's table of byte counts, see
+    /// [`crate::sections::file_header_section::PsdVersion::rle_byte_count_len`]
+    pub(crate) fn new(counts: SharedBytes, count_len: usize) -> ScanlineLens {
+        ScanlineLens { counts, count_len }
+    }
+
+    /// The offset of the first compressed byte of each scanline within the channel, followed
+    /// by the channel's length.
+    ///
+    /// Returns `None` if the lengths don't add up to the compressed bytes, such as for a PSD
+    /// that left them out or a truncated one, in which case the packets have to be read one
+    /// after the other instead.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn offsets(&self, compressed_len: usize) -> Option<Vec<usize>> {
+        let mut offsets = Vec::with_capacity(self.len() + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for len in self.lens() {
+            offset += len;
+            offsets.push(offset);
+        }
+
+        (offset == compressed_len).then_some(offsets)
+    }
+
+    /// The compressed bytes of the scanlines within the range, or `None` if the lengths don't
+    /// add up to the compressed bytes or there aren't that many scanlines
+    pub(crate) fn byte_range(
+        &self,
+        rows: Range<usize>,
+        compressed_len: usize,
+    ) -> Option<Range<usize>> {
+        if rows.end > self.len() {
+            return None;
+        }
+
+        let (mut start, mut end, mut total) = (None, None, 0);
+        for (row, len) in self.lens().enumerate() {
+            if row == rows.start {
+                start = Some(total);
+            }
+            if row == rows.end {
+                end = Some(total);
+            }
+            total += len;
+        }
+
+        // Rows that end at the last scanline end at the end of the channel
+        let end = end.unwrap_or(total);
+        let start = start.unwrap_or(total).min(end);
+        (total == compressed_len).then_some(start..end)
+    }
+
+    /// The number of scanlines
+    fn len(&self) -> usize {
+        match self.count_len {
+            0 => 0,
+            count_len => self.counts.len() / count_len,
+        }
+    }
+
+    fn lens(&self) -> impl Iterator<Item = usize> + '_ {
+        self.counts
+            .chunks_exact(self.count_len.max(1))
+            .map(|count| {
+                count
+                    .iter()
+                    .fold(0, |total, byte| total << 8 | *byte as usize)
+            })
+    }
 }
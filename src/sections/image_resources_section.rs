@@ -1,17 +1,49 @@
-use std::collections::HashMap;
-use std::ops::Range;
+use core::ops::{Range, RangeInclusive};
 
 use thiserror::Error;
 
+use crate::prelude::*;
+pub use crate::sections::image_resources_section::color_sampler::{ColorSampler, ColorSpace};
+pub use crate::sections::image_resources_section::exif::ExifOrientation;
 pub use crate::sections::image_resources_section::image_resource::ImageResource;
 use crate::sections::image_resources_section::image_resource::SlicesImageResource;
-use crate::sections::PsdCursor;
+use crate::sections::image_resources_section::path::ClippingPath;
+pub(crate) use crate::sections::image_resources_section::path::{
+    rasterize_subpaths, rasterize_subpaths_region,
+};
+pub use crate::sections::image_resources_section::path::{BezierKnot, PathPoint, PsdPath, Subpath};
+pub use crate::sections::image_resources_section::resolution::{ResolutionInfo, ResolutionUnit};
+pub use crate::sections::image_resources_section::url::UrlListEntry;
+use crate::sections::{PsdCursor, PsdCursorError};
+use crate::PsdWarning;
 
 const EXPECTED_RESOURCE_BLOCK_SIGNATURE: [u8; 4] = [56, 66, 73, 77];
 const EXPECTED_DESCRIPTOR_VERSION: u32 = 16;
+const RESOURCE_RESOLUTION_INFO: i16 = 1005;
+const RESOURCE_ALPHA_NAMES: i16 = 1006;
+const RESOURCE_WORK_PATH: i16 = 1025;
+const RESOURCE_URL: i16 = 1035;
+const RESOURCE_GLOBAL_ANGLE: i16 = 1037;
+const RESOURCE_ICC_PROFILE: i16 = 1039;
+const RESOURCE_COLOR_SAMPLERS: i16 = 1044;
+const RESOURCE_UNICODE_ALPHA_NAMES: i16 = 1045;
 const RESOURCE_SLICES_INFO: i16 = 1050;
-
+const RESOURCE_URL_LIST: i16 = 1054;
+const RESOURCE_VERSION_INFO: i16 = 1057;
+const RESOURCE_EXIF_DATA: i16 = 1058;
+const RESOURCE_SAVED_PATHS: RangeInclusive<i16> = 2000..=2997;
+const RESOURCE_CLIPPING_PATH_NAME: i16 = 2999;
+// There is no resource for the Swatches panel. Photoshop keeps swatches in its presets and in
+// exported .aco and .ase files instead of in the PSD, so the only colors that a PSD saves are
+// its color samplers (`RESOURCE_COLOR_SAMPLERS`) and the colors of its spot channels.
+
+mod alpha_names;
+mod color_sampler;
+mod exif;
 mod image_resource;
+mod path;
+mod resolution;
+mod url;
 
 struct ImageResourcesBlock {
     resource_id: i16,
@@ -22,10 +54,31 @@ struct ImageResourcesBlock {
 #[derive(Debug)]
 pub struct ImageResourcesSection {
     pub(crate) resources: Vec<ImageResource>,
+    pub(crate) paths: Vec<PsdPath>,
+    pub(crate) work_path: Option<PsdPath>,
+    pub(crate) clipping_path: Option<ClippingPath>,
+    pub(crate) color_samplers: Vec<ColorSampler>,
+    /// Whether the image data section holds the real composite image, from the version info
+    /// resource. Files that were saved with "Maximize Compatibility" turned off don't.
+    pub(crate) has_real_merged_data: Option<bool>,
+    pub(crate) resolution: Option<ResolutionInfo>,
+    pub(crate) icc_profile: Option<Vec<u8>>,
+    /// The URL that the whole image links to when it is saved for the web
+    pub(crate) url: Option<String>,
+    pub(crate) url_list: Vec<UrlListEntry>,
+    /// The angle of the light that casts the layers' drop shadows, in degrees
+    pub(crate) global_angle: Option<i32>,
+    /// The orientation tag of the EXIF data of a photo
+    pub(crate) exif_orientation: Option<ExifOrientation>,
+    /// The names of the alpha and spot channels, from the Unicode alpha names resource or, in
+    /// older files, the alpha channel names resource
+    pub(crate) alpha_names: Vec<String>,
+    /// The ID of each resource block and where it is within the section
+    pub(crate) block_ranges: Vec<(i16, Range<usize>)>,
 }
 
 /// Represents an malformed resource block
-#[derive(Debug, PartialEq, Error)]
+#[derive(Debug, Clone, PartialEq, Error)]
 pub enum ImageResourcesSectionError {
     #[error(
         r#"The first four bytes (indices 0-3) must always equal [56, 66, 73, 77],
@@ -35,35 +88,153 @@ pub enum ImageResourcesSectionError {
 
     #[error("Invalid resource descriptor: {0}")]
     InvalidResource(ImageResourcesDescriptorError),
+
+    #[error("Unexpected end of data: {0}")]
+    UnexpectedEnd(#[from] PsdCursorError),
 }
 
 impl ImageResourcesSection {
-    pub fn from_bytes(bytes: &[u8]) -> Result<ImageResourcesSection, ImageResourcesSectionError> {
+    /// Read the image resources section (including the length marker).
+    ///
+    /// Resources that we don't parse yet are added to the warnings. When recovering, malformed
+    /// resources are skipped and added to the warnings instead of failing.
+    pub fn from_bytes(
+        bytes: &[u8],
+        recover: bool,
+        warnings: &mut Vec<PsdWarning>,
+    ) -> Result<ImageResourcesSection, ImageResourcesSectionError> {
+        span!(DEBUG, "image_resources_section", len = bytes.len());
+
         let mut cursor = PsdCursor::new(bytes);
 
         let mut resources = vec![];
-
-        let length = cursor.read_u32() as u64;
-
-        while cursor.position() < length {
-            let block = ImageResourcesSection::read_resource_block(&mut cursor)?;
+        let mut paths = vec![];
+        let mut work_path = None;
+        let mut clipping_path = None;
+        let mut color_samplers = vec![];
+        let mut has_real_merged_data = None;
+        let mut resolution = None;
+        let mut icc_profile = None;
+        let mut url = None;
+        let mut url_list = vec![];
+        let mut global_angle = None;
+        let mut exif_orientation = None;
+        let mut alpha_names = vec![];
+        let mut unicode_alpha_names = vec![];
+        let mut block_ranges = vec![];
+
+        // The length doesn't count its own 4 bytes. A block that runs past the end of the
+        // section is cut short, so it fails to be read.
+        let end = cursor.read_u32()? as u64 + 4;
+
+        while cursor.position() < end {
+            let start = cursor.position() as usize;
+            let block = match ImageResourcesSection::read_resource_block(&mut cursor) {
+                Ok(block) => block,
+                Err(error) if recover => {
+                    warnings.push(PsdWarning::SkippedRemainingResources { error });
+                    break;
+                }
+                Err(error) => return Err(error),
+            };
 
             let rid = block.resource_id;
+            block_ranges.push((rid, start..cursor.position() as usize));
+
             match rid {
                 _ if rid == RESOURCE_SLICES_INFO => {
-                    let slices_image_resource = ImageResourcesSection::read_slice_block(
+                    let slices_image_resource = match ImageResourcesSection::read_slice_block(
                         &cursor.get_ref()[block.data_range],
                     )
-                    .map_err(ImageResourcesSectionError::InvalidResource)?;
+                    .map_err(ImageResourcesSectionError::InvalidResource)
+                    {
+                        Ok(slices_image_resource) => slices_image_resource,
+                        Err(error) if recover => {
+                            warnings.push(PsdWarning::SkippedResource {
+                                resource_id: rid,
+                                error,
+                            });
+                            continue;
+                        }
+                        Err(error) => return Err(error),
+                    };
                     resources.push(ImageResource::Slices(slices_image_resource));
                 }
-                _ => {}
+                _ if RESOURCE_SAVED_PATHS.contains(&rid) => {
+                    let data = &cursor.get_ref()[block.data_range];
+                    paths.push(PsdPath::from_bytes(rid, block.name, data));
+                }
+                _ if rid == RESOURCE_WORK_PATH => {
+                    let data = &cursor.get_ref()[block.data_range];
+                    work_path = Some(PsdPath::from_bytes(rid, block.name, data));
+                }
+                _ if rid == RESOURCE_COLOR_SAMPLERS => {
+                    color_samplers =
+                        ColorSampler::read_color_samplers(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_VERSION_INFO => {
+                    // A 4 byte version followed by the "has real merged data" flag
+                    has_real_merged_data = cursor.get_ref()[block.data_range]
+                        .get(4)
+                        .map(|flag| *flag != 0);
+                }
+                _ if rid == RESOURCE_RESOLUTION_INFO => {
+                    resolution = ResolutionInfo::from_bytes(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_ICC_PROFILE => {
+                    icc_profile = Some(cursor.get_ref()[block.data_range].to_vec());
+                }
+                _ if rid == RESOURCE_URL => {
+                    url = url::read_document_url(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_URL_LIST => {
+                    url_list = UrlListEntry::read_url_list(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_GLOBAL_ANGLE => {
+                    global_angle = cursor.get_ref()[block.data_range]
+                        .get(..4)
+                        .map(|angle| i32::from_be_bytes([angle[0], angle[1], angle[2], angle[3]]));
+                }
+                _ if rid == RESOURCE_ALPHA_NAMES => {
+                    alpha_names =
+                        alpha_names::read_alpha_names(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_UNICODE_ALPHA_NAMES => {
+                    unicode_alpha_names =
+                        alpha_names::read_unicode_alpha_names(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_EXIF_DATA => {
+                    exif_orientation =
+                        ExifOrientation::from_exif(&cursor.get_ref()[block.data_range]);
+                }
+                _ if rid == RESOURCE_CLIPPING_PATH_NAME => {
+                    clipping_path = ClippingPath::from_bytes(&cursor.get_ref()[block.data_range]);
+                }
+                _ => warnings.push(PsdWarning::IgnoredResource { resource_id: rid }),
             }
         }
 
-        assert_eq!(cursor.position(), length + 4);
+        // The Unicode names aren't limited to 255 bytes or to Mac Roman
+        if !unicode_alpha_names.is_empty() {
+            alpha_names = unicode_alpha_names;
+        }
 
-        Ok(ImageResourcesSection { resources })
+        Ok(ImageResourcesSection {
+            resources,
+            paths,
+            work_path,
+            clipping_path,
+            color_samplers,
+            has_real_merged_data,
+            resolution,
+            icc_profile,
+            url,
+            url_list,
+            global_angle,
+            exif_orientation,
+            alpha_names,
+            block_ranges,
+        })
     }
 
     /// +----------+--------------------------------------------------------------------------------------------------------------------+
@@ -79,15 +250,15 @@ impl ImageResourcesSection {
         cursor: &mut PsdCursor,
     ) -> Result<ImageResourcesBlock, ImageResourcesSectionError> {
         // First four bytes must be '8BIM'
-        let signature = cursor.read_4();
+        let signature = cursor.read_4()?;
         if signature != EXPECTED_RESOURCE_BLOCK_SIGNATURE {
             return Err(ImageResourcesSectionError::InvalidSignature {});
         }
 
-        let resource_id = cursor.read_i16();
-        let name = cursor.read_pascal_string();
+        let resource_id = cursor.read_i16()?;
+        let name = cursor.read_pascal_string()?;
 
-        let data_len = cursor.read_u32();
+        let data_len = cursor.read_u32()?;
         let pos = cursor.position() as usize;
         // Note: data length is padded to even.
         let data_len = data_len + data_len % 2;
@@ -95,7 +266,7 @@ impl ImageResourcesSection {
             start: pos,
             end: pos + data_len as usize,
         };
-        cursor.read(data_len);
+        cursor.read(data_len)?;
 
         Ok(ImageResourcesBlock {
             resource_id,
@@ -119,21 +290,19 @@ impl ImageResourcesSection {
     ) -> Result<SlicesImageResource, ImageResourcesDescriptorError> {
         let mut cursor = PsdCursor::new(bytes);
 
-        let version = cursor.read_i32();
+        let version = cursor.read_i32()?;
         if version != 6 {
-            unimplemented!(
-                "Only the Adobe Photoshop 6.0 slices resource format is currently supported"
-            );
+            return Err(ImageResourcesDescriptorError::UnsupportedSlicesVersion { version });
         }
 
-        let _top = cursor.read_i32();
-        let _left = cursor.read_i32();
-        let _bottom = cursor.read_i32();
-        let _right = cursor.read_i32();
+        let _top = cursor.read_i32()?;
+        let _left = cursor.read_i32()?;
+        let _bottom = cursor.read_i32()?;
+        let _right = cursor.read_i32()?;
 
-        let group_of_slices_name = cursor.read_unicode_string_padding(1);
+        let group_of_slices_name = cursor.read_unicode_string_padding(1)?;
 
-        let number_of_slices = cursor.read_u32();
+        let number_of_slices = cursor.read_u32()?;
 
         let mut descriptors = Vec::new();
 
@@ -182,44 +351,44 @@ impl ImageResourcesSection {
     fn read_slice_body(
         cursor: &mut PsdCursor,
     ) -> Result<Option<DescriptorStructure>, ImageResourcesDescriptorError> {
-        let _slice_id = cursor.read_i32();
-        let _group_id = cursor.read_i32();
-        let origin = cursor.read_i32();
+        let _slice_id = cursor.read_i32()?;
+        let _group_id = cursor.read_i32()?;
+        let origin = cursor.read_i32()?;
 
         // if origin = 1, Associated Layer ID is present
         if origin == 1 {
-            cursor.read_i32();
+            cursor.read_i32()?;
         }
 
-        let _name = cursor.read_unicode_string_padding(1);
+        let _name = cursor.read_unicode_string_padding(1)?;
 
-        let _type = cursor.read_i32();
+        let _type = cursor.read_i32()?;
 
-        let _top = cursor.read_i32();
-        let _left = cursor.read_i32();
-        let _bottom = cursor.read_i32();
-        let _right = cursor.read_i32();
+        let _top = cursor.read_i32()?;
+        let _left = cursor.read_i32()?;
+        let _bottom = cursor.read_i32()?;
+        let _right = cursor.read_i32()?;
 
-        let _url = cursor.read_unicode_string_padding(1);
+        let _url = cursor.read_unicode_string_padding(1)?;
 
-        let _target = cursor.read_unicode_string_padding(1);
+        let _target = cursor.read_unicode_string_padding(1)?;
 
-        let _message = cursor.read_unicode_string_padding(1);
+        let _message = cursor.read_unicode_string_padding(1)?;
 
-        let _alt_tag = cursor.read_unicode_string_padding(1);
+        let _alt_tag = cursor.read_unicode_string_padding(1)?;
 
-        let _cell_text_html = cursor.read_1();
-        let _cell_text = cursor.read_unicode_string_padding(1);
+        let _cell_text_html = cursor.read_1()?;
+        let _cell_text = cursor.read_unicode_string_padding(1)?;
 
-        let _horizontal_alignment = cursor.read_i32();
-        let _vertical_alignment = cursor.read_i32();
-        let _argb_color = cursor.read_i32();
+        let _horizontal_alignment = cursor.read_i32()?;
+        let _vertical_alignment = cursor.read_i32()?;
+        let _argb_color = cursor.read_i32()?;
 
         let pos = cursor.position();
-        let descriptor_version = cursor.peek_u32();
+        let descriptor_version = cursor.peek_u32()?;
 
         Ok(if descriptor_version == EXPECTED_DESCRIPTOR_VERSION {
-            cursor.read_4();
+            cursor.read_4()?;
 
             let descriptor = DescriptorStructure::read_descriptor_structure(cursor)?;
             if descriptor.class_id.as_slice() == [0, 0, 0, 0] {
@@ -339,6 +508,9 @@ pub struct PropertyStructure {
 /// |                                    | '#Pxl' = pixels: tagged unit value                     |
 /// | 8                                  | Actual value (double)                                  |
 /// +------------------------------------+--------------------------------------------------------+
+///
+/// Photoshop also writes points ('#Pnt') and millimeters ('#Mlm'), such as in the settings of
+/// a shape's stroke, although the specification doesn't list them.
 #[derive(Debug)]
 pub enum UnitFloatStructure {
     /// Base degrees
@@ -347,12 +519,16 @@ pub enum UnitFloatStructure {
     Density(f64),
     /// Base 72ppi
     Distance(f64),
-    /// Base coerced
-    None,
+    /// Base coerced, a value without a unit
+    None(f64),
     /// Unit value
     Percent(f64),
     /// Tagged unit value
     Pixels(f64),
+    /// Points, 72 per inch
+    Points(f64),
+    /// Millimeters
+    Millimeters(f64),
 }
 
 /// Unit float structure units keys
@@ -368,6 +544,10 @@ const UNIT_FLOAT_NONE: &[u8; 4] = b"#Nne";
 const UNIT_FLOAT_PERCENT: &[u8; 4] = b"#Prc";
 /// '#Pxl' = pixels: tagged unit value
 const UNIT_FLOAT_PIXELS: &[u8; 4] = b"#Pxl";
+/// '#Pnt' = points
+const UNIT_FLOAT_POINTS: &[u8; 4] = b"#Pnt";
+/// '#Mlm' = millimeters
+const UNIT_FLOAT_MILLIMETERS: &[u8; 4] = b"#Mlm";
 
 /// +----------+--------------------------------------------------------------------------------------------+
 /// |  Length  |                                        Description                                         |
@@ -499,20 +679,27 @@ const OS_TYPE_INDEX: &[u8; 4] = b"indx";
 /// 'name' = Name
 const OS_TYPE_NAME: &[u8; 4] = b"name";
 
-#[derive(Debug, PartialEq, Error)]
+#[derive(Debug, Clone, PartialEq, Error)]
 pub enum ImageResourcesDescriptorError {
     #[error(r#"Invalid TypeOS field."#)]
     InvalidTypeOS {},
     #[error(r#"Invalid unit name."#)]
     InvalidUnitName {},
+    #[error(
+        "Only the Adobe Photoshop 6.0 slices resource format is currently supported, not \
+         version {version}."
+    )]
+    UnsupportedSlicesVersion { version: i32 },
+    #[error("Unexpected end of data: {0}")]
+    UnexpectedEnd(#[from] PsdCursorError),
 }
 
 impl DescriptorStructure {
-    fn read_descriptor_structure(
+    pub(crate) fn read_descriptor_structure(
         cursor: &mut PsdCursor,
     ) -> Result<DescriptorStructure, ImageResourcesDescriptorError> {
-        let name = cursor.read_unicode_string_padding(1);
-        let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
+        let name = cursor.read_unicode_string_padding(1)?;
+        let class_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
         let fields = DescriptorStructure::read_fields(cursor, false)?;
 
         Ok(DescriptorStructure {
@@ -526,12 +713,13 @@ impl DescriptorStructure {
         cursor: &mut PsdCursor,
         sub_list: bool,
     ) -> Result<HashMap<String, DescriptorField>, ImageResourcesDescriptorError> {
-        let count = cursor.read_u32();
-        let mut m = HashMap::with_capacity(count as usize);
+        let count = cursor.read_u32()?;
+        // Each field has at least a key length, a key and a type
+        let mut m = HashMap::with_capacity(cursor.capacity_for(count as usize, 12));
 
         for n in 0..count {
-            let key = DescriptorStructure::read_key_length(cursor);
-            let key = String::from_utf8(key.to_vec()).unwrap();
+            let key = DescriptorStructure::read_key_length(cursor)?;
+            let key = String::from_utf8_lossy(key).into_owned();
 
             m.insert(key, DescriptorStructure::read_descriptor_field(cursor)?);
         }
@@ -543,8 +731,9 @@ impl DescriptorStructure {
         cursor: &mut PsdCursor,
         sub_list: bool,
     ) -> Result<Vec<DescriptorField>, ImageResourcesDescriptorError> {
-        let count = cursor.read_u32();
-        let mut vec = Vec::with_capacity(count as usize);
+        let count = cursor.read_u32()?;
+        // Each item has at least a type
+        let mut vec = Vec::with_capacity(cursor.capacity_for(count as usize, 4));
 
         for n in 0..count {
             let field = DescriptorStructure::read_descriptor_field(cursor)?;
@@ -558,7 +747,7 @@ impl DescriptorStructure {
         cursor: &mut PsdCursor,
     ) -> Result<DescriptorField, ImageResourcesDescriptorError> {
         let mut os_type = [0; 4];
-        os_type.copy_from_slice(cursor.read_4());
+        os_type.copy_from_slice(cursor.read_4()?);
 
         let r: DescriptorField = match &os_type {
             OS_TYPE_REFERENCE => {
@@ -570,31 +759,31 @@ impl DescriptorStructure {
             OS_TYPE_LIST => {
                 DescriptorField::List(DescriptorStructure::read_list_structure(cursor)?)
             }
-            OS_TYPE_DOUBLE => DescriptorField::Double(cursor.read_f64()),
+            OS_TYPE_DOUBLE => DescriptorField::Double(cursor.read_f64()?),
             OS_TYPE_UNIT_FLOAT => {
                 DescriptorField::UnitFloat(DescriptorStructure::read_unit_float(cursor)?)
             }
-            OS_TYPE_TEXT => DescriptorField::String(cursor.read_unicode_string_padding(1)),
+            OS_TYPE_TEXT => DescriptorField::String(cursor.read_unicode_string_padding(1)?),
             OS_TYPE_ENUMERATED => DescriptorField::EnumeratedDescriptor(
-                DescriptorStructure::read_enumerated_descriptor(cursor),
+                DescriptorStructure::read_enumerated_descriptor(cursor)?,
             ),
-            OS_TYPE_LARGE_INTEGER => DescriptorField::LargeInteger(cursor.read_i64()),
-            OS_TYPE_INTEGER => DescriptorField::Integer(cursor.read_i32()),
-            OS_TYPE_BOOL => DescriptorField::Boolean(cursor.read_u8() > 0),
+            OS_TYPE_LARGE_INTEGER => DescriptorField::LargeInteger(cursor.read_i64()?),
+            OS_TYPE_INTEGER => DescriptorField::Integer(cursor.read_i32()?),
+            OS_TYPE_BOOL => DescriptorField::Boolean(cursor.read_u8()? > 0),
             OS_TYPE_GLOBAL_OBJECT => {
                 DescriptorField::Descriptor(DescriptorStructure::read_descriptor_structure(cursor)?)
             }
             OS_TYPE_CLASS => {
-                DescriptorField::Class(DescriptorStructure::read_class_structure(cursor))
+                DescriptorField::Class(DescriptorStructure::read_class_structure(cursor)?)
             }
             OS_TYPE_CLASS2 => {
-                DescriptorField::Class(DescriptorStructure::read_class_structure(cursor))
+                DescriptorField::Class(DescriptorStructure::read_class_structure(cursor)?)
             }
             OS_TYPE_ALIAS => {
-                DescriptorField::Alias(DescriptorStructure::read_alias_structure(cursor))
+                DescriptorField::Alias(DescriptorStructure::read_alias_structure(cursor)?)
             }
             OS_TYPE_RAW_DATA => {
-                DescriptorField::RawData(DescriptorStructure::read_raw_data(cursor))
+                DescriptorField::RawData(DescriptorStructure::read_raw_data(cursor)?)
             }
             _ => return Err(ImageResourcesDescriptorError::InvalidTypeOS {}),
         };
@@ -620,30 +809,31 @@ impl DescriptorStructure {
     fn read_reference_structure(
         cursor: &mut PsdCursor,
     ) -> Result<Vec<DescriptorField>, ImageResourcesDescriptorError> {
-        let count = cursor.read_u32();
-        let mut vec = Vec::with_capacity(count as usize);
+        let count = cursor.read_u32()?;
+        // Each item has at least a key length, a key and a type
+        let mut vec = Vec::with_capacity(cursor.capacity_for(count as usize, 12));
 
         for n in 0..count {
-            DescriptorStructure::read_key_length(cursor);
+            DescriptorStructure::read_key_length(cursor)?;
 
             let mut os_type = [0; 4];
-            os_type.copy_from_slice(cursor.read_4());
+            os_type.copy_from_slice(cursor.read_4()?);
             vec.push(match &os_type {
                 OS_TYPE_PROPERTY => {
-                    DescriptorField::Property(DescriptorStructure::read_property_structure(cursor))
+                    DescriptorField::Property(DescriptorStructure::read_property_structure(cursor)?)
                 }
                 OS_TYPE_CLASS3 => {
-                    DescriptorField::Class(DescriptorStructure::read_class_structure(cursor))
+                    DescriptorField::Class(DescriptorStructure::read_class_structure(cursor)?)
                 }
                 OS_TYPE_ENUMERATED_REFERENCE => DescriptorField::EnumeratedReference(
-                    DescriptorStructure::read_enumerated_reference(cursor),
+                    DescriptorStructure::read_enumerated_reference(cursor)?,
                 ),
                 OS_TYPE_OFFSET => {
-                    DescriptorField::Offset(DescriptorStructure::read_offset_structure(cursor))
+                    DescriptorField::Offset(DescriptorStructure::read_offset_structure(cursor)?)
                 }
-                OS_TYPE_IDENTIFIER => DescriptorField::Identifier(cursor.read_i32()),
-                OS_TYPE_INDEX => DescriptorField::Index(cursor.read_i32()),
-                OS_TYPE_NAME => DescriptorField::Name(DescriptorStructure::read_name(cursor)),
+                OS_TYPE_IDENTIFIER => DescriptorField::Identifier(cursor.read_i32()?),
+                OS_TYPE_INDEX => DescriptorField::Index(cursor.read_i32()?),
+                OS_TYPE_NAME => DescriptorField::Name(DescriptorStructure::read_name(cursor)?),
                 _ => return Err(ImageResourcesDescriptorError::InvalidTypeOS {}),
             });
         }
@@ -651,73 +841,79 @@ impl DescriptorStructure {
         Ok(vec)
     }
 
-    fn read_property_structure(cursor: &mut PsdCursor) -> PropertyStructure {
-        let name = cursor.read_unicode_string();
-        let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
-        let key_id = DescriptorStructure::read_key_length(cursor).to_vec();
+    fn read_property_structure(
+        cursor: &mut PsdCursor,
+    ) -> Result<PropertyStructure, PsdCursorError> {
+        let name = cursor.read_unicode_string()?;
+        let class_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
+        let key_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
 
-        PropertyStructure {
+        Ok(PropertyStructure {
             name,
             class_id,
             key_id,
-        }
+        })
     }
 
     fn read_unit_float(
         cursor: &mut PsdCursor,
     ) -> Result<UnitFloatStructure, ImageResourcesDescriptorError> {
         let mut unit_float = [0; 4];
-        unit_float.copy_from_slice(cursor.read_4());
+        unit_float.copy_from_slice(cursor.read_4()?);
 
         Ok(match &unit_float {
-            UNIT_FLOAT_ANGLE => UnitFloatStructure::Angle(cursor.read_f64()),
-            UNIT_FLOAT_DENSITY => UnitFloatStructure::Density(cursor.read_f64()),
-            UNIT_FLOAT_DISTANCE => UnitFloatStructure::Distance(cursor.read_f64()),
-            UNIT_FLOAT_NONE => UnitFloatStructure::None,
-            UNIT_FLOAT_PERCENT => UnitFloatStructure::Percent(cursor.read_f64()),
-            UNIT_FLOAT_PIXELS => UnitFloatStructure::Pixels(cursor.read_f64()),
+            UNIT_FLOAT_ANGLE => UnitFloatStructure::Angle(cursor.read_f64()?),
+            UNIT_FLOAT_DENSITY => UnitFloatStructure::Density(cursor.read_f64()?),
+            UNIT_FLOAT_DISTANCE => UnitFloatStructure::Distance(cursor.read_f64()?),
+            UNIT_FLOAT_NONE => UnitFloatStructure::None(cursor.read_f64()?),
+            UNIT_FLOAT_PERCENT => UnitFloatStructure::Percent(cursor.read_f64()?),
+            UNIT_FLOAT_PIXELS => UnitFloatStructure::Pixels(cursor.read_f64()?),
+            UNIT_FLOAT_POINTS => UnitFloatStructure::Points(cursor.read_f64()?),
+            UNIT_FLOAT_MILLIMETERS => UnitFloatStructure::Millimeters(cursor.read_f64()?),
             _ => return Err(ImageResourcesDescriptorError::InvalidUnitName {}),
         })
     }
 
-    fn read_class_structure(cursor: &mut PsdCursor) -> ClassStructure {
-        let name = cursor.read_unicode_string();
-        let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
+    fn read_class_structure(cursor: &mut PsdCursor) -> Result<ClassStructure, PsdCursorError> {
+        let name = cursor.read_unicode_string()?;
+        let class_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
 
-        ClassStructure { name, class_id }
+        Ok(ClassStructure { name, class_id })
     }
 
-    fn read_enumerated_reference(cursor: &mut PsdCursor) -> EnumeratedReference {
-        let name = cursor.read_unicode_string();
-        let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
-        let key_id = DescriptorStructure::read_key_length(cursor).to_vec();
-        let enum_field = DescriptorStructure::read_key_length(cursor).to_vec();
+    fn read_enumerated_reference(
+        cursor: &mut PsdCursor,
+    ) -> Result<EnumeratedReference, PsdCursorError> {
+        let name = cursor.read_unicode_string()?;
+        let class_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
+        let key_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
+        let enum_field = DescriptorStructure::read_key_length(cursor)?.to_vec();
 
-        EnumeratedReference {
+        Ok(EnumeratedReference {
             name,
             class_id,
             key_id,
             enum_field,
-        }
+        })
     }
 
-    fn read_offset_structure(cursor: &mut PsdCursor) -> OffsetStructure {
-        let name = cursor.read_unicode_string();
-        let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
-        let offset = cursor.read_u32();
+    fn read_offset_structure(cursor: &mut PsdCursor) -> Result<OffsetStructure, PsdCursorError> {
+        let name = cursor.read_unicode_string()?;
+        let class_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
+        let offset = cursor.read_u32()?;
 
-        OffsetStructure {
+        Ok(OffsetStructure {
             name,
             class_id,
             offset,
-        }
+        })
     }
 
-    fn read_alias_structure(cursor: &mut PsdCursor) -> AliasStructure {
-        let length = cursor.read_u32();
-        let data = cursor.read(length).to_vec();
+    fn read_alias_structure(cursor: &mut PsdCursor) -> Result<AliasStructure, PsdCursorError> {
+        let length = cursor.read_u32()?;
+        let data = cursor.read(length)?.to_vec();
 
-        AliasStructure { data }
+        Ok(AliasStructure { data })
     }
 
     fn read_list_structure(
@@ -726,38 +922,111 @@ impl DescriptorStructure {
         DescriptorStructure::read_list(cursor, true)
     }
 
-    fn read_enumerated_descriptor(cursor: &mut PsdCursor) -> EnumeratedDescriptor {
-        let type_field = DescriptorStructure::read_key_length(cursor).to_vec();
-        let enum_field = DescriptorStructure::read_key_length(cursor).to_vec();
+    fn read_enumerated_descriptor(
+        cursor: &mut PsdCursor,
+    ) -> Result<EnumeratedDescriptor, PsdCursorError> {
+        let type_field = DescriptorStructure::read_key_length(cursor)?.to_vec();
+        let enum_field = DescriptorStructure::read_key_length(cursor)?.to_vec();
 
-        EnumeratedDescriptor {
+        Ok(EnumeratedDescriptor {
             type_field,
             enum_field,
-        }
+        })
     }
 
-    fn read_raw_data(cursor: &mut PsdCursor) -> Vec<u8> {
-        let length = cursor.read_u32();
-        cursor.read(length).to_vec()
+    fn read_raw_data(cursor: &mut PsdCursor) -> Result<Vec<u8>, PsdCursorError> {
+        let length = cursor.read_u32()?;
+        Ok(cursor.read(length)?.to_vec())
     }
 
     // Note: this structure is not documented
-    fn read_name(cursor: &mut PsdCursor) -> NameStructure {
-        let name = cursor.read_unicode_string();
-        let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
-        let value = cursor.read_unicode_string();
+    fn read_name(cursor: &mut PsdCursor) -> Result<NameStructure, PsdCursorError> {
+        let name = cursor.read_unicode_string()?;
+        let class_id = DescriptorStructure::read_key_length(cursor)?.to_vec();
+        let value = cursor.read_unicode_string()?;
 
-        NameStructure {
+        Ok(NameStructure {
             name,
             class_id,
             value,
-        }
+        })
     }
 
-    fn read_key_length<'a>(cursor: &'a mut PsdCursor) -> &'a [u8] {
-        let length = cursor.read_u32();
+    fn read_key_length<'a>(cursor: &'a mut PsdCursor) -> Result<&'a [u8], PsdCursorError> {
+        let length = cursor.read_u32()?;
         let length = if length > 0 { length } else { 4 };
 
         cursor.read(length)
     }
 }
+
+// Helpers for pulling typed values out of a descriptor that we've already parsed.
+impl DescriptorStructure {
+    /// Get a nested descriptor by key
+    pub(crate) fn descriptor(&self, key: &str) -> Option<&DescriptorStructure> {
+        match self.fields.get(key)? {
+            DescriptorField::Descriptor(descriptor) => Some(descriptor),
+            _ => None,
+        }
+    }
+
+    /// Get a numeric field by key, regardless of whether it was stored as an integer, a double
+    /// or a unit float.
+    pub(crate) fn number(&self, key: &str) -> Option<f64> {
+        match self.fields.get(key)? {
+            DescriptorField::Double(value) => Some(*value),
+            DescriptorField::Integer(value) => Some(*value as f64),
+            DescriptorField::LargeInteger(value) => Some(*value as f64),
+            DescriptorField::UnitFloat(unit_float) => Some(unit_float.value()),
+            _ => None,
+        }
+    }
+
+    /// Get a boolean field by key
+    pub(crate) fn boolean(&self, key: &str) -> Option<bool> {
+        match self.fields.get(key)? {
+            DescriptorField::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a text field by key, without the trailing null character that Photoshop writes
+    pub(crate) fn string(&self, key: &str) -> Option<&str> {
+        match self.fields.get(key)? {
+            DescriptorField::String(value) => Some(value.trim_end_matches('\0')),
+            _ => None,
+        }
+    }
+
+    /// Get the value of an enumerated field by key
+    pub(crate) fn enumerated(&self, key: &str) -> Option<&[u8]> {
+        match self.fields.get(key)? {
+            DescriptorField::EnumeratedDescriptor(enumerated) => Some(&enumerated.enum_field),
+            _ => None,
+        }
+    }
+
+    /// Get a list field by key
+    pub(crate) fn list(&self, key: &str) -> Option<&[DescriptorField]> {
+        match self.fields.get(key)? {
+            DescriptorField::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+impl UnitFloatStructure {
+    /// The value of the unit float, regardless of its unit
+    pub(crate) fn value(&self) -> f64 {
+        match self {
+            UnitFloatStructure::Angle(value)
+            | UnitFloatStructure::Density(value)
+            | UnitFloatStructure::Distance(value)
+            | UnitFloatStructure::None(value)
+            | UnitFloatStructure::Percent(value)
+            | UnitFloatStructure::Pixels(value)
+            | UnitFloatStructure::Points(value)
+            | UnitFloatStructure::Millimeters(value) => *value,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::prelude::*;
+use crate::sections::PsdCursor;
+
+/// Read the alpha channel names resource, a Pascal string for each of the alpha and spot
+/// channels that follow the color channels.
+///
+/// Unlike the other Pascal strings in a PSD the names aren't padded. A name that is cut short
+/// ends the list.
+pub(crate) fn read_alpha_names(bytes: &[u8]) -> Vec<String> {
+    let mut names = vec![];
+
+    let mut remaining = bytes;
+    while let Some((len, rest)) = remaining.split_first() {
+        let name = match rest.get(..*len as usize) {
+            Some(name) => name,
+            None => break,
+        };
+        names.push(String::from_utf8_lossy(name).into_owned());
+
+        remaining = &rest[*len as usize..];
+    }
+
+    names
+}
+
+/// Read the Unicode alpha names resource, which holds the same names as the alpha channel
+/// names resource as Unicode strings.
+///
+/// The length of each name counts its null terminator, which is removed. A name that is cut
+/// short ends the list.
+pub(crate) fn read_unicode_alpha_names(bytes: &[u8]) -> Vec<String> {
+    let mut cursor = PsdCursor::new(bytes);
+
+    let mut names = vec![];
+    while let Ok(name) = cursor.read_unicode_string_padding(1) {
+        names.push(name.trim_end_matches('\0').to_string());
+    }
+
+    names
+}
@@ -0,0 +1,169 @@
+use crate::prelude::*;
+use crate::sections::{PsdCursor, PsdCursorError};
+
+/// A point that was placed with Photoshop's color sampler tool, to keep an eye on the color of
+/// the image at that point.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Color samplers resource format
+///
+/// | Length   | Description                                                     |
+/// |----------|-----------------------------------------------------------------|
+/// | 4        | Version ( = 1 or 2)                                             |
+/// | 4        | Number of color samplers to follow                              |
+///
+/// The following is repeated for each color sampler:
+///
+/// | Length   | Description                                                                      |
+/// |----------|----------------------------------------------------------------------------------|
+/// | 8        | The vertical and horizontal position of the point. Fixed 16.16 in version 1, and |
+/// |          | floating point in version 2                                                      |
+/// | 2        | Color space                                                                      |
+/// | 2        | Depth (version 2 only)                                                           |
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorSampler {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) color_space: ColorSpace,
+    pub(crate) depth: Option<u16>,
+}
+
+/// The color space that a color is displayed or stored in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum ColorSpace {
+    /// The color space of the document, which is what a color sampler shows by default
+    Actual,
+    Rgb,
+    Hsb,
+    Cmyk,
+    Lab,
+    Grayscale,
+    /// A color space that isn't one of the above, such as a custom color book
+    Other(i16),
+}
+
+impl ColorSpace {
+    /// Photoshop's ID for a color space
+    pub(crate) fn new(id: i16) -> ColorSpace {
+        match id {
+            -1 => ColorSpace::Actual,
+            0 => ColorSpace::Rgb,
+            1 => ColorSpace::Hsb,
+            2 => ColorSpace::Cmyk,
+            7 => ColorSpace::Lab,
+            8 => ColorSpace::Grayscale,
+            id => ColorSpace::Other(id),
+        }
+    }
+}
+
+impl ColorSampler {
+    /// Read the color samplers resource.
+    ///
+    /// Unknown versions and samplers that are cut short are ignored.
+    pub(crate) fn read_color_samplers(bytes: &[u8]) -> Vec<ColorSampler> {
+        let mut cursor = PsdCursor::new(bytes);
+        let (version, count) = match (cursor.read_u32(), cursor.read_u32()) {
+            (Ok(version), Ok(count)) => (version, count),
+            _ => return vec![],
+        };
+        if version != 1 && version != 2 {
+            return vec![];
+        }
+
+        let mut samplers = vec![];
+        for _ in 0..count {
+            match ColorSampler::read_color_sampler(&mut cursor, version) {
+                Ok(sampler) => samplers.push(sampler),
+                Err(_) => break,
+            }
+        }
+
+        samplers
+    }
+
+    fn read_color_sampler(
+        cursor: &mut PsdCursor,
+        version: u32,
+    ) -> Result<ColorSampler, PsdCursorError> {
+        let (y, x) = if version == 1 {
+            let y = cursor.read_i32()? as f64 / 65536.;
+            let x = cursor.read_i32()? as f64 / 65536.;
+            (y, x)
+        } else {
+            let y = f32::from_bits(cursor.read_u32()?) as f64;
+            let x = f32::from_bits(cursor.read_u32()?) as f64;
+            (y, x)
+        };
+
+        let color_space = ColorSpace::new(cursor.read_i16()?);
+        let depth = if version == 2 {
+            Some(cursor.read_u16()?)
+        } else {
+            None
+        };
+
+        Ok(ColorSampler {
+            x,
+            y,
+            color_space,
+            depth,
+        })
+    }
+
+    /// The horizontal position of the sampler, in pixels from the left of the canvas
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    /// The vertical position of the sampler, in pixels from the top of the canvas
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    /// The color space that the sampler displays its color in
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// The bit depth that the sampler displays its color at, if the PSD stores it
+    pub fn depth(&self) -> Option<u16> {
+        self.depth
+    }
+
+    /// The pixel that the sampler is on, if the sampler is within a canvas of the given size
+    pub(crate) fn pixel(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (x, y) = (self.x.floor(), self.y.floor());
+        if x < 0. || y < 0. || x >= width as f64 || y >= height as f64 {
+            return None;
+        }
+
+        Some((x as u32, y as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Version 2 stores floating point positions and a depth for each sampler.
+    #[test]
+    fn version_2() {
+        let mut bytes = vec![0, 0, 0, 2, 0, 0, 0, 1];
+        bytes.extend_from_slice(&2.5f32.to_be_bytes());
+        bytes.extend_from_slice(&1.25f32.to_be_bytes());
+        bytes.extend_from_slice(&[0, 7, 0, 16]);
+
+        assert_eq!(
+            ColorSampler::read_color_samplers(&bytes),
+            vec![ColorSampler {
+                x: 1.25,
+                y: 2.5,
+                color_space: ColorSpace::Lab,
+                depth: Some(16),
+            }]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::prelude::*;
+
+/// The EXIF tag that holds the orientation
+const TAG_ORIENTATION: u16 = 0x0112;
+/// The TIFF type of a 16 bit unsigned integer
+const TYPE_SHORT: u16 = 3;
+
+/// How the pixels of a photo have to be rotated or flipped to show it upright, from the
+/// orientation tag of the PSD's EXIF data.
+///
+/// Cameras store photos the way the sensor saw them and record how the camera was held, so
+/// a photo that is brought into Photoshop can keep its pixels sideways. See
+/// [`crate::PsdOptions::apply_exif_orientation`].
+///
+/// Each variant is named after what has to be done to the stored pixels to show them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExifOrientation {
+    /// The pixels are already upright (orientation 1)
+    Normal,
+    /// Mirror the pixels left to right (orientation 2)
+    FlipHorizontal,
+    /// Rotate the pixels 180 degrees (orientation 3)
+    Rotate180,
+    /// Mirror the pixels top to bottom (orientation 4)
+    FlipVertical,
+    /// Mirror the pixels across the line from the top left to the bottom right corner
+    /// (orientation 5)
+    Transpose,
+    /// Rotate the pixels 90 degrees clockwise (orientation 6)
+    Rotate90,
+    /// Mirror the pixels across the line from the top right to the bottom left corner
+    /// (orientation 7)
+    Transverse,
+    /// Rotate the pixels 270 degrees clockwise (orientation 8)
+    Rotate270,
+}
+
+impl ExifOrientation {
+    /// The orientation with the value of the EXIF orientation tag, from 1 to 8
+    pub fn from_tag(tag: u16) -> Option<ExifOrientation> {
+        let orientation = match tag {
+            1 => ExifOrientation::Normal,
+            2 => ExifOrientation::FlipHorizontal,
+            3 => ExifOrientation::Rotate180,
+            4 => ExifOrientation::FlipVertical,
+            5 => ExifOrientation::Transpose,
+            6 => ExifOrientation::Rotate90,
+            7 => ExifOrientation::Transverse,
+            8 => ExifOrientation::Rotate270,
+            _ => return None,
+        };
+
+        Some(orientation)
+    }
+
+    /// Read the orientation tag from the first image file directory of the EXIF data resource,
+    /// which is a TIFF file without any pixels.
+    ///
+    /// Returns `None` if the data doesn't have a valid orientation tag.
+    pub(crate) fn from_exif(exif: &[u8]) -> Option<ExifOrientation> {
+        // Some writers keep the "Exif" header of a JPEG's APP1 segment
+        let tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(exif);
+
+        let big_endian = match tiff.get(..4)? {
+            b"MM\0*" => true,
+            b"II*\0" => false,
+            _ => return None,
+        };
+        let read_u16 = |offset: usize| {
+            let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+            Some(match big_endian {
+                true => u16::from_be_bytes(bytes),
+                false => u16::from_le_bytes(bytes),
+            })
+        };
+        let read_u32 = |offset: usize| {
+            let bytes = tiff.get(offset..offset + 4)?;
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            Some(match big_endian {
+                true => u32::from_be_bytes(bytes),
+                false => u32::from_le_bytes(bytes),
+            })
+        };
+
+        let ifd = read_u32(4)? as usize;
+        let entry_count = read_u16(ifd)? as usize;
+
+        // Each entry is a 2 byte tag, a 2 byte type, a 4 byte count and a 4 byte value, which
+        // holds a SHORT in its first two bytes
+        (0..entry_count)
+            .map(|entry| ifd + 2 + entry * 12)
+            .find(|entry| read_u16(*entry) == Some(TAG_ORIENTATION))
+            .filter(|entry| read_u16(entry + 2) == Some(TYPE_SHORT))
+            .and_then(|entry| read_u16(entry + 8))
+            .and_then(ExifOrientation::from_tag)
+    }
+
+    /// Whether showing the pixels swaps their width and height
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(
+            self,
+            ExifOrientation::Transpose
+                | ExifOrientation::Rotate90
+                | ExifOrientation::Transverse
+                | ExifOrientation::Rotate270
+        )
+    }
+
+    /// The width and height of `width` x `height` pixels once they are shown
+    pub fn oriented_size(self, width: u32, height: u32) -> (u32, u32) {
+        match self.swaps_dimensions() {
+            true => (height, width),
+            false => (width, height),
+        }
+    }
+
+    /// Rotate or flip `width` x `height` RGBA pixels (`[R, G, B, A, R, G, B, A, ...]`) to show
+    /// them upright. The pixels that are returned are [`ExifOrientation::oriented_size`].
+    pub fn apply(self, rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+        if self == ExifOrientation::Normal {
+            return rgba.to_vec();
+        }
+
+        let (width, height) = (width as usize, height as usize);
+        let (oriented_width, oriented_height) = match self.swaps_dimensions() {
+            true => (height, width),
+            false => (width, height),
+        };
+
+        let mut oriented = Vec::with_capacity(rgba.len());
+        for y in 0..oriented_height {
+            for x in 0..oriented_width {
+                let (src_x, src_y) = match self {
+                    ExifOrientation::Normal => (x, y),
+                    ExifOrientation::FlipHorizontal => (width - 1 - x, y),
+                    ExifOrientation::Rotate180 => (width - 1 - x, height - 1 - y),
+                    ExifOrientation::FlipVertical => (x, height - 1 - y),
+                    ExifOrientation::Transpose => (y, x),
+                    ExifOrientation::Rotate90 => (y, height - 1 - x),
+                    ExifOrientation::Transverse => (width - 1 - y, height - 1 - x),
+                    ExifOrientation::Rotate270 => (width - 1 - y, x),
+                };
+
+                let idx = (src_y * width + src_x) * 4;
+                oriented.extend_from_slice(&rgba[idx..idx + 4]);
+            }
+        }
+
+        oriented
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3x2 pixels whose red is their index, so that each one can be followed
+    fn pixels() -> Vec<u8> {
+        (0..6).flat_map(|idx| [idx, 0, 0, 255]).collect()
+    }
+
+    fn reds(rgba: &[u8]) -> Vec<u8> {
+        rgba.chunks_exact(4).map(|pixel| pixel[0]).collect()
+    }
+
+    /// Each orientation moves the pixels of
+    ///
+    /// 0 1 2
+    /// 3 4 5
+    #[test]
+    fn apply() {
+        let expected: [(ExifOrientation, &[u8]); 8] = [
+            (ExifOrientation::Normal, &[0, 1, 2, 3, 4, 5]),
+            (ExifOrientation::FlipHorizontal, &[2, 1, 0, 5, 4, 3]),
+            (ExifOrientation::Rotate180, &[5, 4, 3, 2, 1, 0]),
+            (ExifOrientation::FlipVertical, &[3, 4, 5, 0, 1, 2]),
+            (ExifOrientation::Transpose, &[0, 3, 1, 4, 2, 5]),
+            (ExifOrientation::Rotate90, &[3, 0, 4, 1, 5, 2]),
+            (ExifOrientation::Transverse, &[5, 2, 4, 1, 3, 0]),
+            (ExifOrientation::Rotate270, &[2, 5, 1, 4, 0, 3]),
+        ];
+
+        for (tag, (orientation, reds_shown)) in (1..).zip(expected) {
+            assert_eq!(ExifOrientation::from_tag(tag), Some(orientation));
+            assert_eq!(reds(&orientation.apply(&pixels(), 3, 2)), reds_shown);
+        }
+    }
+
+    /// The orientation is read from little and big endian TIFFs.
+    #[test]
+    fn from_exif() {
+        // One entry for the image width, then the orientation
+        let little_endian = [
+            b"II*\0".as_slice(),
+            &8u32.to_le_bytes(),
+            &2u16.to_le_bytes(),
+            &[0x00, 0x01, 3, 0, 1, 0, 0, 0, 3, 0, 0, 0],
+            &[0x12, 0x01, 3, 0, 1, 0, 0, 0, 8, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(
+            ExifOrientation::from_exif(&little_endian),
+            Some(ExifOrientation::Rotate270)
+        );
+
+        let big_endian = [
+            b"Exif\0\0MM\0*".as_slice(),
+            &8u32.to_be_bytes(),
+            &1u16.to_be_bytes(),
+            &[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0],
+        ]
+        .concat();
+        assert_eq!(
+            ExifOrientation::from_exif(&big_endian),
+            Some(ExifOrientation::Rotate90)
+        );
+
+        assert_eq!(ExifOrientation::from_exif(&big_endian[..20]), None);
+    }
+}
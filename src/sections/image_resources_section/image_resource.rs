@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::sections::image_resources_section::DescriptorStructure;
 
 /// An image resource from the image resources section
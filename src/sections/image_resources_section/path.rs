@@ -0,0 +1,505 @@
+use crate::geometry::{Point, Rect};
+use crate::prelude::*;
+use crate::sections::{PsdCursor, PsdCursorError};
+
+/// Each path record is 26 bytes long, starting with a two byte selector
+const PATH_RECORD_LEN: usize = 26;
+
+const CLOSED_SUBPATH_LENGTH: u16 = 0;
+const CLOSED_SUBPATH_KNOT_LINKED: u16 = 1;
+const CLOSED_SUBPATH_KNOT_UNLINKED: u16 = 2;
+const OPEN_SUBPATH_LENGTH: u16 = 3;
+const OPEN_SUBPATH_KNOT_LINKED: u16 = 4;
+const OPEN_SUBPATH_KNOT_UNLINKED: u16 = 5;
+const INITIAL_FILL_RULE: u16 = 8;
+
+/// How far, in pixels, the straight lines that curves are split into can stray from the curves
+/// when rasterizing
+const FLATTENING_TOLERANCE: f64 = 0.1;
+
+/// A path that was saved in the PSD, such as a clipping path or the work path.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Path resource format
+///
+/// The path is stored as a list of 26 byte records. The first two bytes of each record are a
+/// selector that determines the kind of the record.
+///
+/// | Selector | Description                                 |
+/// |----------|---------------------------------------------|
+/// | 0        | Closed subpath length record                |
+/// | 1        | Closed subpath Bezier knot, linked          |
+/// | 2        | Closed subpath Bezier knot, unlinked        |
+/// | 3        | Open subpath length record                  |
+/// | 4        | Open subpath Bezier knot, linked            |
+/// | 5        | Open subpath Bezier knot, unlinked          |
+/// | 6        | Path fill rule record                       |
+/// | 7        | Clipboard record                            |
+/// | 8        | Initial fill rule record                    |
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsdPath {
+    pub(crate) resource_id: i16,
+    pub(crate) name: String,
+    pub(crate) subpaths: Vec<Subpath>,
+    pub(crate) fill_starts_with_all_pixels: bool,
+}
+
+/// A series of connected Bezier knots within a [`PsdPath`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpath {
+    pub(crate) closed: bool,
+    pub(crate) knots: Vec<BezierKnot>,
+}
+
+/// A point on a path along with the control points of the curves that enter and leave it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BezierKnot {
+    pub(crate) linked: bool,
+    pub(crate) preceding: PathPoint,
+    pub(crate) anchor: PathPoint,
+    pub(crate) leaving: PathPoint,
+}
+
+/// A position on a path.
+///
+/// Path points are stored relative to the size of the document, so `0.0` is the top or left
+/// edge of the canvas and `1.0` is the bottom or right edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPoint {
+    /// The horizontal position, as a fraction of the width of the document
+    pub x: f64,
+    /// The vertical position, as a fraction of the height of the document
+    pub y: f64,
+}
+
+/// The clipping path resource, which names the saved path that is used as the clipping path.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClippingPath {
+    pub(crate) name: String,
+    pub(crate) flatness: f64,
+}
+
+impl PsdPath {
+    /// Read the records of a path resource.
+    ///
+    /// Knots that come before the first subpath length record are ignored, as are trailing
+    /// bytes that don't make up a full record.
+    pub(crate) fn from_bytes(resource_id: i16, name: String, bytes: &[u8]) -> PsdPath {
+        let mut subpaths: Vec<Subpath> = vec![];
+        let mut fill_starts_with_all_pixels = false;
+
+        for record in bytes.chunks_exact(PATH_RECORD_LEN) {
+            // Every record is long enough for the values that it holds
+            if read_path_record(record, &mut subpaths, &mut fill_starts_with_all_pixels).is_err() {
+                break;
+            }
+        }
+
+        PsdPath {
+            resource_id,
+            name,
+            subpaths,
+            fill_starts_with_all_pixels,
+        }
+    }
+
+    /// The ID of the image resource that the path was stored in.
+    ///
+    /// Saved paths use IDs 2000 through 2997 and the work path uses 1025.
+    pub fn resource_id(&self) -> i16 {
+        self.resource_id
+    }
+
+    /// The name of the path, as shown in Photoshop's paths panel
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The subpaths that make up the path
+    pub fn subpaths(&self) -> &[Subpath] {
+        &self.subpaths
+    }
+
+    /// Whether filling the path starts with all pixels selected, instead of none
+    pub fn fill_starts_with_all_pixels(&self) -> bool {
+        self.fill_starts_with_all_pixels
+    }
+}
+
+impl Subpath {
+    /// Whether the last knot connects back to the first
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// The knots of the subpath, in order
+    pub fn knots(&self) -> &[BezierKnot] {
+        &self.knots
+    }
+
+    /// The points along the subpath in pixels, with its curves split into straight lines. The
+    /// last point connects back to the first, even if the subpath is open.
+    fn flatten(&self, width: u32, height: u32) -> Vec<(f64, f64)> {
+        let to_pixels = |point: PathPoint| point.to_pixels(width, height);
+        let mut points = vec![];
+
+        for (idx, knot) in self.knots.iter().enumerate() {
+            let start = to_pixels(knot.anchor);
+            points.push(start);
+
+            let next = match self.knots.get(idx + 1) {
+                Some(next) => next,
+                None if self.closed => &self.knots[0],
+                None => break,
+            };
+            let control_1 = to_pixels(knot.leaving);
+            let control_2 = to_pixels(next.preceding);
+            let end = to_pixels(next.anchor);
+
+            // Split the curve into enough lines that none of them stray further than the
+            // tolerance, based on how sharply its control points bend
+            let bend = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+                let (x, y) = (a.0 - 2. * b.0 + c.0, a.1 - 2. * b.1 + c.1);
+                (x * x + y * y).sqrt()
+            };
+            let bend = bend(start, control_1, control_2).max(bend(control_1, control_2, end));
+            let lines = (0.75 * bend / FLATTENING_TOLERANCE)
+                .sqrt()
+                .ceil()
+                .clamp(1., 256.) as usize;
+
+            for line in 1..lines {
+                let t = line as f64 / lines as f64;
+                let u = 1. - t;
+                let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+                points.push((
+                    a * start.0 + b * control_1.0 + c * control_2.0 + d * end.0,
+                    a * start.1 + b * control_1.1 + c * control_2.1 + d * end.1,
+                ));
+            }
+        }
+
+        points
+    }
+}
+
+/// Fill the subpaths into a coverage mask with one byte per pixel of a `width` x `height`
+/// canvas, from 0 (outside) to 255 (inside).
+///
+/// Each subpath is filled on its own, closing it if it is open, and the subpaths are combined.
+/// Edges are anti-aliased by how much of each pixel they cover.
+pub(crate) fn rasterize_subpaths(subpaths: &[Subpath], width: u32, height: u32) -> Vec<u8> {
+    match Rect::from_origin_and_size(Point::new(0, 0), width, height) {
+        Some(canvas) => rasterize_subpaths_region(subpaths, (width, height), canvas),
+        None => vec![],
+    }
+}
+
+/// Like [`rasterize_subpaths`], but only the pixels of the canvas within the region, with one
+/// byte per pixel of the region.
+///
+/// Lines to the left of the region still cover the pixels to their right, so they are moved to
+/// its left edge instead of being left out, and lines below or above it are skipped.
+pub(crate) fn rasterize_subpaths_region(
+    subpaths: &[Subpath],
+    (canvas_width, canvas_height): (u32, u32),
+    region: Rect,
+) -> Vec<u8> {
+    let (width, height) = (region.width() as usize, region.height() as usize);
+    let mut coverage = vec![0; width * height];
+
+    // Every row has room for the lines that are on or past the right edge of the region
+    let stride = width + 2;
+    let mut accumulation = vec![0.; stride * height];
+
+    for subpath in subpaths {
+        let points: Vec<(f64, f64)> = subpath
+            .flatten(canvas_width, canvas_height)
+            .into_iter()
+            .map(|(x, y)| (x - region.left as f64, y - region.top as f64))
+            .collect();
+        if points.len() < 3 {
+            continue;
+        }
+
+        accumulation.fill(0.);
+        for (idx, start) in points.iter().enumerate() {
+            let end = points[(idx + 1) % points.len()];
+            accumulate_line(&mut accumulation, (stride, height), *start, end);
+        }
+
+        // The coverage of each pixel is the sum of the lines' areas from the left edge
+        let rows = accumulation
+            .chunks_exact(stride)
+            .zip(coverage.chunks_exact_mut(width));
+        for (row, coverage) in rows {
+            let mut area = 0.;
+            for (cell, coverage) in row.iter().zip(coverage.iter_mut()) {
+                area += cell;
+                let value = (area.abs().min(1.) * 255.).round() as u8;
+                *coverage = (*coverage).max(value);
+            }
+        }
+    }
+
+    coverage
+}
+
+/// Add the area that a line covers to each of the cells that it passes through, so that summing
+/// a row's cells from the left gives the winding of each pixel weighted by how much of the pixel
+/// is inside.
+///
+/// Lines are split where they cross the left or right edge, and the parts outside are clamped
+/// to that edge, which doesn't change what they cover.
+fn accumulate_line(
+    accumulation: &mut [f64],
+    (stride, height): (usize, usize),
+    start: (f64, f64),
+    end: (f64, f64),
+) {
+    let max_x = (stride - 2) as f64;
+    for edge in [0., max_x] {
+        if (start.0 < edge && edge < end.0) || (end.0 < edge && edge < start.0) {
+            let t = (edge - start.0) / (end.0 - start.0);
+            let crossing = (edge, start.1 + (end.1 - start.1) * t);
+            accumulate_line(accumulation, (stride, height), start, crossing);
+            accumulate_line(accumulation, (stride, height), crossing, end);
+            return;
+        }
+    }
+
+    let start = (start.0.clamp(0., max_x), start.1);
+    let end = (end.0.clamp(0., max_x), end.1);
+
+    if start.1 == end.1 {
+        return;
+    }
+    let (direction, top, bottom) = if start.1 < end.1 {
+        (1., start, end)
+    } else {
+        (-1., end, start)
+    };
+
+    let dxdy = (bottom.0 - top.0) / (bottom.1 - top.1);
+    let mut x = top.0;
+    if top.1 < 0. {
+        x -= top.1 * dxdy;
+    }
+
+    let first_row = top.1.max(0.) as usize;
+    let end_row = bottom.1.min(height as f64).ceil().max(0.) as usize;
+    for y in first_row..end_row {
+        let row = &mut accumulation[y * stride..(y + 1) * stride];
+
+        let dy = ((y + 1) as f64).min(bottom.1) - (y as f64).max(top.1);
+        let next_x = x + dxdy * dy;
+        let d = dy * direction;
+
+        let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+        let x0_floor = x0.floor();
+        let x0i = x0_floor as usize;
+        let x1_ceil = x1.ceil();
+        let x1i = x1_ceil as usize;
+
+        if x1i <= x0i + 1 {
+            // The line stays within one pixel of this row
+            let x_mid = 0.5 * (x + next_x) - x0_floor;
+            row[x0i] += d - d * x_mid;
+            row[x0i + 1] += d * x_mid;
+        } else {
+            let s = 1. / (x1 - x0);
+            let x0_fract = x0 - x0_floor;
+            let a0 = 0.5 * s * (1. - x0_fract) * (1. - x0_fract);
+            let x1_fract = x1 - x1_ceil + 1.;
+            let am = 0.5 * s * x1_fract * x1_fract;
+
+            row[x0i] += d * a0;
+            if x1i == x0i + 2 {
+                row[x0i + 1] += d * (1. - a0 - am);
+            } else {
+                let a1 = s * (1.5 - x0_fract);
+                row[x0i + 1] += d * (a1 - a0);
+                for cell in row[x0i + 2..x1i - 1].iter_mut() {
+                    *cell += d * s;
+                }
+                let a2 = a1 + (x1i - x0i - 3) as f64 * s;
+                row[x1i - 1] += d * (1. - a2 - am);
+            }
+            row[x1i] += d * am;
+        }
+
+        x = next_x;
+    }
+}
+
+impl BezierKnot {
+    /// Whether the control points are linked, so that moving one moves the other
+    pub fn is_linked(&self) -> bool {
+        self.linked
+    }
+
+    /// The control point of the curve that enters the anchor
+    pub fn preceding(&self) -> PathPoint {
+        self.preceding
+    }
+
+    /// The point that the path passes through
+    pub fn anchor(&self) -> PathPoint {
+        self.anchor
+    }
+
+    /// The control point of the curve that leaves the anchor
+    pub fn leaving(&self) -> PathPoint {
+        self.leaving
+    }
+}
+
+impl PathPoint {
+    /// The position of the point in pixels, given the size of the document
+    pub fn to_pixels(&self, width: u32, height: u32) -> (f64, f64) {
+        (self.x * width as f64, self.y * height as f64)
+    }
+}
+
+impl ClippingPath {
+    /// | Length   | Description                                 |
+    /// |----------|---------------------------------------------|
+    /// | Variable | Name of the clipping path: Pascal string    |
+    /// | 2        | Flatness, fixed point 8.8                   |
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<ClippingPath> {
+        let name_len = *bytes.first()? as usize;
+        let name = bytes.get(1..1 + name_len)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        let flatness = match bytes.get(1 + name_len..3 + name_len) {
+            Some(flatness) => u16::from_be_bytes([flatness[0], flatness[1]]) as f64 / 256.,
+            None => 0.,
+        };
+
+        Some(ClippingPath { name, flatness })
+    }
+}
+
+/// Points are stored vertical component first, as signed fixed point 8.24 numbers
+/// Read a path record, adding a subpath for a subpath length record or a knot to the last
+/// subpath for a knot record
+fn read_path_record(
+    record: &[u8],
+    subpaths: &mut Vec<Subpath>,
+    fill_starts_with_all_pixels: &mut bool,
+) -> Result<(), PsdCursorError> {
+    let mut cursor = PsdCursor::new(record);
+
+    match cursor.read_u16()? {
+        selector @ (CLOSED_SUBPATH_LENGTH | OPEN_SUBPATH_LENGTH) => {
+            // We don't reserve the number of knots, since a record this short could claim
+            // far more knots than the resource holds
+            let _knot_count = cursor.read_u16()?;
+            subpaths.push(Subpath {
+                closed: selector == CLOSED_SUBPATH_LENGTH,
+                knots: vec![],
+            });
+        }
+        selector @ (CLOSED_SUBPATH_KNOT_LINKED
+        | CLOSED_SUBPATH_KNOT_UNLINKED
+        | OPEN_SUBPATH_KNOT_LINKED
+        | OPEN_SUBPATH_KNOT_UNLINKED) => {
+            let linked =
+                selector == CLOSED_SUBPATH_KNOT_LINKED || selector == OPEN_SUBPATH_KNOT_LINKED;
+
+            let knot = BezierKnot {
+                linked,
+                preceding: read_path_point(&mut cursor)?,
+                anchor: read_path_point(&mut cursor)?,
+                leaving: read_path_point(&mut cursor)?,
+            };
+            if let Some(subpath) = subpaths.last_mut() {
+                subpath.knots.push(knot);
+            }
+        }
+        INITIAL_FILL_RULE => {
+            *fill_starts_with_all_pixels = cursor.read_u16()? == 1;
+        }
+        // Path fill rule and clipboard records
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn read_path_point(cursor: &mut PsdCursor) -> Result<PathPoint, PsdCursorError> {
+    let y = cursor.read_i32()? as f64 / (1 << 24) as f64;
+    let x = cursor.read_i32()? as f64 / (1 << 24) as f64;
+
+    Ok(PathPoint { x, y })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A circle of radius 0.4 in the middle of the canvas, made of four curves
+    fn circle() -> Subpath {
+        // How far the control points are from the anchors of a circle of radius 1
+        const K: f64 = 0.552_284_8;
+
+        let (center, radius) = (0.5, 0.4);
+        let knot = |(x, y): (f64, f64), (dx, dy): (f64, f64)| {
+            let point = |x: f64, y: f64| PathPoint {
+                x: center + x * radius,
+                y: center + y * radius,
+            };
+            BezierKnot {
+                linked: true,
+                preceding: point(x - dx * K, y - dy * K),
+                anchor: point(x, y),
+                leaving: point(x + dx * K, y + dy * K),
+            }
+        };
+        Subpath {
+            closed: true,
+            knots: vec![
+                knot((1., 0.), (0., 1.)),
+                knot((0., 1.), (-1., 0.)),
+                knot((-1., 0.), (0., -1.)),
+                knot((0., -1.), (1., 0.)),
+            ],
+        }
+    }
+
+    /// A circle made of four curves covers about the area of a circle.
+    #[test]
+    fn rasterize_curves() {
+        let coverage = rasterize_subpaths(&[circle()], 100, 100);
+
+        let area = coverage.iter().map(|value| *value as f64).sum::<f64>() / 255.;
+        let expected = core::f64::consts::PI * 40. * 40.;
+        // The lines that the curves are split into cut a little off of the circle
+        assert!((area - expected).abs() < expected * 0.005, "{}", area);
+
+        assert_eq!(coverage[50 * 100 + 50], 255);
+        assert_eq!(coverage[50 * 100 + 95], 0);
+        assert_eq!(coverage[15 * 100 + 15], 0);
+    }
+
+    /// Rasterizing a region gives the same pixels as cropping the whole canvas, including the
+    /// pixels whose edges are to the left of the region.
+    #[test]
+    fn rasterize_region() {
+        let coverage = rasterize_subpaths(&[circle()], 100, 100);
+
+        let region = Rect {
+            top: 30,
+            left: 40,
+            bottom: 69,
+            right: 94,
+        };
+        let region_coverage = rasterize_subpaths_region(&[circle()], (100, 100), region);
+
+        let cropped: Vec<u8> = (30..70)
+            .flat_map(|y| coverage[y * 100 + 40..y * 100 + 95].iter().copied())
+            .collect();
+        assert_eq!(region_coverage, cropped);
+    }
+}
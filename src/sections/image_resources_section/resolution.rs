@@ -0,0 +1,71 @@
+use crate::sections::PsdCursor;
+
+/// The resolution of the image, which is what sets the image's physical size when it's printed.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// ResolutionInfo structure
+///
+/// | Length   | Description                                                     |
+/// |----------|-----------------------------------------------------------------|
+/// | 4        | Horizontal resolution in pixels per inch. Fixed 16.16           |
+/// | 2        | Display unit of the horizontal resolution                       |
+/// | 2        | Display unit of the width                                       |
+/// | 4        | Vertical resolution in pixels per inch. Fixed 16.16             |
+/// | 2        | Display unit of the vertical resolution                         |
+/// | 2        | Display unit of the height                                      |
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolutionInfo {
+    pub(crate) horizontal_dpi: f64,
+    pub(crate) vertical_dpi: f64,
+    pub(crate) display_unit: ResolutionUnit,
+}
+
+/// The unit that Photoshop shows a resolution in. The resolution is always stored in pixels
+/// per inch.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum ResolutionUnit {
+    PixelsPerInch,
+    PixelsPerCentimeter,
+}
+
+impl ResolutionInfo {
+    /// Read the resolution info resource, or `None` if it is cut short.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<ResolutionInfo> {
+        if bytes.len() < 16 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(bytes);
+
+        let horizontal_dpi = cursor.read_i32().ok()? as f64 / 65536.;
+        let display_unit = match cursor.read_u16().ok()? {
+            2 => ResolutionUnit::PixelsPerCentimeter,
+            _ => ResolutionUnit::PixelsPerInch,
+        };
+        let _width_unit = cursor.read_u16().ok()?;
+        let vertical_dpi = cursor.read_i32().ok()? as f64 / 65536.;
+
+        Some(ResolutionInfo {
+            horizontal_dpi,
+            vertical_dpi,
+            display_unit,
+        })
+    }
+
+    /// The number of pixels per inch across the image
+    pub fn horizontal_dpi(&self) -> f64 {
+        self.horizontal_dpi
+    }
+
+    /// The number of pixels per inch down the image
+    pub fn vertical_dpi(&self) -> f64 {
+        self.vertical_dpi
+    }
+
+    /// The unit that Photoshop shows the resolution in
+    pub fn display_unit(&self) -> ResolutionUnit {
+        self.display_unit
+    }
+}
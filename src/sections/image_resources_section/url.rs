@@ -0,0 +1,84 @@
+use crate::prelude::*;
+use crate::sections::{PsdCursor, PsdCursorError};
+
+/// A URL from the URL list resource, which ImageReady and Photoshop's "Save for Web" use to
+/// link the slices of a web page.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// URL list resource format
+///
+/// | Length   | Description                                                     |
+/// |----------|-----------------------------------------------------------------|
+/// | 4        | Number of URLs to follow                                        |
+///
+/// The following is repeated for each URL:
+///
+/// | Length   | Description                                                     |
+/// |----------|-----------------------------------------------------------------|
+/// | 4        | Long                                                            |
+/// | 4        | ID                                                              |
+/// | Variable | URL: Unicode string                                             |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlListEntry {
+    pub(crate) number: u32,
+    pub(crate) id: u32,
+    pub(crate) url: String,
+}
+
+impl UrlListEntry {
+    /// Read the URL list resource.
+    ///
+    /// URLs that are cut short are ignored.
+    pub(crate) fn read_url_list(bytes: &[u8]) -> Vec<UrlListEntry> {
+        let mut cursor = PsdCursor::new(bytes);
+        let count = match cursor.read_u32() {
+            Ok(count) => count,
+            Err(_) => return vec![],
+        };
+
+        let mut urls = vec![];
+        for _ in 0..count {
+            match UrlListEntry::read_url_list_entry(&mut cursor) {
+                Ok(url) => urls.push(url),
+                Err(_) => break,
+            }
+        }
+
+        urls
+    }
+
+    fn read_url_list_entry(cursor: &mut PsdCursor) -> Result<UrlListEntry, PsdCursorError> {
+        let number = cursor.read_u32()?;
+        let id = cursor.read_u32()?;
+        let url = cursor.read_unicode_string_padding(1)?;
+
+        Ok(UrlListEntry { number, id, url })
+    }
+
+    /// The number that Photoshop stores before the URL's ID
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// The ID of the URL
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Read the URL resource, which is the text of the URL that the whole image links to.
+///
+/// The resource's data is padded to make its size even, so trailing null characters are
+/// removed.
+pub(crate) fn read_document_url(bytes: &[u8]) -> Option<String> {
+    let url = String::from_utf8_lossy(bytes);
+    let url = url.trim_end_matches('\0');
+
+    (!url.is_empty()).then(|| url.to_string())
+}
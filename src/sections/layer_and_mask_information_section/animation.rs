@@ -0,0 +1,266 @@
+use crate::prelude::*;
+use crate::sections::image_resources_section::{DescriptorField, DescriptorStructure};
+
+/// How a layer looks in some of the frames of a frame animation.
+///
+/// Photoshop stores the layer's frame states in its metadata (`mlst`). Frames that were added by
+/// tweening have states of their own, so the tweened opacity and position of every frame can be
+/// read from here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerFrameState {
+    pub(crate) frame_ids: Vec<u32>,
+    pub(crate) visible: Option<bool>,
+    pub(crate) offset: Option<(f64, f64)>,
+    pub(crate) opacity: Option<u8>,
+}
+
+impl LayerFrameState {
+    /// Read the frame states of a layer's `mlst` metadata item.
+    pub(crate) fn from_descriptor(descriptor: &DescriptorStructure) -> Vec<LayerFrameState> {
+        let states = descriptor.list("LaSt").unwrap_or_default();
+
+        states
+            .iter()
+            .filter_map(|state| match state {
+                DescriptorField::Descriptor(state) => Some(state),
+                _ => None,
+            })
+            .map(|state| LayerFrameState {
+                frame_ids: state
+                    .list("FrLs")
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|frame_id| match frame_id {
+                        DescriptorField::Integer(frame_id) => Some(*frame_id as u32),
+                        _ => None,
+                    })
+                    .collect(),
+                visible: state.boolean("enab"),
+                offset: state.descriptor("Ofst").and_then(horizontal_vertical),
+                opacity: state
+                    .descriptor("blendOptions")
+                    .and_then(|blend_options| blend_options.number("Opct"))
+                    .map(percent_to_opacity),
+            })
+            .collect()
+    }
+
+    /// The ids of the frames that the layer looks like this in
+    pub fn frame_ids(&self) -> &[u32] {
+        &self.frame_ids
+    }
+
+    /// Whether the layer is visible in these frames, if it differs from the layer
+    pub fn visible(&self) -> Option<bool> {
+        self.visible
+    }
+
+    /// How far the layer is moved in these frames, in pixels, if it is moved
+    pub fn offset(&self) -> Option<(f64, f64)> {
+        self.offset
+    }
+
+    /// The opacity of the layer in these frames, if it differs from the layer.
+    /// 0 = transparent ... 255 = opaque
+    pub fn opacity(&self) -> Option<u8> {
+        self.opacity
+    }
+}
+
+/// The keyframes of one of the layer's properties in a timeline animation.
+///
+/// Photoshop stores the layer's timeline in its metadata (`tmln`). Use
+/// [`TimelineTrack::value_at`] to get the value between keyframes the way that Photoshop
+/// tweens it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineTrack {
+    pub(crate) kind: TimelineTrackKind,
+    pub(crate) enabled: bool,
+    pub(crate) keyframes: Vec<Keyframe>,
+}
+
+/// The property that a [`TimelineTrack`] animates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineTrackKind {
+    /// The layer's opacity (`opacityTrack`)
+    Opacity,
+    /// The layer's position (`sheetPositionTrack`)
+    Position,
+    /// Any other track, such as the layer's style (`styleTrack`), by its id
+    Other(String),
+}
+
+/// The value of a [`TimelineTrack`] at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    pub(crate) time: f64,
+    pub(crate) interpolation: KeyframeInterpolation,
+    pub(crate) value: KeyframeValue,
+}
+
+/// How the value of a track changes between a keyframe and the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyframeInterpolation {
+    /// The value changes evenly (`Lnr `)
+    Linear,
+    /// The value stays the same until the next keyframe (`hold`)
+    Hold,
+}
+
+/// The value of a keyframe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyframeValue {
+    /// 0 = transparent ... 255 = opaque
+    Opacity(u8),
+    /// How far the layer is moved, in pixels
+    Position(f64, f64),
+    /// A value that isn't parsed, such as a layer style. See [`crate::LayerMetadata::fields`].
+    Other,
+}
+
+impl TimelineTrack {
+    /// Read the tracks of a layer's `tmln` metadata item.
+    pub(crate) fn from_descriptor(descriptor: &DescriptorStructure) -> Vec<TimelineTrack> {
+        let tracks = descriptor.list("trackList").unwrap_or_default();
+
+        tracks
+            .iter()
+            .filter_map(|track| match track {
+                DescriptorField::Descriptor(track) => Some(track),
+                _ => None,
+            })
+            .map(|track| {
+                let id = track.enumerated("trackID").unwrap_or_default();
+                let kind = match id {
+                    b"opacityTrack" => TimelineTrackKind::Opacity,
+                    b"sheetPositionTrack" => TimelineTrackKind::Position,
+                    _ => TimelineTrackKind::Other(String::from_utf8_lossy(id).into_owned()),
+                };
+
+                let mut keyframes: Vec<Keyframe> = track
+                    .list("keyList")
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|keyframe| match keyframe {
+                        DescriptorField::Descriptor(keyframe) => Keyframe::read(&kind, keyframe),
+                        _ => None,
+                    })
+                    .collect();
+                keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+                TimelineTrack {
+                    kind,
+                    enabled: track.boolean("enab").unwrap_or(true),
+                    keyframes,
+                }
+            })
+            .collect()
+    }
+
+    /// The property that the track animates
+    pub fn kind(&self) -> &TimelineTrackKind {
+        &self.kind
+    }
+
+    /// Whether the track is enabled. Photoshop ignores the keyframes of disabled tracks.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The track's keyframes, from the earliest to the latest
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// The value of the track `time` seconds into the timeline, or `None` if the track has no
+    /// keyframes.
+    ///
+    /// Before the first keyframe and after the last one the value stays the same. In between,
+    /// opacities and positions are interpolated the way that the earlier keyframe says to.
+    pub fn value_at(&self, time: f64) -> Option<KeyframeValue> {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time);
+
+        let (from, to) = match next {
+            None => return self.keyframes.last().map(|keyframe| keyframe.value),
+            Some(0) => return Some(self.keyframes[0].value),
+            Some(next) => (&self.keyframes[next - 1], &self.keyframes[next]),
+        };
+
+        if from.interpolation == KeyframeInterpolation::Hold {
+            return Some(from.value);
+        }
+
+        let t = (time - from.time) / (to.time - from.time);
+        let lerp = |from: f64, to: f64| from + (to - from) * t;
+
+        let value = match (from.value, to.value) {
+            (KeyframeValue::Opacity(from), KeyframeValue::Opacity(to)) => {
+                KeyframeValue::Opacity(lerp(from as f64, to as f64).round() as u8)
+            }
+            (KeyframeValue::Position(from_x, from_y), KeyframeValue::Position(to_x, to_y)) => {
+                KeyframeValue::Position(lerp(from_x, to_x), lerp(from_y, to_y))
+            }
+            (value, _) => value,
+        };
+
+        Some(value)
+    }
+}
+
+impl Keyframe {
+    fn read(kind: &TimelineTrackKind, keyframe: &DescriptorStructure) -> Option<Keyframe> {
+        let time = keyframe.descriptor("time")?;
+        let denominator = time.number("denominator").filter(|d| *d != 0.)?;
+        let time = time.number("numerator")? / denominator;
+
+        let interpolation = match keyframe.enumerated("animInterpStyle") {
+            Some(b"hold") => KeyframeInterpolation::Hold,
+            _ => KeyframeInterpolation::Linear,
+        };
+
+        let key = keyframe.descriptor("animKey");
+        let value = match kind {
+            TimelineTrackKind::Opacity => key
+                .and_then(|key| key.number("Opct"))
+                .map(percent_to_opacity)
+                .map(KeyframeValue::Opacity),
+            TimelineTrackKind::Position => key
+                .and_then(horizontal_vertical)
+                .map(|(x, y)| KeyframeValue::Position(x, y)),
+            TimelineTrackKind::Other(_) => Some(KeyframeValue::Other),
+        };
+
+        Some(Keyframe {
+            time,
+            interpolation,
+            value: value.unwrap_or(KeyframeValue::Other),
+        })
+    }
+
+    /// When the keyframe is, in seconds from the start of the timeline
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// How the value changes between this keyframe and the next one
+    pub fn interpolation(&self) -> KeyframeInterpolation {
+        self.interpolation
+    }
+
+    /// The value of the track at this keyframe
+    pub fn value(&self) -> KeyframeValue {
+        self.value
+    }
+}
+
+/// Read the `Hrzn` and `Vrtc` fields of a descriptor
+fn horizontal_vertical(descriptor: &DescriptorStructure) -> Option<(f64, f64)> {
+    Some((descriptor.number("Hrzn")?, descriptor.number("Vrtc")?))
+}
+
+fn percent_to_opacity(percent: f64) -> u8 {
+    (percent / 100. * 255.).round().clamp(0., 255.) as u8
+}
@@ -0,0 +1,258 @@
+use crate::geometry::Rect;
+use crate::prelude::*;
+use crate::sections::image_resources_section::ColorSpace;
+use crate::sections::{PsdCursor, PsdCursorError};
+
+/// Key of `Annotations (Photoshop 6.0)`, "Anno"
+pub(super) const KEY_ANNOTATIONS: &[u8; 4] = b"Anno";
+
+/// The bytes of an annotation before its author, from its type to its color
+const ANNOTATION_HEADER_LEN: u64 = 4 + 1 + 1 + 2 + 16 + 16 + 10;
+
+/// A note that was attached to the image with Photoshop's note tool, such as an artist's
+/// comment.
+///
+/// Annotations are stored in the additional layer information at the end of the layer and mask
+/// information section.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 2        | Major version ( = 2)                                                        |
+/// | 2        | Minor version ( = 1)                                                        |
+/// | 4        | Number of annotations                                                       |
+///
+/// The following is repeated for each annotation:
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 4        | Length of the annotation, padded to a multiple of 4 bytes                   |
+/// | 4        | Type of annotation: 'txtA' (text) or 'sndA' (sound)                         |
+/// | 1        | Open annotation flag                                                        |
+/// | 1        | Flags                                                                       |
+/// | 2        | Optional blocks                                                             |
+/// | 4 * 4    | Icon location: top, left, bottom, right                                     |
+/// | 4 * 4    | Popup location: top, left, bottom, right                                    |
+/// | 10       | Color: 2 bytes for the color space and 4 * 2 bytes for its components      |
+/// | Variable | Author: Pascal string                                                       |
+/// | Variable | Name: Pascal string                                                         |
+/// | Variable | Modification date: Pascal string                                            |
+/// | 4        | Length of the data that follows                                             |
+/// | 4        | Key: 'txtC' (text) or 'sndM' (sound)                                        |
+/// | 4        | Length of the data                                                          |
+/// | Variable | Data                                                                        |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub(crate) kind: AnnotationKind,
+    pub(crate) open: bool,
+    pub(crate) icon_rect: Rect,
+    pub(crate) popup_rect: Rect,
+    pub(crate) color_space: ColorSpace,
+    pub(crate) color: [u16; 4],
+    pub(crate) author: String,
+    pub(crate) name: String,
+    pub(crate) modified: String,
+    pub(crate) text: Option<String>,
+}
+
+/// What an annotation holds
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AnnotationKind {
+    /// A text note
+    Text,
+    /// A recorded sound
+    Sound,
+}
+
+impl Annotation {
+    /// Read all of the annotations in an `Anno` block.
+    ///
+    /// Annotations that are cut short or have an unknown type are skipped.
+    pub(super) fn read_annotations(bytes: &[u8]) -> Vec<Annotation> {
+        let mut annotations = vec![];
+
+        // The number of annotations comes after the major and minor version
+        let mut cursor = PsdCursor::new(bytes);
+        cursor.seek(4);
+        let count = match cursor.read_u32() {
+            Ok(count) => count,
+            Err(_) => return annotations,
+        };
+
+        let mut start = cursor.position() as usize;
+        for _ in 0..count {
+            cursor.seek(start as u64);
+            let annotation_len = match cursor.read_u32() {
+                Ok(annotation_len) => annotation_len as usize,
+                Err(_) => break,
+            };
+
+            // The length includes its own 4 bytes
+            let end = start + annotation_len;
+            if annotation_len < 4 || end > bytes.len() {
+                break;
+            }
+
+            if let Some(annotation) = Annotation::read_annotation(&bytes[start + 4..end]) {
+                annotations.push(annotation);
+            }
+
+            // Each annotation is padded to a multiple of 4 bytes
+            start = end + (4 - annotation_len % 4) % 4;
+        }
+
+        annotations
+    }
+
+    fn read_annotation(bytes: &[u8]) -> Option<Annotation> {
+        let len = bytes.len() as u64;
+        if len < ANNOTATION_HEADER_LEN {
+            return None;
+        }
+        let mut cursor = PsdCursor::new(bytes);
+
+        let kind = match cursor.read_4().ok()? {
+            b"txtA" => AnnotationKind::Text,
+            b"sndA" => AnnotationKind::Sound,
+            _ => return None,
+        };
+        let open = cursor.read_u8().ok()? != 0;
+        let _flags = cursor.read_u8().ok()?;
+        let _optional_blocks = cursor.read_u16().ok()?;
+
+        let icon_rect = read_rect(&mut cursor).ok()?;
+        let popup_rect = read_rect(&mut cursor).ok()?;
+
+        let color_space = ColorSpace::new(cursor.read_i16().ok()?);
+        let mut color = [0; 4];
+        for component in color.iter_mut() {
+            *component = cursor.read_u16().ok()?;
+        }
+
+        let mut strings = [String::new(), String::new(), String::new()];
+        for string in strings.iter_mut() {
+            // The length byte and the string, padded to make the size even
+            let string_len = *cursor.get_ref()[cursor.position() as usize..].first()?;
+            if cursor.position() + (string_len as u64 | 1) + 1 > len {
+                return None;
+            }
+            *string = cursor.read_pascal_string().ok()?;
+        }
+        let [author, name, modified] = strings;
+
+        if cursor.position() + 12 > len {
+            return None;
+        }
+        let _data_block_len = cursor.read_u32().ok()?;
+        let _key = cursor.read_4().ok()?;
+        let data_len = cursor.read_u32().ok()?;
+        if cursor.position() + data_len as u64 > len {
+            return None;
+        }
+        let data = cursor.read(data_len).ok()?;
+
+        let text = match kind {
+            AnnotationKind::Text => Some(decode_text(data)),
+            AnnotationKind::Sound => None,
+        };
+
+        Some(Annotation {
+            kind,
+            open,
+            icon_rect,
+            popup_rect,
+            color_space,
+            color,
+            author,
+            name,
+            modified,
+            text,
+        })
+    }
+
+    /// Whether the annotation is a text note or a sound
+    pub fn kind(&self) -> AnnotationKind {
+        self.kind
+    }
+
+    /// Whether the annotation's popup was open when the PSD was saved
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Where the annotation's icon is on the canvas. This is the point that the annotation is
+    /// anchored to.
+    pub fn icon_rect(&self) -> Rect {
+        self.icon_rect
+    }
+
+    /// Where the annotation's popup is on the canvas when it is open
+    pub fn popup_rect(&self) -> Rect {
+        self.popup_rect
+    }
+
+    /// The color space of [`Annotation::color`]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// The components of the annotation's color, in its [`Annotation::color_space`]. Unused
+    /// components are 0.
+    pub fn color(&self) -> [u16; 4] {
+        self.color
+    }
+
+    /// The name of the person who wrote the annotation
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// The annotation's name, which Photoshop leaves empty
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// When the annotation was last changed, as Photoshop formats it
+    pub fn modified(&self) -> &str {
+        &self.modified
+    }
+
+    /// The text of a text annotation, or `None` for a sound annotation
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+}
+
+/// A rectangle stored as its top, left, bottom and right edges, where the bottom and right are
+/// exclusive
+fn read_rect(cursor: &mut PsdCursor) -> Result<Rect, PsdCursorError> {
+    let top = cursor.read_i32()?;
+    let left = cursor.read_i32()?;
+    let bottom = cursor.read_i32()?;
+    let right = cursor.read_i32()?;
+
+    Ok(Rect {
+        top,
+        left,
+        bottom: bottom.saturating_sub(1),
+        right: right.saturating_sub(1),
+    })
+}
+
+/// Text that starts with a byte order mark is UTF-16, otherwise it is a single byte per
+/// character. Photoshop ends the text with a null character, which we remove.
+fn decode_text(data: &[u8]) -> String {
+    let text = match data {
+        [0xfe, 0xff, utf16 @ ..] => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(data).into_owned(),
+    };
+
+    text.trim_end_matches('\0').to_string()
+}
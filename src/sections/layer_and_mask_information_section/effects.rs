@@ -0,0 +1,636 @@
+use crate::blend::{apply_opacity, blend_rgba};
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+use crate::prelude::*;
+use crate::sections::image_resources_section::{
+    DescriptorField, DescriptorStructure, ImageResourcesDescriptorError,
+};
+use crate::sections::layer_and_mask_information_section::fill::read_color;
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::sections::layer_and_mask_information_section::vector_stroke::StrokeAlignment;
+use crate::sections::PsdCursor;
+
+/// Key of `Object-based effects layer info (Photoshop 6.0)`, "lfx2"
+pub(super) const KEY_OBJECT_EFFECTS: &[u8; 4] = b"lfx2";
+
+/// The keys of the effects that we read, including the lists that Photoshop CC uses for layers
+/// with more than one of them
+const READ_EFFECTS: [&str; 6] = [
+    "DrSh",
+    "dropShadowMulti",
+    "FrFX",
+    "frameFXMulti",
+    "SoFi",
+    "solidFillMulti",
+];
+
+/// The largest size of a stroke and of a drop shadow that Photoshop allows, in pixels. Larger
+/// sizes in a file are clamped to it, since rendering takes time in proportion to the square
+/// of the size.
+const MAX_EFFECT_SIZE: f64 = 250.;
+
+/// The farthest that Photoshop allows a drop shadow to be from its layer, in pixels
+const MAX_SHADOW_DISTANCE: f64 = 30_000.;
+
+/// The angle of the light that casts shadows, in degrees, when a PSD doesn't store one
+pub(crate) const DEFAULT_GLOBAL_LIGHT_ANGLE: f64 = 120.;
+
+/// The layer style of a layer, from Photoshop's Layer Style dialog.
+///
+/// Only the drop shadow, stroke and color overlay are read, see
+/// [`LayerEffects::ignored_effects`] for the others. Effects that are turned off are left out.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Object-based effects layer info (Photoshop 6.0)
+///
+/// | Length   | Description                                            |
+/// |----------|--------------------------------------------------------|
+/// | 4        | Object effect version: 0                               |
+/// | 4        | Descriptor version ( = 16 for Photoshop 6.0)           |
+/// | Variable | Descriptor (see See Descriptor structure)              |
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerEffects {
+    pub(crate) drop_shadow: Option<DropShadow>,
+    pub(crate) stroke: Option<StrokeEffect>,
+    pub(crate) color_overlay: Option<ColorOverlay>,
+    pub(crate) ignored_effects: Vec<String>,
+}
+
+/// A shadow that is cast behind the layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropShadow {
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+    pub(crate) angle: f64,
+    pub(crate) use_global_light: bool,
+    pub(crate) distance: f64,
+    pub(crate) spread: f64,
+    pub(crate) size: f64,
+    pub(crate) knocked_out: bool,
+}
+
+/// A solid color outline around the edges of the layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeEffect {
+    pub(crate) alignment: StrokeAlignment,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+    pub(crate) size: f64,
+}
+
+/// A solid color that covers the layer's pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorOverlay {
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+}
+
+impl LayerEffects {
+    /// Read the `lfx2` additional layer information block.
+    ///
+    /// Returns `None` if the effects are turned off or none of them are turned on.
+    pub(super) fn from_bytes(
+        bytes: &[u8],
+    ) -> Result<Option<LayerEffects>, ImageResourcesDescriptorError> {
+        let mut cursor = PsdCursor::new(bytes);
+
+        // Object effect version ( = 0) and descriptor version ( = 16)
+        let _version = cursor.read_u32()?;
+        let _descriptor_version = cursor.read_u32()?;
+        let descriptor = DescriptorStructure::read_descriptor_structure(&mut cursor)?;
+
+        if !descriptor.boolean("masterFXSwitch").unwrap_or(true) {
+            return Ok(None);
+        }
+
+        let stroke = enabled_effect(&descriptor, "FrFX", "frameFXMulti");
+
+        let mut ignored_effects: Vec<String> = descriptor
+            .fields
+            .iter()
+            .filter(|(key, _)| !READ_EFFECTS.contains(&key.as_str()))
+            .filter(|(_, effect)| is_enabled(effect))
+            .map(|(key, _)| key.clone())
+            .collect();
+        // Strokes that are painted with a gradient or a pattern aren't read
+        let stroke_effect = stroke.and_then(StrokeEffect::from_descriptor);
+        if stroke.is_some() && stroke_effect.is_none() {
+            ignored_effects.push("FrFX".to_string());
+        }
+        ignored_effects.sort();
+
+        let effects = LayerEffects {
+            drop_shadow: enabled_effect(&descriptor, "DrSh", "dropShadowMulti")
+                .map(DropShadow::from_descriptor),
+            stroke: stroke_effect,
+            color_overlay: enabled_effect(&descriptor, "SoFi", "solidFillMulti")
+                .map(ColorOverlay::from_descriptor),
+            ignored_effects,
+        };
+
+        if effects.drop_shadow.is_none()
+            && effects.stroke.is_none()
+            && effects.color_overlay.is_none()
+            && effects.ignored_effects.is_empty()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(effects))
+    }
+
+    /// The shadow behind the layer, if it has one
+    pub fn drop_shadow(&self) -> Option<&DropShadow> {
+        self.drop_shadow.as_ref()
+    }
+
+    /// The outline around the layer, if it has one
+    pub fn stroke(&self) -> Option<&StrokeEffect> {
+        self.stroke.as_ref()
+    }
+
+    /// The color that covers the layer, if it has one
+    pub fn color_overlay(&self) -> Option<&ColorOverlay> {
+        self.color_overlay.as_ref()
+    }
+
+    /// The keys of the effects that are turned on but aren't read or rendered, such as `OrGl`
+    /// for an outer glow or `ebbl` for a bevel
+    pub fn ignored_effects(&self) -> &[String] {
+        &self.ignored_effects
+    }
+
+    /// Point the drop shadow away from the document's global light, if it uses it
+    pub(crate) fn set_global_light_angle(&mut self, angle: f64) {
+        if let Some(drop_shadow) = self.drop_shadow.as_mut() {
+            if drop_shadow.use_global_light {
+                drop_shadow.angle = angle;
+            }
+        }
+    }
+
+    /// Render the effects around the RGBA pixels of a layer, which are the size of the PSD.
+    ///
+    /// The fill opacity fades the layer's pixels and its color overlay, but not its stroke or
+    /// its shadow. The shadow and the stroke are drawn onto the layer with normal blending,
+    /// since they are rendered on their own rather than against the layers below.
+    pub(crate) fn render(
+        &self,
+        mut rgba: Vec<u8>,
+        (width, height): (usize, usize),
+        fill_opacity: u8,
+    ) -> Vec<u8> {
+        let shape = Mask {
+            width,
+            height,
+            alpha: rgba.chunks_exact(4).map(|pixel| pixel[3]).collect(),
+        };
+
+        if let Some(overlay) = &self.color_overlay {
+            let [red, green, blue] = overlay.color;
+            for pixel in rgba.chunks_exact_mut(4) {
+                if pixel[3] == 0 {
+                    continue;
+                }
+                let below = [pixel[0], pixel[1], pixel[2], 255];
+                let blended = blend_rgba(
+                    [red, green, blue, overlay.opacity],
+                    below,
+                    overlay.blend_mode,
+                );
+                pixel[..3].copy_from_slice(&blended[..3]);
+            }
+        }
+
+        if fill_opacity != 255 {
+            for pixel in rgba.chunks_exact_mut(4) {
+                let mut copy = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                apply_opacity(&mut copy, fill_opacity);
+                pixel[3] = copy[3];
+            }
+        }
+
+        if let Some(stroke) = &self.stroke {
+            let size = effect_radius(stroke.size);
+            let coverage = match stroke.alignment {
+                StrokeAlignment::Outside => shape.dilate(size).subtract(&shape),
+                StrokeAlignment::Inside => shape.subtract(&shape.erode(size)),
+                StrokeAlignment::Center => {
+                    let half = size.div_ceil(2);
+                    shape.dilate(size - half).subtract(&shape.erode(half))
+                }
+            };
+
+            let [red, green, blue] = stroke.color;
+            for (pixel, alpha) in rgba.chunks_exact_mut(4).zip(coverage.alpha) {
+                if alpha == 0 {
+                    continue;
+                }
+                let mut color = [red, green, blue, alpha];
+                apply_opacity(&mut color, stroke.opacity);
+                let layer = [pixel[0], pixel[1], pixel[2], pixel[3]];
+
+                // An outside stroke is behind the layer, the others are on top of it
+                let blended = match stroke.alignment {
+                    StrokeAlignment::Outside => blend_rgba(layer, color, BlendMode::Normal),
+                    _ => blend_rgba(color, layer, BlendMode::Normal),
+                };
+                pixel.copy_from_slice(&blended);
+            }
+        }
+
+        if let Some(shadow) = &self.drop_shadow {
+            // The spread is how much of the shadow's size is solid instead of blurred
+            let size = effect_radius(shadow.size);
+            let choke = effect_radius(size as f64 * shadow.spread.clamp(0., 100.) / 100.);
+            let blur = size.saturating_sub(choke);
+
+            let radians = shadow.angle.to_radians();
+            let distance = shadow.distance.clamp(-MAX_SHADOW_DISTANCE, MAX_SHADOW_DISTANCE);
+            let offset_x = (-radians.cos() * distance).round() as isize;
+            let offset_y = (radians.sin() * distance).round() as isize;
+
+            let mut shadow_mask = shape.dilate(choke).blur(blur).offset(offset_x, offset_y);
+            if shadow.knocked_out {
+                shadow_mask = shadow_mask.subtract(&shape);
+            }
+
+            let [red, green, blue] = shadow.color;
+            for (pixel, alpha) in rgba.chunks_exact_mut(4).zip(shadow_mask.alpha) {
+                if alpha == 0 {
+                    continue;
+                }
+                let mut color = [red, green, blue, alpha];
+                apply_opacity(&mut color, shadow.opacity);
+                let layer = [pixel[0], pixel[1], pixel[2], pixel[3]];
+
+                pixel.copy_from_slice(&blend_rgba(layer, color, BlendMode::Normal));
+            }
+        }
+
+        rgba
+    }
+}
+
+impl DropShadow {
+    fn from_descriptor(descriptor: &DescriptorStructure) -> DropShadow {
+        DropShadow {
+            blend_mode: blend_mode(descriptor, BlendMode::Multiply),
+            color: descriptor.descriptor("Clr ").map_or([0; 3], read_color),
+            opacity: opacity(descriptor, 75.),
+            angle: descriptor
+                .number("lagl")
+                .unwrap_or(DEFAULT_GLOBAL_LIGHT_ANGLE),
+            use_global_light: descriptor.boolean("uglg").unwrap_or(true),
+            distance: descriptor.number("Dstn").unwrap_or(5.),
+            spread: descriptor.number("Ckmt").unwrap_or(0.),
+            size: descriptor.number("blur").unwrap_or(5.),
+            knocked_out: descriptor.boolean("layerConceals").unwrap_or(true),
+        }
+    }
+
+    /// How the shadow is blended with the layers below
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color of the shadow, `[R, G, B]`
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the shadow. 0 = transparent ... 255 = opaque
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// The angle of the light that casts the shadow, in degrees counterclockwise from the
+    /// right. The shadow falls on the opposite side of the layer.
+    ///
+    /// If the shadow uses the global light, this is the document's global light angle.
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// Whether the shadow uses the same light as the rest of the document
+    pub fn use_global_light(&self) -> bool {
+        self.use_global_light
+    }
+
+    /// How far the shadow is from the layer, in pixels
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    /// How much of the shadow is solid before it starts to fade, as a percentage of its size
+    pub fn spread(&self) -> f64 {
+        self.spread
+    }
+
+    /// How far the shadow reaches past the edges of the layer, in pixels
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// Whether the shadow is hidden where it is behind the layer, which shows when the layer's
+    /// fill opacity is lowered
+    pub fn knocked_out(&self) -> bool {
+        self.knocked_out
+    }
+}
+
+impl StrokeEffect {
+    /// Only strokes that are painted with a solid color are read
+    fn from_descriptor(descriptor: &DescriptorStructure) -> Option<StrokeEffect> {
+        if !matches!(descriptor.enumerated("PntT"), None | Some(b"SClr")) {
+            return None;
+        }
+
+        let alignment = match descriptor.enumerated("Styl") {
+            Some(b"InsF") => StrokeAlignment::Inside,
+            Some(b"CtrF") => StrokeAlignment::Center,
+            _ => StrokeAlignment::Outside,
+        };
+
+        Some(StrokeEffect {
+            alignment,
+            blend_mode: blend_mode(descriptor, BlendMode::Normal),
+            color: descriptor.descriptor("Clr ").map_or([0; 3], read_color),
+            opacity: opacity(descriptor, 100.),
+            size: descriptor.number("Sz  ").unwrap_or(3.),
+        })
+    }
+
+    /// Where the stroke sits compared to the edges of the layer
+    pub fn alignment(&self) -> StrokeAlignment {
+        self.alignment
+    }
+
+    /// How the stroke is blended with the layers below
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color of the stroke, `[R, G, B]`
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the stroke. 0 = transparent ... 255 = opaque
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// The width of the stroke, in pixels
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+}
+
+impl ColorOverlay {
+    fn from_descriptor(descriptor: &DescriptorStructure) -> ColorOverlay {
+        ColorOverlay {
+            blend_mode: blend_mode(descriptor, BlendMode::Normal),
+            color: descriptor.descriptor("Clr ").map_or([0; 3], read_color),
+            opacity: opacity(descriptor, 100.),
+        }
+    }
+
+    /// How the color is blended with the layer's pixels
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color, `[R, G, B]`
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the color. 0 = transparent ... 255 = opaque
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+}
+
+/// The effect under the key, or the first of the effects under the key that Photoshop CC uses
+/// for layers with more than one of them, if it is turned on
+fn enabled_effect<'a>(
+    descriptor: &'a DescriptorStructure,
+    key: &str,
+    multi_key: &str,
+) -> Option<&'a DescriptorStructure> {
+    let is_enabled = |effect: &&DescriptorStructure| effect.boolean("enab").unwrap_or(true);
+
+    match descriptor.descriptor(key) {
+        Some(effect) => Some(effect).filter(is_enabled),
+        None => descriptor
+            .list(multi_key)?
+            .iter()
+            .filter_map(|effect| match effect {
+                DescriptorField::Descriptor(effect) => Some(effect),
+                _ => None,
+            })
+            .find(is_enabled),
+    }
+}
+
+/// Whether a field of the effects descriptor is an effect, or a list of effects, that is
+/// turned on
+fn is_enabled(field: &DescriptorField) -> bool {
+    match field {
+        DescriptorField::Descriptor(effect) => effect.boolean("enab") == Some(true),
+        DescriptorField::List(effects) => effects.iter().any(is_enabled),
+        _ => false,
+    }
+}
+
+fn blend_mode(descriptor: &DescriptorStructure, default: BlendMode) -> BlendMode {
+    descriptor
+        .enumerated("Md  ")
+        .and_then(BlendMode::from_descriptor_key)
+        .unwrap_or(default)
+}
+
+fn opacity(descriptor: &DescriptorStructure, default: f64) -> u8 {
+    let opacity = descriptor.number("Opct").unwrap_or(default);
+
+    (opacity / 100. * 255.).round().clamp(0., 255.) as u8
+}
+
+/// The coverage of each pixel of the PSD
+struct Mask {
+    width: usize,
+    height: usize,
+    alpha: Vec<u8>,
+}
+
+impl Mask {
+    /// Grow the mask by the radius in every direction, with round corners
+    fn dilate(&self, radius: usize) -> Mask {
+        if radius == 0 {
+            return self.with_alpha(self.alpha.clone());
+        }
+
+        let offsets = disk(radius);
+        let mut alpha = vec![0; self.alpha.len()];
+        for top in 0..self.height {
+            for left in 0..self.width {
+                let source = self.alpha[top * self.width + left];
+                if source == 0 {
+                    continue;
+                }
+
+                for (x, y) in offsets.iter() {
+                    let (x, y) = (left as isize + x, top as isize + y);
+                    if let Some(idx) = self.idx(x, y) {
+                        alpha[idx] = alpha[idx].max(source);
+                    }
+                }
+            }
+        }
+
+        self.with_alpha(alpha)
+    }
+
+    /// Shrink the mask by the radius in every direction. Pixels outside of the PSD are
+    /// transparent.
+    fn erode(&self, radius: usize) -> Mask {
+        if radius == 0 {
+            return self.with_alpha(self.alpha.clone());
+        }
+
+        let offsets = disk(radius);
+        let mut alpha = vec![0; self.alpha.len()];
+        for top in 0..self.height {
+            for left in 0..self.width {
+                let idx = top * self.width + left;
+                if self.alpha[idx] == 0 {
+                    continue;
+                }
+
+                alpha[idx] = offsets
+                    .iter()
+                    .map(|(x, y)| {
+                        self.idx(left as isize + x, top as isize + y)
+                            .map_or(0, |idx| self.alpha[idx])
+                    })
+                    .min()
+                    .unwrap_or(0);
+            }
+        }
+
+        self.with_alpha(alpha)
+    }
+
+    /// Soften the mask with three box blurs that together reach the radius
+    fn blur(&self, radius: usize) -> Mask {
+        let box_radius = radius.div_ceil(3);
+        if box_radius == 0 {
+            return self.with_alpha(self.alpha.clone());
+        }
+
+        let mut alpha: Vec<u32> = self.alpha.iter().map(|alpha| *alpha as u32).collect();
+        for _ in 0..3 {
+            alpha = box_blur(&alpha, (self.width, self.height), box_radius, 1, self.width);
+            alpha = box_blur(&alpha, (self.height, self.width), box_radius, self.width, 1);
+        }
+
+        self.with_alpha(alpha.into_iter().map(|alpha| alpha as u8).collect())
+    }
+
+    /// Move the mask by a number of pixels
+    fn offset(&self, x: isize, y: isize) -> Mask {
+        let mut alpha = vec![0; self.alpha.len()];
+        for top in 0..self.height {
+            for left in 0..self.width {
+                if let Some(idx) = self.idx(left as isize - x, top as isize - y) {
+                    alpha[top * self.width + left] = self.alpha[idx];
+                }
+            }
+        }
+
+        self.with_alpha(alpha)
+    }
+
+    /// Remove the other mask from this one
+    fn subtract(&self, other: &Mask) -> Mask {
+        let alpha = self
+            .alpha
+            .iter()
+            .zip(other.alpha.iter())
+            .map(|(alpha, other)| (*alpha as u16 * (255 - *other as u16) / 255) as u8)
+            .collect();
+
+        self.with_alpha(alpha)
+    }
+
+    fn idx(&self, x: isize, y: isize) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+
+        Some(y as usize * self.width + x as usize)
+    }
+
+    fn with_alpha(&self, alpha: Vec<u8>) -> Mask {
+        Mask {
+            width: self.width,
+            height: self.height,
+            alpha,
+        }
+    }
+}
+
+/// Round the size of an effect to a whole number of pixels, within the sizes that Photoshop
+/// allows
+fn effect_radius(size: f64) -> usize {
+    size.round().clamp(0., MAX_EFFECT_SIZE) as usize
+}
+
+/// The offsets of the pixels within the radius of a pixel
+fn disk(radius: usize) -> Vec<(isize, isize)> {
+    let radius = radius as isize;
+
+    (-radius..=radius)
+        .flat_map(|y| (-radius..=radius).map(move |x| (x, y)))
+        .filter(|(x, y)| x * x + y * y <= radius * radius)
+        .collect()
+}
+
+/// Average each pixel with its neighbors along one axis. `len` is the number of pixels along
+/// the axis, `step` is the distance between them and `stride` is the distance between lines.
+fn box_blur(
+    alpha: &[u32],
+    (len, lines): (usize, usize),
+    radius: usize,
+    step: usize,
+    stride: usize,
+) -> Vec<u32> {
+    let mut blurred = vec![0; alpha.len()];
+    let diameter = (radius * 2 + 1) as u32;
+
+    for line in 0..lines {
+        let start = line * stride;
+        let at = |idx: isize| -> u32 {
+            if idx < 0 || idx as usize >= len {
+                0
+            } else {
+                alpha[start + idx as usize * step]
+            }
+        };
+
+        let mut sum: u32 = (0..=radius as isize).map(at).sum();
+        for idx in 0..len {
+            blurred[start + idx * step] = (sum + diameter / 2) / diameter;
+
+            sum += at(idx as isize + radius as isize + 1);
+            sum -= at(idx as isize - radius as isize);
+        }
+    }
+
+    blurred
+}
@@ -0,0 +1,519 @@
+use crate::color::{cmyk_to_rgb, gray_to_rgb, hsb_to_rgb};
+use crate::geometry::{Point, Rect};
+use crate::prelude::*;
+use crate::sections::image_resources_section::{
+    DescriptorField, DescriptorStructure, ImageResourcesDescriptorError,
+};
+use crate::sections::layer_and_mask_information_section::pattern::Pattern;
+use crate::sections::PsdCursor;
+
+/// Key of `Solid color sheet setting (Photoshop 6.0)`, "SoCo"
+pub(super) const KEY_SOLID_COLOR_FILL: &[u8; 4] = b"SoCo";
+/// Key of `Gradient fill setting (Photoshop 6.0)`, "GdFl"
+pub(super) const KEY_GRADIENT_FILL: &[u8; 4] = b"GdFl";
+/// Key of `Pattern fill setting (Photoshop 6.0)`, "PtFl"
+pub(super) const KEY_PATTERN_FILL: &[u8; 4] = b"PtFl";
+
+/// Gradient stop locations are stored as integers between 0 and 4096
+const GRADIENT_LOCATION_SCALE: f64 = 4096.;
+
+/// The contents of a fill layer.
+///
+/// Fill layers don't store any pixels of their own. Instead they describe how to fill the
+/// canvas, and we render that description whenever the layer's pixels are requested.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Solid color sheet setting, gradient fill setting and pattern fill setting are each stored
+/// in the additional layer information as a 4 byte version ( = 16) followed by a descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillLayer {
+    /// A solid color fill layer (`SoCo`). Stored as `[R, G, B]`.
+    SolidColor([u8; 3]),
+    /// A gradient fill layer (`GdFl`)
+    Gradient(GradientFill),
+    /// A pattern fill layer (`PtFl`)
+    Pattern(PatternFill),
+}
+
+/// The shape of a gradient
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum GradientKind {
+    Linear,
+    Radial,
+    Angle,
+    Reflected,
+    Diamond,
+}
+
+/// A color along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientColorStop {
+    /// Where this stop sits along the gradient, from 0.0 to 1.0
+    pub location: f64,
+    /// Where the midpoint between the previous stop and this stop sits, from 0.0 to 1.0
+    pub midpoint: f64,
+    /// The color of the stop, `[R, G, B]`
+    pub color: [u8; 3],
+}
+
+/// An opacity along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientTransparencyStop {
+    /// Where this stop sits along the gradient, from 0.0 to 1.0
+    pub location: f64,
+    /// Where the midpoint between the previous stop and this stop sits, from 0.0 to 1.0
+    pub midpoint: f64,
+    /// The opacity of the stop. 0 = transparent ... 255 = opaque
+    pub opacity: u8,
+}
+
+/// The settings of a gradient fill layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientFill {
+    pub(crate) kind: GradientKind,
+    pub(crate) angle: f64,
+    pub(crate) scale: f64,
+    pub(crate) reverse: bool,
+    pub(crate) offset: (f64, f64),
+    pub(crate) color_stops: Vec<GradientColorStop>,
+    pub(crate) transparency_stops: Vec<GradientTransparencyStop>,
+}
+
+/// The settings of a pattern fill layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternFill {
+    pub(crate) pattern_name: String,
+    pub(crate) pattern_id: String,
+    pub(crate) scale: f64,
+    pub(crate) phase: (f64, f64),
+    pub(crate) pattern: Option<Pattern>,
+}
+
+impl FillLayer {
+    /// Read the additional layer information block for one of the fill layer keys.
+    pub(super) fn from_bytes(
+        key: &[u8; 4],
+        bytes: &[u8],
+    ) -> Result<Option<FillLayer>, ImageResourcesDescriptorError> {
+        let mut cursor = PsdCursor::new(bytes);
+
+        // Version ( = 16)
+        let _version = cursor.read_u32()?;
+        let descriptor = DescriptorStructure::read_descriptor_structure(&mut cursor)?;
+
+        Ok(FillLayer::from_descriptor(key, &descriptor))
+    }
+
+    /// Read the descriptor of one of the fill layer keys, such as the fill of a shape's stroke.
+    pub(super) fn from_descriptor(
+        key: &[u8; 4],
+        descriptor: &DescriptorStructure,
+    ) -> Option<FillLayer> {
+        match key {
+            KEY_SOLID_COLOR_FILL => descriptor
+                .descriptor("Clr ")
+                .map(|color| FillLayer::SolidColor(read_color(color))),
+            KEY_GRADIENT_FILL => GradientFill::from_descriptor(descriptor).map(FillLayer::Gradient),
+            KEY_PATTERN_FILL => PatternFill::from_descriptor(descriptor).map(FillLayer::Pattern),
+            _ => None,
+        }
+    }
+
+    /// Render the fill into RGBA pixels that cover the entire PSD.
+    pub(crate) fn rgba(&self, psd_width: u32, psd_height: u32) -> Vec<u8> {
+        let mut rgba = vec![0; canvas_rgba_len(psd_width, psd_height)];
+        if let Some(canvas) = Rect::from_origin_and_size(Point::new(0, 0), psd_width, psd_height) {
+            self.rgba_region_into(canvas, (psd_width, psd_height), &mut rgba);
+        }
+
+        rgba
+    }
+
+    /// Render the fill within a region of the PSD into a buffer of
+    /// `region.width() * region.height() * 4` bytes
+    pub(crate) fn rgba_region_into(
+        &self,
+        region: Rect,
+        (psd_width, psd_height): (u32, u32),
+        rgba: &mut [u8],
+    ) {
+        match self {
+            FillLayer::SolidColor([r, g, b]) => {
+                for pixel in rgba.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&[*r, *g, *b, 255]);
+                }
+            }
+            FillLayer::Gradient(gradient) => {
+                gradient.rgba_region_into(region, (psd_width, psd_height), rgba)
+            }
+            FillLayer::Pattern(pattern) => pattern.rgba_region_into(region, rgba),
+        }
+    }
+
+    /// The fill's pixel at (x, y) within the PSD
+    pub(crate) fn pixel_at(&self, x: u32, y: u32, (psd_width, psd_height): (u32, u32)) -> [u8; 4] {
+        let mut pixel = [0; 4];
+        let region = Rect {
+            top: y as i32,
+            left: x as i32,
+            bottom: y as i32,
+            right: x as i32,
+        };
+        self.rgba_region_into(region, (psd_width, psd_height), &mut pixel);
+
+        pixel
+    }
+}
+
+impl GradientFill {
+    fn from_descriptor(descriptor: &DescriptorStructure) -> Option<GradientFill> {
+        let gradient = descriptor.descriptor("Grad")?;
+
+        let kind = match descriptor.enumerated("Type") {
+            Some(b"Rdl ") => GradientKind::Radial,
+            Some(b"Angl") => GradientKind::Angle,
+            Some(b"Rflc") => GradientKind::Reflected,
+            Some(b"Dmnd") => GradientKind::Diamond,
+            _ => GradientKind::Linear,
+        };
+
+        let offset = descriptor
+            .descriptor("Ofst")
+            .map(|point| {
+                (
+                    point.number("Hrzn").unwrap_or(0.),
+                    point.number("Vrtc").unwrap_or(0.),
+                )
+            })
+            .unwrap_or((0., 0.));
+
+        let mut color_stops: Vec<GradientColorStop> = gradient
+            .list("Clrs")
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(as_descriptor)
+            .map(|stop| {
+                let color = match stop.enumerated("Type") {
+                    // Background color stops default to white, foreground color stops to black
+                    Some(b"BckC") => [255, 255, 255],
+                    Some(b"FrgC") => [0, 0, 0],
+                    _ => stop.descriptor("Clr ").map(read_color).unwrap_or([0, 0, 0]),
+                };
+
+                GradientColorStop {
+                    location: stop_location(stop),
+                    midpoint: stop_midpoint(stop),
+                    color,
+                }
+            })
+            .collect();
+        color_stops.sort_by(|a, b| a.location.total_cmp(&b.location));
+
+        let mut transparency_stops: Vec<GradientTransparencyStop> = gradient
+            .list("Trns")
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(as_descriptor)
+            .map(|stop| {
+                let opacity = stop.number("Opct").unwrap_or(100.);
+
+                GradientTransparencyStop {
+                    location: stop_location(stop),
+                    midpoint: stop_midpoint(stop),
+                    opacity: (opacity / 100. * 255.).round().clamp(0., 255.) as u8,
+                }
+            })
+            .collect();
+        transparency_stops.sort_by(|a, b| a.location.total_cmp(&b.location));
+
+        Some(GradientFill {
+            kind,
+            angle: descriptor.number("Angl").unwrap_or(0.),
+            scale: descriptor.number("Scl ").unwrap_or(100.) / 100.,
+            reverse: descriptor.boolean("Rvrs").unwrap_or(false),
+            offset,
+            color_stops,
+            transparency_stops,
+        })
+    }
+
+    /// The shape of the gradient
+    pub fn kind(&self) -> GradientKind {
+        self.kind
+    }
+
+    /// The angle of the gradient in degrees, counter clockwise from the positive x axis
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// The scale of the gradient, where 1.0 is 100%
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// If true, the gradient runs from its last stop to its first stop
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    /// The (horizontal, vertical) offset of the gradient's center, as a percentage of the
+    /// width and height of the PSD
+    pub fn offset(&self) -> (f64, f64) {
+        self.offset
+    }
+
+    /// The color stops of the gradient, sorted by location
+    pub fn color_stops(&self) -> &Vec<GradientColorStop> {
+        &self.color_stops
+    }
+
+    /// The transparency stops of the gradient, sorted by location
+    pub fn transparency_stops(&self) -> &Vec<GradientTransparencyStop> {
+        &self.transparency_stops
+    }
+
+    /// Render the gradient within a region of the PSD.
+    ///
+    /// Photoshop doesn't document exactly how it maps a gradient onto the canvas, so this is an
+    /// approximation. The gradient is centered on the canvas (plus the offset) and, at 100%
+    /// scale, a linear gradient spans the full extent of the canvas along the gradient's angle.
+    fn rgba_region_into(&self, region: Rect, (psd_width, psd_height): (u32, u32), rgba: &mut [u8]) {
+        let (width, height) = (psd_width as f64, psd_height as f64);
+
+        let center_x = width / 2. + self.offset.0 / 100. * width;
+        let center_y = height / 2. + self.offset.1 / 100. * height;
+
+        // Pixel rows go down while Photoshop's angles go counter clockwise, so flip y.
+        let radians = self.angle.to_radians();
+        let (dir_x, dir_y) = (radians.cos(), -radians.sin());
+
+        let half_length = ((width * dir_x).abs() + (height * dir_y).abs()) / 2. * self.scale;
+        let half_length = half_length.max(f64::EPSILON);
+
+        let pixels = (region.top..=region.bottom)
+            .flat_map(|top| (region.left..=region.right).map(move |left| (left, top)));
+        for (pixel, (left, top)) in rgba.chunks_exact_mut(4).zip(pixels) {
+            let x = left as f64 + 0.5 - center_x;
+            let y = top as f64 + 0.5 - center_y;
+
+            let along = x * dir_x + y * dir_y;
+            let across = -x * dir_y + y * dir_x;
+
+            let t = match self.kind {
+                GradientKind::Linear => (along / half_length + 1.) / 2.,
+                GradientKind::Radial => (x * x + y * y).sqrt() / half_length,
+                GradientKind::Angle => {
+                    let angle = (-across).atan2(along);
+                    let turn = angle / (2. * core::f64::consts::PI);
+                    if turn < 0. {
+                        turn + 1.
+                    } else {
+                        turn
+                    }
+                }
+                GradientKind::Reflected => along.abs() / half_length,
+                GradientKind::Diamond => (along.abs() + across.abs()) / half_length,
+            };
+
+            let t = t.clamp(0., 1.);
+            let t = if self.reverse { 1. - t } else { t };
+
+            let [r, g, b] = self.color_at(t);
+            pixel.copy_from_slice(&[r, g, b, self.opacity_at(t)]);
+        }
+    }
+
+    /// The color of the gradient at `t` (0.0 - 1.0)
+    fn color_at(&self, t: f64) -> [u8; 3] {
+        let value = interpolate_stops(&self.color_stops, t, |stop| {
+            let [r, g, b] = stop.color;
+            (stop.location, stop.midpoint, [r as f64, g as f64, b as f64])
+        });
+
+        match value {
+            Some([r, g, b]) => [r.round() as u8, g.round() as u8, b.round() as u8],
+            None => [0, 0, 0],
+        }
+    }
+
+    /// The opacity of the gradient at `t` (0.0 - 1.0)
+    fn opacity_at(&self, t: f64) -> u8 {
+        let value = interpolate_stops(&self.transparency_stops, t, |stop| {
+            (stop.location, stop.midpoint, [stop.opacity as f64])
+        });
+
+        match value {
+            Some([opacity]) => opacity.round() as u8,
+            None => 255,
+        }
+    }
+}
+
+impl PatternFill {
+    fn from_descriptor(descriptor: &DescriptorStructure) -> Option<PatternFill> {
+        let pattern = descriptor.descriptor("Ptrn")?;
+
+        let phase = descriptor
+            .descriptor("phase")
+            .map(|point| {
+                (
+                    point.number("Hrzn").unwrap_or(0.),
+                    point.number("Vrtc").unwrap_or(0.),
+                )
+            })
+            .unwrap_or((0., 0.));
+
+        Some(PatternFill {
+            pattern_name: pattern.string("Nm  ").unwrap_or("").to_string(),
+            pattern_id: pattern.string("Idnt").unwrap_or("").to_string(),
+            scale: descriptor.number("Scl ").unwrap_or(100.) / 100.,
+            phase,
+            pattern: None,
+        })
+    }
+
+    /// The name of the pattern that this layer is filled with
+    pub fn pattern_name(&self) -> &str {
+        &self.pattern_name
+    }
+
+    /// The unique ID of the pattern that this layer is filled with
+    pub fn pattern_id(&self) -> &str {
+        &self.pattern_id
+    }
+
+    /// The scale of the pattern, where 1.0 is 100%
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// The (horizontal, vertical) offset of the pattern in pixels
+    pub fn phase(&self) -> (f64, f64) {
+        self.phase
+    }
+
+    /// The pattern that this layer is filled with, if the PSD contains a pattern with a
+    /// matching ID.
+    pub fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    /// Tile the pattern across a region of the PSD. If we couldn't find the pattern there is
+    /// nothing to draw.
+    fn rgba_region_into(&self, region: Rect, rgba: &mut [u8]) {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern,
+            None => {
+                rgba.fill(0);
+                return;
+            }
+        };
+
+        let scale = if self.scale > 0. { self.scale } else { 1. };
+
+        let pixels = (region.top..=region.bottom)
+            .flat_map(|top| (region.left..=region.right).map(move |left| (left, top)));
+        for (pixel, (x, y)) in rgba.chunks_exact_mut(4).zip(pixels) {
+            let pattern_x = ((x as f64 - self.phase.0) / scale).floor() as i64;
+            let pattern_y = ((y as f64 - self.phase.1) / scale).floor() as i64;
+            pixel.copy_from_slice(&pattern.tiled_pixel(pattern_x, pattern_y));
+        }
+    }
+}
+
+/// The length of RGBA pixels that cover the entire PSD. When the PSD was parsed, `check_canvas`
+/// made sure that this fits in a u32, so it can't overflow.
+fn canvas_rgba_len(psd_width: u32, psd_height: u32) -> usize {
+    psd_width as usize * psd_height as usize * 4
+}
+
+fn as_descriptor(field: &DescriptorField) -> Option<&DescriptorStructure> {
+    match field {
+        DescriptorField::Descriptor(descriptor) => Some(descriptor),
+        _ => None,
+    }
+}
+
+fn stop_location(stop: &DescriptorStructure) -> f64 {
+    stop.number("Lctn").unwrap_or(0.) / GRADIENT_LOCATION_SCALE
+}
+
+fn stop_midpoint(stop: &DescriptorStructure) -> f64 {
+    stop.number("Mdpn").unwrap_or(50.) / 100.
+}
+
+/// Given stops sorted by location, find the value at `t`. The `stop` function gives the
+/// (location, midpoint, value) of a stop.
+///
+/// The midpoint of a stop describes where, between the previous stop and this stop, the
+/// value is halfway between the two.
+fn interpolate_stops<S, const N: usize>(
+    stops: &[S],
+    t: f64,
+    stop: impl Fn(&S) -> (f64, f64, [f64; N]),
+) -> Option<[f64; N]> {
+    let first = stop(stops.first()?);
+    let last = stop(stops.last()?);
+
+    if t <= first.0 {
+        return Some(first.2);
+    }
+    if t >= last.0 {
+        return Some(last.2);
+    }
+
+    // The first stop at or after `t`, which can't be the first stop
+    let end_idx = stops.partition_point(|s| stop(s).0 < t);
+    let (start, end) = (stop(&stops[end_idx - 1]), stop(&stops[end_idx]));
+
+    let span = end.0 - start.0;
+    if span <= 0. {
+        return Some(end.2);
+    }
+
+    let progress = (t - start.0) / span;
+    let midpoint = end.1.clamp(0.01, 0.99);
+    let progress = if progress < midpoint {
+        0.5 * progress / midpoint
+    } else {
+        0.5 + 0.5 * (progress - midpoint) / (1. - midpoint)
+    };
+
+    let mut value = start.2;
+    for (value, end) in value.iter_mut().zip(end.2.iter()) {
+        *value += (end - *value) * progress;
+    }
+
+    Some(value)
+}
+
+/// Read a color descriptor (RGBC, HSBC, CMYC or Grsc) into `[R, G, B]`
+pub(crate) fn read_color(color: &DescriptorStructure) -> [u8; 3] {
+    let to_u8 = |value: f64| value.round().clamp(0., 255.) as u8;
+    // CMYK and gray are stored as percentages of ink, while the crate's colors are inverted
+    // bytes where 255 means no ink
+    let inverted_ink = |key: &str| to_u8(255. * (1. - color.number(key).unwrap_or(0.) / 100.));
+
+    match color.class_id.as_slice() {
+        b"HSBC" => hsb_to_rgb(
+            color.number("H   ").unwrap_or(0.) as f32,
+            (color.number("Strt").unwrap_or(0.) / 100.) as f32,
+            (color.number("Brgh").unwrap_or(0.) / 100.) as f32,
+        ),
+        b"CMYC" => cmyk_to_rgb([
+            inverted_ink("Cyn "),
+            inverted_ink("Mgnt"),
+            inverted_ink("Ylw "),
+            inverted_ink("Blck"),
+        ]),
+        b"Grsc" => gray_to_rgb(inverted_ink("Gry ")),
+        _ => [
+            to_u8(color.number("Rd  ").unwrap_or(0.)),
+            to_u8(color.number("Grn ").unwrap_or(0.)),
+            to_u8(color.number("Bl  ").unwrap_or(0.)),
+        ],
+    }
+}
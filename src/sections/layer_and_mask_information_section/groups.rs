@@ -1,6 +1,6 @@
+use crate::prelude::*;
 use crate::PsdGroup;
-use std::collections::HashMap;
-use std::ops::Deref;
+use core::ops::Deref;
 
 #[derive(Debug)]
 pub(crate) struct Groups {
@@ -27,6 +27,21 @@ impl Groups {
     pub fn group_ids_in_order(&self) -> &Vec<u32> {
         &self.group_ids_in_order
     }
+
+    /// The groups in the same order as their IDs in `group_ids_in_order`.
+    pub(crate) fn in_order(&self) -> impl DoubleEndedIterator<Item = &PsdGroup> + '_ {
+        self.group_ids_in_order
+            .iter()
+            .filter_map(move |id| self.groups.get(id))
+    }
+
+    pub(crate) fn get_mut(&mut self, id: u32) -> Option<&mut PsdGroup> {
+        self.groups.get_mut(&id)
+    }
+
+    pub(crate) fn set_group_ids_in_order(&mut self, group_ids_in_order: Vec<u32>) {
+        self.group_ids_in_order = group_ids_in_order;
+    }
 }
 
 impl Deref for Groups {
@@ -1,13 +1,43 @@
-use std::collections::HashMap;
-use std::ops::{Deref, Range};
+use core::fmt;
+use core::hash::Hasher;
+use core::ops::{Deref, Range};
+use core::str::FromStr;
+
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
 
 use thiserror::Error;
 
+use crate::blend;
+use crate::generator::AssetSpec;
+use crate::geometry::{crop_rgba, LayerPlacement, Point, Rect};
+use crate::hash::XxHash64;
+use crate::options::{channel_byte_count, PsdLimitError};
+use crate::pixel_format::PixelFormat;
+use crate::prelude::*;
+use crate::psd_channel::rle_decompress;
+use crate::psd_channel::rle_decompress_channel;
+use crate::psd_channel::rle_decompress_rows;
 use crate::psd_channel::IntoRgba;
 use crate::psd_channel::PsdChannelCompression;
 use crate::psd_channel::PsdChannelError;
 use crate::psd_channel::PsdChannelKind;
+use crate::sections::file_header_section::PsdDepth;
 use crate::sections::image_data_section::ChannelBytes;
+use crate::sections::image_resources_section::ImageResourcesDescriptorError;
+use crate::sections::layer_and_mask_information_section::animation::{
+    LayerFrameState, TimelineTrack,
+};
+use crate::sections::layer_and_mask_information_section::effects::LayerEffects;
+use crate::sections::layer_and_mask_information_section::fill::FillLayer;
+use crate::sections::layer_and_mask_information_section::mask::LayerMask;
+use crate::sections::layer_and_mask_information_section::metadata::LayerMetadata;
+use crate::sections::layer_and_mask_information_section::text::TextLayer;
+use crate::sections::layer_and_mask_information_section::vector_mask::VectorMask;
+use crate::sections::layer_and_mask_information_section::vector_stroke::VectorStroke;
+use crate::sections::PsdCursorError;
 
 /// Information about a layer in a PSD file.
 ///
@@ -18,14 +48,8 @@ use crate::sections::image_data_section::ChannelBytes;
 pub struct LayerProperties {
     /// The name of this layer
     pub(super) name: String,
-    /// The position of the top of the layer
-    pub(crate) layer_top: i32,
-    /// The position of the left of the layer
-    pub(crate) layer_left: i32,
-    /// The position of the bottom of the layer
-    pub(crate) layer_bottom: i32,
-    /// The position of the right of the layer
-    pub(crate) layer_right: i32,
+    /// The rectangle that the layer covers, even if it is empty
+    pub(crate) rect: Rect,
     /// If true, the layer is marked as visible
     pub(crate) visible: bool,
     /// The opacity of the layer
@@ -40,6 +64,15 @@ pub struct LayerProperties {
     pub(crate) blend_mode: BlendMode,
     /// If layer is nested, contains parent group ID, otherwise `None`
     pub(crate) group_id: Option<u32>,
+    /// If true, the layer's bounding box has no area
+    pub(crate) empty: bool,
+    /// How much of the layers below the layer's pixels show through
+    pub(crate) knockout: Knockout,
+    /// If true, the layers clipped to this one are blended with it before they're blended with
+    /// the layers below
+    pub(crate) blend_clipped_elements: bool,
+    /// If true, the layer's effects are blended with its pixels before its blend mode is used
+    pub(crate) blend_interior_elements: bool,
 }
 
 impl LayerProperties {
@@ -52,10 +85,12 @@ impl LayerProperties {
     ) -> Self {
         LayerProperties {
             name,
-            layer_top: layer_record.top,
-            layer_left: layer_record.left,
-            layer_bottom: layer_record.bottom,
-            layer_right: layer_record.right,
+            rect: Rect {
+                top: layer_record.top,
+                left: layer_record.left,
+                bottom: layer_record.bottom,
+                right: layer_record.right,
+            },
             opacity: layer_record.opacity,
             clipping_mask: layer_record.clipping_base,
             visible: layer_record.visible,
@@ -63,6 +98,10 @@ impl LayerProperties {
             psd_width,
             psd_height,
             group_id,
+            empty: layer_record.empty,
+            knockout: layer_record.knockout,
+            blend_clipped_elements: layer_record.blend_clipped_elements,
+            blend_interior_elements: layer_record.blend_interior_elements,
         }
     }
 
@@ -71,16 +110,41 @@ impl LayerProperties {
         &self.name
     }
 
+    /// The files that Adobe Generator exports the layer as, from the export directives in its
+    /// name such as `icon.png, 200% icon@2x.png`. See [`crate::generator`].
+    pub fn generator_assets(&self) -> Vec<AssetSpec> {
+        crate::generator::parse_asset_specs(&self.name)
+    }
+
+    /// Whether the name matches a glob pattern, where `*` matches any number of characters and
+    /// `?` matches exactly one character.
+    ///
+    /// For example, `icon_*` matches "icon_home" and "icon_" but not "big_icon_home".
+    pub fn name_matches(&self, pattern: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = self.name.chars().collect();
+
+        glob_matches(&pattern, &name)
+    }
+
     /// The width of the layer
     pub fn width(&self) -> u16 {
+        if self.empty {
+            return 0;
+        }
+
         // If left is at 0 and right is at 4, the width is 5
-        (self.layer_right - self.layer_left) as u16 + 1
+        (self.rect.right - self.rect.left) as u16 + 1
     }
 
     /// The height of the layer
     pub fn height(&self) -> u16 {
+        if self.empty {
+            return 0;
+        }
+
         // If top is at 0 and bottom is at 3, the height is 4
-        (self.layer_bottom - self.layer_top) as u16 + 1
+        (self.rect.bottom - self.rect.top) as u16 + 1
     }
 
     /// If true, the layer is marked as visible
@@ -88,24 +152,41 @@ impl LayerProperties {
         self.visible
     }
 
-    /// The position of the top of the layer
+    /// The position of the top row of the layer.
+    ///
+    /// Layer positions are in the PSD's pixel coordinates, where (0, 0) is the top left pixel
+    /// of the canvas. Layers can extend past the canvas, so positions can be negative or larger
+    /// than the PSD's width and height.
     pub fn layer_top(&self) -> i32 {
-        self.layer_top
+        self.rect.top
     }
 
-    /// The position of the left of the layer
+    /// The position of the left column of the layer. Can be negative, see
+    /// [`LayerProperties::layer_top`].
     pub fn layer_left(&self) -> i32 {
-        self.layer_left
+        self.rect.left
     }
 
-    /// The position of the bottom of the layer
+    /// The position of the bottom row of the layer (inclusive). Can be past the bottom of the
+    /// PSD, see [`LayerProperties::layer_top`].
     pub fn layer_bottom(&self) -> i32 {
-        self.layer_bottom
+        self.rect.bottom
     }
 
-    /// The position of the right of the layer
+    /// The position of the right column of the layer (inclusive). Can be past the right of the
+    /// PSD, see [`LayerProperties::layer_top`].
     pub fn layer_right(&self) -> i32 {
-        self.layer_right
+        self.rect.right
+    }
+
+    /// The rectangle that the layer's pixels cover, or `None` if the layer is empty. Can extend
+    /// past the PSD, see [`LayerProperties::layer_top`].
+    pub fn bounds(&self) -> Option<Rect> {
+        if self.empty {
+            return None;
+        }
+
+        Some(self.rect)
     }
 
     /// The opacity of the layer
@@ -127,6 +208,25 @@ impl LayerProperties {
     pub fn parent_id(&self) -> Option<u32> {
         self.group_id
     }
+
+    /// Whether the layer's pixels knock out the layers below them, from the layer's advanced
+    /// blending options
+    pub fn knockout(&self) -> Knockout {
+        self.knockout
+    }
+
+    /// Whether the layers that are clipped to this one are blended with it as a group, from the
+    /// layer's advanced blending options. Defaults to true.
+    pub fn blend_clipped_elements(&self) -> bool {
+        self.blend_clipped_elements
+    }
+
+    /// Whether the layer's interior effects, such as inner glow and satin, are blended with its
+    /// pixels before the layer's blend mode is used, from the layer's advanced blending options.
+    /// Defaults to false.
+    pub fn blend_interior_elements(&self) -> bool {
+        self.blend_interior_elements
+    }
 }
 
 /// PsdGroup represents a group of layers
@@ -138,6 +238,41 @@ pub struct PsdGroup {
     pub(in crate) contained_layers: Range<usize>,
     /// Common layer properties
     pub(in crate) layer_properties: LayerProperties,
+    /// Whether the group was expanded in the layers panel
+    pub(in crate) expanded: bool,
+    /// The union of the bounds of the group's layers
+    pub(in crate) bounds: Option<Rect>,
+}
+
+/// Match the name against the pattern, returning to the most recent `*` and letting it match
+/// one more character whenever the rest of the pattern doesn't match.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // The position of the most recent `*` in the pattern and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
 impl PsdGroup {
@@ -158,6 +293,8 @@ impl PsdGroup {
             id,
             contained_layers,
             layer_properties,
+            expanded: false,
+            bounds: None,
         }
     }
 
@@ -165,6 +302,28 @@ impl PsdGroup {
     pub fn id(&self) -> u32 {
         self.id
     }
+
+    /// Whether the group was expanded (open) or collapsed (closed) in the Photoshop layers panel
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    /// The rectangle that the group's layers cover, including the layers of the groups inside of
+    /// it, or `None` if none of its layers cover any pixels.
+    ///
+    /// The group's own record doesn't cover any pixels, so unlike [`LayerProperties::bounds`]
+    /// this is the union of the bounds of its layers. Fill layers cover the entire PSD.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Update the group's bounds from the layers that it contains
+    pub(crate) fn update_bounds(&mut self, contained_layers: &[PsdLayer]) {
+        self.bounds = contained_layers
+            .iter()
+            .filter_map(|layer| layer.pixel_bounds())
+            .reduce(|bounds, layer_bounds| bounds.union(&layer_bounds));
+    }
 }
 
 impl Deref for PsdGroup {
@@ -189,24 +348,75 @@ pub struct PsdLayer {
     ///
     /// Storing the channels separately allows for this flexability.
     pub(super) channels: LayerChannels,
+    /// The kind of each channel and the length of its data in the PSD, in the order that the
+    /// channels are stored in
+    pub(super) channel_data_lengths: Vec<(PsdChannelKind, u32)>,
+    /// Where the layer's record is within the layer and mask information section
+    pub(super) record_range: Range<usize>,
+    /// Where the layer's channel data starts within the layer and mask information section
+    pub(super) channel_data_offset: usize,
+    /// If this is a fill layer, what it is filled with
+    pub(super) fill: Option<FillLayer>,
+    /// If this is a shape layer, its stroke
+    pub(super) vector_stroke: Option<VectorStroke>,
+    /// If this is a shape layer, what its shape is filled with
+    pub(super) vector_fill: Option<FillLayer>,
+    /// The layer's vector mask, such as the outline of a shape layer
+    pub(super) vector_mask: Option<VectorMask>,
+    /// If this is a type layer, its text and where it is placed
+    pub(super) text: Option<TextLayer>,
+    /// The layer style, if any of its effects are turned on
+    pub(super) effects: Option<LayerEffects>,
+    /// The user supplied layer mask
+    pub(super) mask: Option<LayerMask>,
+    /// The unique ID of the layer within the PSD
+    pub(super) layer_id: Option<u32>,
+    /// The opacity of the layer's pixels, not including its layer effects
+    pub(super) fill_opacity: u8,
+    /// The items of the layer's metadata setting
+    pub(super) metadata: Vec<LayerMetadata>,
     /// Common layer properties
     pub(in crate) layer_properties: LayerProperties,
+    /// The bounds of the non-transparent pixels, calculated the first time that they're needed
+    pub(super) opaque_bounds: OnceLock<Option<LayerBounds>>,
+    /// Whether [`PsdLayer::rgba`] keeps the pixels in `rgba_cache`, see
+    /// [`crate::PsdOptions::cache_layer_rgba`]
+    pub(super) cache_rgba: bool,
+    /// The pixels from [`PsdLayer::rgba`], when they are cached
+    pub(super) rgba_cache: OnceLock<Vec<u8>>,
+    /// Whether fully transparent pixels are returned as `[0, 0, 0, 0]`, see
+    /// [`crate::PsdOptions::clear_transparent_pixels`]
+    pub(super) clear_transparent: bool,
+    /// Whether [`PsdLayer::rgba`] multiplies the layer's vector mask into its pixels, see
+    /// [`crate::PsdOptions::rasterize_vector_masks`]
+    pub(super) apply_vector_mask: bool,
 }
 
+/// A rectangle of pixels within the PSD.
+///
+/// Like the layer's `layer_right` and `layer_bottom`, `right` and `bottom` are inclusive.
+pub type LayerBounds = Rect;
+
 /// An error when working with a PsdLayer
-#[derive(Debug, PartialEq, Error)]
+#[derive(Debug, Clone, PartialEq, Error)]
 pub enum PsdLayerError {
     #[error(
         r#"Could not combine Red, Green, Blue and Alpha.
         This layer is missing channel: {channel:#?}"#
     )]
     MissingChannels { channel: PsdChannelKind },
-    #[error("{channel_id} is an invalid channel id, must be 0, 1, 2, -1, -2, or -3.")]
+    #[error("{channel_id} is an invalid channel id, must be -3 or more.")]
     InvalidChannel { channel_id: i16 },
     #[error(r#"Unknown blending mode: {mode:#?}"#)]
     UnknownBlendingMode { mode: [u8; 4] },
     #[error("{compression} is an invalid layer channel compression. Must be 0, 1, 2 or 3")]
     InvalidCompression { compression: u16 },
+    #[error("Invalid additional layer information descriptor: {0}")]
+    InvalidDescriptor(ImageResourcesDescriptorError),
+    #[error("Unexpected end of data: {0}")]
+    UnexpectedEnd(#[from] PsdCursorError),
+    #[error("{0}")]
+    LimitError(#[from] PsdLimitError),
 }
 
 impl PsdLayer {
@@ -227,6 +437,24 @@ impl PsdLayer {
                 group_id,
             ),
             channels,
+            channel_data_lengths: layer_record.channel_data_lengths.clone(),
+            record_range: layer_record.record_range.clone(),
+            channel_data_offset: layer_record.channel_data_offset,
+            fill: layer_record.fill.clone(),
+            vector_stroke: layer_record.vector_stroke.clone(),
+            vector_fill: layer_record.vector_fill.clone(),
+            text: layer_record.text.clone(),
+            vector_mask: layer_record.vector_mask.clone(),
+            effects: layer_record.effects.clone(),
+            mask: layer_record.mask.clone(),
+            layer_id: layer_record.layer_id,
+            fill_opacity: layer_record.fill_opacity,
+            metadata: layer_record.metadata.clone(),
+            opaque_bounds: OnceLock::new(),
+            cache_rgba: false,
+            rgba_cache: OnceLock::new(),
+            clear_transparent: false,
+            apply_vector_mask: false,
         }
     }
 
@@ -238,17 +466,876 @@ impl PsdLayer {
         match self.channels.get(&channel) {
             Some(channel) => match channel {
                 ChannelBytes::RawData(_) => Ok(PsdChannelCompression::RawData),
-                ChannelBytes::RleCompressed(_) => Ok(PsdChannelCompression::RleCompressed),
+                ChannelBytes::RleCompressed(..) => Ok(PsdChannelCompression::RleCompressed),
             },
             None => Err(PsdChannelError::ChannelNotFound { channel }),
         }
     }
 
+    /// The kind, compression and length in bytes of each of the layer's channels, in the
+    /// order that they're stored in the PSD.
+    ///
+    /// The length is of the channel's data as it is stored in the PSD (not counting the two
+    /// bytes that store its compression), so the channels can be audited without decoding
+    /// them.
+    pub fn channels(
+        &self,
+    ) -> impl Iterator<Item = (PsdChannelKind, PsdChannelCompression, usize)> + '_ {
+        self.channel_data_lengths
+            .iter()
+            .filter_map(move |(kind, len)| {
+                let compression = self.compression(*kind).ok()?;
+                Some((*kind, compression, *len as usize))
+            })
+    }
+
+    /// The bytes of the layer's record within the PSD file, given where the layer and mask
+    /// information section starts.
+    pub(crate) fn record_range(&self, section_start: usize) -> Range<usize> {
+        section_start + self.record_range.start..section_start + self.record_range.end
+    }
+
+    /// The bytes of each of the layer's channels within the PSD file, including the two bytes
+    /// that store its compression, given where the layer and mask information section starts.
+    pub(crate) fn channel_data_ranges(
+        &self,
+        section_start: usize,
+    ) -> Vec<(PsdChannelKind, Range<usize>)> {
+        let mut start = section_start + self.channel_data_offset;
+
+        self.channel_data_lengths
+            .iter()
+            .map(|(kind, len)| {
+                let range = start..start + 2 + *len as usize;
+                start = range.end;
+                (*kind, range)
+            })
+            .collect()
+    }
+
     /// Create a vector that interleaves the red, green, blue and alpha channels in this PSD
     ///
     /// vec![R, G, B, A, R, G, B, A, ...]
+    ///
+    /// Fill layers don't have pixels of their own, so for them we render the fill across the
+    /// entire PSD.
+    ///
+    /// Empty layers are fully transparent.
+    ///
+    /// Pixels of the layer that are outside of the PSD are cropped off, see
+    /// [`PsdLayer::rgba_full_extent`] to get them.
+    ///
+    /// When the PSD was parsed with [`crate::PsdOptions::cache_layer_rgba`], the pixels are
+    /// interleaved the first time that this is called and copied from the cache after that.
     pub fn rgba(&self) -> Vec<u8> {
-        self.generate_rgba()
+        if self.cache_rgba {
+            return self.rgba_cache.get_or_init(|| self.decode_rgba()).clone();
+        }
+
+        self.decode_rgba()
+    }
+
+    /// The same pixels as [`PsdLayer::rgba`], laid out in the format, such as BGRA for a GPU
+    /// texture.
+    pub fn pixels(&self, format: PixelFormat) -> Vec<u8> {
+        let decoded_as_is = !self.cache_rgba
+            && self.fill.is_none()
+            && !self.apply_vector_mask
+            && !self.clear_transparent;
+        if !decoded_as_is || !self.is_within_psd() {
+            return format.layout_rgba(self.rgba());
+        }
+
+        span!(DEBUG, "layer_pixels", name = self.name());
+
+        let pixel_count = self.psd_width as usize * self.psd_height as usize;
+        let mut pixels = vec![0; pixel_count * format.bytes_per_pixel()];
+        if !self.is_empty() {
+            self.generate_pixels_into(format, &mut pixels);
+        }
+
+        pixels
+    }
+
+    /// Like [`PsdLayer::rgba`], but with the layer's [`PsdLayer::effects`] rendered around its
+    /// pixels and its fill opacity multiplied into them.
+    ///
+    /// The drop shadow and the stroke can reach past the layer's bounds, so they are drawn
+    /// with normal blending instead of their own blend modes.
+    pub fn rgba_with_effects(&self) -> Vec<u8> {
+        let rgba = self.rgba();
+
+        match &self.effects {
+            Some(effects) => {
+                let mut rgba = effects.render(
+                    rgba,
+                    (self.psd_width as usize, self.psd_height as usize),
+                    self.fill_opacity,
+                );
+                if self.clear_transparent {
+                    blend::clear_transparent_pixels(&mut rgba);
+                }
+                rgba
+            }
+            None => rgba,
+        }
+    }
+
+    /// Like [`PsdLayer::rgba`], but reuses the buffer's allocation when the pixels are decoded
+    /// from the layer's channels.
+    pub(crate) fn rgba_into_vec(&self, rgba: &mut Vec<u8>) {
+        let decoded_as_is = !self.cache_rgba && self.fill.is_none() && !self.apply_vector_mask;
+        if !decoded_as_is || self.is_empty() || !self.is_within_psd() {
+            *rgba = self.rgba();
+            return;
+        }
+
+        span!(DEBUG, "layer_rgba", name = self.name());
+
+        rgba.clear();
+        rgba.resize(self.psd_width as usize * self.psd_height as usize * 4, 0);
+        self.generate_rgba_into(rgba);
+        if self.clear_transparent {
+            blend::clear_transparent_pixels(rgba);
+        }
+    }
+
+    /// Like [`PsdLayer::rgba`], but only the pixels within a region of the PSD, written into a
+    /// buffer of `region.width() * region.height() * 4` bytes. The region must be within the
+    /// PSD.
+    ///
+    /// Only the rows of the layer's channels that are within the region are decompressed, and
+    /// fills and vector masks are only rendered within it, so that a large layer can be
+    /// flattened one tile at a time. A layer whose RGBA is already cached is cropped instead.
+    pub(crate) fn rgba_region_into(&self, region: Rect, rgba: &mut Vec<u8>) {
+        rgba.clear();
+        rgba.resize(region.width() as usize * region.height() as usize * 4, 0);
+
+        if let Some(cached) = self.rgba_cache.get() {
+            crop_rgba(cached, self.psd_width, region, rgba);
+            return;
+        }
+
+        span!(DEBUG, "layer_rgba_region", name = self.name());
+
+        match &self.fill {
+            Some(fill) => fill.rgba_region_into(region, (self.psd_width, self.psd_height), rgba),
+            None => self.generate_region_into(region, rgba),
+        }
+        if let Some(coverage) = self.applied_vector_mask_region(region) {
+            for (pixel, coverage) in rgba.chunks_exact_mut(4).zip(coverage) {
+                pixel[3] = (pixel[3] as u16 * coverage as u16 / 255) as u8;
+            }
+        }
+        if self.clear_transparent {
+            blend::clear_transparent_pixels(rgba);
+        }
+    }
+
+    /// Interleave the rows of the layer's channels that are within a region of the PSD into a
+    /// transparent buffer of the region's RGBA
+    fn generate_region_into(&self, region: Rect, rgba: &mut [u8]) {
+        let bounds = match self.bounds() {
+            Some(bounds) if !self.is_empty() => bounds,
+            _ => return,
+        };
+        let overlap = match bounds.intersect(&region) {
+            Some(overlap) => overlap,
+            None => return,
+        };
+
+        let rows = (overlap.top - bounds.top) as usize..(overlap.bottom - bounds.top) as usize + 1;
+        let red = match self.channel_rows(PsdChannelKind::Red, rows.clone()) {
+            Some(red) => red,
+            None => return,
+        };
+        // Grayscale layers only have one color channel
+        let green = self.channel_rows(PsdChannelKind::Green, rows.clone());
+        let blue = self.channel_rows(PsdChannelKind::Blue, rows.clone());
+        let alpha = self.channel_rows(PsdChannelKind::TransparencyMask, rows);
+
+        let layer_width = bounds.width() as usize;
+        let region_width = region.width() as usize;
+        for top in overlap.top..=overlap.bottom {
+            let layer_row = (top - overlap.top) as usize * layer_width;
+            let region_row = (top - region.top) as usize * region_width;
+
+            for left in overlap.left..=overlap.right {
+                let idx = layer_row + (left - bounds.left) as usize;
+                let pixel_idx = (region_row + (left - region.left) as usize) * 4;
+
+                rgba[pixel_idx] = red[idx];
+                rgba[pixel_idx + 1] = green.as_ref().unwrap_or(&red)[idx];
+                rgba[pixel_idx + 2] = blue.as_ref().unwrap_or(&red)[idx];
+                // If there is no transparency data then the layer is opaque
+                rgba[pixel_idx + 3] = alpha.as_ref().map_or(255, |alpha| alpha[idx]);
+            }
+        }
+    }
+
+    /// Decompress the rows of one of the layer's color or transparency channels, one byte per
+    /// pixel of the layer's width, or `None` if the layer does not have the channel
+    fn channel_rows(&self, channel: PsdChannelKind, rows: Range<usize>) -> Option<Vec<u8>> {
+        let width = self.rect.width() as usize;
+        let range = rows.start * width..rows.end * width;
+
+        let mut bytes = match self.get_channel(channel)? {
+            ChannelBytes::RawData(bytes) => {
+                let end = range.end.min(bytes.len());
+                bytes[range.start.min(end)..end].to_vec()
+            }
+            ChannelBytes::RleCompressed(bytes, scanline_lens) => {
+                rle_decompress_rows(bytes, scanline_lens, rows, width)
+            }
+        };
+
+        // Guard against channels that are shorter than their rectangle
+        bytes.resize(range.len(), 0);
+
+        Some(bytes)
+    }
+
+    /// The rasterized vector mask, when it is multiplied into the layer's pixels
+    fn applied_vector_mask(&self) -> Option<Vec<u8>> {
+        if !self.apply_vector_mask {
+            return None;
+        }
+
+        self.rasterize_vector_mask()
+    }
+
+    /// Like `applied_vector_mask`, but only within a region of the PSD
+    fn applied_vector_mask_region(&self, region: Rect) -> Option<Vec<u8>> {
+        if !self.apply_vector_mask {
+            return None;
+        }
+
+        self.rasterize_vector_mask_region(region)
+    }
+
+    fn decode_rgba(&self) -> Vec<u8> {
+        span!(DEBUG, "layer_rgba", name = self.name());
+
+        let mut rgba = match &self.fill {
+            Some(fill) => fill.rgba(self.psd_width, self.psd_height),
+            None if self.is_empty() => vec![0; (self.psd_width * self.psd_height * 4) as usize],
+            None if self.is_within_psd() => self.generate_rgba(),
+            None => self.generate_cropped_rgba(),
+        };
+        if let Some(coverage) = self.applied_vector_mask() {
+            for (pixel, coverage) in rgba.chunks_exact_mut(4).zip(coverage) {
+                pixel[3] = (pixel[3] as u16 * coverage as u16 / 255) as u8;
+            }
+        }
+        if self.clear_transparent {
+            blend::clear_transparent_pixels(&mut rgba);
+        }
+
+        rgba
+    }
+
+    /// Like [`PsdLayer::rgba`], but with the layer's opacity and fill opacity multiplied into the
+    /// alpha channel, which is how the layer looks when it is flattened.
+    pub fn rgba_with_opacity(&self) -> Vec<u8> {
+        let mut rgba = self.rgba();
+        if self.opacity == 255 && self.fill_opacity == 255 {
+            return rgba;
+        }
+
+        for pixel in rgba.chunks_exact_mut(4) {
+            let mut copy = [0; 4];
+            copy.copy_from_slice(pixel);
+            self.apply_opacity(&mut copy);
+            pixel.copy_from_slice(&copy);
+        }
+        if self.clear_transparent {
+            blend::clear_transparent_pixels(&mut rgba);
+        }
+
+        rgba
+    }
+
+    /// Multiply the layer's opacity and fill opacity into the pixel's alpha
+    pub(crate) fn apply_opacity(&self, pixel: &mut [u8; 4]) {
+        blend::apply_opacity(pixel, self.opacity);
+        if self.fill_opacity != 255 {
+            blend::apply_opacity(pixel, self.fill_opacity);
+        }
+    }
+
+    /// The RGBA pixels of the entire layer, including any pixels that are outside of the PSD,
+    /// along with the layer's bounds within the PSD.
+    ///
+    /// The pixels are `bounds.width() * bounds.height() * 4` bytes long, with the top left pixel
+    /// of the layer first.
+    ///
+    /// Fill layers cover the entire PSD, so their bounds are the PSD's. Returns `None` for
+    /// empty layers.
+    pub fn rgba_full_extent(&self) -> Option<(Vec<u8>, LayerBounds)> {
+        span!(DEBUG, "layer_rgba_full_extent", name = self.name());
+
+        if self.fill.is_some() {
+            let bounds = LayerBounds {
+                top: 0,
+                left: 0,
+                bottom: self.psd_height as i32 - 1,
+                right: self.psd_width as i32 - 1,
+            };
+            return Some((self.rgba(), bounds));
+        }
+
+        if self.is_empty() {
+            return None;
+        }
+
+        let bounds = self.rect;
+        let pixel_count = bounds.width() as usize * bounds.height() as usize;
+
+        let red = self
+            .decoded_channel(PsdChannelKind::Red)
+            .unwrap_or_else(|| vec![0; pixel_count]);
+        let green = self.decoded_channel(PsdChannelKind::Green);
+        let blue = self.decoded_channel(PsdChannelKind::Blue);
+        let alpha = self.decoded_channel(PsdChannelKind::TransparencyMask);
+
+        let mut rgba = Vec::with_capacity(pixel_count * 4);
+        for idx in 0..pixel_count {
+            rgba.push(red[idx]);
+            // Grayscale layers only have one color channel
+            rgba.push(green.as_ref().unwrap_or(&red)[idx]);
+            rgba.push(blue.as_ref().unwrap_or(&red)[idx]);
+            rgba.push(alpha.as_ref().map(|alpha| alpha[idx]).unwrap_or(255));
+        }
+        if self.clear_transparent {
+            blend::clear_transparent_pixels(&mut rgba);
+        }
+
+        Some((rgba, bounds))
+    }
+
+    /// If true, every pixel of the layer is within the PSD
+    fn is_within_psd(&self) -> bool {
+        self.rect.left >= 0
+            && self.rect.top >= 0
+            && (self.rect.right as i64) < self.psd_width as i64
+            && (self.rect.bottom as i64) < self.psd_height as i64
+    }
+
+    /// Generate the RGBA of a layer that extends past the PSD, cropping it to the PSD
+    fn generate_cropped_rgba(&self) -> Vec<u8> {
+        let mut rgba = vec![0; self.psd_width as usize * self.psd_height as usize * 4];
+
+        let red = self.channel_plane(PsdChannelKind::Red);
+        let red = match red {
+            Some(red) => red,
+            None => return rgba,
+        };
+        let green = self.channel_plane(PsdChannelKind::Green);
+        let blue = self.channel_plane(PsdChannelKind::Blue);
+        let alpha = self.alpha_only();
+
+        for (idx, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+            pixel[0] = red[idx];
+            pixel[1] = green.as_ref().unwrap_or(&red)[idx];
+            pixel[2] = blue.as_ref().unwrap_or(&red)[idx];
+            pixel[3] = alpha[idx];
+        }
+
+        rgba
+    }
+
+    /// The luminance of each of the layer's pixels, one byte per pixel of the PSD.
+    ///
+    /// Luminance is calculated from the red, green and blue channels with the Rec. 601 weights,
+    /// without taking transparency into account (see [`PsdLayer::alpha_only`]). Pixels outside
+    /// of the layer are 0.
+    ///
+    /// This is useful for generating heightmaps or masks from a layer without interleaving it
+    /// into RGBA first.
+    pub fn luminance(&self) -> Vec<u8> {
+        if self.fill.is_some() {
+            return self
+                .rgba()
+                .chunks_exact(4)
+                .map(|pixel| luminance(pixel[0], pixel[1], pixel[2]))
+                .collect();
+        }
+
+        let red = self.channel_plane(PsdChannelKind::Red);
+        let red = match red {
+            Some(red) => red,
+            None => return self.transparent_plane(),
+        };
+
+        // Grayscale layers only have one color channel
+        let green = self.channel_plane(PsdChannelKind::Green);
+        let blue = self.channel_plane(PsdChannelKind::Blue);
+        let green = green.as_ref().unwrap_or(&red);
+        let blue = blue.as_ref().unwrap_or(&red);
+
+        red.iter()
+            .zip(green)
+            .zip(blue)
+            .map(|((r, g), b)| luminance(*r, *g, *b))
+            .collect()
+    }
+
+    /// The alpha of each of the layer's pixels, one byte per pixel of the PSD.
+    ///
+    /// Pixels outside of the layer are 0, and layers without a transparency channel are 255
+    /// within their bounds.
+    pub fn alpha_only(&self) -> Vec<u8> {
+        if self.fill.is_some() {
+            return self.rgba().chunks_exact(4).map(|pixel| pixel[3]).collect();
+        }
+
+        if let Some(alpha) = self.channel_plane(PsdChannelKind::TransparencyMask) {
+            return alpha;
+        }
+
+        if self.is_empty() {
+            return self.transparent_plane();
+        }
+
+        let bounds = self.rect;
+        let opaque = vec![255; bounds.width() as usize * bounds.height() as usize];
+        self.psd_sized_plane(&opaque)
+    }
+
+    /// Decode one of the layer's color or transparency channels into a buffer with one byte
+    /// per pixel of the PSD, or `None` if the layer does not have the channel.
+    fn channel_plane(&self, channel: PsdChannelKind) -> Option<Vec<u8>> {
+        let layer_bytes = self.decoded_channel(channel)?;
+
+        Some(self.psd_sized_plane(&layer_bytes))
+    }
+
+    /// The rectangle that one of the layer's channels covers, or `None` if the layer does not
+    /// have the channel or the channel doesn't have any pixels.
+    ///
+    /// The layer masks have their own rectangles, the other channels cover the layer's bounds.
+    pub fn channel_bounds(&self, channel: PsdChannelKind) -> Option<Rect> {
+        if !self.channels.contains_key(&channel) {
+            return None;
+        }
+
+        match channel {
+            PsdChannelKind::UserSuppliedLayerMask => self.mask.as_ref()?.bounds(),
+            PsdChannelKind::RealUserSuppliedLayerMask => self.mask.as_ref()?.real_bounds(),
+            _ => self.bounds(),
+        }
+    }
+
+    /// Decompress one of the layer's channels, one byte per pixel of its
+    /// [`PsdLayer::channel_bounds`] in scan-line order, or `None` if the layer does not have
+    /// the channel.
+    ///
+    /// This includes the channels that aren't used for the layer's RGBA, such as the masks and
+    /// the black of a CMYK layer (`PsdChannelKind::Spot(3)`).
+    pub fn decoded_channel(&self, channel: PsdChannelKind) -> Option<Vec<u8>> {
+        let bounds = self.channel_bounds(channel)?;
+
+        let mut bytes = match self.get_channel(channel)? {
+            ChannelBytes::RawData(bytes) => bytes.to_vec(),
+            ChannelBytes::RleCompressed(bytes, scanline_lens) => {
+                rle_decompress_channel(bytes, scanline_lens)
+            }
+        };
+
+        // Guard against channels that are shorter or longer than their rectangle
+        bytes.resize(bounds.width() as usize * bounds.height() as usize, 0);
+
+        Some(bytes)
+    }
+
+    /// A hash of the layer's pixels, so that a layer that is unchanged between two revisions of
+    /// a PSD can be found without keeping or comparing the pixels of both.
+    ///
+    /// The hash covers the layer's bounds and every one of its decoded channels, including its
+    /// masks. Fill layers hash the pixels of their fill. The layer's name, visibility,
+    /// opacity and blend mode are not part of the hash.
+    ///
+    /// The hash is [xxHash64](https://github.com/Cyan4973/xxHash), so it is the same on every
+    /// platform and can be stored.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = XxHash64::with_seed(0);
+
+        if self.fill.is_some() {
+            hasher.write(&self.rgba());
+            return hasher.finish();
+        }
+
+        let mut kinds: Vec<PsdChannelKind> = self.channels.keys().copied().collect();
+        kinds.sort_by_key(|kind| kind.id());
+
+        for kind in kinds {
+            let (bounds, bytes) = match (self.channel_bounds(kind), self.decoded_channel(kind)) {
+                (Some(bounds), Some(bytes)) => (bounds, bytes),
+                _ => continue,
+            };
+
+            hasher.write(&kind.id().to_le_bytes());
+            for edge in [bounds.top, bounds.left, bounds.bottom, bounds.right].iter() {
+                hasher.write(&edge.to_le_bytes());
+            }
+            hasher.write(&bytes);
+        }
+
+        hasher.finish()
+    }
+
+    /// Place the bytes of one of the layer's channels at the layer's position within a buffer
+    /// with one byte per pixel of the PSD. Pixels that are outside of the PSD are dropped.
+    fn psd_sized_plane(&self, layer_bytes: &[u8]) -> Vec<u8> {
+        let mut plane = self.transparent_plane();
+
+        let bounds = self.rect;
+        let layer_width = bounds.width() as i64;
+        let (psd_width, psd_height) = (self.psd_width as i64, self.psd_height as i64);
+
+        // The columns of the layer that are within the PSD
+        let first_column = (-(bounds.left as i64)).max(0);
+        let last_column = (psd_width - bounds.left as i64).min(layer_width);
+        if first_column >= last_column {
+            return plane;
+        }
+
+        for (row, row_bytes) in layer_bytes.chunks(layer_width as usize).enumerate() {
+            let top = bounds.top as i64 + row as i64;
+            if top < 0 {
+                continue;
+            }
+            if top >= psd_height {
+                break;
+            }
+
+            let last_column = last_column.min(row_bytes.len() as i64);
+            if first_column >= last_column {
+                continue;
+            }
+
+            let start = (top * psd_width + bounds.left as i64 + first_column) as usize;
+            let end = start + (last_column - first_column) as usize;
+            plane[start..end]
+                .copy_from_slice(&row_bytes[first_column as usize..last_column as usize]);
+        }
+
+        plane
+    }
+
+    /// A buffer of zeroes with one byte per pixel of the PSD
+    fn transparent_plane(&self) -> Vec<u8> {
+        vec![0; self.psd_width as usize * self.psd_height as usize]
+    }
+
+    /// If true, the layer's bounding box is zero sized (or negative), so it has no pixels.
+    ///
+    /// This is common for empty layers, fill layers and some adjustment layers.
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    /// The tightest bounds that contain all of the layer's pixels that are not fully
+    /// transparent, or `None` if every pixel is transparent.
+    ///
+    /// This is useful for trimming the transparent edges off of a layer. The bounds are
+    /// calculated the first time that this is called and then cached.
+    pub fn opaque_bounds(&self) -> Option<LayerBounds> {
+        *self
+            .opaque_bounds
+            .get_or_init(|| self.calculate_opaque_bounds())
+    }
+
+    fn calculate_opaque_bounds(&self) -> Option<LayerBounds> {
+        // Fill layers cover the entire PSD, so we scan their rendered pixels
+        if self.fill.is_some() {
+            let alpha: Vec<u8> = self.rgba().iter().skip(3).step_by(4).copied().collect();
+            return alpha_bounds(&alpha, self.psd_width as usize, 0, 0);
+        }
+
+        if self.is_empty() {
+            return None;
+        }
+
+        let width = self.width() as usize;
+        let (top, left) = (self.rect.top, self.rect.left);
+
+        match self.get_channel(PsdChannelKind::TransparencyMask) {
+            Some(ChannelBytes::RawData(alpha)) => alpha_bounds(alpha, width, top, left),
+            Some(ChannelBytes::RleCompressed(alpha, _)) => {
+                alpha_bounds(&rle_decompress(alpha), width, top, left)
+            }
+            // If there is no transparency data then the layer is opaque
+            None => self.bounds(),
+        }
+    }
+
+    /// The alpha of the pixel at the given position within the PSD after applying the layer's
+    /// mask. Pixels outside of the layer are fully transparent.
+    pub(crate) fn alpha_at(&self, x: u32, y: u32) -> u8 {
+        let alpha = if let Some(fill) = &self.fill {
+            fill.pixel_at(x, y, (self.psd_width, self.psd_height))[3]
+        } else if !self.contains_canvas_point(x, y) {
+            0
+        } else {
+            self.channel_value_at(PsdChannelKind::TransparencyMask, x, y)
+                .unwrap_or(255)
+        };
+
+        let pixel = Rect {
+            top: y as i32,
+            left: x as i32,
+            bottom: y as i32,
+            right: x as i32,
+        };
+        let alpha = match self.applied_vector_mask_region(pixel) {
+            Some(coverage) => (alpha as u16 * coverage[0] as u16 / 255) as u8,
+            None => alpha,
+        };
+
+        let mask = match &self.mask {
+            Some(mask) if !mask.disabled => mask,
+            _ => return alpha,
+        };
+
+        let in_mask = mask
+            .bounds()
+            .map(|bounds| bounds.contains(canvas_point(x, y)))
+            .unwrap_or(false);
+        let mask_value = if in_mask {
+            self.channel_value_at(PsdChannelKind::UserSuppliedLayerMask, x, y)
+                .unwrap_or(mask.default_color)
+        } else {
+            mask.default_color
+        };
+        let mask_value = mask.apply_density(mask_value);
+
+        (alpha as u16 * mask_value as u16 / 255) as u8
+    }
+
+    /// The value of a channel at the given position within the PSD, or `None` if the layer
+    /// does not have the channel.
+    ///
+    /// Positions outside of the channel's rectangle are 0.
+    fn channel_value_at(&self, channel: PsdChannelKind, x: u32, y: u32) -> Option<u8> {
+        let bytes = self.get_channel(channel)?;
+
+        let bounds = match self.channel_bounds(channel) {
+            Some(bounds) if bounds.contains(canvas_point(x, y)) => bounds,
+            _ => return Some(0),
+        };
+
+        let width = bounds.width() as usize;
+        let (row, column) = (
+            (y as i32 - bounds.top) as usize,
+            (x as i32 - bounds.left) as usize,
+        );
+
+        // Only the pixel's row is decompressed
+        let value = match bytes {
+            ChannelBytes::RawData(bytes) => bytes.get(row * width + column).copied(),
+            ChannelBytes::RleCompressed(bytes, scanline_lens) => {
+                rle_decompress_rows(bytes, scanline_lens, row..row + 1, width)
+                    .get(column)
+                    .copied()
+            }
+        };
+
+        Some(value.unwrap_or(0))
+    }
+
+    /// The number of bytes that the layer's channels decompress to, or `None` if it overflows
+    pub(crate) fn decoded_byte_count(&self, depth: PsdDepth) -> Option<u64> {
+        let mut total: u64 = 0;
+
+        for channel in self.channels.keys() {
+            if let Some(bounds) = self.channel_bounds(*channel) {
+                let bytes =
+                    channel_byte_count(bounds.width() as u64, bounds.height() as u64, depth)?;
+                total = total.checked_add(bytes)?;
+            }
+        }
+
+        Some(total)
+    }
+
+    /// The unique ID that Photoshop gave the layer.
+    ///
+    /// Unlike the layer's name or position, the ID stays the same when the layer is renamed or
+    /// moved. PSDs saved by older versions of Photoshop might not store one.
+    pub fn layer_id(&self) -> Option<u32> {
+        self.layer_id
+    }
+
+    /// The items of the layer's metadata (`shmd`), in the order that they're stored in
+    pub fn metadata(&self) -> &[LayerMetadata] {
+        &self.metadata
+    }
+
+    /// Get the layer's metadata item with the given four character key, such as `cust`
+    pub fn metadata_by_key(&self, key: &str) -> Option<&LayerMetadata> {
+        self.metadata.iter().find(|item| item.key() == key)
+    }
+
+    /// When the layer was last modified, in seconds since the Unix epoch.
+    ///
+    /// Photoshop stores this in the layer's custom metadata (`cust`). PSDs saved by older
+    /// versions of Photoshop might not have it.
+    pub fn modification_time(&self) -> Option<f64> {
+        self.metadata_by_key("cust")?
+            .descriptor()?
+            .number("layerTime")
+    }
+
+    /// The layer's states in the frames of a frame animation, from its metadata (`mlst`).
+    ///
+    /// Empty if the PSD doesn't have a frame animation. The data is parsed every time that this
+    /// is called.
+    pub fn animation_frames(&self) -> Vec<LayerFrameState> {
+        self.metadata_by_key("mlst")
+            .and_then(|item| item.descriptor())
+            .map(|descriptor| LayerFrameState::from_descriptor(&descriptor))
+            .unwrap_or_default()
+    }
+
+    /// The layer's keyframes in a timeline animation, one track per animated property, from its
+    /// metadata (`tmln`).
+    ///
+    /// Empty if the PSD doesn't have a timeline animation. The data is parsed every time that
+    /// this is called.
+    pub fn timeline_tracks(&self) -> Vec<TimelineTrack> {
+        self.metadata_by_key("tmln")
+            .and_then(|item| item.descriptor())
+            .map(|descriptor| TimelineTrack::from_descriptor(&descriptor))
+            .unwrap_or_default()
+    }
+
+    /// The opacity of the layer's pixels, which unlike [`LayerProperties::opacity`] doesn't
+    /// affect the layer's effects. 255 if the PSD doesn't store it.
+    pub fn fill_opacity(&self) -> u8 {
+        self.fill_opacity
+    }
+
+    /// The user supplied layer mask, if the layer has one
+    pub fn mask(&self) -> Option<&LayerMask> {
+        self.mask.as_ref()
+    }
+
+    /// If this is a solid color, gradient or pattern fill layer, returns what it is filled with.
+    pub fn fill(&self) -> Option<&FillLayer> {
+        self.fill.as_ref()
+    }
+
+    /// If this is a shape layer (saved by Photoshop CS6 or later), returns the settings of the
+    /// shape's stroke, such as its width, color and dash pattern.
+    pub fn vector_stroke(&self) -> Option<&VectorStroke> {
+        self.vector_stroke.as_ref()
+    }
+
+    /// If this is a shape layer (saved by Photoshop CS6 or later), returns what the shape is
+    /// filled with. See [`VectorStroke::fill_enabled`] for whether the fill is shown.
+    pub fn vector_fill(&self) -> Option<&FillLayer> {
+        self.vector_fill.as_ref()
+    }
+
+    /// The layer's vector mask, such as the outline of a shape layer, if it has one
+    pub fn vector_mask(&self) -> Option<&VectorMask> {
+        self.vector_mask.as_ref()
+    }
+
+    /// The layer's vector mask rasterized into one byte per pixel of the PSD, from 0 (hidden)
+    /// to 255 (shown), with the mask's density (see [`LayerMask::vector_mask_density`])
+    /// applied. The mask's feather isn't applied.
+    ///
+    /// Returns `None` if the layer doesn't have a vector mask or the mask is disabled.
+    pub fn rasterize_vector_mask(&self) -> Option<Vec<u8>> {
+        let vector_mask = self
+            .vector_mask
+            .as_ref()
+            .filter(|mask| !mask.is_disabled())?;
+        let mut coverage = vector_mask.rasterize(self.psd_width, self.psd_height);
+        self.apply_vector_mask_density(&mut coverage);
+
+        Some(coverage)
+    }
+
+    /// Like [`PsdLayer::rasterize_vector_mask`], but only the pixels within a region of the PSD,
+    /// with one byte per pixel of the region
+    pub(crate) fn rasterize_vector_mask_region(&self, region: Rect) -> Option<Vec<u8>> {
+        let vector_mask = self
+            .vector_mask
+            .as_ref()
+            .filter(|mask| !mask.is_disabled())?;
+        let mut coverage = vector_mask.rasterize_region((self.psd_width, self.psd_height), region);
+        self.apply_vector_mask_density(&mut coverage);
+
+        Some(coverage)
+    }
+
+    fn apply_vector_mask_density(&self, coverage: &mut [u8]) {
+        let density = self.mask.as_ref().and_then(|mask| mask.vector_mask_density);
+        if let Some(density) = density {
+            for value in coverage.iter_mut() {
+                *value = 255 - ((255 - *value as u16) * density as u16 / 255) as u8;
+            }
+        }
+    }
+
+    /// If this is a type layer, returns its text along with the transform and warp that place
+    /// it on the canvas.
+    pub fn text(&self) -> Option<&TextLayer> {
+        self.text.as_ref()
+    }
+
+    /// The layer's style, if any of its effects are turned on. Only the drop shadow, stroke
+    /// and color overlay are read.
+    pub fn effects(&self) -> Option<&LayerEffects> {
+        self.effects.as_ref()
+    }
+
+    /// The rectangle that the layer's pixels cover, which is the entire PSD for fill layers
+    fn pixel_bounds(&self) -> Option<Rect> {
+        match self.fill {
+            Some(_) => {
+                Rect::from_origin_and_size(Point::new(0, 0), self.psd_width, self.psd_height)
+            }
+            None => self.bounds(),
+        }
+    }
+
+    /// Whether the pixel at (x, y) of the PSD is within the layer.
+    ///
+    /// Fill layers cover the entire PSD, while empty layers don't contain any pixels.
+    pub fn contains_canvas_point(&self, x: u32, y: u32) -> bool {
+        self.canvas_to_layer_coords(x, y).is_some()
+    }
+
+    /// The position within the layer of the pixel at (x, y) of the PSD, or `None` if the pixel
+    /// isn't within the layer.
+    ///
+    /// (0, 0) is the top left pixel of the layer's [`LayerProperties::bounds`]. Fill layers
+    /// cover the entire PSD, so their positions are the same as the PSD's.
+    pub fn canvas_to_layer_coords(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        let point = self.placement()?.canvas_to_layer(canvas_point(x, y))?;
+
+        Some((point.x as u32, point.y as u32))
+    }
+
+    /// Where the layer's pixels are on the canvas and within the layer, along with the part of
+    /// them that is on the canvas, or `None` if the layer is empty.
+    ///
+    /// Fill layers cover the entire PSD.
+    ///
+    /// ```ignore
+    /// let placement = layer.placement().unwrap();
+    /// if let Some(visible) = placement.visible_layer_rect() {
+    ///     // Crop the layer's own pixels to the canvas
+    /// }
+    /// ```
+    pub fn placement(&self) -> Option<LayerPlacement> {
+        let bounds = self.pixel_bounds()?;
+
+        Some(LayerPlacement::new(bounds, self.psd_width, self.psd_height))
     }
 
     // Get one of the PsdLayerChannels of this PsdLayer
@@ -257,6 +1344,48 @@ impl PsdLayer {
     }
 }
 
+/// The luminance of a color, using the Rec. 601 weights
+fn luminance(red: u8, green: u8, blue: u8) -> u8 {
+    ((299 * red as u32 + 587 * green as u32 + 114 * blue as u32 + 500) / 1000) as u8
+}
+
+/// A pixel of the PSD. PSDs are at most 300,000 pixels wide, so it always fits.
+fn canvas_point(x: u32, y: u32) -> Point {
+    Point::new(x as i32, y as i32)
+}
+
+/// Find the bounds of the non zero bytes in an alpha channel that is `width` pixels wide and
+/// whose top left pixel is at (left, top) within the PSD.
+fn alpha_bounds(alpha: &[u8], width: usize, top: i32, left: i32) -> Option<LayerBounds> {
+    if width == 0 {
+        return None;
+    }
+
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+    for (row, row_alpha) in alpha.chunks(width).enumerate() {
+        let first = match row_alpha.iter().position(|alpha| *alpha != 0) {
+            Some(first) => first,
+            None => continue,
+        };
+        let last = row_alpha.iter().rposition(|alpha| *alpha != 0).unwrap();
+
+        bounds = Some(match bounds {
+            Some((top, min_left, _, max_right)) => {
+                (top, min_left.min(first), row, max_right.max(last))
+            }
+            None => (row, first, row, last),
+        });
+    }
+
+    bounds.map(|(min_top, min_left, max_bottom, max_right)| LayerBounds {
+        top: top + min_top as i32,
+        left: left + min_left as i32,
+        bottom: top + max_bottom as i32,
+        right: left + max_right as i32,
+    })
+}
+
 impl Deref for PsdLayer {
     type Target = LayerProperties;
 
@@ -290,42 +1419,122 @@ impl GroupDivider {
     }
 }
 
-/// Describes how to blend a layer with the layer below it
-#[derive(Debug, Clone, Copy)]
-#[allow(missing_docs)]
+/// Describes how to blend a layer with the layer below it.
+///
+/// Each blend mode is stored in the PSD as a four character key, which is shown in parentheses
+/// below. See [`BlendMode::as_key`].
+///
+/// The keys are also how blend modes are named as text, so that config files and command line
+/// tools can use the same names as the PSD specification. Keys that are shorter than four
+/// characters are padded with spaces in the PSD, which are left out of the text.
+///
+/// ```
+/// use psd::BlendMode;
+///
+/// assert_eq!("mul".parse(), Ok(BlendMode::Multiply));
+/// assert_eq!("mul ".parse(), Ok(BlendMode::Multiply));
+/// assert_eq!(BlendMode::Multiply.to_string(), "mul");
+/// assert_eq!(BlendMode::DarkerColor.to_string(), "dkCl");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlendMode {
+    /// Only for groups. The group's layers are blended with the layers below the group as if they
+    /// weren't in a group. (`pass`)
     PassThrough = 0,
+    /// The layer covers the layers below it. (`norm`)
     Normal = 1,
+    /// Randomly replaces pixels below the layer, based on the layer's opacity. (`diss`)
     Dissolve = 2,
+    /// Keeps the darker of the two colors, channel by channel. (`dark`)
     Darken = 3,
+    /// Multiplies the colors, which always darkens. (`mul `)
     Multiply = 4,
+    /// Darkens the colors below to reflect the layer by increasing contrast. (`idiv`)
     ColorBurn = 5,
+    /// Darkens the colors below to reflect the layer by decreasing brightness. (`lbrn`)
     LinearBurn = 6,
+    /// Keeps whichever of the two colors has the lower total of all channels. (`dkCl`)
     DarkerColor = 7,
+    /// Keeps the lighter of the two colors, channel by channel. (`lite`)
     Lighten = 8,
+    /// Multiplies the inverse of the colors, which always lightens. (`scrn`)
     Screen = 9,
+    /// Brightens the colors below to reflect the layer by decreasing contrast. (`div `)
     ColorDodge = 10,
+    /// Adds the colors together, also known as Add. (`lddg`)
     LinearDodge = 11,
+    /// Keeps whichever of the two colors has the higher total of all channels. (`lgCl`)
     LighterColor = 12,
+    /// Multiplies or screens depending on the colors below the layer. (`over`)
     Overlay = 13,
+    /// Darkens or lightens depending on the layer, like shining a diffused spotlight. (`sLit`)
     SoftLight = 14,
+    /// Multiplies or screens depending on the layer, like shining a harsh spotlight. (`hLit`)
     HardLight = 15,
+    /// Burns or dodges the colors by changing the contrast, depending on the layer. (`vLit`)
     VividLight = 16,
+    /// Burns or dodges the colors by changing the brightness, depending on the layer. (`lLit`)
     LinearLight = 17,
+    /// Replaces the colors below, depending on the layer. (`pLit`)
     PinLight = 18,
+    /// Snaps each channel to either 0 or 255. (`hMix`)
     HardMix = 19,
+    /// Subtracts the darker of the two colors from the lighter one. (`diff`)
     Difference = 20,
+    /// Like difference, but with lower contrast. (`smud`)
     Exclusion = 21,
+    /// Subtracts the layer from the colors below. (`fsub`)
     Subtract = 22,
+    /// Divides the colors below by the layer. (`fdiv`)
     Divide = 23,
+    /// Uses the hue of the layer with the saturation and luminance of the colors below. (`hue `)
     Hue = 24,
+    /// Uses the saturation of the layer with the hue and luminance of the colors below. (`sat `)
     Saturation = 25,
+    /// Uses the hue and saturation of the layer with the luminance of the colors below. (`colr`)
     Color = 26,
+    /// Uses the luminance of the layer with the hue and saturation of the colors below. (`lum `)
     Luminosity = 27,
 }
 
 impl BlendMode {
-    pub(super) fn match_mode(mode: [u8; 4]) -> Option<BlendMode> {
+    /// Every blend mode, in the order that Photoshop lists them
+    pub const ALL: [BlendMode; 28] = [
+        BlendMode::PassThrough,
+        BlendMode::Normal,
+        BlendMode::Dissolve,
+        BlendMode::Darken,
+        BlendMode::Multiply,
+        BlendMode::ColorBurn,
+        BlendMode::LinearBurn,
+        BlendMode::DarkerColor,
+        BlendMode::Lighten,
+        BlendMode::Screen,
+        BlendMode::ColorDodge,
+        BlendMode::LinearDodge,
+        BlendMode::LighterColor,
+        BlendMode::Overlay,
+        BlendMode::SoftLight,
+        BlendMode::HardLight,
+        BlendMode::VividLight,
+        BlendMode::LinearLight,
+        BlendMode::PinLight,
+        BlendMode::HardMix,
+        BlendMode::Difference,
+        BlendMode::Exclusion,
+        BlendMode::Subtract,
+        BlendMode::Divide,
+        BlendMode::Hue,
+        BlendMode::Saturation,
+        BlendMode::Color,
+        BlendMode::Luminosity,
+    ];
+
+    /// The blend mode that is stored as the four character key, or `None` if the key isn't a
+    /// blend mode.
+    ///
+    /// Unlike `BlendMode::from`, this doesn't fall back to [`BlendMode::Normal`].
+    pub fn from_key(mode: [u8; 4]) -> Option<BlendMode> {
         match &mode {
             b"pass" => Some(BlendMode::PassThrough),
             b"norm" => Some(BlendMode::Normal),
@@ -358,6 +1567,156 @@ impl BlendMode {
             _ => None,
         }
     }
+
+    /// Descriptors, such as the settings of a shape's stroke, store blend modes as the values
+    /// of the `BlnM` enumeration instead of the keys of layer records
+    pub(crate) fn from_descriptor_key(key: &[u8]) -> Option<BlendMode> {
+        match key {
+            b"passThrough" => Some(BlendMode::PassThrough),
+            b"Nrml" => Some(BlendMode::Normal),
+            b"Dslv" => Some(BlendMode::Dissolve),
+            b"Drkn" => Some(BlendMode::Darken),
+            b"Mltp" => Some(BlendMode::Multiply),
+            b"CBrn" => Some(BlendMode::ColorBurn),
+            b"linearBurn" => Some(BlendMode::LinearBurn),
+            b"darkerColor" => Some(BlendMode::DarkerColor),
+            b"Lghn" => Some(BlendMode::Lighten),
+            b"Scrn" => Some(BlendMode::Screen),
+            b"CDdg" => Some(BlendMode::ColorDodge),
+            b"linearDodge" => Some(BlendMode::LinearDodge),
+            b"lighterColor" => Some(BlendMode::LighterColor),
+            b"Ovrl" => Some(BlendMode::Overlay),
+            b"SftL" => Some(BlendMode::SoftLight),
+            b"HrdL" => Some(BlendMode::HardLight),
+            b"vividLight" => Some(BlendMode::VividLight),
+            b"linearLight" => Some(BlendMode::LinearLight),
+            b"pinLight" => Some(BlendMode::PinLight),
+            b"hardMix" => Some(BlendMode::HardMix),
+            b"Dfrn" => Some(BlendMode::Difference),
+            b"Xclu" => Some(BlendMode::Exclusion),
+            b"blendSubtraction" => Some(BlendMode::Subtract),
+            b"blendDivide" => Some(BlendMode::Divide),
+            b"H   " => Some(BlendMode::Hue),
+            b"Strt" => Some(BlendMode::Saturation),
+            b"Clr " => Some(BlendMode::Color),
+            b"Lmns" => Some(BlendMode::Luminosity),
+            _ => None,
+        }
+    }
+
+    /// The four character key that the blend mode is stored as in the PSD
+    pub fn as_key(&self) -> [u8; 4] {
+        match self {
+            BlendMode::PassThrough => *b"pass",
+            BlendMode::Normal => *b"norm",
+            BlendMode::Dissolve => *b"diss",
+            BlendMode::Darken => *b"dark",
+            BlendMode::Multiply => *b"mul ",
+            BlendMode::ColorBurn => *b"idiv",
+            BlendMode::LinearBurn => *b"lbrn",
+            BlendMode::DarkerColor => *b"dkCl",
+            BlendMode::Lighten => *b"lite",
+            BlendMode::Screen => *b"scrn",
+            BlendMode::ColorDodge => *b"div ",
+            BlendMode::LinearDodge => *b"lddg",
+            BlendMode::LighterColor => *b"lgCl",
+            BlendMode::Overlay => *b"over",
+            BlendMode::SoftLight => *b"sLit",
+            BlendMode::HardLight => *b"hLit",
+            BlendMode::VividLight => *b"vLit",
+            BlendMode::LinearLight => *b"lLit",
+            BlendMode::PinLight => *b"pLit",
+            BlendMode::HardMix => *b"hMix",
+            BlendMode::Difference => *b"diff",
+            BlendMode::Exclusion => *b"smud",
+            BlendMode::Subtract => *b"fsub",
+            BlendMode::Divide => *b"fdiv",
+            BlendMode::Hue => *b"hue ",
+            BlendMode::Saturation => *b"sat ",
+            BlendMode::Color => *b"colr",
+            BlendMode::Luminosity => *b"lum ",
+        }
+    }
+}
+
+impl From<[u8; 4]> for BlendMode {
+    /// Keys that aren't a blend mode are treated as [`BlendMode::Normal`], like Photoshop does.
+    fn from(key: [u8; 4]) -> Self {
+        BlendMode::from_key(key).unwrap_or(BlendMode::Normal)
+    }
+}
+
+impl FromStr for BlendMode {
+    type Err = ParseBlendModeError;
+
+    /// Parse a four character key, such as `norm` or `mul`. The trailing spaces of shorter keys
+    /// are optional. Keys are case sensitive, like in the PSD.
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let unknown = || ParseBlendModeError {
+            key: key.to_string(),
+        };
+
+        let bytes = key.as_bytes();
+        if bytes.is_empty() || bytes.len() > 4 {
+            return Err(unknown());
+        }
+
+        let mut padded = *b"    ";
+        padded[..bytes.len()].copy_from_slice(bytes);
+
+        BlendMode::from_key(padded).ok_or_else(unknown)
+    }
+}
+
+impl fmt::Display for BlendMode {
+    /// Write the four character key without its trailing spaces, such as `mul` for
+    /// [`BlendMode::Multiply`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = self.as_key();
+
+        // Every key is ASCII
+        f.write_str(core::str::from_utf8(&key).unwrap().trim_end())
+    }
+}
+
+/// An error when parsing a [`BlendMode`] from text
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Unknown blend mode key: {key:?}")]
+pub struct ParseBlendModeError {
+    key: String,
+}
+
+impl ParseBlendModeError {
+    /// The text that isn't a blend mode key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// How a layer punches through the layers below it, which is set in the layer's advanced
+/// blending options.
+///
+/// Where a knockout layer has pixels, the layers below it down to the knockout's stopping point
+/// are hidden, so lowering the layer's fill opacity reveals what's under them instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Knockout {
+    /// The layers below show through as usual
+    #[default]
+    None,
+    /// Knocks out the other layers of the layer's group, revealing the layers below the group
+    Shallow,
+    /// Knocks out every layer down to the background
+    Deep,
+}
+
+impl Knockout {
+    pub(super) fn from_byte(byte: u8) -> Knockout {
+        match byte {
+            0 => Knockout::None,
+            2 => Knockout::Deep,
+            _ => Knockout::Shallow,
+        }
+    }
 }
 
 /// A layer record within the layer info section
@@ -376,6 +1735,10 @@ pub struct LayerRecord {
     ///
     /// A 2x2 image would have 4 bytes per channel.
     pub(super) channel_data_lengths: Vec<(PsdChannelKind, u32)>,
+    /// Where the record is within the layer and mask information section
+    pub(super) record_range: Range<usize>,
+    /// Where the record's channel data starts within the layer and mask information section
+    pub(super) channel_data_offset: usize,
     /// The position of the top of the image
     pub(super) top: i32,
     /// The position of the left of the image
@@ -394,13 +1757,59 @@ pub struct LayerRecord {
     pub(super) blend_mode: BlendMode,
     /// Group divider tag
     pub(super) divider_type: Option<GroupDivider>,
+    /// The contents of the layer, if it is a fill layer
+    pub(super) fill: Option<FillLayer>,
+    /// The stroke of the layer, if it is a shape layer
+    pub(super) vector_stroke: Option<VectorStroke>,
+    /// What the layer's shape is filled with, if it is a shape layer
+    pub(super) vector_fill: Option<FillLayer>,
+    /// The layer's vector mask, if it has one
+    pub(super) vector_mask: Option<VectorMask>,
+    /// The text of the layer, if it is a type layer
+    pub(super) text: Option<TextLayer>,
+    /// The layer style, if any of its effects are turned on
+    pub(super) effects: Option<LayerEffects>,
+    /// If true, the rectangle of this layer record has no area
+    pub(super) empty: bool,
+    /// The user supplied layer mask
+    pub(super) mask: Option<LayerMask>,
+    /// The unique ID of the layer within the PSD
+    pub(super) layer_id: Option<u32>,
+    /// The opacity of the layer's pixels, not including its layer effects
+    pub(super) fill_opacity: u8,
+    /// The items of the layer's metadata setting
+    pub(super) metadata: Vec<LayerMetadata>,
+    /// How much of the layers below the layer's pixels show through
+    pub(super) knockout: Knockout,
+    /// Whether the layers clipped to this one are blended with it first
+    pub(super) blend_clipped_elements: bool,
+    /// Whether the layer's interior effects are blended with its pixels first
+    pub(super) blend_interior_elements: bool,
 }
 
 impl LayerRecord {
     /// The height of this layer record
     pub fn height(&self) -> i32 {
+        if self.empty {
+            return 0;
+        }
+
         (self.bottom - self.top) + 1
     }
+
+    /// The number of rows of pixels in one of the layer's channels, since masks have their own
+    /// rectangles
+    pub(crate) fn channel_height(&self, channel: PsdChannelKind) -> usize {
+        let mask_bounds = match (channel, &self.mask) {
+            (PsdChannelKind::UserSuppliedLayerMask, Some(mask)) => mask.bounds(),
+            (PsdChannelKind::RealUserSuppliedLayerMask, Some(mask)) => mask.real_bounds(),
+            _ => return self.height() as usize,
+        };
+
+        mask_bounds
+            .map(|bounds| bounds.height() as usize)
+            .unwrap_or(0)
+    }
 }
 
 impl IntoRgba for PsdLayer {
@@ -431,13 +1840,17 @@ impl IntoRgba for PsdLayer {
     /// position within the PSD.
     fn rgba_idx(&self, idx: usize) -> usize {
         let left_in_layer = idx % self.width() as usize;
-        let left_in_psd = self.layer_properties.layer_left as usize + left_in_layer;
+        let left_in_psd = self.layer_properties.rect.left as usize + left_in_layer;
 
-        let top_in_psd = idx / self.width() as usize + self.layer_properties.layer_top as usize;
+        let top_in_psd = idx / self.width() as usize + self.layer_properties.rect.top as usize;
 
         (top_in_psd * self.layer_properties.psd_width as usize) + left_in_psd
     }
 
+    fn rgba_row_len(&self) -> usize {
+        self.width() as usize
+    }
+
     fn red(&self) -> &ChannelBytes {
         self.get_channel(PsdChannelKind::Red).unwrap()
     }
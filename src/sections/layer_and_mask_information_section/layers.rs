@@ -1,6 +1,6 @@
+use crate::prelude::*;
 use crate::PsdLayer;
-use std::collections::HashMap;
-use std::ops::{Deref, Range};
+use core::ops::{Deref, Range};
 
 /// `NamedItems` is immutable container for storing items with order-preservation
 /// and indexing by id and name
@@ -43,6 +43,16 @@ impl Layers {
         self.items.push(item);
         self.item_indices.insert(name, self.items.len() - 1);
     }
+
+    /// The layers, in order, for changing their caches
+    pub(crate) fn iter_mut(&mut self) -> core::slice::IterMut<'_, PsdLayer> {
+        self.items.iter_mut()
+    }
+
+    /// Take the layers out, in order
+    pub(crate) fn into_items(self) -> Vec<PsdLayer> {
+        self.items
+    }
 }
 
 impl Deref for Layers {
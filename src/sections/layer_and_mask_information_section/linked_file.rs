@@ -0,0 +1,195 @@
+use crate::prelude::*;
+use crate::sections::image_resources_section::DescriptorStructure;
+use crate::sections::PsdCursor;
+
+/// Key of `Linked Layer`, "lnkD"
+pub(super) const KEY_LINKED_LAYER: &[u8; 4] = b"lnkD";
+/// Key of `Linked Layer`, "lnk2"
+pub(super) const KEY_LINKED_LAYER_2: &[u8; 4] = b"lnk2";
+/// Key of `Linked Layer`, "lnk3"
+pub(super) const KEY_LINKED_LAYER_3: &[u8; 4] = b"lnk3";
+
+/// The bytes of a linked file after its length, from its type to its version
+const LINKED_FILE_HEADER_LEN: u64 = 4 + 4;
+
+/// A file that a smart object layer shows, such as the original PNG or PSD that was placed
+/// into the document.
+///
+/// Linked files are stored in the additional layer information at the end of the layer and mask
+/// information section. Files that were placed as embedded smart objects keep their bytes in
+/// the PSD, while linked smart objects only refer to a file next to it.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// The following is repeated for each linked file:
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 8        | Length of the data to follow, padded to a multiple of 4 bytes               |
+/// | 4        | Type: 'liFD' linked file data, 'liFE' linked file external or 'liFA' alias  |
+/// | 4        | Version ( = 1 to 7)                                                         |
+/// | Variable | Pascal string. Unique ID.                                                   |
+/// | Variable | Unicode string of the original file name                                    |
+/// | 4        | File type                                                                   |
+/// | 4        | File creator                                                                |
+/// | 8        | Length of the data to follow                                                |
+/// | 1        | File open descriptor present                                                |
+/// | Variable | If present, a version ( = 16) and a descriptor of the open parameters       |
+/// | Variable | If the type is 'liFE', a version ( = 16) and a descriptor of the file       |
+/// | Variable | Raw bytes of the file, if the type is 'liFD'                                |
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedFile {
+    pub(crate) kind: LinkedFileKind,
+    pub(crate) unique_id: String,
+    pub(crate) name: String,
+    pub(crate) file_type: [u8; 4],
+    pub(crate) data: Option<Vec<u8>>,
+}
+
+/// Where the bytes of a linked file are
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LinkedFileKind {
+    /// The file is embedded in the PSD
+    Embedded,
+    /// The file is next to the PSD, and only its location is stored
+    External,
+    /// The file is referred to by a macOS alias
+    Alias,
+}
+
+impl LinkedFile {
+    /// Read all of the linked files in a `lnkD`, `lnk2` or `lnk3` block.
+    ///
+    /// Linked files that are cut short or have an unknown type are skipped.
+    pub(super) fn read_linked_files(bytes: &[u8]) -> Vec<LinkedFile> {
+        let mut linked_files = vec![];
+
+        let mut start = 0;
+        while start + 8 <= bytes.len() {
+            let mut len = [0; 8];
+            len.copy_from_slice(&bytes[start..start + 8]);
+            let file_len = u64::from_be_bytes(len);
+
+            let end = match (start as u64 + 8).checked_add(file_len) {
+                Some(end) if end <= bytes.len() as u64 => end as usize,
+                _ => break,
+            };
+
+            if let Some(linked_file) = LinkedFile::read_linked_file(&bytes[start + 8..end]) {
+                linked_files.push(linked_file);
+            }
+
+            // Each linked file is padded to a multiple of 4 bytes
+            start = end + (4 - file_len as usize % 4) % 4;
+        }
+
+        linked_files
+    }
+
+    fn read_linked_file(bytes: &[u8]) -> Option<LinkedFile> {
+        let len = bytes.len() as u64;
+        if len < LINKED_FILE_HEADER_LEN {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(bytes);
+        let kind = match cursor.read_4().ok()? {
+            b"liFD" => LinkedFileKind::Embedded,
+            b"liFE" => LinkedFileKind::External,
+            b"liFA" => LinkedFileKind::Alias,
+            _ => return None,
+        };
+        let _version = cursor.read_u32().ok()?;
+
+        // The unique ID is a Pascal string without any padding
+        if cursor.position() + 1 > len {
+            return None;
+        }
+        let unique_id_len = cursor.read_u8().ok()? as u64;
+        if cursor.position() + unique_id_len > len {
+            return None;
+        }
+        let unique_id =
+            String::from_utf8_lossy(cursor.read(unique_id_len as u32).ok()?).into_owned();
+
+        if cursor.position() + 4 > len {
+            return None;
+        }
+        let name_len = cursor.read_u32().ok()? as u64 * 2;
+        if cursor.position() + name_len > len {
+            return None;
+        }
+        let name: Vec<u16> = cursor
+            .read(name_len as u32)
+            .ok()?
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        let name = String::from_utf16_lossy(&name)
+            .trim_end_matches('\0')
+            .to_string();
+
+        // File type, file creator, the length of the file and the open descriptor flag
+        if cursor.position() + 4 + 4 + 8 + 1 > len {
+            return None;
+        }
+        let mut file_type = [0; 4];
+        file_type.copy_from_slice(cursor.read_4().ok()?);
+        let _file_creator = cursor.read_4().ok()?;
+        let mut data_len = [0; 8];
+        data_len.copy_from_slice(cursor.read(8).ok()?);
+        let data_len = u64::from_be_bytes(data_len);
+
+        let mut data = None;
+        if kind == LinkedFileKind::Embedded {
+            // The descriptor of the file open parameters comes before the file
+            if cursor.read_u8().ok()? != 0 {
+                if cursor.position() + 4 > len {
+                    return None;
+                }
+                let _descriptor_version = cursor.read_u32().ok()?;
+                DescriptorStructure::read_descriptor_structure(&mut cursor).ok()?;
+            }
+
+            if cursor.position() + data_len > len {
+                return None;
+            }
+            data = Some(cursor.read(data_len as u32).ok()?.to_vec());
+        }
+
+        Some(LinkedFile {
+            kind,
+            unique_id,
+            name,
+            file_type,
+            data,
+        })
+    }
+
+    /// Where the file's bytes are
+    pub fn kind(&self) -> LinkedFileKind {
+        self.kind
+    }
+
+    /// The unique ID of the file, which smart object layers use to refer to it
+    pub fn unique_id(&self) -> &str {
+        &self.unique_id
+    }
+
+    /// The name of the file when it was placed, such as `logo.png`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The file's four character type, such as `png `. Empty if it isn't known.
+    pub fn file_type(&self) -> &str {
+        core::str::from_utf8(&self.file_type)
+            .unwrap_or("")
+            .trim_end_matches('\0')
+    }
+
+    /// The bytes of the file, if it is embedded in the PSD
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+}
@@ -0,0 +1,186 @@
+use crate::geometry::Rect;
+use crate::sections::{PsdCursor, PsdCursorError};
+
+/// The user supplied layer mask of a layer.
+///
+/// The mask's pixels are stored in the layer's `UserSuppliedLayerMask` channel. Pixels outside
+/// of the mask's rectangle have the mask's default color.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Layer mask / adjustment layer data
+///
+/// | Length   | Description                                                                                     |
+/// |----------|-------------------------------------------------------------------------------------------------|
+/// | 4        | Size of the data: Check the size and flags to determine what is or is not present. If zero, the following fields are not present |
+/// | 4 * 4    | Rectangle enclosing layer mask: Top, left, bottom, right                                        |
+/// | 1        | Default color. 0 or 255                                                                         |
+/// | 1        | Flags. <br> bit 0 = position relative to layer <br> bit 1 = layer mask disabled <br> bit 2 = invert layer mask when blending (Obsolete) <br> bit 3 = indicates that the user mask actually came from rendering other data <br> bit 4 = indicates that the user and/or vector masks have parameters applied to them |
+/// | 1        | Mask Parameters. Only present if bit 4 of Flags set above.                                      |
+/// | Variable | Mask Parameters bit flags present as follows: <br> bit 0 = user mask density, 1 byte <br> bit 1 = user mask feather, 8 byte, double <br> bit 2 = vector mask density, 1 byte <br> bit 3 = vector mask feather, 8 bytes, double |
+/// | 2        | Padding. Only present if size = 20. Otherwise the following is present                          |
+/// | 1        | Real Flags. Same as Flags information above.                                                    |
+/// | 1        | Real user mask background. 0 or 255.                                                            |
+/// | 4 * 4    | Rectangle enclosing layer mask: Top, left, bottom, right.                                       |
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerMask {
+    pub(crate) top: i32,
+    pub(crate) left: i32,
+    /// Exclusive
+    pub(crate) bottom: i32,
+    /// Exclusive
+    pub(crate) right: i32,
+    pub(crate) default_color: u8,
+    pub(crate) disabled: bool,
+    pub(crate) user_mask_density: Option<u8>,
+    pub(crate) user_mask_feather: Option<f64>,
+    pub(crate) vector_mask_density: Option<u8>,
+    pub(crate) vector_mask_feather: Option<f64>,
+    /// The rectangle of the real user mask, which is only stored for layers that have both a
+    /// user mask and a vector mask
+    pub(crate) real_rect: Option<Rect>,
+}
+
+impl LayerMask {
+    /// Read the layer mask / adjustment layer data, not including its length marker.
+    ///
+    /// Returns `None` when the layer does not have a mask.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<LayerMask> {
+        // Rectangle, default color and flags
+        if bytes.len() < 18 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(bytes);
+
+        let top = cursor.read_i32().ok()?;
+        let left = cursor.read_i32().ok()?;
+        let bottom = cursor.read_i32().ok()?;
+        let right = cursor.read_i32().ok()?;
+
+        let default_color = cursor.read_u8().ok()?;
+        let flags = cursor.read_u8().ok()?;
+
+        let mut mask = LayerMask {
+            top,
+            left,
+            bottom,
+            right,
+            default_color,
+            disabled: flags & (1 << 1) != 0,
+            user_mask_density: None,
+            user_mask_feather: None,
+            vector_mask_density: None,
+            vector_mask_feather: None,
+            real_rect: None,
+        };
+
+        if flags & (1 << 4) != 0 {
+            // The parameters that were read before the data ran out are kept
+            mask.read_parameters(&mut cursor).ok();
+        }
+
+        // The real flags, the real user mask background and the real rectangle
+        if cursor.position() + 18 <= bytes.len() as u64 {
+            cursor.read(2).ok()?;
+
+            let top = cursor.read_i32().ok()?;
+            let left = cursor.read_i32().ok()?;
+            let bottom = cursor.read_i32().ok()?;
+            let right = cursor.read_i32().ok()?;
+            if bottom > top && right > left {
+                mask.real_rect = Some(Rect {
+                    top,
+                    left,
+                    bottom: bottom - 1,
+                    right: right - 1,
+                });
+            }
+        }
+
+        Some(mask)
+    }
+
+    /// Read the density and feather of the user and vector masks, stopping at the first
+    /// parameter that doesn't fit in the data.
+    fn read_parameters(&mut self, cursor: &mut PsdCursor) -> Result<(), PsdCursorError> {
+        let parameters = cursor.read_u8()?;
+
+        if parameters & (1 << 0) != 0 {
+            self.user_mask_density = Some(cursor.read_u8()?);
+        }
+        if parameters & (1 << 1) != 0 {
+            self.user_mask_feather = Some(cursor.read_f64()?);
+        }
+        if parameters & (1 << 2) != 0 {
+            self.vector_mask_density = Some(cursor.read_u8()?);
+        }
+        if parameters & (1 << 3) != 0 {
+            self.vector_mask_feather = Some(cursor.read_f64()?);
+        }
+
+        Ok(())
+    }
+
+    /// The rectangle that the mask's pixels cover, or `None` if the mask doesn't have any
+    /// pixels.
+    pub fn bounds(&self) -> Option<Rect> {
+        if self.bottom <= self.top || self.right <= self.left {
+            return None;
+        }
+
+        Some(Rect {
+            top: self.top,
+            left: self.left,
+            bottom: self.bottom - 1,
+            right: self.right - 1,
+        })
+    }
+
+    /// The rectangle that the pixels of the real user mask cover, which is stored in the
+    /// layer's `RealUserSuppliedLayerMask` channel.
+    ///
+    /// Only layers that have both a user mask and a vector mask have one.
+    pub fn real_bounds(&self) -> Option<Rect> {
+        self.real_rect
+    }
+
+    /// The value of every pixel outside of the mask's rectangle. 0 (hidden) or 255 (shown).
+    pub fn default_color(&self) -> u8 {
+        self.default_color
+    }
+
+    /// If true, the mask has been disabled and does not affect the layer
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    /// How strongly the user mask hides the layer, from 0 (not at all) to 255 (fully), if it
+    /// was saved. Masks without a density hide the layer fully.
+    pub fn user_mask_density(&self) -> Option<u8> {
+        self.user_mask_density
+    }
+
+    /// The radius, in pixels, that the edges of the user mask are blurred by. Feathering is not
+    /// applied when compositing.
+    pub fn user_mask_feather(&self) -> Option<f64> {
+        self.user_mask_feather
+    }
+
+    /// How strongly the layer's vector mask hides the layer, from 0 to 255, if it was saved
+    pub fn vector_mask_density(&self) -> Option<u8> {
+        self.vector_mask_density
+    }
+
+    /// The radius, in pixels, that the edges of the layer's vector mask are blurred by
+    pub fn vector_mask_feather(&self) -> Option<f64> {
+        self.vector_mask_feather
+    }
+
+    /// Weaken a value of the user mask by the mask's density, so that a density of 0 leaves
+    /// every pixel shown.
+    pub(crate) fn apply_density(&self, mask_value: u8) -> u8 {
+        let density = self.user_mask_density.unwrap_or(255) as u16;
+        255 - ((255 - mask_value as u16) * density / 255) as u8
+    }
+}
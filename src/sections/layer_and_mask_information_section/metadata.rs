@@ -0,0 +1,112 @@
+use crate::prelude::*;
+use crate::sections::image_resources_section::{DescriptorField, DescriptorStructure};
+use crate::sections::{PsdCursor, PsdCursorError};
+
+/// Key of `Metadata setting (Photoshop 6.0)`, "shmd"
+pub(super) const KEY_METADATA_SETTING: &[u8; 4] = b"shmd";
+
+/// The version that precedes the descriptor of metadata items that store one
+const DESCRIPTOR_VERSION: u32 = 16;
+
+/// An item from a layer's metadata, such as the time that the layer was last modified (`cust`)
+/// or its timeline (`tmln`).
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Metadata setting (Photoshop 6.0)
+///
+/// | Length   | Description                                                     |
+/// |----------|-----------------------------------------------------------------|
+/// | 4        | Count of metadata items to follow                               |
+///
+/// The following is repeated the number of times specified by the count above:
+///
+/// | Length   | Description                                                     |
+/// |----------|-----------------------------------------------------------------|
+/// | 4        | Signature of the data                                           |
+/// | 4        | Key of the data                                                 |
+/// | 1        | Copy on sheet duplication                                       |
+/// | 3        | Padding                                                         |
+/// | 4        | Length of data to follow                                        |
+/// | Variable | Undocumented data. Usually a version ( = 16) and a descriptor   |
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerMetadata {
+    pub(crate) key: [u8; 4],
+    pub(crate) copy_on_sheet_duplication: bool,
+    pub(crate) data: Vec<u8>,
+}
+
+impl LayerMetadata {
+    /// Read the items of a metadata setting block.
+    ///
+    /// Items that claim to be longer than the block are cut short.
+    pub(super) fn read_metadata_setting(bytes: &[u8]) -> Vec<LayerMetadata> {
+        let mut cursor = PsdCursor::new(bytes);
+        let count = match cursor.read_u32() {
+            Ok(count) => count,
+            Err(_) => return vec![],
+        };
+
+        let mut items = vec![];
+        for _ in 0..count {
+            match LayerMetadata::read_metadata_item(&mut cursor) {
+                Ok(item) => items.push(item),
+                Err(_) => break,
+            }
+        }
+
+        items
+    }
+
+    fn read_metadata_item(cursor: &mut PsdCursor) -> Result<LayerMetadata, PsdCursorError> {
+        let _signature = cursor.read_4()?;
+        let mut key = [0; 4];
+        key.copy_from_slice(cursor.read_4()?);
+        let copy_on_sheet_duplication = cursor.read_u8()? != 0;
+        cursor.read(3)?;
+
+        let data_len = (cursor.read_u32()? as u64).min(cursor.remaining());
+        let data = cursor.read(data_len as u32)?.to_vec();
+
+        Ok(LayerMetadata {
+            key,
+            copy_on_sheet_duplication,
+            data,
+        })
+    }
+
+    /// The item's four character key, such as `cust` or `tmln`
+    pub fn key(&self) -> &str {
+        core::str::from_utf8(&self.key).unwrap_or_default()
+    }
+
+    /// Whether Photoshop copies the item when the layer is duplicated
+    pub fn copy_on_sheet_duplication(&self) -> bool {
+        self.copy_on_sheet_duplication
+    }
+
+    /// The item's data, exactly as it is stored in the PSD
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The fields of the item's descriptor, if the item's data is a descriptor.
+    ///
+    /// The data is parsed every time that this is called.
+    pub fn fields(&self) -> Option<HashMap<String, DescriptorField>> {
+        self.descriptor().map(|descriptor| descriptor.fields)
+    }
+
+    pub(crate) fn descriptor(&self) -> Option<DescriptorStructure> {
+        if self.data.len() < 4 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(&self.data);
+        if cursor.read_u32().ok()? != DESCRIPTOR_VERSION {
+            return None;
+        }
+
+        DescriptorStructure::read_descriptor_structure(&mut cursor).ok()
+    }
+}
@@ -1,15 +1,46 @@
-use std::collections::HashMap;
-use std::ops::Range;
+use core::ops::Range;
 
+use crate::options::PsdLimitError;
+use crate::prelude::*;
 use crate::psd_channel::PsdChannelCompression;
 use crate::psd_channel::PsdChannelKind;
-use crate::sections::image_data_section::ChannelBytes;
+use crate::sections::file_header_section::PsdVersion;
+use crate::sections::image_data_section::{ChannelBytes, ScanlineLens};
+use crate::sections::layer_and_mask_information_section::annotation::{
+    Annotation, KEY_ANNOTATIONS,
+};
+use crate::sections::layer_and_mask_information_section::effects::{
+    LayerEffects, KEY_OBJECT_EFFECTS,
+};
+use crate::sections::layer_and_mask_information_section::fill::{
+    FillLayer, KEY_GRADIENT_FILL, KEY_PATTERN_FILL, KEY_SOLID_COLOR_FILL,
+};
 use crate::sections::layer_and_mask_information_section::groups::Groups;
 use crate::sections::layer_and_mask_information_section::layer::{
-    BlendMode, GroupDivider, LayerChannels, LayerRecord, PsdGroup, PsdLayer, PsdLayerError,
+    BlendMode, GroupDivider, Knockout, LayerChannels, LayerRecord, PsdGroup, PsdLayer,
+    PsdLayerError,
 };
 use crate::sections::layer_and_mask_information_section::layers::Layers;
-use crate::sections::PsdCursor;
+use crate::sections::layer_and_mask_information_section::linked_file::{
+    LinkedFile, KEY_LINKED_LAYER, KEY_LINKED_LAYER_2, KEY_LINKED_LAYER_3,
+};
+use crate::sections::layer_and_mask_information_section::mask::LayerMask;
+use crate::sections::layer_and_mask_information_section::metadata::{
+    LayerMetadata, KEY_METADATA_SETTING,
+};
+use crate::sections::layer_and_mask_information_section::pattern::{
+    Pattern, KEY_PATTERNS, KEY_PATTERNS_2, KEY_PATTERNS_3,
+};
+use crate::sections::layer_and_mask_information_section::text::{TextLayer, KEY_TYPE_TOOL};
+use crate::sections::layer_and_mask_information_section::vector_mask::{
+    VectorMask, KEY_VECTOR_MASK, KEY_VECTOR_MASK_2,
+};
+use crate::sections::layer_and_mask_information_section::vector_stroke::{
+    read_vector_fill, VectorStroke, KEY_VECTOR_STROKE, KEY_VECTOR_STROKE_CONTENT,
+};
+use crate::sections::{PsdCursor, PsdCursorError};
+use crate::shared_bytes::SharedBytes;
+use crate::PsdWarning;
 
 /// One of the possible additional layer block signatures
 const SIGNATURE_EIGHT_BIM: [u8; 4] = [56, 66, 73, 77];
@@ -21,10 +52,34 @@ const SIGNATURE_EIGHT_B64: [u8; 4] = [56, 66, 54, 52];
 const KEY_UNICODE_LAYER_NAME: &[u8; 4] = b"luni";
 /// Key of `Section divider setting (Photoshop 6.0)`, "lsct"
 const KEY_SECTION_DIVIDER_SETTING: &[u8; 4] = b"lsct";
-
+/// Key of `Nested section divider setting (Photoshop CS5)`, "lsdk"
+const KEY_NESTED_SECTION_DIVIDER_SETTING: &[u8; 4] = b"lsdk";
+/// Key of `Layer ID`, "lyid"
+const KEY_LAYER_ID: &[u8; 4] = b"lyid";
+/// Key of `Blend interior elements (fill opacity)`, "iOpa"
+const KEY_FILL_OPACITY: &[u8; 4] = b"iOpa";
+/// Key of `Knockout setting`, "knko"
+const KEY_KNOCKOUT_SETTING: &[u8; 4] = b"knko";
+/// Key of `Blend clipping elements`, "clbl"
+const KEY_BLEND_CLIPPING_ELEMENTS: &[u8; 4] = b"clbl";
+/// Key of `Blend interior elements`, "infx"
+const KEY_BLEND_INTERIOR_ELEMENTS: &[u8; 4] = b"infx";
+
+pub mod animation;
+pub mod annotation;
+pub mod effects;
+pub mod fill;
 pub mod groups;
 pub mod layer;
 pub mod layers;
+pub mod linked_file;
+pub mod mask;
+pub mod metadata;
+pub mod pattern;
+mod reorder;
+pub mod text;
+pub mod vector_mask;
+pub mod vector_stroke;
 
 /// The LayerAndMaskInformationSection comes from the bytes in the fourth section of the PSD.
 ///
@@ -57,25 +112,56 @@ pub mod layers;
 pub struct LayerAndMaskInformationSection {
     pub(crate) layers: Layers,
     pub(crate) groups: Groups,
+    pub(crate) patterns: Vec<Pattern>,
+    pub(crate) annotations: Vec<Annotation>,
+    pub(crate) linked_files: Vec<LinkedFile>,
 }
 
+/// The patterns, annotations and linked files that are stored after the layer records
+type GlobalLayerInfo = (Vec<Pattern>, Vec<Annotation>, Vec<LinkedFile>);
+
 /// Frame represents a group stack frame
 #[derive(Debug)]
 struct Frame {
     start_idx: usize,
-    name: String,
+    /// The record of the layer that opened the group, which holds the group's properties
+    layer_record: Option<LayerRecord>,
     group_id: u32,
     parent_group_id: u32,
+    expanded: bool,
 }
 
 impl LayerAndMaskInformationSection {
+    /// Read the number of layer records from the bytes of the layer and mask information
+    /// section (including the length marker), without parsing them.
+    pub(crate) fn layer_count(bytes: &[u8]) -> usize {
+        // The section's length marker and the layer info section's length marker come first
+        match bytes.get(8..10) {
+            Some(count) => i16::from_be_bytes([count[0], count[1]]).unsigned_abs() as usize,
+            None => 0,
+        }
+    }
+
     /// Create a LayerAndMaskInformationSection from the bytes in the corresponding section in a
     /// PSD file (including the length marker).
+    ///
+    /// When recovering, malformed layer records are skipped and added to the warnings instead
+    /// of failing. Fails if the patterns decode to more than `max_decoded_bytes`.
     pub fn from_bytes(
-        bytes: &[u8],
+        bytes: &SharedBytes,
         psd_width: u32,
         psd_height: u32,
+        version: PsdVersion,
+        recover: bool,
+        max_decoded_bytes: u64,
+        warnings: &mut Vec<PsdWarning>,
     ) -> Result<LayerAndMaskInformationSection, PsdLayerError> {
+        span!(
+            DEBUG,
+            "layer_and_mask_information_section",
+            len = bytes.len()
+        );
+
         let mut cursor = PsdCursor::new(bytes);
 
         // The first four bytes of the section is the length marker for the layer and mask
@@ -85,10 +171,11 @@ impl LayerAndMaskInformationSection {
         // the exact number of bytes in the layer and information mask section of the PSD file,
         // so there's no way for us to accidentally read too many bytes. If we did the program
         // would panic.
-        cursor.read_4();
+        cursor.read_4()?;
 
         // Read the next four bytes to get the length of the layer info section.
-        let _layer_info_section_len = cursor.read_u32();
+        let layer_info_section_len = cursor.read_u32()?;
+        let layer_info_section_end = cursor.position() + layer_info_section_len as u64;
 
         // Next 2 bytes is the layer count
         //
@@ -99,26 +186,153 @@ impl LayerAndMaskInformationSection {
         //
         // Layer count. If it is a negative number, its absolute value is the number of layers and
         // the first alpha channel contains the transparency data for the merged result.
-        let layer_count = cursor.read_i16();
+        let layer_count = cursor.read_i16()?;
 
         // TODO: If the layer count was negative we were supposed to treat the first alpha
         // channel as transparency data for the merged result.. So add a new test with a transparent
         // PSD and make sure that we're handling this case properly.
-        let layer_count: u16 = layer_count.abs() as u16;
-        let (group_count, layer_records) =
-            LayerAndMaskInformationSection::read_layer_records(&mut cursor, layer_count)?;
+        let layer_count: u16 = layer_count.unsigned_abs();
+        let (group_count, mut layer_records) = LayerAndMaskInformationSection::read_layer_records(
+            &mut cursor,
+            bytes,
+            layer_count,
+            version,
+            recover,
+            warnings,
+        )?;
+
+        // The layer info section is padded, so we jump to its end instead of relying on
+        // having read every byte.
+        cursor.seek(layer_info_section_end);
+
+        let (patterns, annotations, linked_files) =
+            LayerAndMaskInformationSection::read_global_additional_layer_info(
+                &mut cursor,
+                max_decoded_bytes,
+                warnings,
+            )?;
+
+        // Pattern fill layers refer to their pattern by ID
+        for (layer_record, _) in layer_records.iter_mut() {
+            if let Some(FillLayer::Pattern(pattern_fill)) = &mut layer_record.fill {
+                pattern_fill.pattern = patterns
+                    .iter()
+                    .find(|pattern| pattern.id == pattern_fill.pattern_id)
+                    .cloned();
+            }
+        }
 
         LayerAndMaskInformationSection::decode_layers(
             layer_records,
             group_count,
             (psd_width, psd_height),
+            patterns,
+            annotations,
+            linked_files,
         )
     }
 
+    /// Skip over the global layer mask info and read the series of tagged blocks that follow it.
+    ///
+    /// At the moment we only parse the patterns, annotations and linked files, the other blocks
+    /// are skipped and added to the warnings.
+    fn read_global_additional_layer_info(
+        cursor: &mut PsdCursor,
+        max_decoded_bytes: u64,
+        warnings: &mut Vec<PsdWarning>,
+    ) -> Result<GlobalLayerInfo, PsdLimitError> {
+        let mut patterns = vec![];
+        let mut annotations = vec![];
+        let mut linked_files = vec![];
+        let mut decoded_bytes = 0;
+
+        // We do not currently use the global layer mask info, skip it
+        let global_layer_mask_info_len = match cursor.read_u32() {
+            Ok(len) => len,
+            Err(_) => return Ok((patterns, annotations, linked_files)),
+        };
+        if cursor.read(global_layer_mask_info_len).is_err() {
+            return Ok((patterns, annotations, linked_files));
+        }
+
+        while is_additional_layer_info(cursor) {
+            let (key, bytes) = match read_additional_layer_info(cursor) {
+                Ok(block) => block,
+                Err(_) => break,
+            };
+
+            match &key {
+                KEY_PATTERNS | KEY_PATTERNS_2 | KEY_PATTERNS_3 => {
+                    patterns.extend(Pattern::read_patterns(
+                        bytes,
+                        max_decoded_bytes,
+                        &mut decoded_bytes,
+                    )?);
+                }
+                KEY_ANNOTATIONS => {
+                    annotations.extend(Annotation::read_annotations(bytes));
+                }
+                KEY_LINKED_LAYER | KEY_LINKED_LAYER_2 | KEY_LINKED_LAYER_3 => {
+                    linked_files.extend(LinkedFile::read_linked_files(bytes));
+                }
+                // TODO: Skipping other keys until we implement parsing for them
+                _ => warnings.push(PsdWarning::IgnoredLayerInfo {
+                    layer: None,
+                    key: String::from_utf8_lossy(&key).into_owned(),
+                }),
+            }
+        }
+
+        Ok((patterns, annotations, linked_files))
+    }
+
+    /// Turn the cache of each layer's RGBA pixels on or off, see
+    /// [`crate::PsdOptions::cache_layer_rgba`]
+    pub(crate) fn set_layer_rgba_caching(&mut self, cache: bool) {
+        for layer in self.layers.iter_mut() {
+            layer.cache_rgba = cache;
+        }
+    }
+
+    /// Turn on or off returning fully transparent pixels as `[0, 0, 0, 0]`, see
+    /// [`crate::PsdOptions::clear_transparent_pixels`]
+    pub(crate) fn set_clear_transparent_pixels(&mut self, clear: bool) {
+        for layer in self.layers.iter_mut() {
+            layer.clear_transparent = clear;
+        }
+    }
+
+    /// Turn on or off multiplying each layer's vector mask into its pixels, see
+    /// [`crate::PsdOptions::rasterize_vector_masks`]
+    pub(crate) fn set_vector_mask_rasterization(&mut self, rasterize: bool) {
+        for layer in self.layers.iter_mut() {
+            layer.apply_vector_mask = rasterize;
+        }
+    }
+
+    /// Point the drop shadows that use the global light away from it
+    pub(crate) fn set_global_light_angle(&mut self, angle: f64) {
+        for layer in self.layers.iter_mut() {
+            if let Some(effects) = layer.effects.as_mut() {
+                effects.set_global_light_angle(angle);
+            }
+        }
+    }
+
+    /// Drop the RGBA pixels that the layers have cached
+    pub(crate) fn clear_layer_rgba_caches(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.rgba_cache = Default::default();
+        }
+    }
+
     fn decode_layers(
         layer_records: Vec<(LayerRecord, LayerChannels)>,
         group_count: usize,
         psd_size: (u32, u32),
+        patterns: Vec<Pattern>,
+        annotations: Vec<Annotation>,
+        linked_files: Vec<LinkedFile>,
     ) -> Result<LayerAndMaskInformationSection, PsdLayerError> {
         let mut layers = Layers::with_capacity(layer_records.len());
         let mut groups = Groups::with_capacity(group_count);
@@ -126,9 +340,10 @@ impl LayerAndMaskInformationSection {
         // Create stack with root-level
         let mut stack: Vec<Frame> = vec![Frame {
             start_idx: 0,
-            name: String::from("root"),
+            layer_record: None,
             group_id: 0,
             parent_group_id: 0,
+            expanded: true,
         }];
 
         // Viewed group counter
@@ -144,11 +359,15 @@ impl LayerAndMaskInformationSection {
                 Some(GroupDivider::CloseFolder) | Some(GroupDivider::OpenFolder) => {
                     already_viewed = already_viewed + 1;
 
+                    let expanded =
+                        matches!(layer_record.divider_type, Some(GroupDivider::OpenFolder));
+
                     let frame = Frame {
                         start_idx: layers.len(),
-                        name: layer_record.name,
+                        layer_record: Some(layer_record),
                         group_id: already_viewed,
                         parent_group_id: current_group_id,
+                        expanded,
                     };
 
                     stack.push(frame);
@@ -156,6 +375,10 @@ impl LayerAndMaskInformationSection {
 
                 // close the folder
                 Some(GroupDivider::BoundingSection) => {
+                    // The record that opened this group was skipped while recovering
+                    if stack.len() == 1 {
+                        continue;
+                    }
                     let frame = stack.pop().unwrap();
 
                     let range = Range {
@@ -163,11 +386,15 @@ impl LayerAndMaskInformationSection {
                         end: layers.len(),
                     };
 
-                    groups.push(PsdGroup::new(
-                        frame.name,
+                    // The group's name and properties are stored on the record that opened it,
+                    // not on the hidden bounding section divider.
+                    let group_record = frame.layer_record.as_ref().unwrap_or(&layer_record);
+
+                    let mut group = PsdGroup::new(
+                        group_record.name.clone(),
                         frame.group_id,
                         range,
-                        &layer_record,
+                        group_record,
                         psd_size.0,
                         psd_size.1,
                         if frame.parent_group_id > 0 {
@@ -175,7 +402,11 @@ impl LayerAndMaskInformationSection {
                         } else {
                             None
                         },
-                    ));
+                    );
+                    group.expanded = frame.expanded;
+                    group.update_bounds(&layers[group.contained_layers.clone()]);
+
+                    groups.push(group);
                 }
 
                 _ => {
@@ -191,19 +422,52 @@ impl LayerAndMaskInformationSection {
             };
         }
 
-        Ok(LayerAndMaskInformationSection { layers, groups })
+        Ok(LayerAndMaskInformationSection {
+            layers,
+            groups,
+            patterns,
+            annotations,
+            linked_files,
+        })
     }
 
     fn read_layer_records(
         cursor: &mut PsdCursor,
+        section: &SharedBytes,
         layer_count: u16,
+        version: PsdVersion,
+        recover: bool,
+        warnings: &mut Vec<PsdWarning>,
     ) -> Result<(usize, Vec<(LayerRecord, LayerChannels)>), PsdLayerError> {
         let mut groups_count = 0;
 
-        let mut layer_records = vec![];
+        // Records that were skipped while recovering only keep the length of their channels,
+        // so that we can skip over them too
+        let mut layer_records: Vec<Result<LayerRecord, u64>> = vec![];
         // Read each layer record
-        for _layer_num in 0..layer_count {
-            let layer_record = read_layer_record(cursor)?;
+        for layer_num in 0..layer_count {
+            let start = cursor.position();
+            let mut layer_record = match read_layer_record(cursor, warnings) {
+                Ok(layer_record) => layer_record,
+                Err(error) if recover => {
+                    cursor.seek(start);
+                    let skipped = skip_layer_record(cursor);
+                    warnings.push(PsdWarning::SkippedLayer {
+                        record: layer_num as usize,
+                        error,
+                    });
+
+                    match skipped {
+                        Ok(channels_len) => layer_records.push(Err(channels_len)),
+                        // The record is cut short, so the records after it can't be found
+                        Err(_) => break,
+                    }
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            layer_record.record_range = start as usize..cursor.position() as usize;
 
             match layer_record.divider_type {
                 Some(GroupDivider::BoundingSection) => {
@@ -212,16 +476,47 @@ impl LayerAndMaskInformationSection {
                 _ => {}
             }
 
-            layer_records.push(layer_record);
+            layer_records.push(Ok(layer_record));
         }
 
         let mut result = vec![];
-        for layer_record in layer_records {
-            let channels = read_layer_channels(
-                cursor,
-                &layer_record.channel_data_lengths,
-                layer_record.height() as usize,
-            )?;
+        for (layer_num, layer_record) in layer_records.into_iter().enumerate() {
+            let start = cursor.position();
+            let mut layer_record = match layer_record {
+                Ok(layer_record) => layer_record,
+                Err(channels_len) => {
+                    cursor.seek(start + channels_len);
+                    continue;
+                }
+            };
+            layer_record.channel_data_offset = start as usize;
+
+            let channels = read_layer_channels(cursor, section, &layer_record, version, warnings);
+            let channels = match channels {
+                Ok(channels) => channels,
+                Err(error) if recover => {
+                    // The compression and data of each channel
+                    let channels_len: u64 = layer_record
+                        .channel_data_lengths
+                        .iter()
+                        .map(|(_, len)| *len as u64 + 2)
+                        .sum();
+                    cursor.seek(start + channels_len);
+
+                    if matches!(
+                        layer_record.divider_type,
+                        Some(GroupDivider::BoundingSection)
+                    ) {
+                        groups_count -= 1;
+                    }
+                    warnings.push(PsdWarning::SkippedLayer {
+                        record: layer_num,
+                        error,
+                    });
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
 
             result.push((layer_record, channels));
         }
@@ -247,35 +542,54 @@ impl LayerAndMaskInformationSection {
     }
 }
 
-/// Reads layer channels
+/// Reads layer channels, which share the bytes of the section that the cursor is reading
+///
+/// Channels with unsupported compression are left empty and added to the warnings.
 fn read_layer_channels(
     cursor: &mut PsdCursor,
-    channel_data_lengths: &Vec<(PsdChannelKind, u32)>,
-    scanlines: usize,
+    section: &SharedBytes,
+    layer_record: &LayerRecord,
+    version: PsdVersion,
+    warnings: &mut Vec<PsdWarning>,
 ) -> Result<LayerChannels, PsdLayerError> {
-    let capacity = channel_data_lengths.len();
+    let capacity = layer_record.channel_data_lengths.len();
     let mut channels = HashMap::with_capacity(capacity);
 
-    for (channel_kind, channel_length) in channel_data_lengths.iter() {
-        let compression = cursor.read_u16();
+    for (channel_kind, channel_length) in layer_record.channel_data_lengths.iter() {
+        let compression = cursor.read_u16()?;
         let compression = PsdChannelCompression::new(compression)
             .ok_or(PsdLayerError::InvalidCompression { compression })?;
 
-        let channel_data = cursor.read(*channel_length);
+        // The layer masks have their own rectangles
+        let scanlines = layer_record.channel_height(*channel_kind);
+
+        let start = cursor.position() as usize;
+        cursor.read(*channel_length)?;
+        let channel_data = section.slice(start..start + *channel_length as usize);
+
         let channel_bytes = match compression {
-            PsdChannelCompression::RawData => ChannelBytes::RawData(channel_data.into()),
+            PsdChannelCompression::RawData => ChannelBytes::RawData(channel_data),
             PsdChannelCompression::RleCompressed => {
-                // We're skipping over the bytes that describe the length of each scanline since
-                // we don't currently use them. We might re-think this in the future when we
-                // implement serialization of a Psd back into bytes.. But not a concern at the
-                // moment.
                 // Compressed bytes per scanline are encoded at the beginning as 2 bytes
-                // per scanline
-                let channel_data = &channel_data[2 * scanlines..];
-
-                ChannelBytes::RleCompressed(channel_data.into())
+                // per scanline (4 bytes in a PSB). Layers without any pixels (such as fill
+                // layers) might not have them at all.
+                let count_len = version.rle_byte_count_len();
+                let byte_counts_len = count_len * scanlines;
+                let byte_counts = channel_data.slice(0..byte_counts_len);
+                let channel_data = channel_data.slice(byte_counts_len..channel_data.len());
+
+                ChannelBytes::RleCompressed(channel_data, ScanlineLens::new(byte_counts, count_len))
+            }
+            // We can't decompress zip yet
+            PsdChannelCompression::ZipWithoutPrediction
+            | PsdChannelCompression::ZipWithPrediction => {
+                warnings.push(PsdWarning::UnsupportedLayerCompression {
+                    layer: layer_record.name.clone(),
+                    channel: *channel_kind,
+                    compression,
+                });
+                ChannelBytes::RawData(vec![].into())
             }
-            _ => unimplemented!("Zip compression currently unsupported"),
         };
 
         channels.insert(*channel_kind, channel_bytes);
@@ -284,6 +598,51 @@ fn read_layer_channels(
     Ok(channels)
 }
 
+/// Whether an additional layer information block starts at the cursor
+fn is_additional_layer_info(cursor: &PsdCursor) -> bool {
+    matches!(
+        cursor.peek_4(),
+        Ok(signature) if signature == SIGNATURE_EIGHT_BIM || signature == SIGNATURE_EIGHT_B64
+    )
+}
+
+/// Read the key and data of the additional layer information block at the cursor
+fn read_additional_layer_info<'c>(
+    cursor: &'c mut PsdCursor,
+) -> Result<([u8; 4], &'c [u8]), PsdCursorError> {
+    let _signature = cursor.read_4()?;
+    let mut key = [0; 4];
+    key.copy_from_slice(cursor.read_4()?);
+    let len = cursor.read_u32()?;
+
+    Ok((key, cursor.read(len)?))
+}
+
+/// Skip over a layer record without interpreting it, returning the number of bytes of channel
+/// data that belong to it, or an error if the record is cut short.
+///
+/// Only the lengths that are needed to find the end of the record are read, so this works for
+/// records that [`read_layer_record`] fails on.
+fn skip_layer_record(cursor: &mut PsdCursor) -> Result<u64, PsdCursorError> {
+    // Top, left, bottom and right
+    cursor.read(16)?;
+
+    let channel_count = cursor.read_u16()?;
+    let mut channels_len = 0;
+    for _ in 0..channel_count {
+        let _channel_id = cursor.read_i16()?;
+        channels_len += cursor.read_u32()? as u64;
+    }
+
+    // Blend mode signature and key, opacity, clipping, flags and filler
+    cursor.read(12)?;
+
+    let extra_data_len = cursor.read_u32()?;
+    cursor.read(extra_data_len)?;
+
+    Ok(channels_len)
+}
+
 /// Read bytes, starting from the cursor, until we've processed all of the data for a layer in
 /// the layer records section.
 ///
@@ -310,7 +669,10 @@ fn read_layer_channels(
 /// | Variable               | Layer mask data: See See Layer mask / adjustment layer data for structure. Can be 40 bytes, 24 bytes, or 4 bytes if no layer mask.                                                                                                                                                                                                                                                                                                                                                                                                                                                                |
 /// | Variable               | Layer blending ranges: See See Layer blending ranges data.                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                        |
 /// | Variable               | Layer name: Pascal string, padded to a multiple of 4 bytes.                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                       |
-fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerError> {
+fn read_layer_record(
+    cursor: &mut PsdCursor,
+    warnings: &mut Vec<PsdWarning>,
+) -> Result<LayerRecord, PsdLayerError> {
     let mut channel_data_lengths = vec![];
 
     // FIXME:
@@ -321,30 +683,34 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
     // Photoshop.
 
     // Read the rectangle that encloses the layer mask.
-    let top = cursor.read_i32();
+    let top = cursor.read_i32()?;
 
-    let left = cursor.read_i32();
+    let left = cursor.read_i32()?;
 
     // Subtract one in order to zero index. If a layer is fully transparent it's bottom will
     // already be 0 so we don't subtract
-    let bottom = cursor.read_i32();
+    let bottom = cursor.read_i32()?;
+    // Right and bottom are exclusive, so a layer without any area has them at or before its
+    // top and left.
+    let mut empty = bottom <= top;
     let bottom = if bottom == 0 { 0 } else { bottom - 1 };
 
     // Subtract one in order to zero index. If a layer is fully transparent it's right will
     // already be zero so we don't subtract.
-    let right = cursor.read_i32();
+    let right = cursor.read_i32()?;
+    empty |= right <= left;
     let right = if right == 0 { 0 } else { right - 1 };
 
     // Get the number of channels in the layer
-    let channel_count = cursor.read_u16();
+    let channel_count = cursor.read_u16()?;
 
     // Read the channel information
     for _ in 0..channel_count {
-        let channel_id = cursor.read_i16();
+        let channel_id = cursor.read_i16()?;
         let channel_id =
             PsdChannelKind::new(channel_id).ok_or(PsdLayerError::InvalidChannel { channel_id })?;
 
-        let channel_length = cursor.read_u32();
+        let channel_length = cursor.read_u32()?;
         // The first two bytes encode the compression, the rest of the bytes
         // are the channel data.
         let channel_data_length = channel_length - 2;
@@ -353,18 +719,18 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
     }
 
     // We do not currently parse the blend mode signature, skip it
-    cursor.read_4();
+    cursor.read_4()?;
 
     let mut key = [0; 4];
-    key.copy_from_slice(cursor.read_4());
-    let blend_mode = match BlendMode::match_mode(key) {
+    key.copy_from_slice(cursor.read_4()?);
+    let mut blend_mode = match BlendMode::from_key(key) {
         Some(v) => v,
         None => return Err(PsdLayerError::UnknownBlendingMode { mode: key }),
     };
 
-    let opacity = cursor.read_u8();
+    let opacity = cursor.read_u8()?;
 
-    let clipping_base = cursor.read_u8();
+    let clipping_base = cursor.read_u8()?;
     let clipping_base = clipping_base == 0;
 
     // We do not currently parse all flags, only visible
@@ -374,25 +740,26 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
     //  - bit 2 = obsolete;
     //  - bit 3 = 1 for Photoshop 5.0 and later, tells if bit 4 has useful information;
     //  - bit 4 = pixel data irrelevant to appearance of document
-    let visible = cursor.read_u8() & (1 << 1) != 0; // here we get second bit - visible
+    //
+    // Despite what the docs say, Photoshop sets the second bit when the layer is hidden.
+    let visible = cursor.read_u8()? & (1 << 1) == 0;
 
     // We do not currently parse the filter, skip it
-    cursor.read_1();
+    cursor.read_1()?;
 
     // We do not currently use the length of the extra data field, skip it
-    cursor.read_4();
+    cursor.read_4()?;
 
-    // We do not currently use the layer mask data, skip it
-    let layer_mask_data_len = cursor.read_u32();
-    cursor.read(layer_mask_data_len);
+    let layer_mask_data_len = cursor.read_u32()?;
+    let mask = LayerMask::from_bytes(cursor.read(layer_mask_data_len)?);
 
     // We do not currently use the layer blending range, skip it
-    let layer_blending_range_data_len = cursor.read_u32();
-    cursor.read(layer_blending_range_data_len);
+    let layer_blending_range_data_len = cursor.read_u32()?;
+    cursor.read(layer_blending_range_data_len)?;
 
     // Read the layer name
-    let name_len = cursor.read_u8();
-    let name = cursor.read(name_len as u32);
+    let name_len = cursor.read_u8()?;
+    let name = cursor.read(name_len as u32)?;
     let name = String::from_utf8_lossy(name);
     let mut name = name.to_string();
 
@@ -403,46 +770,164 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
     // The 1 is the 1 byte that we read for the name length
     let bytes_mod_4 = (name_len + 1) % 4;
     let padding = (4 - bytes_mod_4) % 4;
-    cursor.read(padding as u32);
+    cursor.read(padding as u32)?;
 
     let mut divider_type = None;
+    let mut fill = None;
+    let mut vector_stroke = None;
+    let mut vector_fill = None;
+    let mut vector_mask = None;
+    let mut text = None;
+    let mut effects = None;
+    let mut layer_id = None;
+    let mut fill_opacity = 255;
+    let mut metadata = vec![];
+    let mut knockout = Knockout::None;
+    let mut blend_clipped_elements = true;
+    let mut blend_interior_elements = false;
+    let mut ignored_keys = vec![];
+    let mut skipped_keys = vec![];
     // There can be multiple additional layer information sections so we'll loop
     // until we stop seeing them.
-    while cursor.peek_4() == SIGNATURE_EIGHT_BIM || cursor.peek_4() == SIGNATURE_EIGHT_B64 {
-        let _signature = cursor.read_4();
+    while is_additional_layer_info(cursor) {
+        let _signature = cursor.read_4()?;
         let mut key = [0; 4];
-        key.copy_from_slice(cursor.read_4());
-        let additional_layer_info_len = cursor.read_u32();
+        key.copy_from_slice(cursor.read_4()?);
+        let additional_layer_info_len = cursor.read_u32()?;
 
         match &key {
             KEY_UNICODE_LAYER_NAME => {
-                name = cursor.read_unicode_string();
+                name = cursor.read_unicode_string()?;
             }
-            KEY_SECTION_DIVIDER_SETTING => {
-                divider_type = GroupDivider::match_divider(cursor.read_i32());
+            // The nested section divider setting has the same structure as the section
+            // divider setting
+            KEY_SECTION_DIVIDER_SETTING | KEY_NESTED_SECTION_DIVIDER_SETTING => {
+                divider_type = GroupDivider::match_divider(cursor.read_i32()?);
 
-                // data present only if length >= 12
+                // data present only if length >= 12. Groups store their blend mode here, since
+                // the record's blend mode can't be pass through.
                 if additional_layer_info_len >= 12 {
-                    let _signature = cursor.read_4();
-                    let _key = cursor.read_4();
+                    let _signature = cursor.read_4()?;
+                    let mut key = [0; 4];
+                    key.copy_from_slice(cursor.read_4()?);
+                    if let Some(mode) = BlendMode::from_key(key) {
+                        blend_mode = mode;
+                    }
                 }
 
                 // data present only if length >= 16
                 if additional_layer_info_len >= 16 {
-                    cursor.read_4();
+                    cursor.read_4()?;
+                }
+            }
+
+            KEY_LAYER_ID => {
+                layer_id = Some(cursor.read_u32()?);
+            }
+
+            // The fill opacity is followed by three bytes of padding
+            KEY_FILL_OPACITY => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                fill_opacity = bytes.first().copied().unwrap_or(255);
+            }
+
+            // Each of these is a flag followed by three bytes of padding
+            KEY_KNOCKOUT_SETTING => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                knockout = Knockout::from_byte(bytes.first().copied().unwrap_or(0));
+            }
+            KEY_BLEND_CLIPPING_ELEMENTS => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                blend_clipped_elements = bytes.first().map(|flag| *flag != 0).unwrap_or(true);
+            }
+            KEY_BLEND_INTERIOR_ELEMENTS => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                blend_interior_elements = bytes.first().map(|flag| *flag != 0).unwrap_or(false);
+            }
+
+            KEY_METADATA_SETTING => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                metadata = LayerMetadata::read_metadata_setting(bytes);
+            }
+
+            KEY_SOLID_COLOR_FILL | KEY_GRADIENT_FILL | KEY_PATTERN_FILL => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                match FillLayer::from_bytes(&key, bytes) {
+                    Ok(layer_fill) => fill = layer_fill,
+                    Err(error) => skipped_keys.push((key, PsdLayerError::InvalidDescriptor(error))),
+                }
+            }
+
+            KEY_VECTOR_STROKE => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                match VectorStroke::from_bytes(bytes) {
+                    Ok(stroke) => vector_stroke = Some(stroke),
+                    Err(error) => skipped_keys.push((key, PsdLayerError::InvalidDescriptor(error))),
+                }
+            }
+            KEY_VECTOR_STROKE_CONTENT => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                match read_vector_fill(bytes) {
+                    Ok(content) => vector_fill = content,
+                    Err(error) => skipped_keys.push((key, PsdLayerError::InvalidDescriptor(error))),
+                }
+            }
+
+            KEY_VECTOR_MASK | KEY_VECTOR_MASK_2 => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                vector_mask = VectorMask::from_bytes(bytes);
+            }
+
+            KEY_TYPE_TOOL => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                match TextLayer::from_bytes(bytes) {
+                    Ok(layer_text) => text = Some(layer_text),
+                    Err(error) => skipped_keys.push((key, PsdLayerError::InvalidDescriptor(error))),
+                }
+            }
+
+            KEY_OBJECT_EFFECTS => {
+                let bytes = cursor.read(additional_layer_info_len)?;
+                match LayerEffects::from_bytes(bytes) {
+                    Ok(layer_effects) => effects = layer_effects,
+                    Err(error) => skipped_keys.push((key, PsdLayerError::InvalidDescriptor(error))),
                 }
             }
 
             // TODO: Skipping other keys until we implement parsing for them
             _ => {
-                cursor.read(additional_layer_info_len);
+                cursor.read(additional_layer_info_len)?;
+                ignored_keys.push(String::from_utf8_lossy(&key).into_owned());
             }
         }
     }
 
+    // The layer's name can come after the keys that we ignored or couldn't read
+    warnings.extend(
+        ignored_keys
+            .into_iter()
+            .map(|key| PsdWarning::IgnoredLayerInfo {
+                layer: Some(name.clone()),
+                key,
+            }),
+    );
+    warnings.extend(
+        skipped_keys
+            .into_iter()
+            .map(|(key, error)| PsdWarning::SkippedLayerInfo {
+                layer: name.clone(),
+                key: String::from_utf8_lossy(&key).into_owned(),
+                error,
+            }),
+    );
+
     Ok(LayerRecord {
         name,
         channel_data_lengths,
+        // Set by the caller, which knows where the record started
+        record_range: 0..0,
+        // Set once the channel data is reached, after every layer record
+        channel_data_offset: 0,
         top,
         left,
         bottom,
@@ -452,5 +937,19 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
         clipping_base,
         blend_mode,
         divider_type,
+        fill,
+        vector_stroke,
+        vector_fill,
+        vector_mask,
+        text,
+        effects,
+        empty,
+        mask,
+        layer_id,
+        fill_opacity,
+        metadata,
+        knockout,
+        blend_clipped_elements,
+        blend_interior_elements,
     })
 }
@@ -0,0 +1,451 @@
+use crate::color::{cmyk_to_rgb, gray_to_rgb, lab_to_rgb};
+use crate::options::PsdLimitError;
+use crate::prelude::*;
+use crate::psd_channel::rle_decompress;
+use crate::sections::file_header_section::ColorMode;
+use crate::sections::PsdCursor;
+
+/// Key of `Patterns (Photoshop 6.0)`, "Patt"
+pub(super) const KEY_PATTERNS: &[u8; 4] = b"Patt";
+/// Key of `Patterns (Photoshop 6.0 and CS (8.0))`, "Pat2"
+pub(super) const KEY_PATTERNS_2: &[u8; 4] = b"Pat2";
+/// Key of `Patterns (Photoshop CS3)`, "Pat3"
+pub(super) const KEY_PATTERNS_3: &[u8; 4] = b"Pat3";
+
+/// The number of entries in an indexed color table
+const COLOR_TABLE_LEN: usize = 256;
+
+/// A pattern that is stored in the PSD, such as the pattern of a pattern fill layer.
+///
+/// Patterns are stored in the additional layer information at the end of the layer and mask
+/// information section and are referenced by their ID.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 4        | Length of this pattern                                                      |
+/// | 4        | Version ( =1)                                                               |
+/// | 4        | The image mode of the file.                                                 |
+/// | 4        | Point: vertical, 2 bytes and horizontal, 2 bytes                            |
+/// | Variable | Name: Unicode string                                                        |
+/// | Variable | Unique ID for this pattern: Pascal string                                   |
+/// | Variable | Index color table (256 * 3 RGB values): only present when image mode is indexed color |
+/// | Variable | Pattern data as Virtual Memory Array List                                   |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) color_mode: ColorMode,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) rgba: Vec<u8>,
+}
+
+impl Pattern {
+    /// Read all of the patterns in a `Patt`, `Pat2` or `Pat3` block.
+    ///
+    /// Patterns that we can't make sense of (such as an unsupported color mode) are skipped.
+    /// The bytes that the patterns decode to are added to `decoded_bytes`, and the patterns
+    /// fail to be read if that is more than `max_decoded_bytes`.
+    pub(super) fn read_patterns(
+        bytes: &[u8],
+        max_decoded_bytes: u64,
+        decoded_bytes: &mut u64,
+    ) -> Result<Vec<Pattern>, PsdLimitError> {
+        let mut patterns = vec![];
+        let mut start = 0;
+
+        while start + 4 <= bytes.len() {
+            let mut cursor = PsdCursor::new(&bytes[start..]);
+            let pattern_len = match cursor.read_u32() {
+                Ok(pattern_len) => pattern_len as usize,
+                Err(_) => break,
+            };
+
+            let end = start + 4 + pattern_len;
+            if pattern_len == 0 || end > bytes.len() {
+                break;
+            }
+
+            let pattern =
+                Pattern::read_pattern(&bytes[start + 4..end], max_decoded_bytes, decoded_bytes)?;
+            if let Some(pattern) = pattern {
+                patterns.push(pattern);
+            }
+
+            // Each pattern is padded to a multiple of 4 bytes
+            start = (end + 3) & !3;
+        }
+
+        Ok(patterns)
+    }
+
+    fn read_pattern(
+        bytes: &[u8],
+        max_decoded_bytes: u64,
+        decoded_bytes: &mut u64,
+    ) -> Result<Option<Pattern>, PsdLimitError> {
+        let mut cursor = PsdCursor::new(bytes);
+
+        let (mut pattern, color_table) = match Pattern::read_header(&mut cursor) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let array_count = match read_virtual_memory_array_count(&mut cursor) {
+            Some(array_count) => array_count,
+            None => return Ok(None),
+        };
+
+        // Each array decodes to a plane with a byte per pixel, and the planes are combined
+        // into RGBA. Arrays take at least 8 bytes, so there can't be more than fit in the data.
+        let pixel_count = pattern.width as u64 * pattern.height as u64;
+        let plane_count = cursor.capacity_for(array_count, 8) as u64 + 4;
+        *decoded_bytes = decoded_bytes.saturating_add(pixel_count * plane_count);
+        if *decoded_bytes > max_decoded_bytes {
+            return Err(PsdLimitError::TooManyDecodedBytes {
+                bytes: *decoded_bytes,
+                max: max_decoded_bytes,
+            });
+        }
+
+        let planes =
+            read_virtual_memory_arrays(&mut cursor, array_count, pattern.width, pattern.height);
+        pattern.rgba = match planes_to_rgba(
+            pattern.color_mode,
+            color_table.as_deref(),
+            &planes,
+            pattern.width,
+            pattern.height,
+        ) {
+            Some(rgba) => rgba,
+            None => return Ok(None),
+        };
+
+        Ok(Some(pattern))
+    }
+
+    /// Read everything before the pattern's virtual memory array list, returning the pattern
+    /// without its pixels and its indexed color table
+    fn read_header(cursor: &mut PsdCursor) -> Option<(Pattern, Option<Vec<u8>>)> {
+        // Version ( =1)
+        let _version = cursor.read_u32().ok()?;
+        let color_mode = ColorMode::new(cursor.read_u32().ok()? as u8)?;
+
+        let height = cursor.read_u16().ok()? as u32;
+        let width = cursor.read_u16().ok()? as u32;
+
+        let name = cursor.read_unicode_string_padding(1).ok()?;
+        let name = name.trim_end_matches('\0').to_string();
+
+        // Unlike most pascal strings in a PSD the ID is not padded
+        let id_len = cursor.read_u8().ok()?;
+        let id = String::from_utf8_lossy(cursor.read(id_len as u32).ok()?).to_string();
+
+        let color_table = match color_mode {
+            ColorMode::Indexed => Some(cursor.read(COLOR_TABLE_LEN as u32 * 3).ok()?.to_vec()),
+            _ => None,
+        };
+
+        let pattern = Pattern {
+            id,
+            name,
+            color_mode,
+            width,
+            height,
+            rgba: vec![],
+        };
+
+        Some((pattern, color_table))
+    }
+
+    /// The unique ID of the pattern. Pattern fill layers refer to their pattern by this ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The name of the pattern
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The color mode that the pattern was stored in
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// The width of the pattern in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the pattern in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels of the pattern as RGBA, `width * height * 4` bytes long.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Get the RGBA pixel at the given coordinate, repeating the pattern in every direction.
+    pub(crate) fn tiled_pixel(&self, x: i64, y: i64) -> [u8; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0; 4];
+        }
+
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        let idx = (y * self.width as usize + x) * 4;
+
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.rgba[idx..idx + 4]);
+        pixel
+    }
+}
+
+/// Read the header of a virtual memory array list, returning the number of arrays that follow
+/// it.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 4        | Version ( =3)                                                               |
+/// | 4        | Length                                                                      |
+/// | 4 * 4    | Rectangle: top, left, bottom, right                                         |
+/// | 4        | Number of channels                                                          |
+///
+/// Followed by a virtual memory array for each channel, plus one for a user mask and one for a
+/// sheet mask.
+fn read_virtual_memory_array_count(cursor: &mut PsdCursor) -> Option<usize> {
+    let _version = cursor.read_u32().ok()?;
+    let _length = cursor.read_u32().ok()?;
+
+    // We use the dimensions from the pattern's point instead of this rectangle
+    cursor.read(16).ok()?;
+
+    let channel_count = cursor.read_u32().ok()? as usize;
+    Some(channel_count.saturating_add(2))
+}
+
+/// Read the arrays of a virtual memory array list into one 8 bit plane per array. Arrays that
+/// aren't written are `None`.
+fn read_virtual_memory_arrays(
+    cursor: &mut PsdCursor,
+    array_count: usize,
+    width: u32,
+    height: u32,
+) -> Vec<Option<Vec<u8>>> {
+    // Each array has at least the flag that says whether it is written and its length
+    let mut planes = Vec::with_capacity(cursor.capacity_for(array_count, 8));
+    for _ in 0..array_count {
+        if cursor.position() + 8 > cursor.get_ref().len() as u64 {
+            break;
+        }
+
+        planes.push(read_virtual_memory_array(cursor, width, height));
+    }
+
+    planes
+}
+
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 4        | Boolean indicating whether array is written, skip following data if 0.      |
+/// | 4        | Length, skip following data if 0.                                           |
+/// | 4        | Pixel depth: 1, 8, 16 or 32                                                 |
+/// | 4 * 4    | Rectangle: top, left, bottom, right                                         |
+/// | 2        | Pixel depth: 1, 8, 16 or 32                                                 |
+/// | 1        | Compression mode of data to follow. 0 = raw, 1 = RLE                        |
+/// | Variable | Actual data based on parameters and compression                             |
+fn read_virtual_memory_array(cursor: &mut PsdCursor, width: u32, height: u32) -> Option<Vec<u8>> {
+    let is_written = cursor.read_u32().ok()?;
+    if is_written == 0 {
+        return None;
+    }
+
+    let length = cursor.read_u32().ok()?;
+    if length == 0 {
+        return None;
+    }
+
+    let data = cursor.read(length).ok()?;
+    let mut array = PsdCursor::new(data);
+
+    let _depth = array.read_u32().ok()?;
+    array.read(16).ok()?;
+    let depth = array.read_u16().ok()?;
+    let compression = array.read_u8().ok()?;
+
+    let remaining = (data.len() as u64).checked_sub(array.position())?;
+    let data = array.read(remaining as u32).ok()?;
+
+    let data = match compression {
+        0 => data.to_vec(),
+        // Compressed bytes per scanline are encoded at the beginning as 2 bytes per scanline
+        1 => rle_decompress(data.get(2 * height as usize..).unwrap_or(&[])),
+        _ => return None,
+    };
+
+    let mut plane: Vec<u8> = match depth {
+        8 => data,
+        // Keep the most significant byte
+        16 => data.iter().step_by(2).copied().collect(),
+        32 => data
+            .chunks_exact(4)
+            .map(|bytes| {
+                let value = f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (value.clamp(0., 1.) * 255.).round() as u8
+            })
+            .collect(),
+        _ => return None,
+    };
+
+    // Arrays that don't hold a value for every pixel of the pattern are malformed
+    let pixel_count = width as usize * height as usize;
+    if plane.len() < pixel_count {
+        return None;
+    }
+
+    plane.truncate(pixel_count);
+    Some(plane)
+}
+
+/// Combine the planes of a pattern into RGBA pixels
+fn planes_to_rgba(
+    color_mode: ColorMode,
+    color_table: Option<&[u8]>,
+    planes: &[Option<Vec<u8>>],
+    width: u32,
+    height: u32,
+) -> Option<Vec<u8>> {
+    let pixel_count = (width * height) as usize;
+
+    let color_channel_count = match color_mode {
+        ColorMode::Rgb | ColorMode::Lab => 3,
+        ColorMode::Cmyk => 4,
+        _ => 1,
+    };
+
+    let mut color_planes = Vec::with_capacity(color_channel_count);
+    for plane in planes.iter().take(color_channel_count) {
+        color_planes.push(plane.as_ref()?);
+    }
+    if color_planes.len() < color_channel_count {
+        return None;
+    }
+
+    // An extra channel after the color channels holds the transparency, otherwise
+    // the user mask does (if it was written).
+    let alpha = planes.iter().skip(color_channel_count).flatten().next();
+
+    let mut rgba = Vec::with_capacity(pixel_count * 4);
+    for idx in 0..pixel_count {
+        let [r, g, b] = match (color_mode, color_table) {
+            (ColorMode::Rgb, _) => [
+                color_planes[0][idx],
+                color_planes[1][idx],
+                color_planes[2][idx],
+            ],
+            (ColorMode::Indexed, Some(table)) => {
+                let entry = color_planes[0][idx] as usize;
+                [
+                    table[entry],
+                    table[COLOR_TABLE_LEN + entry],
+                    table[2 * COLOR_TABLE_LEN + entry],
+                ]
+            }
+            (ColorMode::Cmyk, _) => cmyk_to_rgb([
+                color_planes[0][idx],
+                color_planes[1][idx],
+                color_planes[2][idx],
+                color_planes[3][idx],
+            ]),
+            (ColorMode::Lab, _) => lab_to_rgb([
+                color_planes[0][idx],
+                color_planes[1][idx],
+                color_planes[2][idx],
+            ]),
+            _ => gray_to_rgb(color_planes[0][idx]),
+        };
+
+        let a = alpha.map(|alpha| alpha[idx]).unwrap_or(255);
+
+        rgba.extend_from_slice(&[r, g, b, a]);
+    }
+
+    Some(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grayscale pattern with a single raw 8 bit array holding the given bytes
+    fn grayscale_pattern(width: u16, height: u16, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(&(ColorMode::Grayscale as u32).to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        // An empty name and a one character ID
+        bytes.extend_from_slice(&[0, 0, 0, 0, 1, b'a']);
+
+        // The virtual memory array list's version, length, rectangle and number of channels
+        bytes.extend_from_slice(&3u32.to_be_bytes());
+        bytes.extend_from_slice(&[0; 4 + 16]);
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+
+        let mut array = vec![];
+        array.extend_from_slice(&8u32.to_be_bytes());
+        array.extend_from_slice(&[0; 16]);
+        array.extend_from_slice(&8u16.to_be_bytes());
+        array.push(0);
+        array.extend_from_slice(pixels);
+
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(&(array.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&array);
+
+        bytes
+    }
+
+    fn read_pattern(bytes: &[u8]) -> Result<Option<Pattern>, PsdLimitError> {
+        Pattern::read_pattern(bytes, u64::MAX, &mut 0)
+    }
+
+    #[test]
+    fn reads_plane_with_every_pixel() {
+        let pattern = read_pattern(&grayscale_pattern(2, 1, &[10, 20]))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(pattern.rgba(), [10, 10, 10, 255, 20, 20, 20, 255]);
+    }
+
+    /// Arrays with fewer bytes than the pattern's pixels are skipped instead of being padded,
+    /// so a pattern that claims to be huge doesn't allocate a plane for every pixel.
+    #[test]
+    fn skips_plane_that_is_cut_short() {
+        assert_eq!(read_pattern(&grayscale_pattern(2, 1, &[10])), Ok(None));
+        assert_eq!(
+            read_pattern(&grayscale_pattern(u16::MAX, u16::MAX, &[10, 20])),
+            Ok(None)
+        );
+    }
+
+    /// The limit is checked before any of the planes are decoded
+    #[test]
+    fn fails_when_pattern_decodes_to_more_than_limit() {
+        // A plane for the channel, the user mask and the sheet mask plus the RGBA for each of
+        // the 2 pixels
+        assert_eq!(
+            Pattern::read_pattern(&grayscale_pattern(2, 1, &[10, 20]), 13, &mut 0),
+            Err(PsdLimitError::TooManyDecodedBytes { bytes: 14, max: 13 })
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::sections::layer_and_mask_information_section::groups::Groups;
+use crate::sections::layer_and_mask_information_section::layers::Layers;
+use crate::sections::layer_and_mask_information_section::LayerAndMaskInformationSection;
+use crate::PsdLayer;
+
+/// The layers and groups as a tree, so that they can be moved around without having to keep
+/// every group's range of layers up to date along the way.
+enum Node {
+    Layer(Box<PsdLayer>),
+    Group(u32, Vec<Node>),
+}
+
+impl LayerAndMaskInformationSection {
+    /// Move the layer at `idx` so that it ends up at `new_idx`, inside of the group of the layer
+    /// that it is placed above.
+    pub(crate) fn move_layer(&mut self, idx: usize, new_idx: usize) {
+        let len = self.layers.len();
+        assert!(
+            idx < len,
+            "layer index {} is out of bounds for {} layers",
+            idx,
+            len
+        );
+        assert!(
+            new_idx < len,
+            "layer index {} is out of bounds for {} layers",
+            new_idx,
+            len
+        );
+
+        if idx == new_idx {
+            return;
+        }
+
+        let mut tree = self.take_tree();
+        let layer = remove_layer(&mut tree, idx);
+
+        if new_idx < len - 1 {
+            // Above the layer that is currently at `new_idx`
+            let mut path = layer_path(&tree, new_idx).unwrap();
+            let pos = path.pop().unwrap();
+            children_at(&mut tree, &path).insert(pos, layer);
+        } else {
+            // Below the layer that is currently last
+            let mut path = layer_path(&tree, len - 2).unwrap();
+            let pos = path.pop().unwrap();
+            children_at(&mut tree, &path).insert(pos + 1, layer);
+        }
+
+        self.put_tree(tree);
+    }
+
+    /// Move the layer at `idx` to the top of the group, or to the top of the PSD if `group_id`
+    /// is `None`.
+    pub(crate) fn set_parent(&mut self, idx: usize, group_id: Option<u32>) {
+        let len = self.layers.len();
+        assert!(
+            idx < len,
+            "layer index {} is out of bounds for {} layers",
+            idx,
+            len
+        );
+
+        if let Some(group_id) = group_id {
+            assert!(
+                self.groups.contains_key(&group_id),
+                "there is no group with the ID {}",
+                group_id
+            );
+        }
+
+        let mut tree = self.take_tree();
+        let layer = remove_layer(&mut tree, idx);
+
+        let children = match group_id.and_then(|group_id| group_path(&tree, group_id)) {
+            Some(path) => {
+                match &mut children_at(&mut tree, &path[..path.len() - 1])[path[path.len() - 1]] {
+                    Node::Group(_, children) => children,
+                    Node::Layer(_) => unreachable!("paths to groups end at a group"),
+                }
+            }
+            None => &mut tree,
+        };
+        children.insert(0, layer);
+
+        self.put_tree(tree);
+    }
+
+    fn take_tree(&mut self) -> Vec<Node> {
+        let layers = core::mem::replace(&mut self.layers, Layers::new()).into_items();
+        let mut layers: Vec<Option<PsdLayer>> = layers.into_iter().map(Some).collect();
+
+        let range = 0..layers.len();
+        build_tree(&mut layers, &self.groups, None, range)
+    }
+
+    fn put_tree(&mut self, tree: Vec<Node>) {
+        let mut layers = vec![];
+        let mut group_ids_in_order = vec![];
+        flatten_tree(
+            tree,
+            None,
+            &mut layers,
+            &mut self.groups,
+            &mut group_ids_in_order,
+        );
+
+        self.layers = Layers::with_capacity(layers.len());
+        for layer in layers {
+            self.layers.push(layer.name().to_string(), layer);
+        }
+        self.groups.set_group_ids_in_order(group_ids_in_order);
+    }
+}
+
+/// Build the nodes of the layers within the range that are inside of the parent group.
+fn build_tree(
+    layers: &mut [Option<PsdLayer>],
+    groups: &Groups,
+    parent_id: Option<u32>,
+    range: Range<usize>,
+) -> Vec<Node> {
+    // Empty groups come before a group that starts at the same layer, and the IDs keep the
+    // order the same no matter what order the groups are stored in
+    let mut child_groups: Vec<_> = groups
+        .values()
+        .filter(|group| group.parent_id() == parent_id)
+        .collect();
+    child_groups.sort_by_key(|group| {
+        let contained_layers = &group.contained_layers;
+        (
+            contained_layers.start,
+            !contained_layers.is_empty(),
+            group.id,
+        )
+    });
+
+    let mut child_groups = child_groups.into_iter().peekable();
+    let mut nodes = vec![];
+    let mut idx = range.start;
+
+    loop {
+        while let Some(group) = child_groups.next_if(|group| group.contained_layers.start <= idx) {
+            let contained_layers = group.contained_layers.clone();
+            let children = build_tree(layers, groups, Some(group.id), contained_layers.clone());
+
+            nodes.push(Node::Group(group.id, children));
+            idx = idx.max(contained_layers.end);
+        }
+
+        if idx >= range.end {
+            break;
+        }
+
+        if let Some(layer) = layers[idx].take() {
+            nodes.push(Node::Layer(Box::new(layer)));
+        }
+        idx += 1;
+    }
+
+    nodes
+}
+
+/// Put the layers back in order, updating each group's range of layers and each layer's and
+/// group's parent along the way.
+fn flatten_tree(
+    nodes: Vec<Node>,
+    parent_id: Option<u32>,
+    layers: &mut Vec<PsdLayer>,
+    groups: &mut Groups,
+    group_ids_in_order: &mut Vec<u32>,
+) {
+    for node in nodes {
+        match node {
+            Node::Layer(mut layer) => {
+                layer.layer_properties.group_id = parent_id;
+                layers.push(*layer);
+            }
+            Node::Group(id, children) => {
+                let start = layers.len();
+                flatten_tree(children, Some(id), layers, groups, group_ids_in_order);
+
+                if let Some(group) = groups.get_mut(id) {
+                    group.contained_layers = start..layers.len();
+                    group.layer_properties.group_id = parent_id;
+                    group.update_bounds(&layers[start..]);
+                }
+                // Groups are closed after the groups that are nested inside of them
+                group_ids_in_order.push(id);
+            }
+        }
+    }
+}
+
+/// The positions of the nodes that lead to the layer at the index.
+fn layer_path(nodes: &[Node], idx: usize) -> Option<Vec<usize>> {
+    fn find(nodes: &[Node], remaining: &mut usize, path: &mut Vec<usize>) -> bool {
+        for (pos, node) in nodes.iter().enumerate() {
+            path.push(pos);
+            match node {
+                Node::Layer(_) if *remaining == 0 => return true,
+                Node::Layer(_) => *remaining -= 1,
+                Node::Group(_, children) => {
+                    if find(children, remaining, path) {
+                        return true;
+                    }
+                }
+            }
+            path.pop();
+        }
+
+        false
+    }
+
+    let mut path = vec![];
+    let mut remaining = idx;
+    find(nodes, &mut remaining, &mut path).then_some(path)
+}
+
+/// The positions of the nodes that lead to the group.
+fn group_path(nodes: &[Node], group_id: u32) -> Option<Vec<usize>> {
+    for (pos, node) in nodes.iter().enumerate() {
+        if let Node::Group(id, children) = node {
+            if *id == group_id {
+                return Some(vec![pos]);
+            }
+
+            if let Some(mut path) = group_path(children, group_id) {
+                path.insert(0, pos);
+                return Some(path);
+            }
+        }
+    }
+
+    None
+}
+
+/// The children of the group at the end of the path, or the top level nodes for an empty path.
+fn children_at<'a>(mut nodes: &'a mut Vec<Node>, path: &[usize]) -> &'a mut Vec<Node> {
+    for pos in path {
+        nodes = match &mut nodes[*pos] {
+            Node::Group(_, children) => children,
+            Node::Layer(_) => unreachable!("paths only go through groups"),
+        };
+    }
+
+    nodes
+}
+
+fn remove_layer(tree: &mut Vec<Node>, idx: usize) -> Node {
+    let mut path = layer_path(tree, idx).unwrap();
+    let pos = path.pop().unwrap();
+
+    children_at(tree, &path).remove(pos)
+}
@@ -0,0 +1,216 @@
+use crate::prelude::*;
+use crate::sections::image_resources_section::{
+    DescriptorStructure, ImageResourcesDescriptorError,
+};
+use crate::sections::PsdCursor;
+
+/// Key of `Type tool object setting (Photoshop 6.0)`, "TySh"
+pub(super) const KEY_TYPE_TOOL: &[u8; 4] = b"TySh";
+
+/// The version, transform, text version and descriptor version that come before the text
+/// descriptor
+const TEXT_HEADER_LEN: usize = 2 + 6 * 8 + 2 + 4;
+
+/// The text of a type layer, along with where Photoshop placed it.
+///
+/// The layer's pixels hold the text as Photoshop rendered it. To render the text yourself, lay
+/// it out from the origin and then move it onto the canvas with the [`TextLayer::transform`],
+/// after bending it with the [`TextLayer::warp`].
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Type tool object setting (Photoshop 6.0)
+///
+/// | Length   | Description                                                         |
+/// |----------|---------------------------------------------------------------------|
+/// | 2        | Version ( = 1 for Photoshop 6.0)                                    |
+/// | 6 * 8    | Transform: xx, xy, yx, yy, tx, and ty respectively.                 |
+/// | 2        | Text version ( = 50 for Photoshop 6.0)                              |
+/// | 4        | Descriptor version ( = 16 for Photoshop 6.0)                        |
+/// | Variable | Text data (see See Descriptor structure)                            |
+/// | 2        | Warp version ( = 1 for Photoshop 6.0)                               |
+/// | 4        | Descriptor version ( = 16 for Photoshop 6.0)                        |
+/// | Variable | Warp data (see See Descriptor structure)                            |
+/// | 4 * 8    | Left, top, right, bottom                                            |
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayer {
+    pub(crate) text: String,
+    pub(crate) transform: [f64; 6],
+    pub(crate) warp: TextWarp,
+}
+
+/// How the text of a type layer is bent, from Photoshop's Warp Text dialog
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextWarp {
+    pub(crate) style: WarpStyle,
+    pub(crate) bend: f64,
+    pub(crate) horizontal_distortion: f64,
+    pub(crate) vertical_distortion: f64,
+    pub(crate) vertical: bool,
+}
+
+/// The shape that text is warped into
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum WarpStyle {
+    /// The text isn't warped
+    None,
+    Arc,
+    ArcLower,
+    ArcUpper,
+    Arch,
+    Bulge,
+    ShellLower,
+    ShellUpper,
+    Flag,
+    Wave,
+    Fish,
+    Rise,
+    FishEye,
+    Inflate,
+    Squeeze,
+    Twist,
+    /// Warped with a mesh, which isn't described by the other settings of the warp
+    Custom,
+}
+
+impl TextLayer {
+    /// Read the `TySh` additional layer information block.
+    pub(super) fn from_bytes(bytes: &[u8]) -> Result<TextLayer, ImageResourcesDescriptorError> {
+        if bytes.len() < TEXT_HEADER_LEN {
+            return Ok(TextLayer {
+                text: String::new(),
+                transform: [1., 0., 0., 1., 0., 0.],
+                warp: TextWarp::default(),
+            });
+        }
+
+        let mut cursor = PsdCursor::new(bytes);
+
+        // Version ( = 1)
+        let _version = cursor.read_u16()?;
+        let mut transform = [0.; 6];
+        for value in transform.iter_mut() {
+            *value = cursor.read_f64()?;
+        }
+        // Text version ( = 50) and descriptor version ( = 16)
+        let _text_version = cursor.read_u16()?;
+        let _descriptor_version = cursor.read_u32()?;
+        let text = DescriptorStructure::read_descriptor_structure(&mut cursor)?;
+
+        // Warp version ( = 1) and descriptor version ( = 16). Older files might stop before the
+        // warp.
+        let warp = if bytes.len() as u64 >= cursor.position() + 6 {
+            let _warp_version = cursor.read_u16()?;
+            let _descriptor_version = cursor.read_u32()?;
+            let warp = DescriptorStructure::read_descriptor_structure(&mut cursor)?;
+
+            TextWarp::from_descriptor(&warp)
+        } else {
+            TextWarp::default()
+        };
+
+        Ok(TextLayer {
+            text: text.string("Txt ").unwrap_or("").to_string(),
+            transform,
+            warp,
+        })
+    }
+
+    /// The text of the layer. Photoshop separates paragraphs with a carriage return (`\r`).
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The affine transform from the text's own coordinates to the canvas, as
+    /// `[xx, xy, yx, yy, tx, ty]`.
+    ///
+    /// The origin of the text is on the baseline at the start of the first line, and
+    /// `(tx, ty)` is where that ends up on the canvas. The other values scale, rotate and skew
+    /// the text.
+    pub fn transform(&self) -> [f64; 6] {
+        self.transform
+    }
+
+    /// Move a point from the text's own coordinates onto the canvas, using the
+    /// [`TextLayer::transform`].
+    pub fn transform_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let [xx, xy, yx, yy, tx, ty] = self.transform;
+
+        (xx * x + yx * y + tx, xy * x + yy * y + ty)
+    }
+
+    /// How the text is bent
+    pub fn warp(&self) -> &TextWarp {
+        &self.warp
+    }
+}
+
+impl TextWarp {
+    fn from_descriptor(descriptor: &DescriptorStructure) -> TextWarp {
+        let style = match descriptor.enumerated("warpStyle") {
+            Some(b"warpArc") => WarpStyle::Arc,
+            Some(b"warpArcLower") => WarpStyle::ArcLower,
+            Some(b"warpArcUpper") => WarpStyle::ArcUpper,
+            Some(b"warpArch") => WarpStyle::Arch,
+            Some(b"warpBulge") => WarpStyle::Bulge,
+            Some(b"warpShellLower") => WarpStyle::ShellLower,
+            Some(b"warpShellUpper") => WarpStyle::ShellUpper,
+            Some(b"warpFlag") => WarpStyle::Flag,
+            Some(b"warpWave") => WarpStyle::Wave,
+            Some(b"warpFish") => WarpStyle::Fish,
+            Some(b"warpRise") => WarpStyle::Rise,
+            Some(b"warpFisheye") => WarpStyle::FishEye,
+            Some(b"warpInflate") => WarpStyle::Inflate,
+            Some(b"warpSqueeze") => WarpStyle::Squeeze,
+            Some(b"warpTwist") => WarpStyle::Twist,
+            Some(b"warpCustom") => WarpStyle::Custom,
+            _ => WarpStyle::None,
+        };
+
+        TextWarp {
+            style,
+            bend: descriptor.number("warpValue").unwrap_or(0.),
+            horizontal_distortion: descriptor.number("warpPerspective").unwrap_or(0.),
+            vertical_distortion: descriptor.number("warpPerspectiveOther").unwrap_or(0.),
+            vertical: descriptor.enumerated("warpRotate") == Some(b"Vrtc"),
+        }
+    }
+
+    /// The shape that the text is warped into
+    pub fn style(&self) -> WarpStyle {
+        self.style
+    }
+
+    /// How much the text is bent, as a percentage from -100 to 100
+    pub fn bend(&self) -> f64 {
+        self.bend
+    }
+
+    /// How much the text is distorted horizontally, as a percentage from -100 to 100
+    pub fn horizontal_distortion(&self) -> f64 {
+        self.horizontal_distortion
+    }
+
+    /// How much the text is distorted vertically, as a percentage from -100 to 100
+    pub fn vertical_distortion(&self) -> f64 {
+        self.vertical_distortion
+    }
+
+    /// Whether the text is bent along the vertical axis instead of the horizontal one
+    pub fn vertical(&self) -> bool {
+        self.vertical
+    }
+}
+
+impl Default for TextWarp {
+    fn default() -> Self {
+        TextWarp {
+            style: WarpStyle::None,
+            bend: 0.,
+            horizontal_distortion: 0.,
+            vertical_distortion: 0.,
+            vertical: false,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::geometry::Rect;
+use crate::prelude::*;
+use crate::sections::image_resources_section::{
+    rasterize_subpaths, rasterize_subpaths_region, PsdPath, Subpath,
+};
+use crate::sections::PsdCursor;
+
+/// Key of `Vector mask setting (Photoshop 6.0)`, "vmsk"
+pub(super) const KEY_VECTOR_MASK: &[u8; 4] = b"vmsk";
+/// Key of `Vector mask setting (Photoshop CS6)`, "vsms", which has the same structure
+pub(super) const KEY_VECTOR_MASK_2: &[u8; 4] = b"vsms";
+
+/// The vector mask of a layer, such as the outline of a shape layer.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Vector mask setting
+///
+/// | Length   | Description                                                             |
+/// |----------|-------------------------------------------------------------------------|
+/// | 4        | Version ( = 3 for Photoshop 6.0)                                        |
+/// | 4        | Flags. bit 1 = invert, bit 2 = not link, bit 3 = disable                |
+/// | Variable | Path records, in the same format as the path resource (see [`PsdPath`]) |
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorMask {
+    pub(crate) inverted: bool,
+    pub(crate) linked: bool,
+    pub(crate) disabled: bool,
+    pub(crate) subpaths: Vec<Subpath>,
+    pub(crate) fill_starts_with_all_pixels: bool,
+}
+
+impl VectorMask {
+    /// Read the `vmsk` or `vsms` additional layer information block.
+    ///
+    /// Returns `None` if the block is too short to hold the version and flags.
+    pub(super) fn from_bytes(bytes: &[u8]) -> Option<VectorMask> {
+        if bytes.len() < 8 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(bytes);
+        let _version = cursor.read_u32().ok()?;
+        let flags = cursor.read_u32().ok()?;
+
+        let path = PsdPath::from_bytes(0, String::new(), &bytes[8..]);
+
+        Some(VectorMask {
+            inverted: flags & 1 != 0,
+            linked: flags & (1 << 1) == 0,
+            disabled: flags & (1 << 2) != 0,
+            subpaths: path.subpaths,
+            fill_starts_with_all_pixels: path.fill_starts_with_all_pixels,
+        })
+    }
+
+    /// Whether the mask shows the outside of its path instead of the inside
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Whether the mask moves along with the layer
+    pub fn is_linked(&self) -> bool {
+        self.linked
+    }
+
+    /// If true, the mask has been disabled and does not affect the layer
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    /// The subpaths that make up the mask's path.
+    ///
+    /// Like the points of a [`PsdPath`], the points are relative to the size of the document.
+    pub fn subpaths(&self) -> &[Subpath] {
+        &self.subpaths
+    }
+
+    /// Whether filling the path starts with all pixels shown, so that the path hides the pixels
+    /// within it
+    pub fn fill_starts_with_all_pixels(&self) -> bool {
+        self.fill_starts_with_all_pixels
+    }
+
+    /// Rasterize the mask into one byte per pixel of a `width` x `height` document, from 0
+    /// (hidden) to 255 (shown), anti-aliasing its edges.
+    ///
+    /// The subpaths are combined with each other, since the operation (such as subtracting or
+    /// intersecting) that Photoshop combines them with isn't read. Whether the mask is disabled
+    /// is up to the caller.
+    pub fn rasterize(&self, width: u32, height: u32) -> Vec<u8> {
+        let coverage = rasterize_subpaths(&self.subpaths, width, height);
+
+        self.apply_fill_rule(coverage)
+    }
+
+    /// Like [`VectorMask::rasterize`], but only the pixels of the document within the region,
+    /// with one byte per pixel of the region
+    pub(crate) fn rasterize_region(&self, (width, height): (u32, u32), region: Rect) -> Vec<u8> {
+        let coverage = rasterize_subpaths_region(&self.subpaths, (width, height), region);
+
+        self.apply_fill_rule(coverage)
+    }
+
+    /// Flip the coverage when the pixels outside of the path are the ones that are shown
+    fn apply_fill_rule(&self, mut coverage: Vec<u8>) -> Vec<u8> {
+        if self.inverted != self.fill_starts_with_all_pixels {
+            for value in coverage.iter_mut() {
+                *value = 255 - *value;
+            }
+        }
+
+        coverage
+    }
+}
@@ -0,0 +1,236 @@
+use crate::prelude::*;
+use crate::sections::image_resources_section::{
+    DescriptorField, DescriptorStructure, ImageResourcesDescriptorError,
+};
+use crate::sections::layer_and_mask_information_section::fill::{
+    FillLayer, KEY_GRADIENT_FILL, KEY_PATTERN_FILL, KEY_SOLID_COLOR_FILL,
+};
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::sections::PsdCursor;
+
+/// Key of `Vector stroke data (Photoshop CS6)`, "vstk"
+pub(super) const KEY_VECTOR_STROKE: &[u8; 4] = b"vstk";
+/// Key of `Vector stroke content data (Photoshop CS6)`, "vscg"
+pub(super) const KEY_VECTOR_STROKE_CONTENT: &[u8; 4] = b"vscg";
+
+/// The stroke of a shape layer, along with whether the shape is filled.
+///
+/// Together with the shape's path and its fill (see [`crate::PsdLayer::vector_fill`]) this
+/// describes the shape the way that an SVG would.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Vector stroke data is stored in the additional layer information as a 4 byte version
+/// ( = 16) followed by a descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorStroke {
+    pub(crate) stroke_enabled: bool,
+    pub(crate) fill_enabled: bool,
+    pub(crate) width: f64,
+    pub(crate) dash_offset: f64,
+    pub(crate) miter_limit: f64,
+    pub(crate) line_cap: LineCap,
+    pub(crate) line_join: LineJoin,
+    pub(crate) alignment: StrokeAlignment,
+    pub(crate) dash_pattern: Vec<f64>,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) opacity: u8,
+    pub(crate) content: Option<FillLayer>,
+}
+
+/// The shape of the ends of a stroke
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// The shape of the corners of a stroke
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[allow(missing_docs)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// Where a stroke sits compared to the shape's path
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StrokeAlignment {
+    /// Within the shape
+    Inside,
+    /// Centered on the path
+    Center,
+    /// Outside of the shape
+    Outside,
+}
+
+impl VectorStroke {
+    /// Read the `vstk` additional layer information block.
+    pub(super) fn from_bytes(bytes: &[u8]) -> Result<VectorStroke, ImageResourcesDescriptorError> {
+        let mut cursor = PsdCursor::new(bytes);
+
+        // Version ( = 16)
+        let _version = cursor.read_u32()?;
+        let descriptor = DescriptorStructure::read_descriptor_structure(&mut cursor)?;
+
+        let line_cap = match descriptor.enumerated("strokeStyleLineCapType") {
+            Some(b"strokeStyleRoundCap") => LineCap::Round,
+            Some(b"strokeStyleSquareCap") => LineCap::Square,
+            _ => LineCap::Butt,
+        };
+        let line_join = match descriptor.enumerated("strokeStyleLineJoinType") {
+            Some(b"strokeStyleRoundJoin") => LineJoin::Round,
+            Some(b"strokeStyleBevelJoin") => LineJoin::Bevel,
+            _ => LineJoin::Miter,
+        };
+        let alignment = match descriptor.enumerated("strokeStyleLineAlignment") {
+            Some(b"strokeStyleAlignCenter") => StrokeAlignment::Center,
+            Some(b"strokeStyleAlignOutside") => StrokeAlignment::Outside,
+            _ => StrokeAlignment::Inside,
+        };
+
+        let dash_pattern = descriptor
+            .list("strokeStyleLineDashSet")
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|dash| match dash {
+                DescriptorField::UnitFloat(dash) => Some(dash.value()),
+                DescriptorField::Double(dash) => Some(*dash),
+                _ => None,
+            })
+            .collect();
+
+        let blend_mode = descriptor
+            .enumerated("strokeStyleBlendMode")
+            .and_then(BlendMode::from_descriptor_key)
+            .unwrap_or(BlendMode::Normal);
+        let opacity = descriptor.number("strokeStyleOpacity").unwrap_or(100.);
+
+        let content = descriptor
+            .descriptor("strokeStyleContent")
+            .and_then(read_content);
+
+        Ok(VectorStroke {
+            stroke_enabled: descriptor.boolean("strokeEnabled").unwrap_or(true),
+            fill_enabled: descriptor.boolean("fillEnabled").unwrap_or(true),
+            width: descriptor.number("strokeStyleLineWidth").unwrap_or(1.),
+            dash_offset: descriptor.number("strokeStyleLineDashOffset").unwrap_or(0.),
+            miter_limit: descriptor.number("strokeStyleMiterLimit").unwrap_or(100.),
+            line_cap,
+            line_join,
+            alignment,
+            dash_pattern,
+            blend_mode,
+            opacity: (opacity / 100. * 255.).round().clamp(0., 255.) as u8,
+            content,
+        })
+    }
+
+    /// Whether the shape is stroked. Photoshop keeps the stroke's settings when it is turned
+    /// off.
+    pub fn stroke_enabled(&self) -> bool {
+        self.stroke_enabled
+    }
+
+    /// Whether the shape is filled with its [`crate::PsdLayer::vector_fill`]
+    pub fn fill_enabled(&self) -> bool {
+        self.fill_enabled
+    }
+
+    /// The width of the stroke, in pixels
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// How far into the dash pattern the stroke starts
+    pub fn dash_offset(&self) -> f64 {
+        self.dash_offset
+    }
+
+    /// The miter limit of the stroke's corners, as a multiple of the stroke's width
+    pub fn miter_limit(&self) -> f64 {
+        self.miter_limit
+    }
+
+    /// The shape of the ends of the stroke
+    pub fn line_cap(&self) -> LineCap {
+        self.line_cap
+    }
+
+    /// The shape of the corners of the stroke
+    pub fn line_join(&self) -> LineJoin {
+        self.line_join
+    }
+
+    /// Where the stroke sits compared to the shape's path
+    pub fn alignment(&self) -> StrokeAlignment {
+        self.alignment
+    }
+
+    /// The lengths of the dashes and the gaps between them, alternating, as multiples of the
+    /// stroke's width. Empty if the stroke is solid.
+    pub fn dash_pattern(&self) -> &[f64] {
+        &self.dash_pattern
+    }
+
+    /// How the stroke is blended with the shape
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The opacity of the stroke. 0 = transparent ... 255 = opaque
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// What the stroke is painted with, which is a solid color, gradient or pattern
+    pub fn content(&self) -> Option<&FillLayer> {
+        self.content.as_ref()
+    }
+
+    /// The color of the stroke, `[R, G, B]`, if it is painted with a solid color
+    pub fn color(&self) -> Option<[u8; 3]> {
+        match self.content {
+            Some(FillLayer::SolidColor(color)) => Some(color),
+            _ => None,
+        }
+    }
+}
+
+/// Read the `vscg` additional layer information block, which is what the shape is filled with.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                          |
+/// |----------|------------------------------------------------------|
+/// | 4        | Key of the fill: 'SoCo', 'GdFl' or 'PtFl'            |
+/// | 4        | Version ( = 16)                                      |
+/// | Variable | Descriptor                                           |
+pub(super) fn read_vector_fill(
+    bytes: &[u8],
+) -> Result<Option<FillLayer>, ImageResourcesDescriptorError> {
+    if bytes.len() < 8 {
+        return Ok(None);
+    }
+
+    let mut key = [0; 4];
+    key.copy_from_slice(&bytes[..4]);
+
+    FillLayer::from_bytes(&key, &bytes[4..])
+}
+
+/// The paint of a stroke is stored like a fill layer, using the class of its descriptor instead
+/// of a key
+fn read_content(content: &DescriptorStructure) -> Option<FillLayer> {
+    let key = match content.class_id.as_slice() {
+        b"solidColorLayer" => KEY_SOLID_COLOR_FILL,
+        b"gradientLayer" => KEY_GRADIENT_FILL,
+        b"patternLayer" => KEY_PATTERN_FILL,
+        _ => return None,
+    };
+
+    FillLayer::from_descriptor(key, content)
+}
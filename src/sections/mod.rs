@@ -1,9 +1,15 @@
-use std::io::Cursor;
+use core::convert::TryFrom;
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::psd_channel::PsdChannelKind;
+use crate::shared_bytes::SharedBytes;
+use thiserror::Error;
 
 use self::file_header_section::{FileHeaderSectionError, EXPECTED_PSD_SIGNATURE};
 
 /// The length of the entire file header section
-const FILE_HEADER_SECTION_LEN: usize = 26;
+pub(crate) const FILE_HEADER_SECTION_LEN: usize = 26;
 
 pub mod file_header_section;
 pub mod image_data_section;
@@ -11,13 +17,19 @@ pub mod image_resources_section;
 pub mod layer_and_mask_information_section;
 
 /// References to the different major sections of a PSD file
+///
+/// The layer and mask information and image data sections are shared with the channels that
+/// are read from them, so that the channels point into these sections instead of each holding
+/// a copy of their bytes.
 #[derive(Debug)]
 pub struct MajorSections<'a> {
     pub(crate) file_header: &'a [u8],
     pub(crate) color_mode_data: &'a [u8],
     pub(crate) image_resources: &'a [u8],
-    pub(crate) layer_and_mask: &'a [u8],
-    pub(crate) image_data: &'a [u8],
+    pub(crate) layer_and_mask: SharedBytes,
+    pub(crate) image_data: SharedBytes,
+    /// Where each of the sections is within the PSD file
+    pub(crate) layout: SectionLayout,
 }
 
 impl<'a> MajorSections<'a> {
@@ -56,7 +68,27 @@ impl<'a> MajorSections<'a> {
     /// A 4-byte length field, representing the number of characters in the string (not bytes).
     ///
     /// The string of Unicode values, two bytes per character.
+    ///
+    /// The layer and mask information and image data sections are copied once, so that the PSD
+    /// can keep them after `bytes` is gone. See [`MajorSections::from_shared`] to avoid the copy.
     pub fn from_bytes(bytes: &[u8]) -> Result<MajorSections, FileHeaderSectionError> {
+        MajorSections::read(bytes, |range| SharedBytes::new(bytes[range].to_vec()))
+    }
+
+    /// Like [`MajorSections::from_bytes`], but the layer and mask information and image data
+    /// sections share `bytes` instead of being copied.
+    pub(crate) fn from_shared(
+        bytes: &SharedBytes,
+    ) -> Result<MajorSections<'_>, FileHeaderSectionError> {
+        MajorSections::read(bytes, |range| bytes.slice(range))
+    }
+
+    /// Split the bytes of a PSD file into its major sections, getting the sections that the
+    /// channels are read from with `share`.
+    fn read(
+        bytes: &'a [u8],
+        share: impl Fn(Range<usize>) -> SharedBytes,
+    ) -> Result<MajorSections<'a>, FileHeaderSectionError> {
         // File header section must be 26 bytes long.
         if bytes.len() < FILE_HEADER_SECTION_LEN {
             return Err(FileHeaderSectionError::IncorrectLength {
@@ -67,192 +99,319 @@ impl<'a> MajorSections<'a> {
         let mut cursor = PsdCursor::new(bytes);
 
         // First four bytes must be '8BPS'
-        let signature = cursor.peek_4();
+        let signature = cursor.peek_4()?;
         if signature != EXPECTED_PSD_SIGNATURE {
             return Err(FileHeaderSectionError::InvalidSignature {});
         }
 
         // File Header Section
         let file_header = &bytes[0..FILE_HEADER_SECTION_LEN];
-        cursor.read(FILE_HEADER_SECTION_LEN as u32);
+        cursor.read(FILE_HEADER_SECTION_LEN as u32)?;
 
-        let (color_start, color_end) = read_major_section_start_end(&mut cursor);
-        let (img_res_start, img_res_end) = read_major_section_start_end(&mut cursor);
-        let (layer_mask_start, layer_mask_end) = read_major_section_start_end(&mut cursor);
+        let (color_start, color_end) = read_major_section_start_end(&mut cursor)?;
+        let (img_res_start, img_res_end) = read_major_section_start_end(&mut cursor)?;
+        let (layer_mask_start, layer_mask_end) = read_major_section_start_end(&mut cursor)?;
 
         // The remaining bytes are the image data section.
-        let image_data = &bytes[cursor.position() as usize..];
+        let image_data = share(cursor.position() as usize..bytes.len());
+
+        let layout = SectionLayout::from_section_lengths([
+            FILE_HEADER_SECTION_LEN,
+            color_end - color_start,
+            img_res_end - img_res_start,
+            layer_mask_end - layer_mask_start,
+            image_data.len(),
+        ]);
 
         Ok(MajorSections {
             file_header,
             color_mode_data: &bytes[color_start..color_end],
             image_resources: &bytes[img_res_start..img_res_end],
-            layer_and_mask: &bytes[layer_mask_start..layer_mask_end],
+            layer_and_mask: share(layer_mask_start..layer_mask_end),
             image_data,
+            layout,
         })
     }
 }
 
+/// The ranges of bytes that the major sections of a PSD file and the channels of its layers
+/// were read from, for tools that inspect or patch the original file.
+///
+/// The ranges of the length delimited sections include their length markers, so one section
+/// ends where the next one starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionLayout {
+    pub(crate) file_header: Range<usize>,
+    pub(crate) color_mode_data: Range<usize>,
+    pub(crate) image_resources: Range<usize>,
+    pub(crate) layer_and_mask: Range<usize>,
+    pub(crate) image_data: Range<usize>,
+    /// The ID of each resource block, in the order that they're stored in
+    pub(crate) resources: Vec<(i16, Range<usize>)>,
+    /// The record of each layer, in the same order as `Psd::layers`
+    pub(crate) layer_records: Vec<Range<usize>>,
+    /// The channels of each layer, in the same order as `Psd::layers`
+    pub(crate) layer_channels: Vec<Vec<(PsdChannelKind, Range<usize>)>>,
+}
+
+impl SectionLayout {
+    /// Lay out the five major sections one after the other, given their lengths in the order
+    /// that they're stored in.
+    pub(crate) fn from_section_lengths(lengths: [usize; 5]) -> SectionLayout {
+        let mut start = 0;
+        let mut ranges = lengths.iter().map(|len| {
+            let range = start..start + len;
+            start = range.end;
+            range
+        });
+        let mut next = || ranges.next().unwrap();
+
+        SectionLayout {
+            file_header: next(),
+            color_mode_data: next(),
+            image_resources: next(),
+            layer_and_mask: next(),
+            image_data: next(),
+            resources: vec![],
+            layer_records: vec![],
+            layer_channels: vec![],
+        }
+    }
+
+    /// The bytes of the file header section
+    pub fn file_header(&self) -> Range<usize> {
+        self.file_header.clone()
+    }
+
+    /// The bytes of the color mode data section
+    pub fn color_mode_data(&self) -> Range<usize> {
+        self.color_mode_data.clone()
+    }
+
+    /// The bytes of the image resources section
+    pub fn image_resources(&self) -> Range<usize> {
+        self.image_resources.clone()
+    }
+
+    /// The bytes of the layer and mask information section
+    pub fn layer_and_mask(&self) -> Range<usize> {
+        self.layer_and_mask.clone()
+    }
+
+    /// The bytes of the image data section, which runs to the end of the file
+    pub fn image_data(&self) -> Range<usize> {
+        self.image_data.clone()
+    }
+
+    /// The ID and bytes of each block of the image resources section, in the order that they're
+    /// stored in. Resources that were skipped while recovering are left out.
+    pub fn resources(&self) -> &[(i16, Range<usize>)] {
+        &self.resources
+    }
+
+    /// The bytes of a layer's record, which holds everything about the layer but its channels,
+    /// or `None` if there is no layer at the index. The index is the same as for `Psd::layers`.
+    pub fn layer_record(&self, layer_idx: usize) -> Option<Range<usize>> {
+        self.layer_records.get(layer_idx).cloned()
+    }
+
+    /// The bytes of each of a layer's channels, in the order that they're stored in, or `None`
+    /// if there is no layer at the index. The index is the same as for `Psd::layers`.
+    ///
+    /// Each range starts at the two bytes that store the channel's compression.
+    pub fn layer_channels(&self, layer_idx: usize) -> Option<&[(PsdChannelKind, Range<usize>)]> {
+        self.layer_channels
+            .get(layer_idx)
+            .map(|channels| channels.as_slice())
+    }
+}
+
 /// Get the start and end indices of a major section
-fn read_major_section_start_end(cursor: &mut PsdCursor) -> (usize, usize) {
+fn read_major_section_start_end(cursor: &mut PsdCursor) -> Result<(usize, usize), PsdCursorError> {
     let start = cursor.position() as usize;
-    let data_len = cursor.read_u32();
-    cursor.read(data_len);
+    let data_len = cursor.read_u32()?;
+    cursor.read(data_len)?;
     let end = cursor.position() as usize;
 
-    (start, end)
+    Ok((start, end))
+}
+
+/// The bytes that a [`PsdCursor`] was reading ended before the value that it was reading
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PsdCursorError {
+    #[error("Expected {len} bytes at position {position}, but there are only {available}.")]
+    UnexpectedEnd {
+        position: u64,
+        len: usize,
+        available: usize,
+    },
 }
 
 /// A Cursor wrapping bytes from a PSD file.
 ///
-/// Provides methods that abstract common ways of parsing PSD bytes.
+/// Provides methods that abstract common ways of parsing PSD bytes. Reading past the end of the
+/// bytes returns a [`PsdCursorError`] instead of panicking, since the lengths that we read come
+/// from the file.
 pub(crate) struct PsdCursor<'a> {
-    cursor: Cursor<&'a [u8]>,
+    bytes: &'a [u8],
+    position: u64,
 }
 
 impl<'a> PsdCursor<'a> {
     /// Create a new PsdCursor
     pub fn new(bytes: &[u8]) -> PsdCursor {
-        PsdCursor {
-            cursor: Cursor::new(bytes),
-        }
+        PsdCursor { bytes, position: 0 }
     }
 
     /// Get the cursor's position
     pub fn position(&self) -> u64 {
-        self.cursor.position()
+        self.position
+    }
+
+    /// The number of bytes after the cursor's position
+    pub fn remaining(&self) -> u64 {
+        (self.bytes.len() as u64).saturating_sub(self.position)
+    }
+
+    /// The capacity to reserve for `count` items that each take at least `item_len` bytes, so
+    /// that a count that was read from the file can't reserve more than the bytes could hold
+    pub fn capacity_for(&self, count: usize, item_len: u64) -> usize {
+        (count as u64).min(self.remaining() / item_len) as usize
     }
 
     pub fn seek(&mut self, pos: u64) {
-        self.cursor.set_position(pos);
+        self.position = pos;
     }
 
     /// Get the underlying bytes in the cursor
     pub fn get_ref(&self) -> &[u8] {
-        self.cursor.get_ref()
+        self.bytes
     }
 
     /// Advance the cursor by count bytes and return those bytes
-    pub fn read(&mut self, count: u32) -> &[u8] {
-        let start = self.cursor.position() as usize;
-        let end = start + count as usize;
-        let bytes = &self.cursor.get_ref()[start..end];
+    pub fn read(&mut self, count: u32) -> Result<&[u8], PsdCursorError> {
+        self.read_len(count as usize)
+    }
 
-        self.cursor.set_position(end as u64);
-        bytes
+    fn read_len(&mut self, len: usize) -> Result<&[u8], PsdCursorError> {
+        let bytes = Self::slice(self.bytes, self.position, len)?;
+
+        self.position += len as u64;
+        Ok(bytes)
     }
 
-    pub fn peek_u32(&self) -> u32 {
-        let bytes = self.peek_4();
-        u32_from_be_bytes(bytes)
+    pub fn peek_u32(&self) -> Result<u32, PsdCursorError> {
+        let bytes = self.peek_4()?;
+        Ok(u32_from_be_bytes(bytes))
     }
 
     /// Peek at the next four bytes
-    pub fn peek_4(&self) -> &[u8] {
+    pub fn peek_4(&self) -> Result<&[u8], PsdCursorError> {
         self.peek(4)
     }
 
     /// Get the next n bytes without moving the cursor
-    fn peek(&self, n: u8) -> &[u8] {
-        let start = self.cursor.position() as usize;
-        let end = start + n as usize;
-        let bytes = &self.cursor.get_ref()[start..end];
-        bytes
+    fn peek(&self, n: u8) -> Result<&[u8], PsdCursorError> {
+        Self::slice(self.bytes, self.position, n as usize)
+    }
+
+    /// The `len` bytes at the position, or an error if they run past the end of the bytes
+    fn slice(bytes: &[u8], position: u64, len: usize) -> Result<&[u8], PsdCursorError> {
+        let start = usize::try_from(position).ok();
+        let range = start.and_then(|start| Some(start..start.checked_add(len)?));
+
+        range
+            .and_then(|range| bytes.get(range))
+            .ok_or(PsdCursorError::UnexpectedEnd {
+                position,
+                len,
+                available: bytes.len().saturating_sub(start.unwrap_or(usize::MAX)),
+            })
     }
 
     /// Read 1 byte
-    pub fn read_1(&mut self) -> &[u8] {
+    pub fn read_1(&mut self) -> Result<&[u8], PsdCursorError> {
         self.read(1)
     }
 
     /// Read 2 bytes
-    pub fn read_2(&mut self) -> &[u8] {
+    pub fn read_2(&mut self) -> Result<&[u8], PsdCursorError> {
         self.read(2)
     }
 
     /// Read 4 bytes
-    pub fn read_4(&mut self) -> &[u8] {
+    pub fn read_4(&mut self) -> Result<&[u8], PsdCursorError> {
         self.read(4)
     }
 
     /// Read 6 bytes
-    pub fn read_6(&mut self) -> &[u8] {
+    pub fn read_6(&mut self) -> Result<&[u8], PsdCursorError> {
         self.read(6)
     }
 
     /// Read 8 bytes
-    pub fn read_8(&mut self) -> &[u8] {
+    pub fn read_8(&mut self) -> Result<&[u8], PsdCursorError> {
         self.read(8)
     }
 
     /// Read 1 byte as a u8
-    pub fn read_u8(&mut self) -> u8 {
-        self.read_1()[0]
+    pub fn read_u8(&mut self) -> Result<u8, PsdCursorError> {
+        Ok(self.read_1()?[0])
     }
 
     /// Read 2 bytes as a u16
-    pub fn read_u16(&mut self) -> u16 {
-        let bytes = self.read_2();
+    pub fn read_u16(&mut self) -> Result<u16, PsdCursorError> {
+        let bytes = self.read_2()?;
 
         let mut array = [0; 2];
         array.copy_from_slice(bytes);
 
-        u16::from_be_bytes(array)
+        Ok(u16::from_be_bytes(array))
     }
 
     /// Read 4 bytes as a u32
-    pub fn read_u32(&mut self) -> u32 {
-        let bytes = self.read_4();
-        u32_from_be_bytes(bytes)
-    }
-
-    /// Read 1 byte as a i8
-    pub fn read_i8(&mut self) -> i8 {
-        let bytes = self.read_1();
-
-        let mut array = [0; 1];
-        array.copy_from_slice(bytes);
-
-        i8::from_be_bytes(array)
+    pub fn read_u32(&mut self) -> Result<u32, PsdCursorError> {
+        let bytes = self.read_4()?;
+        Ok(u32_from_be_bytes(bytes))
     }
 
     /// Read 2 bytes as a i16
-    pub fn read_i16(&mut self) -> i16 {
-        let bytes = self.read_2();
+    pub fn read_i16(&mut self) -> Result<i16, PsdCursorError> {
+        let bytes = self.read_2()?;
 
         let mut array = [0; 2];
         array.copy_from_slice(bytes);
 
-        i16::from_be_bytes(array)
+        Ok(i16::from_be_bytes(array))
     }
 
     /// Read 4 bytes as a i32
-    pub fn read_i32(&mut self) -> i32 {
-        let bytes = self.read_4();
+    pub fn read_i32(&mut self) -> Result<i32, PsdCursorError> {
+        let bytes = self.read_4()?;
 
         let mut array = [0; 4];
         array.copy_from_slice(bytes);
-        i32::from_be_bytes(array)
+        Ok(i32::from_be_bytes(array))
     }
 
     /// Read 8 bytes as a f64
-    pub fn read_f64(&mut self) -> f64 {
-        let bytes = self.read_8();
+    pub fn read_f64(&mut self) -> Result<f64, PsdCursorError> {
+        let bytes = self.read_8()?;
 
         let mut array = [0; 8];
         array.copy_from_slice(bytes);
 
-        f64::from_be_bytes(array)
+        Ok(f64::from_be_bytes(array))
     }
 
     /// Read 8 bytes as a i64
-    pub fn read_i64(&mut self) -> i64 {
-        let bytes = self.read_8();
+    pub fn read_i64(&mut self) -> Result<i64, PsdCursorError> {
+        let bytes = self.read_8()?;
 
         let mut array = [0; 8];
         array.copy_from_slice(bytes);
 
-        i64::from_be_bytes(array)
+        Ok(i64::from_be_bytes(array))
     }
 
     /// Reads 'Unicode string'
@@ -260,7 +419,7 @@ impl<'a> PsdCursor<'a> {
     /// Unicode string is
     /// A 4-byte length field, representing the number of UTF-16 code units in the string (not bytes).
     /// The string of Unicode values, two bytes per character and a two byte null for the end of the string.
-    pub fn read_unicode_string(&mut self) -> String {
+    pub fn read_unicode_string(&mut self) -> Result<String, PsdCursorError> {
         self.read_unicode_string_padding(4)
     }
 
@@ -269,27 +428,31 @@ impl<'a> PsdCursor<'a> {
     /// Unicode string is
     /// A 4-byte length field, representing the number of UTF-16 code units in the string (not bytes).
     /// The string of Unicode values, two bytes per character and a two byte null for the end of the string.
-    pub fn read_unicode_string_padding(&mut self, padding: usize) -> String {
-        let length = self.read_u32() as usize;
+    ///
+    /// Unpaired surrogates are replaced, like invalid bytes in a pascal string.
+    pub fn read_unicode_string_padding(
+        &mut self,
+        padding: usize,
+    ) -> Result<String, PsdCursorError> {
+        let length = self.read_u32()? as usize;
         // UTF-16 encoding - two bytes per character
-        let length_bytes = length * 2;
+        let length_bytes = length.saturating_mul(2);
 
-        let data = self.read(length_bytes as u32);
-        let result =
-            String::from_utf16(&u8_slice_to_u16(data).as_slice()[..length as usize]).unwrap();
+        let data = self.read_len(length_bytes)?;
+        let result = String::from_utf16_lossy(&u8_slice_to_u16(data));
 
-        self.read_padding(4 + length_bytes, padding);
+        self.read_padding(4 + length_bytes, padding)?;
 
-        result
+        Ok(result)
     }
 
-    fn read_padding(&mut self, size: usize, divisor: usize) -> &[u8] {
+    fn read_padding(&mut self, size: usize, divisor: usize) -> Result<&[u8], PsdCursorError> {
         let remainder = size % divisor;
         if remainder > 0 {
             let to_read = divisor - remainder;
-            self.read(to_read as u32)
+            self.read_len(to_read)
         } else {
-            &[] as &[u8]
+            Ok(&[] as &[u8])
         }
     }
 
@@ -297,14 +460,16 @@ impl<'a> PsdCursor<'a> {
     ///
     /// Pascal string is UTF-8 string, padded to make the size even
     /// (a null name consists of two bytes of 0)
-    pub fn read_pascal_string(&mut self) -> String {
-        let len = self.read_u8();
-        let data = self.read(len as u32);
-        let result = String::from_utf8(data.to_vec()).unwrap();
-
-        // read null byte
-        self.read_u8();
-        result
+    pub fn read_pascal_string(&mut self) -> Result<String, PsdCursorError> {
+        let len = self.read_u8()?;
+        let data = self.read(len as u32)?;
+        let result = String::from_utf8_lossy(data).into_owned();
+
+        // The length byte and the string are padded to make the size even
+        if len.is_multiple_of(2) {
+            self.read_u8()?;
+        }
+        Ok(result)
     }
 }
 
@@ -322,3 +487,75 @@ fn u32_from_be_bytes(bytes: &[u8]) -> u32 {
 
     u32::from_be_bytes(array)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The length byte and an odd length string already have an even size, so they're not
+    // followed by a padding byte.
+    #[test]
+    fn pascal_string_odd_length_is_not_padded() {
+        let bytes = [3, b'a', b'b', b'c', 9];
+        let mut cursor = PsdCursor::new(&bytes);
+
+        assert_eq!(cursor.read_pascal_string().unwrap(), "abc");
+        assert_eq!(cursor.position(), 4);
+    }
+
+    #[test]
+    fn pascal_string_even_length_is_padded() {
+        let bytes = [2, b'a', b'b', 0, 9];
+        let mut cursor = PsdCursor::new(&bytes);
+
+        assert_eq!(cursor.read_pascal_string().unwrap(), "ab");
+        assert_eq!(cursor.position(), 4);
+
+        let empty = [0, 0, 9];
+        let mut cursor = PsdCursor::new(&empty);
+
+        assert_eq!(cursor.read_pascal_string().unwrap(), "");
+        assert_eq!(cursor.position(), 2);
+    }
+
+    // Pascal strings are stored in the system's encoding, which isn't always UTF-8
+    #[test]
+    fn pascal_string_invalid_utf8_is_replaced() {
+        let bytes = [1, 0xE9];
+        let mut cursor = PsdCursor::new(&bytes);
+
+        assert_eq!(cursor.read_pascal_string().unwrap(), "\u{FFFD}");
+    }
+
+    // Lengths come from the file, so reading past the end is an error instead of a panic
+    #[test]
+    fn reading_past_the_end_is_an_error() {
+        let bytes = [0, 0, 0, 9, 0, b'a'];
+        let mut cursor = PsdCursor::new(&bytes);
+
+        assert_eq!(
+            cursor.read_unicode_string(),
+            Err(PsdCursorError::UnexpectedEnd {
+                position: 4,
+                len: 18,
+                available: 2,
+            })
+        );
+        assert!(cursor.read_u32().is_err());
+        assert_eq!(cursor.read_u16(), Ok(b'a' as u16));
+        assert!(cursor.read_u8().is_err());
+
+        cursor.seek(100);
+        assert!(cursor.peek_4().is_err());
+    }
+
+    // Unpaired surrogates are replaced instead of failing to read the string
+    #[test]
+    fn unicode_string_unpaired_surrogate_is_replaced() {
+        let bytes = [0, 0, 0, 2, 0xD8, 0x00, 0, b'a', 0, 0, 0, 0];
+        let mut cursor = PsdCursor::new(&bytes);
+
+        assert_eq!(cursor.read_unicode_string().unwrap(), "\u{FFFD}a");
+        assert_eq!(cursor.position(), 8);
+    }
+}
@@ -0,0 +1,78 @@
+//! Bytes that share the buffer that a PSD was read from, so that the layer and composite image
+//! channels can point into the file instead of each holding a copy of their part of it.
+
+use alloc::sync::Arc;
+use core::fmt;
+use core::ops::{Deref, Range};
+
+use crate::prelude::*;
+
+/// A range of bytes within a buffer that is shared with everything else that was read from it.
+///
+/// Cloning or slicing only bumps the buffer's reference count, the buffer is freed once nothing
+/// points into it anymore.
+#[derive(Clone)]
+pub struct SharedBytes {
+    buffer: Arc<Vec<u8>>,
+    range: Range<usize>,
+}
+
+impl SharedBytes {
+    /// Share all of the bytes, without copying them
+    pub(crate) fn new(bytes: Vec<u8>) -> SharedBytes {
+        let range = 0..bytes.len();
+
+        SharedBytes {
+            buffer: Arc::new(bytes),
+            range,
+        }
+    }
+
+    /// The bytes within `range`, which is relative to the start of these bytes and cut short
+    /// at their end.
+    pub(crate) fn slice(&self, range: Range<usize>) -> SharedBytes {
+        let end = (self.range.start + range.end).min(self.range.end);
+        let start = (self.range.start + range.start).min(end);
+
+        SharedBytes {
+            buffer: Arc::clone(&self.buffer),
+            range: start..end,
+        }
+    }
+}
+
+impl Deref for SharedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SharedBytes::new(bytes)
+    }
+}
+
+impl fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Slices are relative to the bytes that they're taken from, and never reach past them.
+    #[test]
+    fn slice() {
+        let bytes = SharedBytes::new(vec![0, 1, 2, 3, 4, 5]);
+
+        let middle = bytes.slice(1..5);
+        assert_eq!(&*middle, &[1, 2, 3, 4]);
+        assert_eq!(&*middle.slice(2..10), &[3, 4]);
+        assert!(middle.slice(8..10).is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+//! Pack layers and groups into a sprite sheet, such as the frames of an animation that are
+//! drawn as separate layers.
+//!
+//! ```ignore
+//! let psd = Psd::from_bytes(bytes)?;
+//!
+//! // Every layer inside of the "Walk" group is a frame
+//! let walk = psd.group_by_name("Walk").unwrap().id();
+//! let sheet = psd::spritesheet::pack(
+//!     &psd,
+//!     &|source| matches!(source, FrameSource::Layer(layer) if layer.parent_id() == Some(walk)),
+//!     &SpritesheetOptions::default(),
+//! )?;
+//!
+//! for frame in sheet.frames() {
+//!     println!("{} is at {}x{}", frame.name, frame.x, frame.y);
+//! }
+//! ```
+
+use core::cmp::Reverse;
+
+use crate::blend::blend_rgba;
+use crate::prelude::*;
+use crate::{Psd, PsdError, PsdGroup, PsdLayer};
+
+/// A layer or group that can become a frame of a sprite sheet
+#[derive(Debug, Clone, Copy)]
+pub enum FrameSource<'a> {
+    /// A layer, rendered on its own
+    Layer(&'a PsdLayer),
+    /// A group, rendered by flattening the visible layers inside of it
+    Group(&'a PsdGroup),
+}
+
+impl<'a> FrameSource<'a> {
+    /// The name of the layer or group
+    pub fn name(&self) -> &'a str {
+        match self {
+            FrameSource::Layer(layer) => layer.name(),
+            FrameSource::Group(group) => group.name(),
+        }
+    }
+}
+
+/// Configures how [`pack`] lays out the frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpritesheetOptions {
+    /// The number of transparent pixels between frames. Defaults to 1.
+    pub padding: u32,
+    /// The width at which rows of frames wrap. Frames that are wider than this get a row of
+    /// their own. Defaults to 4096.
+    pub max_width: u32,
+    /// Crop each frame to its non-transparent pixels, instead of keeping the size of the PSD.
+    /// Defaults to true.
+    pub trim: bool,
+}
+
+impl Default for SpritesheetOptions {
+    fn default() -> Self {
+        SpritesheetOptions {
+            padding: 1,
+            max_width: 4096,
+            trim: true,
+        }
+    }
+}
+
+/// Where a frame ended up in the sprite sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRect {
+    /// The name of the layer or group
+    pub name: String,
+    /// The position of the frame's left column within the sprite sheet
+    pub x: u32,
+    /// The position of the frame's top row within the sprite sheet
+    pub y: u32,
+    /// The width of the frame, in pixels
+    pub width: u32,
+    /// The height of the frame, in pixels
+    pub height: u32,
+    /// The position of the frame's left column within the PSD, which is where trimmed frames
+    /// need to be drawn to line up with each other
+    pub offset_x: i32,
+    /// The position of the frame's top row within the PSD
+    pub offset_y: i32,
+}
+
+/// The RGBA pixels of a sprite sheet, along with where each frame is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spritesheet {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    frames: Vec<FrameRect>,
+}
+
+impl Spritesheet {
+    /// The width of the sprite sheet, in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the sprite sheet, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels of the sprite sheet.
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// The frames, in the order that they appear in the layers panel from top to bottom
+    pub fn frames(&self) -> &[FrameRect] {
+        &self.frames
+    }
+
+    /// Split the sprite sheet into its pixels and its frames
+    pub fn into_parts(self) -> (Vec<u8>, Vec<FrameRect>) {
+        (self.rgba, self.frames)
+    }
+}
+
+/// Render every layer and group that the selector returns true for, and pack them into a
+/// sprite sheet.
+///
+/// Layers are rendered with [`PsdLayer::rgba_with_opacity`] even if they are hidden, since hidden
+/// layers are often the frames of an animation. Groups are rendered by flattening the visible
+/// layers inside of them. Frames without any non-transparent pixels are left out when trimming.
+///
+/// Frames are packed into rows, tallest first, so that little space is wasted.
+pub fn pack(
+    psd: &Psd,
+    selector: &dyn Fn(FrameSource) -> bool,
+    options: &SpritesheetOptions,
+) -> Result<Spritesheet, PsdError> {
+    let mut sources = vec![];
+    for group in psd.groups_in_order() {
+        let depth = group_depth(psd, group.parent_id());
+        sources.push((
+            (group.contained_layers.start, depth),
+            FrameSource::Group(group),
+        ));
+    }
+    for (idx, layer) in psd.layers().iter().enumerate() {
+        sources.push(((idx, usize::MAX), FrameSource::Layer(layer)));
+    }
+    sources.retain(|(_, source)| selector(*source));
+    // Groups come before the layers inside of them, and outer groups before inner groups
+    sources.sort_by_key(|(position, _)| *position);
+
+    let mut frames = vec![];
+    for (_, source) in sources {
+        let rgba = render(psd, source)?;
+
+        let (width, height) = (psd.width(), psd.height());
+        let bounds = if options.trim {
+            match opaque_bounds(&rgba, width) {
+                Some(bounds) => bounds,
+                None => continue,
+            }
+        } else {
+            (0, 0, width, height)
+        };
+
+        frames.push((source.name().to_string(), rgba, bounds));
+    }
+
+    // Lay out the frames in rows, tallest first
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by_key(|idx| {
+        let (.., (.., height)) = frames[*idx];
+        Reverse(height)
+    });
+
+    let padding = options.padding;
+    let mut positions = vec![(0, 0); frames.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    let (mut sheet_width, mut sheet_height) = (0, 0);
+    for idx in order {
+        let (_, _, (_, _, width, height)) = frames[idx];
+        if x > 0 && x + width > options.max_width {
+            x = 0;
+            y += row_height + padding;
+            row_height = 0;
+        }
+
+        positions[idx] = (x, y);
+        sheet_width = sheet_width.max(x + width);
+        sheet_height = sheet_height.max(y + height);
+
+        x += width + padding;
+        row_height = row_height.max(height);
+    }
+
+    let mut rgba = vec![0; sheet_width as usize * sheet_height as usize * 4];
+    let mut frame_rects = Vec::with_capacity(frames.len());
+    for ((name, frame_rgba, (left, top, width, height)), (x, y)) in
+        frames.into_iter().zip(positions)
+    {
+        let row_len = width as usize * 4;
+        for row in 0..height as usize {
+            let src = ((top as usize + row) * psd.width() as usize + left as usize) * 4;
+            let dst = ((y as usize + row) * sheet_width as usize + x as usize) * 4;
+            rgba[dst..dst + row_len].copy_from_slice(&frame_rgba[src..src + row_len]);
+        }
+
+        frame_rects.push(FrameRect {
+            name,
+            x,
+            y,
+            width,
+            height,
+            offset_x: left as i32,
+            offset_y: top as i32,
+        });
+    }
+
+    Ok(Spritesheet {
+        width: sheet_width,
+        height: sheet_height,
+        rgba,
+        frames: frame_rects,
+    })
+}
+
+/// The RGBA pixels of the layer or group, the size of the PSD
+fn render(psd: &Psd, source: FrameSource) -> Result<Vec<u8>, PsdError> {
+    match source {
+        FrameSource::Layer(layer) => Ok(layer.rgba_with_opacity()),
+        FrameSource::Group(group) => {
+            let layers = &psd.layers()[group.contained_layers.clone()];
+            if layers.is_empty() {
+                return Ok(vec![0; psd.width() as usize * psd.height() as usize * 4]);
+            }
+
+            let filter = |(idx, _): (usize, &PsdLayer)| group.contained_layers.contains(&idx);
+            Ok(psd.flatten_unoriented(&filter, &blend_rgba))
+        }
+    }
+}
+
+/// The number of groups that a group with this parent is nested inside of
+fn group_depth(psd: &Psd, mut parent_id: Option<u32>) -> usize {
+    let mut depth = 0;
+    while let Some(group) = parent_id.and_then(|id| psd.groups().get(&id)) {
+        depth += 1;
+        parent_id = group.parent_id();
+    }
+
+    depth
+}
+
+/// `(left, top, width, height)` of the pixels that aren't fully transparent
+fn opaque_bounds(rgba: &[u8], width: u32) -> Option<(u32, u32, u32, u32)> {
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+
+    for (idx, pixel) in rgba.chunks_exact(4).enumerate() {
+        if pixel[3] == 0 {
+            continue;
+        }
+
+        let (x, y) = (idx as u32 % width, idx as u32 / width);
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x);
+        bottom = bottom.max(y);
+    }
+
+    if left == u32::MAX {
+        return None;
+    }
+
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
@@ -0,0 +1,185 @@
+//! Compare flattened PSDs against reference PNGs, so that crates building on this one can write
+//! golden image regression tests, such as for the blend modes.
+//!
+//! Requires the `test-utils` feature.
+//!
+//! ```ignore
+//! #[test]
+//! fn multiply() {
+//!     let psd = Psd::from_bytes(include_bytes!("fixtures/multiply.psd")).unwrap();
+//!
+//!     // Allow each channel to be off by one, since Photoshop rounds differently than we do
+//!     psd::testing::assert_flattened_matches_png(&psd, include_bytes!("fixtures/multiply.png"), 1);
+//! }
+//! ```
+
+use thiserror::Error;
+
+use crate::{Psd, PsdError};
+
+/// Why pixels didn't match a reference PNG
+#[derive(Debug, PartialEq, Error)]
+pub enum GoldenError {
+    /// The reference image couldn't be decoded
+    #[error("The reference image isn't a valid PNG: {0}")]
+    InvalidPng(String),
+    /// The pixels are a different size than the reference image
+    #[error(
+        "The image is {width}x{height}, but the reference is {expected_width}x{expected_height}."
+    )]
+    SizeMismatch {
+        /// The width of the pixels that were compared
+        width: u32,
+        /// The height of the pixels that were compared
+        height: u32,
+        /// The width of the reference image
+        expected_width: u32,
+        /// The height of the reference image
+        expected_height: u32,
+    },
+    /// Some of the pixels differ from the reference image by more than the tolerance
+    #[error(
+        "{count} pixels differ from the reference by more than the tolerance. The first is at \
+        ({x}, {y}), which is {actual:?} instead of {expected:?}."
+    )]
+    PixelMismatch {
+        /// The number of pixels that differ
+        count: usize,
+        /// The column of the first pixel that differs, from the left
+        x: u32,
+        /// The row of the first pixel that differs, from the top
+        y: u32,
+        /// The first pixel that differs
+        actual: [u8; 4],
+        /// The pixel of the reference image at the same position
+        expected: [u8; 4],
+    },
+    /// The PSD couldn't be flattened
+    #[error("Failed to flatten the PSD: {0}")]
+    Flatten(PsdError),
+}
+
+/// Compare the flattened layers of the PSD (see [`Psd::flatten_layers_rgba`]) against a
+/// reference PNG.
+///
+/// See [`compare_rgba_to_png`] for how the pixels are compared.
+pub fn compare_flattened_to_png(psd: &Psd, png: &[u8], tolerance: u8) -> Result<(), GoldenError> {
+    let rgba = psd
+        .flatten_layers_rgba(&|_| true)
+        .map_err(GoldenError::Flatten)?;
+
+    let (width, height) = psd.oriented_size();
+    compare_rgba_to_png(&rgba, width, height, png, tolerance)
+}
+
+/// Compare RGBA pixels (`[R, G, B, A, R, G, B, A, ...]`) against a reference PNG.
+///
+/// Pixels match when each of their channels is within `tolerance` of the reference. Two fully
+/// transparent pixels always match, since the color under a transparent pixel doesn't show.
+///
+/// The PNG can be of any color type. 16 bit PNGs are compared by their 8 most significant bits.
+pub fn compare_rgba_to_png(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    png: &[u8],
+    tolerance: u8,
+) -> Result<(), GoldenError> {
+    let (expected_rgba, expected_width, expected_height) = decode_png(png)?;
+    if (width, height) != (expected_width, expected_height) || rgba.len() != expected_rgba.len() {
+        return Err(GoldenError::SizeMismatch {
+            width,
+            height,
+            expected_width,
+            expected_height,
+        });
+    }
+
+    let mut mismatch = None;
+    let mut count = 0;
+    for (idx, (actual, expected)) in rgba
+        .chunks_exact(4)
+        .zip(expected_rgba.chunks_exact(4))
+        .enumerate()
+    {
+        if pixels_match(actual, expected, tolerance) {
+            continue;
+        }
+
+        count += 1;
+        if mismatch.is_none() {
+            let mut pixels = ([0; 4], [0; 4]);
+            pixels.0.copy_from_slice(actual);
+            pixels.1.copy_from_slice(expected);
+            mismatch = Some((idx as u32, pixels));
+        }
+    }
+
+    match mismatch {
+        Some((idx, (actual, expected))) => Err(GoldenError::PixelMismatch {
+            count,
+            x: idx % width,
+            y: idx / width,
+            actual,
+            expected,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Panic with a description of the first difference unless the flattened layers of the PSD
+/// match the reference PNG. See [`compare_flattened_to_png`].
+#[track_caller]
+pub fn assert_flattened_matches_png(psd: &Psd, png: &[u8], tolerance: u8) {
+    if let Err(err) = compare_flattened_to_png(psd, png, tolerance) {
+        panic!("{}", err);
+    }
+}
+
+fn pixels_match(actual: &[u8], expected: &[u8], tolerance: u8) -> bool {
+    if actual[3] == 0 && expected[3] == 0 {
+        return true;
+    }
+
+    actual
+        .iter()
+        .zip(expected)
+        .all(|(actual, expected)| actual.abs_diff(*expected) <= tolerance)
+}
+
+/// Decode a PNG into RGBA pixels, along with its width and height
+fn decode_png(png: &[u8]) -> Result<(Vec<u8>, u32, u32), GoldenError> {
+    let invalid = |err: png::DecodingError| GoldenError::InvalidPng(err.to_string());
+
+    let mut decoder = png::Decoder::new(png);
+    // Expand palettes and low bit depths, and strip 16 bit channels down to 8 bits
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid)?;
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(invalid)?;
+    pixels.truncate(info.line_size * info.height as usize);
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|gray| [gray[0], gray[0], gray[0], gray[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels
+            .iter()
+            .flat_map(|gray| [*gray, *gray, *gray, 255])
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(GoldenError::InvalidPng(
+                "the palette couldn't be expanded".to_string(),
+            ))
+        }
+    };
+
+    Ok((rgba, info.width, info.height))
+}
@@ -0,0 +1,16 @@
+//! Optional `tracing` instrumentation.
+
+/// Enter a `tracing` span that lasts until the end of the current scope.
+///
+/// Does nothing unless the `tracing` feature is enabled.
+///
+/// ```ignore
+/// span!(DEBUG, "image_data_section", len = bytes.len());
+/// ```
+macro_rules! span {
+    ($level:ident, $name:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::span!(tracing::Level::$level, $name $(, $field = $value)*).entered();
+    };
+}
@@ -0,0 +1,757 @@
+//! Check that the bytes of a PSD are consistent with the file format's specification.
+//!
+//! Parsing is lenient, so a PSD that parses can still have problems that other programs would
+//! choke on, such as a section length that doesn't match the section or channel data that is
+//! shorter than the layer's bounds need. Validating walks the whole file and reports every
+//! problem that it finds instead of stopping at the first one.
+//!
+//! ```ignore
+//! let bytes = std::fs::read("my-psd-file.psd")?;
+//!
+//! let report = Psd::validate(&bytes);
+//! for issue in report.issues() {
+//!     println!("{}", issue);
+//! }
+//! assert!(report.is_valid());
+//! ```
+
+use core::ops::Range;
+
+use thiserror::Error;
+
+use crate::options::channel_byte_count;
+use crate::prelude::*;
+use crate::sections::file_header_section::{FileHeaderSection, FileHeaderSectionError, PsdDepth};
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::sections::layer_and_mask_information_section::mask::LayerMask;
+use crate::sections::PsdCursor;
+
+/// The signatures that image resource blocks can have. Photoshop writes `8BIM`, the others
+/// come from older versions and other Adobe programs.
+const RESOURCE_SIGNATURES: [&[u8; 4]; 5] = [b"8BIM", b"MeSa", b"AgHg", b"PHUT", b"DCSR"];
+/// The signatures that blocks of additional layer information can have
+const LAYER_INFO_SIGNATURES: [&[u8; 4]; 2] = [b"8BIM", b"8B64"];
+
+/// The problems that were found while validating a PSD.
+#[derive(Debug, PartialEq, Default)]
+pub struct ValidationReport {
+    pub(crate) issues: Vec<ValidationIssue>,
+}
+
+/// A section of the PSD file that has a length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum PsdSection {
+    ColorModeData,
+    ImageResources,
+    LayerAndMaskInformation,
+}
+
+/// A problem with the bytes of a PSD.
+///
+/// Layer records are referred to by their position in the file, where 0 is the bottom most
+/// record, since the records might be too broken to become layers.
+#[derive(Debug, PartialEq, Error)]
+pub enum ValidationIssue {
+    /// The file header is invalid, so nothing after it could be checked
+    #[error("Invalid file header: '{0}'.")]
+    InvalidFileHeader(FileHeaderSectionError),
+
+    /// A section's length runs past the end of the file, or past the end of the section that
+    /// it is in
+    #[error("The {section:?} section ends at byte {end}, past the {available} bytes available.")]
+    SectionOutOfBounds {
+        /// The section
+        section: PsdSection,
+        /// Where the section's length says that it ends
+        end: u64,
+        /// Where the section has to end
+        available: u64,
+    },
+
+    /// The blocks or records within a section don't add up to the section's length
+    #[error("The {section:?} section is {declared} bytes long, but its contents are {actual}.")]
+    SectionLengthMismatch {
+        /// The section
+        section: PsdSection,
+        /// The length that is stored for the section
+        declared: u64,
+        /// The length of the section's contents
+        actual: u64,
+    },
+
+    /// An image resource block doesn't start with a known signature, so the resources after
+    /// it couldn't be checked
+    #[error("The image resource block at byte {offset} has an invalid signature.")]
+    InvalidResourceSignature {
+        /// Where the block starts in the file
+        offset: usize,
+    },
+
+    /// An image resource block runs past the end of the image resources section
+    #[error("The image resource block at byte {offset} runs past the end of its section.")]
+    ResourceOutOfBounds {
+        /// Where the block starts in the file
+        offset: usize,
+    },
+
+    /// A byte that the specification says is zero, such as padding or a filler byte, isn't
+    #[error("The padding byte at {offset} isn't zero.")]
+    NonZeroPadding {
+        /// Where the byte is in the file
+        offset: usize,
+    },
+
+    /// A layer record runs past the end of the layer info, so the records and channels after
+    /// it couldn't be checked
+    #[error("Layer record {record} runs past the end of the layer info.")]
+    LayerRecordOutOfBounds {
+        /// The position of the record
+        record: usize,
+    },
+
+    /// A layer record says that it has more channels than a PSD can have
+    #[error("Layer record {record} has {count} channels, but at most 56 are allowed.")]
+    InvalidLayerChannelCount {
+        /// The position of the record
+        record: usize,
+        /// The number of channels in the record
+        count: u16,
+    },
+
+    /// A layer record's blend mode doesn't have the `8BIM` signature
+    #[error("Layer record {record} has an invalid blend mode signature.")]
+    InvalidBlendModeSignature {
+        /// The position of the record
+        record: usize,
+    },
+
+    /// A layer record's blend mode key isn't one of the blend modes
+    #[error("Layer record {record} has the unknown blend mode '{key}'.")]
+    UnknownBlendMode {
+        /// The position of the record
+        record: usize,
+        /// The four character key of the blend mode
+        key: String,
+    },
+
+    /// A layer record is missing one of the color channels of the PSD's color mode
+    #[error("Layer record {record} is missing color channel {channel_id}.")]
+    MissingColorChannel {
+        /// The position of the record
+        record: usize,
+        /// The ID of the missing channel
+        channel_id: i16,
+    },
+
+    /// A block of additional layer information doesn't start with a known signature, so the
+    /// blocks after it couldn't be checked
+    #[error("The additional layer information at byte {offset} has an invalid signature.")]
+    InvalidLayerInfoSignature {
+        /// Where the block starts in the file
+        offset: usize,
+    },
+
+    /// A block of additional layer information runs past the end of the data that it is in
+    #[error("The additional layer information at byte {offset} runs past the end of its data.")]
+    LayerInfoOutOfBounds {
+        /// Where the block starts in the file
+        offset: usize,
+    },
+
+    /// A channel's data runs past the end of the layer info
+    #[error("Channel {channel_id} of layer record {record} runs past the end of the layer info.")]
+    ChannelOutOfBounds {
+        /// The position of the record
+        record: usize,
+        /// The ID of the channel
+        channel_id: i16,
+    },
+
+    /// A channel or the composite image uses a compression that doesn't exist
+    #[error("Unknown compression {compression}.")]
+    InvalidCompression {
+        /// The position of the layer record that the channel belongs to, or `None` for the
+        /// composite image
+        record: Option<usize>,
+        /// The stored compression
+        compression: u16,
+    },
+
+    /// The length of a channel's data doesn't match the size of the layer or mask that it
+    /// belongs to
+    #[error(
+        "Channel {channel_id} of layer record {record} is {actual} bytes long instead of {expected}."
+    )]
+    ChannelLengthMismatch {
+        /// The position of the record
+        record: usize,
+        /// The ID of the channel
+        channel_id: i16,
+        /// The length that the channel's size and compression need
+        expected: u64,
+        /// The length that is stored for the channel
+        actual: u64,
+    },
+
+    /// The length of the composite image's data doesn't match the size and channel count in
+    /// the file header
+    #[error("The composite image is {actual} bytes long instead of {expected}.")]
+    CompositeLengthMismatch {
+        /// The length that the PSD's size, channels and compression need
+        expected: u64,
+        /// The length of the image data section
+        actual: u64,
+    },
+}
+
+impl ValidationReport {
+    /// Whether no problems were found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The problems that were found, in the order that they appear in the file
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+}
+
+/// The parts of the file header that the other sections are checked against
+struct Header {
+    width: u64,
+    height: u64,
+    channel_count: u64,
+    depth: PsdDepth,
+    color_channel_count: usize,
+}
+
+/// The fields of a layer record that its channels are checked against
+struct Record {
+    rows: u64,
+    columns: u64,
+    channels: Vec<(i16, u64)>,
+    mask: Option<LayerMask>,
+}
+
+/// Check the bytes of a PSD, see [`crate::Psd::validate`]
+pub(crate) fn validate(bytes: &[u8]) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    report.validate(bytes);
+    report
+}
+
+impl ValidationReport {
+    fn validate(&mut self, bytes: &[u8]) {
+        let header = match bytes.get(..26).map(FileHeaderSection::from_bytes) {
+            Some(Ok(header)) => header,
+            Some(Err(error)) => {
+                self.issues.push(ValidationIssue::InvalidFileHeader(error));
+                return;
+            }
+            None => {
+                let length = bytes.len();
+                self.issues.push(ValidationIssue::InvalidFileHeader(
+                    FileHeaderSectionError::IncorrectLength { length },
+                ));
+                return;
+            }
+        };
+        let header = Header {
+            width: header.width.0 as u64,
+            height: header.height.0 as u64,
+            channel_count: header.channel_count.count() as u64,
+            depth: header.depth,
+            color_channel_count: header.color_mode.color_channel_count(),
+        };
+
+        let sections = [
+            PsdSection::ColorModeData,
+            PsdSection::ImageResources,
+            PsdSection::LayerAndMaskInformation,
+        ];
+        let mut start = 26;
+        for section in sections.iter() {
+            let range = match self.length_prefixed(bytes, start, bytes.len(), *section) {
+                Some(range) => range,
+                None => return,
+            };
+            start = range.end;
+
+            match section {
+                PsdSection::ImageResources => self.validate_resources(bytes, range),
+                PsdSection::LayerAndMaskInformation => {
+                    self.validate_layer_and_mask(bytes, range, &header)
+                }
+                _ => {}
+            }
+        }
+
+        self.validate_image_data(&bytes[start..], &header);
+    }
+
+    /// The range of the data that follows the 4 byte length at `start`, if it fits before `end`
+    fn length_prefixed(
+        &mut self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+        section: PsdSection,
+    ) -> Option<Range<usize>> {
+        let data_end = read_u32(bytes, start)
+            .filter(|_| start + 4 <= end)
+            .map(|len| start as u64 + 4 + len as u64);
+
+        match data_end {
+            Some(data_end) if data_end <= end as u64 => Some(start + 4..data_end as usize),
+            _ => {
+                self.issues.push(ValidationIssue::SectionOutOfBounds {
+                    section,
+                    end: data_end.unwrap_or(start as u64 + 4),
+                    available: end as u64,
+                });
+                None
+            }
+        }
+    }
+
+    /// | Length   | Description                                                       |
+    /// |----------|-------------------------------------------------------------------|
+    /// | 4        | Signature: '8BIM'                                                 |
+    /// | 2        | Unique identifier for the resource                                |
+    /// | Variable | Name: Pascal string, padded to make the size even                 |
+    /// | 4        | Actual size of resource data that follows                         |
+    /// | Variable | The resource data, padded to make the size even                   |
+    fn validate_resources(&mut self, bytes: &[u8], section: Range<usize>) {
+        let mut position = section.start;
+
+        while position < section.end {
+            let offset = position;
+            let signature = bytes.get(position..position + 4);
+            if !RESOURCE_SIGNATURES
+                .iter()
+                .any(|expected| signature == Some(&expected[..]))
+            {
+                self.issues
+                    .push(ValidationIssue::InvalidResourceSignature { offset });
+                return;
+            }
+
+            // The signature, ID and name's length
+            let name_len = match bytes.get(position + 6) {
+                Some(name_len) if position + 7 <= section.end => *name_len as usize,
+                _ => {
+                    self.issues
+                        .push(ValidationIssue::ResourceOutOfBounds { offset });
+                    return;
+                }
+            };
+            position += 6 + (name_len + 1) + (name_len + 1) % 2;
+            if name_len % 2 == 0 {
+                self.check_padding(bytes, position - 1..position);
+            }
+
+            let data_len = match read_u32(bytes, position).filter(|_| position + 4 <= section.end) {
+                Some(data_len) => data_len as usize,
+                None => {
+                    self.issues
+                        .push(ValidationIssue::ResourceOutOfBounds { offset });
+                    return;
+                }
+            };
+            position += 4;
+
+            let data_end = position + data_len;
+            if data_end + data_len % 2 > section.end {
+                self.issues
+                    .push(ValidationIssue::ResourceOutOfBounds { offset });
+                return;
+            }
+            position = data_end + data_len % 2;
+            self.check_padding(bytes, data_end..position);
+        }
+    }
+
+    /// The layer info, followed by the global layer mask info and the global additional layer
+    /// information.
+    fn validate_layer_and_mask(&mut self, bytes: &[u8], section: Range<usize>, header: &Header) {
+        if section.is_empty() {
+            return;
+        }
+
+        let layer_info = match self.length_prefixed(
+            bytes,
+            section.start,
+            section.end,
+            PsdSection::LayerAndMaskInformation,
+        ) {
+            Some(layer_info) => layer_info,
+            None => return,
+        };
+        self.validate_layer_info(bytes, layer_info.clone(), header);
+
+        let global_layer_mask = match self.length_prefixed(
+            bytes,
+            layer_info.end,
+            section.end,
+            PsdSection::LayerAndMaskInformation,
+        ) {
+            Some(global_layer_mask) => global_layer_mask,
+            None => return,
+        };
+
+        self.validate_additional_layer_info(bytes, global_layer_mask.end..section.end);
+    }
+
+    /// The layer records, followed by the channel image data of every layer
+    fn validate_layer_info(&mut self, bytes: &[u8], layer_info: Range<usize>, header: &Header) {
+        if layer_info.len() < 2 {
+            return;
+        }
+
+        let layer_count = i16::from_be_bytes([bytes[layer_info.start], bytes[layer_info.start + 1]])
+            .unsigned_abs() as usize;
+
+        let mut position = layer_info.start + 2;
+        let mut records = vec![];
+        for record in 0..layer_count {
+            match self.validate_layer_record(bytes, position, layer_info.end, record, header) {
+                Some((layer_record, end)) => {
+                    records.push(layer_record);
+                    position = end;
+                }
+                None => {
+                    self.issues
+                        .push(ValidationIssue::LayerRecordOutOfBounds { record });
+                    return;
+                }
+            }
+        }
+
+        for (record, layer_record) in records.iter().enumerate() {
+            for (channel_id, len) in layer_record.channels.iter() {
+                let channel_id = *channel_id;
+                let end = position as u64 + *len;
+                if end > layer_info.end as u64 {
+                    self.issues
+                        .push(ValidationIssue::ChannelOutOfBounds { record, channel_id });
+                    return;
+                }
+
+                let (rows, columns) = layer_record.channel_size(channel_id);
+                let data = &bytes[position..end as usize];
+                if let Some(expected) =
+                    self.expected_channel_len(data, rows, columns, header, record)
+                {
+                    if expected != *len {
+                        self.issues.push(ValidationIssue::ChannelLengthMismatch {
+                            record,
+                            channel_id,
+                            expected,
+                            actual: *len,
+                        });
+                    }
+                }
+
+                position = end as usize;
+            }
+        }
+
+        // The layer info is padded to make its size even
+        self.check_padding(bytes, position..layer_info.end);
+        if layer_info.end - position > 3 {
+            self.issues.push(ValidationIssue::SectionLengthMismatch {
+                section: PsdSection::LayerAndMaskInformation,
+                declared: layer_info.len() as u64,
+                actual: (position - layer_info.start) as u64,
+            });
+        }
+    }
+
+    /// Check the layer record that starts at `start`, returning its fields and where it ends.
+    ///
+    /// Returns `None` if the record runs past `end`.
+    fn validate_layer_record(
+        &mut self,
+        bytes: &[u8],
+        start: usize,
+        end: usize,
+        record: usize,
+        header: &Header,
+    ) -> Option<(Record, usize)> {
+        // The rectangle and the number of channels
+        if start + 18 > end {
+            return None;
+        }
+        let mut cursor = PsdCursor::new(&bytes[..end]);
+        cursor.seek(start as u64);
+
+        let top = cursor.read_i32().ok()? as i64;
+        let left = cursor.read_i32().ok()? as i64;
+        let bottom = cursor.read_i32().ok()? as i64;
+        let right = cursor.read_i32().ok()? as i64;
+
+        let channel_count = cursor.read_u16().ok()?;
+        if channel_count > 56 {
+            self.issues.push(ValidationIssue::InvalidLayerChannelCount {
+                record,
+                count: channel_count,
+            });
+        }
+
+        // The channels, blend mode, opacity, clipping, flags, filler and extra data length
+        if cursor.position() + 6 * channel_count as u64 + 16 > end as u64 {
+            return None;
+        }
+        let channels: Vec<(i16, u64)> = (0..channel_count)
+            .map(|_| Some((cursor.read_i16().ok()?, cursor.read_u32().ok()? as u64)))
+            .collect::<Option<_>>()?;
+
+        if cursor.read_4().ok()? != b"8BIM" {
+            self.issues
+                .push(ValidationIssue::InvalidBlendModeSignature { record });
+        }
+        let mut key = [0; 4];
+        key.copy_from_slice(cursor.read_4().ok()?);
+        if BlendMode::from_key(key).is_none() {
+            self.issues.push(ValidationIssue::UnknownBlendMode {
+                record,
+                key: String::from_utf8_lossy(&key).into_owned(),
+            });
+        }
+
+        // Opacity, clipping and flags
+        cursor.read(3).ok()?;
+        let filler = cursor.position() as usize;
+        self.check_padding(bytes, filler..filler + 1);
+        cursor.read(1).ok()?;
+
+        let extra_data_len = cursor.read_u32().ok()? as u64;
+        let extra_data_start = cursor.position() as usize;
+        if extra_data_start as u64 + extra_data_len > end as u64 {
+            return None;
+        }
+        let extra_data_end = extra_data_start + extra_data_len as usize;
+
+        for channel_id in 0..header.color_channel_count as i16 {
+            if channels.iter().all(|(id, _)| *id != channel_id) {
+                self.issues
+                    .push(ValidationIssue::MissingColorChannel { record, channel_id });
+            }
+        }
+
+        // The layer mask data, the blending ranges and the name
+        let mut position = extra_data_start;
+        let mut mask = None;
+        for idx in 0..2 {
+            let len = read_u32(bytes, position)
+                .filter(|_| position + 4 <= extra_data_end)
+                .map(|len| len as usize)
+                .filter(|len| position + 4 + len <= extra_data_end);
+            let len = match len {
+                Some(len) => len,
+                None => {
+                    self.issues.push(ValidationIssue::SectionOutOfBounds {
+                        section: PsdSection::LayerAndMaskInformation,
+                        end: position as u64 + 4,
+                        available: extra_data_end as u64,
+                    });
+                    return Some((
+                        Record::new(top, left, bottom, right, channels, mask),
+                        extra_data_end,
+                    ));
+                }
+            };
+
+            if idx == 0 {
+                mask = LayerMask::from_bytes(&bytes[position + 4..position + 4 + len]);
+            }
+            position += 4 + len;
+        }
+
+        // The name is a Pascal string padded to a multiple of 4 bytes
+        if let Some(name_len) = bytes.get(position).filter(|_| position < extra_data_end) {
+            let name_len = *name_len as usize + 1;
+            let name_end = position + name_len + (4 - name_len % 4) % 4;
+            if name_end <= extra_data_end {
+                self.check_padding(bytes, position + name_len..name_end);
+                self.validate_additional_layer_info(bytes, name_end..extra_data_end);
+            }
+        }
+
+        Some((
+            Record::new(top, left, bottom, right, channels, mask),
+            extra_data_end,
+        ))
+    }
+
+    /// A series of blocks of additional layer information
+    ///
+    /// | Length   | Description                                                       |
+    /// |----------|-------------------------------------------------------------------|
+    /// | 4        | Signature: '8BIM' or '8B64'                                       |
+    /// | 4        | Key                                                               |
+    /// | 4        | Length of the data to follow                                      |
+    /// | Variable | Data                                                              |
+    fn validate_additional_layer_info(&mut self, bytes: &[u8], range: Range<usize>) {
+        let mut position = range.start;
+
+        let has_signature = |position: usize| {
+            LAYER_INFO_SIGNATURES
+                .iter()
+                .any(|expected| bytes.get(position..position + 4) == Some(&expected[..]))
+        };
+
+        // The last block can be followed by a few bytes of padding
+        while position + 12 <= range.end {
+            let offset = position;
+            if !has_signature(position) {
+                self.issues
+                    .push(ValidationIssue::InvalidLayerInfoSignature { offset });
+                return;
+            }
+
+            let len = read_u32(bytes, position + 8).unwrap_or(0) as u64;
+            let end = position as u64 + 12 + len;
+            if end > range.end as u64 {
+                self.issues
+                    .push(ValidationIssue::LayerInfoOutOfBounds { offset });
+                return;
+            }
+            position = end as usize;
+
+            // Photoshop pads the data of some blocks to a multiple of 4 bytes, even though the
+            // length doesn't include the padding
+            let padding = (4 - len as usize % 4) % 4;
+            if !has_signature(position) && has_signature(position + padding) {
+                self.check_padding(bytes, position..position + padding);
+                position += padding;
+            }
+        }
+
+        self.check_padding(bytes, position..range.end);
+    }
+
+    /// The length that a channel with the given size needs, or `None` if it can't be known
+    /// because the channel is zip compressed or the depth is invalid.
+    fn expected_channel_len(
+        &mut self,
+        data: &[u8],
+        rows: u64,
+        columns: u64,
+        header: &Header,
+        record: usize,
+    ) -> Option<u64> {
+        if data.len() < 2 {
+            return Some(2);
+        }
+
+        let compression = u16::from_be_bytes([data[0], data[1]]);
+        match compression {
+            0 => Some(2 + channel_byte_count(columns, rows, header.depth)?),
+            1 => Some(2 + rle_len(&data[2..], rows)),
+            2 | 3 => None,
+            _ => {
+                self.issues.push(ValidationIssue::InvalidCompression {
+                    record: Some(record),
+                    compression,
+                });
+                None
+            }
+        }
+    }
+
+    /// The composite image, which is every channel of the PSD one after the other
+    fn validate_image_data(&mut self, data: &[u8], header: &Header) {
+        // PSDs that were saved without a composite image might not have any image data
+        if data.is_empty() {
+            return;
+        }
+        if data.len() < 2 {
+            self.issues.push(ValidationIssue::CompositeLengthMismatch {
+                expected: 2,
+                actual: data.len() as u64,
+            });
+            return;
+        }
+
+        let rows = header.height * header.channel_count;
+        let compression = u16::from_be_bytes([data[0], data[1]]);
+        let expected = match compression {
+            0 => channel_byte_count(header.width, header.height, header.depth)
+                .map(|len| 2 + len * header.channel_count),
+            1 => Some(2 + rle_len(&data[2..], rows)),
+            2 | 3 => None,
+            _ => {
+                self.issues.push(ValidationIssue::InvalidCompression {
+                    record: None,
+                    compression,
+                });
+                None
+            }
+        };
+
+        if let Some(expected) = expected.filter(|expected| *expected != data.len() as u64) {
+            self.issues.push(ValidationIssue::CompositeLengthMismatch {
+                expected,
+                actual: data.len() as u64,
+            });
+        }
+    }
+
+    /// Report every byte in the range that isn't zero
+    fn check_padding(&mut self, bytes: &[u8], range: Range<usize>) {
+        for offset in range {
+            if bytes.get(offset).is_some_and(|byte| *byte != 0) {
+                self.issues.push(ValidationIssue::NonZeroPadding { offset });
+            }
+        }
+    }
+}
+
+impl Record {
+    fn new(
+        top: i64,
+        left: i64,
+        bottom: i64,
+        right: i64,
+        channels: Vec<(i16, u64)>,
+        mask: Option<LayerMask>,
+    ) -> Record {
+        Record {
+            rows: (bottom - top).max(0) as u64,
+            columns: (right - left).max(0) as u64,
+            channels,
+            mask,
+        }
+    }
+
+    /// The number of rows and columns of the channel, since masks have their own rectangles
+    fn channel_size(&self, channel_id: i16) -> (u64, u64) {
+        let mask_rect = match channel_id {
+            -2 => self.mask.as_ref().and_then(|mask| mask.bounds()),
+            -3 => self.mask.as_ref().and_then(|mask| mask.real_bounds()),
+            _ => return (self.rows, self.columns),
+        };
+
+        mask_rect.map_or((0, 0), |rect| (rect.height() as u64, rect.width() as u64))
+    }
+}
+
+/// The length of RLE compressed rows, which are a table of each row's 2 byte length followed by
+/// the rows. Only the table needs to be present.
+fn rle_len(data: &[u8], rows: u64) -> u64 {
+    let table_len = rows * 2;
+    let row_lens: u64 = data
+        .chunks_exact(2)
+        .take(rows as usize)
+        .map(|row_len| u16::from_be_bytes([row_len[0], row_len[1]]) as u64)
+        .sum();
+
+    table_len + row_lens
+}
+
+fn read_u32(bytes: &[u8], position: usize) -> Option<u32> {
+    let bytes = bytes.get(position..position + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
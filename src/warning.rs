@@ -0,0 +1,91 @@
+//! Problems that were found while parsing a PSD that didn't stop it from being parsed.
+
+use thiserror::Error;
+
+use crate::prelude::*;
+use crate::psd_channel::{PsdChannelCompression, PsdChannelKind};
+
+use crate::sections::image_resources_section::ImageResourcesSectionError;
+use crate::sections::layer_and_mask_information_section::layer::PsdLayerError;
+
+/// Something that was left out of a PSD while parsing it.
+///
+/// Malformed layers and resources are only skipped when [`crate::PsdOptions::recover`] is
+/// turned on, otherwise they fail the parse. Data that we don't support yet, such as image
+/// resources and additional layer information that we don't parse, is always reported.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum PsdWarning {
+    /// A malformed layer record was skipped, along with its channels
+    #[error("Skipped layer record {record}: '{error}'.")]
+    SkippedLayer {
+        /// The position of the layer record in the file, where 0 is the bottom most record
+        record: usize,
+        /// Why the layer record couldn't be read
+        error: PsdLayerError,
+    },
+    /// A malformed image resource was skipped
+    #[error("Skipped image resource {resource_id}: '{error}'.")]
+    SkippedResource {
+        /// The ID of the resource
+        resource_id: i16,
+        /// Why the resource couldn't be read
+        error: ImageResourcesSectionError,
+    },
+    /// The image resources after a malformed resource block were skipped, since there is no way
+    /// to know where the next block starts
+    #[error("Skipped the remaining image resources: '{error}'.")]
+    SkippedRemainingResources {
+        /// Why the resource block couldn't be read
+        error: ImageResourcesSectionError,
+    },
+    /// An image resource that we don't parse yet was ignored
+    #[error("Ignored image resource {resource_id}.")]
+    IgnoredResource {
+        /// The ID of the resource
+        resource_id: i16,
+    },
+    /// A block of additional layer information that we don't parse yet was ignored
+    #[error("Ignored additional layer information '{key}'.")]
+    IgnoredLayerInfo {
+        /// The name of the layer that the block belongs to, or `None` for the blocks that
+        /// follow the layers and apply to the whole PSD
+        layer: Option<String>,
+        /// The four character key of the block, such as `lfx2`
+        key: String,
+    },
+    /// A block of additional layer information couldn't be read, so the layer was read without
+    /// it
+    #[error("Skipped additional layer information '{key}' of layer '{layer}': '{error}'.")]
+    SkippedLayerInfo {
+        /// The name of the layer that the block belongs to
+        layer: String,
+        /// The four character key of the block, such as `lfx2`
+        key: String,
+        /// Why the block couldn't be read
+        error: PsdLayerError,
+    },
+    /// A layer channel uses a compression that we can't decompress yet, so it was left empty
+    #[error(
+        "Layer '{layer}' has a {channel:?} channel with unsupported compression {compression:?}."
+    )]
+    UnsupportedLayerCompression {
+        /// The name of the layer
+        layer: String,
+        /// The channel that was left empty
+        channel: PsdChannelKind,
+        /// How the channel is compressed
+        compression: PsdChannelCompression,
+    },
+    /// The composite image uses a compression that we can't decompress yet, so it was left out
+    /// and the layers are flattened instead
+    #[error("The composite image has unsupported compression {compression:?}.")]
+    UnsupportedCompositeCompression {
+        /// How the composite image is compressed
+        compression: PsdChannelCompression,
+    },
+    /// The RLE compressed composite image is shorter than its scanline byte counts say, so it
+    /// was left out and the layers are flattened instead
+    #[error("The composite image's compressed data was cut short.")]
+    TruncatedComposite,
+}
@@ -0,0 +1,104 @@
+//! Threads that decompress large channels in parallel. They are started the first time that a
+//! channel is split across them and are kept for the rest of the process, so that decoding a PSD
+//! with many layers doesn't start new threads for every channel.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+use crate::prelude::*;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads that run jobs from a shared queue
+pub(crate) struct WorkerPool {
+    jobs: Mutex<Sender<Job>>,
+    threads: usize,
+}
+
+/// The pool of one thread per core that is shared by every PSD
+pub(crate) fn workers() -> &'static WorkerPool {
+    static WORKERS: OnceLock<WorkerPool> = OnceLock::new();
+
+    WORKERS.get_or_init(|| {
+        let threads = thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+
+        WorkerPool::new(threads)
+    })
+}
+
+impl WorkerPool {
+    fn new(threads: usize) -> WorkerPool {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+
+        // Threads that the OS refused to start are left out, see `WorkerPool::threads`
+        let threads = (0..threads)
+            .filter(|_| {
+                let queue = Arc::clone(&queue);
+                thread::Builder::new()
+                    .name("psd-worker".to_string())
+                    .spawn(move || loop {
+                        // The queue is only locked while waiting for the next job
+                        let job = match queue.lock().map(|queue| queue.recv()) {
+                            Ok(Ok(job)) => job,
+                            _ => return,
+                        };
+
+                        // A job that panics is reported by `map`, and the thread moves on to the
+                        // next job
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    })
+                    .is_ok()
+            })
+            .count();
+
+        WorkerPool {
+            jobs: Mutex::new(jobs),
+            threads,
+        }
+    }
+
+    /// The number of threads that run jobs, which can be 0 if none of them could be started
+    pub(crate) fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Run the jobs on the pool's threads and return their outputs in the same order as the
+    /// jobs, waiting until all of them have finished.
+    ///
+    /// Panics if one of the jobs panicked. There must be at least one thread.
+    pub(crate) fn map<T, F>(&self, jobs: impl IntoIterator<Item = F>) -> Vec<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (outputs, finished) = mpsc::channel();
+
+        let mut count = 0;
+        {
+            let queue = self.jobs.lock().unwrap();
+            for (idx, job) in jobs.into_iter().enumerate() {
+                let outputs = outputs.clone();
+                let job: Job = Box::new(move || {
+                    let _ = outputs.send((idx, job()));
+                });
+                queue
+                    .send(job)
+                    .expect("The worker threads are never stopped");
+                count += 1;
+            }
+        }
+
+        // Every job holds a sender until it has run, so this ends once they are all done
+        drop(outputs);
+        let mut finished: Vec<(usize, T)> = finished.iter().collect();
+        assert_eq!(finished.len(), count, "A worker thread panicked");
+
+        finished.sort_by_key(|(idx, _)| *idx);
+        finished.into_iter().map(|(_, output)| output).collect()
+    }
+}
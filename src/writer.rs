@@ -0,0 +1,486 @@
+//! Write PSDs.
+//!
+//! The writer currently writes 8 bit RGB PSDs with a composite image and layers of pixels.
+//!
+//! ```ignore
+//! let bytes = PsdWriter::new(width, height)
+//!     .composite_rgba(rgba)
+//!     .layer(WriterLayer::new("Sprite", 16, 16, sprite_rgba).position(8, 8))
+//!     .to_bytes()?;
+//!
+//! std::fs::write("flattened.psd", bytes)?;
+//! ```
+
+use thiserror::Error;
+
+use crate::prelude::*;
+use crate::psd_channel::rle_compress;
+use crate::sections::file_header_section::EXPECTED_PSD_SIGNATURE;
+use crate::{BlendMode, PsdError};
+
+/// Photoshop won't open PSDs that are wider or taller than this
+const MAX_PSD_SIZE: u32 = 30_000;
+
+/// Red, green, blue and transparency
+const CHANNEL_COUNT: u16 = 4;
+
+/// The IDs of the transparency, red, green and blue channels of a layer, along with the offset
+/// of the channel within an RGBA pixel
+const LAYER_CHANNELS: [(i16, usize); 4] = [(-1, 3), (0, 0), (1, 1), (2, 2)];
+
+/// The version info resource, which says whether the composite image is real
+const RESOURCE_VERSION_INFO: i16 = 1057;
+
+/// How channel data is compressed when it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriterCompression {
+    /// The channels are written without any compression
+    Raw,
+    /// Every scanline of every channel is compressed with PackBits. This is what Photoshop
+    /// uses, and is much smaller for images with flat areas of color.
+    #[default]
+    Rle,
+}
+
+/// Options that control how a PSD is written.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WriterOptions {
+    /// How channel data is compressed. Defaults to [`WriterCompression::Rle`].
+    pub compression: WriterCompression,
+}
+
+/// A PSD could not be written
+#[derive(Debug, PartialEq, Error)]
+pub enum PsdWriterError {
+    /// The canvas is empty, or is larger than Photoshop allows
+    #[error("A {width}x{height} PSD can't be written. PSDs must be 1x1 to 30000x30000.")]
+    InvalidSize {
+        /// The width of the canvas
+        width: u32,
+        /// The height of the canvas
+        height: u32,
+    },
+
+    /// The RGBA pixels don't cover the canvas
+    #[error("Expected {expected} bytes of RGBA pixels, but there were {actual}.")]
+    InvalidRgbaLength {
+        /// Four bytes for every pixel of the canvas
+        expected: usize,
+        /// The number of bytes that were given
+        actual: usize,
+    },
+
+    /// The PSD that was written couldn't be read back, such as to flatten its layers into the
+    /// composite image
+    #[error("The written PSD couldn't be read: '{0}'.")]
+    ReadError(PsdError),
+}
+
+/// Writes a PSD.
+#[derive(Debug, Clone)]
+pub struct PsdWriter {
+    width: u32,
+    height: u32,
+    composite: Option<Vec<u8>>,
+    layers: Vec<WriterLayer>,
+    options: WriterOptions,
+}
+
+/// A layer of pixels to write.
+#[derive(Debug, Clone)]
+pub struct WriterLayer {
+    name: String,
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    opacity: u8,
+    visible: bool,
+    blend_mode: BlendMode,
+}
+
+impl PsdWriter {
+    /// Start writing a PSD with the given canvas size.
+    pub fn new(width: u32, height: u32) -> PsdWriter {
+        PsdWriter {
+            width,
+            height,
+            composite: None,
+            layers: vec![],
+            options: WriterOptions::default(),
+        }
+    }
+
+    /// Set the options that control how the PSD is written.
+    pub fn options(mut self, options: WriterOptions) -> PsdWriter {
+        self.options = options;
+        self
+    }
+
+    /// Set the final image of the PSD, as RGBA pixels (`vec![R, G, B, A, R, G, B, A, ...]`).
+    ///
+    /// Without a composite image a PSD without layers is fully transparent, and a PSD with
+    /// layers is marked as not having a composite image (see [`Psd::has_composite_image`]), so
+    /// that readers flatten the layers instead.
+    ///
+    /// [`Psd::has_composite_image`]: crate::Psd::has_composite_image
+    pub fn composite_rgba(mut self, rgba: Vec<u8>) -> PsdWriter {
+        self.composite = Some(rgba);
+        self
+    }
+
+    /// Add a layer below the layers that have already been added, so that the layers are in
+    /// the same order as [`Psd::layers`].
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layer(mut self, layer: WriterLayer) -> PsdWriter {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Write the PSD.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PsdWriterError> {
+        let (width, height) = (self.width, self.height);
+        if width == 0 || height == 0 || width > MAX_PSD_SIZE || height > MAX_PSD_SIZE {
+            return Err(PsdWriterError::InvalidSize { width, height });
+        }
+
+        let pixel_count = width as usize * height as usize;
+        if let Some(composite) = &self.composite {
+            check_rgba_len(composite, width, height)?;
+        }
+        for layer in self.layers.iter() {
+            check_rgba_len(&layer.rgba, layer.width, layer.height)?;
+        }
+
+        let mut bytes = vec![];
+        self.write_file_header(&mut bytes);
+
+        // An empty color mode data section
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+
+        write_with_len(&mut bytes, |bytes| self.write_image_resources(bytes));
+        write_with_len(&mut bytes, |bytes| {
+            self.write_layer_and_mask_information(bytes)
+        });
+
+        let transparent;
+        let composite = match &self.composite {
+            Some(composite) => composite,
+            None => {
+                transparent = vec![0; pixel_count * 4];
+                &transparent
+            }
+        };
+        self.write_image_data(&mut bytes, composite);
+
+        Ok(bytes)
+    }
+
+    /// | Length | Description                                  |
+    /// |--------|----------------------------------------------|
+    /// | 4      | Signature: '8BPS'                            |
+    /// | 2      | Version: 1                                   |
+    /// | 6      | Reserved: must be zero                       |
+    /// | 2      | The number of channels                       |
+    /// | 4      | The height of the image in pixels            |
+    /// | 4      | The width of the image in pixels             |
+    /// | 2      | Depth: the number of bits per channel        |
+    /// | 2      | The color mode of the file: RGB = 3          |
+    fn write_file_header(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&EXPECTED_PSD_SIGNATURE);
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&[0; 6]);
+        bytes.extend_from_slice(&CHANNEL_COUNT.to_be_bytes());
+        bytes.extend_from_slice(&self.height.to_be_bytes());
+        bytes.extend_from_slice(&self.width.to_be_bytes());
+        bytes.extend_from_slice(&8u16.to_be_bytes());
+        bytes.extend_from_slice(&3u16.to_be_bytes());
+    }
+
+    /// | Length   | Description                                       |
+    /// |----------|---------------------------------------------------|
+    /// | 4        | Signature: '8BIM'                                 |
+    /// | 2        | Unique identifier for the resource                |
+    /// | Variable | Name: Pascal string, padded to make the size even |
+    /// | 4        | Actual size of resource data that follows         |
+    /// | Variable | The resource data, padded to make the size even   |
+    fn write_image_resources(&self, bytes: &mut Vec<u8>) {
+        // Only needed to say that there is no composite image
+        if self.composite.is_some() || self.layers.is_empty() {
+            return;
+        }
+
+        // Version, has real merged data, writer name, reader name and file version
+        let mut version_info = vec![];
+        version_info.extend_from_slice(&1u32.to_be_bytes());
+        version_info.push(0);
+        write_unicode_string(&mut version_info, "psd");
+        write_unicode_string(&mut version_info, "psd");
+        version_info.extend_from_slice(&1u32.to_be_bytes());
+
+        bytes.extend_from_slice(b"8BIM");
+        bytes.extend_from_slice(&RESOURCE_VERSION_INFO.to_be_bytes());
+        // An empty name
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(&(version_info.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&version_info);
+        if version_info.len() % 2 == 1 {
+            bytes.push(0);
+        }
+    }
+
+    /// | Length   | Description                                                     |
+    /// |----------|-----------------------------------------------------------------|
+    /// | 4        | Length of the layer info section, rounded up to a multiple of 2 |
+    /// | 2        | Layer count                                                     |
+    /// | Variable | Layer records, from the bottom layer to the top layer           |
+    /// | Variable | Channel image data, in the same order as the layer records      |
+    /// | 4        | Length of the global layer mask info (= 0)                      |
+    fn write_layer_and_mask_information(&self, bytes: &mut Vec<u8>) {
+        write_with_len(bytes, |bytes| {
+            if self.layers.is_empty() {
+                return;
+            }
+
+            let start = bytes.len();
+            bytes.extend_from_slice(&(self.layers.len() as i16).to_be_bytes());
+
+            let channels: Vec<Vec<Vec<u8>>> = self
+                .layers
+                .iter()
+                .rev()
+                .map(|layer| layer.channel_data(self.options.compression))
+                .collect();
+
+            for (layer, channels) in self.layers.iter().rev().zip(channels.iter()) {
+                layer.write_record(bytes, channels);
+            }
+            for channel in channels.iter().flatten() {
+                bytes.extend_from_slice(channel);
+            }
+
+            if (bytes.len() - start) % 2 == 1 {
+                bytes.push(0);
+            }
+        });
+
+        // An empty global layer mask info
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+    }
+
+    /// Write the composite image in planar order, one channel after another.
+    fn write_image_data(&self, bytes: &mut Vec<u8>, rgba: &[u8]) {
+        let width = self.width as usize;
+        let channels: Vec<Vec<u8>> = (0..CHANNEL_COUNT as usize)
+            .map(|offset| rgba.iter().skip(offset).step_by(4).copied().collect())
+            .collect();
+
+        match self.options.compression {
+            WriterCompression::Raw => {
+                bytes.extend_from_slice(&0u16.to_be_bytes());
+                for channel in channels.iter() {
+                    bytes.extend_from_slice(channel);
+                }
+            }
+            WriterCompression::Rle => {
+                bytes.extend_from_slice(&1u16.to_be_bytes());
+
+                let scanlines: Vec<Vec<u8>> = channels
+                    .iter()
+                    .flat_map(|channel| channel.chunks(width).map(rle_compress))
+                    .collect();
+
+                // The byte counts of every scanline come before all of the compressed data
+                for scanline in scanlines.iter() {
+                    bytes.extend_from_slice(&(scanline.len() as u16).to_be_bytes());
+                }
+                for scanline in scanlines.iter() {
+                    bytes.extend_from_slice(scanline);
+                }
+            }
+        }
+    }
+}
+
+impl WriterLayer {
+    /// A layer of RGBA pixels (`vec![R, G, B, A, R, G, B, A, ...]`) at the top left of the
+    /// canvas.
+    pub fn new(name: impl Into<String>, width: u32, height: u32, rgba: Vec<u8>) -> WriterLayer {
+        WriterLayer {
+            name: name.into(),
+            left: 0,
+            top: 0,
+            width,
+            height,
+            rgba,
+            opacity: 255,
+            visible: true,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+
+    /// Place the layer's top left corner at the given pixel of the canvas.
+    ///
+    /// Layers can extend past the edges of the canvas.
+    pub fn position(mut self, left: i32, top: i32) -> WriterLayer {
+        self.left = left;
+        self.top = top;
+        self
+    }
+
+    /// Set the opacity of the layer, from 0 (transparent) to 255 (opaque).
+    pub fn opacity(mut self, opacity: u8) -> WriterLayer {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Set whether the layer is visible.
+    pub fn visible(mut self, visible: bool) -> WriterLayer {
+        self.visible = visible;
+        self
+    }
+
+    /// Set how the layer is blended with the layers below it.
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> WriterLayer {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// The data of each channel, including its compression, in the order of `LAYER_CHANNELS`.
+    fn channel_data(&self, compression: WriterCompression) -> Vec<Vec<u8>> {
+        let width = self.width as usize;
+
+        LAYER_CHANNELS
+            .iter()
+            .map(|(_, offset)| {
+                let channel: Vec<u8> = self.rgba.iter().skip(*offset).step_by(4).copied().collect();
+
+                let mut data = vec![];
+                match compression {
+                    WriterCompression::Raw => {
+                        data.extend_from_slice(&0u16.to_be_bytes());
+                        data.extend_from_slice(&channel);
+                    }
+                    WriterCompression::Rle => {
+                        data.extend_from_slice(&1u16.to_be_bytes());
+
+                        let scanlines: Vec<Vec<u8>> =
+                            channel.chunks(width.max(1)).map(rle_compress).collect();
+                        for scanline in scanlines.iter() {
+                            data.extend_from_slice(&(scanline.len() as u16).to_be_bytes());
+                        }
+                        for scanline in scanlines.iter() {
+                            data.extend_from_slice(scanline);
+                        }
+                    }
+                }
+
+                data
+            })
+            .collect()
+    }
+
+    /// | Length   | Description                                                                         |
+    /// |----------|-------------------------------------------------------------------------------------|
+    /// | 4 * 4    | Rectangle containing the contents of the layer: top, left, bottom, right            |
+    /// | 2        | Number of channels in the layer                                                     |
+    /// | 6 * n    | Channel information: 2 bytes for the channel ID and 4 for the length of its data    |
+    /// | 4        | Blend mode signature: '8BIM'                                                        |
+    /// | 4        | Blend mode key                                                                      |
+    /// | 1        | Opacity                                                                             |
+    /// | 1        | Clipping                                                                            |
+    /// | 1        | Flags                                                                               |
+    /// | 1        | Filler (zero)                                                                       |
+    /// | 4        | Length of the extra data field                                                      |
+    /// | Variable | Layer mask data, layer blending ranges, layer name and additional layer information |
+    fn write_record(&self, bytes: &mut Vec<u8>, channels: &[Vec<u8>]) {
+        let bottom = self.top + self.height as i32;
+        let right = self.left + self.width as i32;
+        for edge in [self.top, self.left, bottom, right] {
+            bytes.extend_from_slice(&edge.to_be_bytes());
+        }
+
+        bytes.extend_from_slice(&(LAYER_CHANNELS.len() as u16).to_be_bytes());
+        for ((id, _), data) in LAYER_CHANNELS.iter().zip(channels) {
+            bytes.extend_from_slice(&id.to_be_bytes());
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        }
+
+        bytes.extend_from_slice(b"8BIM");
+        bytes.extend_from_slice(&self.blend_mode.as_key());
+        bytes.push(self.opacity);
+        bytes.push(0);
+        // Bit 1 is set for hidden layers, and bit 3 says that bit 4 is meaningful
+        let flags = if self.visible { 0b1000 } else { 0b1010 };
+        bytes.push(flags);
+        bytes.push(0);
+
+        write_with_len(bytes, |bytes| {
+            // No layer mask or blending ranges
+            bytes.extend_from_slice(&0u32.to_be_bytes());
+            bytes.extend_from_slice(&0u32.to_be_bytes());
+
+            // The name as a Pascal string padded to a multiple of 4 bytes. Since Photoshop
+            // reads it as Mac Roman, anything but ASCII is replaced, and the full name is
+            // stored in the unicode layer name.
+            let name: Vec<u8> = self
+                .name
+                .chars()
+                .take(255)
+                .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+                .collect();
+            let start = bytes.len();
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(&name);
+            while (bytes.len() - start) % 4 != 0 {
+                bytes.push(0);
+            }
+
+            bytes.extend_from_slice(b"8BIMluni");
+            write_with_len(bytes, |bytes| {
+                let start = bytes.len();
+                write_unicode_string(bytes, &self.name);
+
+                // Padded to a multiple of 4 bytes, like Photoshop does
+                while (bytes.len() - start) % 4 != 0 {
+                    bytes.push(0);
+                }
+            });
+        });
+    }
+}
+
+fn check_rgba_len(rgba: &[u8], width: u32, height: u32) -> Result<(), PsdWriterError> {
+    let expected = width as usize * height as usize * 4;
+    if rgba.len() != expected {
+        return Err(PsdWriterError::InvalidRgbaLength {
+            expected,
+            actual: rgba.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Write a 4 byte length followed by whatever the closure writes.
+fn write_with_len(bytes: &mut Vec<u8>, write: impl FnOnce(&mut Vec<u8>)) {
+    let len_idx = bytes.len();
+    bytes.extend_from_slice(&[0; 4]);
+
+    write(bytes);
+
+    let len = (bytes.len() - len_idx - 4) as u32;
+    bytes[len_idx..len_idx + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+/// A 4 byte count of UTF-16 code units followed by the code units
+fn write_unicode_string(bytes: &mut Vec<u8>, string: &str) {
+    let units: Vec<u16> = string.encode_utf16().collect();
+
+    bytes.extend_from_slice(&(units.len() as u32).to_be_bytes());
+    for unit in units {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use psd::geometry::Rect;
+use psd::{AnnotationKind, ColorSpace, Psd};
+
+/// Annotations are stored at the end of the layer and mask information section.
+///
+/// cargo test --test annotations read_annotations -- --exact
+#[test]
+fn read_annotations() -> Result<()> {
+    let psd = include_bytes!("./fixtures/annotations-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert!(psd.warnings().is_empty());
+
+    let annotations = psd.annotations();
+    assert_eq!(annotations.len(), 2);
+
+    let note = &annotations[0];
+    assert_eq!(note.kind(), AnnotationKind::Text);
+    assert!(note.is_open());
+    assert_eq!(note.author(), "Ada");
+    assert_eq!(note.name(), "");
+    assert_eq!(note.modified(), "2024-01-02");
+    assert_eq!(note.text(), Some("Fix the café sign"));
+    assert_eq!(
+        note.icon_rect(),
+        Rect {
+            top: 0,
+            left: 1,
+            bottom: 0,
+            right: 1
+        }
+    );
+    assert_eq!(
+        note.popup_rect(),
+        Rect {
+            top: 0,
+            left: 0,
+            bottom: 39,
+            right: 119
+        }
+    );
+    assert_eq!(note.color_space(), ColorSpace::Rgb);
+    assert_eq!(note.color(), [65535, 65535, 0, 0]);
+
+    let sound = &annotations[1];
+    assert_eq!(sound.kind(), AnnotationKind::Sound);
+    assert!(!sound.is_open());
+    assert_eq!(sound.author(), "Grace");
+    assert_eq!(sound.text(), None);
+
+    Ok(())
+}
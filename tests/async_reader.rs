@@ -0,0 +1,175 @@
+#![cfg(feature = "async")]
+
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use anyhow::Result;
+use futures_io::{AsyncRead, AsyncSeek};
+use psd::{Psd, PsdError, PsdLimitError, PsdOptions};
+
+const PSD_8X8: &[u8] = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+
+/// Reading a PSD from an async reader gives the same result as parsing its bytes.
+///
+/// cargo test --features async --test async_reader from_async_reader -- --exact
+#[test]
+fn from_async_reader() -> Result<()> {
+    let psd = block_on(Psd::from_async_reader(AsyncCursor::new(PSD_8X8)))?;
+    let expected = Psd::from_bytes(PSD_8X8)?;
+
+    assert_eq!(psd.layers().len(), expected.layers().len());
+    for (layer, expected) in psd.layers().iter().zip(expected.layers()) {
+        assert_eq!(layer.name(), expected.name());
+        assert_eq!(layer.rgba(), expected.rgba());
+    }
+    assert_eq!(psd.rgba(), expected.rgba());
+    assert_eq!(psd.section_layout(), expected.section_layout());
+
+    Ok(())
+}
+
+/// Nothing but the file header is read from PSDs that are larger than the limits.
+///
+/// cargo test --features async --test async_reader canvas_too_large -- --exact
+#[test]
+fn canvas_too_large() {
+    let options = PsdOptions {
+        max_width: 4,
+        ..PsdOptions::default()
+    };
+
+    let mut reader = AsyncCursor::new(PSD_8X8);
+    let err = block_on(Psd::from_async_reader_with_options(&mut reader, &options)).unwrap_err();
+
+    assert_eq!(
+        err,
+        PsdError::LimitError(PsdLimitError::CanvasTooLarge {
+            width: 8,
+            height: 8,
+            max_width: 4,
+            max_height: 30_000,
+        })
+    );
+    assert_eq!(reader.position, 26);
+}
+
+/// A PSD that ends in the middle of a section is an error.
+///
+/// cargo test --features async --test async_reader truncated -- --exact
+#[test]
+fn truncated() {
+    let truncated = &PSD_8X8[..100];
+
+    let err = block_on(Psd::from_async_reader(AsyncCursor::new(truncated))).unwrap_err();
+    assert!(matches!(
+        err,
+        PsdError::ReadError {
+            kind: io::ErrorKind::UnexpectedEof,
+            ..
+        }
+    ));
+}
+
+/// The reader's errors are returned along with their message.
+///
+/// cargo test --features async --test async_reader reader_error -- --exact
+#[test]
+fn reader_error() {
+    let err = block_on(Psd::from_async_reader(FailingReader)).unwrap_err();
+
+    assert_eq!(
+        err,
+        PsdError::ReadError {
+            kind: io::ErrorKind::ConnectionReset,
+            message: "the download was interrupted".to_string(),
+        }
+    );
+}
+
+/// A reader whose reads and seeks fail, like a dropped connection.
+struct FailingReader;
+
+impl AsyncRead for FailingReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "the download was interrupted",
+        )))
+    }
+}
+
+impl AsyncSeek for FailingReader {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "the download was interrupted",
+        )))
+    }
+}
+
+/// An in memory reader that returns at most a few bytes per read, like a network stream.
+struct AsyncCursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> AsyncCursor<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        AsyncCursor { bytes, position: 0 }
+    }
+}
+
+impl AsyncRead for AsyncCursor<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let start = self.position.min(self.bytes.len());
+        let len = buf.len().min(self.bytes.len() - start).min(7);
+
+        buf[..len].copy_from_slice(&self.bytes[start..start + len]);
+        self.position += len;
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncSeek for AsyncCursor<'_> {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.bytes.len() as i64 + offset,
+        };
+        self.position = position as usize;
+
+        Poll::Ready(Ok(position as u64))
+    }
+}
+
+/// Run a future whose reader never returns `Poll::Pending`.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use psd::batch::PsdBatch;
+use psd::{Psd, PsdOptions};
+
+const PSDS: [&[u8]; 4] = [
+    include_bytes!("fixtures/rle-3-layer-8x8.psd"),
+    include_bytes!("fixtures/transparent-top-layer-2x1.psd"),
+    include_bytes!("fixtures/layer-effects-10x10.psd"),
+    include_bytes!("fixtures/3x3-opaque-center.psd"),
+];
+
+/// A batch flattens PSDs of different sizes one after another into the same pixels as
+/// flattening each of them on its own.
+///
+/// cargo test --test batch flatten_psds_in_a_batch -- --exact
+#[test]
+fn flatten_psds_in_a_batch() -> Result<()> {
+    let mut batch = PsdBatch::new();
+
+    for bytes in PSDS.iter().chain(PSDS.iter().rev()) {
+        let psd = Psd::from_bytes(bytes)?;
+
+        let image = batch.flatten(bytes.to_vec())?;
+        assert_eq!((image.width(), image.height()), (psd.width(), psd.height()));
+        assert_eq!(image.rgba(), psd.flatten_layers_rgba(&|_| true)?);
+
+        let hidden_top = batch.flatten_psd(&psd, &|(idx, _)| idx != 0);
+        assert_eq!(
+            hidden_top.rgba(),
+            psd.flatten_layers_rgba(&|(idx, _)| idx != 0)?
+        );
+
+        assert_eq!(batch.composite(bytes.to_vec())?.rgba(), psd.rgba());
+    }
+
+    Ok(())
+}
+
+/// The batch parses PSDs with its options, and keeps working after its buffers are released.
+///
+/// cargo test --test batch batch_options_and_release_buffers -- --exact
+#[test]
+fn batch_options_and_release_buffers() -> Result<()> {
+    let options = PsdOptions {
+        max_width: 4,
+        ..PsdOptions::default()
+    };
+    let mut batch = PsdBatch::with_options(options);
+
+    assert!(batch.flatten(PSDS[2].to_vec()).is_err());
+
+    batch.release_buffers();
+    let psd = Psd::from_bytes(PSDS[3])?;
+    assert_eq!(
+        batch.flatten(PSDS[3].to_vec())?.rgba(),
+        psd.flatten_layers_rgba(&|_| true)?
+    );
+
+    Ok(())
+}
+
+/// PSDs that are flattened across threads are returned in order.
+///
+/// cargo test --features parallel --test batch flatten_all_across_threads -- --exact
+#[cfg(feature = "parallel")]
+#[test]
+fn flatten_all_across_threads() -> Result<()> {
+    let psds: Vec<&[u8]> = PSDS.iter().cycle().take(20).copied().collect();
+    let owned = psds.iter().map(|bytes| bytes.to_vec()).collect();
+
+    let flattened = psd::batch::flatten_all(owned, &PsdOptions::default(), |idx, image| {
+        (idx, image.map(|image| image.rgba().to_vec()))
+    });
+
+    assert_eq!(flattened.len(), psds.len());
+    for (expected_idx, (idx, rgba)) in flattened.into_iter().enumerate() {
+        assert_eq!(idx, expected_idx);
+        let psd = Psd::from_bytes(psds[idx])?;
+        assert_eq!(rgba?, psd.flatten_layers_rgba(&|_| true)?);
+    }
+
+    Ok(())
+}
@@ -2,7 +2,7 @@
 //! (PathBuf, [f32; 4])
 
 use anyhow::Result;
-use psd::Psd;
+use psd::{blend_rgba, BlendMode, Psd};
 
 const BLEND_NORMAL_BLUE_RED_PIXEL: [u8; 4] = [85, 0, 170, 192];
 const BLEND_MULTIPLY_BLUE_RED_PIXEL: [u8; 4] = [85, 0, 85, 192];
@@ -222,3 +222,95 @@ fn exclusion() -> Result<()> {
 
     Ok(())
 }
+
+/// The blend mode of each layer is exposed, along with the key that it is stored as.
+///
+/// cargo test --test blend layer_blend_mode -- --exact
+#[test]
+fn layer_blend_mode() -> Result<()> {
+    let psd = include_bytes!("./fixtures/blending/blue-red-1x1-multiply.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let blend_modes: Vec<BlendMode> = psd.layers().iter().map(|l| l.blend_mode()).collect();
+    assert!(blend_modes.contains(&BlendMode::Multiply));
+    assert_eq!(BlendMode::Multiply.as_key(), *b"mul ");
+
+    Ok(())
+}
+
+/// Blend modes convert to and from their keys, and unknown keys are treated as normal.
+///
+/// cargo test --test blend blend_mode_from_key -- --exact
+#[test]
+fn blend_mode_from_key() {
+    for blend_mode in [
+        BlendMode::PassThrough,
+        BlendMode::LinearDodge,
+        BlendMode::Luminosity,
+    ] {
+        assert_eq!(BlendMode::from(blend_mode.as_key()), blend_mode);
+    }
+
+    assert_eq!(BlendMode::from(*b"what"), BlendMode::Normal);
+}
+
+/// A custom blender is used to blend layers, and can fall back to the crate's blending.
+///
+/// cargo test --test blend flatten_with_blender -- --exact
+#[test]
+fn flatten_with_blender() -> Result<()> {
+    let psd = include_bytes!("./fixtures/blending/blue-red-1x1-multiply.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let image = psd.flatten_layers_rgba_with_blender(&|_| true, &|_, _, _| [1, 2, 3, 4])?;
+    assert_eq!(image[0..4], [1, 2, 3, 4]);
+
+    let screen_instead_of_multiply = |top, bottom, blend_mode| match blend_mode {
+        BlendMode::Multiply => blend_rgba(top, bottom, BlendMode::Screen),
+        _ => blend_rgba(top, bottom, blend_mode),
+    };
+    let image = psd.flatten_layers_rgba_with_blender(&|_| true, &screen_instead_of_multiply)?;
+    assert_eq!(image[0..4], BLEND_SCREEN_BLUE_RED_PIXEL);
+
+    Ok(())
+}
+
+/// Blending in linear light avoids darkening colors that are blended over each other.
+///
+/// cargo test --test blend flatten_linear -- --exact
+#[test]
+fn flatten_linear() -> Result<()> {
+    let white = (1, 1, vec![255, 255, 255, 128]);
+    let black = (1, 1, vec![0, 0, 0, 255]);
+    let psd = psd::compose::from_images(vec![("White", white, 0, 0), ("Black", black, 0, 0)])?;
+
+    let image = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(image, [128, 128, 128, 255]);
+
+    let image = psd.flatten_layers_rgba_linear(&|_| true)?;
+    assert_eq!(image, [188, 188, 188, 255]);
+
+    Ok(())
+}
+
+/// Blend modes are named by their four character keys, and every key round trips through text.
+///
+/// cargo test --test blend blend_mode_keys -- --exact
+#[test]
+fn blend_mode_keys() {
+    for blend_mode in BlendMode::ALL {
+        assert_eq!(BlendMode::from_key(blend_mode.as_key()), Some(blend_mode));
+        assert_eq!(blend_mode.to_string().parse(), Ok(blend_mode));
+    }
+
+    assert_eq!("norm".parse(), Ok(BlendMode::Normal));
+    assert_eq!("hue".parse(), Ok(BlendMode::Hue));
+    assert_eq!("hue ".parse(), Ok(BlendMode::Hue));
+    assert_eq!(BlendMode::Luminosity.to_string(), "lum");
+
+    let error = "Norm".parse::<BlendMode>().unwrap_err();
+    assert_eq!(error.key(), "Norm");
+    assert!("".parse::<BlendMode>().is_err());
+    assert!("normal".parse::<BlendMode>().is_err());
+    assert_eq!(BlendMode::from_key(*b"nope"), None);
+}
@@ -0,0 +1,106 @@
+use psd::capability::UnsupportedFeature;
+use psd::writer::{PsdWriter, WriterLayer};
+use psd::{BlendMode, Psd, PsdChannelCompression};
+
+const UNSUPPORTED_FEATURES: &[u8] = include_bytes!("fixtures/unsupported-features-2x2.psd");
+const ZIP_COMPRESSED: &[u8] = include_bytes!("fixtures/zip-compressed-1x1.psd");
+const TEXT_LAYER: &[u8] = include_bytes!("fixtures/text-layer-8x4.psd");
+const LAYER_EFFECTS: &[u8] = include_bytes!("fixtures/layer-effects-10x10.psd");
+
+/// Adjustment layers and effects that aren't rendered are reported.
+///
+/// cargo test --test capability_report adjustment_layers_and_effects -- --exact
+#[test]
+fn adjustment_layers_and_effects() {
+    let psd = Psd::from_bytes(UNSUPPORTED_FEATURES).unwrap();
+    let report = psd.capability_report();
+
+    assert_eq!(
+        report.gaps(),
+        [
+            UnsupportedFeature::AdjustmentLayers { count: 1 },
+            UnsupportedFeature::LayerEffects {
+                layers: 1,
+                effects: vec!["FrFX".to_string(), "OrGl".to_string()],
+            },
+        ]
+    );
+    assert!(!report.is_fully_supported());
+    assert_eq!(report.gaps()[0].to_string(), "1 adjustment layers ignored.");
+}
+
+/// Channels with unsupported compression are counted, and the data that was ignored is listed.
+///
+/// cargo test --test capability_report compression_and_ignored_data -- --exact
+#[test]
+fn compression_and_ignored_data() {
+    let psd = Psd::from_bytes(ZIP_COMPRESSED).unwrap();
+
+    assert_eq!(
+        psd.capability_report().gaps(),
+        [
+            UnsupportedFeature::LayerCompression {
+                compression: PsdChannelCompression::ZipWithoutPrediction,
+                channels: 4,
+            },
+            UnsupportedFeature::CompositeCompression {
+                compression: PsdChannelCompression::ZipWithoutPrediction,
+            },
+            UnsupportedFeature::IgnoredResources {
+                resource_ids: vec![1036],
+            },
+            UnsupportedFeature::IgnoredLayerInfo {
+                keys: vec!["FMsk".to_string(), "brst".to_string()],
+            },
+        ]
+    );
+}
+
+/// Text layers are reported, while the effects that are rendered aren't.
+///
+/// cargo test --test capability_report text_layers_and_rendered_effects -- --exact
+#[test]
+fn text_layers_and_rendered_effects() {
+    let psd = Psd::from_bytes(TEXT_LAYER).unwrap();
+    assert_eq!(
+        psd.capability_report().gaps(),
+        [UnsupportedFeature::TextLayers { count: 1 }]
+    );
+
+    let psd = Psd::from_bytes(LAYER_EFFECTS).unwrap();
+    assert!(psd.capability_report().is_fully_supported());
+}
+
+/// Visible layers with blend modes that can't be blended are reported, once per blend mode.
+///
+/// cargo test --test capability_report unsupported_blend_modes -- --exact
+#[test]
+fn unsupported_blend_modes() {
+    let layer = |name: &str| WriterLayer::new(name, 1, 1, vec![255, 0, 0, 255]);
+    let bytes = PsdWriter::new(1, 1)
+        .layer(layer("Normal"))
+        .layer(layer("Hue").blend_mode(BlendMode::Hue))
+        .layer(layer("Dissolve").blend_mode(BlendMode::Dissolve))
+        .layer(layer("Another hue").blend_mode(BlendMode::Hue))
+        .layer(
+            layer("Hidden")
+                .blend_mode(BlendMode::HardMix)
+                .visible(false),
+        )
+        .to_bytes()
+        .unwrap();
+    let psd = Psd::from_bytes(&bytes).unwrap();
+
+    let report = psd.capability_report();
+    assert_eq!(
+        report.gaps(),
+        [UnsupportedFeature::BlendModes {
+            layers: 3,
+            blend_modes: vec![BlendMode::Dissolve, BlendMode::Hue],
+        }]
+    );
+    assert_eq!(
+        report.gaps()[0].to_string(),
+        "3 layers use blend modes that aren't supported: [Dissolve, Hue]."
+    );
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use psd::ColorMode;
 use psd::Psd;
 use psd::PsdDepth;
+use psd::{PsdChannelCompression, PsdChannelKind};
 
 /// cargo test --test channels one_channel_grayscale_raw_data -- --exact
 #[test]
@@ -60,3 +61,248 @@ fn two_channel_grayscale_raw_data() -> Result<()> {
 
     Ok(())
 }
+
+/// The second channel of a grayscale image is its transparency.
+///
+/// The edges of this PSD fade out, while the rest of it is opaque.
+///
+/// cargo test --test channels two_channel_grayscale_alpha -- --exact
+#[test]
+fn two_channel_grayscale_alpha() -> Result<()> {
+    let psd = include_bytes!("./fixtures/two-channel-8x8.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let composite = psd.rgba();
+    assert_eq!(composite.len(), 8 * 8 * 4);
+
+    let alpha = |x: usize, y: usize| composite[(y * 8 + x) * 4 + 3];
+
+    // Corners
+    assert_eq!(alpha(0, 0), 0xdb);
+    assert_eq!(alpha(7, 7), 0xdb);
+    // Center
+    assert_eq!(alpha(3, 3), 255);
+
+    // Grayscale pixels have the same red, green and blue
+    for pixel in composite.chunks(4) {
+        assert_eq!(pixel[0], pixel[1]);
+        assert_eq!(pixel[1], pixel[2]);
+    }
+
+    Ok(())
+}
+
+/// A layer's alpha can be read as a single channel buffer the size of the PSD.
+///
+/// cargo test --test channels layer_alpha_only -- --exact
+#[test]
+fn layer_alpha_only() -> Result<()> {
+    let psd = include_bytes!("./fixtures/3x3-opaque-center.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("OpaqueCenter").unwrap();
+    let alpha = layer.alpha_only();
+
+    #[rustfmt::skip]
+    assert_eq!(alpha, [
+        0, 0, 255,
+        0, 255, 0,
+        0, 0, 0,
+    ]);
+
+    // Every pixel of a layer without a transparency channel is opaque, but only within the
+    // layer's bounds.
+    let psd = include_bytes!("./fixtures/layer-at-pixel-3x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(
+        psd.layer_by_name("Background").unwrap().alpha_only(),
+        [255, 255, 255]
+    );
+    assert_eq!(psd.layer_by_name("Dot").unwrap().alpha_only(), [0, 0, 255]);
+
+    Ok(())
+}
+
+/// A layer's luminance can be read as a single channel buffer the size of the PSD.
+///
+/// cargo test --test channels layer_luminance -- --exact
+#[test]
+fn layer_luminance() -> Result<()> {
+    let psd = include_bytes!("./fixtures/layer-at-pixel-3x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    // Red and blue, using the Rec. 601 weights
+    assert_eq!(
+        psd.layer_by_name("Background").unwrap().luminance(),
+        [76, 76, 76]
+    );
+    assert_eq!(
+        psd.layer_by_name("Masked").unwrap().luminance(),
+        [29, 29, 29]
+    );
+
+    // Yellow, but only within the layer's bounds
+    assert_eq!(
+        psd.layer_by_name("In Hidden Group").unwrap().luminance(),
+        [226, 0, 0]
+    );
+
+    Ok(())
+}
+
+/// Layers that hang off of the edges of the PSD are cropped to the PSD.
+///
+/// cargo test --test channels single_channel_layer_outside_of_psd -- --exact
+#[test]
+fn single_channel_layer_outside_of_psd() -> Result<()> {
+    let psd = include_bytes!("./fixtures/negative-top-left-layer.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = &psd.layers()[0];
+    assert_eq!(layer.luminance().len(), 1);
+    assert_eq!(layer.alpha_only().len(), 1);
+
+    Ok(())
+}
+
+/// The kind, compression and stored length of every channel is available without decoding.
+///
+/// cargo test --test channels layer_channels -- --exact
+#[test]
+fn layer_channels() -> Result<()> {
+    let psd = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    // 8 scanlines of 2 byte lengths followed by 2 bytes of RLE data per scanline
+    let kinds: Vec<_> = psd
+        .layer_by_name("Red Layer")
+        .unwrap()
+        .channels()
+        .map(|(kind, compression, len)| {
+            assert_eq!(compression, PsdChannelCompression::RleCompressed);
+            (kind, len)
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (PsdChannelKind::TransparencyMask, 32),
+            (PsdChannelKind::Red, 32),
+            (PsdChannelKind::Green, 32),
+            (PsdChannelKind::Blue, 32),
+        ]
+    );
+
+    Ok(())
+}
+
+/// Layer masks are listed along with the color and transparency channels.
+///
+/// cargo test --test channels layer_channels_with_mask -- --exact
+#[test]
+fn layer_channels_with_mask() -> Result<()> {
+    let psd = include_bytes!("./fixtures/layer-at-pixel-3x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let kinds: Vec<_> = psd
+        .layer_by_name("Masked")
+        .unwrap()
+        .channels()
+        .map(|(kind, compression, len)| {
+            assert_eq!(compression, PsdChannelCompression::RawData);
+            (kind, len)
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (PsdChannelKind::Red, 3),
+            (PsdChannelKind::Green, 3),
+            (PsdChannelKind::Blue, 3),
+            (PsdChannelKind::TransparencyMask, 3),
+            (PsdChannelKind::UserSuppliedLayerMask, 1),
+        ]
+    );
+
+    Ok(())
+}
+
+/// The black channel of a CMYK layer and the real user supplied layer mask are read, and each
+/// mask is decoded with its own rectangle.
+///
+/// cargo test --test channels layer_black_and_real_mask_channels -- --exact
+#[test]
+fn layer_black_and_real_mask_channels() -> Result<()> {
+    let psd = include_bytes!("./fixtures/cmyk-masks-2x2.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("Masked").unwrap();
+
+    let kinds: Vec<_> = layer.channels().map(|(kind, _, _)| kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            PsdChannelKind::TransparencyMask,
+            PsdChannelKind::Red,
+            PsdChannelKind::Green,
+            PsdChannelKind::Blue,
+            PsdChannelKind::Spot(3),
+            PsdChannelKind::UserSuppliedLayerMask,
+            PsdChannelKind::RealUserSuppliedLayerMask,
+        ]
+    );
+
+    assert_eq!(
+        layer.decoded_channel(PsdChannelKind::Spot(3)),
+        Some(vec![0, 128])
+    );
+
+    let mask = layer.mask().unwrap();
+    assert_eq!(
+        layer.channel_bounds(PsdChannelKind::UserSuppliedLayerMask),
+        mask.bounds()
+    );
+    assert_eq!(
+        layer.decoded_channel(PsdChannelKind::UserSuppliedLayerMask),
+        Some(vec![200])
+    );
+
+    let real_bounds = mask.real_bounds().unwrap();
+    assert_eq!((real_bounds.width(), real_bounds.height()), (1, 2));
+    assert_eq!(
+        layer.channel_bounds(PsdChannelKind::RealUserSuppliedLayerMask),
+        Some(real_bounds)
+    );
+    assert_eq!(
+        layer.decoded_channel(PsdChannelKind::RealUserSuppliedLayerMask),
+        Some(vec![10, 20])
+    );
+
+    assert_eq!(PsdChannelKind::Spot(3).id(), 3);
+    assert_eq!(PsdChannelKind::new(3), Some(PsdChannelKind::Spot(3)));
+    assert_eq!(
+        PsdChannelKind::new(PsdChannelKind::RealUserSuppliedLayerMask.id()),
+        Some(PsdChannelKind::RealUserSuppliedLayerMask)
+    );
+
+    Ok(())
+}
+
+/// Channels after the color channels of the composite image are read by their position.
+///
+/// cargo test --test channels composite_spot_channel -- --exact
+#[test]
+fn composite_spot_channel() -> Result<()> {
+    let psd = include_bytes!("./fixtures/cmyk-masks-2x2.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(
+        psd.composite_channel(PsdChannelKind::Spot(3))
+            .map(|black| black.into_owned()),
+        Some(vec![13, 14, 15, 16])
+    );
+    assert_eq!(psd.composite_channel(PsdChannelKind::Spot(4)), None);
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use psd::{ColorSpace, Psd};
+
+const COLOR_SAMPLERS: &[u8] = include_bytes!("./fixtures/color-samplers-4x4.psd");
+
+/// The position and color space of each color sampler is read.
+///
+/// cargo test --test color_samplers color_samplers -- --exact
+#[test]
+fn color_samplers() {
+    let psd = Psd::from_bytes(COLOR_SAMPLERS).unwrap();
+
+    let samplers: Vec<(f64, f64, ColorSpace)> = psd
+        .color_samplers()
+        .iter()
+        .map(|sampler| (sampler.x(), sampler.y(), sampler.color_space()))
+        .collect();
+    assert_eq!(
+        samplers,
+        [
+            (2.5, 1.5, ColorSpace::Actual),
+            (0., 3., ColorSpace::Lab),
+            (10., 2., ColorSpace::Rgb),
+        ]
+    );
+    assert_eq!(psd.color_samplers()[0].depth(), None);
+}
+
+/// The color under each sampler comes from the final image, and samplers that are off of the
+/// canvas have no color.
+///
+/// cargo test --test color_samplers sampled_colors -- --exact
+#[test]
+fn sampled_colors() {
+    let psd = Psd::from_bytes(COLOR_SAMPLERS).unwrap();
+
+    assert_eq!(
+        psd.sampled_colors(),
+        [Some([120, 60, 100, 255]), Some([0, 180, 100, 255]), None]
+    );
+}
@@ -0,0 +1,76 @@
+use psd::compose;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+/// Each image becomes a layer at its position, and the canvas fits every image.
+///
+/// cargo test --test compose from_images -- --exact
+#[test]
+fn from_images() {
+    let images = vec![
+        ("Red", (1, 1, RED.to_vec()), 2, 0),
+        ("Blue", (2, 2, BLUE.repeat(4)), 0, 1),
+    ];
+    let psd = compose::from_images(images).unwrap();
+
+    assert_eq!((psd.width(), psd.height()), (3, 3));
+
+    let layers: Vec<(&str, i32, i32, u16, u16)> = psd
+        .layers()
+        .iter()
+        .map(|l| {
+            (
+                l.name(),
+                l.layer_left(),
+                l.layer_top(),
+                l.width(),
+                l.height(),
+            )
+        })
+        .collect();
+    assert_eq!(layers, [("Red", 2, 0, 1, 1), ("Blue", 0, 1, 2, 2)]);
+
+    #[rustfmt::skip]
+    let expected = [
+        TRANSPARENT, TRANSPARENT, RED,
+        BLUE, BLUE, TRANSPARENT,
+        BLUE, BLUE, TRANSPARENT,
+    ]
+    .concat();
+    assert!(psd.has_composite_image());
+    assert_eq!(psd.rgba(), expected);
+}
+
+/// Images that overlap are stacked with the first image on top, and images can be partially
+/// off of the canvas.
+///
+/// cargo test --test compose overlapping_images -- --exact
+#[test]
+fn overlapping_images() {
+    let red = [255, 0, 0, 255].repeat(4);
+    let blue: &[u8] = &BLUE.repeat(4);
+
+    let psd = compose::from_images([
+        ("Top", (2, 2, &red[..]), -1, -1),
+        ("Bottom", (2, 2, blue), 0, 0),
+    ])
+    .unwrap();
+
+    assert_eq!((psd.width(), psd.height()), (2, 2));
+    assert_eq!(psd.rgba(), [RED, BLUE, BLUE, BLUE].concat());
+}
+
+/// The limits that guard against untrusted files don't stop us from reading back what we
+/// wrote, such as more layers than Photoshop allows.
+///
+/// cargo test --test compose more_images_than_the_layer_limit -- --exact
+#[test]
+fn more_images_than_the_layer_limit() {
+    let images = (0..8001).map(|idx| (format!("{}", idx), (1, 1, RED.to_vec()), 0, 0));
+    let psd = compose::from_images(images).unwrap();
+
+    assert_eq!(psd.layers().len(), 8001);
+    assert_eq!(psd.rgba(), RED);
+}
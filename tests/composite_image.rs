@@ -0,0 +1,176 @@
+use psd::{PixelFormat, Psd, PsdChannelKind};
+
+const NO_COMPOSITE: &[u8] = include_bytes!("./fixtures/no-composite-2x1.psd");
+const EMPTY_COMPOSITE: &[u8] = include_bytes!("./fixtures/empty-composite-2x1.psd");
+const SIXTEEN_BIT: &[u8] = include_bytes!("./fixtures/rgb-16-bit-2x1.psd");
+const SIXTEEN_BIT_RLE: &[u8] = include_bytes!("./fixtures/rgb-16-bit-rle-2x1.psd");
+
+const RED_GREEN: [u8; 8] = [255, 0, 0, 255, 0, 255, 0, 255];
+
+/// A PSD that was saved with "Maximize Compatibility" turned off has a placeholder composite
+/// image, so its layers are flattened instead.
+///
+/// cargo test --test composite_image maximize_compatibility_off -- --exact
+#[test]
+fn maximize_compatibility_off() {
+    let psd = Psd::from_bytes(NO_COMPOSITE).unwrap();
+
+    assert!(!psd.has_composite_image());
+    assert_eq!(psd.rgba(), RED_GREEN);
+}
+
+/// The layers are flattened when the image data section is empty or missing.
+///
+/// cargo test --test composite_image empty_composite -- --exact
+#[test]
+fn empty_composite() {
+    let without_image_data = &EMPTY_COMPOSITE[..EMPTY_COMPOSITE.len() - 2];
+
+    for bytes in [EMPTY_COMPOSITE, without_image_data] {
+        let psd = Psd::from_bytes(bytes).unwrap();
+
+        assert!(!psd.has_composite_image());
+        assert_eq!(psd.rgba(), RED_GREEN);
+    }
+}
+
+/// Regular PSDs have a composite image.
+///
+/// cargo test --test composite_image has_composite_image -- --exact
+#[test]
+fn has_composite_image() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd")).unwrap();
+
+    assert!(psd.has_composite_image());
+}
+
+/// The channels of the composite image can be read without converting them into RGBA.
+///
+/// cargo test --test composite_image composite_channel -- --exact
+#[test]
+fn composite_channel() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/rgb-spot-channel-1x1.psd")).unwrap();
+    let channel = |kind| psd.composite_channel(kind).map(|plane| plane.into_owned());
+
+    assert_eq!(channel(PsdChannelKind::Red), Some(vec![255]));
+    assert_eq!(channel(PsdChannelKind::Green), Some(vec![0]));
+    assert_eq!(channel(PsdChannelKind::Blue), Some(vec![0]));
+    assert_eq!(channel(PsdChannelKind::TransparencyMask), Some(vec![128]));
+    assert_eq!(channel(PsdChannelKind::UserSuppliedLayerMask), None);
+}
+
+/// Rle compressed channels are decompressed.
+///
+/// cargo test --test composite_image composite_channel_rle -- --exact
+#[test]
+fn composite_channel_rle() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/cmyk-rle-2x1.psd")).unwrap();
+
+    // Photoshop stores CMYK inverted, so the fully cyan pixel is 0
+    let cyan = psd.composite_channel(PsdChannelKind::Red).unwrap();
+    assert_eq!(cyan.into_owned(), vec![0, 255]);
+}
+/// There are no channels to read when the PSD doesn't have a composite image.
+///
+/// cargo test --test composite_image composite_channel_without_composite -- --exact
+#[test]
+fn composite_channel_without_composite() {
+    let psd = Psd::from_bytes(NO_COMPOSITE).unwrap();
+
+    assert!(psd.composite_channel(PsdChannelKind::Red).is_none());
+}
+
+/// The most common colors of the composite image.
+///
+/// cargo test --test composite_image dominant_colors -- --exact
+#[test]
+fn dominant_colors() {
+    let psd = Psd::from_bytes(NO_COMPOSITE).unwrap();
+
+    let colors: Vec<[u8; 3]> = psd
+        .dominant_colors(5)
+        .iter()
+        .map(|color| color.rgb)
+        .collect();
+    assert_eq!(colors, vec![[0, 255, 0], [255, 0, 0]]);
+}
+
+/// The composite image and the layers can be laid out in other pixel formats.
+///
+/// cargo test --test composite_image pixel_formats -- --exact
+#[test]
+fn pixel_formats() {
+    let psd = Psd::from_bytes(NO_COMPOSITE).unwrap();
+
+    assert_eq!(psd.pixels(PixelFormat::Rgba8), RED_GREEN);
+    assert_eq!(
+        psd.pixels(PixelFormat::Bgra8),
+        [0, 0, 255, 255, 0, 255, 0, 255]
+    );
+    assert_eq!(psd.pixels(PixelFormat::Rgb8), [255, 0, 0, 0, 255, 0]);
+
+    let rgba16 = psd.pixels(PixelFormat::Rgba16);
+    assert_eq!(rgba16.len(), 2 * PixelFormat::Rgba16.bytes_per_pixel());
+    assert_eq!(rgba16[..2], 65535u16.to_ne_bytes());
+
+    let layer = &psd.layers()[0];
+    let rgb: Vec<u8> = layer
+        .rgba()
+        .chunks_exact(4)
+        .flat_map(|pixel| pixel[..3].to_vec())
+        .collect();
+    assert_eq!(layer.pixels(PixelFormat::Rgb8), rgb);
+}
+
+/// The composite image of a 16 bit PSD keeps its 16 bit values in `Rgba16`, whether it is raw
+/// or RLE compressed.
+///
+/// cargo test --test composite_image sixteen_bit_pixels -- --exact
+#[test]
+fn sixteen_bit_pixels() {
+    for bytes in [SIXTEEN_BIT, SIXTEEN_BIT_RLE] {
+        let psd = Psd::from_bytes(bytes).unwrap();
+
+        let rgba16: Vec<u16> = psd
+            .pixels(PixelFormat::Rgba16)
+            .chunks_exact(2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(
+            rgba16,
+            [0x1234, 0xFFFF, 0x0000, 0xFFFF, 0x8000, 0x0101, 0xABCD, 0x4000]
+        );
+
+        assert_eq!(psd.rgba(), [0x12, 0xFF, 0x00, 0xFF, 0x80, 0x01, 0xAB, 0x40]);
+        assert_eq!(
+            psd.pixels(PixelFormat::Bgra8),
+            [0x00, 0xFF, 0x12, 0xFF, 0xAB, 0x01, 0x80, 0x40]
+        );
+    }
+}
+
+/// Reading a PSD from bytes that it owns gives the same pixels as reading it from a slice.
+///
+/// cargo test --test composite_image from_vec -- --exact
+#[test]
+fn from_vec() {
+    for entry in std::fs::read_dir("tests/fixtures").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("psd") {
+            continue;
+        }
+
+        let bytes = std::fs::read(&path).unwrap();
+        let (from_slice, from_vec) = match (Psd::from_bytes(&bytes), Psd::from_vec(bytes.clone())) {
+            (Ok(from_slice), Ok(from_vec)) => (from_slice, from_vec),
+            (Err(_), Err(_)) => continue,
+            _ => panic!("{:?} was only read from one of the two", path),
+        };
+
+        assert_eq!(from_slice.rgba(), from_vec.rgba(), "{:?}", path);
+        assert_eq!(from_slice.layers().len(), from_vec.layers().len());
+        for (a, b) in from_slice.layers().iter().zip(from_vec.layers()) {
+            assert_eq!(a.rgba(), b.rgba(), "{:?} {}", path, a.name());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use psd::compositor::Compositor;
+use psd::writer::{PsdWriter, WriterLayer};
+use psd::Psd;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+/// A red pixel on the left, above a hidden 2x1 green layer in the middle, above a blue
+/// background.
+fn layered_psd() -> Result<Psd> {
+    let bytes = PsdWriter::new(4, 1)
+        .layer(WriterLayer::new("Red", 1, 1, RED.to_vec()))
+        .layer(
+            WriterLayer::new("Green", 2, 1, [GREEN, GREEN].concat())
+                .position(1, 0)
+                .visible(false),
+        )
+        .layer(WriterLayer::new("Blue", 4, 1, [BLUE; 4].concat()))
+        .to_bytes()?;
+
+    Ok(Psd::from_bytes(&bytes)?)
+}
+
+/// The compositor starts out with the visible layers flattened.
+///
+/// cargo test --test compositor new_flattens_visible_layers -- --exact
+#[test]
+fn new_flattens_visible_layers() -> Result<()> {
+    let psd = layered_psd()?;
+    let compositor = Compositor::new(&psd);
+
+    assert_eq!(compositor.rgba(), [RED, BLUE, BLUE, BLUE].concat());
+    assert!(compositor.is_layer_visible(0));
+    assert!(!compositor.is_layer_visible(1));
+
+    Ok(())
+}
+
+/// Showing and hiding layers updates the pixels that they cover.
+///
+/// cargo test --test compositor set_layer_visible -- --exact
+#[test]
+fn set_layer_visible() -> Result<()> {
+    let psd = layered_psd()?;
+    let mut compositor = Compositor::new(&psd);
+
+    compositor.set_layer_visible(1, true);
+    assert_eq!(compositor.rgba(), [RED, GREEN, GREEN, BLUE].concat());
+    assert_eq!(compositor.rgba(), psd.flatten_layers_rgba(&|_| true)?);
+
+    compositor.set_layer_visible(0, false);
+    assert_eq!(compositor.rgba(), [BLUE, GREEN, GREEN, BLUE].concat());
+
+    compositor.set_layer_visible(1, false);
+    compositor.set_layer_visible(2, false);
+    assert_eq!(compositor.into_rgba(), [0; 16]);
+
+    Ok(())
+}
+
+/// Layer indices must be within the PSD's layers.
+///
+/// cargo test --test compositor set_layer_visible_out_of_bounds -- --exact
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn set_layer_visible_out_of_bounds() {
+    let psd = layered_psd().unwrap();
+    Compositor::new(&psd).set_layer_visible(3, true);
+}
@@ -1,10 +1,12 @@
 use anyhow::Result;
-use psd::{Psd, PsdChannelCompression};
+use psd::{Psd, PsdChannelCompression, PsdWarning};
 
 const RED_PIXEL: [u8; 4] = [255, 0, 0, 255];
 const GREEN_PIXEL: [u8; 4] = [0, 255, 0, 255];
 const BLUE_PIXEL: [u8; 4] = [0, 0, 255, 255];
 
+const WIDE_RLE: &[u8] = include_bytes!("./fixtures/wide-rle-9000x2.psd");
+
 /// cargo test --test compression rle_decompress_final_image -- --exact
 #[test]
 fn rle_decompress_final_image() -> Result<()> {
@@ -40,6 +42,37 @@ fn rle_decompress_layer() -> Result<()> {
     Ok(())
 }
 
+/// Scanlines that compress to more than 8000 bytes, in a PSD that is 9000 pixels wide.
+///
+/// cargo test --test compression rle_decompress_wide_image -- --exact
+#[test]
+fn rle_decompress_wide_image() -> Result<()> {
+    let psd = Psd::from_bytes(WIDE_RLE)?;
+
+    assert_eq!(psd.compression(), &PsdChannelCompression::RleCompressed);
+
+    let expected = make_wide_rgba();
+    assert_eq!(psd.rgba(), expected);
+    assert_eq!(psd.layer_by_name("Wide").unwrap().rgba(), expected);
+    assert!(psd.warnings().is_empty());
+
+    Ok(())
+}
+
+/// A composite image that is shorter than its scanline byte counts say is left out, and the
+/// layers are flattened instead.
+///
+/// cargo test --test compression rle_truncated_composite -- --exact
+#[test]
+fn rle_truncated_composite() -> Result<()> {
+    let psd = Psd::from_bytes(&WIDE_RLE[..WIDE_RLE.len() - 10])?;
+
+    assert_eq!(psd.warnings(), [PsdWarning::TruncatedComposite]);
+    assert_eq!(psd.rgba(), make_wide_rgba());
+
+    Ok(())
+}
+
 fn test_rle_layer(psd: &Psd, layer_name: &str, expected_pixels: &[u8]) {
     let layer = psd.layer_by_name(layer_name).unwrap();
     assert_eq!(&layer.rgba().as_slice(), &expected_pixels);
@@ -72,3 +105,18 @@ fn make_8x8_rgba(color: [u8; 4]) -> Vec<u8> {
 
     pixels
 }
+
+/// The pixels of wide-rle-9000x2.psd
+fn make_wide_rgba() -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(9000 * 2 * 4);
+    for x in 0..9000 {
+        for channel in 0..3 {
+            rgba.push(((x * (channel + 3) + channel * 50) % 251) as u8);
+        }
+        rgba.push(255);
+    }
+    for _ in 0..9000 {
+        rgba.extend_from_slice(&[0, 100, 200, 255]);
+    }
+    rgba
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use psd::Psd;
+
+const BEFORE: &[u8] = include_bytes!("./fixtures/diff-before-2x2.psd");
+const AFTER: &[u8] = include_bytes!("./fixtures/diff-after-2x2.psd");
+
+/// Layers whose pixels didn't change between revisions keep their hash, even if their opacity
+/// or blend mode changed.
+///
+/// cargo test --test content_hash layer_content_hash -- --exact
+#[test]
+fn layer_content_hash() -> Result<()> {
+    let before = Psd::from_bytes(BEFORE)?;
+    let after = Psd::from_bytes(AFTER)?;
+
+    let hash = |psd: &Psd, name: &str| psd.layer_by_name(name).unwrap().content_hash();
+
+    assert_eq!(hash(&before, "Blended"), hash(&after, "Blended"));
+
+    // A pixel of the background was painted and the square was moved
+    assert_ne!(hash(&before, "Background"), hash(&after, "Background"));
+    assert_ne!(hash(&before, "Square"), hash(&after, "Moved Square"));
+
+    Ok(())
+}
+
+/// PSDs that look the same have the same composite hash.
+///
+/// cargo test --test content_hash composite_hash -- --exact
+#[test]
+fn composite_hash() -> Result<()> {
+    let green = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+    let renamed = Psd::from_bytes(include_bytes!(
+        "./fixtures/green-chinese-layer-name-1x1.psd"
+    ))?;
+    let red = Psd::from_bytes(include_bytes!("./fixtures/two-layers-red-green-1x1.psd"))?;
+
+    assert_eq!(green.rgba(), renamed.rgba());
+    assert_eq!(green.composite_hash(), renamed.composite_hash());
+
+    assert_ne!(green.rgba(), red.rgba());
+    assert_ne!(green.composite_hash(), red.composite_hash());
+
+    Ok(())
+}
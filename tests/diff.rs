@@ -0,0 +1,108 @@
+use anyhow::Result;
+use psd::diff::{diff, DiffOptions, LayerChange, LayerRef};
+use psd::{LayerBounds, Psd};
+
+const BEFORE: &[u8] = include_bytes!("./fixtures/diff-before-2x2.psd");
+const AFTER: &[u8] = include_bytes!("./fixtures/diff-after-2x2.psd");
+
+/// Layers are matched up by their ID, so renamed and moved layers are reported as such.
+///
+/// cargo test --test diff diff_layers -- --exact
+#[test]
+fn diff_layers() -> Result<()> {
+    let before = Psd::from_bytes(BEFORE)?;
+    let after = Psd::from_bytes(AFTER)?;
+
+    let diff = diff(&before, &after, &DiffOptions::default());
+    let changes = diff.changes();
+
+    assert!(changes.contains(&LayerChange::Removed(layer_ref(3, "Removed"))));
+    assert!(changes.contains(&LayerChange::Added(layer_ref(4, "Added"))));
+    assert!(changes.contains(&LayerChange::Renamed {
+        layer: layer_ref(2, "Moved Square"),
+        old_name: "Square".to_string(),
+    }));
+    assert!(changes.contains(&LayerChange::BoundsChanged {
+        layer: layer_ref(2, "Moved Square"),
+        old: Some(LayerBounds {
+            top: 0,
+            left: 0,
+            bottom: 0,
+            right: 0
+        }),
+        new: Some(LayerBounds {
+            top: 1,
+            left: 1,
+            bottom: 1,
+            right: 1
+        }),
+    }));
+
+    let (old, new) = changes
+        .iter()
+        .find_map(|change| match change {
+            LayerChange::BlendChanged { layer, old, new } if layer.name == "Blended" => {
+                Some((old, new))
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(old.opacity, 255);
+    assert_eq!(new.opacity, 128);
+    assert_ne!(old.blend_mode, new.blend_mode);
+
+    // Pixels are only compared when asked to
+    assert!(!changes
+        .iter()
+        .any(|change| matches!(change, LayerChange::PixelsChanged { .. })));
+    assert_eq!(changes.len(), 5);
+
+    Ok(())
+}
+
+/// One of the four pixels of the background layer was painted over.
+///
+/// cargo test --test diff diff_pixels -- --exact
+#[test]
+fn diff_pixels() -> Result<()> {
+    let before = Psd::from_bytes(BEFORE)?;
+    let after = Psd::from_bytes(AFTER)?;
+
+    let diff = diff(&before, &after, &DiffOptions { pixels: true });
+
+    let background = diff.changes().iter().find_map(|change| match change {
+        LayerChange::PixelsChanged { layer, percent } if layer.name == "Background" => {
+            Some(*percent)
+        }
+        _ => None,
+    });
+    assert_eq!(background, Some(25.));
+
+    // Moving the square changes both the pixel it moved from and the pixel it moved to
+    let square = diff.changes().iter().find_map(|change| match change {
+        LayerChange::PixelsChanged { layer, percent } if layer.id == Some(2) => Some(*percent),
+        _ => None,
+    });
+    assert_eq!(square, Some(100.));
+
+    Ok(())
+}
+
+/// Comparing a PSD with itself finds no changes.
+///
+/// cargo test --test diff diff_identical -- --exact
+#[test]
+fn diff_identical() -> Result<()> {
+    let before = Psd::from_bytes(BEFORE)?;
+
+    assert!(diff(&before, &before, &DiffOptions { pixels: true }).is_empty());
+
+    Ok(())
+}
+
+fn layer_ref(id: u32, name: &str) -> LayerRef {
+    LayerRef {
+        id: Some(id),
+        name: name.to_string(),
+    }
+}
@@ -0,0 +1,52 @@
+use psd::{ExifOrientation, Psd, PsdOptions};
+
+const EXIF_ROTATE_90: &[u8] = include_bytes!("./fixtures/exif-rotate-90-3x2.psd");
+
+fn reds(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|pixel| pixel[0]).collect()
+}
+
+/// The orientation is read from the EXIF data, but only applied when asked for.
+///
+/// cargo test --test exif_orientation exif_orientation_not_applied_by_default -- --exact
+#[test]
+fn exif_orientation_not_applied_by_default() {
+    let psd = Psd::from_bytes(EXIF_ROTATE_90).unwrap();
+
+    assert_eq!(psd.exif_orientation(), Some(ExifOrientation::Rotate90));
+    assert_eq!(psd.oriented_size(), (3, 2));
+    assert_eq!(reds(&psd.rgba()), [0, 40, 80, 120, 160, 200]);
+    assert_eq!(
+        reds(&psd.flatten_layers_rgba(&|_| true).unwrap()),
+        [0, 40, 80, 120, 160, 200]
+    );
+}
+
+/// The composite image and the flattened layers are rotated to show the photo upright, while
+/// the layers keep the document's orientation.
+///
+/// cargo test --test exif_orientation apply_exif_orientation -- --exact
+#[test]
+fn apply_exif_orientation() {
+    let options = PsdOptions {
+        apply_exif_orientation: true,
+        ..PsdOptions::default()
+    };
+    let psd = Psd::from_bytes_with_options(EXIF_ROTATE_90, &options).unwrap();
+
+    assert_eq!((psd.width(), psd.height()), (3, 2));
+    assert_eq!(psd.oriented_size(), (2, 3));
+
+    let rotated = [120, 0, 160, 40, 200, 80];
+    assert_eq!(reds(&psd.rgba()), rotated);
+    assert_eq!(reds(&psd.flatten_layers_rgba(&|_| true).unwrap()), rotated);
+
+    let mut rgba = vec![0; 2 * 3 * 4];
+    psd.flatten_layers_rgba_into(&mut rgba, &|_| true).unwrap();
+    assert_eq!(reds(&rgba), rotated);
+
+    assert_eq!(
+        reds(&psd.layer_by_name("Photo").unwrap().rgba()),
+        [0, 40, 80, 120, 160, 200]
+    );
+}
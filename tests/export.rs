@@ -0,0 +1,183 @@
+#![cfg(feature = "export")]
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use psd::export::ImageFormat;
+use psd::{Psd, PsdError};
+
+/// cargo test --features export --test export layer_to_png -- --exact
+#[test]
+fn layer_to_png() -> Result<()> {
+    let psd = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    for layer in psd.layers() {
+        let png = psd::export::layer_to_png(layer)?;
+
+        assert_eq!(decode_png(&png), (1, 1, layer.rgba()));
+    }
+
+    Ok(())
+}
+
+/// cargo test --features export --test export flattened_to_png -- --exact
+#[test]
+fn flattened_to_png() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let png = psd::export::flattened_to_png(&psd)?;
+
+    assert_eq!(
+        decode_png(&png),
+        (2, 1, psd.flatten_layers_rgba(&|_| true)?)
+    );
+
+    Ok(())
+}
+
+/// Pixels that don't fill the image, or an empty image, are an error instead of a panic.
+///
+/// cargo test --features export --test export rgba_to_png_invalid_size -- --exact
+#[test]
+fn rgba_to_png_invalid_size() {
+    assert_eq!(
+        psd::export::rgba_to_png(&[0; 4], 2, 1),
+        Err(PsdError::InvalidBufferLength {
+            expected: 8,
+            actual: 4
+        })
+    );
+    assert!(matches!(
+        psd::export::rgba_to_png(&[], 0, 0),
+        Err(PsdError::EncodeError(_))
+    ));
+}
+
+const ICC_PROFILE_300_DPI: &[u8] = include_bytes!("./fixtures/icc-profile-300-dpi-2x1.psd");
+
+/// The composite image's PNG holds the PSD's resolution and ICC profile.
+///
+/// cargo test --features export --test export composite_to_png_embeds_profile -- --exact
+#[test]
+fn composite_to_png_embeds_profile() -> Result<()> {
+    let psd = Psd::from_bytes(ICC_PROFILE_300_DPI)?;
+
+    let png = psd::export::composite_to_png(&psd)?;
+    assert_eq!(decode_png(&png), (2, 1, psd.rgba()));
+
+    // 300 pixels per inch is 11811 pixels per meter
+    let phys = png_chunk(&png, b"pHYs").unwrap();
+    assert_eq!(phys[..4], 11811u32.to_be_bytes());
+    assert_eq!(phys[4..8], 11811u32.to_be_bytes());
+    assert_eq!(phys[8], 1);
+
+    // The profile is stored in a zlib stream without compression
+    let iccp = png_chunk(&png, b"iCCP").unwrap();
+    assert!(iccp.starts_with(b"ICC Profile\0\0"));
+    assert!(contains(&iccp, psd.icc_profile().unwrap()));
+
+    // PSDs without them don't get the chunks
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/mask-density-2x1.psd"))?;
+    let png = psd::export::composite_to_png(&psd)?;
+    assert_eq!(png_chunk(&png, b"pHYs"), None);
+    assert_eq!(png_chunk(&png, b"iCCP"), None);
+
+    Ok(())
+}
+
+/// The composite image's TIFF holds the PSD's pixels, resolution and ICC profile.
+///
+/// cargo test --features export --test export composite_to_tiff_embeds_profile -- --exact
+#[test]
+fn composite_to_tiff_embeds_profile() -> Result<()> {
+    let psd = Psd::from_bytes(ICC_PROFILE_300_DPI)?;
+
+    let tiff = psd::export::composite_to_tiff(&psd);
+    assert_eq!(&tiff[..4], b"II*\0");
+
+    let u16_at = |idx: usize| u16::from_le_bytes([tiff[idx], tiff[idx + 1]]);
+    let u32_at =
+        |idx: usize| u32::from_le_bytes([tiff[idx], tiff[idx + 1], tiff[idx + 2], tiff[idx + 3]]);
+
+    // The count and value or offset of each entry
+    let directory = u32_at(4) as usize;
+    let entry = |tag: u16| {
+        (0..u16_at(directory) as usize)
+            .map(|idx| directory + 2 + idx * 12)
+            .find(|entry| u16_at(*entry) == tag)
+            .map(|entry| (u32_at(entry + 4) as usize, u32_at(entry + 8) as usize))
+    };
+
+    assert_eq!(entry(256), Some((1, 2)));
+    assert_eq!(entry(257), Some((1, 1)));
+
+    let (_, strip_offset) = entry(273).unwrap();
+    let (_, strip_len) = entry(279).unwrap();
+    assert_eq!(tiff[strip_offset..strip_offset + strip_len], psd.rgba()[..]);
+
+    let (_, x_resolution) = entry(282).unwrap();
+    assert_eq!(
+        u32_at(x_resolution) as f64 / u32_at(x_resolution + 4) as f64,
+        300.
+    );
+    // Inches
+    assert_eq!(entry(296), Some((1, 2)));
+
+    let (icc_len, icc_offset) = entry(34675).unwrap();
+    assert_eq!(
+        &tiff[icc_offset..icc_offset + icc_len],
+        psd.icc_profile().unwrap()
+    );
+
+    Ok(())
+}
+
+/// The composite image can be saved on another thread.
+///
+/// cargo test --features export --test export save_composite_in_background -- --exact
+#[test]
+fn save_composite_in_background() -> Result<()> {
+    let psd = Arc::new(Psd::from_bytes(ICC_PROFILE_300_DPI)?);
+    let path = std::env::temp_dir().join("psd-save-composite-in-background.png");
+
+    let saving = psd::export::save_composite_in_background(psd.clone(), &path, ImageFormat::Png);
+    saving.join().unwrap()?;
+
+    assert_eq!(std::fs::read(&path)?, psd::export::composite_to_png(&psd)?);
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+/// The data of the first chunk of the PNG with the given type
+fn png_chunk(png: &[u8], kind: &[u8; 4]) -> Option<Vec<u8>> {
+    // Skip the signature
+    let mut idx = 8;
+    while idx + 8 <= png.len() {
+        let len = u32::from_be_bytes([png[idx], png[idx + 1], png[idx + 2], png[idx + 3]]) as usize;
+        if &png[idx + 4..idx + 8] == kind {
+            return Some(png[idx + 8..idx + 8 + len].to_vec());
+        }
+        // Length, type, data and CRC
+        idx += 12 + len;
+    }
+
+    None
+}
+
+fn contains(bytes: &[u8], expected: &[u8]) -> bool {
+    bytes
+        .windows(expected.len())
+        .any(|window| window == expected)
+}
+
+fn decode_png(png: &[u8]) -> (u32, u32, Vec<u8>) {
+    let mut reader = png::Decoder::new(png).read_info().unwrap();
+
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut rgba).unwrap();
+
+    (info.width, info.height, rgba)
+}
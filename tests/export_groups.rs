@@ -0,0 +1,60 @@
+use psd::geometry::Rect;
+use psd::Psd;
+
+const EXPORT_GROUPS: &[u8] = include_bytes!("./fixtures/export-groups-4x2.psd");
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+/// Each top level group is flattened on its own and cropped to its bounds. Nested groups are
+/// part of their top level group, and empty groups are left out.
+///
+/// cargo test --test export_groups export_top_level_groups -- --exact
+#[test]
+fn export_top_level_groups() {
+    let psd = Psd::from_bytes(EXPORT_GROUPS).unwrap();
+
+    let exported = psd.export_groups(|_| true);
+    let names: Vec<&str> = exported.iter().map(|(name, ..)| name.as_str()).collect();
+    assert_eq!(names, ["Right", "Left"]);
+
+    let (_, left_rgba, left_bounds) = &exported[1];
+    assert_eq!(
+        *left_bounds,
+        Rect {
+            top: 0,
+            left: 0,
+            bottom: 1,
+            right: 0
+        }
+    );
+    assert_eq!(*left_rgba, [RED, RED].concat());
+
+    // The white background isn't part of the group
+    let (_, right_rgba, right_bounds) = &exported[0];
+    assert_eq!(
+        *right_bounds,
+        Rect {
+            top: 0,
+            left: 2,
+            bottom: 1,
+            right: 3
+        }
+    );
+    assert_eq!(*right_rgba, [BLUE, BLUE, TRANSPARENT, GREEN].concat());
+}
+
+/// Only the groups that pass the filter are exported.
+///
+/// cargo test --test export_groups export_filtered_groups -- --exact
+#[test]
+fn export_filtered_groups() {
+    let psd = Psd::from_bytes(EXPORT_GROUPS).unwrap();
+
+    let exported = psd.export_groups(|group| group.name() != "Left");
+
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].0, "Right");
+}
@@ -0,0 +1,61 @@
+use psd::{ExtraChannel, Psd};
+
+/// The channels after the color and transparency channels are named after the Unicode alpha
+/// names, which take precedence over the older alpha channel names.
+///
+/// cargo test --test extra_channels extra_channel_names -- --exact
+#[test]
+fn extra_channel_names() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/alpha-channel-names-1x1.psd")).unwrap();
+
+    assert_eq!(psd.alpha_channel_names(), ["Glow Mask", "Spot Röd"]);
+
+    let channels = psd.extra_channels();
+    let names: Vec<Option<&str>> = channels.iter().map(ExtraChannel::name).collect();
+    assert_eq!(names, [Some("Glow Mask"), Some("Spot Röd")]);
+
+    let glow = psd.extra_channel_by_name("Glow Mask").unwrap();
+    assert_eq!(glow.index(), 4);
+    assert_eq!(
+        psd.composite_channel(glow.kind()).unwrap().into_owned(),
+        [64]
+    );
+
+    let spot = psd.extra_channel_by_name("Spot Röd").unwrap();
+    assert_eq!(
+        psd.composite_channel(spot.kind()).unwrap().into_owned(),
+        [192]
+    );
+
+    assert_eq!(psd.extra_channel_by_name("Spot Rod"), None);
+}
+
+/// When every channel after the color channels has a name, the first of them is a saved
+/// selection rather than the transparency.
+///
+/// cargo test --test extra_channels saved_selection_without_transparency -- --exact
+#[test]
+fn saved_selection_without_transparency() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/saved-selection-1x1.psd")).unwrap();
+
+    let channels = psd.extra_channels();
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0].index(), 3);
+    assert_eq!(channels[0].name(), Some("Alpha 1"));
+}
+
+/// Extra channels that the PSD doesn't name don't have a name.
+///
+/// cargo test --test extra_channels unnamed_extra_channels -- --exact
+#[test]
+fn unnamed_extra_channels() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/rgb-spot-channel-1x1.psd")).unwrap();
+
+    let channels = psd.extra_channels();
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0].index(), 4);
+    assert_eq!(channels[0].name(), None);
+
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd")).unwrap();
+    assert!(psd.extra_channels().is_empty());
+}
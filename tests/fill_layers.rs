@@ -0,0 +1,89 @@
+use anyhow::Result;
+use psd::{BlendMode, FillLayer, GradientKind, LineCap, LineJoin, Psd, StrokeAlignment};
+
+const BLUE_PIXEL: [u8; 4] = [0, 0, 255, 255];
+
+/// A solid color fill layer has no pixels of its own, so we render its color across the PSD.
+///
+/// cargo test --test fill_layers solid_color_fill_layer -- --exact
+#[test]
+fn solid_color_fill_layer() -> Result<()> {
+    let psd = include_bytes!("./fixtures/fill-layers-4x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("Solid Color Fill").unwrap();
+    assert_eq!(layer.fill(), Some(&FillLayer::SolidColor([0, 0, 255])));
+
+    let flattened = psd.flatten_layers_rgba(&|(_, layer)| layer.name() == "Solid Color Fill")?;
+    assert_eq!(flattened, BLUE_PIXEL.repeat(4));
+
+    Ok(())
+}
+
+/// A black to white linear gradient fill from left to right.
+///
+/// cargo test --test fill_layers gradient_fill_layer -- --exact
+#[test]
+fn gradient_fill_layer() -> Result<()> {
+    let psd = include_bytes!("./fixtures/fill-layers-4x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("Gradient Fill").unwrap();
+    let gradient = match layer.fill() {
+        Some(FillLayer::Gradient(gradient)) => gradient,
+        other => panic!("Expected a gradient fill, got {:?}", other),
+    };
+    assert_eq!(gradient.kind(), GradientKind::Linear);
+    assert_eq!(gradient.color_stops().len(), 2);
+    assert_eq!(gradient.transparency_stops().len(), 2);
+
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+
+    // Sampled at the center of each pixel, so 1/8, 3/8, 5/8 and 7/8 of the way across
+    let expected = [32, 96, 159, 223];
+    for (idx, gray) in expected.iter().enumerate() {
+        assert_eq!(
+            &flattened[idx * 4..idx * 4 + 4],
+            &[*gray, *gray, *gray, 255]
+        );
+    }
+
+    Ok(())
+}
+
+/// Shape layers store their stroke and what their shape is filled with.
+///
+/// cargo test --test fill_layers vector_stroke -- --exact
+#[test]
+fn vector_stroke() -> Result<()> {
+    let psd = include_bytes!("./fixtures/vector-stroke-2x2.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("Rectangle 1").unwrap();
+    assert_eq!(
+        layer.vector_fill(),
+        Some(&FillLayer::SolidColor([0, 0, 255]))
+    );
+
+    let stroke = layer.vector_stroke().unwrap();
+    assert!(stroke.stroke_enabled());
+    assert!(stroke.fill_enabled());
+    assert_eq!(stroke.width(), 3.);
+    assert_eq!(stroke.dash_offset(), 0.5);
+    assert_eq!(stroke.dash_pattern(), &[4., 2.]);
+    assert_eq!(stroke.line_cap(), LineCap::Round);
+    assert_eq!(stroke.line_join(), LineJoin::Bevel);
+    assert_eq!(stroke.alignment(), StrokeAlignment::Center);
+    assert_eq!(stroke.blend_mode(), BlendMode::Multiply);
+    assert_eq!(stroke.opacity(), 128);
+    assert_eq!(stroke.color(), Some([255, 0, 0]));
+
+    let plain = include_bytes!("./fixtures/fill-layers-4x1.psd");
+    let plain = Psd::from_bytes(plain)?;
+    assert!(plain
+        .layers()
+        .iter()
+        .all(|layer| layer.vector_stroke().is_none()));
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use psd::Psd;
+
+const GROUPS_PSD: &[u8] =
+    include_bytes!("./fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+
+/// Layers can be found with a predicate, from the top layer to the bottom layer.
+///
+/// cargo test --test find_layers find_layers -- --exact
+#[test]
+fn find_layers() {
+    let psd = Psd::from_bytes(GROUPS_PSD).unwrap();
+
+    let names: Vec<&str> = psd
+        .find_layers(|layer| layer.parent_id().is_none())
+        .iter()
+        .map(|layer| layer.name())
+        .collect();
+    assert_eq!(names, ["Firth Layer"]);
+
+    assert_eq!(psd.find_layers(|_| true).len(), psd.layers().len());
+}
+
+/// Layers can be found by matching their names against glob patterns.
+///
+/// cargo test --test find_layers layers_matching -- --exact
+#[test]
+fn layers_matching() {
+    let psd = Psd::from_bytes(GROUPS_PSD).unwrap();
+
+    let names = |pattern: &str| -> Vec<String> {
+        psd.layers_matching(pattern)
+            .iter()
+            .map(|layer| layer.name().to_string())
+            .collect()
+    };
+
+    assert_eq!(names("F*th Layer"), ["Fourth Layer", "Firth Layer"]);
+    assert_eq!(names("?irst*"), ["First Layer"]);
+    assert_eq!(names("*").len(), 6);
+    assert!(names("Layer").is_empty());
+}
+
+/// Glob patterns must match the entire name.
+///
+/// cargo test --test find_layers name_matches -- --exact
+#[test]
+fn name_matches() {
+    let psd = Psd::from_bytes(GROUPS_PSD).unwrap();
+    let layer = psd.layer_by_name("First Layer").unwrap();
+
+    assert!(layer.name_matches("First Layer"));
+    assert!(layer.name_matches("First*Layer*"));
+    assert!(layer.name_matches("**Layer"));
+    assert!(layer.name_matches("*st La?er"));
+    assert!(!layer.name_matches("First"));
+    assert!(!layer.name_matches("First Layer?"));
+}
+
+/// Searching within a group includes the layers of its nested groups.
+///
+/// cargo test --test find_layers find_layers_in_group -- --exact
+#[test]
+fn find_layers_in_group() {
+    let psd = Psd::from_bytes(GROUPS_PSD).unwrap();
+    let outside_group = psd.group_by_name("outside group").unwrap().id();
+
+    let names: Vec<&str> = psd
+        .find_layers_in_group(outside_group, |layer| layer.name_matches("*Layer"))
+        .iter()
+        .map(|layer| layer.name())
+        .collect();
+    assert_eq!(
+        names,
+        ["First Layer", "Second Layer", "Third Layer", "Fourth Layer"]
+    );
+
+    assert!(psd.find_layers_in_group(1234, |_| true).is_empty());
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use psd::Psd;
+use psd::{Knockout, Psd, PsdError, PsdOptions};
 
 const RED_PIXEL: [u8; 4] = [255, 0, 0, 255];
 const _GREEN_PIXEL: [u8; 4] = [0, 255, 0, 255];
@@ -38,3 +38,168 @@ fn no_matching_layers() -> Result<()> {
 
     Ok(())
 }
+
+/// Flattening into a buffer overwrites whatever was in it, so the buffer can be reused.
+///
+/// cargo test --test flatten_layers flatten_into_reused_buffer -- --exact
+#[test]
+fn flatten_into_reused_buffer() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let mut buffer = vec![7; 8];
+
+    psd.flatten_layers_rgba_into(&mut buffer, &|_| true)?;
+    assert_eq!(buffer, psd.flatten_layers_rgba(&|_| true)?);
+
+    psd.flatten_layers_rgba_into(&mut buffer, &|_| false)?;
+    assert_eq!(buffer, [0; 8]);
+
+    buffer.fill(7);
+    psd.rgba_into(&mut buffer)?;
+    assert_eq!(buffer, psd.rgba());
+
+    Ok(())
+}
+
+/// Buffers that aren't the size of the PSD are rejected.
+///
+/// cargo test --test flatten_layers flatten_into_wrong_length -- --exact
+#[test]
+fn flatten_into_wrong_length() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let expected_err = PsdError::InvalidBufferLength {
+        expected: 8,
+        actual: 4,
+    };
+    assert_eq!(
+        psd.flatten_layers_rgba_into(&mut [0; 4], &|_| true),
+        Err(expected_err)
+    );
+
+    let expected_err = PsdError::InvalidBufferLength {
+        expected: 8,
+        actual: 12,
+    };
+    assert_eq!(psd.rgba_into(&mut [0; 12]), Err(expected_err));
+
+    Ok(())
+}
+
+/// Groups that aren't pass through are flattened on their own before being blended with the
+/// layers below them, and knockout layers reveal the layers below the ones they cover.
+///
+/// cargo test --test flatten_layers flatten_isolated_group_with_knockout -- --exact
+#[test]
+fn flatten_isolated_group_with_knockout() -> Result<()> {
+    let psd = include_bytes!("./fixtures/knockout-isolated-group-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let knockout = psd.layer_by_name("Knockout").unwrap();
+    assert_eq!(knockout.knockout(), Knockout::Deep);
+    assert_eq!(knockout.fill_opacity(), 0);
+    assert!(!knockout.blend_clipped_elements());
+    assert!(knockout.blend_interior_elements());
+
+    let blue = psd.layer_by_name("Blue").unwrap();
+    assert_eq!(blue.knockout(), Knockout::None);
+    assert!(blue.blend_clipped_elements());
+    assert!(!blue.blend_interior_elements());
+
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+
+    // The green pixel is knocked out, leaving the background
+    assert_eq!(&flattened[0..4], &RED_PIXEL);
+    // The blue pixel is blended with the background at the group's opacity
+    assert_eq!(&flattened[4..8], &[127, 0, 128, 255]);
+
+    Ok(())
+}
+
+/// Flattening one row at a time gives the same pixels as flattening the whole PSD.
+///
+/// cargo test --test flatten_layers flatten_rows -- --exact
+#[test]
+fn flatten_rows() -> Result<()> {
+    let options = PsdOptions {
+        rasterize_vector_masks: true,
+        ..PsdOptions::default()
+    };
+    for bytes in [
+        &include_bytes!("./fixtures/rle-3-layer-8x8.psd")[..],
+        &include_bytes!("./fixtures/green-clipping-10x10.psd")[..],
+        &include_bytes!("./fixtures/knockout-isolated-group-2x1.psd")[..],
+        &include_bytes!("./fixtures/green-1x1.psd")[..],
+        &include_bytes!("./fixtures/fill-layers-4x1.psd")[..],
+        &include_bytes!("./fixtures/pattern-fill-4x1.psd")[..],
+        &include_bytes!("./fixtures/vector-mask-8x8.psd")[..],
+    ] {
+        let psd = Psd::from_bytes_with_options(bytes, &options)?;
+
+        let rows = psd.flatten_rows(&|_| true);
+        assert_eq!(rows.len(), psd.height() as usize);
+
+        let rows: Vec<Vec<u8>> = rows.collect();
+        assert!(rows.iter().all(|row| row.len() == psd.width() as usize * 4));
+        assert_eq!(rows.concat(), psd.flatten_layers_rgba(&|_| true)?);
+
+        let hidden: Vec<u8> = psd.flatten_rows(&|_| false).flatten().collect();
+        assert_eq!(hidden, psd.flatten_layers_rgba(&|_| false)?);
+    }
+
+    Ok(())
+}
+
+/// Flattening one tile at a time gives the same pixels as flattening the whole PSD, including
+/// layers that extend past the PSD, fill layers, vector masks and tiles that are cut short by
+/// its edges.
+///
+/// cargo test --test flatten_layers flatten_tiles -- --exact
+#[test]
+fn flatten_tiles() -> Result<()> {
+    let options = PsdOptions {
+        rasterize_vector_masks: true,
+        ..PsdOptions::default()
+    };
+    for bytes in [
+        &include_bytes!("./fixtures/rle-3-layer-8x8.psd")[..],
+        &include_bytes!("./fixtures/green-clipping-10x10.psd")[..],
+        &include_bytes!("./fixtures/knockout-isolated-group-2x1.psd")[..],
+        &include_bytes!("./fixtures/negative-top-left-layer.psd")[..],
+        &include_bytes!("./fixtures/off-canvas-2x2.psd")[..],
+        &include_bytes!("./fixtures/green-1x1.psd")[..],
+        &include_bytes!("./fixtures/fill-layers-4x1.psd")[..],
+        &include_bytes!("./fixtures/pattern-fill-4x1.psd")[..],
+        &include_bytes!("./fixtures/vector-mask-8x8.psd")[..],
+    ] {
+        let psd = Psd::from_bytes_with_options(bytes, &options)?;
+        let width = psd.width() as usize;
+
+        for tile_size in [1, 3, 64] {
+            let mut tiled = vec![0; width * psd.height() as usize * 4];
+            let mut pixel_count = 0;
+
+            psd.flatten_tiles(tile_size, &|_| true, |rect, rgba| {
+                assert!(rect.width() <= tile_size && rect.height() <= tile_size);
+                assert_eq!(
+                    rgba.len(),
+                    rect.width() as usize * rect.height() as usize * 4
+                );
+                pixel_count += rgba.len() / 4;
+
+                let row_len = rect.width() as usize * 4;
+                for (row, tile_row) in rgba.chunks_exact(row_len).enumerate() {
+                    let start = ((rect.top as usize + row) * width + rect.left as usize) * 4;
+                    tiled[start..start + row_len].copy_from_slice(tile_row);
+                }
+            });
+
+            assert_eq!(pixel_count, width * psd.height() as usize);
+            assert_eq!(tiled, psd.flatten_layers_rgba(&|_| true)?);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Counts the bytes that are allocated while flattening, so this is the only test in its binary.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use psd::writer::{PsdWriter, WriterLayer};
+use psd::Psd;
+
+/// Keeps track of the bytes that are allocated and the most that were allocated at once
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 1024;
+const LAYER_COUNT: u8 = 4;
+
+/// Flattening one row at a time decodes the layers one band of rows at a time, instead of
+/// holding the pixels of every layer across the whole PSD.
+///
+/// cargo test --test flatten_rows_memory flatten_rows_memory_is_bounded -- --exact
+#[test]
+fn flatten_rows_memory_is_bounded() -> Result<()> {
+    let mut writer = PsdWriter::new(WIDTH, HEIGHT);
+    for layer in 0..LAYER_COUNT {
+        // Every layer is see-through, so each of them is decoded to flatten every pixel
+        let rgba: Vec<u8> = (0..WIDTH * HEIGHT)
+            .flat_map(|idx| [(idx % 251) as u8, layer * 60, (idx / WIDTH) as u8, 128])
+            .collect();
+        writer = writer.layer(WriterLayer::new(
+            format!("Layer {}", layer),
+            WIDTH,
+            HEIGHT,
+            rgba,
+        ));
+    }
+    let psd = Psd::from_vec(writer.to_bytes()?)?;
+
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+
+    let mut rows = vec![];
+    for row in psd.flatten_rows(&|_| true) {
+        rows.push(row);
+    }
+
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+    let flattened_len = (WIDTH * HEIGHT * 4) as usize;
+    // The rows that were kept, and less than the pixels of a single layer on top of them
+    assert!(
+        peak < flattened_len * 2,
+        "Flattening rows allocated {} bytes",
+        peak
+    );
+
+    assert_eq!(rows.concat(), psd.flatten_layers_rgba(&|_| true)?);
+
+    Ok(())
+}
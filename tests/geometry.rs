@@ -0,0 +1,157 @@
+use psd::geometry::{Point, Rect};
+use psd::writer::{PsdWriter, WriterLayer};
+use psd::Psd;
+
+/// cargo test --test geometry rect_helpers -- --exact
+#[test]
+fn rect_helpers() {
+    let rect = Rect::from_origin_and_size(Point::new(-1, 2), 3, 2).unwrap();
+    assert_eq!(
+        rect,
+        Rect {
+            top: 2,
+            left: -1,
+            bottom: 3,
+            right: 1,
+        }
+    );
+    assert_eq!((rect.width(), rect.height()), (3, 2));
+    assert_eq!(rect.origin(), Point::new(-1, 2));
+    assert!(Rect::from_origin_and_size(Point::new(0, 0), 0, 2).is_none());
+
+    assert!(rect.contains(Point::new(1, 3)));
+    assert!(!rect.contains(Point::new(2, 3)));
+
+    let other = Rect::from_origin_and_size(Point::new(1, 0), 5, 3).unwrap();
+    assert_eq!(
+        rect.intersect(&other),
+        Rect::from_origin_and_size(Point::new(1, 2), 1, 1)
+    );
+    assert!(rect
+        .intersect(&Rect::from_origin_and_size(Point::new(5, 5), 1, 1).unwrap())
+        .is_none());
+    assert_eq!(
+        rect.union(&other),
+        Rect {
+            top: 0,
+            left: -1,
+            bottom: 3,
+            right: 5,
+        }
+    );
+
+    assert_eq!(
+        Point::new(1, 1).to_canvas_space(rect.origin()),
+        Point::new(0, 3)
+    );
+    assert_eq!(
+        Rect::from_origin_and_size(Point::new(0, 0), 1, 1)
+            .unwrap()
+            .to_canvas_space(rect.origin()),
+        Rect::from_origin_and_size(Point::new(-1, 2), 1, 1).unwrap()
+    );
+}
+
+/// Layers that extend past the canvas can be cropped to it.
+///
+/// cargo test --test geometry layer_bounds_on_canvas -- --exact
+#[test]
+fn layer_bounds_on_canvas() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/negative-top-left-layer.psd")).unwrap();
+    let layer = &psd.layers()[0];
+
+    let bounds = layer.bounds().unwrap();
+    assert!(bounds.left < 0 || bounds.top < 0);
+
+    let on_canvas = bounds.intersect(&psd.canvas_rect()).unwrap();
+    assert!(on_canvas.left >= 0 && on_canvas.top >= 0);
+    assert_eq!(psd.canvas_rect().width(), psd.width());
+}
+
+/// Points on the canvas can be turned into positions within a layer that starts past the
+/// canvas.
+///
+/// cargo test --test geometry canvas_to_layer_coords -- --exact
+#[test]
+fn canvas_to_layer_coords() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/negative-top-left-layer.psd")).unwrap();
+    let layer = &psd.layers()[0];
+
+    // The layer's top left pixel is at (-4, -4)
+    assert!(layer.contains_canvas_point(0, 0));
+    assert_eq!(layer.canvas_to_layer_coords(0, 0), Some((4, 4)));
+    assert_eq!(layer.canvas_to_layer_coords(4, 4), Some((8, 8)));
+
+    assert!(!layer.contains_canvas_point(5, 0));
+    assert_eq!(layer.canvas_to_layer_coords(0, 5), None);
+}
+
+/// A layer's placement has its rectangle on the canvas and within the layer, and the part of
+/// them that is on the canvas. The right and bottom of each rectangle are inclusive.
+///
+/// cargo test --test geometry layer_placement -- --exact
+#[test]
+fn layer_placement() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/off-canvas-2x2.psd")).unwrap();
+
+    let rect = |left, top, right, bottom| Rect {
+        top,
+        left,
+        bottom,
+        right,
+    };
+
+    let off_left = psd.layer_by_name("Off Left").unwrap().placement().unwrap();
+    assert_eq!(off_left.canvas_rect(), rect(-1, 0, 0, 1));
+    assert_eq!(off_left.layer_rect(), rect(0, 0, 1, 1));
+    assert_eq!(off_left.visible_canvas_rect(), Some(rect(0, 0, 0, 1)));
+    assert_eq!(off_left.visible_layer_rect(), Some(rect(1, 0, 1, 1)));
+    assert_eq!(
+        off_left.canvas_to_layer(Point::new(0, 1)),
+        Some(Point::new(1, 1))
+    );
+    assert_eq!(off_left.canvas_to_layer(Point::new(1, 1)), None);
+
+    let off_bottom_right = psd
+        .layer_by_name("Off Bottom Right")
+        .unwrap()
+        .placement()
+        .unwrap();
+    assert_eq!(off_bottom_right.canvas_rect(), rect(1, 1, 2, 2));
+    assert_eq!(
+        off_bottom_right.visible_canvas_rect(),
+        Some(rect(1, 1, 1, 1))
+    );
+    assert_eq!(
+        off_bottom_right.visible_layer_rect(),
+        Some(rect(0, 0, 0, 0))
+    );
+
+    // The layer's right column is within it, while the column to its right isn't
+    let layer = psd.layer_by_name("Off Left").unwrap();
+    assert!(layer.contains_canvas_point(0, 1));
+    assert!(!layer.contains_canvas_point(1, 0));
+}
+
+/// Layers that are entirely off of the canvas aren't visible, and empty layers aren't placed.
+///
+/// cargo test --test geometry layer_placement_off_canvas -- --exact
+#[test]
+fn layer_placement_off_canvas() {
+    let bytes = PsdWriter::new(2, 2)
+        .layer(WriterLayer::new("Off Canvas", 1, 1, vec![255; 4]).position(2, 0))
+        .to_bytes()
+        .unwrap();
+    let psd = Psd::from_bytes(&bytes).unwrap();
+
+    let placement = psd.layers()[0].placement().unwrap();
+    assert_eq!(
+        placement.canvas_rect(),
+        Rect::from_origin_and_size(Point::new(2, 0), 1, 1).unwrap()
+    );
+    assert_eq!(placement.visible_canvas_rect(), None);
+    assert_eq!(placement.visible_layer_rect(), None);
+
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/empty-layer-2x1.psd")).unwrap();
+    assert!(psd.layers().iter().any(|layer| layer.placement().is_none()));
+}
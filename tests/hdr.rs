@@ -0,0 +1,58 @@
+use psd::hdr::{ToneMapOptions, ToneMapping};
+use psd::Psd;
+
+const HDR: &[u8] = include_bytes!("./fixtures/hdr-32-bit-2x1.psd");
+
+/// The floats of a 32 bit PSD can be read as is, including colors that are brighter than white.
+///
+/// cargo test --test hdr rgba32f -- --exact
+#[test]
+fn rgba32f() {
+    let psd = Psd::from_bytes(HDR).unwrap();
+
+    assert_eq!(
+        psd.rgba32f().unwrap(),
+        [4.0, 0.5, 0.0, 1.0, 1.0, 0.25, 0.0, 1.0]
+    );
+
+    let eight_bit = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd")).unwrap();
+    assert!(eight_bit.rgba32f().is_none());
+}
+
+/// By default colors brighter than white are clamped, and the linear light is converted to sRGB.
+///
+/// cargo test --test hdr rgba_clamps_by_default -- --exact
+#[test]
+fn rgba_clamps_by_default() {
+    let psd = Psd::from_bytes(HDR).unwrap();
+
+    assert_eq!(psd.rgba(), [255, 188, 0, 255, 255, 137, 0, 255]);
+    assert_eq!(psd.rgba_tone_mapped(&ToneMapOptions::default()), psd.rgba());
+}
+
+/// The exposure scales the colors before they are tone mapped.
+///
+/// cargo test --test hdr tone_mapping -- --exact
+#[test]
+fn tone_mapping() {
+    let psd = Psd::from_bytes(HDR).unwrap();
+
+    let darker = ToneMapOptions {
+        exposure: 0.5,
+        ..ToneMapOptions::default()
+    };
+    assert_eq!(
+        psd.rgba_tone_mapped(&darker),
+        [255, 137, 0, 255, 188, 99, 0, 255]
+    );
+
+    // 4.0 becomes 0.8, so the highlight is no longer clipped
+    let reinhard = ToneMapOptions {
+        exposure: 1.,
+        tone_mapping: ToneMapping::Reinhard,
+    };
+    assert_eq!(
+        psd.rgba_tone_mapped(&reinhard),
+        [231, 156, 0, 255, 188, 124, 0, 255]
+    );
+}
@@ -1,4 +1,4 @@
-use psd::Psd;
+use psd::{ColorMode, Psd};
 
 const RED_PIXEL: [u8; 4] = [255, 0, 0, 255];
 
@@ -11,3 +11,57 @@ fn image_data_section() {
 
     assert_eq!(&psd.rgba(), &RED_PIXEL);
 }
+
+/// Channels after the transparency, such as spot colors, don't affect the final image.
+///
+/// cargo test --test image_data_section rgb_with_spot_channel -- --exact
+#[test]
+fn rgb_with_spot_channel() {
+    let psd = include_bytes!("./fixtures/rgb-spot-channel-1x1.psd");
+
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert_eq!(psd.rgba(), [255, 0, 0, 128]);
+}
+
+/// CMYK composite images are converted to RGB, with the fifth channel as transparency.
+///
+/// cargo test --test image_data_section cmyk_with_transparency -- --exact
+#[test]
+fn cmyk_with_transparency() {
+    let psd = include_bytes!("./fixtures/cmyk-rle-2x1.psd");
+
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert_eq!(psd.color_mode(), ColorMode::Cmyk);
+    assert_eq!(psd.rgba(), [0, 255, 255, 255, 128, 128, 128, 255]);
+}
+
+/// Bitmap images have a bit per pixel, where a set bit is black, and every row starts on a new
+/// byte.
+///
+/// cargo test --test image_data_section bitmap -- --exact
+#[test]
+fn bitmap() {
+    let black = [0, 0, 0, 255];
+    let white = [255, 255, 255, 255];
+    let mut expected = vec![];
+    for pixel in [
+        black, white, black, white, black, black, white, white, white, white,
+    ] {
+        expected.extend_from_slice(&pixel);
+    }
+    for _ in 0..10 {
+        expected.extend_from_slice(&black);
+    }
+
+    for psd in [
+        &include_bytes!("./fixtures/bitmap-10x2.psd")[..],
+        &include_bytes!("./fixtures/bitmap-rle-10x2.psd")[..],
+    ] {
+        let psd = Psd::from_bytes(psd).unwrap();
+
+        assert_eq!(psd.color_mode(), ColorMode::Bitmap);
+        assert_eq!(psd.rgba(), expected);
+    }
+}
@@ -1,4 +1,4 @@
-use psd::{DescriptorField, ImageResource, Psd};
+use psd::{DescriptorField, ImageResource, Psd, ResolutionUnit};
 
 /// In this test we check that root descriptor's `bounds` field is equal to 1
 /// So, then fields parsed correctly
@@ -61,3 +61,46 @@ fn image_check_16x16p_bound_field() {
         panic!("expected descriptor");
     }
 }
+
+/// The resolution info and ICC profile resources are read.
+///
+/// cargo test --test image_resources_section resolution_and_icc_profile -- --exact
+#[test]
+fn resolution_and_icc_profile() {
+    let psd = include_bytes!("./fixtures/icc-profile-300-dpi-2x1.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let resolution = psd.resolution().unwrap();
+    assert_eq!(resolution.horizontal_dpi(), 300.);
+    assert_eq!(resolution.vertical_dpi(), 300.);
+    assert_eq!(resolution.display_unit(), ResolutionUnit::PixelsPerInch);
+
+    assert_eq!(psd.icc_profile(), Some(&b"not a real ICC profile"[..]));
+}
+
+/// The URL and URL list resources that are used when saving for the web are read.
+///
+/// cargo test --test image_resources_section url_and_url_list -- --exact
+#[test]
+fn url_and_url_list() {
+    let psd = include_bytes!("./fixtures/url-list-1x1.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert_eq!(psd.url(), Some("https://example.com/"));
+
+    let urls = psd.url_list();
+    assert_eq!(urls.len(), 2);
+    assert_eq!(
+        (urls[0].id(), urls[0].url()),
+        (1, "https://example.com/shop")
+    );
+    assert_eq!(
+        (urls[1].id(), urls[1].url()),
+        (2, "https://example.com/café")
+    );
+
+    // PSDs without them don't have any
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/icc-profile-300-dpi-2x1.psd")).unwrap();
+    assert_eq!(psd.url(), None);
+    assert!(psd.url_list().is_empty());
+}
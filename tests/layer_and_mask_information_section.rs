@@ -1,4 +1,4 @@
-use psd::{Psd, PsdGroup};
+use psd::{LayerBounds, Psd, PsdGroup};
 
 const GREEN_PIXEL: [u8; 4] = [0, 255, 0, 255];
 
@@ -238,3 +238,104 @@ fn group_by_name<'a>(psd: &'a Psd, name: &str) -> &'a PsdGroup {
         .unwrap()
         .1
 }
+
+/// Layers without any area are empty and transparent.
+///
+/// cargo test --test layer_and_mask_information_section empty_layers -- --exact
+#[test]
+fn empty_layers() {
+    let psd = include_bytes!("fixtures/empty-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    for name in &["Empty Layer", "Inverted Bounds"] {
+        let layer = psd.layer_by_name(name).unwrap();
+
+        assert!(layer.is_empty());
+        assert_eq!((layer.width(), layer.height()), (0, 0));
+        assert_eq!(layer.rgba(), vec![0; 8]);
+    }
+
+    let layer = psd.layer_by_name("Top Left Pixel").unwrap();
+    assert!(!layer.is_empty());
+    assert_eq!((layer.width(), layer.height()), (1, 1));
+
+    let flattened = psd.flatten_layers_rgba(&|_| true).unwrap();
+    assert_eq!(flattened, [GREEN_PIXEL, [255, 0, 0, 255]].concat());
+}
+
+/// Layers that extend past the PSD have negative or out of bounds positions, are cropped to the
+/// PSD by `rgba` and keep their off-canvas pixels in `rgba_full_extent`.
+///
+/// cargo test --test layer_and_mask_information_section off_canvas_layers -- --exact
+#[test]
+fn off_canvas_layers() {
+    let psd = include_bytes!("./fixtures/off-canvas-2x2.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 128];
+    const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+    let off_left = psd.layer_by_name("Off Left").unwrap();
+    assert_eq!(off_left.layer_left(), -1);
+    assert_eq!(
+        off_left.rgba(),
+        [GREEN, TRANSPARENT, GREEN, TRANSPARENT].concat()
+    );
+
+    let (pixels, bounds) = off_left.rgba_full_extent().unwrap();
+    assert_eq!(
+        bounds,
+        LayerBounds {
+            top: 0,
+            left: -1,
+            bottom: 1,
+            right: 0
+        }
+    );
+    assert_eq!(pixels, [RED, GREEN, RED, GREEN].concat());
+
+    let off_bottom_right = psd.layer_by_name("Off Bottom Right").unwrap();
+    assert_eq!(
+        off_bottom_right.rgba(),
+        [TRANSPARENT, TRANSPARENT, TRANSPARENT, BLUE].concat()
+    );
+
+    let (pixels, bounds) = off_bottom_right.rgba_full_extent().unwrap();
+    assert_eq!(
+        bounds,
+        LayerBounds {
+            top: 1,
+            left: 1,
+            bottom: 2,
+            right: 2
+        }
+    );
+    assert_eq!(pixels, [BLUE, WHITE, WHITE, WHITE].concat());
+
+    let flattened = psd.flatten_layers_rgba(&|_| true).unwrap();
+    assert_eq!(flattened, [GREEN, TRANSPARENT, GREEN, BLUE].concat());
+}
+
+/// A 9x9 layer that starts 4 pixels above and to the left of a 1x1 PSD.
+///
+/// cargo test --test layer_and_mask_information_section negative_top_left_full_extent -- --exact
+#[test]
+fn negative_top_left_full_extent() {
+    let psd = include_bytes!("./fixtures/negative-top-left-layer.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let layer = &psd.layers()[0];
+    assert_eq!(layer.rgba().len(), 4);
+
+    let (pixels, bounds) = layer.rgba_full_extent().unwrap();
+    assert_eq!((bounds.left, bounds.top), (-4, -4));
+    assert_eq!((bounds.width(), bounds.height()), (9, 9));
+    assert_eq!(pixels.len(), 9 * 9 * 4);
+
+    // The pixel at the center of the layer is the only one on the canvas
+    let center = (4 * 9 + 4) * 4;
+    assert_eq!(&layer.rgba()[..], &pixels[center..center + 4]);
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use psd::{KeyframeInterpolation, KeyframeValue, Psd, TimelineTrackKind};
+
+const LAYER_ANIMATION: &[u8] = include_bytes!("./fixtures/layer-animation-1x1.psd");
+
+/// The layer's state in each frame of a frame animation is read from its `mlst` metadata.
+///
+/// cargo test --test layer_animation animation_frames -- --exact
+#[test]
+fn animation_frames() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_ANIMATION)?;
+
+    let frames = psd.layer_by_name("Animated").unwrap().animation_frames();
+    assert_eq!(frames.len(), 3);
+
+    assert_eq!(frames[0].frame_ids(), &[1]);
+    assert_eq!(frames[0].visible(), Some(true));
+    assert_eq!(frames[0].offset(), None);
+    assert_eq!(frames[0].opacity(), Some(255));
+
+    assert_eq!(frames[1].frame_ids(), &[2, 3]);
+    assert_eq!(frames[1].offset(), Some((4., -2.)));
+    assert_eq!(frames[1].opacity(), Some(128));
+
+    assert_eq!(frames[2].frame_ids(), &[4]);
+    assert_eq!(frames[2].visible(), Some(false));
+    assert_eq!(frames[2].opacity(), None);
+
+    // The raw metadata is still there for anything that isn't parsed
+    let layer = psd.layer_by_name("Animated").unwrap();
+    assert!(layer.metadata_by_key("mlst").unwrap().fields().is_some());
+
+    assert!(psd
+        .layer_by_name("Static")
+        .unwrap()
+        .animation_frames()
+        .is_empty());
+
+    Ok(())
+}
+
+/// Timeline keyframes are read from the `tmln` metadata and tweened like Photoshop does.
+///
+/// cargo test --test layer_animation timeline_tracks -- --exact
+#[test]
+fn timeline_tracks() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_ANIMATION)?;
+
+    let tracks = psd.layer_by_name("Animated").unwrap().timeline_tracks();
+    assert_eq!(tracks.len(), 3);
+
+    let opacity = &tracks[0];
+    assert_eq!(opacity.kind(), &TimelineTrackKind::Opacity);
+    assert!(opacity.enabled());
+    let times: Vec<f64> = opacity.keyframes().iter().map(|key| key.time()).collect();
+    assert_eq!(times, vec![0., 1.]);
+    assert_eq!(opacity.value_at(-1.), Some(KeyframeValue::Opacity(255)));
+    assert_eq!(opacity.value_at(0.5), Some(KeyframeValue::Opacity(128)));
+    assert_eq!(opacity.value_at(5.), Some(KeyframeValue::Opacity(0)));
+
+    let position = &tracks[1];
+    assert_eq!(position.kind(), &TimelineTrackKind::Position);
+    assert_eq!(
+        position.keyframes()[0].interpolation(),
+        KeyframeInterpolation::Hold
+    );
+    assert_eq!(position.value_at(1.), Some(KeyframeValue::Position(0., 0.)));
+    assert_eq!(
+        position.value_at(2.),
+        Some(KeyframeValue::Position(10., 20.))
+    );
+
+    let style = &tracks[2];
+    assert_eq!(style.kind(), &TimelineTrackKind::Other("styleTrack".into()));
+    assert!(!style.enabled());
+    assert_eq!(style.value_at(0.), Some(KeyframeValue::Other));
+
+    assert!(psd
+        .layer_by_name("Static")
+        .unwrap()
+        .timeline_tracks()
+        .is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use psd::patch::PsdPatch;
+use psd::{Psd, PsdOptions};
+
+const LAYER_AT_PIXEL_PSD: &[u8] = include_bytes!("./fixtures/layer-at-pixel-3x1.psd");
+const VECTOR_MASK_PSD: &[u8] = include_bytes!("./fixtures/vector-mask-8x8.psd");
+
+/// Hidden layers, masked out pixels and clipped layers outside of their base layer are skipped.
+///
+/// cargo test --test layer_at_pixel layer_at_pixel_skips_hidden_and_masked_pixels -- --exact
+#[test]
+fn layer_at_pixel_skips_hidden_and_masked_pixels() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_AT_PIXEL_PSD)?;
+
+    assert_eq!(layer_name_at(&psd, 0, 0, false), Some("Masked"));
+    assert_eq!(layer_name_at(&psd, 1, 0, false), Some("Background"));
+    assert_eq!(layer_name_at(&psd, 2, 0, false), Some("Clipped"));
+
+    Ok(())
+}
+
+/// Layers that are hidden, or are inside of a hidden group, are considered when asked to.
+///
+/// cargo test --test layer_at_pixel layer_at_pixel_include_hidden -- --exact
+#[test]
+fn layer_at_pixel_include_hidden() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_AT_PIXEL_PSD)?;
+
+    assert_eq!(layer_name_at(&psd, 0, 0, true), Some("In Hidden Group"));
+    assert_eq!(layer_name_at(&psd, 1, 0, true), Some("Hidden"));
+    assert_eq!(layer_name_at(&psd, 2, 0, true), Some("Clipped"));
+
+    Ok(())
+}
+
+/// A layer that is clipped to a hidden layer is hidden along with it.
+///
+/// cargo test --test layer_at_pixel layer_at_pixel_hidden_clipping_base -- --exact
+#[test]
+fn layer_at_pixel_hidden_clipping_base() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_AT_PIXEL_PSD)?;
+    let base = psd
+        .layers()
+        .iter()
+        .position(|layer| layer.name() == "Dot")
+        .unwrap();
+
+    let hidden_base = PsdPatch::new()
+        .layer_visible(base, false)
+        .apply(LAYER_AT_PIXEL_PSD)?;
+    let psd = Psd::from_bytes(&hidden_base)?;
+
+    assert_eq!(layer_name_at(&psd, 2, 0, false), Some("Masked"));
+    assert_eq!(layer_name_at(&psd, 2, 0, true), Some("Clipped"));
+
+    Ok(())
+}
+
+/// There aren't any layers outside of the PSD.
+///
+/// cargo test --test layer_at_pixel layer_at_pixel_outside_of_psd -- --exact
+#[test]
+fn layer_at_pixel_outside_of_psd() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_AT_PIXEL_PSD)?;
+
+    assert!(psd.layer_at_pixel(3, 0, true).is_none());
+    assert!(psd.layer_at_pixel(0, 1, true).is_none());
+
+    Ok(())
+}
+
+/// The layer mask and visibility of layers and groups are read from the layer records.
+///
+/// cargo test --test layer_at_pixel layer_mask_and_visibility -- --exact
+#[test]
+fn layer_mask_and_visibility() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_AT_PIXEL_PSD)?;
+
+    let masked = psd.layer_by_name("Masked").unwrap();
+    let mask = masked.mask().unwrap();
+    let bounds = mask.bounds().unwrap();
+    assert_eq!((bounds.left, bounds.right), (1, 1));
+    assert_eq!(mask.default_color(), 255);
+    assert!(!mask.is_disabled());
+    assert!(masked.visible());
+
+    assert!(psd.layer_by_name("Background").unwrap().mask().is_none());
+    assert!(!psd.layer_by_name("Hidden").unwrap().visible());
+
+    let group = psd.groups().values().next().unwrap();
+    assert_eq!(group.name(), "Hidden Group");
+    assert!(!group.visible());
+
+    Ok(())
+}
+
+/// The density and feather of a layer mask are read, and a mask that isn't fully dense only
+/// partially hides the layer.
+///
+/// cargo test --test layer_at_pixel mask_density -- --exact
+#[test]
+fn mask_density() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/mask-density-2x1.psd"))?;
+
+    let mask = psd.layer_by_name("Masked").unwrap().mask().unwrap();
+    assert_eq!(mask.user_mask_density(), Some(128));
+    assert_eq!(mask.user_mask_feather(), Some(2.5));
+    assert_eq!(mask.vector_mask_density(), None);
+    assert_eq!(mask.vector_mask_feather(), None);
+
+    // The mask hides the right pixel, but only at half density
+    assert_eq!(layer_name_at(&psd, 1, 0, false), Some("Masked"));
+
+    Ok(())
+}
+
+/// Fill layers are evaluated at the pixel instead of being rendered across the PSD, along with
+/// their vector mask when it is applied, and give the same alpha as their pixels.
+///
+/// cargo test --test layer_at_pixel layer_at_pixel_fill_layer_with_vector_mask -- --exact
+#[test]
+fn layer_at_pixel_fill_layer_with_vector_mask() -> Result<()> {
+    let options = PsdOptions {
+        rasterize_vector_masks: true,
+        ..PsdOptions::default()
+    };
+    let psd = Psd::from_bytes_with_options(VECTOR_MASK_PSD, &options)?;
+    let square = psd.layer_by_name("Square").unwrap();
+    assert!(square.fill().is_some());
+
+    let layers_rgba: Vec<(&str, Vec<u8>)> = psd
+        .layers()
+        .iter()
+        .map(|layer| (layer.name(), layer.rgba()))
+        .collect();
+    let mut found_square = false;
+    for y in 0..psd.height() {
+        for x in 0..psd.width() {
+            let idx = (y * psd.width() + x) as usize * 4 + 3;
+            let expected = layers_rgba
+                .iter()
+                .find(|(_, rgba)| rgba[idx] > 0)
+                .map(|(name, _)| *name);
+
+            assert_eq!(layer_name_at(&psd, x, y, false), expected, "({}, {})", x, y);
+            found_square |= expected == Some("Square");
+        }
+    }
+    assert!(found_square);
+
+    Ok(())
+}
+
+fn layer_name_at(psd: &Psd, x: u32, y: u32, include_hidden: bool) -> Option<&str> {
+    psd.layer_at_pixel(x, y, include_hidden)
+        .map(|layer| layer.name())
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use psd::compositor::Compositor;
+use psd::{BlendMode, Psd, StrokeAlignment};
+
+const LAYER_EFFECTS: &[u8] = include_bytes!("fixtures/layer-effects-10x10.psd");
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+fn pixel(rgba: &[u8], x: usize, y: usize) -> [u8; 4] {
+    let idx = (y * 10 + x) * 4;
+
+    let mut pixel = [0; 4];
+    pixel.copy_from_slice(&rgba[idx..idx + 4]);
+    pixel
+}
+
+/// The drop shadow, stroke and color overlay of a layer are read, and the drop shadow points
+/// away from the document's global light.
+///
+/// cargo test --test layer_effects read_layer_effects -- --exact
+#[test]
+fn read_layer_effects() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_EFFECTS)?;
+    assert_eq!(psd.global_light_angle(), 90.);
+
+    let effects = psd.layer_by_name("Button").unwrap().effects().unwrap();
+
+    let drop_shadow = effects.drop_shadow().unwrap();
+    assert_eq!(drop_shadow.blend_mode(), BlendMode::Multiply);
+    assert_eq!(drop_shadow.color(), [0, 0, 0]);
+    assert_eq!(drop_shadow.opacity(), 255);
+    assert!(drop_shadow.use_global_light());
+    assert_eq!(drop_shadow.angle(), 90.);
+    assert_eq!(drop_shadow.distance(), 2.);
+    assert_eq!(drop_shadow.spread(), 0.);
+    assert_eq!(drop_shadow.size(), 0.);
+    assert!(drop_shadow.knocked_out());
+
+    let stroke = effects.stroke().unwrap();
+    assert_eq!(stroke.alignment(), StrokeAlignment::Outside);
+    assert_eq!(stroke.blend_mode(), BlendMode::Normal);
+    assert_eq!(stroke.color(), [0, 255, 0]);
+    assert_eq!(stroke.opacity(), 255);
+    assert_eq!(stroke.size(), 1.);
+
+    let color_overlay = effects.color_overlay().unwrap();
+    assert_eq!(color_overlay.color(), [0, 0, 255]);
+    assert_eq!(color_overlay.opacity(), 255);
+
+    // The outer glow is turned off
+    assert!(effects.ignored_effects().is_empty());
+
+    // The layer's effects are all turned off
+    assert!(psd
+        .layer_by_name("Effects Off")
+        .unwrap()
+        .effects()
+        .is_none());
+
+    Ok(())
+}
+
+/// The compositor only renders layer effects when asked to. The shadow falls below the layer,
+/// under its stroke.
+///
+/// cargo test --test layer_effects compositor_renders_effects -- --exact
+#[test]
+fn compositor_renders_effects() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_EFFECTS)?;
+
+    let plain = Compositor::new(&psd);
+    assert_eq!(pixel(plain.rgba(), 4, 4), RED);
+    assert_eq!(pixel(plain.rgba(), 4, 2), WHITE);
+    assert_eq!(pixel(plain.rgba(), 4, 8), WHITE);
+
+    let styled = Compositor::with_effects(&psd);
+    let rgba = styled.rgba();
+
+    // The color overlay covers the layer
+    for y in 3..7 {
+        for x in 3..7 {
+            assert_eq!(pixel(rgba, x, y), BLUE, "({}, {})", x, y);
+        }
+    }
+
+    // The stroke is outside of the layer, without its corners
+    for edge in 3..7 {
+        assert_eq!(pixel(rgba, edge, 2), GREEN);
+        assert_eq!(pixel(rgba, edge, 7), GREEN);
+        assert_eq!(pixel(rgba, 2, edge), GREEN);
+        assert_eq!(pixel(rgba, 7, edge), GREEN);
+    }
+    assert_eq!(pixel(rgba, 2, 2), WHITE);
+
+    // The shadow is 2 pixels below the layer
+    for x in 3..7 {
+        assert_eq!(pixel(rgba, x, 8), BLACK);
+        assert_eq!(pixel(rgba, x, 9), WHITE);
+    }
+    assert_eq!(pixel(rgba, 4, 1), WHITE);
+
+    // Turned off effects aren't rendered
+    assert_eq!(pixel(rgba, 0, 0), RED);
+
+    Ok(())
+}
+
+/// Hiding a layer with effects removes its effects from outside of its bounds too.
+///
+/// cargo test --test layer_effects hide_layer_with_effects -- --exact
+#[test]
+fn hide_layer_with_effects() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_EFFECTS)?;
+    let button = psd
+        .layers()
+        .iter()
+        .position(|layer| layer.name() == "Button")
+        .unwrap();
+
+    let mut compositor = Compositor::with_effects(&psd);
+    compositor.set_layer_visible(button, false);
+
+    assert_eq!(pixel(compositor.rgba(), 4, 2), WHITE);
+    assert_eq!(pixel(compositor.rgba(), 4, 8), WHITE);
+    assert_eq!(pixel(compositor.rgba(), 4, 4), WHITE);
+
+    Ok(())
+}
+
+/// Replace the number of the first `key` unit float of the layer effects with `value`
+fn with_effect_size(psd: &[u8], key: &[u8; 4], value: f64) -> Vec<u8> {
+    let mut psd = psd.to_vec();
+
+    let field = [&key[..], b"UntF#Pxl"].concat();
+    let start = psd.windows(12).position(|window| window == field).unwrap() + 12;
+    psd[start..start + 8].copy_from_slice(&value.to_be_bytes());
+
+    psd
+}
+
+/// Sizes and distances that are larger than Photoshop allows are clamped, instead of taking
+/// forever to render or overflowing.
+///
+/// cargo test --test layer_effects huge_effect_sizes_are_clamped -- --exact
+#[test]
+fn huge_effect_sizes_are_clamped() -> Result<()> {
+    let bytes = with_effect_size(LAYER_EFFECTS, b"Sz  ", 1e12);
+    let bytes = with_effect_size(&bytes, b"blur", 1e12);
+    let bytes = with_effect_size(&bytes, b"Ckmt", 1e12);
+    let bytes = with_effect_size(&bytes, b"Dstn", 1e300);
+    let psd = Psd::from_bytes(&bytes)?;
+
+    let styled = Compositor::with_effects(&psd);
+
+    // The stroke reaches past the corners of the layer
+    assert_eq!(pixel(styled.rgba(), 2, 2), GREEN);
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use psd::{Psd, PsdGroup};
+use psd::geometry::Rect;
+use psd::{BlendMode, Psd, PsdGroup};
 const TOP_LEVEL_ID: u32 = 1;
 
 /// Verify that we can get a group by it's ID.
@@ -131,3 +132,113 @@ fn group_by_name<'a>(psd: &'a Psd, name: &str) -> &'a PsdGroup {
         .unwrap()
         .1
 }
+
+/// Groups remember whether they were expanded or collapsed in the layers panel.
+///
+/// cargo test --test layer_groups group_expanded_state -- --exact
+#[test]
+fn group_expanded_state() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert!(group_by_name(&psd, "outside group").is_expanded());
+    assert!(group_by_name(&psd, "second group inside").is_expanded());
+    assert!(!group_by_name(&psd, "third group inside").is_expanded());
+}
+
+/// Groups can be visited in the same order as their IDs in `group_ids_in_order`.
+///
+/// cargo test --test layer_groups groups_in_order -- --exact
+#[test]
+fn groups_in_order() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-inside-another.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let names: Vec<&str> = psd.groups_in_order().iter().map(|g| g.name()).collect();
+    assert_eq!(names, ["group inside", "group outside"]);
+
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let ids: Vec<u32> = psd.groups_in_order().iter().map(|g| g.id()).collect();
+    assert_eq!(&ids, psd.group_ids_in_order());
+}
+
+/// Groups can be looked up by their name.
+///
+/// cargo test --test layer_groups group_by_name_lookup -- --exact
+#[test]
+fn group_by_name_lookup() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert_eq!(psd.group_by_name("outside group").unwrap().id(), 1);
+    assert_eq!(psd.group_by_name("outside group 2").unwrap().id(), 6);
+    assert!(psd.group_by_name("missing group").is_none());
+}
+
+/// Groups have the properties of their record, and cover the pixels of their layers.
+///
+/// cargo test --test layer_groups group_properties_and_bounds -- --exact
+#[test]
+fn group_properties_and_bounds() {
+    let psd = include_bytes!("fixtures/knockout-isolated-group-2x1.psd");
+    let mut psd = Psd::from_bytes(psd).unwrap();
+
+    let group = psd.group_by_name("Isolated").unwrap();
+    assert_eq!(group.name(), "Isolated");
+    assert!(group.visible());
+    assert_eq!(group.opacity(), 128);
+    assert_eq!(group.blend_mode(), BlendMode::Normal);
+    assert_eq!(
+        group.bounds(),
+        Some(Rect {
+            top: 0,
+            left: 0,
+            bottom: 0,
+            right: 1,
+        })
+    );
+
+    // The bounds follow the layers that are moved out of the group
+    let blue = psd.layers().iter().position(|layer| layer.name() == "Blue");
+    psd.set_parent(blue.unwrap(), None);
+    assert_eq!(
+        psd.group_by_name("Isolated").unwrap().bounds(),
+        Some(Rect {
+            top: 0,
+            left: 0,
+            bottom: 0,
+            right: 0,
+        })
+    );
+}
+
+/// The records that open and close groups are never in the layers, which go from the top of the
+/// layers view to the bottom with the layers of each group next to each other.
+///
+/// cargo test --test layer_groups layers_order_without_group_records -- --exact
+#[test]
+fn layers_order_without_group_records() {
+    let psd = include_bytes!("fixtures/export-groups-4x2.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let names: Vec<&str> = psd.layers().iter().map(|layer| layer.name()).collect();
+    assert_eq!(names, ["Green", "Blue", "Red", "Background"]);
+    assert_eq!(psd.layer_by_idx(0).name(), "Green");
+
+    let group_names: Vec<&str> = psd
+        .groups_in_order()
+        .iter()
+        .map(|group| group.name())
+        .collect();
+    assert_eq!(group_names, ["Empty", "Inner", "Right", "Left"]);
+
+    // Every layer that is in a group is within the group's layers
+    for layer in psd.layers() {
+        if let Some(parent_id) = layer.parent_id() {
+            let group_layers = psd.get_group_sub_layers(&parent_id).unwrap();
+            assert!(group_layers.iter().any(|other| std::ptr::eq(other, layer)));
+        }
+    }
+}
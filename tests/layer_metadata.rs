@@ -0,0 +1,47 @@
+use anyhow::Result;
+use psd::{DescriptorField, Psd};
+
+const LAYER_METADATA: &[u8] = include_bytes!("./fixtures/layer-metadata-1x1.psd");
+
+/// Every item of a layer's metadata setting is exposed, in order.
+///
+/// cargo test --test layer_metadata metadata_items -- --exact
+#[test]
+fn metadata_items() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_METADATA)?;
+    let layer = psd.layer_by_name("With Metadata").unwrap();
+
+    let keys: Vec<&str> = layer.metadata().iter().map(|item| item.key()).collect();
+    assert_eq!(keys, vec!["cust", "abcd"]);
+
+    let cust = layer.metadata_by_key("cust").unwrap();
+    assert!(cust.copy_on_sheet_duplication());
+    match cust.fields().unwrap().get("layerTime") {
+        Some(DescriptorField::Double(time)) => assert_eq!(*time, 1700000000.5),
+        other => panic!("Unexpected layerTime {:?}", other),
+    }
+
+    let raw = layer.metadata_by_key("abcd").unwrap();
+    assert!(!raw.copy_on_sheet_duplication());
+    assert_eq!(raw.data(), &[1, 2, 3]);
+    assert!(raw.fields().is_none());
+
+    Ok(())
+}
+
+/// The modification time is read from the `cust` metadata item.
+///
+/// cargo test --test layer_metadata modification_time -- --exact
+#[test]
+fn modification_time() -> Result<()> {
+    let psd = Psd::from_bytes(LAYER_METADATA)?;
+
+    let layer = psd.layer_by_name("With Metadata").unwrap();
+    assert_eq!(layer.modification_time(), Some(1700000000.5));
+
+    let layer = psd.layer_by_name("Without Metadata").unwrap();
+    assert!(layer.metadata().is_empty());
+    assert_eq!(layer.modification_time(), None);
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use psd::{blend_rgba, BlendMode, Psd};
+
+/// The layer's fill opacity is read and multiplied into the alpha of its pixels.
+///
+/// cargo test --test layer_opacity rgba_with_fill_opacity -- --exact
+#[test]
+fn rgba_with_fill_opacity() -> Result<()> {
+    let psd = include_bytes!("./fixtures/fill-opacity-1x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let red = psd.layer_by_name("Red").unwrap();
+    assert_eq!(red.opacity(), 255);
+    assert_eq!(red.fill_opacity(), 128);
+    assert_eq!(red.rgba(), [255, 0, 0, 255]);
+    assert_eq!(red.rgba_with_opacity(), [255, 0, 0, 128]);
+
+    let background = psd.layer_by_name("Background").unwrap();
+    assert_eq!(background.fill_opacity(), 255);
+    assert_eq!(background.rgba_with_opacity(), background.rgba());
+
+    // Flattening uses the same alpha
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+    let expected = blend_rgba([255, 0, 0, 128], [0, 0, 255, 255], BlendMode::Normal);
+    assert_eq!(flattened, expected);
+
+    Ok(())
+}
+
+/// The layer's opacity is multiplied into the alpha of its pixels.
+///
+/// cargo test --test layer_opacity rgba_with_opacity -- --exact
+#[test]
+fn rgba_with_opacity() -> Result<()> {
+    let bytes = psd::writer::PsdWriter::new(1, 1)
+        .layer(psd::writer::WriterLayer::new("Half", 1, 1, vec![0, 255, 0, 255]).opacity(128))
+        .to_bytes()?;
+    let psd = Psd::from_bytes(&bytes)?;
+
+    let layer = psd.layer_by_name("Half").unwrap();
+    assert_eq!(layer.rgba(), [0, 255, 0, 255]);
+    assert_eq!(layer.rgba_with_opacity(), [0, 255, 0, 128]);
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use anyhow::Result;
+use psd::{Psd, PsdError, PsdLimitError, PsdOptions};
+
+const PSD_8X8: &[u8] = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+
+/// The default limits are large enough for regular PSDs.
+///
+/// cargo test --test limits default_limits -- --exact
+#[test]
+fn default_limits() -> Result<()> {
+    let psd = Psd::from_bytes_with_options(PSD_8X8, &PsdOptions::default())?;
+    assert_eq!(psd.layers().len(), 3);
+
+    Ok(())
+}
+
+/// PSDs that are wider or taller than the limit are rejected.
+///
+/// cargo test --test limits canvas_too_large -- --exact
+#[test]
+fn canvas_too_large() {
+    let options = PsdOptions {
+        max_width: 4,
+        ..PsdOptions::default()
+    };
+
+    let err = Psd::from_bytes_with_options(PSD_8X8, &options).unwrap_err();
+    assert_eq!(
+        err,
+        PsdError::LimitError(PsdLimitError::CanvasTooLarge {
+            width: 8,
+            height: 8,
+            max_width: 4,
+            max_height: 30_000,
+        })
+    );
+}
+
+/// PSDs with more layers than the limit are rejected.
+///
+/// cargo test --test limits too_many_layers -- --exact
+#[test]
+fn too_many_layers() {
+    let options = PsdOptions {
+        max_layer_count: 2,
+        ..PsdOptions::default()
+    };
+
+    let err = Psd::from_bytes_with_options(PSD_8X8, &options).unwrap_err();
+    assert_eq!(
+        err,
+        PsdError::LimitError(PsdLimitError::TooManyLayers { count: 3, max: 2 })
+    );
+}
+
+/// The layer count is checked before the layer records are read, so a tiny file that claims
+/// to have thousands of layers is rejected up front.
+///
+/// cargo test --test limits crafted_layer_count -- --exact
+#[test]
+fn crafted_layer_count() {
+    let mut bytes = PSD_8X8.to_vec();
+
+    // The layer count comes after the header, the color mode data and image resources
+    // sections and the two length markers of the layer and mask information section
+    let color_mode_len = u32::from_be_bytes([bytes[26], bytes[27], bytes[28], bytes[29]]) as usize;
+    let resources_start = 30 + color_mode_len;
+    let resources_len = u32::from_be_bytes([
+        bytes[resources_start],
+        bytes[resources_start + 1],
+        bytes[resources_start + 2],
+        bytes[resources_start + 3],
+    ]) as usize;
+    let layer_count_start = resources_start + 4 + resources_len + 8;
+
+    bytes[layer_count_start..layer_count_start + 2].copy_from_slice(&i16::MAX.to_be_bytes());
+
+    let err = Psd::from_bytes(&bytes).unwrap_err();
+    assert_eq!(
+        err,
+        PsdError::LimitError(PsdLimitError::TooManyLayers {
+            count: i16::MAX as usize,
+            max: 8_000
+        })
+    );
+}
+
+/// PSDs whose channels decompress to more bytes than the limit are rejected.
+///
+/// cargo test --test limits too_many_decoded_bytes -- --exact
+#[test]
+fn too_many_decoded_bytes() {
+    let options = PsdOptions {
+        max_decoded_bytes: 64,
+        ..PsdOptions::default()
+    };
+
+    let err = Psd::from_bytes_with_options(PSD_8X8, &options).unwrap_err();
+    match err {
+        PsdError::LimitError(PsdLimitError::TooManyDecodedBytes { bytes, max }) => {
+            assert_eq!(max, 64);
+            // At least the three 8x8 color channels of the composite image
+            assert!(bytes >= 3 * 64);
+        }
+        other => panic!(
+            "Expected the decoded bytes limit to be exceeded, got {:?}",
+            other
+        ),
+    }
+}
+
+/// Layers are decoded lazily, so parsing only counts their channels. The RGBA buffer the size
+/// of the canvas that each layer is decoded into is counted when the layers are flattened.
+///
+/// cargo test --test limits layer_rgba_counts_when_flattening -- --exact
+#[test]
+fn layer_rgba_counts_when_flattening() -> Result<()> {
+    // The composite image's three 8x8 color channels and the four 8x8 channels of each of the
+    // three layers
+    let channel_bytes = 3 * 64 + 3 * 4 * 64;
+    let options = PsdOptions {
+        max_decoded_bytes: channel_bytes,
+        ..PsdOptions::default()
+    };
+
+    let psd = Psd::from_bytes_with_options(PSD_8X8, &options)?;
+    assert_eq!(psd.layers()[0].rgba().len(), 8 * 8 * 4);
+
+    // The flattened pixels and an 8x8 RGBA buffer for each of the three layers
+    let err = psd.flatten_layers_rgba(&|_| true).unwrap_err();
+    assert_eq!(
+        err,
+        PsdError::LimitError(PsdLimitError::TooManyDecodedBytes {
+            bytes: 4 * 8 * 8 * 4,
+            max: channel_bytes,
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use psd::{LinkedFileKind, Psd};
+
+const LINKED_FILES: &[u8] = include_bytes!("./fixtures/linked-files-1x1.psd");
+
+/// Embedded files keep their bytes, while external files only have their name and ID.
+///
+/// cargo test --test linked_files linked_files -- --exact
+#[test]
+fn linked_files() -> Result<()> {
+    let psd = Psd::from_bytes(LINKED_FILES)?;
+
+    let linked_files = psd.linked_files();
+    assert_eq!(linked_files.len(), 2);
+
+    let logo = &linked_files[0];
+    assert_eq!(logo.kind(), LinkedFileKind::Embedded);
+    assert_eq!(logo.unique_id(), "5d3c1f2a-embedded");
+    assert_eq!(logo.name(), "logo.png");
+    assert_eq!(logo.file_type(), "png ");
+    let data = logo.data().unwrap();
+    assert_eq!(data.len(), 29);
+    assert!(data.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let photo = psd.linked_file_by_id("9e8f7a6b-external").unwrap();
+    assert_eq!(photo.kind(), LinkedFileKind::External);
+    assert_eq!(photo.name(), "photo.jpg");
+    assert_eq!(photo.file_type(), "JPEG");
+    assert_eq!(photo.data(), None);
+
+    assert!(psd.linked_file_by_id("missing").is_none());
+    assert!(psd.warnings().is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use psd::patch::{PsdPatch, PsdPatchError};
+use psd::{Psd, ResolutionUnit};
+
+const TWO_LAYERS: &[u8] = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+// Without unicode layer names or a resolution, and with layer IDs after the layer names
+const DIFF_BEFORE: &[u8] = include_bytes!("./fixtures/diff-before-2x2.psd");
+const ICC_PROFILE_300_DPI: &[u8] = include_bytes!("./fixtures/icc-profile-300-dpi-2x1.psd");
+
+/// Hiding a layer only changes its flags.
+///
+/// cargo test --test patch layer_visible -- --exact
+#[test]
+fn layer_visible() -> Result<()> {
+    let patched = PsdPatch::new().layer_visible(0, false).apply(TWO_LAYERS)?;
+
+    let changed: Vec<usize> = (0..TWO_LAYERS.len())
+        .filter(|idx| TWO_LAYERS[*idx] != patched[*idx])
+        .collect();
+    assert_eq!(patched.len(), TWO_LAYERS.len());
+    assert_eq!(changed.len(), 1);
+
+    let psd = Psd::from_bytes(&patched)?;
+    assert!(!psd.layers()[0].visible());
+    assert!(psd.layers()[1].visible());
+
+    let unhidden = PsdPatch::new().layer_visible(0, true).apply(&patched)?;
+    assert_eq!(unhidden, TWO_LAYERS);
+
+    Ok(())
+}
+
+/// Renaming layers changes the length of their records, without touching their pixels or any
+/// of the other sections.
+///
+/// cargo test --test patch layer_name -- --exact
+#[test]
+fn layer_name() -> Result<()> {
+    for bytes in [TWO_LAYERS, DIFF_BEFORE].iter() {
+        let original = Psd::from_bytes(bytes)?;
+        let patched = PsdPatch::new()
+            .layer_name(0, "A much longer name")
+            .layer_name(1, "Grün")
+            .apply(bytes)?;
+        let psd = Psd::from_bytes(&patched)?;
+
+        assert_eq!(psd.layers()[0].name(), "A much longer name");
+        assert_eq!(psd.layers()[1].name(), "Grün");
+        for (layer, original) in psd.layers().iter().zip(original.layers()) {
+            assert_eq!(layer.rgba(), original.rgba());
+            assert_eq!(layer.layer_id(), original.layer_id());
+        }
+
+        let (layout, original_layout) = (psd.section_layout(), original.section_layout());
+        assert_eq!(
+            patched[..layout.layer_and_mask().start],
+            bytes[..original_layout.layer_and_mask().start]
+        );
+        assert_eq!(
+            patched[layout.image_data()],
+            bytes[original_layout.image_data()]
+        );
+    }
+
+    assert_eq!(
+        PsdPatch::new().layer_name(2, "Missing").apply(TWO_LAYERS),
+        Err(PsdPatchError::LayerNotFound { layer_idx: 2 })
+    );
+
+    Ok(())
+}
+
+/// Resources are replaced, or added if the PSD doesn't have them.
+///
+/// cargo test --test patch resource -- --exact
+#[test]
+fn resource() -> Result<()> {
+    // 72 pixels per centimeter
+    let mut resolution = vec![];
+    for _ in 0..2 {
+        resolution.extend_from_slice(&(72u32 << 16).to_be_bytes());
+        resolution.extend_from_slice(&2u16.to_be_bytes());
+        resolution.extend_from_slice(&2u16.to_be_bytes());
+    }
+
+    for bytes in [ICC_PROFILE_300_DPI, DIFF_BEFORE].iter() {
+        let original = Psd::from_bytes(bytes)?;
+        let patched = PsdPatch::new()
+            .resource(1005, resolution.clone())
+            .apply(bytes)?;
+        let psd = Psd::from_bytes(&patched)?;
+
+        let patched_resolution = psd.resolution().unwrap();
+        assert_eq!(patched_resolution.horizontal_dpi(), 72.);
+        assert_eq!(
+            patched_resolution.display_unit(),
+            ResolutionUnit::PixelsPerCentimeter
+        );
+
+        assert_eq!(psd.icc_profile(), original.icc_profile());
+        assert_eq!(psd.rgba(), original.rgba());
+        assert_eq!(psd.layers().len(), original.layers().len());
+    }
+
+    Ok(())
+}
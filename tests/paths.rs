@@ -0,0 +1,96 @@
+use psd::{PathPoint, Psd};
+
+const PATHS: &[u8] = include_bytes!("./fixtures/paths-4x4.psd");
+
+/// Saved paths are read in the order that they were stored, along with their subpaths and knots.
+///
+/// cargo test --test paths saved_paths -- --exact
+#[test]
+fn saved_paths() {
+    let psd = Psd::from_bytes(PATHS).unwrap();
+
+    let names: Vec<&str> = psd.paths().iter().map(|path| path.name()).collect();
+    assert_eq!(names, ["Outline", "Open Line"]);
+
+    let outline = &psd.paths()[0];
+    assert_eq!(outline.resource_id(), 2000);
+    assert!(outline.fill_starts_with_all_pixels());
+    assert_eq!(outline.subpaths().len(), 1);
+
+    let subpath = &outline.subpaths()[0];
+    assert!(subpath.is_closed());
+
+    let anchors: Vec<PathPoint> = subpath.knots().iter().map(|knot| knot.anchor()).collect();
+    assert_eq!(
+        anchors,
+        [point(0.25, 0.25), point(0.75, 0.25), point(0.5, 0.75)]
+    );
+
+    let linked: Vec<bool> = subpath
+        .knots()
+        .iter()
+        .map(|knot| knot.is_linked())
+        .collect();
+    assert_eq!(linked, [true, false, false]);
+
+    assert_eq!(anchors[2].to_pixels(psd.width(), psd.height()), (2., 3.));
+}
+
+/// The control points of a knot are read separately from its anchor.
+///
+/// cargo test --test paths open_subpath_control_points -- --exact
+#[test]
+fn open_subpath_control_points() {
+    let psd = Psd::from_bytes(PATHS).unwrap();
+
+    let open_line = &psd.paths()[1];
+    assert!(!open_line.fill_starts_with_all_pixels());
+
+    let subpath = &open_line.subpaths()[0];
+    assert!(!subpath.is_closed());
+
+    let knot = subpath.knots()[0];
+    assert!(knot.is_linked());
+    assert_eq!(knot.preceding(), point(0.0, 0.5));
+    assert_eq!(knot.anchor(), point(0.125, 0.5));
+    assert_eq!(knot.leaving(), point(0.25, 0.375));
+}
+
+/// The clipping path is resolved to the saved path that it names.
+///
+/// cargo test --test paths clipping_path -- --exact
+#[test]
+fn clipping_path() {
+    let psd = Psd::from_bytes(PATHS).unwrap();
+
+    assert_eq!(psd.clipping_path().unwrap().name(), "Outline");
+    assert_eq!(psd.clipping_path_flatness(), Some(1.5));
+}
+
+/// The work path is kept apart from the saved paths.
+///
+/// cargo test --test paths work_path -- --exact
+#[test]
+fn work_path() {
+    let psd = Psd::from_bytes(PATHS).unwrap();
+
+    let work_path = psd.work_path().unwrap();
+    assert_eq!(work_path.resource_id(), 1025);
+    assert_eq!(work_path.subpaths()[0].knots()[0].anchor(), point(0.5, 0.5));
+}
+
+/// PSDs without paths have no clipping path.
+///
+/// cargo test --test paths no_paths -- --exact
+#[test]
+fn no_paths() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd")).unwrap();
+
+    assert!(psd.paths().is_empty());
+    assert!(psd.work_path().is_none());
+    assert!(psd.clipping_path().is_none());
+}
+
+fn point(x: f64, y: f64) -> PathPoint {
+    PathPoint { x, y }
+}
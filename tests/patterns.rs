@@ -0,0 +1,50 @@
+use anyhow::Result;
+use psd::{FillLayer, Psd};
+
+const RED_PIXEL: [u8; 4] = [255, 0, 0, 255];
+const BLUE_PIXEL: [u8; 4] = [0, 0, 255, 255];
+
+/// Patterns are stored at the end of the layer and mask information section.
+///
+/// cargo test --test patterns read_patterns -- --exact
+#[test]
+fn read_patterns() -> Result<()> {
+    let psd = include_bytes!("./fixtures/pattern-fill-4x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.patterns().len(), 1);
+
+    let pattern = psd.pattern_by_id("d0b1c2a3-pattern-red-blue").unwrap();
+    assert_eq!(pattern.name(), "Red Blue");
+    assert_eq!((pattern.width(), pattern.height()), (2, 1));
+    assert_eq!(pattern.rgba(), [RED_PIXEL, BLUE_PIXEL].concat().as_slice());
+
+    assert!(psd.pattern_by_id("missing").is_none());
+
+    Ok(())
+}
+
+/// A pattern fill layer is rendered by repeating its pattern across the PSD.
+///
+/// cargo test --test patterns pattern_fill_layer -- --exact
+#[test]
+fn pattern_fill_layer() -> Result<()> {
+    let psd = include_bytes!("./fixtures/pattern-fill-4x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("Pattern Fill").unwrap();
+    let pattern_fill = match layer.fill() {
+        Some(FillLayer::Pattern(pattern_fill)) => pattern_fill,
+        other => panic!("Expected a pattern fill, got {:?}", other),
+    };
+    assert_eq!(pattern_fill.pattern_id(), "d0b1c2a3-pattern-red-blue");
+    assert_eq!(pattern_fill.pattern().unwrap().name(), "Red Blue");
+
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(
+        flattened,
+        [BLUE_PIXEL, RED_PIXEL, BLUE_PIXEL, RED_PIXEL].concat()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "quantize")]
+
+use psd::quantize::{quantize, QuantizeError, QuantizeOptions};
+
+/// Images with few enough colors keep their exact colors.
+///
+/// cargo test --features quantize --test quantize few_colors_are_exact -- --exact
+#[test]
+fn few_colors_are_exact() {
+    let rgba = vec![255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 0, 0, 0];
+
+    let indexed = quantize(&rgba, 2, 2, &QuantizeOptions::default()).unwrap();
+
+    assert_eq!(indexed.palette().len(), 3);
+    assert_eq!(indexed.indices()[0], indexed.indices()[2]);
+    assert_eq!(indexed.to_rgba(), rgba);
+}
+
+/// The palette is limited to the palette size, and dithering keeps the average color of a
+/// gradient close to the original.
+///
+/// cargo test --features quantize --test quantize gradient -- --exact
+#[test]
+fn gradient() {
+    let rgba: Vec<u8> = (0..=255u8)
+        .flat_map(|gray| [gray, gray, gray, 255])
+        .collect();
+
+    let options = QuantizeOptions {
+        palette_size: 2,
+        dither: false,
+    };
+    let banded = quantize(&rgba, 256, 1, &options).unwrap();
+    assert_eq!(banded.palette().len(), 2);
+    // The middle gray is as close to either color, so it is skipped
+    assert_eq!(banded.indices()[0..128], [banded.indices()[0]; 128]);
+    assert_eq!(banded.indices()[129..], [banded.indices()[255]; 127]);
+
+    let options = QuantizeOptions {
+        palette_size: 2,
+        dither: true,
+    };
+    let dithered = quantize(&rgba, 256, 1, &options).unwrap();
+    assert_eq!(dithered.palette().len(), 2);
+
+    // Dithering mixes both colors into each half of the gradient
+    assert!(dithered.indices()[96..128].contains(&dithered.indices()[255]));
+
+    let average = |rgba: &[u8]| rgba.iter().step_by(4).map(|r| *r as f32).sum::<f32>() / 256.;
+    assert!((average(&dithered.to_rgba()) - average(&rgba)).abs() < 2.);
+}
+
+/// Palettes must have 1 to 256 colors, and the pixels must cover the image.
+///
+/// cargo test --features quantize --test quantize invalid_input -- --exact
+#[test]
+fn invalid_input() {
+    let options = QuantizeOptions {
+        palette_size: 0,
+        ..QuantizeOptions::default()
+    };
+    assert_eq!(
+        quantize(&[0; 4], 1, 1, &options),
+        Err(QuantizeError::InvalidPaletteSize { palette_size: 0 })
+    );
+
+    assert_eq!(
+        quantize(&[0; 4], 2, 1, &QuantizeOptions::default()),
+        Err(QuantizeError::InvalidRgbaLength {
+            expected: 8,
+            actual: 4
+        })
+    );
+}
@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use psd::{Psd, PsdError, PsdOptions, PsdWarning};
+
+const MALFORMED: &[u8] = include_bytes!("./fixtures/malformed-layer-and-resource-1x1.psd");
+
+/// Malformed layers fail the parse unless recovering.
+///
+/// cargo test --test recover malformed_layer_fails_without_recover -- --exact
+#[test]
+fn malformed_layer_fails_without_recover() {
+    let error = Psd::from_bytes(MALFORMED).unwrap_err();
+
+    assert!(matches!(error, PsdError::LayerError(_)));
+}
+
+/// When recovering, malformed layers and resources are skipped and reported as warnings, while
+/// the rest of the PSD is still read.
+///
+/// cargo test --test recover recover_skips_malformed_layer_and_resource -- --exact
+#[test]
+fn recover_skips_malformed_layer_and_resource() {
+    let options = PsdOptions {
+        recover: true,
+        ..PsdOptions::default()
+    };
+    let (psd, warnings) = Psd::from_bytes_with_warnings(MALFORMED, &options).unwrap();
+
+    let names: Vec<&str> = psd.layers().iter().map(|layer| layer.name()).collect();
+    assert_eq!(names, ["Blue", "Red"]);
+
+    // The channels of the skipped layer were skipped too
+    assert_eq!(psd.layer_by_name("Blue").unwrap().rgba(), [0, 0, 255, 255]);
+    assert_eq!(psd.layer_by_name("Red").unwrap().rgba(), [255, 0, 0, 255]);
+
+    assert_eq!(warnings.len(), 2);
+    assert!(matches!(
+        warnings[0],
+        PsdWarning::SkippedLayer { record: 1, .. }
+    ));
+    assert!(matches!(
+        warnings[1],
+        PsdWarning::SkippedRemainingResources { .. }
+    ));
+}
+
+/// Well formed PSDs don't skip anything, they only warn about data that we don't parse yet.
+///
+/// cargo test --test recover no_warnings -- --exact
+#[test]
+fn no_warnings() {
+    let (_, warnings) = Psd::from_bytes_with_warnings(
+        include_bytes!("./fixtures/green-1x1.psd"),
+        &PsdOptions::default(),
+    )
+    .unwrap();
+
+    assert!(warnings.iter().all(|warning| matches!(
+        warning,
+        PsdWarning::IgnoredResource { .. } | PsdWarning::IgnoredLayerInfo { .. }
+    )));
+}
+
+/// PSDs that are cut short fail to parse or are partly read when recovering, instead of
+/// panicking.
+///
+/// cargo test --test recover truncated_fixtures_do_not_panic -- --exact
+#[test]
+fn truncated_fixtures_do_not_panic() {
+    let options = PsdOptions {
+        recover: true,
+        ..PsdOptions::default()
+    };
+
+    for path in fixtures(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))) {
+        let bytes = std::fs::read(&path).unwrap();
+
+        // Every length of the small fixtures, and a few hundred lengths of the larger ones
+        let step = (bytes.len() / 500).max(1);
+        for len in (0..bytes.len()).step_by(step) {
+            let result = std::panic::catch_unwind(|| {
+                if let Ok(psd) = Psd::from_bytes_with_options(&bytes[..len], &options) {
+                    psd.rgba();
+                    for layer in psd.layers() {
+                        layer.rgba();
+                    }
+                }
+            });
+
+            assert!(
+                result.is_ok(),
+                "{} panicked when cut short to {} bytes",
+                path.display(),
+                len
+            );
+        }
+    }
+}
+
+/// Every PSD in the fixtures directory and the directories within it
+fn fixtures(dir: &Path) -> Vec<PathBuf> {
+    let mut fixtures = vec![];
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            fixtures.extend(self::fixtures(&path));
+        } else if path.extension() == Some("psd".as_ref()) {
+            fixtures.push(path);
+        }
+    }
+
+    fixtures
+}
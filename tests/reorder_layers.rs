@@ -0,0 +1,125 @@
+use psd::Psd;
+
+const SUBGROUPS: &[u8] =
+    include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+
+/// Moving a layer puts it in the group of the layer that it ends up above.
+///
+/// cargo test --test reorder_layers move_layer -- --exact
+#[test]
+fn move_layer() {
+    let mut psd = Psd::from_bytes(SUBGROUPS).unwrap();
+    let firth = layer_idx(&psd, "Firth Layer");
+
+    psd.move_layer(firth, 0);
+
+    assert_eq!(layer_names(&psd)[0..2], ["Firth Layer", "First Layer"]);
+    assert_eq!(parent_name(&psd, "Firth Layer"), Some("first group inside"));
+    assert_groups_are_consistent(&psd);
+
+    // Moving to the bottom puts the layer in the group of the layer that was at the bottom
+    psd.move_layer(0, 5);
+
+    assert_eq!(layer_names(&psd)[5], "Firth Layer");
+    assert_eq!(parent_name(&psd, "Firth Layer"), Some("outside group 2"));
+    assert_groups_are_consistent(&psd);
+}
+
+/// Layers can be moved into any group, including empty groups, and out of every group.
+///
+/// cargo test --test reorder_layers set_parent -- --exact
+#[test]
+fn set_parent() {
+    let mut psd = Psd::from_bytes(SUBGROUPS).unwrap();
+    let empty_group = group_id(&psd, "third group inside");
+    let group_2 = group_id(&psd, "outside group 2");
+
+    psd.set_parent(layer_idx(&psd, "Sixth Layer"), Some(empty_group));
+
+    assert_eq!(parent_name(&psd, "Sixth Layer"), Some("third group inside"));
+    assert_eq!(psd.get_group_sub_layers(&group_2).unwrap().len(), 0);
+    assert_groups_are_consistent(&psd);
+
+    psd.set_parent(layer_idx(&psd, "Second Layer"), None);
+
+    assert_eq!(layer_names(&psd)[0], "Second Layer");
+    assert_eq!(parent_name(&psd, "Second Layer"), None);
+    assert_groups_are_consistent(&psd);
+
+    // The nested groups are closed before the groups that they're in
+    let order = psd.group_ids_in_order();
+    let outside = group_id(&psd, "outside group");
+    let position = |id| order.iter().position(|group_id| *group_id == id).unwrap();
+    assert!(position(empty_group) < position(outside));
+}
+
+/// Layers can be sorted by moving them one at a time.
+///
+/// cargo test --test reorder_layers sort_layers -- --exact
+#[test]
+fn sort_layers() {
+    let mut psd = Psd::from_bytes(SUBGROUPS).unwrap();
+
+    for idx in 0..psd.layers().len() {
+        let smallest = (idx..psd.layers().len())
+            .min_by_key(|idx| psd.layers()[*idx].name().to_string())
+            .unwrap();
+        psd.move_layer(smallest, idx);
+        assert_groups_are_consistent(&psd);
+    }
+
+    let mut sorted = layer_names(&psd);
+    sorted.sort();
+    assert_eq!(layer_names(&psd), sorted);
+}
+
+/// Every group contains exactly the layers that are nested inside of it.
+fn assert_groups_are_consistent(psd: &Psd) {
+    for (id, group) in psd.groups() {
+        let sub_layers: Vec<&str> = psd
+            .get_group_sub_layers(id)
+            .unwrap()
+            .iter()
+            .map(|layer| layer.name())
+            .collect();
+
+        let nested: Vec<&str> = psd
+            .layers()
+            .iter()
+            .filter(|layer| {
+                let mut parent_id = layer.parent_id();
+                while let Some(parent) = parent_id {
+                    if parent == *id {
+                        return true;
+                    }
+                    parent_id = psd.groups()[&parent].parent_id();
+                }
+                false
+            })
+            .map(|layer| layer.name())
+            .collect();
+
+        assert_eq!(sub_layers, nested, "{}", group.name());
+    }
+}
+
+fn layer_names(psd: &Psd) -> Vec<&str> {
+    psd.layers().iter().map(|layer| layer.name()).collect()
+}
+
+fn layer_idx(psd: &Psd, name: &str) -> usize {
+    layer_names(psd).iter().position(|n| *n == name).unwrap()
+}
+
+fn group_id(psd: &Psd, name: &str) -> u32 {
+    psd.groups()
+        .values()
+        .find(|group| group.name() == name)
+        .unwrap()
+        .id()
+}
+
+fn parent_name<'a>(psd: &'a Psd, name: &str) -> Option<&'a str> {
+    let parent_id = psd.layer_by_name(name).unwrap().parent_id()?;
+    Some(psd.groups()[&parent_id].name())
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use psd::{Psd, PsdChannelCompression};
+
+const PSD_8X8: &[u8] = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+
+/// The major sections follow one another and cover the entire file.
+///
+/// cargo test --test section_layout major_sections -- --exact
+#[test]
+fn major_sections() -> Result<()> {
+    let psd = Psd::from_bytes(PSD_8X8)?;
+    let layout = psd.section_layout();
+
+    assert_eq!(layout.file_header(), 0..26);
+    assert_eq!(&PSD_8X8[layout.file_header()][..4], b"8BPS");
+
+    let sections = [
+        layout.color_mode_data(),
+        layout.image_resources(),
+        layout.layer_and_mask(),
+    ];
+    let mut start = layout.file_header().end;
+    for section in sections.iter() {
+        assert_eq!(section.start, start);
+
+        // Each length delimited section starts with the length of the rest of the section
+        let len = &PSD_8X8[section.start..section.start + 4];
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        assert_eq!(section.len(), 4 + len);
+
+        start = section.end;
+    }
+
+    assert_eq!(layout.image_data(), start..PSD_8X8.len());
+
+    // The resource blocks fill the image resources section after its length
+    let mut start = layout.image_resources().start + 4;
+    for (_, block) in layout.resources() {
+        assert_eq!(block.start, start);
+        assert_eq!(&PSD_8X8[block.start..block.start + 4], b"8BIM");
+        start = block.end;
+    }
+    assert_eq!(start, layout.image_resources().end);
+
+    Ok(())
+}
+
+/// Each layer's record and channels are found where they're stored, and follow the layer when
+/// it is moved.
+///
+/// cargo test --test section_layout layer_channels -- --exact
+#[test]
+fn layer_channels() -> Result<()> {
+    let mut psd = Psd::from_bytes(PSD_8X8)?;
+    let layout = psd.section_layout();
+
+    for (idx, layer) in psd.layers().iter().enumerate() {
+        let channels = layout.layer_channels(idx).unwrap();
+        assert_eq!(channels.len(), layer.channels().count());
+
+        for ((kind, range), (expected_kind, compression, len)) in
+            channels.iter().zip(layer.channels())
+        {
+            assert_eq!(*kind, expected_kind);
+            assert_eq!(range.len(), 2 + len);
+            assert!(layout.layer_and_mask().start <= range.start);
+            assert!(range.end <= layout.layer_and_mask().end);
+
+            assert_eq!(compression, PsdChannelCompression::RleCompressed);
+            assert_eq!(PSD_8X8[range.start..range.start + 2], [0, 1]);
+        }
+    }
+    assert_eq!(layout.layer_channels(psd.layers().len()), None);
+
+    // The records come before all of the channels
+    let first_channel = layout.layer_channels(0).unwrap()[0].1.start;
+    for idx in 0..psd.layers().len() {
+        let record = layout.layer_record(idx).unwrap();
+        assert!(layout.layer_and_mask().start < record.start);
+        assert!(record.end <= first_channel);
+    }
+
+    let top_layer = layout.layer_channels(0).unwrap().to_vec();
+    let last = psd.layers().len() - 1;
+    psd.move_layer(0, last);
+    assert_eq!(
+        psd.section_layout().layer_channels(last).unwrap(),
+        top_layer
+    );
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use psd::spritesheet::{pack, FrameRect, FrameSource, SpritesheetOptions};
+use psd::Psd;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// Each layer of a hidden group is trimmed and packed, tallest first.
+///
+/// cargo test --test spritesheet pack_layers -- --exact
+#[test]
+fn pack_layers() -> Result<()> {
+    let psd = include_bytes!("./fixtures/spritesheet-4x4.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let options = SpritesheetOptions {
+        padding: 0,
+        ..SpritesheetOptions::default()
+    };
+    let sheet = pack(
+        &psd,
+        &|source| matches!(source, FrameSource::Layer(layer) if layer.parent_id().is_some()),
+        &options,
+    )?;
+
+    assert_eq!(
+        sheet.frames(),
+        [
+            FrameRect {
+                name: "Frame 2".to_string(),
+                x: 1,
+                y: 0,
+                width: 2,
+                height: 1,
+                offset_x: 2,
+                offset_y: 0,
+            },
+            FrameRect {
+                name: "Frame 1".to_string(),
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 2,
+                offset_x: 0,
+                offset_y: 2,
+            },
+        ]
+    );
+
+    assert_eq!((sheet.width(), sheet.height()), (3, 2));
+    let pixels: Vec<&[u8]> = sheet.rgba().chunks(4).collect();
+    assert_eq!(pixels, [&BLUE[..], &RED, &RED, &BLUE, &[0; 4], &[0; 4]]);
+
+    Ok(())
+}
+
+/// Groups are flattened into a single frame, and frames wrap onto new rows with padding.
+///
+/// cargo test --test spritesheet pack_group_and_layer -- --exact
+#[test]
+fn pack_group_and_layer() -> Result<()> {
+    let psd = include_bytes!("./fixtures/spritesheet-4x4.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let options = SpritesheetOptions {
+        padding: 1,
+        max_width: 4,
+        trim: false,
+    };
+    let sheet = pack(
+        &psd,
+        &|source| source.name() == "Walk" || source.name() == "Background",
+        &options,
+    )?;
+
+    let frames: Vec<(&str, u32, u32)> = sheet
+        .frames()
+        .iter()
+        .map(|frame| (frame.name.as_str(), frame.x, frame.y))
+        .collect();
+    assert_eq!(frames, [("Walk", 0, 0), ("Background", 0, 5)]);
+    assert_eq!((sheet.width(), sheet.height()), (4, 9));
+
+    let pixel = |x: usize, y: usize| &sheet.rgba()[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+    assert_eq!(pixel(2, 0), RED);
+    assert_eq!(pixel(0, 3), BLUE);
+    assert_eq!(pixel(0, 0), [0; 4]);
+    assert_eq!(pixel(0, 4), [0; 4]);
+    assert_eq!(pixel(3, 8), WHITE);
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "test-utils")]
+
+use anyhow::Result;
+use psd::testing::{self, GoldenError};
+use psd::Psd;
+
+const MULTIPLY: &[u8] = include_bytes!("fixtures/blending/blue-red-1x1-multiply.psd");
+const NORMAL: &[u8] = include_bytes!("fixtures/blending/blue-red-1x1-normal.psd");
+const MULTIPLY_PNG: &[u8] = include_bytes!("fixtures/blending/blue-red-1x1-multiply.png");
+
+/// A flattened PSD matches the PNG of its own pixels.
+///
+/// cargo test --features test-utils --test testing flattened_matches_png -- --exact
+#[test]
+fn flattened_matches_png() -> Result<()> {
+    let psd = Psd::from_bytes(MULTIPLY)?;
+
+    testing::assert_flattened_matches_png(&psd, MULTIPLY_PNG, 0);
+
+    Ok(())
+}
+
+/// Pixels that differ by more than the tolerance are reported, and match once the tolerance
+/// covers the difference.
+///
+/// cargo test --features test-utils --test testing flattened_differs_from_png -- --exact
+#[test]
+fn flattened_differs_from_png() -> Result<()> {
+    // Normal blending has 170 blue instead of multiply's 85
+    let psd = Psd::from_bytes(NORMAL)?;
+
+    assert_eq!(
+        testing::compare_flattened_to_png(&psd, MULTIPLY_PNG, 84),
+        Err(GoldenError::PixelMismatch {
+            count: 1,
+            x: 0,
+            y: 0,
+            actual: [85, 0, 170, 192],
+            expected: [85, 0, 85, 192],
+        })
+    );
+    assert_eq!(
+        testing::compare_flattened_to_png(&psd, MULTIPLY_PNG, 85),
+        Ok(())
+    );
+
+    Ok(())
+}
+
+/// Pixels of a different size, or a reference that isn't a PNG, never match.
+///
+/// cargo test --features test-utils --test testing size_mismatch_and_invalid_png -- --exact
+#[test]
+fn size_mismatch_and_invalid_png() {
+    assert_eq!(
+        testing::compare_rgba_to_png(&[0; 8], 2, 1, MULTIPLY_PNG, 255),
+        Err(GoldenError::SizeMismatch {
+            width: 2,
+            height: 1,
+            expected_width: 1,
+            expected_height: 1,
+        })
+    );
+
+    assert!(matches!(
+        testing::compare_rgba_to_png(&[0; 4], 1, 1, MULTIPLY, 255),
+        Err(GoldenError::InvalidPng(_))
+    ));
+}
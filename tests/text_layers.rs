@@ -0,0 +1,42 @@
+use anyhow::Result;
+use psd::{Psd, WarpStyle};
+
+const TEXT_LAYER: &[u8] = include_bytes!("./fixtures/text-layer-8x4.psd");
+
+/// The text of a type layer, along with the transform and warp that place it on the canvas.
+///
+/// cargo test --test text_layers text_transform_and_warp -- --exact
+#[test]
+fn text_transform_and_warp() -> Result<()> {
+    let psd = Psd::from_bytes(TEXT_LAYER)?;
+
+    let text = psd.layer_by_name("Hello World").unwrap().text().unwrap();
+    assert_eq!(text.text(), "Hello\rWorld");
+
+    assert_eq!(text.transform(), [2., 0., 0., 2., 1.5, 12.25]);
+    assert_eq!(text.transform_point(0., 0.), (1.5, 12.25));
+    assert_eq!(text.transform_point(3., -1.), (7.5, 10.25));
+
+    let warp = text.warp();
+    assert_eq!(warp.style(), WarpStyle::Arc);
+    assert_eq!(warp.bend(), 50.);
+    assert_eq!(warp.horizontal_distortion(), 0.);
+    assert_eq!(warp.vertical_distortion(), -25.);
+    assert!(warp.vertical());
+
+    assert!(psd.warnings().is_empty());
+
+    Ok(())
+}
+
+/// Layers that aren't type layers don't have any text.
+///
+/// cargo test --test text_layers no_text -- --exact
+#[test]
+fn no_text() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/vector-stroke-2x2.psd"))?;
+
+    assert!(psd.layer_by_name("Rectangle 1").unwrap().text().is_none());
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "std")]
+
+use std::thread;
+
+use anyhow::Result;
+use psd::compositor::Compositor;
+use psd::{Psd, PsdGroup, PsdLayer, PsdOptions};
+
+const RLE_3_LAYER_8X8: &[u8] = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+
+/// A PSD can be shared between threads.
+///
+/// cargo test --test threads psd_is_send_and_sync -- --exact
+#[test]
+fn psd_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Psd>();
+    assert_send_sync::<PsdLayer>();
+    assert_send_sync::<PsdGroup>();
+    assert_send_sync::<Compositor<'static>>();
+}
+
+/// Flattening the same PSD from multiple threads at once gives the same pixels as flattening
+/// it on one thread.
+///
+/// cargo test --test threads flatten_from_multiple_threads -- --exact
+#[test]
+fn flatten_from_multiple_threads() -> Result<()> {
+    let psd = Psd::from_bytes(RLE_3_LAYER_8X8)?;
+    let expected = psd.flatten_layers_rgba(&|_| true)?;
+
+    let psd = &psd;
+    let flattened: Vec<Vec<u8>> = thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|_| scope.spawn(move || psd.flatten_layers_rgba(&|_| true).unwrap()))
+            .collect();
+
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect()
+    });
+
+    for rgba in flattened {
+        assert_eq!(rgba, expected);
+    }
+
+    Ok(())
+}
+
+/// Cached layer pixels are the same as the pixels that aren't cached, from any thread and after
+/// the caches are cleared.
+///
+/// cargo test --test threads cached_layer_rgba -- --exact
+#[test]
+fn cached_layer_rgba() -> Result<()> {
+    let expected = Psd::from_bytes(RLE_3_LAYER_8X8)?.flatten_layers_rgba(&|_| true)?;
+
+    let options = PsdOptions {
+        cache_layer_rgba: true,
+        ..PsdOptions::default()
+    };
+    let mut psd = Psd::from_bytes_with_options(RLE_3_LAYER_8X8, &options)?;
+
+    let layer_rgba = psd.layer_by_name("Red Layer").unwrap().rgba();
+    assert_eq!(psd.layer_by_name("Red Layer").unwrap().rgba(), layer_rgba);
+
+    let shared = &psd;
+    thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|_| scope.spawn(move || shared.flatten_layers_rgba(&|_| true).unwrap()))
+            .collect();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+    });
+
+    psd.clear_pixel_caches();
+    assert_eq!(psd.layer_by_name("Red Layer").unwrap().rgba(), layer_rgba);
+    assert_eq!(psd.flatten_layers_rgba(&|_| true)?, expected);
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use psd::Psd;
+use psd::{LayerBounds, Psd, PsdOptions};
 use psd::PsdChannelCompression;
 use psd::PsdChannelKind;
 use std::collections::HashMap;
@@ -86,6 +86,50 @@ fn transparent_above_opaque() -> Result<()> {
     Ok(())
 }
 
+/// Fully transparent pixels keep the color that the PSD stored under them unless the PSD is
+/// parsed with `clear_transparent_pixels`, which returns them as `[0, 0, 0, 0]` everywhere.
+///
+/// cargo test --test transparency clear_transparent_pixels -- --exact
+#[test]
+fn clear_transparent_pixels() -> Result<()> {
+    let bytes = include_bytes!("./fixtures/3x3-opaque-center.psd");
+
+    let psd = Psd::from_bytes(bytes)?;
+    let rgba = psd.rgba();
+    assert!(rgba
+        .chunks_exact(4)
+        .any(|pixel| pixel[3] == 0 && pixel[..3] != [0, 0, 0]));
+
+    let options = PsdOptions {
+        clear_transparent_pixels: true,
+        ..PsdOptions::default()
+    };
+    let cleared = Psd::from_bytes_with_options(bytes, &options)?;
+
+    let outputs = [
+        cleared.rgba(),
+        cleared.flatten_layers_rgba(&|_| true)?,
+        cleared.flatten_rows(&|_| true).flatten().collect(),
+        cleared.layer_by_name("OpaqueCenter").unwrap().rgba(),
+    ];
+    for output in outputs.iter() {
+        for pixel in output.chunks_exact(4) {
+            if pixel[3] == 0 {
+                assert_eq!(pixel, [0, 0, 0, 0]);
+            }
+        }
+    }
+
+    // Pixels with any opacity are untouched
+    for (pixel, cleared) in rgba.chunks_exact(4).zip(outputs[0].chunks_exact(4)) {
+        if pixel[3] != 0 {
+            assert_eq!(pixel, cleared);
+        }
+    }
+
+    Ok(())
+}
+
 // Ensure that the specified, zero-indexed left, top coordinate has the provided pixel color.
 // Otherwise it should be fully transparent.
 // (left, top, pixel)
@@ -136,3 +180,48 @@ fn put_pixel(image: &mut Vec<u8>, width: usize, left: usize, top: usize, new: [u
     image[idx * 4 + 2] = new[2];
     image[idx * 4 + 3] = new[3];
 }
+
+/// The opaque bounds of a raw layer only contain its non-transparent pixels.
+///
+/// cargo test --test transparency opaque_bounds_raw_data -- --exact
+#[test]
+fn opaque_bounds_raw_data() -> Result<()> {
+    let psd = include_bytes!("./fixtures/3x3-opaque-center.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("OpaqueCenter").unwrap();
+
+    let expected = LayerBounds {
+        top: 0,
+        left: 1,
+        bottom: 1,
+        right: 2,
+    };
+    assert_eq!(layer.opaque_bounds(), Some(expected));
+    assert_eq!((expected.width(), expected.height()), (2, 2));
+
+    Ok(())
+}
+
+/// The opaque bounds of an RLE compressed layer only contain its non-transparent pixels.
+///
+/// cargo test --test transparency opaque_bounds_rle_compressed -- --exact
+#[test]
+fn opaque_bounds_rle_compressed() -> Result<()> {
+    let psd = include_bytes!("./fixtures/16x16-rle-partially-opaque.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = &psd.layers()[0];
+
+    let expected = Some(LayerBounds {
+        top: 1,
+        left: 1,
+        bottom: 9,
+        right: 9,
+    });
+    assert_eq!(layer.opaque_bounds(), expected);
+    // Cached
+    assert_eq!(layer.opaque_bounds(), expected);
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use psd::validate::{PsdSection, ValidationIssue};
+use psd::Psd;
+
+const TWO_LAYERS_RED_GREEN: &[u8] = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+
+/// PSDs that were saved by Photoshop don't have any problems.
+///
+/// cargo test --test validate photoshop_files_are_valid -- --exact
+#[test]
+fn photoshop_files_are_valid() {
+    let fixtures: [&[u8]; 5] = [
+        TWO_LAYERS_RED_GREEN,
+        include_bytes!("./fixtures/one-channel-1x1.psd"),
+        include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+        include_bytes!("./fixtures/green-clipping-10x10.psd"),
+        // The rows of 1 bit channels are padded to whole bytes
+        include_bytes!("./fixtures/bitmap-10x2.psd"),
+    ];
+
+    for fixture in fixtures.iter() {
+        let report = Psd::validate(fixture);
+        assert!(report.is_valid(), "{:?}", report.issues());
+    }
+}
+
+/// Every problem is reported, even the ones after a layer that couldn't be parsed.
+///
+/// cargo test --test validate report_every_issue -- --exact
+#[test]
+fn report_every_issue() {
+    let psd = include_bytes!("./fixtures/malformed-layer-and-resource-1x1.psd");
+    let report = Psd::validate(psd);
+
+    assert_eq!(
+        report.issues(),
+        &[
+            ValidationIssue::InvalidResourceSignature { offset: 34 },
+            ValidationIssue::UnknownBlendMode {
+                record: 1,
+                key: "xxxx".to_string()
+            },
+        ]
+    );
+}
+
+/// Changing the bytes of a valid PSD is reported where the change was made.
+///
+/// cargo test --test validate report_corrupted_bytes -- --exact
+#[test]
+fn report_corrupted_bytes() -> Result<()> {
+    let layout = Psd::from_bytes(TWO_LAYERS_RED_GREEN)?.section_layout();
+
+    // The composite image is cut short
+    let truncated = &TWO_LAYERS_RED_GREEN[..TWO_LAYERS_RED_GREEN.len() - 1];
+    assert!(matches!(
+        Psd::validate(truncated).issues(),
+        [ValidationIssue::CompositeLengthMismatch { .. }]
+    ));
+
+    // The top layer's first channel has an unknown compression
+    let mut bytes = TWO_LAYERS_RED_GREEN.to_vec();
+    let (_, channel) = &layout.layer_channels(0).unwrap()[0];
+    bytes[channel.start + 1] = 9;
+    assert_eq!(
+        Psd::validate(&bytes).issues(),
+        &[ValidationIssue::InvalidCompression {
+            record: Some(1),
+            compression: 9
+        }]
+    );
+
+    // The image resources section claims to be longer than the file
+    let mut bytes = TWO_LAYERS_RED_GREEN.to_vec();
+    let length = layout.image_resources().start;
+    bytes[length..length + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(matches!(
+        Psd::validate(&bytes).issues(),
+        [ValidationIssue::SectionOutOfBounds {
+            section: PsdSection::ImageResources,
+            ..
+        }]
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use psd::{Psd, PsdOptions};
+
+const VECTOR_MASK: &[u8] = include_bytes!("fixtures/vector-mask-8x8.psd");
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+fn pixel(rgba: &[u8], x: usize, y: usize) -> [u8; 4] {
+    let idx = (y * 8 + x) * 4;
+
+    let mut pixel = [0; 4];
+    pixel.copy_from_slice(&rgba[idx..idx + 4]);
+    pixel
+}
+
+/// The vector masks of a shape layer and a pixel layer are read, along with their flags.
+///
+/// cargo test --test vector_masks read_vector_masks -- --exact
+#[test]
+fn read_vector_masks() -> Result<()> {
+    let psd = Psd::from_bytes(VECTOR_MASK)?;
+
+    let square = psd.layer_by_name("Square").unwrap().vector_mask().unwrap();
+    assert!(!square.is_inverted());
+    assert!(square.is_linked());
+    assert!(!square.is_disabled());
+    assert!(!square.fill_starts_with_all_pixels());
+    assert_eq!(square.subpaths().len(), 1);
+    assert!(square.subpaths()[0].is_closed());
+    assert_eq!(square.subpaths()[0].knots().len(), 4);
+    assert_eq!(square.subpaths()[0].knots()[1].anchor().x, 0.75);
+    assert_eq!(square.subpaths()[0].knots()[1].anchor().y, 0.25);
+
+    // Read from the `vsms` key that Photoshop CS6 uses
+    assert!(psd
+        .layer_by_name("Diamond")
+        .unwrap()
+        .vector_mask()
+        .is_some());
+
+    let disabled = psd.layer_by_name("Disabled").unwrap();
+    let mask = disabled.vector_mask().unwrap();
+    assert!(mask.is_inverted());
+    assert!(!mask.is_linked());
+    assert!(mask.is_disabled());
+    assert_eq!(disabled.rasterize_vector_mask(), None);
+
+    assert!(psd
+        .layer_by_name("Background")
+        .unwrap()
+        .vector_mask()
+        .is_none());
+
+    Ok(())
+}
+
+/// Edges that line up with pixels are sharp, and diagonal edges are anti-aliased by how much
+/// of each pixel they cover.
+///
+/// cargo test --test vector_masks rasterize_vector_masks -- --exact
+#[test]
+fn rasterize_vector_masks() -> Result<()> {
+    let psd = Psd::from_bytes(VECTOR_MASK)?;
+
+    let square = psd
+        .layer_by_name("Square")
+        .unwrap()
+        .rasterize_vector_mask()
+        .unwrap();
+    for y in 0..8 {
+        for x in 0..8 {
+            let inside = (2..6).contains(&x) && (2..6).contains(&y);
+            assert_eq!(
+                square[y * 8 + x],
+                if inside { 255 } else { 0 },
+                "({}, {})",
+                x,
+                y
+            );
+        }
+    }
+
+    let diamond = psd
+        .layer_by_name("Diamond")
+        .unwrap()
+        .rasterize_vector_mask()
+        .unwrap();
+    assert_eq!(diamond[4 * 8 + 4], 255);
+    assert_eq!(diamond[0], 0);
+    // The top corner of the diamond covers half of the pixels on either side of it
+    assert!((127..=128).contains(&diamond[3]));
+    assert!((127..=128).contains(&diamond[4]));
+    // The diamond covers half of the canvas
+    let covered: u32 = diamond.iter().map(|value| *value as u32).sum();
+    assert!((32 * 255 - 8..=32 * 255 + 8).contains(&covered));
+
+    Ok(())
+}
+
+/// Vector masks are only multiplied into the layers' pixels when asked to, which keeps a shape
+/// layer's fill within its shape.
+///
+/// cargo test --test vector_masks flatten_with_vector_masks -- --exact
+#[test]
+fn flatten_with_vector_masks() -> Result<()> {
+    let psd = Psd::from_bytes(VECTOR_MASK)?;
+    let rgba = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(pixel(&rgba, 0, 7), RED);
+
+    let options = PsdOptions {
+        rasterize_vector_masks: true,
+        ..PsdOptions::default()
+    };
+    let psd = Psd::from_bytes_with_options(VECTOR_MASK, &options)?;
+    let rgba = psd.flatten_layers_rgba(&|_| true)?;
+
+    assert_eq!(pixel(&rgba, 0, 7), WHITE);
+    assert_eq!(pixel(&rgba, 4, 4), RED);
+    assert_eq!(pixel(&rgba, 4, 1), BLUE);
+
+    // Half blue at the diamond's anti-aliased edge
+    let edge = pixel(&rgba, 3, 0);
+    assert!((127..=128).contains(&edge[0]));
+    assert_eq!(edge[2], 255);
+
+    // The disabled mask doesn't hide the green pixel
+    assert_eq!(pixel(&rgba, 0, 0), [0, 255, 0, 255]);
+
+    Ok(())
+}
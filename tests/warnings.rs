@@ -0,0 +1,161 @@
+use psd::{Psd, PsdChannelCompression, PsdChannelKind, PsdOptions, PsdWarning};
+
+const ZIP_COMPRESSED: &[u8] = include_bytes!("./fixtures/zip-compressed-1x1.psd");
+
+/// Data that we don't parse yet is reported as warnings instead of being silently dropped.
+///
+/// cargo test --test warnings ignored_and_unsupported_data -- --exact
+#[test]
+fn ignored_and_unsupported_data() {
+    let psd = Psd::from_bytes(ZIP_COMPRESSED).unwrap();
+
+    let zipped_channel = |channel| PsdWarning::UnsupportedLayerCompression {
+        layer: "Zipped".to_string(),
+        channel,
+        compression: PsdChannelCompression::ZipWithoutPrediction,
+    };
+    assert_eq!(
+        psd.warnings(),
+        [
+            PsdWarning::IgnoredLayerInfo {
+                layer: Some("Red".to_string()),
+                key: "brst".to_string(),
+            },
+            zipped_channel(PsdChannelKind::TransparencyMask),
+            zipped_channel(PsdChannelKind::Red),
+            zipped_channel(PsdChannelKind::Green),
+            zipped_channel(PsdChannelKind::Blue),
+            PsdWarning::IgnoredLayerInfo {
+                layer: None,
+                key: "FMsk".to_string(),
+            },
+            PsdWarning::UnsupportedCompositeCompression {
+                compression: PsdChannelCompression::ZipWithoutPrediction,
+            },
+            PsdWarning::IgnoredResource { resource_id: 1036 },
+        ]
+    );
+
+    let (_, warnings) =
+        Psd::from_bytes_with_warnings(ZIP_COMPRESSED, &PsdOptions::default()).unwrap();
+    assert_eq!(warnings, psd.warnings());
+}
+
+/// Zip compressed channels are left empty instead of failing, and the composite image is
+/// flattened from the layers.
+///
+/// cargo test --test warnings unsupported_compression_is_left_out -- --exact
+#[test]
+fn unsupported_compression_is_left_out() {
+    let psd = Psd::from_bytes(ZIP_COMPRESSED).unwrap();
+
+    assert!(!psd.has_composite_image());
+    assert_eq!(psd.layer_by_name("Red").unwrap().rgba(), [255, 0, 0, 255]);
+    assert_eq!(psd.rgba(), [255, 0, 0, 255]);
+}
+
+const FILL_LAYERS: &[u8] = include_bytes!("./fixtures/fill-layers-4x1.psd");
+
+/// Replace the first `os_type` that follows the additional layer information `key` with one
+/// that we can't read, so that reading the block's descriptor fails.
+fn with_unreadable_descriptor(psd: &[u8], key: &[u8; 4], os_type: &[u8; 4]) -> Vec<u8> {
+    let mut psd = psd.to_vec();
+
+    let block = psd.windows(4).position(|window| window == key).unwrap();
+    let field = block
+        + psd[block..]
+            .windows(4)
+            .position(|window| window == os_type)
+            .unwrap();
+    psd[field..field + 4].copy_from_slice(b"ObAr");
+
+    psd
+}
+
+/// A fill that can't be read is skipped with a warning instead of failing the whole PSD.
+///
+/// cargo test --test warnings unreadable_fill_is_skipped -- --exact
+#[test]
+fn unreadable_fill_is_skipped() {
+    let bytes = with_unreadable_descriptor(FILL_LAYERS, b"SoCo", b"Objc");
+    let psd = Psd::from_bytes(&bytes).unwrap();
+
+    let skipped: Vec<_> = psd
+        .warnings()
+        .iter()
+        .filter_map(|warning| match warning {
+            PsdWarning::SkippedLayerInfo { layer, key, .. } => Some((layer.as_str(), key.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].1, "SoCo");
+
+    let layer = psd.layer_by_name(skipped[0].0).unwrap();
+    assert!(layer.fill().is_none());
+}
+
+const VECTOR_STROKE: &[u8] = include_bytes!("./fixtures/vector-stroke-2x2.psd");
+
+/// A vector stroke or fill that can't be read is skipped with a warning instead of failing the
+/// whole PSD.
+///
+/// cargo test --test warnings unreadable_vector_stroke_is_skipped -- --exact
+#[test]
+fn unreadable_vector_stroke_is_skipped() {
+    let bytes = with_unreadable_descriptor(VECTOR_STROKE, b"vstk", b"long");
+    let bytes = with_unreadable_descriptor(&bytes, b"vscg", b"Objc");
+    let psd = Psd::from_bytes(&bytes).unwrap();
+
+    let skipped: Vec<_> = psd
+        .warnings()
+        .iter()
+        .filter_map(|warning| match warning {
+            PsdWarning::SkippedLayerInfo { key, .. } => Some(key.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(skipped.len(), 2);
+    assert!(skipped.contains(&"vstk") && skipped.contains(&"vscg"));
+
+    for layer in psd.layers() {
+        assert!(layer.vector_stroke().is_none());
+        assert!(layer.vector_fill().is_none());
+    }
+}
+
+const TEXT_LAYER: &[u8] = include_bytes!("./fixtures/text-layer-8x4.psd");
+
+/// Type layer data that can't be read is skipped with a warning instead of failing the whole
+/// PSD.
+///
+/// cargo test --test warnings unreadable_text_is_skipped -- --exact
+#[test]
+fn unreadable_text_is_skipped() {
+    let bytes = with_unreadable_descriptor(TEXT_LAYER, b"TySh", b"enum");
+    let psd = Psd::from_bytes(&bytes).unwrap();
+
+    assert!(psd.warnings().iter().any(|warning| matches!(
+        warning,
+        PsdWarning::SkippedLayerInfo { key, .. } if key == "TySh"
+    )));
+    assert!(psd.layers().iter().all(|layer| layer.text().is_none()));
+}
+
+const LAYER_EFFECTS: &[u8] = include_bytes!("./fixtures/layer-effects-10x10.psd");
+
+/// Layer effects that can't be read, such as ones with descriptor fields that we don't
+/// support, are skipped with a warning instead of failing the whole PSD.
+///
+/// cargo test --test warnings unreadable_effects_are_skipped -- --exact
+#[test]
+fn unreadable_effects_are_skipped() {
+    let bytes = with_unreadable_descriptor(LAYER_EFFECTS, b"lfx2", b"UntF");
+    let psd = Psd::from_bytes(&bytes).unwrap();
+
+    assert!(psd.warnings().iter().any(|warning| matches!(
+        warning,
+        PsdWarning::SkippedLayerInfo { key, .. } if key == "lfx2"
+    )));
+    assert!(psd.layers().iter().all(|layer| layer.effects().is_none()));
+}
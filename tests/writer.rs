@@ -0,0 +1,108 @@
+use psd::writer::{PsdWriter, PsdWriterError, WriterCompression, WriterLayer, WriterOptions};
+use psd::{BlendMode, Psd, PsdChannelCompression};
+
+/// A PSD's composite image is read back exactly as it was written, with either compression.
+///
+/// cargo test --test writer composite_round_trip -- --exact
+#[test]
+fn composite_round_trip() {
+    let rgba: Vec<u8> = (0..5 * 3 * 4).map(|byte| (byte * 7) as u8).collect();
+
+    for (compression, expected) in [
+        (WriterCompression::Raw, PsdChannelCompression::RawData),
+        (WriterCompression::Rle, PsdChannelCompression::RleCompressed),
+    ] {
+        let bytes = PsdWriter::new(5, 3)
+            .options(WriterOptions { compression })
+            .composite_rgba(rgba.clone())
+            .to_bytes()
+            .unwrap();
+        let psd = Psd::from_bytes(&bytes).unwrap();
+
+        assert_eq!((psd.width(), psd.height()), (5, 3));
+        assert_eq!(psd.compression(), &expected);
+        assert_eq!(psd.rgba(), rgba);
+    }
+}
+
+/// RLE compression shrinks images with flat areas of color, and is the default.
+///
+/// cargo test --test writer rle_is_smaller -- --exact
+#[test]
+fn rle_is_smaller() {
+    let rgba = [10, 20, 30, 255].repeat(64 * 64);
+    let writer = PsdWriter::new(64, 64).composite_rgba(rgba);
+
+    let rle = writer.to_bytes().unwrap();
+    let raw = writer
+        .options(WriterOptions {
+            compression: WriterCompression::Raw,
+        })
+        .to_bytes()
+        .unwrap();
+
+    assert!(rle.len() * 10 < raw.len());
+}
+
+/// PSDs are transparent when they don't have a composite image, and the pixels must cover the
+/// canvas.
+///
+/// cargo test --test writer invalid_pixels -- --exact
+#[test]
+fn invalid_pixels() {
+    let bytes = PsdWriter::new(2, 2).to_bytes().unwrap();
+    assert_eq!(Psd::from_bytes(&bytes).unwrap().rgba(), [0; 16]);
+
+    assert_eq!(
+        PsdWriter::new(2, 2).composite_rgba(vec![0; 12]).to_bytes(),
+        Err(PsdWriterError::InvalidRgbaLength {
+            expected: 16,
+            actual: 12
+        })
+    );
+    assert_eq!(
+        PsdWriter::new(0, 2).to_bytes(),
+        Err(PsdWriterError::InvalidSize {
+            width: 0,
+            height: 2
+        })
+    );
+}
+
+/// Layers are read back with their names, positions, pixels and properties.
+///
+/// cargo test --test writer layers_round_trip -- --exact
+#[test]
+fn layers_round_trip() {
+    for compression in [WriterCompression::Raw, WriterCompression::Rle] {
+        let bytes = PsdWriter::new(4, 4)
+            .options(WriterOptions { compression })
+            .layer(WriterLayer::new("Über", 1, 1, vec![1, 2, 3, 255]).position(3, 3))
+            .layer(
+                WriterLayer::new("Hidden", 2, 1, vec![9; 8])
+                    .position(-1, 0)
+                    .opacity(128)
+                    .visible(false)
+                    .blend_mode(BlendMode::Multiply),
+            )
+            .to_bytes()
+            .unwrap();
+        let psd = Psd::from_bytes(&bytes).unwrap();
+
+        let top = &psd.layers()[0];
+        assert_eq!(top.name(), "Über");
+        assert_eq!((top.layer_left(), top.layer_top()), (3, 3));
+        assert_eq!(top.rgba()[60..64], [1, 2, 3, 255]);
+
+        let hidden = &psd.layers()[1];
+        assert_eq!(hidden.name(), "Hidden");
+        assert_eq!((hidden.layer_left(), hidden.width()), (-1, 2));
+        assert_eq!(hidden.opacity(), 128);
+        assert!(!hidden.visible());
+        assert_eq!(hidden.blend_mode(), BlendMode::Multiply);
+
+        // Without a composite image the layers are flattened instead
+        assert!(!psd.has_composite_image());
+        assert_eq!(psd.rgba()[60..64], [1, 2, 3, 255]);
+    }
+}